@@ -3,16 +3,88 @@
 mod render;
 mod utils;
 
+use tauri::Manager;
+
 use crate::render::image::{
-    clear_chunk_cache, clear_file_cache, force_preprocess_chunks, get_image_chunk,
-    get_image_metadata_for_file, process_user_image,
+    add_annotation, add_bookmark, add_watch_folder, append_image_upload, apply_retention_rules,
+    begin_image_upload, cancel_chunk_refinements, cancel_export, cancel_image_upload,
+    cancel_preprocess, cancel_scheduled_tasks, capture_screen, choose_level, chunk_to_image,
+    clear_chunk_cache, clear_file_cache, clear_telemetry, create_tour, create_tour_from_bookmarks,
+    delete_annotation, delete_tour, detect_changes, detect_stitching_artifacts,
+    enforce_cache_limit, export_annotations, export_chunks_arrow, export_for_print,
+    export_pyramidal_tiff, export_region, export_telemetry, export_tile_archive, export_tour,
+    find_duplicate, find_similar, finish_image_upload, force_preprocess_chunks, get_access_heatmap,
+    get_annotation_history, get_average_color, get_backend_info, get_blended_chunk, get_cache_info,
+    get_cache_read_only, get_chunk_cache_stats, get_chunk_infos, get_chunk_order,
+    get_chunk_request_stats, get_compare_chunk, get_decode_sandbox, get_dicom_info,
+    get_display_profile, get_fft, get_folder_index, get_image_chunk, get_image_chunks,
+    get_image_metadata_for_file, get_job_history, get_level_formats, get_line_profile, get_locale,
+    get_lod_bias, get_magnifier, get_maintenance_config, get_memory_usage, get_notification_config,
+    get_overlay_chunk, get_pdf_page_count, get_pixel_size, get_portable_cache, get_power_status,
+    get_progressive_chunk, get_proxy_chunk, get_proxy_scale, get_quality_metrics,
+    get_retention_policy, get_retention_rules, get_reviewer, get_rpc_server_status,
+    get_scheduler_status, get_startup_image, get_startup_preload_config, get_system_info, get_tags,
+    get_telemetry_enabled, get_texture_info, get_texture_level, get_timing_metrics,
+    get_window_state, goto_bookmark, goto_tour_step, handle_dropped_paths, handle_startup_args,
+    image_to_chunk, import_annotations, import_from_camera, import_tour, index_folder,
+    list_annotations, list_bookmarks, list_camera_devices, list_camera_files, list_duplicates,
+    list_fits_hdus, list_live_images, list_monitors, list_region_locks, list_tours,
+    list_watch_folders, lock_region, open_deep_link, open_video_frame, pin_cache,
+    preprocess_levels, process_clipboard_image, process_dicom_image, process_fits_image,
+    process_image_bytes, process_pdf_page, process_psd_image, process_texture_image,
+    process_user_image, process_user_image_async, rechunk_image, redo, refresh_cache,
+    remove_bookmark, remove_watch_folder, remove_window_state, render_viewport,
+    request_full_resolution, reset_timing_metrics, run_diagnostics, run_maintenance_now,
+    search_images, set_app_backgrounded, set_cache_read_only, set_chunk_cache_budget,
+    set_chunk_order, set_decode_sandbox, set_display_profile, set_level_format, set_locale,
+    set_lod_bias, set_maintenance_config, set_notification_config, set_portable_cache,
+    set_power_mode, set_proxy_scale, set_retention_rules, set_reviewer, set_startup_preload,
+    set_tags, set_telemetry_enabled, set_window_settings, set_window_viewport, simulate_pan,
+    start_live_mode, start_maintenance_scheduler, start_memory_pressure_monitor, start_rpc_server,
+    stop_live_mode, stop_rpc_server, test_notification, trim_memory, undo, unlock_region,
+    unpin_cache, update_annotation, update_tour, warm_cache,
 };
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // 以解码子进程方式启动时只执行解码 不创建窗口
+    if let Some(file_path) = render::image::decode_worker_request() {
+        std::process::exit(render::image::run_decode_worker(&file_path));
+    }
+
+    start_memory_pressure_monitor();
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_fs::init())
+        .setup(|app| {
+            // 内存中的 chunk 缓存由所有请求共享 命令通过 tauri::State 取得
+            app.manage(render::image::chunk_memory_cache().clone());
+            // 用户开启遥测后记录本地诊断数据
+            render::image::init_telemetry(app.handle());
+            // 移动端的缓存放在应用沙盒中 需要在访问缓存之前设置
+            render::image::init_mobile_storage(app.handle());
+            // 通过"打开方式"启动时 命令行参数中带有图片路径
+            handle_startup_args(app.handle());
+            // 空闲时定期执行缓存维护
+            start_maintenance_scheduler(app.handle().clone());
+            // 设置了 IMAGES_GL_RPC_PORT 时开启本地控制接口
+            render::image::start_rpc_server_from_env(app.handle());
+            // 上次退出（或移动端被系统杀掉）时还没完成的后台预处理继续排队
+            render::image::preprocess_queue::resume_preprocess_queue(app.handle());
+            render::image::resume_folder_indexing(app.handle());
+            render::image::start_watch_folders(app.handle());
+            // 开启了启动预热时在前端请求之前预热最近打开的图片
+            render::image::start_startup_preload(app.handle());
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            // 窗口关闭后清理该窗口的图片状态
+            if let tauri::WindowEvent::Destroyed = event {
+                remove_window_state(window.label());
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             process_user_image,
             get_image_metadata_for_file,
@@ -20,7 +92,178 @@ pub fn run() {
             clear_chunk_cache,
             clear_file_cache,
             force_preprocess_chunks,
+            run_diagnostics,
+            get_system_info,
+            get_memory_usage,
+            trim_memory,
+            simulate_pan,
+            open_deep_link,
+            get_window_state,
+            set_window_viewport,
+            set_window_settings,
+            handle_dropped_paths,
+            get_startup_image,
+            set_locale,
+            get_locale,
+            rechunk_image,
+            refresh_cache,
+            start_live_mode,
+            stop_live_mode,
+            list_live_images,
+            process_clipboard_image,
+            list_monitors,
+            capture_screen,
+            get_pdf_page_count,
+            process_pdf_page,
+            get_dicom_info,
+            process_dicom_image,
+            list_fits_hdus,
+            process_fits_image,
+            process_psd_image,
+            get_texture_info,
+            get_texture_level,
+            process_texture_image,
+            open_video_frame,
+            set_decode_sandbox,
+            get_decode_sandbox,
+            export_region,
+            cancel_export,
+            export_pyramidal_tiff,
+            export_tile_archive,
+            find_duplicate,
+            list_duplicates,
+            find_similar,
+            get_access_heatmap,
+            enforce_cache_limit,
+            pin_cache,
+            unpin_cache,
+            get_cache_info,
+            get_maintenance_config,
+            set_maintenance_config,
+            run_maintenance_now,
+            set_power_mode,
+            get_power_status,
+            get_display_profile,
+            set_display_profile,
+            add_bookmark,
+            list_bookmarks,
+            remove_bookmark,
+            goto_bookmark,
+            list_tours,
+            create_tour,
+            create_tour_from_bookmarks,
+            update_tour,
+            delete_tour,
+            export_tour,
+            import_tour,
+            goto_tour_step,
+            list_annotations,
+            add_annotation,
+            update_annotation,
+            delete_annotation,
+            export_annotations,
+            import_annotations,
+            undo,
+            redo,
+            get_annotation_history,
+            get_reviewer,
+            set_reviewer,
+            lock_region,
+            unlock_region,
+            list_region_locks,
+            set_cache_read_only,
+            get_cache_read_only,
+            start_rpc_server,
+            stop_rpc_server,
+            get_rpc_server_status,
+            export_chunks_arrow,
+            get_notification_config,
+            set_notification_config,
+            test_notification,
+            get_job_history,
+            set_telemetry_enabled,
+            get_telemetry_enabled,
+            export_telemetry,
+            clear_telemetry,
+            get_backend_info,
+            set_app_backgrounded,
+            set_proxy_scale,
+            get_proxy_scale,
+            get_proxy_chunk,
+            request_full_resolution,
+            render_viewport,
+            export_for_print,
+            set_tags,
+            get_tags,
+            search_images,
+            index_folder,
+            get_folder_index,
+            add_watch_folder,
+            remove_watch_folder,
+            list_watch_folders,
+            list_camera_devices,
+            list_camera_files,
+            import_from_camera,
+            get_average_color,
+            get_line_profile,
+            get_fft,
+            get_quality_metrics,
+            get_blended_chunk,
+            get_compare_chunk,
+            detect_changes,
+            detect_stitching_artifacts,
+            get_overlay_chunk,
+            get_pixel_size,
+            get_magnifier,
+            get_progressive_chunk,
+            cancel_chunk_refinements,
+            choose_level,
+            set_lod_bias,
+            get_lod_bias,
+            warm_cache,
+            get_startup_preload_config,
+            set_startup_preload,
+            cancel_scheduled_tasks,
+            get_scheduler_status,
+            get_chunk_request_stats,
+            get_retention_rules,
+            set_retention_rules,
+            apply_retention_rules,
+            get_retention_policy,
+            get_portable_cache,
+            set_portable_cache,
+            preprocess_levels,
+            get_level_formats,
+            set_level_format,
+            process_user_image_async,
+            get_chunk_order,
+            set_chunk_order,
+            cancel_preprocess,
+            image_to_chunk,
+            chunk_to_image,
+            get_timing_metrics,
+            reset_timing_metrics,
+            process_image_bytes,
+            begin_image_upload,
+            append_image_upload,
+            finish_image_upload,
+            cancel_image_upload,
+            get_chunk_cache_stats,
+            set_chunk_cache_budget,
+            get_chunk_infos,
+            get_image_chunks,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_app, _event| {
+            // 退出前保存 chunk 访问统计
+            if let tauri::RunEvent::Exit = &_event {
+                render::image::flush_access_stats();
+            }
+            // macOS 上双击关联文件时 应用已经在运行 通过事件传入文件
+            #[cfg(any(target_os = "macos", target_os = "ios"))]
+            if let tauri::RunEvent::Opened { urls } = &_event {
+                render::image::handle_opened_urls(_app, urls);
+            }
+        });
 }
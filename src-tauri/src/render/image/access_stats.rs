@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use super::cache::{image_cache_dir, is_cache_read_only, load_cached_metadata};
+use super::types::AccessHeatmap;
+use crate::utils::time::{get_time, Stopwatch};
+
+// chunk 访问统计：记录前端每次请求 chunk 的次数和最后访问时间
+// 数据保存在各图片缓存目录的 access_stats.json 中 重启后继续累积
+// 用于热力图展示 以及在淘汰缓存时优先保留经常回看的区域
+
+// 访问统计文件
+const ACCESS_STATS_FILE: &str = "access_stats.json";
+// 写回磁盘的最小间隔 每次请求都写文件没有必要
+const ACCESS_FLUSH_INTERVAL_MS: u128 = 30_000;
+// 访问分数的半衰期 一周前的访问只算一半
+const ACCESS_HALF_LIFE_MS: f64 = 7.0 * 24.0 * 3600.0 * 1000.0;
+// 热力图的默认最大边长（格子数）
+const DEFAULT_HEATMAP_CELLS: u32 = 64;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+struct ChunkAccess {
+    chunk_x: u32,
+    chunk_y: u32,
+    count: u64,
+    last_access_ms: u64,
+}
+
+struct FileAccessStats {
+    chunks: HashMap<(u32, u32), ChunkAccess>,
+    dirty: bool,
+    last_flush: Stopwatch,
+}
+
+// 最近一次 chunk 访问的时间（毫秒时间戳）用于判断用户是否空闲
+static LAST_ACTIVITY_MS: AtomicU64 = AtomicU64::new(0);
+
+static ACCESS_STATS: OnceLock<Mutex<HashMap<String, FileAccessStats>>> = OnceLock::new();
+
+fn access_stats() -> &'static Mutex<HashMap<String, FileAccessStats>> {
+    ACCESS_STATS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// 第一次用到某个文件时从磁盘加载
+fn load_file_stats(file_path: &str) -> FileAccessStats {
+    let path = image_cache_dir(file_path).join(ACCESS_STATS_FILE);
+    let entries: Vec<ChunkAccess> = fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    FileAccessStats {
+        chunks: entries
+            .into_iter()
+            .map(|entry| ((entry.chunk_x, entry.chunk_y), entry))
+            .collect(),
+        dirty: false,
+        last_flush: Stopwatch::start(),
+    }
+}
+
+fn flush_file_stats(file_path: &str, stats: &mut FileAccessStats) {
+    let cache_dir = image_cache_dir(file_path);
+    // 缓存已经被清理时不要重新创建目录 只读缓存的统计只保留在内存中
+    if !cache_dir.exists() || is_cache_read_only() {
+        return;
+    }
+    let entries: Vec<&ChunkAccess> = stats.chunks.values().collect();
+    match serde_json::to_string(&entries) {
+        Ok(json) => {
+            if let Err(e) = fs::write(cache_dir.join(ACCESS_STATS_FILE), json) {
+                println!("[RUST] 保存访问统计失败: {e}");
+            }
+        }
+        Err(e) => println!("[RUST] 序列化访问统计失败: {e}"),
+    }
+    stats.dirty = false;
+    stats.last_flush = Stopwatch::start();
+}
+
+/// 记录一次 chunk 访问
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `chunk_x` - chunk 的 X 索引
+/// * `chunk_y` - chunk 的 Y 索引
+pub fn record_chunk_access(file_path: &str, chunk_x: u32, chunk_y: u32) {
+    LAST_ACTIVITY_MS.store(get_time() as u64, Ordering::Relaxed);
+    let Ok(mut all) = access_stats().lock() else {
+        return;
+    };
+    let stats = all
+        .entry(file_path.to_string())
+        .or_insert_with(|| load_file_stats(file_path));
+
+    let now = get_time();
+    let entry = stats
+        .chunks
+        .entry((chunk_x, chunk_y))
+        .or_insert(ChunkAccess {
+            chunk_x,
+            chunk_y,
+            ..Default::default()
+        });
+    entry.count += 1;
+    entry.last_access_ms = now as u64;
+    stats.dirty = true;
+
+    if stats.last_flush.elapsed_ms() >= ACCESS_FLUSH_INTERVAL_MS {
+        flush_file_stats(file_path, stats);
+    }
+}
+
+/// 最近一次 chunk 访问的时间（毫秒时间戳） 本次启动后还没有访问时为 0
+pub fn last_activity_ms() -> u64 {
+    LAST_ACTIVITY_MS.load(Ordering::Relaxed)
+}
+
+/// 把所有未保存的访问统计写回磁盘 退出前调用
+pub fn flush_access_stats() {
+    if let Ok(mut all) = access_stats().lock() {
+        for (file_path, stats) in all.iter_mut() {
+            if stats.dirty {
+                flush_file_stats(file_path, stats);
+            }
+        }
+    }
+}
+
+/// 清除某个文件的访问统计（缓存被清理或重新分块时调用 旧的 chunk 索引已经没有意义）
+pub fn reset_access_stats(file_path: &str) {
+    if let Ok(mut all) = access_stats().lock() {
+        all.remove(file_path);
+    }
+    if is_cache_read_only() {
+        return;
+    }
+    let _ = fs::remove_file(image_cache_dir(file_path).join(ACCESS_STATS_FILE));
+}
+
+/// 清除内存中所有文件的访问统计（整个缓存目录被清理时调用）
+pub fn clear_access_stats() {
+    if let Ok(mut all) = access_stats().lock() {
+        all.clear();
+    }
+}
+
+// 按访问次数和最近程度打分 每过一个半衰期分数减半
+fn decayed_score(access: &ChunkAccess, now_ms: u64) -> f64 {
+    let age = now_ms.saturating_sub(access.last_access_ms) as f64;
+    access.count as f64 * 0.5f64.powf(age / ACCESS_HALF_LIFE_MS)
+}
+
+/// 图片的保留分数 为各 chunk 分数之和 越高越应该留在缓存中
+/// 经常回看的图片即使最近没有打开也会保留较高的分数 淘汰缓存时优先淘汰分数低的
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `f64` - 保留分数 从未访问过为 0
+pub fn image_retention_score(file_path: &str) -> f64 {
+    let Ok(mut all) = access_stats().lock() else {
+        return 0.0;
+    };
+    let stats = all
+        .entry(file_path.to_string())
+        .or_insert_with(|| load_file_stats(file_path));
+    let now = get_time() as u64;
+    stats
+        .chunks
+        .values()
+        .map(|access| decayed_score(access, now))
+        .sum()
+}
+
+/// 图片最后一次被访问的时间（毫秒时间戳）
+pub fn last_image_access_ms(file_path: &str) -> Option<u64> {
+    let mut all = access_stats().lock().ok()?;
+    let stats = all
+        .entry(file_path.to_string())
+        .or_insert_with(|| load_file_stats(file_path));
+    stats
+        .chunks
+        .values()
+        .map(|access| access.last_access_ms)
+        .max()
+}
+
+/// 获取图片的 chunk 访问热力图
+/// chunk 网格超过 max_cells 时合并相邻的 chunk 每格为其中 chunk 的访问次数之和
+/// # Arguments
+/// * `file_path` - 图片文件路径（需要已经预处理）
+/// * `max_cells` - 热力图每个方向的最大格子数 默认为 64
+/// # Returns
+/// * `Result<AccessHeatmap, String>` - 热力图
+#[tauri::command]
+pub fn get_access_heatmap(
+    file_path: String,
+    max_cells: Option<u32>,
+) -> Result<AccessHeatmap, String> {
+    let metadata = load_cached_metadata(&file_path)?;
+    let max_cells = max_cells.unwrap_or(DEFAULT_HEATMAP_CELLS).max(1);
+
+    // 每格覆盖 group x group 个 chunk
+    let group = metadata
+        .col_count
+        .max(metadata.row_count)
+        .div_ceil(max_cells)
+        .max(1);
+    let cols = metadata.col_count.div_ceil(group);
+    let rows = metadata.row_count.div_ceil(group);
+    let mut counts = vec![0u64; (cols * rows) as usize];
+    let mut last_access_ms = vec![0u64; (cols * rows) as usize];
+
+    let mut all = access_stats()
+        .lock()
+        .map_err(|e| format!("获取访问统计锁失败: {e}"))?;
+    let stats = all
+        .entry(file_path.clone())
+        .or_insert_with(|| load_file_stats(&file_path));
+    for access in stats.chunks.values() {
+        if access.chunk_x >= metadata.col_count || access.chunk_y >= metadata.row_count {
+            continue;
+        }
+        let index = ((access.chunk_y / group) * cols + access.chunk_x / group) as usize;
+        counts[index] += access.count;
+        last_access_ms[index] = last_access_ms[index].max(access.last_access_ms);
+    }
+
+    Ok(AccessHeatmap {
+        cols,
+        rows,
+        chunks_per_cell: group,
+        max_count: counts.iter().copied().max().unwrap_or(0),
+        total_count: counts.iter().sum(),
+        counts,
+        last_access_ms,
+    })
+}
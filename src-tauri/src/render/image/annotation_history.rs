@@ -0,0 +1,237 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+
+use super::annotations::{list_annotations, modify_annotations};
+use super::types::{Annotation, AnnotationHistoryState};
+
+// 标注的撤销/重做：每次修改记录为一组单个标注的改动（修改前 / 修改后）
+// 撤销时反向执行 重做时正向执行 执行前检查标注仍处于预期状态
+// 共享缓存上其他人已经改过同一个标注时拒绝撤销 不会覆盖别人的修改
+// 历史只保存在内存中 每张图片最多保留 MAX_HISTORY 步 重启后清空
+
+// 每张图片最多保留的撤销步数
+const MAX_HISTORY: usize = 200;
+
+// 单个标注的一次改动 before 为 None 表示新增 after 为 None 表示删除
+#[derive(Debug, Clone)]
+pub(super) struct AnnotationChange {
+    before: Option<Annotation>,
+    after: Option<Annotation>,
+    // 新增时插入的位置 / 删除前所在的位置 撤销后保持原来的顺序
+    index: usize,
+}
+
+#[derive(Debug)]
+struct AnnotationEdit {
+    description: String,
+    changes: Vec<AnnotationChange>,
+}
+
+#[derive(Debug, Default)]
+struct EditHistory {
+    undo: VecDeque<AnnotationEdit>,
+    redo: Vec<AnnotationEdit>,
+}
+
+static HISTORIES: OnceLock<Mutex<HashMap<String, EditHistory>>> = OnceLock::new();
+
+fn histories() -> &'static Mutex<HashMap<String, EditHistory>> {
+    HISTORIES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 比较修改前后的标注列表 得到改动
+/// 顺序为：删除（从后往前）、修改、新增（从前往后） 按顺序执行可以从 before 得到 after
+pub(super) fn diff_annotations(
+    before: &[Annotation],
+    after: &[Annotation],
+) -> Vec<AnnotationChange> {
+    let after_by_id: HashMap<&str, &Annotation> =
+        after.iter().map(|a| (a.id.as_str(), a)).collect();
+    let before_by_id: HashMap<&str, &Annotation> =
+        before.iter().map(|a| (a.id.as_str(), a)).collect();
+
+    let mut changes: Vec<AnnotationChange> = before
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, a)| !after_by_id.contains_key(a.id.as_str()))
+        .map(|(index, a)| AnnotationChange {
+            before: Some(a.clone()),
+            after: None,
+            index,
+        })
+        .collect();
+    changes.extend(after.iter().filter_map(|a| {
+        let old = before_by_id.get(a.id.as_str())?;
+        (*old != a).then(|| AnnotationChange {
+            before: Some((*old).clone()),
+            after: Some(a.clone()),
+            index: 0,
+        })
+    }));
+    changes.extend(
+        after
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| !before_by_id.contains_key(a.id.as_str()))
+            .map(|(index, a)| AnnotationChange {
+                before: None,
+                after: Some(a.clone()),
+                index,
+            }),
+    );
+    changes
+}
+
+/// 记录一次修改 清空重做历史 没有改动时不记录
+pub(super) fn record_annotation_edit(
+    file_path: &str,
+    description: &str,
+    changes: Vec<AnnotationChange>,
+) {
+    if changes.is_empty() {
+        return;
+    }
+    let Ok(mut all) = histories().lock() else {
+        return;
+    };
+    let history = all.entry(file_path.to_string()).or_default();
+    history.undo.push_back(AnnotationEdit {
+        description: description.to_string(),
+        changes,
+    });
+    if history.undo.len() > MAX_HISTORY {
+        history.undo.pop_front();
+    }
+    history.redo.clear();
+}
+
+// 执行一个改动 from 为预期的当前状态 to 为目标状态
+fn apply_change(
+    annotations: &mut Vec<Annotation>,
+    from: &Option<Annotation>,
+    to: &Option<Annotation>,
+    index: usize,
+) -> Result<(), String> {
+    let id = from.as_ref().or(to.as_ref()).map(|a| a.id.as_str());
+    let position = annotations.iter().position(|a| Some(a.id.as_str()) == id);
+    let current = position.map(|i| &annotations[i]);
+    if current != from.as_ref() {
+        return Err(format!("标注 {} 已被其他修改覆盖", id.unwrap_or_default()));
+    }
+
+    match (position, to) {
+        (Some(i), Some(to)) => annotations[i] = to.clone(),
+        (Some(i), None) => {
+            annotations.remove(i);
+        }
+        (None, Some(to)) => annotations.insert(index.min(annotations.len()), to.clone()),
+        (None, None) => {}
+    }
+    Ok(())
+}
+
+// 撤销或重做一步 失败时这一步从历史中丢弃 避免之后一直卡在这里
+fn step_history(file_path: &str, undo: bool) -> Result<AnnotationHistoryState, String> {
+    let edit = {
+        let mut all = histories()
+            .lock()
+            .map_err(|e| format!("获取撤销历史锁失败: {e}"))?;
+        let history = all.entry(file_path.to_string()).or_default();
+        if undo {
+            history.undo.pop_back()
+        } else {
+            history.redo.pop()
+        }
+    };
+    let Some(edit) = edit else {
+        return Err(if undo {
+            "没有可以撤销的操作".to_string()
+        } else {
+            "没有可以重做的操作".to_string()
+        });
+    };
+
+    // 先在副本上全部执行成功再保存 不会只撤销一半
+    modify_annotations(file_path, |annotations| {
+        let mut next = annotations.clone();
+        if undo {
+            for change in edit.changes.iter().rev() {
+                apply_change(&mut next, &change.after, &change.before, change.index)?;
+            }
+        } else {
+            for change in &edit.changes {
+                apply_change(&mut next, &change.before, &change.after, change.index)?;
+            }
+        }
+        *annotations = next;
+        Ok(())
+    })
+    .map_err(|e| {
+        format!(
+            "{}「{}」失败: {e}",
+            if undo { "撤销" } else { "重做" },
+            edit.description
+        )
+    })?;
+
+    println!(
+        "[RUST] {}标注操作: {file_path} {}",
+        if undo { "撤销" } else { "重做" },
+        edit.description
+    );
+    if let Ok(mut all) = histories().lock() {
+        let history = all.entry(file_path.to_string()).or_default();
+        if undo {
+            history.redo.push(edit);
+        } else {
+            history.undo.push_back(edit);
+        }
+    }
+    get_annotation_history(file_path.to_string())
+}
+
+/// 撤销图片上一次的标注操作
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<AnnotationHistoryState, String>` - 撤销后的标注和历史状态
+#[tauri::command]
+pub fn undo(file_path: String) -> Result<AnnotationHistoryState, String> {
+    step_history(&file_path, true)
+}
+
+/// 重做图片上一次撤销的标注操作
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<AnnotationHistoryState, String>` - 重做后的标注和历史状态
+#[tauri::command]
+pub fn redo(file_path: String) -> Result<AnnotationHistoryState, String> {
+    step_history(&file_path, false)
+}
+
+/// 获取图片的标注和撤销/重做状态
+#[tauri::command]
+pub fn get_annotation_history(file_path: String) -> Result<AnnotationHistoryState, String> {
+    let annotations = list_annotations(file_path.clone())?;
+    let all = histories()
+        .lock()
+        .map_err(|e| format!("获取撤销历史锁失败: {e}"))?;
+    let (undo_count, redo_count, next_undo, next_redo) = match all.get(&file_path) {
+        Some(history) => (
+            history.undo.len() as u32,
+            history.redo.len() as u32,
+            history.undo.back().map(|edit| edit.description.clone()),
+            history.redo.last().map(|edit| edit.description.clone()),
+        ),
+        None => (0, 0, None, None),
+    };
+    Ok(AnnotationHistoryState {
+        annotations,
+        undo_count,
+        redo_count,
+        next_undo,
+        next_redo,
+    })
+}
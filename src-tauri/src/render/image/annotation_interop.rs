@@ -0,0 +1,626 @@
+use std::fs;
+use std::path::Path;
+
+use serde_json::{json, Value};
+use url::Url;
+
+use super::annotations::{
+    list_annotations, new_annotation_id, validate_geometry, with_annotations,
+};
+use super::types::{Annotation, AnnotationFormat, AnnotationGeometry, AnnotationImportReport};
+use crate::utils::time::get_time;
+
+// 标注交换：导入导出 GeoJSON 和 W3C Web Annotation 与 QuPath、Label Studio、IIIF 工具互通
+// GeoJSON 默认使用图片像素坐标（y 向下 与 QuPath 一致） 传入仿射变换时转换为地理坐标
+// W3C 矩形使用媒体片段 xywh=pixel: 其他形状使用 SvgSelector 点使用 IIIF 的 PointSelector
+
+const W3C_CONTEXT: &str = "http://www.w3.org/ns/anno.jsonld";
+const MEDIA_FRAGMENTS_SPEC: &str = "http://www.w3.org/TR/media-frags/";
+// 导出的 W3C 标注 ID 前缀 导入时去掉 便于来回转换时保持同一个 ID
+const W3C_ID_PREFIX: &str = "urn:images-gl:annotation:";
+
+// 像素坐标和地理坐标之间的仿射变换 与 GDAL 的 GeoTransform 相同
+// X = t0 + px * t1 + py * t2; Y = t3 + px * t4 + py * t5
+type GeoTransform = [f64; 6];
+
+fn to_geo(t: &Option<GeoTransform>, [px, py]: [f64; 2]) -> [f64; 2] {
+    match t {
+        Some(t) => [t[0] + px * t[1] + py * t[2], t[3] + px * t[4] + py * t[5]],
+        None => [px, py],
+    }
+}
+
+fn from_geo(t: &Option<GeoTransform>, [gx, gy]: [f64; 2]) -> [f64; 2] {
+    match t {
+        Some(t) => {
+            let det = t[1] * t[5] - t[2] * t[4];
+            let (dx, dy) = (gx - t[0], gy - t[3]);
+            [(dx * t[5] - dy * t[2]) / det, (dy * t[1] - dx * t[4]) / det]
+        }
+        None => [gx, gy],
+    }
+}
+
+fn rectangle_corners(x: f64, y: f64, width: f64, height: f64) -> Vec<[f64; 2]> {
+    vec![
+        [x, y],
+        [x + width, y],
+        [x + width, y + height],
+        [x, y + height],
+    ]
+}
+
+// ---------------- GeoJSON ----------------
+
+fn geojson_feature(annotation: &Annotation, transform: &Option<GeoTransform>) -> Value {
+    let coords = |points: &[[f64; 2]]| -> Vec<[f64; 2]> {
+        points.iter().map(|p| to_geo(transform, *p)).collect()
+    };
+    // 多边形的环需要闭合
+    let ring = |points: &[[f64; 2]]| {
+        let mut ring = coords(points);
+        if let Some(first) = ring.first().copied() {
+            ring.push(first);
+        }
+        ring
+    };
+
+    let (geometry, shape) = match &annotation.geometry {
+        AnnotationGeometry::Point { x, y } => (
+            json!({ "type": "Point", "coordinates": to_geo(transform, [*x, *y]) }),
+            "point",
+        ),
+        AnnotationGeometry::Rectangle {
+            x,
+            y,
+            width,
+            height,
+        } => (
+            json!({
+                "type": "Polygon",
+                "coordinates": [ring(&rectangle_corners(*x, *y, *width, *height))],
+            }),
+            "rectangle",
+        ),
+        AnnotationGeometry::Polygon { points } => (
+            json!({ "type": "Polygon", "coordinates": [ring(points)] }),
+            "polygon",
+        ),
+        AnnotationGeometry::Polyline { points } => (
+            json!({ "type": "LineString", "coordinates": coords(points) }),
+            "polyline",
+        ),
+    };
+
+    let mut properties = json!({
+        "shape": shape,
+        "created_ms": annotation.created_ms,
+        "updated_ms": annotation.updated_ms,
+    });
+    for (key, value) in [
+        ("label", &annotation.label),
+        ("comment", &annotation.comment),
+        ("color", &annotation.color),
+        ("author", &annotation.author),
+    ] {
+        if let Some(value) = value {
+            properties[key] = json!(value);
+        }
+    }
+    // QuPath 按 classification 识别类别
+    if let Some(label) = &annotation.label {
+        properties["classification"] = json!({ "name": label });
+    }
+
+    json!({
+        "type": "Feature",
+        "id": annotation.id,
+        "geometry": geometry,
+        "properties": properties,
+    })
+}
+
+fn export_geojson(annotations: &[Annotation], transform: &Option<GeoTransform>) -> Value {
+    json!({
+        "type": "FeatureCollection",
+        "features": annotations
+            .iter()
+            .map(|annotation| geojson_feature(annotation, transform))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn parse_point(value: &Value) -> Option<[f64; 2]> {
+    let array = value.as_array()?;
+    Some([array.first()?.as_f64()?, array.get(1)?.as_f64()?])
+}
+
+fn parse_points(value: &Value) -> Option<Vec<[f64; 2]>> {
+    value.as_array()?.iter().map(parse_point).collect()
+}
+
+fn string_property(properties: &Value, key: &str) -> Option<String> {
+    properties.get(key)?.as_str().map(str::to_string)
+}
+
+fn geojson_annotation(
+    feature: &Value,
+    transform: &Option<GeoTransform>,
+) -> Result<Annotation, String> {
+    let geometry = feature.get("geometry").ok_or("缺少 geometry")?;
+    let coordinates = geometry.get("coordinates").ok_or("缺少 coordinates")?;
+    let properties = feature.get("properties").cloned().unwrap_or(Value::Null);
+    let pixels = |points: Vec<[f64; 2]>| -> Vec<[f64; 2]> {
+        points.into_iter().map(|p| from_geo(transform, p)).collect()
+    };
+
+    let geometry = match geometry.get("type").and_then(|v| v.as_str()) {
+        Some("Point") => {
+            let [x, y] = from_geo(transform, parse_point(coordinates).ok_or("无效的点坐标")?);
+            AnnotationGeometry::Point { x, y }
+        }
+        Some("LineString") => AnnotationGeometry::Polyline {
+            points: pixels(parse_points(coordinates).ok_or("无效的折线坐标")?),
+        },
+        Some("Polygon") => {
+            // 只取外环 内环（洞）没有对应的形状
+            let mut points = pixels(
+                parse_points(coordinates.get(0).ok_or("多边形缺少外环")?)
+                    .ok_or("无效的多边形坐标")?,
+            );
+            if points.len() > 1 && points.first() == points.last() {
+                points.pop();
+            }
+            let is_rectangle =
+                properties.get("shape").and_then(|v| v.as_str()) == Some("rectangle");
+            match (is_rectangle, points.as_slice()) {
+                (true, [[x0, y0], _, [x2, y2], _]) => AnnotationGeometry::Rectangle {
+                    x: x0.min(*x2),
+                    y: y0.min(*y2),
+                    width: (x2 - x0).abs(),
+                    height: (y2 - y0).abs(),
+                },
+                _ => AnnotationGeometry::Polygon { points },
+            }
+        }
+        Some(other) => return Err(format!("不支持的几何类型 {other}")),
+        None => return Err("缺少几何类型".to_string()),
+    };
+    validate_geometry(&geometry)?;
+
+    let now = get_time() as u64;
+    let id = match feature.get("id") {
+        Some(Value::String(id)) => id.clone(),
+        Some(Value::Number(id)) => id.to_string(),
+        _ => new_annotation_id(),
+    };
+    Ok(Annotation {
+        id,
+        geometry,
+        label: string_property(&properties, "label").or_else(|| {
+            properties
+                .get("classification")
+                .and_then(|c| string_property(c, "name"))
+        }),
+        comment: string_property(&properties, "comment"),
+        color: string_property(&properties, "color"),
+        author: string_property(&properties, "author"),
+        created_ms: properties
+            .get("created_ms")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(now),
+        updated_ms: properties
+            .get("updated_ms")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(now),
+    })
+}
+
+fn import_geojson(
+    root: &Value,
+    transform: &Option<GeoTransform>,
+) -> (Vec<Annotation>, Vec<String>) {
+    let features: Vec<&Value> = match root.get("type").and_then(|v| v.as_str()) {
+        Some("FeatureCollection") => root
+            .get("features")
+            .and_then(|v| v.as_array())
+            .map(|features| features.iter().collect())
+            .unwrap_or_default(),
+        Some("Feature") => vec![root],
+        _ => Vec::new(),
+    };
+
+    let mut annotations = Vec::new();
+    let mut skipped = Vec::new();
+    for (index, feature) in features.into_iter().enumerate() {
+        match geojson_annotation(feature, transform) {
+            Ok(annotation) => annotations.push(annotation),
+            Err(e) => skipped.push(format!("第 {} 个要素: {e}", index + 1)),
+        }
+    }
+    (annotations, skipped)
+}
+
+// ---------------- W3C Web Annotation ----------------
+
+fn svg_points(points: &[[f64; 2]]) -> String {
+    points
+        .iter()
+        .map(|[x, y]| format!("{x},{y}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn w3c_selector(geometry: &AnnotationGeometry) -> Value {
+    let svg = |element: String| {
+        json!({
+            "type": "SvgSelector",
+            "value": format!("<svg xmlns=\"http://www.w3.org/2000/svg\">{element}</svg>"),
+        })
+    };
+    match geometry {
+        AnnotationGeometry::Point { x, y } => json!({ "type": "PointSelector", "x": x, "y": y }),
+        AnnotationGeometry::Rectangle {
+            x,
+            y,
+            width,
+            height,
+        } => json!({
+            "type": "FragmentSelector",
+            "conformsTo": MEDIA_FRAGMENTS_SPEC,
+            "value": format!("xywh=pixel:{x},{y},{width},{height}"),
+        }),
+        AnnotationGeometry::Polygon { points } => {
+            svg(format!("<polygon points=\"{}\"/>", svg_points(points)))
+        }
+        AnnotationGeometry::Polyline { points } => svg(format!(
+            "<polyline points=\"{}\" fill=\"none\"/>",
+            svg_points(points)
+        )),
+    }
+}
+
+fn export_w3c(file_path: &str, annotations: &[Annotation]) -> Value {
+    let source = Url::from_file_path(file_path)
+        .map(|url| url.to_string())
+        .unwrap_or_else(|_| file_path.to_string());
+
+    let items: Vec<Value> = annotations
+        .iter()
+        .map(|annotation| {
+            let mut body = Vec::new();
+            if let Some(comment) = &annotation.comment {
+                body.push(
+                    json!({ "type": "TextualBody", "value": comment, "purpose": "commenting" }),
+                );
+            }
+            if let Some(label) = &annotation.label {
+                body.push(json!({ "type": "TextualBody", "value": label, "purpose": "tagging" }));
+            }
+            let mut item = json!({
+                "id": format!("{W3C_ID_PREFIX}{}", annotation.id),
+                "type": "Annotation",
+                "motivation": if annotation.comment.is_some() { "commenting" } else { "tagging" },
+                "body": body,
+                "target": {
+                    "source": source,
+                    "selector": w3c_selector(&annotation.geometry),
+                },
+            });
+            if let Some(author) = &annotation.author {
+                item["creator"] = json!({ "type": "Person", "name": author });
+            }
+            item
+        })
+        .collect();
+
+    json!({
+        "@context": W3C_CONTEXT,
+        "type": "AnnotationPage",
+        "items": items,
+    })
+}
+
+// 读取 SVG 元素的属性值 只处理导出和常见工具生成的简单 SVG
+fn svg_attr(element: &str, name: &str) -> Option<String> {
+    let start = element.find(&format!(" {name}=\""))? + name.len() + 3;
+    let end = element[start..].find('"')? + start;
+    Some(element[start..end].to_string())
+}
+
+fn parse_svg_points(value: &str) -> Option<Vec<[f64; 2]>> {
+    let numbers: Vec<f64> = value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse().ok())
+        .collect::<Option<_>>()?;
+    (numbers.len().is_multiple_of(2)).then(|| numbers.chunks(2).map(|p| [p[0], p[1]]).collect())
+}
+
+fn parse_svg_selector(svg: &str) -> Result<AnnotationGeometry, String> {
+    let number = |element: &str, name: &str| -> Result<f64, String> {
+        svg_attr(element, name)
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| format!("SVG 缺少属性 {name}"))
+    };
+    for tag in ["polygon", "polyline", "rect", "circle"] {
+        let Some(start) = svg.find(&format!("<{tag} ")) else {
+            continue;
+        };
+        let element = &svg[start..svg[start..].find('>').map_or(svg.len(), |end| start + end)];
+        return match tag {
+            "polygon" | "polyline" => {
+                let points = svg_attr(element, "points")
+                    .and_then(|v| parse_svg_points(&v))
+                    .ok_or("SVG 缺少有效的 points")?;
+                Ok(if tag == "polygon" {
+                    AnnotationGeometry::Polygon { points }
+                } else {
+                    AnnotationGeometry::Polyline { points }
+                })
+            }
+            "rect" => Ok(AnnotationGeometry::Rectangle {
+                x: number(element, "x")?,
+                y: number(element, "y")?,
+                width: number(element, "width")?,
+                height: number(element, "height")?,
+            }),
+            _ => Ok(AnnotationGeometry::Point {
+                x: number(element, "cx")?,
+                y: number(element, "cy")?,
+            }),
+        };
+    }
+    Err("SVG 中没有支持的形状".to_string())
+}
+
+fn parse_xywh(value: &str) -> Result<AnnotationGeometry, String> {
+    let value = value.trim_start_matches('#');
+    let params = value
+        .strip_prefix("xywh=pixel:")
+        .or_else(|| value.strip_prefix("xywh="))
+        .ok_or_else(|| format!("不支持的媒体片段 {value}"))?;
+    let numbers: Vec<f64> = params
+        .split(',')
+        .map(|s| {
+            s.trim()
+                .parse()
+                .map_err(|_| format!("无效的媒体片段 {value}"))
+        })
+        .collect::<Result<_, _>>()?;
+    match numbers.as_slice() {
+        [x, y, width, height] => Ok(AnnotationGeometry::Rectangle {
+            x: *x,
+            y: *y,
+            width: *width,
+            height: *height,
+        }),
+        _ => Err(format!("无效的媒体片段 {value}")),
+    }
+}
+
+fn parse_w3c_selector(selector: &Value) -> Result<AnnotationGeometry, String> {
+    let value = || {
+        selector
+            .get("value")
+            .and_then(|v| v.as_str())
+            .ok_or("选择器缺少 value")
+    };
+    match selector.get("type").and_then(|v| v.as_str()) {
+        Some("FragmentSelector") => parse_xywh(value()?),
+        Some("SvgSelector") => parse_svg_selector(value()?),
+        Some("PointSelector") => Ok(AnnotationGeometry::Point {
+            x: selector
+                .get("x")
+                .and_then(|v| v.as_f64())
+                .ok_or("点缺少 x")?,
+            y: selector
+                .get("y")
+                .and_then(|v| v.as_f64())
+                .ok_or("点缺少 y")?,
+        }),
+        Some(other) => Err(format!("不支持的选择器 {other}")),
+        None => Err("缺少选择器类型".to_string()),
+    }
+}
+
+fn w3c_annotation(item: &Value) -> Result<Annotation, String> {
+    let target = item.get("target").ok_or("缺少 target")?;
+    let geometry = match target {
+        // 目标可以直接是带媒体片段的 URI
+        Value::String(uri) => parse_xywh(uri.split_once('#').ok_or("target 没有选择区域")?.1)?,
+        _ => {
+            let selectors: Vec<&Value> = match target.get("selector") {
+                Some(Value::Array(selectors)) => selectors.iter().collect(),
+                Some(selector) => vec![selector],
+                None => return Err("target 缺少 selector".to_string()),
+            };
+            // 多个选择器表示同一区域的不同描述 取第一个能识别的
+            let mut last_error = String::new();
+            let mut parsed = None;
+            for selector in selectors {
+                match parse_w3c_selector(selector) {
+                    Ok(geometry) => {
+                        parsed = Some(geometry);
+                        break;
+                    }
+                    Err(e) => last_error = e,
+                }
+            }
+            parsed.ok_or(last_error)?
+        }
+    };
+    validate_geometry(&geometry)?;
+
+    let bodies: Vec<&Value> = match item.get("body") {
+        Some(Value::Array(bodies)) => bodies.iter().collect(),
+        Some(body) => vec![body],
+        None => Vec::new(),
+    };
+    let mut label = None;
+    let mut comment = None;
+    for body in bodies {
+        let Some(value) = body.get("value").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        match body.get("purpose").and_then(|v| v.as_str()) {
+            Some("tagging" | "classifying") if label.is_none() => label = Some(value.to_string()),
+            _ if comment.is_none() => comment = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    let now = get_time() as u64;
+    Ok(Annotation {
+        id: item
+            .get("id")
+            .and_then(|v| v.as_str())
+            .map(|id| id.trim_start_matches(W3C_ID_PREFIX).to_string())
+            .unwrap_or_else(new_annotation_id),
+        geometry,
+        label,
+        comment,
+        color: None,
+        author: item.get("creator").and_then(|creator| match creator {
+            Value::String(name) => Some(name.clone()),
+            _ => string_property(creator, "name"),
+        }),
+        created_ms: now,
+        updated_ms: now,
+    })
+}
+
+fn import_w3c(root: &Value) -> (Vec<Annotation>, Vec<String>) {
+    // 支持 AnnotationPage、AnnotationCollection（内嵌第一页）、数组和单个标注
+    let items: Vec<&Value> = match root {
+        Value::Array(items) => items.iter().collect(),
+        _ => match root.get("type").and_then(|v| v.as_str()) {
+            Some("AnnotationPage") => root
+                .get("items")
+                .and_then(|v| v.as_array())
+                .map(|items| items.iter().collect())
+                .unwrap_or_default(),
+            Some("AnnotationCollection") => root
+                .get("first")
+                .and_then(|first| first.get("items"))
+                .and_then(|v| v.as_array())
+                .map(|items| items.iter().collect())
+                .unwrap_or_default(),
+            _ => vec![root],
+        },
+    };
+
+    let mut annotations = Vec::new();
+    let mut skipped = Vec::new();
+    for (index, item) in items.into_iter().enumerate() {
+        match w3c_annotation(item) {
+            Ok(annotation) => annotations.push(annotation),
+            Err(e) => skipped.push(format!("第 {} 个标注: {e}", index + 1)),
+        }
+    }
+    (annotations, skipped)
+}
+
+// ---------------- 命令 ----------------
+
+fn detect_format(root: &Value) -> AnnotationFormat {
+    match root.get("type").and_then(|v| v.as_str()) {
+        Some("FeatureCollection" | "Feature") => AnnotationFormat::Geojson,
+        _ => AnnotationFormat::W3c,
+    }
+}
+
+/// 导出图片的标注
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `format` - 导出格式
+/// * `out_path` - 输出文件路径 不传时只返回 JSON 文本
+/// * `geo_transform` - GeoJSON 使用的仿射变换（GDAL GeoTransform） 不传时使用像素坐标
+/// # Returns
+/// * `Result<String, String>` - 导出的 JSON 文本
+#[tauri::command]
+pub fn export_annotations(
+    file_path: String,
+    format: AnnotationFormat,
+    out_path: Option<String>,
+    geo_transform: Option<GeoTransform>,
+) -> Result<String, String> {
+    let annotations = list_annotations(file_path.clone())?;
+    let root = match format {
+        AnnotationFormat::Geojson => export_geojson(&annotations, &geo_transform),
+        AnnotationFormat::W3c => export_w3c(&file_path, &annotations),
+    };
+    let json = serde_json::to_string_pretty(&root).map_err(|e| format!("序列化标注失败: {e}"))?;
+
+    if let Some(out_path) = out_path {
+        fs::write(&out_path, &json).map_err(|e| format!("写入标注文件失败: {e}"))?;
+    }
+    println!(
+        "[RUST] 导出标注: {file_path} {} 个 ({format:?})",
+        annotations.len()
+    );
+    Ok(json)
+}
+
+/// 从文件导入标注 与已有标注合并 ID 相同时覆盖
+/// # Arguments
+/// * `file_path` - 图片文件路径（需要已经预处理）
+/// * `in_path` - GeoJSON 或 W3C Web Annotation 文件
+/// * `format` - 文件格式 不传时根据内容判断
+/// * `geo_transform` - GeoJSON 使用的仿射变换 与导出时相同
+/// * `replace` - 为 true 时先清空已有标注
+/// # Returns
+/// * `Result<AnnotationImportReport, String>` - 导入结果
+#[tauri::command]
+pub fn import_annotations(
+    file_path: String,
+    in_path: String,
+    format: Option<AnnotationFormat>,
+    geo_transform: Option<GeoTransform>,
+    replace: Option<bool>,
+) -> Result<AnnotationImportReport, String> {
+    if let Some(t) = &geo_transform {
+        if t[1] * t[5] - t[2] * t[4] == 0.0 {
+            return Err("仿射变换不可逆".to_string());
+        }
+    }
+    let content =
+        fs::read_to_string(Path::new(&in_path)).map_err(|e| format!("读取标注文件失败: {e}"))?;
+    let root: Value =
+        serde_json::from_str(&content).map_err(|e| format!("解析标注文件失败: {e}"))?;
+
+    let format = format.unwrap_or_else(|| detect_format(&root));
+    let (imported, skipped) = match format {
+        AnnotationFormat::Geojson => import_geojson(&root, &geo_transform),
+        AnnotationFormat::W3c => import_w3c(&root),
+    };
+
+    let report = with_annotations(&file_path, "导入标注", |annotations| {
+        if replace.unwrap_or(false) {
+            annotations.clear();
+        }
+        let mut report = AnnotationImportReport {
+            skipped,
+            ..Default::default()
+        };
+        for annotation in imported {
+            match annotations.iter_mut().find(|a| a.id == annotation.id) {
+                Some(existing) => {
+                    *existing = annotation;
+                    report.replaced += 1;
+                }
+                None => annotations.push(annotation),
+            }
+            report.imported += 1;
+        }
+        Ok(report)
+    })?;
+
+    println!(
+        "[RUST] 导入标注: {in_path} -> {file_path} 导入 {} 个 (覆盖 {}, 跳过 {})",
+        report.imported,
+        report.replaced,
+        report.skipped.len()
+    );
+    Ok(report)
+}
@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use super::annotations::{new_annotation_id, ANNOTATIONS_FILE};
+use super::cache::{
+    check_file_cache_exists, ensure_cache_writable, image_cache_dir, is_cache_read_only,
+};
+use super::types::{Annotation, AnnotationGeometry, RegionLock, Viewport};
+use crate::utils::time::get_time;
+
+// 区域锁：缓存目录放在共享盘上、多人同时审阅一张图片时 避免互相覆盖标注
+// 锁信息保存在缓存目录的 annotation_locks.json 中 读改写标注和锁文件时用操作系统文件锁串行化
+// 锁有过期时间 审阅者异常退出后不会一直占着 需要继续编辑时重新获取即可续期
+
+// 锁信息文件
+const LOCKS_FILE: &str = "annotation_locks.json";
+// 读改写标注和锁文件时加文件锁的文件
+const SHARED_LOCK_FILE: &str = "annotations.lock";
+// 设置后作为默认的审阅者名称
+pub const REVIEWER_ENV: &str = "IMAGES_GL_REVIEWER";
+
+// 锁的默认有效期
+const DEFAULT_LOCK_TTL_SECS: u64 = 15 * 60;
+// 锁的最长有效期
+const MAX_LOCK_TTL_SECS: u64 = 24 * 3600;
+
+static REVIEWER: OnceLock<Mutex<String>> = OnceLock::new();
+
+fn reviewer() -> &'static Mutex<String> {
+    REVIEWER.get_or_init(|| {
+        let name = env::var(REVIEWER_ENV)
+            .ok()
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| {
+                let user = env::var("USERNAME")
+                    .or_else(|_| env::var("USER"))
+                    .unwrap_or_else(|_| "unknown".to_string());
+                match sysinfo::System::host_name() {
+                    Some(host) => format!("{user}@{host}"),
+                    None => user,
+                }
+            });
+        Mutex::new(name)
+    })
+}
+
+/// 当前审阅者名称
+pub fn current_reviewer() -> String {
+    reviewer()
+        .lock()
+        .map(|name| name.clone())
+        .unwrap_or_else(|e| e.into_inner().clone())
+}
+
+/// 获取当前审阅者名称 默认为 用户名@主机名
+#[tauri::command]
+pub fn get_reviewer() -> Result<String, String> {
+    Ok(current_reviewer())
+}
+
+/// 设置当前审阅者名称 区域锁按名称区分持有者
+#[tauri::command]
+pub fn set_reviewer(name: String) -> Result<String, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("审阅者名称不能为空".to_string());
+    }
+    *reviewer()
+        .lock()
+        .map_err(|e| format!("获取审阅者锁失败: {e}"))? = name.clone();
+    println!("[RUST] 当前审阅者: {name}");
+    Ok(name)
+}
+
+/// 持有缓存目录的共享文件锁执行 f 其他进程（包括其他机器）会等待
+/// 网络文件系统不支持文件锁时退化为只在本进程内串行化
+pub(super) fn with_shared_file_lock<T>(
+    cache_dir: &Path,
+    f: impl FnOnce() -> Result<T, String>,
+) -> Result<T, String> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(cache_dir.join(SHARED_LOCK_FILE))
+        .map_err(|e| format!("打开标注锁文件失败: {e}"))?;
+    if let Err(e) = fs4::FileExt::lock(&file) {
+        println!("[RUST] 获取标注文件锁失败 仅在本进程内加锁: {e}");
+    }
+    let result = f();
+    let _ = fs4::FileExt::unlock(&file);
+    result
+}
+
+// 读取未过期的锁 调用方需要持有共享文件锁
+fn load_active_locks(cache_dir: &Path) -> Vec<RegionLock> {
+    let now = get_time() as u64;
+    fs::read_to_string(cache_dir.join(LOCKS_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str::<Vec<RegionLock>>(&content).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|lock| lock.expires_ms > now)
+        .collect()
+}
+
+fn save_locks(cache_dir: &Path, locks: &[RegionLock]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(locks).map_err(|e| format!("序列化区域锁失败: {e}"))?;
+    fs::write(cache_dir.join(LOCKS_FILE), json).map_err(|e| format!("保存区域锁失败: {e}"))
+}
+
+// 几何形状的外接矩形 (x0, y0, x1, y1)
+fn geometry_bounds(geometry: &AnnotationGeometry) -> (f64, f64, f64, f64) {
+    match geometry {
+        AnnotationGeometry::Point { x, y } => (*x, *y, *x, *y),
+        AnnotationGeometry::Rectangle {
+            x,
+            y,
+            width,
+            height,
+        } => (*x, *y, x + width, y + height),
+        AnnotationGeometry::Polygon { points } | AnnotationGeometry::Polyline { points } => {
+            points.iter().fold(
+                (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+                |(x0, y0, x1, y1), [x, y]| (x0.min(*x), y0.min(*y), x1.max(*x), y1.max(*y)),
+            )
+        }
+    }
+}
+
+fn intersects(region: &Viewport, (x0, y0, x1, y1): (f64, f64, f64, f64)) -> bool {
+    x0 <= region.x + region.width
+        && x1 >= region.x
+        && y0 <= region.y + region.height
+        && y1 >= region.y
+}
+
+fn lock_covers(lock: &RegionLock, annotation: &Annotation) -> bool {
+    lock.annotation_ids.contains(&annotation.id)
+        || lock
+            .region
+            .as_ref()
+            .is_some_and(|region| intersects(region, geometry_bounds(&annotation.geometry)))
+}
+
+/// 检查一次标注修改是否碰到其他审阅者的锁 调用方需要持有共享文件锁
+/// 修改前后的形状都要检查 既不能改动锁定区域内的标注 也不能把标注移进锁定区域
+/// # Arguments
+/// * `cache_dir` - 图片的缓存目录
+/// * `before` - 修改前的标注
+/// * `after` - 修改后的标注
+/// # Returns
+/// * `Result<(), String>` - 碰到其他人的锁时返回错误
+pub(super) fn check_region_locks(
+    cache_dir: &Path,
+    before: &[Annotation],
+    after: &[Annotation],
+) -> Result<(), String> {
+    let me = current_reviewer();
+    let locks: Vec<RegionLock> = load_active_locks(cache_dir)
+        .into_iter()
+        .filter(|lock| lock.owner != me)
+        .collect();
+    if locks.is_empty() {
+        return Ok(());
+    }
+
+    let before_by_id: HashMap<&str, &Annotation> =
+        before.iter().map(|a| (a.id.as_str(), a)).collect();
+    let after_by_id: HashMap<&str, &Annotation> =
+        after.iter().map(|a| (a.id.as_str(), a)).collect();
+    let touched = before
+        .iter()
+        .filter(|a| after_by_id.get(a.id.as_str()) != Some(a))
+        .chain(
+            after
+                .iter()
+                .filter(|a| before_by_id.get(a.id.as_str()) != Some(a)),
+        );
+
+    for annotation in touched {
+        if let Some(lock) = locks.iter().find(|lock| lock_covers(lock, annotation)) {
+            return Err(format!(
+                "标注 {} 所在区域已被 {} 锁定",
+                annotation.id, lock.owner
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 锁定区域或标注 与其他审阅者未过期的锁重叠时失败
+/// 自己已有的锁不冲突 可以用来续期
+/// # Arguments
+/// * `file_path` - 图片文件路径（需要已经预处理）
+/// * `region` - 锁定的区域（图片像素坐标）
+/// * `annotation_ids` - 锁定的标注
+/// * `ttl_secs` - 有效期 默认 15 分钟 最长 24 小时
+/// # Returns
+/// * `Result<RegionLock, String>` - 获取到的锁
+#[tauri::command]
+pub fn lock_region(
+    file_path: String,
+    region: Option<Viewport>,
+    annotation_ids: Option<Vec<String>>,
+    ttl_secs: Option<u64>,
+) -> Result<RegionLock, String> {
+    ensure_cache_writable("锁定区域")?;
+    if !check_file_cache_exists(&file_path) {
+        return Err(format!("图片尚未预处理: {file_path}"));
+    }
+    let annotation_ids = annotation_ids.unwrap_or_default();
+    if region.is_none() && annotation_ids.is_empty() {
+        return Err("需要指定锁定的区域或标注".to_string());
+    }
+    if region.is_some_and(|r| r.width <= 0.0 || r.height <= 0.0) {
+        return Err("锁定区域的宽高必须大于 0".to_string());
+    }
+
+    let cache_dir = image_cache_dir(&file_path);
+    let me = current_reviewer();
+    let lock = with_shared_file_lock(&cache_dir, || {
+        let mut locks = load_active_locks(&cache_dir);
+        let annotations: Vec<Annotation> = fs::read_to_string(cache_dir.join(ANNOTATIONS_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        for other in locks.iter().filter(|lock| lock.owner != me) {
+            let region_overlaps = match (&region, &other.region) {
+                (Some(a), Some(b)) => intersects(a, (b.x, b.y, b.x + b.width, b.y + b.height)),
+                _ => false,
+            };
+            // 两把锁覆盖了同一个标注（直接指定或位于对方区域内）也算冲突
+            let shares_annotation = annotations.iter().any(|annotation| {
+                let mine = annotation_ids.contains(&annotation.id)
+                    || region
+                        .as_ref()
+                        .is_some_and(|r| intersects(r, geometry_bounds(&annotation.geometry)));
+                mine && lock_covers(other, annotation)
+            }) || annotation_ids
+                .iter()
+                .any(|id| other.annotation_ids.contains(id));
+            if region_overlaps || shares_annotation {
+                return Err(format!(
+                    "与 {} 的锁冲突 (到期时间 {})",
+                    other.owner, other.expires_ms
+                ));
+            }
+        }
+
+        let now = get_time() as u64;
+        let ttl = ttl_secs
+            .unwrap_or(DEFAULT_LOCK_TTL_SECS)
+            .clamp(1, MAX_LOCK_TTL_SECS);
+        let lock = RegionLock {
+            id: new_annotation_id(),
+            owner: me.clone(),
+            region,
+            annotation_ids,
+            acquired_ms: now,
+            expires_ms: now + ttl * 1000,
+        };
+        locks.push(lock.clone());
+        save_locks(&cache_dir, &locks)?;
+        Ok(lock)
+    })?;
+
+    println!(
+        "[RUST] 获取区域锁: {file_path} {} ({})",
+        lock.id, lock.owner
+    );
+    Ok(lock)
+}
+
+/// 释放区域锁 只能释放自己持有的锁
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `lock_id` - 锁 ID
+/// # Returns
+/// * `Result<Vec<RegionLock>, String>` - 剩余的有效锁
+#[tauri::command]
+pub fn unlock_region(file_path: String, lock_id: String) -> Result<Vec<RegionLock>, String> {
+    ensure_cache_writable("释放区域锁")?;
+    let cache_dir = image_cache_dir(&file_path);
+    let me = current_reviewer();
+    let locks = with_shared_file_lock(&cache_dir, || {
+        let mut locks = load_active_locks(&cache_dir);
+        let index = locks
+            .iter()
+            .position(|lock| lock.id == lock_id)
+            .ok_or_else(|| format!("区域锁不存在或已过期: {lock_id}"))?;
+        if locks[index].owner != me {
+            return Err(format!("区域锁由 {} 持有", locks[index].owner));
+        }
+        locks.remove(index);
+        save_locks(&cache_dir, &locks)?;
+        Ok(locks)
+    })?;
+    println!("[RUST] 释放区域锁: {file_path} {lock_id}");
+    Ok(locks)
+}
+
+/// 列出图片上所有未过期的区域锁
+#[tauri::command]
+pub fn list_region_locks(file_path: String) -> Result<Vec<RegionLock>, String> {
+    let cache_dir = image_cache_dir(&file_path);
+    if !cache_dir.exists() {
+        return Ok(Vec::new());
+    }
+    // 只读缓存不能创建锁文件 直接读取
+    if is_cache_read_only() {
+        return Ok(load_active_locks(&cache_dir));
+    }
+    with_shared_file_lock(&cache_dir, || Ok(load_active_locks(&cache_dir)))
+}
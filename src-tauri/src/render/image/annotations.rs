@@ -0,0 +1,232 @@
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+use super::annotation_history::{diff_annotations, record_annotation_edit};
+use super::annotation_locks::{check_region_locks, with_shared_file_lock};
+use super::cache::{check_file_cache_exists, ensure_cache_writable, image_cache_dir};
+use super::types::{Annotation, AnnotationGeometry};
+use crate::utils::time::get_time;
+
+// 标注存储：每张图片的标注保存在缓存目录的 annotations.json 中
+// 缓存目录放在共享盘上时 多个审阅者可以看到同一份标注 修改时的冲突检测见 annotation_locks.rs
+// 有标注的缓存不会被自动淘汰 只有手动清理缓存时才会一起删除
+
+// 标注文件
+pub(super) const ANNOTATIONS_FILE: &str = "annotations.json";
+
+// 读改写标注文件时持有 避免并发修改时互相覆盖
+static ANNOTATIONS_LOCK: Mutex<()> = Mutex::new(());
+// 同一毫秒内生成多个 ID 时区分
+static ANNOTATION_SEQ: AtomicU32 = AtomicU32::new(0);
+
+/// 生成新的标注 ID
+pub(super) fn new_annotation_id() -> String {
+    let seq = ANNOTATION_SEQ.fetch_add(1, Ordering::Relaxed) & 0xffff;
+    format!("a{:x}{seq:04x}", get_time())
+}
+
+/// 缓存目录中是否保存了标注
+pub fn has_annotations(cache_dir: &Path) -> bool {
+    fs::metadata(cache_dir.join(ANNOTATIONS_FILE)).is_ok_and(|m| m.len() > 2)
+}
+
+/// 校验标注的几何形状
+pub(super) fn validate_geometry(geometry: &AnnotationGeometry) -> Result<(), String> {
+    let finite = |values: &[f64]| values.iter().all(|v| v.is_finite());
+    let valid = match geometry {
+        AnnotationGeometry::Point { x, y } => finite(&[*x, *y]),
+        AnnotationGeometry::Rectangle {
+            x,
+            y,
+            width,
+            height,
+        } => finite(&[*x, *y, *width, *height]) && *width > 0.0 && *height > 0.0,
+        AnnotationGeometry::Polygon { points } => {
+            points.len() >= 3 && points.iter().all(|p| finite(p))
+        }
+        AnnotationGeometry::Polyline { points } => {
+            points.len() >= 2 && points.iter().all(|p| finite(p))
+        }
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("无效的标注形状: {geometry:?}"))
+    }
+}
+
+fn load_annotations_unlocked(file_path: &str) -> Result<Vec<Annotation>, String> {
+    let path = image_cache_dir(file_path).join(ANNOTATIONS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| format!("读取标注失败: {e}"))?;
+    serde_json::from_str(&content).map_err(|e| format!("解析标注失败: {e}"))
+}
+
+/// 在标注列表上执行一次读改写 不记录撤销历史 图片需要已经预处理
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `f` - 修改标注列表 返回 Err 时不保存
+/// # Returns
+/// * `Result<T, String>` - f 的返回值
+pub(super) fn modify_annotations<T>(
+    file_path: &str,
+    f: impl FnOnce(&mut Vec<Annotation>) -> Result<T, String>,
+) -> Result<T, String> {
+    ensure_cache_writable("修改标注")?;
+    if !check_file_cache_exists(file_path) {
+        return Err(format!("图片尚未预处理: {file_path}"));
+    }
+    let _guard = ANNOTATIONS_LOCK
+        .lock()
+        .map_err(|e| format!("获取标注锁失败: {e}"))?;
+    let cache_dir = image_cache_dir(file_path);
+    with_shared_file_lock(&cache_dir, || {
+        let mut annotations = load_annotations_unlocked(file_path)?;
+        let before = annotations.clone();
+        let result = f(&mut annotations)?;
+        check_region_locks(&cache_dir, &before, &annotations)?;
+
+        let json = serde_json::to_string_pretty(&annotations)
+            .map_err(|e| format!("序列化标注失败: {e}"))?;
+        // 先写临时文件再重命名 共享盘上其他人读到的不会是写了一半的文件
+        let path = cache_dir.join(ANNOTATIONS_FILE);
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, json).map_err(|e| format!("保存标注失败: {e}"))?;
+        fs::rename(&tmp_path, &path).map_err(|e| format!("保存标注失败: {e}"))?;
+        Ok(result)
+    })
+}
+
+/// 在标注列表上执行一次读改写 并把改动记录到撤销历史中
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `description` - 操作说明 显示在撤销/重做菜单中
+/// * `f` - 修改标注列表 返回 Err 时不保存
+/// # Returns
+/// * `Result<T, String>` - f 的返回值
+pub(super) fn with_annotations<T>(
+    file_path: &str,
+    description: &str,
+    f: impl FnOnce(&mut Vec<Annotation>) -> Result<T, String>,
+) -> Result<T, String> {
+    let (result, changes) = modify_annotations(file_path, |annotations| {
+        let before = annotations.clone();
+        let result = f(annotations)?;
+        Ok((result, diff_annotations(&before, annotations)))
+    })?;
+    record_annotation_edit(file_path, description, changes);
+    Ok(result)
+}
+
+/// 读取图片的所有标注
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<Vec<Annotation>, String>` - 按创建顺序排列的标注 没有时为空
+#[tauri::command]
+pub fn list_annotations(file_path: String) -> Result<Vec<Annotation>, String> {
+    let _guard = ANNOTATIONS_LOCK
+        .lock()
+        .map_err(|e| format!("获取标注锁失败: {e}"))?;
+    load_annotations_unlocked(&file_path)
+}
+
+/// 添加标注
+/// # Arguments
+/// * `file_path` - 图片文件路径（需要已经预处理）
+/// * `geometry` - 几何形状（图片像素坐标）
+/// * `label` - 分类标签
+/// * `comment` - 批注文字
+/// * `color` - 显示颜色
+/// * `author` - 作者
+/// # Returns
+/// * `Result<Annotation, String>` - 新建的标注
+#[tauri::command]
+pub fn add_annotation(
+    file_path: String,
+    geometry: AnnotationGeometry,
+    label: Option<String>,
+    comment: Option<String>,
+    color: Option<String>,
+    author: Option<String>,
+) -> Result<Annotation, String> {
+    validate_geometry(&geometry)?;
+    let now = get_time() as u64;
+    let annotation = Annotation {
+        id: new_annotation_id(),
+        geometry,
+        label,
+        comment,
+        color,
+        author,
+        created_ms: now,
+        updated_ms: now,
+    };
+
+    with_annotations(&file_path, "添加标注", |annotations| {
+        annotations.push(annotation.clone());
+        Ok(())
+    })?;
+    println!("[RUST] 添加标注: {file_path} {}", annotation.id);
+    Ok(annotation)
+}
+
+/// 修改标注 按 ID 整体替换 创建时间保持不变
+/// 传入的 updated_ms 需要与已保存的一致 否则说明其他人在此期间修改过 拒绝覆盖
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `annotation` - 修改后的标注（updated_ms 为读取时的值）
+/// # Returns
+/// * `Result<Annotation, String>` - 保存后的标注
+#[tauri::command]
+pub fn update_annotation(file_path: String, annotation: Annotation) -> Result<Annotation, String> {
+    validate_geometry(&annotation.geometry)?;
+    let updated = with_annotations(&file_path, "修改标注", |annotations| {
+        let existing = annotations
+            .iter_mut()
+            .find(|a| a.id == annotation.id)
+            .ok_or_else(|| format!("标注不存在: {}", annotation.id))?;
+        if existing.updated_ms != annotation.updated_ms {
+            return Err(format!(
+                "标注 {} 在读取后已被修改 (作者 {}) 请刷新后再保存",
+                annotation.id,
+                existing.author.as_deref().unwrap_or("未知")
+            ));
+        }
+        *existing = Annotation {
+            created_ms: existing.created_ms,
+            updated_ms: get_time() as u64,
+            ..annotation
+        };
+        Ok(existing.clone())
+    })?;
+    println!("[RUST] 修改标注: {file_path} {}", updated.id);
+    Ok(updated)
+}
+
+/// 删除标注
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `annotation_id` - 标注 ID
+/// # Returns
+/// * `Result<Vec<Annotation>, String>` - 删除后剩余的标注
+#[tauri::command]
+pub fn delete_annotation(
+    file_path: String,
+    annotation_id: String,
+) -> Result<Vec<Annotation>, String> {
+    let remaining = with_annotations(&file_path, "删除标注", |annotations| {
+        let before = annotations.len();
+        annotations.retain(|a| a.id != annotation_id);
+        if annotations.len() == before {
+            return Err(format!("标注不存在: {annotation_id}"));
+        }
+        Ok(annotations.clone())
+    })?;
+    println!("[RUST] 删除标注: {file_path} {annotation_id}");
+    Ok(remaining)
+}
@@ -0,0 +1,186 @@
+use std::fs;
+
+use tauri::ipc::Response;
+
+use super::cache::{check_file_cache_exists, load_cached_metadata};
+use super::errors::{localized_error, ErrorCode};
+use super::export::validate_region;
+use super::types::ImageRegion;
+use crate::utils::time::Stopwatch;
+
+// Arrow IPC 导出：把区域内的 chunk 像素和坐标输出为 Arrow 流
+// Python 中用 pyarrow.ipc.open_stream 读取 每行的 pixels 可以直接
+// np.frombuffer(pixels, np.uint8).reshape(height, width, 4) 不需要自己解析 chunk 文件格式
+// 每个 chunk 输出为一个 record batch Binary 列的 i32 偏移只需要容纳一个 chunk 的像素
+// 区域边缘的 chunk 只包含与区域相交的部分
+// 整个流在内存中生成 区域的像素超过 MAX_ARROW_EXPORT_BYTES 时拒绝 更大的区域由调用方分块导出
+// 需要启用 arrow 特性（desktop 特性组默认包含）
+
+// 一次导出的最大像素字节数
+const MAX_ARROW_EXPORT_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// 把区域内的 chunk 编码为 Arrow IPC 流
+/// # Arguments
+/// * `file_path` - 图片文件路径（需要已经预处理）
+/// * `rect` - 区域（图片像素坐标）
+/// # Returns
+/// * `Result<(Vec<u8>, usize), String>` - Arrow IPC 流数据和行数
+pub fn encode_chunks_arrow(
+    file_path: &str,
+    rect: &ImageRegion,
+) -> Result<(Vec<u8>, usize), String> {
+    if !check_file_cache_exists(file_path) {
+        return Err(localized_error(ErrorCode::CacheMissing, &[]));
+    }
+    let metadata = load_cached_metadata(file_path)?;
+    validate_region(&metadata, rect)?;
+    let bytes = rect.width as u64 * rect.height as u64 * 4;
+    if bytes > MAX_ARROW_EXPORT_BYTES {
+        return Err(format!(
+            "Arrow 导出的区域过大: {} 字节 超过 {MAX_ARROW_EXPORT_BYTES} 字节 请分块导出",
+            bytes
+        ));
+    }
+
+    backend::encode(file_path, &metadata, rect)
+}
+
+/// 把区域内的 chunk 像素和坐标导出为 Arrow IPC 流
+/// 每行是一个 chunk 与区域的交集：chunk_x, chunk_y, x, y, width, height, pixels(RGBA8)
+/// # Arguments
+/// * `file_path` - 图片文件路径（需要已经预处理）
+/// * `rect` - 区域（图片像素坐标）
+/// * `out_path` - 同时写入的文件路径 不传时只返回数据
+/// # Returns
+/// * `Result<Response, String>` - Arrow IPC 流数据（二进制）
+#[tauri::command]
+pub fn export_chunks_arrow(
+    file_path: String,
+    rect: ImageRegion,
+    out_path: Option<String>,
+) -> Result<Response, String> {
+    let stopwatch = Stopwatch::start();
+    let (data, rows) = encode_chunks_arrow(&file_path, &rect)?;
+    if let Some(out_path) = &out_path {
+        fs::write(out_path, &data).map_err(|e| format!("写入 Arrow 文件失败: {e}"))?;
+    }
+    println!(
+        "[RUST] 导出 Arrow: {file_path} {rect:?} {rows} 个 chunk, {} 字节 (耗时: {}ms)",
+        data.len(),
+        stopwatch.elapsed_ms()
+    );
+    Ok(Response::new(data))
+}
+
+#[cfg(feature = "arrow")]
+mod backend {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use arrow_array::{ArrayRef, BinaryArray, RecordBatch, UInt32Array};
+    use arrow_ipc::writer::StreamWriter;
+    use arrow_schema::{DataType, Field, Schema};
+
+    use super::super::export::compose_region;
+    use super::super::types::{ChunkInfo, ImageMetadata, ImageRegion};
+
+    fn arrow_schema(file_path: &str, metadata: &ImageMetadata, rect: &ImageRegion) -> Schema {
+        let fields = vec![
+            Field::new("chunk_x", DataType::UInt32, false),
+            Field::new("chunk_y", DataType::UInt32, false),
+            // 像素块左上角在图片中的坐标
+            Field::new("x", DataType::UInt32, false),
+            Field::new("y", DataType::UInt32, false),
+            Field::new("width", DataType::UInt32, false),
+            Field::new("height", DataType::UInt32, false),
+            // RGBA8 按行排列
+            Field::new("pixels", DataType::Binary, false),
+        ];
+        let schema_metadata = HashMap::from([
+            ("file_path".to_string(), file_path.to_string()),
+            ("total_width".to_string(), metadata.total_width.to_string()),
+            (
+                "total_height".to_string(),
+                metadata.total_height.to_string(),
+            ),
+            (
+                "rect".to_string(),
+                format!("{},{},{},{}", rect.x, rect.y, rect.width, rect.height),
+            ),
+            ("pixel_format".to_string(), "rgba8".to_string()),
+        ]);
+        Schema::new(fields).with_metadata(schema_metadata)
+    }
+
+    // chunk 与区域的交集 不相交时为 None
+    fn intersect(chunk: &ChunkInfo, rect: &ImageRegion) -> Option<ImageRegion> {
+        let x0 = rect.x.max(chunk.x);
+        let y0 = rect.y.max(chunk.y);
+        let x1 = (rect.x + rect.width).min(chunk.x + chunk.width);
+        let y1 = (rect.y + rect.height).min(chunk.y + chunk.height);
+        (x0 < x1 && y0 < y1).then(|| ImageRegion {
+            x: x0,
+            y: y0,
+            width: x1 - x0,
+            height: y1 - y0,
+        })
+    }
+
+    /// 编码区域内的 chunk 返回 Arrow IPC 流数据和行数
+    pub fn encode(
+        file_path: &str,
+        metadata: &ImageMetadata,
+        rect: &ImageRegion,
+    ) -> Result<(Vec<u8>, usize), String> {
+        let mut pieces: Vec<(&ChunkInfo, ImageRegion)> = metadata
+            .chunks
+            .iter()
+            .filter_map(|chunk| Some((chunk, intersect(chunk, rect)?)))
+            .collect();
+        pieces.sort_by_key(|(chunk, _)| (chunk.chunk_y, chunk.chunk_x));
+
+        let schema = Arc::new(arrow_schema(file_path, metadata, rect));
+        let mut writer = StreamWriter::try_new(Vec::new(), &schema)
+            .map_err(|e| format!("创建 Arrow 输出失败: {e}"))?;
+
+        for (chunk, piece) in &pieces {
+            let pixels = compose_region(file_path, metadata, piece)?;
+            let column = |value: u32| -> ArrayRef { Arc::new(UInt32Array::from(vec![value])) };
+            let columns = vec![
+                column(chunk.chunk_x),
+                column(chunk.chunk_y),
+                column(piece.x),
+                column(piece.y),
+                column(piece.width),
+                column(piece.height),
+                Arc::new(BinaryArray::from_iter_values([pixels])) as ArrayRef,
+            ];
+            let batch = RecordBatch::try_new(schema.clone(), columns)
+                .map_err(|e| format!("生成 Arrow 数据失败: {e}"))?;
+            writer
+                .write(&batch)
+                .map_err(|e| format!("写入 Arrow 数据失败: {e}"))?;
+        }
+
+        writer
+            .finish()
+            .map_err(|e| format!("写入 Arrow 数据失败: {e}"))?;
+        let data = writer
+            .into_inner()
+            .map_err(|e| format!("写入 Arrow 数据失败: {e}"))?;
+        Ok((data, pieces.len()))
+    }
+}
+
+#[cfg(not(feature = "arrow"))]
+mod backend {
+    use super::super::types::{ImageMetadata, ImageRegion};
+
+    pub fn encode(
+        _file_path: &str,
+        _metadata: &ImageMetadata,
+        _rect: &ImageRegion,
+    ) -> Result<(Vec<u8>, usize), String> {
+        Err("未启用 Arrow 导出 请使用 --features arrow 重新编译".to_string())
+    }
+}
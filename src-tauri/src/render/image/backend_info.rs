@@ -0,0 +1,111 @@
+use image::ImageFormat;
+
+use super::cache::is_cache_read_only;
+use super::config::{CHUNK_FORMAT_VERSION, SUPPORTED_CHUNK_FORMAT_VERSIONS};
+use super::gpu_compute::is_gpu_compute_available;
+use super::types::{BackendDecoder, BackendInfo};
+
+// 版本握手：前端启动时调用 get_backend_info 根据后端的能力调整界面
+// 例如文件选择器只列出当前构建能打开的扩展名 缓存格式不兼容时提示重新预处理
+
+fn decoder(format: &str, extensions: &[&str], command: &str, available: bool) -> BackendDecoder {
+    BackendDecoder {
+        format: format.to_string(),
+        extensions: extensions.iter().map(|e| e.to_string()).collect(),
+        command: command.to_string(),
+        available,
+    }
+}
+
+// 可选特性及是否编译进来 与 Cargo.toml 中的 [features] 对应
+pub(super) const OPTIONAL_FEATURES: [(&str, bool); 8] = [
+    ("screen-capture", cfg!(feature = "screen-capture")),
+    ("clipboard", cfg!(feature = "clipboard")),
+    ("arrow", cfg!(feature = "arrow")),
+    ("pdf", cfg!(feature = "pdf")),
+    ("dicom", cfg!(feature = "dicom")),
+    ("video", cfg!(feature = "video")),
+    ("gpu", cfg!(feature = "gpu")),
+    ("camera", cfg!(feature = "camera")),
+];
+
+/// 当前构建支持的输入格式 自检报告中也使用这个列表
+pub(super) fn supported_decoders() -> Vec<BackendDecoder> {
+    // 预处理流程按内容识别格式后选择对应的解码器（见 decode_source_image_in_process）
+    let pipeline = |format: ImageFormat| format.reading_enabled();
+    vec![
+        decoder(
+            "png",
+            &["png"],
+            "process_user_image",
+            pipeline(ImageFormat::Png),
+        ),
+        decoder(
+            "jpeg",
+            &["jpg", "jpeg"],
+            "process_user_image",
+            pipeline(ImageFormat::Jpeg),
+        ),
+        decoder(
+            "bmp",
+            &["bmp"],
+            "process_user_image",
+            pipeline(ImageFormat::Bmp),
+        ),
+        decoder(
+            "tiff",
+            &["tif", "tiff"],
+            "process_user_image",
+            pipeline(ImageFormat::Tiff),
+        ),
+        decoder(
+            "webp",
+            &["webp"],
+            "process_user_image",
+            pipeline(ImageFormat::WebP),
+        ),
+        decoder("fits", &["fits", "fit", "fts"], "process_fits_image", true),
+        decoder("psd", &["psd", "psb"], "process_psd_image", true),
+        decoder("ktx2", &["ktx2"], "process_texture_image", true),
+        decoder("dds", &["dds"], "process_texture_image", true),
+        decoder("pdf", &["pdf"], "process_pdf_page", cfg!(feature = "pdf")),
+        decoder(
+            "dicom",
+            &["dcm", "dicom"],
+            "process_dicom_image",
+            cfg!(feature = "dicom"),
+        ),
+        decoder(
+            "video",
+            &["mp4", "mov", "mkv", "avi", "webm"],
+            "open_video_frame",
+            cfg!(feature = "video"),
+        ),
+    ]
+}
+
+// 编译进来的可选特性
+pub(super) fn compiled_features() -> Vec<String> {
+    OPTIONAL_FEATURES
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+/// 获取后端版本和能力
+/// 前端启动时调用 根据支持的格式和特性调整界面 根据缓存格式版本判断缓存是否兼容
+/// # Returns
+/// * `Result<BackendInfo, String>` - 后端信息
+#[tauri::command]
+pub fn get_backend_info() -> Result<BackendInfo, String> {
+    Ok(BackendInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        chunk_format_version: CHUNK_FORMAT_VERSION,
+        supported_chunk_format_versions: SUPPORTED_CHUNK_FORMAT_VERSIONS.to_vec(),
+        decoders: supported_decoders(),
+        features: compiled_features(),
+        cache_read_only: is_cache_read_only(),
+        gpu_compute: is_gpu_compute_available(),
+    })
+}
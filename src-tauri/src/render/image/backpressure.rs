@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use super::errors::{localized_error, ErrorCode};
+use super::types::ChunkRequestStats;
+
+// chunk 请求的背压：前端出错时可能一次请求所有 chunk 每个请求都占用一个线程和一块 chunk 大小的内存
+// 限制同时处理的请求数（全局和每张图片） 超出时立即返回 [BUSY] 错误 前端稍后重试即可
+// 被拒绝的请求计入统计 方便发现前端的请求风暴
+
+// 同时处理的 chunk 请求上限
+const MAX_IN_FLIGHT_CHUNKS: usize = 192;
+const MAX_IN_FLIGHT_CHUNKS_PER_IMAGE: usize = 96;
+
+#[derive(Default)]
+struct InFlight {
+    total: usize,
+    per_image: HashMap<String, usize>,
+}
+
+static IN_FLIGHT: OnceLock<Mutex<InFlight>> = OnceLock::new();
+static REJECTED_GLOBAL: AtomicU64 = AtomicU64::new(0);
+static REJECTED_PER_IMAGE: AtomicU64 = AtomicU64::new(0);
+static PEAK_IN_FLIGHT: AtomicU64 = AtomicU64::new(0);
+
+fn in_flight() -> &'static Mutex<InFlight> {
+    IN_FLIGHT.get_or_init(|| Mutex::new(InFlight::default()))
+}
+
+/// chunk 请求的名额 请求结束（包括出错）时自动归还
+pub struct ChunkRequestPermit {
+    file_path: String,
+}
+
+impl ChunkRequestPermit {
+    /// 申请一个 chunk 请求名额 超出上限时返回 [BUSY] 错误
+    /// # Arguments
+    /// * `file_path` - 请求的图片
+    pub fn acquire(file_path: &str) -> Result<Self, String> {
+        let mut state = in_flight()
+            .lock()
+            .map_err(|e| format!("获取 chunk 请求计数锁失败: {e}"))?;
+        if state.total >= MAX_IN_FLIGHT_CHUNKS {
+            REJECTED_GLOBAL.fetch_add(1, Ordering::Relaxed);
+            return Err(localized_error(
+                ErrorCode::Busy,
+                &[("limit", &MAX_IN_FLIGHT_CHUNKS)],
+            ));
+        }
+        let image = state.per_image.entry(file_path.to_string()).or_insert(0);
+        if *image >= MAX_IN_FLIGHT_CHUNKS_PER_IMAGE {
+            REJECTED_PER_IMAGE.fetch_add(1, Ordering::Relaxed);
+            return Err(localized_error(
+                ErrorCode::Busy,
+                &[("limit", &MAX_IN_FLIGHT_CHUNKS_PER_IMAGE)],
+            ));
+        }
+        *image += 1;
+        state.total += 1;
+        PEAK_IN_FLIGHT.fetch_max(state.total as u64, Ordering::Relaxed);
+        Ok(ChunkRequestPermit {
+            file_path: file_path.to_string(),
+        })
+    }
+}
+
+impl Drop for ChunkRequestPermit {
+    fn drop(&mut self) {
+        let mut state = in_flight().lock().unwrap_or_else(|e| e.into_inner());
+        state.total = state.total.saturating_sub(1);
+        if let Some(count) = state.per_image.get_mut(&self.file_path) {
+            *count -= 1;
+            if *count == 0 {
+                state.per_image.remove(&self.file_path);
+            }
+        }
+    }
+}
+
+/// 获取 chunk 请求的统计：正在处理的数量、上限、峰值和被拒绝的次数
+#[tauri::command]
+pub fn get_chunk_request_stats() -> Result<ChunkRequestStats, String> {
+    let state = in_flight()
+        .lock()
+        .map_err(|e| format!("获取 chunk 请求计数锁失败: {e}"))?;
+    Ok(ChunkRequestStats {
+        in_flight: state.total as u32,
+        max_in_flight: MAX_IN_FLIGHT_CHUNKS as u32,
+        max_in_flight_per_image: MAX_IN_FLIGHT_CHUNKS_PER_IMAGE as u32,
+        peak_in_flight: PEAK_IN_FLIGHT.load(Ordering::Relaxed) as u32,
+        rejected_global: REJECTED_GLOBAL.load(Ordering::Relaxed),
+        rejected_per_image: REJECTED_PER_IMAGE.load(Ordering::Relaxed),
+    })
+}
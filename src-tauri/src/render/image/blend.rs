@@ -0,0 +1,170 @@
+use rayon::prelude::*;
+use tauri::ipc::Response;
+
+use super::cache::load_cached_metadata;
+use super::chunk_processing::load_chunk_bytes;
+use super::export::compose_region;
+use super::types::{BlendMode, CompareMode, ImageRegion, SwipeAxis};
+
+// 图片混合和对比：把两张已经配准（像素坐标一致）的图片按 chunk 混合或交替显示 例如修复前后
+// chunk 网格以图片 A 为准 图片 B 的分块大小可以不同 从 B 的缓存中拼出同一块区域
+// 在后端合成 前端只需要按普通 chunk 显示
+
+// A 的 chunk 和 B 中同一块区域的像素
+struct ChunkPair {
+    // A 的 chunk 数据（含 8 字节头部） 结果直接写在这里
+    output: Vec<u8>,
+    // chunk 在图片中的位置和宽度
+    origin: (u32, u32),
+    chunk_width: u32,
+    // chunk 与 B 的交集的尺寸 左上角与 chunk 相同 超出 B 的部分保持 A 不变
+    overlap: (u32, u32),
+    top: Vec<u8>,
+}
+
+fn load_chunk_pair(image_a: String, image_b: &str, x: u32, y: u32) -> Result<ChunkPair, String> {
+    let metadata_a = load_cached_metadata(&image_a)?;
+    let info = metadata_a
+        .chunks
+        .iter()
+        .find(|c| c.chunk_x == x && c.chunk_y == y)
+        .ok_or_else(|| format!("chunk 不存在: ({x}, {y})"))?;
+    let output = load_chunk_bytes(x, y, image_a, false, None)?;
+
+    let metadata_b = load_cached_metadata(image_b)?;
+    let right = (info.x + info.width).min(metadata_b.total_width);
+    let bottom = (info.y + info.height).min(metadata_b.total_height);
+    let overlap = (right.saturating_sub(info.x), bottom.saturating_sub(info.y));
+    let top = if overlap.0 == 0 || overlap.1 == 0 {
+        Vec::new()
+    } else {
+        let region = ImageRegion {
+            x: info.x,
+            y: info.y,
+            width: overlap.0,
+            height: overlap.1,
+        };
+        compose_region(image_b, &metadata_b, &region)?
+    };
+    Ok(ChunkPair {
+        output,
+        origin: (info.x, info.y),
+        chunk_width: info.width,
+        overlap,
+        top,
+    })
+}
+
+impl ChunkPair {
+    // 对交集中的每个像素调用 f(图片坐标, A 的像素, B 的像素) 结果写回 A 的像素
+    fn combine(mut self, f: impl Fn(u32, u32, &mut [u8], &[u8]) + Sync) -> Vec<u8> {
+        if self.top.is_empty() {
+            return self.output;
+        }
+        let (origin_x, origin_y) = self.origin;
+        let top_row_len = self.overlap.0 as usize * 4;
+        self.output[8..]
+            .par_chunks_mut(self.chunk_width as usize * 4)
+            .take(self.overlap.1 as usize)
+            .zip(self.top.par_chunks(top_row_len))
+            .enumerate()
+            .for_each(|(row, (base_row, top_row))| {
+                let py = origin_y + row as u32;
+                for (column, (base, top)) in base_row
+                    .chunks_exact_mut(4)
+                    .zip(top_row.chunks_exact(4))
+                    .enumerate()
+                {
+                    f(origin_x + column as u32, py, base, top);
+                }
+            });
+        self.output
+    }
+}
+
+/// 获取两张图片混合后的 chunk（两张图片都需要先完成预处理）
+/// B 在 A 之上 B 的透明度和 opacity 共同决定混合的比例 结果的透明度与 A 相同
+/// 超出 B 范围的部分保持 A 不变
+/// 数据格式与 chunk 相同：宽度(4字节) + 高度(4字节) + RGBA 数据
+/// # Arguments
+/// * `image_a` - 底层图片路径 决定 chunk 网格
+/// * `image_b` - 上层图片路径
+/// * `x` / `y` - chunk 在 A 中的列、行索引
+/// * `mode` - 混合模式
+/// * `opacity` - 上层的不透明度（0-1）
+#[tauri::command]
+pub fn get_blended_chunk(
+    image_a: String,
+    image_b: String,
+    x: u32,
+    y: u32,
+    mode: BlendMode,
+    opacity: f64,
+) -> Result<Response, String> {
+    if !(0.0..=1.0).contains(&opacity) {
+        return Err(format!("不透明度必须在 0 到 1 之间: {opacity}"));
+    }
+    let pair = load_chunk_pair(image_a, &image_b, x, y)?;
+    if opacity == 0.0 {
+        return Ok(Response::new(pair.output));
+    }
+    let output = pair.combine(|_, _, base, top| {
+        let weight = opacity * top[3] as f64 / 255.0;
+        for c in 0..3 {
+            let (a, b) = (base[c] as f64 / 255.0, top[c] as f64 / 255.0);
+            let blended = match mode {
+                BlendMode::Normal => b,
+                BlendMode::Multiply => a * b,
+                BlendMode::Difference => (a - b).abs(),
+                BlendMode::Screen => 1.0 - (1.0 - a) * (1.0 - b),
+            };
+            base[c] = ((a + (blended - a) * weight) * 255.0).round() as u8;
+        }
+    });
+    Ok(Response::new(output))
+}
+
+/// 获取两张图片对比显示的 chunk（两张图片都需要先完成预处理）
+/// 棋盘格和分割线都使用图片坐标 相邻 chunk 拼起来是连续的
+/// 超出 B 范围的部分显示 A
+/// 数据格式与 chunk 相同：宽度(4字节) + 高度(4字节) + RGBA 数据
+/// # Arguments
+/// * `image_a` / `image_b` - 对比的两张图片 chunk 网格以 A 为准
+/// * `x` / `y` - chunk 在 A 中的列、行索引
+/// * `mode` - 对比方式
+#[tauri::command]
+pub fn get_compare_chunk(
+    image_a: String,
+    image_b: String,
+    x: u32,
+    y: u32,
+    mode: CompareMode,
+) -> Result<Response, String> {
+    match mode {
+        CompareMode::Checkerboard { cell_size: 0 } => {
+            return Err("棋盘格的格子边长必须大于 0".to_string());
+        }
+        CompareMode::Swipe { position, .. } if !position.is_finite() => {
+            return Err(format!("分割线位置无效: {position}"));
+        }
+        _ => {}
+    }
+    let pair = load_chunk_pair(image_a, &image_b, x, y)?;
+    let show_b = move |px: u32, py: u32| match mode {
+        CompareMode::Checkerboard { cell_size } => (px / cell_size + py / cell_size) % 2 == 1,
+        CompareMode::Swipe {
+            axis: SwipeAxis::Vertical,
+            position,
+        } => px as f64 + 0.5 > position,
+        CompareMode::Swipe {
+            axis: SwipeAxis::Horizontal,
+            position,
+        } => py as f64 + 0.5 > position,
+    };
+    let output = pair.combine(|px, py, base, top| {
+        if show_b(px, py) {
+            base.copy_from_slice(top);
+        }
+    });
+    Ok(Response::new(output))
+}
@@ -0,0 +1,206 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Window};
+
+use super::cache::fnv1a_hash;
+use super::commands::{open_user_image, validate_image_path};
+use super::deep_link::NAVIGATION_EVENT;
+use super::types::{Bookmark, NavigationTarget, Tour, Viewport};
+use super::utils::app_data_subdir;
+use super::window_state::{set_window_image, update_window_state};
+use crate::utils::time::get_time;
+
+// 视口书签：标记感兴趣的区域 之后可以跳回去
+// 书签是用户数据 保存在应用数据目录而不是 chunk 缓存中 清理或淘汰缓存不会丢失
+// 每张图片一个文件 文件名与缓存目录一样使用路径的 FNV 哈希 导览（tours.rs）也保存在同一个文件中
+
+// 书签目录（应用数据目录下）
+const BOOKMARK_DIR: &str = "bookmarks";
+
+// 读改写书签文件时持有 避免并发添加时互相覆盖
+pub(super) static BOOKMARKS_LOCK: Mutex<()> = Mutex::new(());
+
+// 单张图片的书签文件
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub(super) struct BookmarkFile {
+    pub file_path: String,
+    pub bookmarks: Vec<Bookmark>,
+    #[serde(default)]
+    pub tours: Vec<Tour>,
+}
+
+pub(super) fn bookmark_file_path(app: &AppHandle, file_path: &str) -> Result<PathBuf, String> {
+    let dir = app_data_subdir(app, BOOKMARK_DIR)?;
+    Ok(dir.join(format!("{:016x}.json", fnv1a_hash(file_path.as_bytes()))))
+}
+
+pub(super) fn load_bookmark_file(path: &PathBuf, file_path: &str) -> Result<BookmarkFile, String> {
+    if !path.exists() {
+        return Ok(BookmarkFile {
+            file_path: file_path.to_string(),
+            ..Default::default()
+        });
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("读取书签失败: {e}"))?;
+    serde_json::from_str(&content).map_err(|e| format!("解析书签失败: {e}"))
+}
+
+pub(super) fn save_bookmark_file(path: &PathBuf, bookmarks: &BookmarkFile) -> Result<(), String> {
+    let json =
+        serde_json::to_string_pretty(bookmarks).map_err(|e| format!("序列化书签失败: {e}"))?;
+    fs::write(path, json).map_err(|e| format!("保存书签失败: {e}"))
+}
+
+pub(super) fn validate_viewport(viewport: &Viewport) -> Result<(), String> {
+    let values = [viewport.x, viewport.y, viewport.width, viewport.height];
+    if values.iter().any(|v| !v.is_finite()) || viewport.width <= 0.0 || viewport.height <= 0.0 {
+        return Err(format!("无效的视口: {viewport:?}"));
+    }
+    Ok(())
+}
+
+/// 添加书签
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `name` - 书签名称
+/// * `viewport` - 要记录的视口（图片像素坐标）
+/// # Returns
+/// * `Result<Bookmark, String>` - 新建的书签
+#[tauri::command]
+pub fn add_bookmark(
+    app: AppHandle,
+    file_path: String,
+    name: String,
+    viewport: Viewport,
+) -> Result<Bookmark, String> {
+    validate_viewport(&viewport)?;
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("书签名称不能为空".to_string());
+    }
+
+    let _guard = BOOKMARKS_LOCK
+        .lock()
+        .map_err(|e| format!("获取书签锁失败: {e}"))?;
+    let path = bookmark_file_path(&app, &file_path)?;
+    let mut bookmarks = load_bookmark_file(&path, &file_path)?;
+
+    let bookmark = Bookmark {
+        id: bookmarks
+            .bookmarks
+            .iter()
+            .map(|b| b.id + 1)
+            .max()
+            .unwrap_or(1),
+        name,
+        viewport,
+        created_ms: get_time() as u64,
+    };
+    bookmarks.bookmarks.push(bookmark.clone());
+    save_bookmark_file(&path, &bookmarks)?;
+
+    println!(
+        "[RUST] 添加书签: {file_path} #{} {}",
+        bookmark.id, bookmark.name
+    );
+    Ok(bookmark)
+}
+
+/// 列出图片的所有书签
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<Vec<Bookmark>, String>` - 按创建顺序排列的书签
+#[tauri::command]
+pub fn list_bookmarks(app: AppHandle, file_path: String) -> Result<Vec<Bookmark>, String> {
+    let _guard = BOOKMARKS_LOCK
+        .lock()
+        .map_err(|e| format!("获取书签锁失败: {e}"))?;
+    let path = bookmark_file_path(&app, &file_path)?;
+    Ok(load_bookmark_file(&path, &file_path)?.bookmarks)
+}
+
+/// 删除书签
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `bookmark_id` - 书签 ID
+/// # Returns
+/// * `Result<Vec<Bookmark>, String>` - 删除后剩余的书签
+#[tauri::command]
+pub fn remove_bookmark(
+    app: AppHandle,
+    file_path: String,
+    bookmark_id: u32,
+) -> Result<Vec<Bookmark>, String> {
+    let _guard = BOOKMARKS_LOCK
+        .lock()
+        .map_err(|e| format!("获取书签锁失败: {e}"))?;
+    let path = bookmark_file_path(&app, &file_path)?;
+    let mut bookmarks = load_bookmark_file(&path, &file_path)?;
+
+    let before = bookmarks.bookmarks.len();
+    bookmarks.bookmarks.retain(|b| b.id != bookmark_id);
+    if bookmarks.bookmarks.len() == before {
+        return Err(format!("书签不存在: #{bookmark_id}"));
+    }
+    save_bookmark_file(&path, &bookmarks)?;
+
+    println!("[RUST] 删除书签: {file_path} #{bookmark_id}");
+    Ok(bookmarks.bookmarks)
+}
+
+/// 跳转到书签
+/// 加载（必要时预处理）图片 然后发出导航事件 前端按书签的视口适配显示
+/// 图片会记录为调用窗口当前打开的图片
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `bookmark_id` - 书签 ID
+/// # Returns
+/// * `Result<NavigationTarget, String>` - 导航目标
+#[tauri::command]
+pub fn goto_bookmark(
+    app: AppHandle,
+    window: Window,
+    file_path: String,
+    bookmark_id: u32,
+) -> Result<NavigationTarget, String> {
+    let bookmark = list_bookmarks(app.clone(), file_path.clone())?
+        .into_iter()
+        .find(|b| b.id == bookmark_id)
+        .ok_or_else(|| format!("书签不存在: #{bookmark_id}"))?;
+    navigate_to_viewport(&app, &window, &file_path, bookmark.viewport)
+}
+
+// 打开图片并导航到指定视口 书签和导览共用
+pub(super) fn navigate_to_viewport(
+    app: &AppHandle,
+    window: &Window,
+    file_path: &str,
+    viewport: Viewport,
+) -> Result<NavigationTarget, String> {
+    validate_image_path(file_path)?;
+    let metadata = open_user_image(file_path)?;
+
+    let target = NavigationTarget {
+        file_path: file_path.to_string(),
+        x: viewport.x + viewport.width / 2.0,
+        y: viewport.y + viewport.height / 2.0,
+        zoom: 1.0,
+        viewport: Some(viewport),
+        metadata,
+    };
+
+    set_window_image(window.label(), file_path);
+    update_window_state(window.label(), |state| state.viewport = Some(viewport));
+    app.emit_to(window.label(), NAVIGATION_EVENT, target.clone())
+        .map_err(|e| format!("发送导航事件失败: {e}"))?;
+
+    println!(
+        "[RUST] 导航到视口: {file_path} @ ({}, {}) {}x{}",
+        viewport.x, viewport.y, viewport.width, viewport.height
+    );
+    Ok(target)
+}
@@ -0,0 +1,82 @@
+use std::sync::{Arc, Mutex, OnceLock};
+
+use super::config::{CHUNK_SIZE_X, CHUNK_SIZE_Y};
+use super::memory::{register_memory_consumer, MemoryConsumer};
+
+// 缓冲池最多保留的字节数 大约 4 个满尺寸 chunk
+const MAX_POOLED_BYTES: u64 = 4 * CHUNK_SIZE_X as u64 * CHUNK_SIZE_Y as u64 * 4;
+
+/// 像素缓冲池
+/// 预处理时每个 chunk 都要分配一块 67MB 的缓冲区 用完归还到池中复用
+/// 避免频繁的大块内存分配和释放
+pub struct BufferPool {
+    buffers: Mutex<Vec<Vec<u8>>>,
+}
+
+impl BufferPool {
+    /// 取出一块容量至少为 capacity 的空缓冲区
+    pub fn acquire(&self, capacity: usize) -> Vec<u8> {
+        if let Ok(mut buffers) = self.buffers.lock() {
+            if let Some(index) = buffers.iter().position(|b| b.capacity() >= capacity) {
+                let mut buffer = buffers.swap_remove(index);
+                buffer.clear();
+                return buffer;
+            }
+        }
+        Vec::with_capacity(capacity)
+    }
+
+    /// 归还缓冲区 超出上限时直接丢弃
+    pub fn release(&self, buffer: Vec<u8>) {
+        if let Ok(mut buffers) = self.buffers.lock() {
+            let pooled: u64 = buffers.iter().map(|b| b.capacity() as u64).sum();
+            if pooled + buffer.capacity() as u64 <= MAX_POOLED_BYTES {
+                buffers.push(buffer);
+            }
+        }
+    }
+}
+
+impl MemoryConsumer for BufferPool {
+    fn name(&self) -> &str {
+        "buffer_pool"
+    }
+
+    fn bytes_held(&self) -> u64 {
+        self.buffers
+            .lock()
+            .map(|buffers| buffers.iter().map(|b| b.capacity() as u64).sum())
+            .unwrap_or(0)
+    }
+
+    fn trim(&self, target_bytes: u64) -> u64 {
+        let Ok(mut buffers) = self.buffers.lock() else {
+            return 0;
+        };
+        let mut held: u64 = buffers.iter().map(|b| b.capacity() as u64).sum();
+        let mut freed = 0;
+        while held > target_bytes {
+            match buffers.pop() {
+                Some(buffer) => {
+                    held -= buffer.capacity() as u64;
+                    freed += buffer.capacity() as u64;
+                }
+                None => break,
+            }
+        }
+        freed
+    }
+}
+
+static BUFFER_POOL: OnceLock<Arc<BufferPool>> = OnceLock::new();
+
+/// 获取全局像素缓冲池 首次调用时登记到内存统计中
+pub fn get_buffer_pool() -> &'static BufferPool {
+    BUFFER_POOL.get_or_init(|| {
+        let pool = Arc::new(BufferPool {
+            buffers: Mutex::new(Vec::new()),
+        });
+        register_memory_consumer(pool.clone());
+        pool
+    })
+}
@@ -1,8 +1,154 @@
 use serde_json;
+use std::collections::HashMap;
+use std::env;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
 
-use super::config::CHUNK_CACHE_DIR;
+use super::access_stats::{clear_access_stats, reset_access_stats};
+use super::chunk_pages::{clear_chunk_pages, forget_chunk_pages};
+use super::chunk_processing::{chunk_memory_cache, CHUNK_TMP_SUFFIX};
+use super::compact_metadata::decode_metadata;
+use super::config::{cache_root, SUPPORTED_CHUNK_FORMAT_VERSIONS};
+use super::errors::{localized_error, ErrorCode};
+use super::magnifier::{clear_magnifier_cache, forget_magnifier_cache};
+use super::portable_cache::{is_portable_cache, portable_cache_dir};
+use super::preprocess_checkpoint::PREPROCESS_PROGRESS_FILE;
+use super::rechunk::RECHUNK_TMP_DIR;
+use super::types::ImageMetadata;
+
+// 每个 chunk 像素哈希的记录文件
+const CHUNK_HASHES_FILE: &str = "pixel_hashes.json";
+
+// 设置为 1 / true 时缓存目录以只读模式使用
+// 用于缓存目录挂载自服务器、由服务器预处理好切片的场景 所有写缓存的操作都会被拒绝
+pub const CACHE_READONLY_ENV: &str = "IMAGES_GL_CACHE_READONLY";
+
+static CACHE_READ_ONLY: AtomicBool = AtomicBool::new(false);
+static CACHE_READ_ONLY_INIT: OnceLock<()> = OnceLock::new();
+
+/// 缓存是否处于只读模式 首次调用时读取环境变量作为默认值
+pub fn is_cache_read_only() -> bool {
+    CACHE_READ_ONLY_INIT.get_or_init(|| {
+        let enabled = env::var(CACHE_READONLY_ENV)
+            .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "on"))
+            .unwrap_or(false);
+        CACHE_READ_ONLY.store(enabled, Ordering::Relaxed);
+    });
+    CACHE_READ_ONLY.load(Ordering::Relaxed)
+}
+
+/// 写缓存之前调用 只读模式下返回 CACHE_READ_ONLY 错误
+/// # Arguments
+/// * `operation` - 被拒绝的操作 显示在错误信息中
+pub fn ensure_cache_writable(operation: &str) -> Result<(), String> {
+    if is_cache_read_only() {
+        return Err(localized_error(
+            ErrorCode::CacheReadOnly,
+            &[("operation", &operation)],
+        ));
+    }
+    Ok(())
+}
+
+/// 开启或关闭缓存只读模式
+#[tauri::command]
+pub fn set_cache_read_only(enabled: bool) -> Result<bool, String> {
+    // 先触发一次初始化 避免之后读取环境变量覆盖这里的设置
+    is_cache_read_only();
+    CACHE_READ_ONLY.store(enabled, Ordering::Relaxed);
+    println!(
+        "[RUST] 缓存只读模式已{}",
+        if enabled { "开启" } else { "关闭" }
+    );
+    Ok(enabled)
+}
+
+/// 获取缓存是否处于只读模式
+#[tauri::command]
+pub fn get_cache_read_only() -> Result<bool, String> {
+    Ok(is_cache_read_only())
+}
+
+/// 获取特定文件的缓存目录
+/// 每个图片对应 chunk_cache 下的一个子目录 目录名为文件路径的哈希
+/// 这样多个图片（多个窗口）的缓存可以同时存在 互不覆盖
+/// 图片开启了便携缓存时为源文件旁边的 .igl-cache 目录
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `PathBuf` - 缓存目录
+pub fn image_cache_dir(file_path: &str) -> PathBuf {
+    if is_portable_cache(file_path) {
+        return portable_cache_dir(file_path);
+    }
+    app_data_cache_dir(file_path)
+}
+
+/// 图片在应用数据目录（chunk_cache）下的缓存目录 不考虑便携缓存
+pub fn app_data_cache_dir(file_path: &str) -> PathBuf {
+    cache_root().join(format!("{:016x}", fnv1a_hash(file_path.as_bytes())))
+}
+
+// 缓存记录的源文件路径是否与请求的一致 防止哈希冲突
+// 便携缓存不会冲突 而且源文件所在的盘符、挂载点可能已经变化 不比较路径
+fn source_path_matches(source_info: &serde_json::Value, file_path: &str) -> bool {
+    is_portable_cache(file_path)
+        || source_info.get("file_path").and_then(|v| v.as_str()) == Some(file_path)
+}
+
+// FNV-1a 64 位哈希 结果在不同平台和 Rust 版本之间保持稳定
+// 标准库的 DefaultHasher 不保证这一点 不能用来生成持久化的目录名
+pub(super) fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// 删除预处理的产物（chunk、临时文件、断点、哈希、概览级别和元数据）
+/// 预处理中途停止后删除不完整的缓存 重新预处理之前删除旧的缓存
+/// 标注、显示配置、区域锁、导航历史等用户数据保留在缓存目录中
+/// 缓存目录属于另一张路径哈希相同的图片时不删除
+pub fn discard_preprocess_output(file_path: &str) -> Result<(), String> {
+    let cache_dir = image_cache_dir(file_path);
+    if let Some(source_info) = load_source_info(&cache_dir) {
+        if !source_path_matches(&source_info, file_path) {
+            println!("[RUST] 缓存目录属于其他图片，不删除: {file_path}");
+            return Ok(());
+        }
+    }
+    if let Ok(entries) = fs::read_dir(&cache_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            if path.is_dir() {
+                if name.starts_with("proxy_") || name == RECHUNK_TMP_DIR {
+                    fs::remove_dir_all(&path).map_err(|e| format!("删除概览级别失败: {e}"))?;
+                }
+            } else if is_preprocess_output(&name) {
+                fs::remove_file(&path).map_err(|e| format!("删除缓存文件失败: {e}"))?;
+            }
+        }
+    }
+    chunk_memory_cache().invalidate_image(file_path);
+    forget_chunk_pages(file_path);
+    forget_magnifier_cache(file_path);
+    println!("[RUST] 已删除预处理的缓存: {file_path}");
+    Ok(())
+}
+
+// 缓存目录中由预处理写入的文件
+fn is_preprocess_output(name: &str) -> bool {
+    (name.starts_with("chunk_") && name.ends_with(".bin"))
+        || name.ends_with(CHUNK_TMP_SUFFIX)
+        || name == PREPROCESS_PROGRESS_FILE
+        || name == CHUNK_HASHES_FILE
+        || name == "metadata.json"
+}
 
 /// 检查特定文件路径的 chunk 缓存是否存在
 /// # Arguments
@@ -10,12 +156,11 @@ use super::config::CHUNK_CACHE_DIR;
 /// # Returns
 /// * `bool` - 是否存在缓存
 pub fn check_file_cache_exists(file_path: &str) -> bool {
-    let cache_dir = Path::new(CHUNK_CACHE_DIR);
+    let cache_dir = image_cache_dir(file_path);
     if !cache_dir.exists() {
         return false;
     }
 
-    // TODO 这个地方 源文件信息文件是统一的一个 当已经被缓存过的文件多了之后 这个文件会变得很大 需要优化 最好是每个图片对应的source_info.json都不一样
     // 检查源文件信息文件是否存在
     let source_info_file = cache_dir.join("source_info.json");
     if !source_info_file.exists() {
@@ -33,9 +178,20 @@ pub fn check_file_cache_exists(file_path: &str) -> bool {
         Err(_) => return false,
     };
 
-    // 检查文件路径是否匹配
-    let cached_path = source_info.get("file_path").and_then(|v| v.as_str());
-    if cached_path != Some(file_path) {
+    // 检查文件路径是否匹配 防止哈希冲突
+    if !source_path_matches(&source_info, file_path) {
+        return false;
+    }
+
+    // 检查 chunk 格式版本 没有记录的旧缓存是版本 1
+    let format_version = source_info
+        .get("chunk_format_version")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(1);
+    if !SUPPORTED_CHUNK_FORMAT_VERSIONS
+        .iter()
+        .any(|&v| u64::from(v) == format_version)
+    {
         return false;
     }
 
@@ -46,7 +202,7 @@ pub fn check_file_cache_exists(file_path: &str) -> bool {
     }
 
     // 检查是否有 chunk 文件
-    if let Ok(entries) = fs::read_dir(cache_dir) {
+    if let Ok(entries) = fs::read_dir(&cache_dir) {
         let chunk_files: Vec<_> = entries
             .filter_map(|entry| entry.ok())
             .filter(|entry| entry.file_name().to_string_lossy().starts_with("chunk_"))
@@ -58,12 +214,78 @@ pub fn check_file_cache_exists(file_path: &str) -> bool {
     false
 }
 
+/// 从缓存加载特定文件的元数据
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<ImageMetadata, String>` - 图片元数据或错误信息
+pub fn load_cached_metadata(file_path: &str) -> Result<ImageMetadata, String> {
+    if !check_file_cache_exists(file_path) {
+        return Err(localized_error(ErrorCode::CacheMissing, &[]));
+    }
+
+    let metadata_filepath = image_cache_dir(file_path).join("metadata.json");
+    let metadata_content =
+        fs::read_to_string(metadata_filepath).map_err(|e| format!("读取缓存元数据失败: {e}"))?;
+    decode_metadata(&metadata_content).map_err(|e| format!("解析缓存元数据失败: {e}"))
+}
+
+/// 读取缓存目录中记录的每个 chunk 的像素哈希
+/// 键为 "chunk_x_chunk_y" 文件不存在时返回空表
+pub fn load_chunk_hashes(cache_dir: &Path) -> HashMap<String, String> {
+    fs::read_to_string(cache_dir.join(CHUNK_HASHES_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// 保存每个 chunk 的像素哈希
+pub fn save_chunk_hashes(cache_dir: &Path, hashes: &HashMap<String, String>) -> Result<(), String> {
+    ensure_cache_writable("保存 chunk 哈希")?;
+    let json = serde_json::to_string(hashes).map_err(|e| format!("序列化 chunk 哈希失败: {e}"))?;
+    fs::write(cache_dir.join(CHUNK_HASHES_FILE), json)
+        .map_err(|e| format!("保存 chunk 哈希失败: {e}"))
+}
+
+/// 读取缓存目录中的源文件信息 source_info.json
+/// # Arguments
+/// * `cache_dir` - 该图片的缓存目录
+/// # Returns
+/// * `Option<serde_json::Value>` - 不存在或无法解析时为 None
+pub fn load_source_info(cache_dir: &Path) -> Option<serde_json::Value> {
+    let content = fs::read_to_string(cache_dir.join("source_info.json")).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// 缓存目录占用的字节数
+pub fn cache_dir_size(cache_dir: &Path) -> u64 {
+    fs::read_dir(cache_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok()?.metadata().ok())
+                .filter(|metadata| metadata.is_file())
+                .map(|metadata| metadata.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// chunk 哈希表的键
+pub fn chunk_hash_key(chunk_x: u32, chunk_y: u32) -> String {
+    format!("{chunk_x}_{chunk_y}")
+}
+
 /// 清理 chunk 缓存
 #[tauri::command]
 pub fn clear_chunk_cache() -> Result<String, String> {
-    let cache_dir = Path::new(CHUNK_CACHE_DIR);
+    ensure_cache_writable("清理缓存")?;
+    let cache_dir = cache_root();
     if cache_dir.exists() {
         fs::remove_dir_all(cache_dir).map_err(|e| format!("清理缓存目录失败: {e}"))?;
+        clear_access_stats();
+        chunk_memory_cache().clear();
+        clear_magnifier_cache();
+        clear_chunk_pages();
         println!("[RUST] Chunk 缓存已清理");
         Ok("Chunk 缓存已清理".to_string())
     } else {
@@ -74,7 +296,8 @@ pub fn clear_chunk_cache() -> Result<String, String> {
 /// 清理特定文件的 chunk 缓存
 #[tauri::command]
 pub fn clear_file_cache(file_path: String) -> Result<String, String> {
-    let cache_dir = Path::new(CHUNK_CACHE_DIR);
+    ensure_cache_writable("清理缓存")?;
+    let cache_dir = image_cache_dir(&file_path);
     if !cache_dir.exists() {
         return Ok("缓存目录不存在".to_string());
     }
@@ -93,13 +316,21 @@ pub fn clear_file_cache(file_path: String) -> Result<String, String> {
         .map_err(|e| format!("解析源文件信息失败: {e}"))?;
 
     // 检查文件路径是否匹配
-    let cached_path = source_info.get("file_path").and_then(|v| v.as_str());
-    if cached_path != Some(&file_path) {
+    if !source_path_matches(&source_info, &file_path) {
         return Ok("缓存文件与指定文件不匹配".to_string());
     }
 
-    // 清理整个缓存目录
-    fs::remove_dir_all(cache_dir).map_err(|e| format!("清理缓存目录失败: {e}"))?;
+    // 只清理这个文件对应的缓存目录 其他图片的缓存保留
+    // 便携缓存清理后重新创建空目录 之后的预处理仍然写到源文件旁边
+    let portable = is_portable_cache(&file_path);
+    fs::remove_dir_all(&cache_dir).map_err(|e| format!("清理缓存目录失败: {e}"))?;
+    if portable {
+        fs::create_dir_all(&cache_dir).map_err(|e| format!("创建便携缓存目录失败: {e}"))?;
+    }
+    reset_access_stats(&file_path);
+    chunk_memory_cache().invalidate_image(&file_path);
+    forget_chunk_pages(&file_path);
+    forget_magnifier_cache(&file_path);
     println!("[RUST] 文件 {file_path} 的缓存已清理");
     Ok(format!("文件 {file_path} 的缓存已清理"))
 }
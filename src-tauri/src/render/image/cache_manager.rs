@@ -0,0 +1,405 @@
+use std::fs;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use super::access_stats::last_image_access_ms;
+use super::access_stats::{image_retention_score, reset_access_stats};
+use super::annotations::has_annotations;
+use super::cache::{
+    cache_dir_size, check_file_cache_exists, ensure_cache_writable, image_cache_dir,
+    load_cached_metadata, load_source_info,
+};
+use super::chunk_pages::forget_chunk_pages;
+use super::chunk_processing::chunk_memory_cache;
+use super::config::cache_root;
+use super::magnifier::forget_magnifier_cache;
+use super::types::{CacheEvictionReport, CacheInfo, RetentionPolicy, RetentionRule};
+use super::window_state::open_images;
+use crate::utils::time::get_time;
+
+// 缓存管理：限制 chunk 缓存的总大小
+// 超出时按保留分数从低到高整图淘汰 分数来自 chunk 访问统计 经常回看的图片留得更久
+// 任何窗口正在打开的图片、被固定的图片和有标注的图片都不会被淘汰
+// 保留规则按图片路径区分不同类型的图片 例如实验室的切片永久保留 下载目录里的图片 7 天没看就淘汰
+
+// 固定列表 放在缓存根目录下 清理或重建单个图片的缓存后固定状态仍然保留
+const PINNED_FILE: &str = "pinned.json";
+// 保留规则 同样放在缓存根目录下
+const RETENTION_RULES_FILE: &str = "retention_rules.json";
+
+const MS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+// 固定列表的读改写需要串行
+static PINNED_LOCK: Mutex<()> = Mutex::new(());
+
+fn load_pinned() -> Vec<String> {
+    fs::read_to_string(cache_root().join(PINNED_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_pinned(pinned: &[String]) -> Result<(), String> {
+    ensure_cache_writable("修改固定列表")?;
+    fs::create_dir_all(cache_root()).map_err(|e| format!("创建缓存目录失败: {e}"))?;
+    let json = serde_json::to_string(pinned).map_err(|e| format!("序列化固定列表失败: {e}"))?;
+    fs::write(cache_root().join(PINNED_FILE), json).map_err(|e| format!("保存固定列表失败: {e}"))
+}
+
+/// 图片是否被固定在缓存中
+pub fn is_cache_pinned(file_path: &str) -> bool {
+    load_pinned().iter().any(|path| path == file_path)
+}
+
+/// 把图片固定在缓存中 淘汰缓存时永远不会移除
+/// 例如实验室每天都要对照的参考切片
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<CacheInfo, String>` - 固定后的缓存信息
+#[tauri::command]
+pub fn pin_cache(file_path: String) -> Result<CacheInfo, String> {
+    {
+        let _guard = PINNED_LOCK
+            .lock()
+            .map_err(|e| format!("获取固定列表锁失败: {e}"))?;
+        let mut pinned = load_pinned();
+        if !pinned.contains(&file_path) {
+            pinned.push(file_path.clone());
+            save_pinned(&pinned)?;
+        }
+    }
+    println!("[RUST] 固定缓存: {file_path}");
+    get_cache_info(file_path)
+}
+
+/// 取消固定
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<CacheInfo, String>` - 取消固定后的缓存信息
+#[tauri::command]
+pub fn unpin_cache(file_path: String) -> Result<CacheInfo, String> {
+    {
+        let _guard = PINNED_LOCK
+            .lock()
+            .map_err(|e| format!("获取固定列表锁失败: {e}"))?;
+        let mut pinned = load_pinned();
+        let before = pinned.len();
+        pinned.retain(|path| path != &file_path);
+        if pinned.len() != before {
+            save_pinned(&pinned)?;
+        }
+    }
+    println!("[RUST] 取消固定缓存: {file_path}");
+    get_cache_info(file_path)
+}
+
+/// 获取图片的缓存信息
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<CacheInfo, String>` - 缓存信息 没有缓存时 cached 为 false
+#[tauri::command]
+pub fn get_cache_info(file_path: String) -> Result<CacheInfo, String> {
+    let cached = check_file_cache_exists(&file_path);
+    let cache_dir = image_cache_dir(&file_path);
+    let chunk_count = if cached {
+        load_cached_metadata(&file_path)?.chunks.len() as u32
+    } else {
+        0
+    };
+    let content_hash = load_source_info(&cache_dir).and_then(|info| {
+        info.get("content_hash")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+    });
+
+    Ok(CacheInfo {
+        pinned: is_cache_pinned(&file_path),
+        size_bytes: if cached {
+            cache_dir_size(&cache_dir)
+        } else {
+            0
+        },
+        cache_dir: cache_dir.to_string_lossy().to_string(),
+        last_access_ms: if cached {
+            last_image_access_ms(&file_path)
+        } else {
+            None
+        },
+        file_path,
+        cached,
+        chunk_count,
+        content_hash,
+    })
+}
+
+fn load_retention_rules() -> Vec<RetentionRule> {
+    fs::read_to_string(cache_root().join(RETENTION_RULES_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// 获取缓存保留规则
+#[tauri::command]
+pub fn get_retention_rules() -> Result<Vec<RetentionRule>, String> {
+    Ok(load_retention_rules())
+}
+
+/// 设置缓存保留规则 替换所有规则 下一次淘汰时生效
+/// 规则按顺序匹配图片路径 第一条匹配的规则生效 没有匹配的图片按默认方式处理
+/// # Arguments
+/// * `rules` - 保留规则 例如 `D:/lab/**` 永久保留、`**/Downloads/**` 7 天后淘汰
+/// # Returns
+/// * `Result<Vec<RetentionRule>, String>` - 保存后的规则
+#[tauri::command]
+pub fn set_retention_rules(rules: Vec<RetentionRule>) -> Result<Vec<RetentionRule>, String> {
+    for rule in &rules {
+        if rule.pattern.trim().is_empty() {
+            return Err("保留规则的路径通配符不能为空".to_string());
+        }
+        if rule.policy == (RetentionPolicy::ExpireAfter { days: 0 }) {
+            return Err(format!("保留天数必须大于 0: {}", rule.pattern));
+        }
+    }
+    ensure_cache_writable("修改保留规则")?;
+    fs::create_dir_all(cache_root()).map_err(|e| format!("创建缓存目录失败: {e}"))?;
+    let json = serde_json::to_string(&rules).map_err(|e| format!("序列化保留规则失败: {e}"))?;
+    fs::write(cache_root().join(RETENTION_RULES_FILE), json)
+        .map_err(|e| format!("保存保留规则失败: {e}"))?;
+    println!("[RUST] 缓存保留规则已更新: {} 条", rules.len());
+    Ok(rules)
+}
+
+/// 获取图片适用的保留策略 没有匹配的规则时为 Default
+/// # Arguments
+/// * `file_path` - 图片文件路径
+#[tauri::command]
+pub fn get_retention_policy(file_path: String) -> Result<RetentionPolicy, String> {
+    Ok(policy_for(&load_retention_rules(), &file_path))
+}
+
+fn policy_for(rules: &[RetentionRule], file_path: &str) -> RetentionPolicy {
+    rules
+        .iter()
+        .find(|rule| glob_matches(&rule.pattern, file_path))
+        .map(|rule| rule.policy)
+        .unwrap_or(RetentionPolicy::Default)
+}
+
+// 路径通配符匹配 统一使用 / 作为分隔符 Windows 上不区分大小写
+fn glob_matches(pattern: &str, path: &str) -> bool {
+    let normalize = |s: &str| {
+        let s = s.replace('\\', "/");
+        if cfg!(windows) {
+            s.to_lowercase()
+        } else {
+            s
+        }
+    };
+    let pattern: Vec<char> = normalize(pattern).chars().collect();
+    let path: Vec<char> = normalize(path).chars().collect();
+    glob_match_from(&pattern, &path)
+}
+
+fn glob_match_from(pattern: &[char], path: &[char]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        ['*', '*', rest @ ..] => {
+            // "**/" 也可以匹配零层目录
+            let rest_without_slash = rest.strip_prefix(&['/']).unwrap_or(rest);
+            glob_match_from(rest_without_slash, path)
+                || (0..=path.len()).any(|i| glob_match_from(rest, &path[i..]))
+        }
+        ['*', rest @ ..] => (0..=path.len())
+            .take_while(|&i| i == 0 || path[i - 1] != '/')
+            .any(|i| glob_match_from(rest, &path[i..])),
+        ['?', rest @ ..] => {
+            matches!(path.first(), Some(c) if *c != '/') && glob_match_from(rest, &path[1..])
+        }
+        [c, rest @ ..] => path.first() == Some(c) && glob_match_from(rest, &path[1..]),
+    }
+}
+
+struct CacheEntry {
+    file_path: Option<String>,
+    cache_dir: std::path::PathBuf,
+    size: u64,
+    score: f64,
+    modified: SystemTime,
+}
+
+// 缓存根目录下所有图片的缓存
+fn list_caches() -> Vec<CacheEntry> {
+    let Ok(entries) = fs::read_dir(cache_root()) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| {
+            let cache_dir = entry.path();
+            let file_path = load_source_info(&cache_dir).and_then(|info| {
+                info.get("file_path")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+            });
+            // 没有源文件信息的目录是中断留下的残缺缓存 最先清理
+            let score = file_path
+                .as_deref()
+                .map(image_retention_score)
+                .unwrap_or(-1.0);
+            CacheEntry {
+                size: cache_dir_size(&cache_dir),
+                modified: entry
+                    .metadata()
+                    .and_then(|m| m.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH),
+                file_path,
+                cache_dir,
+                score,
+            }
+        })
+        .collect()
+}
+
+// 是否可以淘汰 任何窗口正在打开的图片、被固定的图片和有标注的图片都不能淘汰
+fn is_evictable(cache: &CacheEntry, open: &[String], pinned: &[String]) -> bool {
+    if let Some(file_path) = &cache.file_path {
+        if open.contains(file_path) || pinned.contains(file_path) {
+            return false;
+        }
+    }
+    // 标注是用户的工作成果 不随缓存自动淘汰
+    !has_annotations(&cache.cache_dir)
+}
+
+// 删除一张图片的缓存 记录到淘汰结果中
+fn evict_cache(cache: CacheEntry, report: &mut CacheEvictionReport) -> bool {
+    if let Err(e) = fs::remove_dir_all(&cache.cache_dir) {
+        println!("[RUST] 淘汰缓存失败: {} ({e})", cache.cache_dir.display());
+        return false;
+    }
+    report.freed_bytes += cache.size;
+    let name = match cache.file_path {
+        Some(file_path) => {
+            reset_access_stats(&file_path);
+            chunk_memory_cache().invalidate_image(&file_path);
+            forget_magnifier_cache(&file_path);
+            forget_chunk_pages(&file_path);
+            file_path
+        }
+        None => cache.cache_dir.to_string_lossy().to_string(),
+    };
+    println!(
+        "[RUST] 淘汰缓存: {name} ({} MB, 分数 {:.2})",
+        cache.size / 1024 / 1024,
+        cache.score
+    );
+    report.evicted.push(name);
+    true
+}
+
+/// 执行保留规则 淘汰超过保留天数没有访问的图片
+/// 没有访问记录时按缓存目录的修改时间计算
+/// # Returns
+/// * `Result<CacheEvictionReport, String>` - 淘汰结果
+#[tauri::command]
+pub fn apply_retention_rules() -> Result<CacheEvictionReport, String> {
+    ensure_cache_writable("淘汰缓存")?;
+    let rules = load_retention_rules();
+    let mut report = CacheEvictionReport::default();
+    if rules.is_empty() {
+        return Ok(report);
+    }
+
+    let now = get_time() as u64;
+    let open = open_images();
+    let pinned = load_pinned();
+    for cache in list_caches() {
+        let Some(file_path) = cache.file_path.as_deref() else {
+            report.remaining_bytes += cache.size;
+            continue;
+        };
+        let RetentionPolicy::ExpireAfter { days } = policy_for(&rules, file_path) else {
+            report.remaining_bytes += cache.size;
+            continue;
+        };
+        let last_used = last_image_access_ms(file_path).unwrap_or_else(|| {
+            cache
+                .modified
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0)
+        });
+        let expired = now.saturating_sub(last_used) >= days as u64 * MS_PER_DAY;
+        let size = cache.size;
+        if !(expired && is_evictable(&cache, &open, &pinned) && evict_cache(cache, &mut report)) {
+            report.remaining_bytes += size;
+        }
+    }
+    if !report.evicted.is_empty() {
+        println!(
+            "[RUST] 保留规则: 淘汰 {} 个过期图片 释放 {} MB",
+            report.evicted.len(),
+            report.freed_bytes / 1024 / 1024
+        );
+    }
+    Ok(report)
+}
+
+/// 把 chunk 缓存的总大小限制在 max_bytes 以内 保留规则为永久保留的图片不会被淘汰
+/// # Arguments
+/// * `max_bytes` - 允许的最大字节数
+/// # Returns
+/// * `Result<CacheEvictionReport, String>` - 淘汰结果
+#[tauri::command]
+pub fn enforce_cache_limit(max_bytes: u64) -> Result<CacheEvictionReport, String> {
+    ensure_cache_writable("淘汰缓存")?;
+    let mut caches = list_caches();
+
+    let mut total: u64 = caches.iter().map(|cache| cache.size).sum();
+    let mut report = CacheEvictionReport::default();
+    if total <= max_bytes {
+        report.remaining_bytes = total;
+        return Ok(report);
+    }
+
+    // 分数相同（例如都没有访问记录）时先淘汰较早生成的
+    caches.sort_by(|a, b| {
+        a.score
+            .total_cmp(&b.score)
+            .then_with(|| a.modified.cmp(&b.modified))
+    });
+
+    let open = open_images();
+    let pinned = load_pinned();
+    let rules = load_retention_rules();
+    for cache in caches {
+        if total <= max_bytes {
+            break;
+        }
+        let keep = cache
+            .file_path
+            .as_deref()
+            .is_some_and(|file_path| policy_for(&rules, file_path) == RetentionPolicy::Keep);
+        if keep || !is_evictable(&cache, &open, &pinned) {
+            continue;
+        }
+        let size = cache.size;
+        if evict_cache(cache, &mut report) {
+            total -= size;
+        }
+    }
+
+    report.remaining_bytes = total;
+    println!(
+        "[RUST] 缓存大小限制 {} MB: 淘汰 {} 个图片 释放 {} MB",
+        max_bytes / 1024 / 1024,
+        report.evicted.len(),
+        report.freed_bytes / 1024 / 1024
+    );
+    Ok(report)
+}
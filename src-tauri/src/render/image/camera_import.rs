@@ -0,0 +1,648 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::folder_index::{enqueue_folder_index, is_indexed_image, normalize_folder};
+use super::types::{CameraDevice, CameraFile, CameraImportCompleted, CameraImportProgress};
+use backend::CameraSession;
+
+// 相机导入：通过 USB 上的 PTP 协议（MTP 设备兼容）列出相机或扫描仪中的图片 把选中的文件复制到导入文件夹
+// 复制完成后索引导入文件夹 导入的图片和其他图片一样出现在图库中
+// 需要启用 camera 特性 Linux 上设备可能已经被 gvfs 等程序占用 需要先在文件管理器中卸载
+
+// 导入进度和导入结束事件
+pub const CAMERA_IMPORT_PROGRESS_EVENT: &str = "camera_import://progress";
+pub const CAMERA_IMPORT_COMPLETED_EVENT: &str = "camera_import://completed";
+
+// 没有指定目标文件夹时 导入到系统图片文件夹下的这个子文件夹
+const DEFAULT_IMPORT_FOLDER: &str = "Camera Import";
+
+/// 列出连接的相机和扫描仪（支持 PTP 或 MTP 的 USB 设备）
+#[tauri::command]
+pub fn list_camera_devices() -> Result<Vec<CameraDevice>, String> {
+    backend::list_devices()
+}
+
+/// 列出设备上所有支持的图片文件 按文件名排序
+/// # Arguments
+/// * `device_id` - list_camera_devices 返回的设备标识
+#[tauri::command]
+pub fn list_camera_files(device_id: String) -> Result<Vec<CameraFile>, String> {
+    let mut session = CameraSession::open(&device_id)?;
+    let mut files = session.list_files()?;
+    files.retain(|file| is_indexed_image(Path::new(&file.file_name)));
+    files.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    Ok(files)
+}
+
+/// 把设备上选中的文件导入到文件夹 在后台执行
+/// 每个文件处理完发出 camera_import://progress 事件 结束时发出 camera_import://completed
+/// 目标文件夹中已有同名同大小的文件时跳过 同名但大小不同时在文件名后加序号
+/// # Arguments
+/// * `device_id` - 设备标识
+/// * `handles` - 要导入的文件（list_camera_files 返回的 handle）
+/// * `destination` - 目标文件夹 默认为系统图片文件夹下的 "Camera Import"
+/// # Returns
+/// * `Result<String, String>` - 目标文件夹的绝对路径
+#[tauri::command]
+pub fn import_from_camera(
+    app: AppHandle,
+    device_id: String,
+    handles: Vec<u32>,
+    destination: Option<String>,
+) -> Result<String, String> {
+    if handles.is_empty() {
+        return Err("没有选择要导入的文件".to_string());
+    }
+    let destination = match destination {
+        Some(path) => PathBuf::from(path),
+        None => app
+            .path()
+            .picture_dir()
+            .map_err(|e| format!("获取图片文件夹失败: {e}"))?
+            .join(DEFAULT_IMPORT_FOLDER),
+    };
+    fs::create_dir_all(&destination).map_err(|e| format!("创建导入文件夹失败: {e}"))?;
+    let destination = normalize_folder(&destination.to_string_lossy())?;
+
+    // 在命令中打开设备 设备被占用等错误可以直接返回给前端
+    let session = CameraSession::open(&device_id)?;
+    let folder = destination.clone();
+    thread::Builder::new()
+        .name("camera-import".to_string())
+        .spawn(move || {
+            println!(
+                "[RUST] 相机导入开始: {device_id} -> {folder} ({} 个文件)",
+                handles.len()
+            );
+            // 会话在 run_import 返回时关闭 索引导入文件夹时设备已经释放
+            let mut payload = run_import(&app, session, &device_id, &handles, &folder);
+            if payload.imported > 0 {
+                if let Err(e) = enqueue_folder_index(&app, folder.clone(), false) {
+                    payload.error = Some(e);
+                }
+            }
+            println!(
+                "[RUST] 相机导入完成: 导入 {} 跳过 {} 失败 {}",
+                payload.imported, payload.skipped, payload.failed
+            );
+            if let Err(e) = app.emit(CAMERA_IMPORT_COMPLETED_EVENT, payload) {
+                println!("[RUST] 发送相机导入完成事件失败: {e}");
+            }
+        })
+        .map_err(|e| format!("启动相机导入线程失败: {e}"))?;
+    Ok(destination)
+}
+
+fn run_import(
+    app: &AppHandle,
+    mut session: CameraSession,
+    device_id: &str,
+    handles: &[u32],
+    destination: &str,
+) -> CameraImportCompleted {
+    let mut result = CameraImportCompleted {
+        device_id: device_id.to_string(),
+        destination: destination.to_string(),
+        imported: 0,
+        skipped: 0,
+        failed: 0,
+        error: None,
+    };
+
+    for (index, &handle) in handles.iter().enumerate() {
+        let file_path = match import_file(&mut session, handle, Path::new(destination)) {
+            Ok(Some(file_path)) => {
+                result.imported += 1;
+                Some(file_path)
+            }
+            Ok(None) => {
+                result.skipped += 1;
+                None
+            }
+            Err(e) => {
+                println!("[RUST] 导入相机文件失败: {handle} ({e})");
+                result.failed += 1;
+                None
+            }
+        };
+        let progress = CameraImportProgress {
+            device_id: device_id.to_string(),
+            processed: index as u32 + 1,
+            total: handles.len() as u32,
+            file_path,
+        };
+        if let Err(e) = app.emit(CAMERA_IMPORT_PROGRESS_EVENT, progress) {
+            println!("[RUST] 发送相机导入进度失败: {e}");
+        }
+    }
+    result
+}
+
+// 导入一个文件 已经导入过时返回 None
+fn import_file(
+    session: &mut CameraSession,
+    handle: u32,
+    destination: &Path,
+) -> Result<Option<String>, String> {
+    let info = session.object_info(handle)?;
+    // 文件名来自设备 只取最后一段 避免写到目标文件夹之外
+    let name = Path::new(&info.file_name)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .filter(|name| !name.starts_with('.'))
+        .ok_or_else(|| format!("文件名无效: {}", info.file_name))?;
+    if !is_indexed_image(Path::new(&name)) {
+        return Err(format!("不支持的文件类型: {name}"));
+    }
+    let Some(target) = import_target(destination, &name, info.file_size) else {
+        return Ok(None);
+    };
+
+    // 先写到隐藏的临时文件 完整下载后再改名 文件夹索引不会读到不完整的文件
+    let partial = destination.join(format!(".{name}.part"));
+    let file = File::create(&partial).map_err(|e| format!("创建文件失败: {e}"))?;
+    let mut writer = BufWriter::new(file);
+    let downloaded = session
+        .download(handle, &mut writer)
+        .and_then(|_| writer.flush().map_err(|e| format!("写入文件失败: {e}")));
+    drop(writer);
+    if let Err(e) = downloaded {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    fs::rename(&partial, &target).map_err(|e| format!("保存文件失败: {e}"))?;
+    Ok(Some(target.to_string_lossy().to_string()))
+}
+
+// 选择目标文件路径 已有同名同大小的文件时返回 None
+fn import_target(destination: &Path, name: &str, file_size: u64) -> Option<PathBuf> {
+    let path = Path::new(name);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().unwrap_or_default().to_string_lossy();
+    let mut index = 0u32;
+    loop {
+        let candidate = if index == 0 {
+            destination.join(name)
+        } else {
+            destination.join(format!("{stem}_{index}.{extension}"))
+        };
+        match fs::metadata(&candidate) {
+            Err(_) => return Some(candidate),
+            Ok(metadata) if metadata.len() == file_size => return None,
+            Ok(_) => index += 1,
+        }
+    }
+}
+
+#[cfg(feature = "camera")]
+mod backend {
+    use std::io::Write;
+    use std::time::Duration;
+
+    use rusb::{
+        Device, DeviceDescriptor, DeviceHandle, Direction, GlobalContext, InterfaceDescriptor,
+        TransferType,
+    };
+
+    use super::super::types::{CameraDevice, CameraFile};
+
+    // USB 静态图像类（PTP） MTP 设备常用厂商自定义类
+    const USB_CLASS_IMAGE: u8 = 6;
+    const USB_SUBCLASS_STILL_IMAGE: u8 = 1;
+    const USB_CLASS_VENDOR: u8 = 0xFF;
+
+    // 单次 USB 传输的超时和缓冲区大小
+    const USB_TIMEOUT: Duration = Duration::from_secs(10);
+    const TRANSFER_BUFFER_SIZE: usize = 256 * 1024;
+
+    // PTP 容器：长度(u32) 类型(u16) 代码(u16) 事务 ID(u32) 之后是参数或数据
+    const CONTAINER_HEADER_SIZE: usize = 12;
+    const CONTAINER_COMMAND: u16 = 1;
+    const CONTAINER_DATA: u16 = 2;
+    const CONTAINER_RESPONSE: u16 = 3;
+
+    const OP_OPEN_SESSION: u16 = 0x1002;
+    const OP_CLOSE_SESSION: u16 = 0x1003;
+    const OP_GET_STORAGE_IDS: u16 = 0x1004;
+    const OP_GET_OBJECT_HANDLES: u16 = 0x1007;
+    const OP_GET_OBJECT_INFO: u16 = 0x1008;
+    const OP_GET_OBJECT: u16 = 0x1009;
+    const RESPONSE_OK: u16 = 0x2001;
+    const RESPONSE_SESSION_ALREADY_OPEN: u16 = 0x201E;
+
+    // 文件夹对象的格式代码
+    const FORMAT_ASSOCIATION: u16 = 0x3001;
+    // GetObjectHandles 的父对象参数为 0 时返回存储上的所有对象
+    const ALL_OBJECTS: u32 = 0;
+
+    // PTP 接口的编号和批量传输端点
+    struct PtpInterface {
+        number: u8,
+        setting: u8,
+        endpoint_in: u8,
+        endpoint_out: u8,
+    }
+
+    fn device_id(device: &Device<GlobalContext>) -> String {
+        format!("{:03}-{:03}", device.bus_number(), device.address())
+    }
+
+    fn interface_name(
+        handle: &DeviceHandle<GlobalContext>,
+        descriptor: &InterfaceDescriptor,
+    ) -> Option<String> {
+        let language = *handle.read_languages(USB_TIMEOUT).ok()?.first()?;
+        handle
+            .read_interface_string(language, descriptor, USB_TIMEOUT)
+            .ok()
+    }
+
+    // 查找 PTP 接口 MTP 设备只能通过接口名称识别 需要已经打开的设备
+    fn find_ptp_interface(
+        device: &Device<GlobalContext>,
+        handle: Option<&DeviceHandle<GlobalContext>>,
+    ) -> Option<PtpInterface> {
+        let config = device.active_config_descriptor().ok()?;
+        for interface in config.interfaces() {
+            for descriptor in interface.descriptors() {
+                let is_ptp = descriptor.class_code() == USB_CLASS_IMAGE
+                    && descriptor.sub_class_code() == USB_SUBCLASS_STILL_IMAGE;
+                let is_mtp = descriptor.class_code() == USB_CLASS_VENDOR
+                    && handle
+                        .and_then(|handle| interface_name(handle, &descriptor))
+                        .is_some_and(|name| name.contains("MTP"));
+                if !is_ptp && !is_mtp {
+                    continue;
+                }
+                let mut endpoint_in = None;
+                let mut endpoint_out = None;
+                for endpoint in descriptor.endpoint_descriptors() {
+                    if endpoint.transfer_type() != TransferType::Bulk {
+                        continue;
+                    }
+                    match endpoint.direction() {
+                        Direction::In => endpoint_in = Some(endpoint.address()),
+                        Direction::Out => endpoint_out = Some(endpoint.address()),
+                    }
+                }
+                if let (Some(endpoint_in), Some(endpoint_out)) = (endpoint_in, endpoint_out) {
+                    return Some(PtpInterface {
+                        number: descriptor.interface_number(),
+                        setting: descriptor.setting_number(),
+                        endpoint_in,
+                        endpoint_out,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    pub fn list_devices() -> Result<Vec<CameraDevice>, String> {
+        let devices = rusb::devices().map_err(|e| format!("枚举 USB 设备失败: {e}"))?;
+        let mut cameras = Vec::new();
+        for device in devices.iter() {
+            let Ok(descriptor) = device.device_descriptor() else {
+                continue;
+            };
+            // 没有权限打开时仍然能通过描述符识别 PTP 相机 只是读不到名称
+            let handle = device.open().ok();
+            if find_ptp_interface(&device, handle.as_ref()).is_none() {
+                continue;
+            }
+            let read = |read_string: fn(
+                &DeviceHandle<GlobalContext>,
+                &DeviceDescriptor,
+            ) -> rusb::Result<String>| {
+                handle
+                    .as_ref()
+                    .and_then(|handle| read_string(handle, &descriptor).ok())
+                    .map(|value| value.trim().to_string())
+                    .filter(|value| !value.is_empty())
+            };
+            cameras.push(CameraDevice {
+                device_id: device_id(&device),
+                vendor_id: descriptor.vendor_id(),
+                product_id: descriptor.product_id(),
+                manufacturer: read(DeviceHandle::read_manufacturer_string_ascii),
+                model: read(DeviceHandle::read_product_string_ascii),
+                serial_number: read(DeviceHandle::read_serial_number_string_ascii),
+            });
+        }
+        Ok(cameras)
+    }
+
+    // 按 PTP 的小端格式读取数据集
+    struct PtpReader<'a> {
+        data: &'a [u8],
+        offset: usize,
+    }
+
+    impl PtpReader<'_> {
+        fn bytes(&mut self, count: usize) -> Result<&[u8], String> {
+            let bytes = self
+                .data
+                .get(self.offset..self.offset + count)
+                .ok_or("PTP 数据不完整")?;
+            self.offset += count;
+            Ok(bytes)
+        }
+
+        fn u16(&mut self) -> Result<u16, String> {
+            Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+        }
+
+        fn u32(&mut self) -> Result<u32, String> {
+            Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+        }
+
+        // u32 数组：数量 + 元素
+        fn u32_array(&mut self) -> Result<Vec<u32>, String> {
+            let count = self.u32()?;
+            (0..count).map(|_| self.u32()).collect()
+        }
+
+        // PTP 字符串：字符数（含结尾的 0） + UTF-16LE 字符
+        fn string(&mut self) -> Result<String, String> {
+            let count = self.bytes(1)?[0] as usize;
+            let units: Vec<u16> = self
+                .bytes(count * 2)?
+                .chunks_exact(2)
+                .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                .take_while(|&unit| unit != 0)
+                .collect();
+            Ok(String::from_utf16_lossy(&units))
+        }
+    }
+
+    // "YYYYMMDDThhmmss[.s][Z|+hhmm]" -> "YYYY-MM-DD HH:MM:SS"
+    fn parse_ptp_time(raw: &str) -> Option<String> {
+        let bytes = raw.as_bytes();
+        let valid = bytes.len() >= 15
+            && bytes[8] == b'T'
+            && bytes[..15]
+                .iter()
+                .enumerate()
+                .all(|(i, b)| i == 8 || b.is_ascii_digit());
+        if !valid || raw.starts_with("0000") {
+            return None;
+        }
+        Some(format!(
+            "{}-{}-{} {}:{}:{}",
+            &raw[0..4],
+            &raw[4..6],
+            &raw[6..8],
+            &raw[9..11],
+            &raw[11..13],
+            &raw[13..15]
+        ))
+    }
+
+    // 打开的 PTP 会话 释放时关闭会话并归还接口
+    pub struct CameraSession {
+        handle: DeviceHandle<GlobalContext>,
+        interface: PtpInterface,
+        transaction_id: u32,
+    }
+
+    impl CameraSession {
+        pub fn open(device_id: &str) -> Result<Self, String> {
+            let devices = rusb::devices().map_err(|e| format!("枚举 USB 设备失败: {e}"))?;
+            let device = devices
+                .iter()
+                .find(|device| self::device_id(device) == device_id)
+                .ok_or_else(|| format!("找不到设备: {device_id}"))?;
+            let handle = device
+                .open()
+                .map_err(|e| format!("打开设备失败（可能没有访问权限）: {e}"))?;
+            let interface = find_ptp_interface(&device, Some(&handle))
+                .ok_or_else(|| format!("设备不支持 PTP/MTP: {device_id}"))?;
+
+            // Linux 上需要先卸载内核驱动 不支持时忽略
+            let _ = handle.set_auto_detach_kernel_driver(true);
+            handle
+                .claim_interface(interface.number)
+                .map_err(|e| format!("设备正被其他程序使用: {e}"))?;
+            if interface.setting != 0 {
+                handle
+                    .set_alternate_setting(interface.number, interface.setting)
+                    .map_err(|e| format!("设置设备接口失败: {e}"))?;
+            }
+
+            let mut session = CameraSession {
+                handle,
+                interface,
+                transaction_id: 0,
+            };
+            let response = session.transaction(OP_OPEN_SESSION, &[1], &mut std::io::sink())?;
+            if response != RESPONSE_OK && response != RESPONSE_SESSION_ALREADY_OPEN {
+                return Err(format!("打开 PTP 会话失败: 0x{response:04X}"));
+            }
+            Ok(session)
+        }
+
+        // 执行一次 PTP 事务：发送命令 把数据阶段（如果有）写入 data 返回响应代码
+        fn transaction(
+            &mut self,
+            code: u16,
+            params: &[u32],
+            data: &mut dyn Write,
+        ) -> Result<u16, String> {
+            let transaction_id = self.transaction_id;
+            self.transaction_id += 1;
+
+            let length = CONTAINER_HEADER_SIZE + params.len() * 4;
+            let mut command = Vec::with_capacity(length);
+            command.extend_from_slice(&(length as u32).to_le_bytes());
+            command.extend_from_slice(&CONTAINER_COMMAND.to_le_bytes());
+            command.extend_from_slice(&code.to_le_bytes());
+            command.extend_from_slice(&transaction_id.to_le_bytes());
+            for param in params {
+                command.extend_from_slice(&param.to_le_bytes());
+            }
+            self.handle
+                .write_bulk(self.interface.endpoint_out, &command, USB_TIMEOUT)
+                .map_err(|e| format!("发送 PTP 命令失败: {e}"))?;
+
+            let mut buffer = vec![0u8; TRANSFER_BUFFER_SIZE];
+            loop {
+                let received = self.read(&mut buffer)?;
+                // 数据长度正好是包大小的整数倍时设备会补一个空包
+                if received == 0 {
+                    continue;
+                }
+                if received < CONTAINER_HEADER_SIZE {
+                    return Err("PTP 响应不完整".to_string());
+                }
+                let length = u32::from_le_bytes(buffer[0..4].try_into().unwrap());
+                let kind = u16::from_le_bytes(buffer[4..6].try_into().unwrap());
+                let response = u16::from_le_bytes(buffer[6..8].try_into().unwrap());
+                match kind {
+                    CONTAINER_RESPONSE => return Ok(response),
+                    CONTAINER_DATA => self.read_data(&buffer[..received], length, data)?,
+                    _ => return Err(format!("未知的 PTP 容器类型: {kind}")),
+                }
+            }
+        }
+
+        fn read(&self, buffer: &mut [u8]) -> Result<usize, String> {
+            self.handle
+                .read_bulk(self.interface.endpoint_in, buffer, USB_TIMEOUT)
+                .map_err(|e| format!("读取 PTP 数据失败: {e}"))
+        }
+
+        // 读取数据阶段 first 是第一次传输收到的内容（含容器头）
+        // 超过 4GB 的对象长度字段为 0xFFFFFFFF 这时一直读到短包为止
+        fn read_data(&self, first: &[u8], length: u32, data: &mut dyn Write) -> Result<(), String> {
+            let write_error = |e: std::io::Error| format!("写入数据失败: {e}");
+            data.write_all(&first[CONTAINER_HEADER_SIZE..])
+                .map_err(write_error)?;
+            let mut buffer = vec![0u8; TRANSFER_BUFFER_SIZE];
+            if length == u32::MAX {
+                let mut received = first.len();
+                while received == TRANSFER_BUFFER_SIZE {
+                    received = self.read(&mut buffer)?;
+                    data.write_all(&buffer[..received]).map_err(write_error)?;
+                }
+                return Ok(());
+            }
+            let mut remaining = (length as usize).saturating_sub(first.len());
+            while remaining > 0 {
+                let received = self.read(&mut buffer)?;
+                if received == 0 {
+                    return Err("PTP 数据不完整".to_string());
+                }
+                let useful = received.min(remaining);
+                data.write_all(&buffer[..useful]).map_err(write_error)?;
+                remaining -= useful;
+            }
+            Ok(())
+        }
+
+        fn request(&mut self, code: u16, params: &[u32]) -> Result<Vec<u8>, String> {
+            let mut data = Vec::new();
+            let response = self.transaction(code, params, &mut data)?;
+            if response != RESPONSE_OK {
+                return Err(format!("PTP 操作 0x{code:04X} 失败: 0x{response:04X}"));
+            }
+            Ok(data)
+        }
+
+        // 返回对象的格式代码和文件信息
+        fn read_object_info(&mut self, handle: u32) -> Result<(u16, CameraFile), String> {
+            let data = self.request(OP_GET_OBJECT_INFO, &[handle])?;
+            let mut reader = PtpReader {
+                data: &data,
+                offset: 0,
+            };
+            let storage_id = reader.u32()?;
+            let format = reader.u16()?;
+            let _protection = reader.u16()?;
+            let file_size = reader.u32()? as u64;
+            // 缩略图格式、大小和尺寸
+            reader.bytes(2 + 4 + 4 + 4)?;
+            let width = reader.u32()?;
+            let height = reader.u32()?;
+            // 位深、父对象、关联类型、关联描述、序号
+            reader.bytes(4 + 4 + 2 + 4 + 4)?;
+            let file_name = reader.string()?;
+            let capture_time = reader.string().ok().and_then(|raw| parse_ptp_time(&raw));
+            Ok((
+                format,
+                CameraFile {
+                    handle,
+                    storage_id,
+                    file_name,
+                    file_size,
+                    width,
+                    height,
+                    capture_time,
+                },
+            ))
+        }
+
+        pub fn object_info(&mut self, handle: u32) -> Result<CameraFile, String> {
+            self.read_object_info(handle).map(|(_, file)| file)
+        }
+
+        pub fn list_files(&mut self) -> Result<Vec<CameraFile>, String> {
+            let data = self.request(OP_GET_STORAGE_IDS, &[])?;
+            let storages = PtpReader {
+                data: &data,
+                offset: 0,
+            }
+            .u32_array()?;
+            let mut files = Vec::new();
+            // 低 16 位为 0 的存储不存在（例如空的卡槽）
+            for storage_id in storages.into_iter().filter(|id| id & 0xFFFF != 0) {
+                let data = self.request(OP_GET_OBJECT_HANDLES, &[storage_id, 0, ALL_OBJECTS])?;
+                let handles = PtpReader {
+                    data: &data,
+                    offset: 0,
+                }
+                .u32_array()?;
+                for handle in handles {
+                    match self.read_object_info(handle) {
+                        Ok((FORMAT_ASSOCIATION, _)) => {}
+                        Ok((_, file)) => files.push(file),
+                        Err(e) => println!("[RUST] 读取相机文件信息失败: {handle} ({e})"),
+                    }
+                }
+            }
+            Ok(files)
+        }
+
+        pub fn download(&mut self, handle: u32, writer: &mut dyn Write) -> Result<(), String> {
+            let response = self.transaction(OP_GET_OBJECT, &[handle], writer)?;
+            if response != RESPONSE_OK {
+                return Err(format!("下载文件失败: 0x{response:04X}"));
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for CameraSession {
+        fn drop(&mut self) {
+            let _ = self.transaction(OP_CLOSE_SESSION, &[], &mut std::io::sink());
+            let _ = self.handle.release_interface(self.interface.number);
+        }
+    }
+}
+
+#[cfg(not(feature = "camera"))]
+mod backend {
+    use std::io::Write;
+
+    use super::super::types::{CameraDevice, CameraFile};
+
+    const NOT_ENABLED: &str = "未启用相机导入 请使用 --features camera 重新编译";
+
+    pub fn list_devices() -> Result<Vec<CameraDevice>, String> {
+        Err(NOT_ENABLED.to_string())
+    }
+
+    // 没有启用时无法打开会话 这个类型没有值
+    pub enum CameraSession {}
+
+    impl CameraSession {
+        pub fn open(_device_id: &str) -> Result<Self, String> {
+            Err(NOT_ENABLED.to_string())
+        }
+
+        pub fn list_files(&mut self) -> Result<Vec<CameraFile>, String> {
+            match *self {}
+        }
+
+        pub fn object_info(&mut self, _handle: u32) -> Result<CameraFile, String> {
+            match *self {}
+        }
+
+        pub fn download(&mut self, _handle: u32, _writer: &mut dyn Write) -> Result<(), String> {
+            match *self {}
+        }
+    }
+}
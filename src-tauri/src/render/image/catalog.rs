@@ -0,0 +1,320 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use rusqlite::types::ToSql;
+use rusqlite::{params, Connection, OptionalExtension};
+use tauri::AppHandle;
+
+use super::cache::load_cached_metadata;
+use super::exif::read_capture_time;
+use super::types::{CatalogEntry, ImageSearchQuery};
+use super::utils::app_data_subdir;
+use crate::utils::time::get_time;
+
+// 图片目录：处理过的图片的尺寸、格式、EXIF 拍摄时间和用户标签 保存在应用数据目录的 SQLite 数据库中
+// 图片打开或预处理完成时自动加入目录 用户可以给图片打标签 按条件搜索
+// 文件夹索引（folder_index）扫描到的图片也记录在这里 folder_files 表保存扫描状态和缩略图
+
+// 数据库文件 位于应用数据目录的 catalog 子目录下
+const CATALOG_DB: &str = "catalog.sqlite";
+// 其他连接正在写入时的等待时间
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+// search_images 默认返回的条数
+const DEFAULT_SEARCH_LIMIT: u32 = 500;
+// 单个标签的最大长度
+const MAX_TAG_LENGTH: usize = 64;
+
+pub(super) fn open_catalog(app: &AppHandle) -> Result<Connection, String> {
+    let path = app_data_subdir(app, "catalog")?.join(CATALOG_DB);
+    let connection = Connection::open(&path).map_err(|e| format!("打开图片目录失败: {e}"))?;
+    connection
+        .busy_timeout(BUSY_TIMEOUT)
+        .map_err(|e| format!("打开图片目录失败: {e}"))?;
+    init_catalog(&connection)?;
+    Ok(connection)
+}
+
+pub(super) fn init_catalog(connection: &Connection) -> Result<(), String> {
+    connection
+        .execute_batch(
+            "CREATE TABLE IF NOT EXISTS images (
+                file_path TEXT PRIMARY KEY,
+                width INTEGER NOT NULL,
+                height INTEGER NOT NULL,
+                format TEXT,
+                file_size INTEGER NOT NULL,
+                capture_time TEXT,
+                indexed_ms INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS tags (
+                file_path TEXT NOT NULL,
+                tag TEXT NOT NULL COLLATE NOCASE,
+                PRIMARY KEY (file_path, tag)
+            );
+            CREATE TABLE IF NOT EXISTS folder_files (
+                file_path TEXT PRIMARY KEY,
+                folder TEXT NOT NULL,
+                file_size INTEGER NOT NULL,
+                modified_ms INTEGER NOT NULL,
+                thumbnail TEXT,
+                indexed_ms INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS tags_tag ON tags (tag);
+            CREATE INDEX IF NOT EXISTS images_capture_time ON images (capture_time);
+            CREATE INDEX IF NOT EXISTS folder_files_folder ON folder_files (folder);",
+        )
+        .map_err(|e| format!("初始化图片目录失败: {e}"))
+}
+
+/// 把图片加入目录或更新已有的记录 读取尺寸、格式、文件大小和 EXIF 拍摄时间
+/// 已有的标签保留
+pub(super) fn upsert_catalog_image(connection: &Connection, file_path: &str) -> Result<(), String> {
+    let path = Path::new(file_path);
+    let file_size = fs::metadata(path)
+        .map_err(|e| format!("读取文件信息失败: {e}"))?
+        .len();
+    let (width, height) = image_size(file_path)?;
+    let format = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    connection
+        .execute(
+            "INSERT INTO images (file_path, width, height, format, file_size, capture_time, indexed_ms)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT (file_path) DO UPDATE SET
+                width = excluded.width,
+                height = excluded.height,
+                format = excluded.format,
+                file_size = excluded.file_size,
+                capture_time = excluded.capture_time",
+            params![
+                file_path,
+                width,
+                height,
+                format,
+                file_size as i64,
+                read_capture_time(path),
+                get_time() as i64,
+            ],
+        )
+        .map_err(|e| format!("写入图片目录失败: {e}"))?;
+    Ok(())
+}
+
+// 图片尺寸 优先使用缓存的元数据 FITS、PSD 等格式的文件头 image 无法识别
+// 没有缓存时（例如文件夹索引扫描到的图片、只生成了代理副本的图片）再读取文件头
+fn image_size(file_path: &str) -> Result<(u32, u32), String> {
+    if let Ok(metadata) = load_cached_metadata(file_path) {
+        return Ok((metadata.total_width, metadata.total_height));
+    }
+    image::image_dimensions(file_path).map_err(|e| format!("读取图片尺寸失败: {e}"))
+}
+
+/// 图片打开或预处理完成后加入目录 失败只记录日志
+pub fn record_catalog_image(app: &AppHandle, file_path: &str) {
+    let result =
+        open_catalog(app).and_then(|connection| upsert_catalog_image(&connection, file_path));
+    if let Err(e) = result {
+        println!("[RUST] 加入图片目录失败: {file_path} ({e})");
+    }
+}
+
+/// 图片文件被删除后从目录中移除 包括它的标签 失败只记录日志
+pub fn forget_catalog_image(app: &AppHandle, file_path: &str) {
+    let result = open_catalog(app).and_then(|connection| {
+        connection
+            .execute(
+                "DELETE FROM images WHERE file_path = ?1",
+                params![file_path],
+            )
+            .and_then(|_| {
+                connection.execute("DELETE FROM tags WHERE file_path = ?1", params![file_path])
+            })
+            .map_err(|e| format!("更新图片目录失败: {e}"))
+    });
+    if let Err(e) = result {
+        println!("[RUST] 从图片目录移除失败: {file_path} ({e})");
+    }
+}
+
+// 去掉首尾空白和重复的标签（不区分大小写） 保持原来的顺序
+fn normalize_tags(tags: Vec<String>) -> Result<Vec<String>, String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_string();
+        if tag.is_empty() {
+            continue;
+        }
+        if tag.chars().count() > MAX_TAG_LENGTH {
+            return Err(format!("标签不能超过 {MAX_TAG_LENGTH} 个字符: {tag}"));
+        }
+        if !normalized.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+            normalized.push(tag);
+        }
+    }
+    Ok(normalized)
+}
+
+pub(super) fn load_tags(connection: &Connection, file_path: &str) -> Result<Vec<String>, String> {
+    let mut statement = connection
+        .prepare("SELECT tag FROM tags WHERE file_path = ?1 ORDER BY tag")
+        .map_err(|e| format!("查询标签失败: {e}"))?;
+    let tags = statement
+        .query_map(params![file_path], |row| row.get(0))
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<String>>>())
+        .map_err(|e| format!("查询标签失败: {e}"))?;
+    Ok(tags)
+}
+
+/// 替换图片的标签
+pub(super) fn replace_tags(
+    connection: &mut Connection,
+    file_path: &str,
+    tags: Vec<String>,
+) -> Result<Vec<String>, String> {
+    let tags = normalize_tags(tags)?;
+    let known: Option<String> = connection
+        .query_row(
+            "SELECT file_path FROM images WHERE file_path = ?1",
+            params![file_path],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("查询图片目录失败: {e}"))?;
+    if known.is_none() {
+        upsert_catalog_image(connection, file_path)?;
+    }
+
+    let transaction = connection
+        .transaction()
+        .map_err(|e| format!("保存标签失败: {e}"))?;
+    transaction
+        .execute("DELETE FROM tags WHERE file_path = ?1", params![file_path])
+        .map_err(|e| format!("保存标签失败: {e}"))?;
+    for tag in &tags {
+        transaction
+            .execute(
+                "INSERT INTO tags (file_path, tag) VALUES (?1, ?2)",
+                params![file_path, tag],
+            )
+            .map_err(|e| format!("保存标签失败: {e}"))?;
+    }
+    transaction
+        .commit()
+        .map_err(|e| format!("保存标签失败: {e}"))?;
+    load_tags(connection, file_path)
+}
+
+/// 按条件搜索目录
+pub(super) fn query_catalog(
+    connection: &Connection,
+    query: &ImageSearchQuery,
+) -> Result<Vec<CatalogEntry>, String> {
+    let mut conditions: Vec<&str> = Vec::new();
+    let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+    if let Some(text) = query.text.as_ref().filter(|t| !t.trim().is_empty()) {
+        conditions.push("instr(lower(file_path), lower(?)) > 0");
+        values.push(Box::new(text.trim().to_string()));
+    }
+    for tag in normalize_tags(query.tags.clone())? {
+        conditions.push("file_path IN (SELECT file_path FROM tags WHERE tag = ?)");
+        values.push(Box::new(tag));
+    }
+    if let Some(format) = &query.format {
+        conditions.push("format = lower(?)");
+        values.push(Box::new(format.trim_start_matches('.').to_string()));
+    }
+    let ranges = [
+        ("width >= ?", query.min_width),
+        ("width <= ?", query.max_width),
+        ("height >= ?", query.min_height),
+        ("height <= ?", query.max_height),
+    ];
+    for (condition, value) in ranges {
+        if let Some(value) = value {
+            conditions.push(condition);
+            values.push(Box::new(value));
+        }
+    }
+    // 拍摄时间按文本比较 "YYYY-MM-DD" 与 "YYYY-MM-DD HH:MM:SS" 的字典序与时间顺序一致
+    if let Some(after) = &query.captured_after {
+        conditions.push("capture_time >= ?");
+        values.push(Box::new(after.trim().to_string()));
+    }
+    if let Some(before) = &query.captured_before {
+        conditions.push("capture_time < ?");
+        values.push(Box::new(before.trim().to_string()));
+    }
+    values.push(Box::new(query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT)));
+
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    let sql = format!(
+        "SELECT file_path, width, height, format, file_size, capture_time, indexed_ms
+         FROM images {where_clause}
+         ORDER BY COALESCE(capture_time, '') DESC, file_path
+         LIMIT ?"
+    );
+    let mut statement = connection
+        .prepare(&sql)
+        .map_err(|e| format!("搜索图片失败: {e}"))?;
+    let params: Vec<&dyn ToSql> = values.iter().map(|v| v.as_ref()).collect();
+    let mut entries = statement
+        .query_map(params.as_slice(), |row| {
+            Ok(CatalogEntry {
+                file_path: row.get(0)?,
+                width: row.get(1)?,
+                height: row.get(2)?,
+                format: row.get(3)?,
+                file_size: row.get::<_, i64>(4)? as u64,
+                capture_time: row.get(5)?,
+                tags: Vec::new(),
+                indexed_ms: row.get::<_, i64>(6)? as u64,
+            })
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("搜索图片失败: {e}"))?;
+    for entry in &mut entries {
+        entry.tags = load_tags(connection, &entry.file_path)?;
+    }
+    Ok(entries)
+}
+
+/// 设置图片的标签 替换原有的所有标签 图片不在目录中时先加入目录
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `tags` - 标签列表 为空时清除所有标签 重复的标签（不区分大小写）只保留一个
+/// # Returns
+/// * `Result<Vec<String>, String>` - 保存后的标签（按名称排序）
+#[tauri::command]
+pub fn set_tags(
+    app: AppHandle,
+    file_path: String,
+    tags: Vec<String>,
+) -> Result<Vec<String>, String> {
+    let mut connection = open_catalog(&app)?;
+    let tags = replace_tags(&mut connection, &file_path, tags)?;
+    println!("[RUST] 已设置标签: {file_path} {tags:?}");
+    Ok(tags)
+}
+
+/// 获取图片的标签
+#[tauri::command]
+pub fn get_tags(app: AppHandle, file_path: String) -> Result<Vec<String>, String> {
+    load_tags(&open_catalog(&app)?, &file_path)
+}
+
+/// 搜索图片目录 按拍摄时间从新到旧排列 没有拍摄时间的排在最后
+/// # Arguments
+/// * `query` - 搜索条件 所有条件同时满足
+/// # Returns
+/// * `Result<Vec<CatalogEntry>, String>` - 符合条件的图片
+#[tauri::command]
+pub fn search_images(app: AppHandle, query: ImageSearchQuery) -> Result<Vec<CatalogEntry>, String> {
+    query_catalog(&open_catalog(&app)?, &query)
+}
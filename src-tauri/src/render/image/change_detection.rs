@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use rayon::prelude::*;
+
+use super::cache::load_cached_metadata;
+use super::export::compose_region;
+use super::scheduler::run_scheduled;
+use super::types::{ChangedRegion, ImageRegion, TaskKind};
+use crate::utils::time::Stopwatch;
+
+// 变化检测：比较同一场景的两次拍摄（工地、看板、文档） 找出发生变化的区域
+// 以 A 的 chunk 为单位并行比较 把变化的像素归到 16x16 的格子里
+// 再把相邻的格子连成区域 跨越 chunk 边界的变化只报告一次 相距较远的变化分开报告
+
+// 格子的边长 相距小于一个格子的变化会连成同一个区域
+const CHANGE_CELL_SIZE: u32 = 16;
+
+/// 检测两张图片之间的变化区域（两张图片都需要先完成预处理 并且尺寸相同）
+/// 任一通道的差超过阈值的像素视为发生了变化
+/// # Arguments
+/// * `image_a` / `image_b` - 两次拍摄的图片路径 需要已经配准
+/// * `threshold` - 通道差的阈值（0-255） 用来忽略噪声和轻微的曝光差异
+/// # Returns
+/// * `Result<Vec<ChangedRegion>, String>` - 变化区域 按变化像素数从多到少排列
+#[tauri::command]
+pub fn detect_changes(
+    image_a: String,
+    image_b: String,
+    threshold: u8,
+) -> Result<Vec<ChangedRegion>, String> {
+    run_scheduled(TaskKind::Analysis, &image_a, || {
+        find_changes(&image_a, &image_b, threshold)
+    })?
+}
+
+fn find_changes(image_a: &str, image_b: &str, threshold: u8) -> Result<Vec<ChangedRegion>, String> {
+    let stopwatch = Stopwatch::start();
+    let metadata_a = load_cached_metadata(image_a)?;
+    let metadata_b = load_cached_metadata(image_b)?;
+    if (metadata_a.total_width, metadata_a.total_height)
+        != (metadata_b.total_width, metadata_b.total_height)
+    {
+        return Err(format!(
+            "两张图片尺寸不同: {}x{} 和 {}x{}",
+            metadata_a.total_width,
+            metadata_a.total_height,
+            metadata_b.total_width,
+            metadata_b.total_height
+        ));
+    }
+
+    let chunk_cells = metadata_a
+        .chunks
+        .par_iter()
+        .map(|info| {
+            let region = ImageRegion {
+                x: info.x,
+                y: info.y,
+                width: info.width,
+                height: info.height,
+            };
+            let a = compose_region(image_a, &metadata_a, &region)?;
+            let b = compose_region(image_b, &metadata_b, &region)?;
+            Ok(compare_region(&region, &a, &b, threshold))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    // chunk 的边界不一定和格子对齐 同一个格子可能来自两个 chunk
+    let mut cells: HashMap<(u32, u32), CellChange> = HashMap::new();
+    for (key, cell) in chunk_cells.into_iter().flatten() {
+        cells
+            .entry(key)
+            .and_modify(|existing| existing.merge(&cell))
+            .or_insert(cell);
+    }
+
+    let mut regions = connected_regions(cells);
+    regions.sort_by_key(|r| std::cmp::Reverse(r.changed_pixels));
+    println!(
+        "[RUST] 变化检测完成: {} 个 chunk, {} 个变化区域 (耗时: {}ms)",
+        metadata_a.chunks.len(),
+        regions.len(),
+        stopwatch.elapsed_ms()
+    );
+    Ok(regions)
+}
+
+// 一个格子中变化像素的统计 坐标为图片坐标 right 和 bottom 不包含在内
+struct CellChange {
+    left: u32,
+    top: u32,
+    right: u32,
+    bottom: u32,
+    changed_pixels: u64,
+    difference_sum: u64,
+}
+
+impl CellChange {
+    fn merge(&mut self, other: &CellChange) {
+        self.left = self.left.min(other.left);
+        self.top = self.top.min(other.top);
+        self.right = self.right.max(other.right);
+        self.bottom = self.bottom.max(other.bottom);
+        self.changed_pixels += other.changed_pixels;
+        self.difference_sum += other.difference_sum;
+    }
+}
+
+// 比较一个 chunk 的像素 返回有变化的格子
+fn compare_region(
+    region: &ImageRegion,
+    a: &[u8],
+    b: &[u8],
+    threshold: u8,
+) -> Vec<((u32, u32), CellChange)> {
+    let width = region.width as usize;
+    let mut cells: HashMap<(u32, u32), CellChange> = HashMap::new();
+    for (i, (pa, pb)) in a.chunks_exact(4).zip(b.chunks_exact(4)).enumerate() {
+        let difference = pa
+            .iter()
+            .zip(pb)
+            .map(|(x, y)| x.abs_diff(*y))
+            .max()
+            .unwrap_or(0);
+        if difference <= threshold {
+            continue;
+        }
+        let x = region.x + (i % width) as u32;
+        let y = region.y + (i / width) as u32;
+        let pixel = CellChange {
+            left: x,
+            top: y,
+            right: x + 1,
+            bottom: y + 1,
+            changed_pixels: 1,
+            difference_sum: difference as u64,
+        };
+        cells
+            .entry((x / CHANGE_CELL_SIZE, y / CHANGE_CELL_SIZE))
+            .and_modify(|cell| cell.merge(&pixel))
+            .or_insert(pixel);
+    }
+    cells.into_iter().collect()
+}
+
+// 把相邻（包括对角）的格子连成区域
+fn connected_regions(mut cells: HashMap<(u32, u32), CellChange>) -> Vec<ChangedRegion> {
+    let mut regions = Vec::new();
+    while let Some(&start) = cells.keys().next() {
+        let mut total = cells.remove(&start).unwrap();
+        let mut pending = vec![start];
+        while let Some((cx, cy)) = pending.pop() {
+            for ny in cy.saturating_sub(1)..=cy + 1 {
+                for nx in cx.saturating_sub(1)..=cx + 1 {
+                    if let Some(cell) = cells.remove(&(nx, ny)) {
+                        total.merge(&cell);
+                        pending.push((nx, ny));
+                    }
+                }
+            }
+        }
+        regions.push(ChangedRegion {
+            x: total.left,
+            y: total.top,
+            width: total.right - total.left,
+            height: total.bottom - total.top,
+            changed_pixels: total.changed_pixels,
+            score: total.difference_sum as f64 / (total.changed_pixels as f64 * 255.0),
+        });
+    }
+    regions
+}
@@ -0,0 +1,162 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use rayon::prelude::*;
+use tauri::ipc::Response;
+use tauri::AppHandle;
+
+use super::access_stats::record_chunk_access;
+use super::backpressure::ChunkRequestPermit;
+use super::chunk_processing::load_chunk_bytes;
+use super::debug_overlay::is_debug_overlay_enabled_by_env;
+use super::errors::{localized_error, ErrorCode};
+use super::scheduler::run_scheduled;
+use super::types::TaskKind;
+
+// 批量读取 chunk：加载一个视口需要几十个 chunk 每个都单独 invoke 时 IPC 的开销不可忽略
+// 一次请求多个 chunk 在线程池中并行读取 结果按请求顺序拼成一个二进制响应
+// 响应格式（整数均为大端 u32）：
+//   数量 N
+//   N 项：chunk_x chunk_y 状态(0 成功 / 1 失败) 长度 L 数据(L 字节)
+//   成功时数据与 get_image_chunk 相同（宽度 + 高度 + 像素） 失败时是 UTF-8 的错误信息
+// 每个 chunk 单独计入背压名额 超出时只有这一项返回 [BUSY] 错误 其余照常返回
+// 整个响应在内存中拼接 像素数据合计不超过 MAX_BATCH_BYTES 超出的 chunk 返回 [BATCH_FULL] 错误 由前端单独请求
+// 拼接时每个 chunk 复制后立即释放 峰值内存接近响应本身的大小
+
+// 一次最多请求的 chunk 数 更大的视口由前端分批请求
+const MAX_BATCH_CHUNKS: usize = 64;
+// 一次响应最多包含的 chunk 数据字节数
+const MAX_BATCH_BYTES: u64 = 256 * 1024 * 1024;
+
+const STATUS_OK: u32 = 0;
+const STATUS_ERROR: u32 = 1;
+
+/// 批量获取完整分辨率的 chunk 像素数据
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `requests` - 要读取的 chunk 的 (chunk_x, chunk_y) 最多 64 个
+/// * `debug` - 可选 与 get_image_chunk 相同
+/// # Returns
+/// * `Result<Response, String>` - 按请求顺序拼接的二进制响应 单个 chunk 失败不影响其他 chunk
+#[tauri::command]
+pub fn get_image_chunks(
+    app: AppHandle,
+    file_path: String,
+    requests: Vec<(u32, u32)>,
+    debug: Option<bool>,
+) -> Result<Response, String> {
+    if requests.len() > MAX_BATCH_CHUNKS {
+        return Err(format!(
+            "一次最多请求 {MAX_BATCH_CHUNKS} 个 chunk，实际请求了 {} 个",
+            requests.len()
+        ));
+    }
+    let debug = debug.unwrap_or_else(is_debug_overlay_enabled_by_env);
+    println!(
+        "[RUST] 批量获取 {} 个 chunk 从文件 {file_path}",
+        requests.len()
+    );
+
+    let total_bytes = AtomicU64::new(0);
+    let results = run_scheduled(TaskKind::Read, &file_path, || {
+        requests
+            .par_iter()
+            .map(|&(chunk_x, chunk_y)| {
+                record_chunk_access(&file_path, chunk_x, chunk_y);
+                let _permit = ChunkRequestPermit::acquire(&file_path)?;
+                let data =
+                    load_chunk_bytes(chunk_x, chunk_y, file_path.clone(), debug, Some(&app))?;
+                let len = data.len() as u64;
+                if total_bytes.fetch_add(len, Ordering::SeqCst) + len > MAX_BATCH_BYTES {
+                    total_bytes.fetch_sub(len, Ordering::SeqCst);
+                    return Err(localized_error(
+                        ErrorCode::BatchFull,
+                        &[
+                            ("limit", &MAX_BATCH_BYTES),
+                            ("x", &chunk_x),
+                            ("y", &chunk_y),
+                        ],
+                    ));
+                }
+                Ok(data)
+            })
+            .collect::<Vec<Result<Vec<u8>, String>>>()
+    })?;
+
+    Ok(Response::new(encode_batch(&requests, results)))
+}
+
+// 按批量响应的格式拼接读取结果 每项复制后立即释放
+fn encode_batch(requests: &[(u32, u32)], results: Vec<Result<Vec<u8>, String>>) -> Vec<u8> {
+    let payload_len: usize = results
+        .iter()
+        .map(|result| match result {
+            Ok(data) => data.len(),
+            Err(message) => message.len(),
+        })
+        .sum();
+    let mut output = Vec::with_capacity(4 + requests.len() * 16 + payload_len);
+    output.extend_from_slice(&(requests.len() as u32).to_be_bytes());
+    for (&(chunk_x, chunk_y), result) in requests.iter().zip(results) {
+        let (status, data) = match result {
+            Ok(data) => (STATUS_OK, data),
+            Err(message) => (STATUS_ERROR, message.into_bytes()),
+        };
+        output.extend_from_slice(&chunk_x.to_be_bytes());
+        output.extend_from_slice(&chunk_y.to_be_bytes());
+        output.extend_from_slice(&status.to_be_bytes());
+        output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        output.extend_from_slice(&data);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u32(data: &[u8], offset: &mut usize) -> u32 {
+        let value = u32::from_be_bytes(data[*offset..*offset + 4].try_into().unwrap());
+        *offset += 4;
+        value
+    }
+
+    #[test]
+    fn encodes_results_in_request_order() {
+        let requests = [(3, 4), (0, 1), (7, 7)];
+        let results = vec![
+            Ok(vec![1, 2, 3]),
+            Err("[BUSY] 忙".to_string()),
+            Ok(Vec::new()),
+        ];
+        let output = encode_batch(&requests, results);
+
+        let mut offset = 0;
+        assert_eq!(read_u32(&output, &mut offset), 3);
+        let mut items = Vec::new();
+        for _ in 0..3 {
+            let chunk_x = read_u32(&output, &mut offset);
+            let chunk_y = read_u32(&output, &mut offset);
+            let status = read_u32(&output, &mut offset);
+            let len = read_u32(&output, &mut offset) as usize;
+            items.push((
+                chunk_x,
+                chunk_y,
+                status,
+                output[offset..offset + len].to_vec(),
+            ));
+            offset += len;
+        }
+        assert_eq!(offset, output.len());
+        assert_eq!(items[0], (3, 4, STATUS_OK, vec![1, 2, 3]));
+        assert_eq!(
+            items[1],
+            (0, 1, STATUS_ERROR, "[BUSY] 忙".as_bytes().to_vec())
+        );
+        assert_eq!(items[2], (7, 7, STATUS_OK, Vec::new()));
+    }
+
+    #[test]
+    fn encodes_empty_batches() {
+        assert_eq!(encode_batch(&[], Vec::new()), vec![0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,269 @@
+use super::cache::load_cached_metadata;
+use super::display_profile::get_display_profile;
+use super::types::{ChunkPosition, ImageMetadata, ImageRegion, LevelDescriptor};
+
+// 图片坐标与 chunk 网格之间的换算 前端和外部脚本不需要自己实现网格计算
+// 网格来自缓存的元数据（完整分辨率）和 metadata.levels（概览级别） chunk 之间没有重叠
+// displayed 为 true 时坐标是按显示配置旋转后的画面坐标（见 display_profile） 否则是原图坐标
+// 概览级别的 chunk 按缩小倍数换算回完整分辨率的坐标 图片坐标始终以完整分辨率为单位
+
+/// 图片中的点所在的 chunk
+/// # Arguments
+/// * `file_path` - 图片文件路径（需要已经预处理）
+/// * `x` / `y` - 点的坐标（完整分辨率的像素）
+/// * `level` - 分辨率级别 为空时为完整分辨率
+/// * `displayed` - 为 true 时坐标是旋转后的画面坐标
+/// # Returns
+/// * `Result<ChunkPosition, String>` - chunk 索引和点在 chunk 内的位置（该级别的像素）
+#[tauri::command]
+pub fn image_to_chunk(
+    file_path: String,
+    x: f64,
+    y: f64,
+    level: Option<u32>,
+    displayed: Option<bool>,
+) -> Result<ChunkPosition, String> {
+    let metadata = load_cached_metadata(&file_path)?;
+    let grid = level_grid(&metadata, level.unwrap_or(0))?;
+    // 按所在的像素换算 旋转后点仍然落在同一个像素上
+    let (x, y) = (x.floor(), y.floor());
+    let (x, y) = if displayed.unwrap_or(false) {
+        let rotation = display_rotation(&file_path)?;
+        from_display(&metadata, rotation, x, y)
+    } else {
+        (x, y)
+    };
+    let (width, height) = (metadata.total_width as f64, metadata.total_height as f64);
+    if !(0.0..width).contains(&x) || !(0.0..height).contains(&y) {
+        return Err(format!(
+            "坐标 ({x}, {y}) 超出图片范围 {}x{}",
+            metadata.total_width, metadata.total_height
+        ));
+    }
+
+    // 换算到该级别的像素 缩小时最后一个像素可能超出级别尺寸
+    let level_x = ((x / grid.scale as f64) as u32).min(grid.width - 1);
+    let level_y = ((y / grid.scale as f64) as u32).min(grid.height - 1);
+    Ok(ChunkPosition {
+        level: grid.level,
+        chunk_x: level_x / grid.chunk_size_x,
+        chunk_y: level_y / grid.chunk_size_y,
+        offset_x: level_x % grid.chunk_size_x,
+        offset_y: level_y % grid.chunk_size_y,
+    })
+}
+
+/// chunk 覆盖的图片区域
+/// # Arguments
+/// * `file_path` - 图片文件路径（需要已经预处理）
+/// * `chunk_x` / `chunk_y` - chunk 索引
+/// * `level` - 分辨率级别 为空时为完整分辨率
+/// * `displayed` - 为 true 时返回旋转后的画面坐标
+/// # Returns
+/// * `Result<ImageRegion, String>` - 区域（完整分辨率的像素）
+#[tauri::command]
+pub fn chunk_to_image(
+    file_path: String,
+    chunk_x: u32,
+    chunk_y: u32,
+    level: Option<u32>,
+    displayed: Option<bool>,
+) -> Result<ImageRegion, String> {
+    let metadata = load_cached_metadata(&file_path)?;
+    let grid = level_grid(&metadata, level.unwrap_or(0))?;
+    if chunk_x >= grid.col_count || chunk_y >= grid.row_count {
+        return Err(format!(
+            "chunk ({chunk_x}, {chunk_y}) 超出第 {} 级的网格 {}x{}",
+            grid.level, grid.col_count, grid.row_count
+        ));
+    }
+
+    let left = chunk_x * grid.chunk_size_x * grid.scale;
+    let top = chunk_y * grid.chunk_size_y * grid.scale;
+    let right = ((chunk_x + 1) * grid.chunk_size_x * grid.scale).min(metadata.total_width);
+    let bottom = ((chunk_y + 1) * grid.chunk_size_y * grid.scale).min(metadata.total_height);
+    let region = ImageRegion {
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+    };
+    if !displayed.unwrap_or(false) {
+        return Ok(region);
+    }
+    let rotation = display_rotation(&file_path)?;
+    Ok(to_display(&metadata, rotation, region))
+}
+
+// 级别的网格 完整分辨率（第 0 级）来自元数据本身
+fn level_grid(metadata: &ImageMetadata, level: u32) -> Result<LevelDescriptor, String> {
+    if level == 0 {
+        return Ok(LevelDescriptor {
+            level: 0,
+            scale: 1,
+            width: metadata.total_width,
+            height: metadata.total_height,
+            chunk_size_x: metadata.chunk_size_x,
+            chunk_size_y: metadata.chunk_size_y,
+            col_count: metadata.col_count,
+            row_count: metadata.row_count,
+        });
+    }
+    metadata
+        .levels
+        .iter()
+        .find(|descriptor| descriptor.level == level)
+        .cloned()
+        .ok_or_else(|| {
+            format!(
+                "图片没有第 {level} 级 可用的级别为 0 到 {}",
+                metadata.levels.len()
+            )
+        })
+}
+
+fn display_rotation(file_path: &str) -> Result<u32, String> {
+    Ok(get_display_profile(file_path.to_string())?.map_or(0, |profile| profile.rotation))
+}
+
+// 画面中的像素换算为原图中的像素（顺时针旋转的逆变换）
+fn from_display(metadata: &ImageMetadata, rotation: u32, x: f64, y: f64) -> (f64, f64) {
+    let last_x = metadata.total_width as f64 - 1.0;
+    let last_y = metadata.total_height as f64 - 1.0;
+    match rotation {
+        90 => (y, last_y - x),
+        180 => (last_x - x, last_y - y),
+        270 => (last_x - y, x),
+        _ => (x, y),
+    }
+}
+
+// 原图区域换算为顺时针旋转后的画面区域
+fn to_display(metadata: &ImageMetadata, rotation: u32, region: ImageRegion) -> ImageRegion {
+    let (width, height) = (metadata.total_width, metadata.total_height);
+    let right = region.x + region.width;
+    let bottom = region.y + region.height;
+    match rotation {
+        90 => ImageRegion {
+            x: height - bottom,
+            y: region.x,
+            width: region.height,
+            height: region.width,
+        },
+        180 => ImageRegion {
+            x: width - right,
+            y: height - bottom,
+            ..region
+        },
+        270 => ImageRegion {
+            x: region.y,
+            y: width - right,
+            width: region.height,
+            height: region.width,
+        },
+        _ => region,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> ImageMetadata {
+        ImageMetadata {
+            total_width: 700,
+            total_height: 300,
+            chunk_size_x: 256,
+            chunk_size_y: 128,
+            col_count: 3,
+            row_count: 3,
+            chunks: Vec::new(),
+            proxy_scale: None,
+            levels: vec![LevelDescriptor {
+                level: 1,
+                scale: 2,
+                width: 350,
+                height: 150,
+                chunk_size_x: 256,
+                chunk_size_y: 128,
+                col_count: 2,
+                row_count: 2,
+            }],
+            timings: None,
+            chunk_count: None,
+        }
+    }
+
+    #[test]
+    fn level_grids_come_from_the_metadata() {
+        let metadata = metadata();
+        let base = level_grid(&metadata, 0).unwrap();
+        assert_eq!((base.scale, base.col_count, base.row_count), (1, 3, 3));
+        assert_eq!(level_grid(&metadata, 1).unwrap().scale, 2);
+        assert!(level_grid(&metadata, 2).is_err());
+    }
+
+    #[test]
+    fn display_transforms_are_inverse() {
+        let metadata = metadata();
+        let region = ImageRegion {
+            x: 256,
+            y: 128,
+            width: 256,
+            height: 128,
+        };
+        for rotation in [0, 90, 180, 270] {
+            let displayed = to_display(&metadata, rotation, region);
+            if rotation % 180 == 90 {
+                assert_eq!((displayed.width, displayed.height), (128, 256));
+            }
+            // 画面区域的四个角换算回原图后正好是原来区域的四个角
+            let (right, bottom) = (
+                displayed.x + displayed.width - 1,
+                displayed.y + displayed.height - 1,
+            );
+            let mut mapped: Vec<(f64, f64)> = [
+                (displayed.x, displayed.y),
+                (right, displayed.y),
+                (displayed.x, bottom),
+                (right, bottom),
+            ]
+            .iter()
+            .map(|&(x, y)| from_display(&metadata, rotation, x as f64, y as f64))
+            .collect();
+            mapped.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            assert_eq!(
+                mapped,
+                vec![
+                    (256.0, 128.0),
+                    (256.0, 255.0),
+                    (511.0, 128.0),
+                    (511.0, 255.0)
+                ],
+                "rotation {rotation}"
+            );
+        }
+    }
+
+    #[test]
+    fn rotating_the_whole_image_swaps_its_size() {
+        let metadata = metadata();
+        let whole = ImageRegion {
+            x: 0,
+            y: 0,
+            width: 700,
+            height: 300,
+        };
+        let displayed = to_display(&metadata, 90, whole);
+        assert_eq!(
+            (displayed.x, displayed.y, displayed.width, displayed.height),
+            (0, 0, 300, 700)
+        );
+        // 画面左上角是原图左下角
+        assert_eq!(from_display(&metadata, 90, 0.0, 0.0), (0.0, 299.0));
+        assert_eq!(from_display(&metadata, 270, 0.0, 0.0), (699.0, 0.0));
+        assert_eq!(from_display(&metadata, 180, 0.0, 0.0), (699.0, 299.0));
+        let displayed = to_display(&metadata, 180, whole);
+        assert_eq!((displayed.x, displayed.y), (0, 0));
+    }
+}
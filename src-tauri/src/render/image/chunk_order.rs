@@ -0,0 +1,88 @@
+use std::sync::{Mutex, OnceLock};
+
+use super::types::{ChunkInfo, ChunkOrder};
+
+// chunk 的处理顺序：预处理写入 chunk 和预热缓存读取 chunk 时的先后顺序
+// 全景图多为水平平移 行优先时先完成的是完整的水平条带；文档多为竖直滚动 适合列优先
+// Hilbert 曲线让顺序上相邻的 chunk 在图片中也相邻 任意方向平移时先完成的区域都是连续的
+// 瓦片包（PMTiles）同样按 Hilbert 曲线排列瓦片 读取相邻瓦片时在文件中也更接近
+// 只影响处理顺序 metadata.chunks 始终按行优先排列
+
+static CHUNK_ORDER: OnceLock<Mutex<ChunkOrder>> = OnceLock::new();
+
+fn chunk_order_state() -> &'static Mutex<ChunkOrder> {
+    CHUNK_ORDER.get_or_init(|| Mutex::new(ChunkOrder::RowMajor))
+}
+
+/// 当前的 chunk 处理顺序
+pub fn chunk_order() -> ChunkOrder {
+    chunk_order_state()
+        .lock()
+        .map(|order| *order)
+        .unwrap_or(ChunkOrder::RowMajor)
+}
+
+/// 获取 chunk 的处理顺序
+#[tauri::command]
+pub fn get_chunk_order() -> Result<ChunkOrder, String> {
+    Ok(chunk_order())
+}
+
+/// 设置 chunk 的处理顺序 之后的预处理和缓存预热按新的顺序进行
+/// # Arguments
+/// * `order` - 行优先、列优先或 Hilbert 曲线
+/// # Returns
+/// * `Result<ChunkOrder, String>` - 设置后的顺序
+#[tauri::command]
+pub fn set_chunk_order(order: ChunkOrder) -> Result<ChunkOrder, String> {
+    *chunk_order_state()
+        .lock()
+        .map_err(|e| format!("获取 chunk 顺序锁失败: {e}"))? = order;
+    println!("[RUST] chunk 处理顺序已设置为 {order:?}");
+    Ok(order)
+}
+
+/// 把行优先排列的 chunk 按指定顺序重新排列
+/// # Arguments
+/// * `chunks` - 行优先排列的 chunk（metadata.chunks）
+/// * `col_count` - 每行的 chunk 数
+/// * `order` - 处理顺序
+pub fn ordered_chunks(chunks: &[ChunkInfo], col_count: u32, order: ChunkOrder) -> Vec<&ChunkInfo> {
+    let mut ordered: Vec<&ChunkInfo> = chunks.iter().collect();
+    match order {
+        ChunkOrder::RowMajor => {}
+        ChunkOrder::ColumnMajor => ordered.sort_by_key(|chunk| (chunk.chunk_x, chunk.chunk_y)),
+        ChunkOrder::Hilbert => {
+            let row_count = (chunks.len() as u32).div_ceil(col_count.max(1));
+            let side = col_count.max(row_count).max(1).next_power_of_two() as u64;
+            ordered.sort_by_key(|chunk| {
+                hilbert_distance(side, chunk.chunk_x as u64, chunk.chunk_y as u64)
+            });
+        }
+    }
+    ordered
+}
+
+/// 点在 Hilbert 曲线上的序号
+/// # Arguments
+/// * `side` - 曲线覆盖的正方形边长 需要是 2 的幂
+/// * `x` / `y` - 点的坐标 需要小于 side
+pub fn hilbert_distance(side: u64, x: u64, y: u64) -> u64 {
+    let (mut x, mut y) = (x, y);
+    let mut distance = 0u64;
+    let mut s = side / 2;
+    while s > 0 {
+        let rx = u64::from(x & s > 0);
+        let ry = u64::from(y & s > 0);
+        distance += s * s * ((3 * rx) ^ ry);
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - (x & (s - 1));
+                y = s - 1 - (y & (s - 1));
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    distance
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use super::cache::load_cached_metadata;
+use super::types::{ChunkInfo, ChunkInfoPage, ImageMetadata};
+
+// 分页返回 chunk 信息：超大图片或较小的 chunk 尺寸下 chunks 有数万项 全部放进加载结果会让首次 IPC 很慢
+// 加载命令传入 paged 时返回的元数据中 chunks 为空 chunk_count 为总数
+// chunk 的位置可以由 total_width / chunk_size_x / col_count 等网格参数算出 前端通常不需要逐项读取
+// 需要原始的 ChunkInfo 时通过 get_chunk_infos 按行优先的序号范围读取
+// 分页返回时完整的列表留在内存中 应用重启后从缓存的 metadata.json 重新读取
+
+// 每页最多返回的 chunk 数
+const MAX_CHUNK_PAGE: u32 = 4096;
+// 内存中最多保留几张图片的 chunk 列表
+const MAX_PAGED_IMAGES: usize = 8;
+
+static PAGED_CHUNKS: OnceLock<Mutex<HashMap<String, Arc<Vec<ChunkInfo>>>>> = OnceLock::new();
+
+fn paged_chunks() -> &'static Mutex<HashMap<String, Arc<Vec<ChunkInfo>>>> {
+    PAGED_CHUNKS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn remember_chunks(file_path: &str, chunks: Arc<Vec<ChunkInfo>>) {
+    let mut paged = paged_chunks().lock().unwrap_or_else(|e| e.into_inner());
+    if paged.len() >= MAX_PAGED_IMAGES && !paged.contains_key(file_path) {
+        paged.clear();
+    }
+    paged.insert(file_path.to_string(), chunks);
+}
+
+/// 图片的缓存被重写时调用 丢弃内存中旧的 chunk 列表
+pub fn forget_chunk_pages(file_path: &str) {
+    let mut paged = paged_chunks().lock().unwrap_or_else(|e| e.into_inner());
+    paged.remove(file_path);
+}
+
+/// 整个缓存目录被清理时调用 丢弃内存中所有的 chunk 列表
+pub fn clear_chunk_pages() {
+    let mut paged = paged_chunks().lock().unwrap_or_else(|e| e.into_inner());
+    paged.clear();
+}
+
+/// 传入 paged 时把元数据中的 chunk 列表换成总数 否则原样返回
+/// # Arguments
+/// * `paged` - 加载命令的 paged 参数
+/// * `file_path` - 图片文件路径
+/// * `metadata` - 完整的元数据
+pub fn apply_chunk_paging(
+    paged: Option<bool>,
+    file_path: &str,
+    mut metadata: ImageMetadata,
+) -> ImageMetadata {
+    if !paged.unwrap_or(false) {
+        return metadata;
+    }
+    let chunks = std::mem::take(&mut metadata.chunks);
+    metadata.chunk_count = Some(chunks.len() as u32);
+    remember_chunks(file_path, Arc::new(chunks));
+    metadata
+}
+
+/// 按行优先的序号范围读取 chunk 信息
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `start` - 第一个 chunk 的序号 (chunk_y * col_count + chunk_x)
+/// * `count` - 读取的数量 最多 4096
+/// # Returns
+/// * `Result<ChunkInfoPage, String>` - 本页的 chunk 信息和总数 超出范围的部分不返回
+#[tauri::command]
+pub fn get_chunk_infos(file_path: String, start: u32, count: u32) -> Result<ChunkInfoPage, String> {
+    let remembered = paged_chunks()
+        .lock()
+        .map_err(|e| format!("获取 chunk 列表锁失败: {e}"))?
+        .get(&file_path)
+        .cloned();
+    let chunks = match remembered {
+        Some(chunks) => chunks,
+        None => {
+            let chunks = Arc::new(load_cached_metadata(&file_path)?.chunks);
+            remember_chunks(&file_path, chunks.clone());
+            chunks
+        }
+    };
+
+    let total = chunks.len() as u32;
+    let start = start.min(total);
+    let end = start.saturating_add(count.min(MAX_CHUNK_PAGE)).min(total);
+    Ok(ChunkInfoPage {
+        start,
+        total,
+        chunks: chunks[start as usize..end as usize].to_vec(),
+    })
+}
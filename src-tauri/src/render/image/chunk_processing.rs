@@ -1,14 +1,30 @@
-use crate::utils::time::get_time;
+use crate::utils::time::TimingSpan;
 use image::GenericImageView;
-use memmap2::MmapOptions;
+use memmap2::{Mmap, MmapOptions};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
+use std::ops::Deref;
 use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
 use std::thread;
+use std::time::SystemTime;
 use tauri::ipc::Response;
-
-use super::cache::check_file_cache_exists;
-use super::config::CHUNK_CACHE_DIR;
-use super::types::ChunkInfo;
+use tauri::{AppHandle, State};
+
+use super::buffer_pool::get_buffer_pool;
+use super::cache::{check_file_cache_exists, image_cache_dir};
+use super::chunk_repair::{
+    emit_chunk_warning, generate_missing_chunk, regenerate_chunk, validate_chunk_data,
+};
+use super::debug_overlay::draw_chunk_debug_overlay;
+use super::errors::{localized_error, ErrorCode};
+use super::memory::{register_memory_consumer, MemoryConsumer};
+use super::portable_cache::is_portable_cache;
+use super::types::{ChunkCacheStats, ChunkInfo, ChunkWarning};
+use super::write_tracker::chunk_write_state;
+
+// 正在写入的 chunk 临时文件的后缀
+pub const CHUNK_TMP_SUFFIX: &str = ".tmp";
 
 /// 并行处理单个 chunk 的函数
 /// # Arguments
@@ -16,13 +32,13 @@ use super::types::ChunkInfo;
 /// * `chunk_info` - chunk 信息
 /// * `cache_dir` - 缓存目录
 /// # Returns
-/// * `Result<(), String>` - 是否成功
+/// * `Result<String, String>` - 像素数据的哈希（用于后续增量更新）或错误信息
 pub fn process_single_chunk_parallel(
     rgba_img: &image::RgbaImage,
     chunk_info: &ChunkInfo,
     cache_dir: &Path,
-) -> Result<(), String> {
-    let chunk_start = get_time();
+) -> Result<String, String> {
+    let chunk_span = TimingSpan::new("chunk.write");
 
     // 提取指定区域的像素数据
     let pixels = extract_chunk_pixels(
@@ -33,9 +49,7 @@ pub fn process_single_chunk_parallel(
         chunk_info.height,
     );
 
-    // TODO 这里可以维护一个像素内存池
-    // 一来可以避免频繁的内存分配和释放
-    // 二来前端初始访问图片的chunk时, 可以直接从内存中读取并返回, 而不需要从缓存的图片chunk文件中读取
+    // TODO 前端初始访问图片的chunk时, 可以直接从内存中读取并返回, 而不需要从缓存的图片chunk文件中读取
 
     // NOTE
     // 内存映射文件是一种在虚拟内存和文件系统之间建立映射关系的机制。
@@ -49,8 +63,10 @@ pub fn process_single_chunk_parallel(
     // 6. 双向映射, 既可以内存映射到文件, 也可以文件映射到内存
 
     // 保存 chunk 到文件（使用内存映射优化）
+    // 先写入临时文件 完整写入后再改名 读取方只会看到旧的或新的完整 chunk 不会读到写了一半的文件
     let chunk_filename = format!("chunk_{}_{}.bin", chunk_info.chunk_x, chunk_info.chunk_y);
     let chunk_filepath = cache_dir.join(&chunk_filename);
+    let tmp_filepath = cache_dir.join(format!("{chunk_filename}{CHUNK_TMP_SUFFIX}"));
 
     // 计算chunk文件大小：宽度(4字节) + 高度(4字节) + 像素数据
     let chunk_file_size = 8 + pixels.len() as u64;
@@ -61,7 +77,7 @@ pub fn process_single_chunk_parallel(
         .write(true)
         .create(true)
         .truncate(true)
-        .open(&chunk_filepath)
+        .open(&tmp_filepath)
         .map_err(|e| {
             format!(
                 "创建 chunk ({}, {}) 文件失败: {}",
@@ -104,19 +120,36 @@ pub fn process_single_chunk_parallel(
             chunk_info.chunk_x, chunk_info.chunk_y, e
         )
     })?;
+    drop(mmap_guard);
+    drop(chunk_file);
+    fs::rename(&tmp_filepath, &chunk_filepath).map_err(|e| {
+        let _ = fs::remove_file(&tmp_filepath);
+        format!(
+            "替换 chunk ({}, {}) 文件失败: {}",
+            chunk_info.chunk_x, chunk_info.chunk_y, e
+        )
+    })?;
+
+    let pixel_count = pixels.len() / 4;
+    let pixel_hash = hash_pixels(&pixels);
+    // 像素数据已经写入文件 归还缓冲区供下一个 chunk 复用
+    get_buffer_pool().release(pixels);
 
-    let chunk_end = get_time();
     println!(
-        "[RUST] Chunk ({}, {}) 内存映射处理完成: {}ms (耗时: {}ms), 像素: {}, 文件大小: {} 字节",
+        "[RUST] Chunk ({}, {}) 内存映射处理完成 (耗时: {}ms), 像素: {}, 文件大小: {} 字节",
         chunk_info.chunk_x,
         chunk_info.chunk_y,
-        chunk_end,
-        chunk_end - chunk_start,
-        pixels.len() / 4,
+        chunk_span.elapsed_ms(),
+        pixel_count,
         chunk_file_size
     );
 
-    Ok(())
+    Ok(pixel_hash)
+}
+
+/// 计算像素数据的哈希 源图片变化后用于判断哪些 chunk 需要重新生成
+pub fn hash_pixels(pixels: &[u8]) -> String {
+    blake3::hash(pixels).to_hex().to_string()
 }
 
 /// 像素提取函数
@@ -137,8 +170,8 @@ pub fn extract_chunk_pixels(
 ) -> Vec<u8> {
     // 预分配内存，避免动态扩容
     let pixel_count = (width * height) as usize;
-    // rgba 需要4个字节
-    let mut pixels = Vec::with_capacity(pixel_count * 4);
+    // rgba 需要4个字节 从缓冲池中取 避免每个 chunk 都重新分配
+    let mut pixels = get_buffer_pool().acquire(pixel_count * 4);
 
     // 创建图片指定区域的视图 避免重复转换
     let chunk_view = rgba_img.view(x, y, width, height);
@@ -157,44 +190,191 @@ pub fn extract_chunk_pixels(
 }
 
 /// 同步版本的 chunk 获取函数（在 rayon 线程中执行）
+/// `debug` 为 true 时会在像素中绘制 chunk 索引、层级和边框
+/// chunk 文件损坏时尝试从源文件重新生成 并通过 `app` 发出警告事件
 pub fn get_image_chunk_sync(
     chunk_x: u32,
     chunk_y: u32,
     file_path: String,
+    debug: bool,
+    app: Option<&AppHandle>,
 ) -> Result<Response, String> {
-    let start_time = get_time();
+    // 零拷贝返回：直接传递原始数据，避免序列化和反序列化
+    // 数据格式：宽度(4字节) + 高度(4字节) + 像素数据
+    // 前端可以直接解析这个格式，无需额外的JSON序列化开销
+    load_chunk_bytes(chunk_x, chunk_y, file_path, debug, app).map(Response::new)
+}
+
+/// 读取 chunk 文件数据 格式：宽度(4字节) + 高度(4字节) + 像素数据
+/// 参数与 get_image_chunk_sync 相同 供不经过 Tauri IPC 的调用方（例如 RPC）使用
+pub fn load_chunk_bytes(
+    chunk_x: u32,
+    chunk_y: u32,
+    file_path: String,
+    debug: bool,
+    app: Option<&AppHandle>,
+) -> Result<Vec<u8>, String> {
+    let span = TimingSpan::new("chunk.load");
     println!(
-        "[RUST] 开始获取 chunk ({}, {}) 从文件 {}: {}ms (线程: {:?})",
+        "[RUST] 开始获取 chunk ({}, {}) 从文件 {} (线程: {:?})",
         chunk_x,
         chunk_y,
         file_path,
-        start_time,
         thread::current().id()
     );
 
-    // 检查特定文件的缓存是否存在
-    if !check_file_cache_exists(&file_path) {
-        return Err(
-            "Chunk 缓存不存在，请先调用 get_image_metadata_for_file 进行预处理".to_string(),
-        );
+    // 图片正在预处理时只返回已经完整写入的 chunk 其余的返回 [CHUNK_NOT_READY]
+    let write_state = chunk_write_state(&file_path, chunk_x, chunk_y);
+    if write_state == Some(false) {
+        return Err(localized_error(
+            ErrorCode::ChunkNotReady,
+            &[("x", &chunk_x), ("y", &chunk_y)],
+        ));
     }
 
+    // 内存缓存命中时不读磁盘 返回副本 调试叠加层不会影响缓存
+    let memory_cache = chunk_memory_cache();
+    let ticket = memory_cache.ticket(&file_path);
+    if let Some(chunk_data) = memory_cache.get(&file_path, chunk_x, chunk_y, &ticket) {
+        return finish_chunk(chunk_data, chunk_x, chunk_y, debug, span);
+    }
+
+    // 没有在预处理时检查特定文件的缓存是否存在
+    let cached = write_state.unwrap_or_else(|| check_file_cache_exists(&file_path));
+
     // 从缓存文件读取 chunk 数据
     let chunk_filename = format!("chunk_{chunk_x}_{chunk_y}.bin");
-    let chunk_filepath = Path::new(CHUNK_CACHE_DIR).join(&chunk_filename);
-
-    if !chunk_filepath.exists() {
-        return Err(format!("Chunk 文件不存在: {chunk_filepath:?}"));
+    let chunk_filepath = image_cache_dir(&file_path).join(&chunk_filename);
+
+    if !cached || !chunk_filepath.exists() {
+        // 缓存被清理或 chunk 被淘汰 源文件还在时只生成这一个 chunk 不需要重新预处理整张图片
+        return match generate_missing_chunk(&file_path, chunk_x, chunk_y, cached) {
+            Ok(chunk_data) => {
+                let chunk_data = Arc::new(ChunkBytes::Owned(chunk_data));
+                memory_cache.insert(&file_path, chunk_x, chunk_y, &ticket, chunk_data.clone());
+                finish_chunk(chunk_data, chunk_x, chunk_y, debug, span)
+            }
+            Err(e) => {
+                println!("[RUST] 按需生成 chunk ({chunk_x}, {chunk_y}) 失败: {e}");
+                Err(if cached {
+                    localized_error(
+                        ErrorCode::ChunkMissing,
+                        &[("path", &chunk_filepath.display())],
+                    )
+                } else {
+                    localized_error(ErrorCode::CacheMissing, &[])
+                })
+            }
+        };
     }
 
-    // 直接读取文件数据，零拷贝传输
-    let chunk_data = fs::read(&chunk_filepath).map_err(|e| format!("读取 chunk 文件失败: {e}"))?;
+    // 校验和放入内存缓存都不复制数据 返回给前端时最多复制一次
+    let mut chunk_data = map_chunk_file(&file_path, &chunk_filepath)?;
 
     // 验证数据格式：宽度(4字节) + 高度(4字节) + 像素数据
-    if chunk_data.len() < 8 {
-        return Err("Chunk 文件格式错误：数据长度不足".to_string());
+    // 单个 chunk 损坏（例如写入时被中断导致截断）时 从源文件重新生成这一个 chunk
+    if let Err(invalid) = validate_chunk_data(&chunk_data) {
+        // 先释放映射 映射中的文件被替换后仍然指向旧内容
+        drop(chunk_data);
+        match regenerate_chunk(&file_path, chunk_x, chunk_y)
+            .and_then(|_| map_chunk_file(&file_path, &chunk_filepath))
+        {
+            Ok(regenerated) => {
+                emit_chunk_warning(
+                    app,
+                    ChunkWarning {
+                        file_path: file_path.clone(),
+                        chunk_x,
+                        chunk_y,
+                        regenerated: true,
+                        message: invalid,
+                    },
+                );
+                chunk_data = regenerated;
+            }
+            Err(e) => {
+                let message = format!("{invalid}；重新生成失败: {e}");
+                emit_chunk_warning(
+                    app,
+                    ChunkWarning {
+                        file_path: file_path.clone(),
+                        chunk_x,
+                        chunk_y,
+                        regenerated: false,
+                        message: message.clone(),
+                    },
+                );
+                return Err(message);
+            }
+        }
+    }
+
+    let chunk_data = Arc::new(chunk_data);
+    memory_cache.insert(&file_path, chunk_x, chunk_y, &ticket, chunk_data.clone());
+    finish_chunk(chunk_data, chunk_x, chunk_y, debug, span)
+}
+
+/// chunk 数据（宽度 + 高度 + 像素）
+/// 缓存中的 chunk 文件只读映射 不复制到内存 按需生成的 chunk 保存在内存中
+pub enum ChunkBytes {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl Deref for ChunkBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            ChunkBytes::Mapped(mmap) => mmap,
+            ChunkBytes::Owned(data) => data,
+        }
+    }
+}
+
+impl ChunkBytes {
+    /// 让系统提前把映射的内容读入页缓存 内存中的数据不需要
+    pub fn prefetch(&self) {
+        #[cfg(unix)]
+        if let ChunkBytes::Mapped(mmap) = self {
+            let _ = mmap.advise(memmap2::Advice::WillNeed);
+        }
     }
+}
 
+/// 读取 chunk 文件 不校验内容
+/// 本地缓存目录中的文件只读映射 chunk 文件只会整体替换（先写临时文件再改名）或删除 不会原地修改 映射在替换后仍然指向旧内容
+/// Windows 上被映射的文件无法删除或替换 便携缓存可能位于网络共享上 映射的文件被截断或连接断开时访问会触发 SIGBUS
+/// 这两种情况直接读入内存 不保留映射
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `path` - chunk 文件路径
+/// # Returns
+/// * `Result<ChunkBytes, String>` - chunk 数据
+pub fn map_chunk_file(file_path: &str, path: &Path) -> Result<ChunkBytes, String> {
+    if cfg!(windows) || is_portable_cache(file_path) {
+        let data = fs::read(path).map_err(|e| format!("读取 chunk 文件失败: {e}"))?;
+        return Ok(ChunkBytes::Owned(data));
+    }
+    let file = fs::File::open(path).map_err(|e| format!("读取 chunk 文件失败: {e}"))?;
+    let mmap = unsafe { Mmap::map(&file) }.map_err(|e| format!("映射 chunk 文件失败: {e}"))?;
+    Ok(ChunkBytes::Mapped(mmap))
+}
+
+// 取出返回给前端的数据 记录日志 调试模式下绘制 chunk 信息 返回时结束读取的计时区间
+// 映射的数据或同时在内存缓存中的数据复制一份 只在这里使用的内存数据直接返回 不再复制
+fn finish_chunk(
+    chunk_data: Arc<ChunkBytes>,
+    chunk_x: u32,
+    chunk_y: u32,
+    debug: bool,
+    span: TimingSpan,
+) -> Result<Vec<u8>, String> {
+    let mut chunk_data = match Arc::try_unwrap(chunk_data) {
+        Ok(ChunkBytes::Owned(data)) => data,
+        Ok(mapped) => mapped.to_vec(),
+        Err(shared) => shared.to_vec(),
+    };
     // 解析头部信息用于日志
     let width = u32::from_be_bytes([chunk_data[0], chunk_data[1], chunk_data[2], chunk_data[3]]);
     let height = u32::from_be_bytes([chunk_data[4], chunk_data[5], chunk_data[6], chunk_data[7]]);
@@ -208,20 +388,426 @@ pub fn get_image_chunk_sync(
         chunk_x, chunk_y, x, y, width, height, pixels_len, thread::current().id()
     );
 
-    let end_time = get_time();
-    let processing_time = end_time - start_time;
+    if debug {
+        // 调试模式下需要修改像素 这里的数据是副本 不会影响磁盘和内存中的缓存
+        draw_chunk_debug_overlay(&mut chunk_data, chunk_x, chunk_y, 0)?;
+    }
 
     println!(
-        "[RUST] Chunk ({}, {}) 零拷贝获取完成: {}ms (总耗时: {}ms) (线程: {:?})",
+        "[RUST] Chunk ({}, {}) 零拷贝获取完成 (总耗时: {}ms) (线程: {:?})",
         chunk_x,
         chunk_y,
-        end_time,
-        processing_time,
+        span.elapsed_ms(),
         thread::current().id()
     );
 
-    // 零拷贝返回：直接传递原始数据，避免序列化和反序列化
-    // 数据格式：宽度(4字节) + 高度(4字节) + 像素数据
-    // 前端可以直接解析这个格式，无需额外的JSON序列化开销
-    Ok(Response::new(chunk_data))
+    Ok(chunk_data)
+}
+
+// 内存中的 chunk 缓存：平移时反复请求的 chunk 直接从内存返回 不再读磁盘
+// 只缓存完整分辨率的 chunk 按最近使用淘汰 总大小不超过字节预算
+// 从本地缓存目录读取的 chunk 以只读映射的形式保存（见 map_chunk_file） 占用的是系统页缓存
+// 预算默认 512MB 可以通过环境变量 IMAGES_GL_CHUNK_CACHE_MB 或 set_chunk_cache_budget 调整
+// 图片重新预处理、chunk 文件被替换或缓存被清理时对应的条目随之移除
+// 每个条目记录放入时源文件的大小和修改时间 源文件变化后不再命中 由正常的读取流程检查缓存是否过期
+// 每张图片有一个代数 移除条目时递增 读取开始后图片的缓存被重写时 读到的旧数据不会再放进缓存
+// 全局只有一份 IPC 命令通过 tauri::State 取得 RPC 等其他调用方直接使用同一份
+
+// 设置字节预算（MB）的环境变量
+pub const CHUNK_CACHE_BUDGET_ENV: &str = "IMAGES_GL_CHUNK_CACHE_MB";
+// 默认字节预算
+const DEFAULT_CHUNK_CACHE_BYTES: u64 = 512 * 1024 * 1024;
+
+type ChunkKey = (String, u32, u32);
+
+// 源文件的大小和修改时间
+type SourceStamp = Option<(u64, SystemTime)>;
+
+fn source_stamp(file_path: &str) -> SourceStamp {
+    let metadata = fs::metadata(file_path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+/// 读取 chunk 之前取得的凭证 记录源文件的状态和图片缓存的代数
+/// 命中和放入缓存时都需要 见 ChunkMemoryCache::get / insert
+#[derive(Clone, Copy)]
+pub struct ChunkCacheTicket {
+    source: SourceStamp,
+    generation: u64,
+}
+
+struct CachedChunk {
+    data: Arc<ChunkBytes>,
+    source: SourceStamp,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct ChunkCacheState {
+    budget: u64,
+    bytes: u64,
+    // 每次访问递增 用作最近使用顺序
+    tick: u64,
+    entries: HashMap<ChunkKey, CachedChunk>,
+    // 最近使用顺序 -> 条目 第一个是最久没有使用的
+    order: BTreeMap<u64, ChunkKey>,
+    hits: u64,
+    misses: u64,
+    // 图片 -> 缓存代数 没有记录的图片使用 base_generation
+    generations: HashMap<String, u64>,
+    base_generation: u64,
+    next_generation: u64,
+}
+
+impl ChunkCacheState {
+    fn generation(&self, file_path: &str) -> u64 {
+        self.generations
+            .get(file_path)
+            .copied()
+            .unwrap_or(self.base_generation)
+    }
+
+    fn bump_generation(&mut self, file_path: &str) {
+        self.next_generation += 1;
+        let generation = self.next_generation;
+        self.generations.insert(file_path.to_string(), generation);
+    }
+
+    fn remove(&mut self, key: &ChunkKey) -> u64 {
+        match self.entries.remove(key) {
+            Some(entry) => {
+                self.order.remove(&entry.last_used);
+                self.bytes -= entry.data.len() as u64;
+                entry.data.len() as u64
+            }
+            None => 0,
+        }
+    }
+
+    // 淘汰最久没有使用的条目直到不超过 target 返回释放的字节数
+    fn evict_to(&mut self, target: u64) -> u64 {
+        let mut freed = 0;
+        while self.bytes > target {
+            let Some((_, key)) = self.order.pop_first() else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&key) {
+                self.bytes -= entry.data.len() as u64;
+                freed += entry.data.len() as u64;
+            }
+        }
+        freed
+    }
+}
+
+/// 内存中的 chunk 缓存（按最近使用淘汰）
+pub struct ChunkMemoryCache {
+    state: Mutex<ChunkCacheState>,
+}
+
+impl ChunkMemoryCache {
+    /// 创建指定字节预算的缓存
+    pub fn new(budget_bytes: u64) -> Self {
+        ChunkMemoryCache {
+            state: Mutex::new(ChunkCacheState {
+                budget: budget_bytes,
+                ..ChunkCacheState::default()
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ChunkCacheState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 读取一个 chunk 之前调用 取得之后 get / insert 需要的凭证
+    pub fn ticket(&self, file_path: &str) -> ChunkCacheTicket {
+        let source = source_stamp(file_path);
+        ChunkCacheTicket {
+            source,
+            generation: self.lock().generation(file_path),
+        }
+    }
+
+    /// 取出缓存的 chunk 数据（宽度 + 高度 + 像素） 同时标记为最近使用
+    /// 放入后源文件发生了变化时移除这个条目 不算命中
+    pub fn get(
+        &self,
+        file_path: &str,
+        chunk_x: u32,
+        chunk_y: u32,
+        ticket: &ChunkCacheTicket,
+    ) -> Option<Arc<ChunkBytes>> {
+        let mut state = self.lock();
+        let key = (file_path.to_string(), chunk_x, chunk_y);
+        state.tick += 1;
+        let tick = state.tick;
+        let Some(entry) = state
+            .entries
+            .get_mut(&key)
+            .filter(|entry| entry.source == ticket.source)
+        else {
+            state.remove(&key);
+            state.misses += 1;
+            return None;
+        };
+        let previous = std::mem::replace(&mut entry.last_used, tick);
+        let data = entry.data.clone();
+        state.order.remove(&previous);
+        state.order.insert(tick, key);
+        state.hits += 1;
+        Some(data)
+    }
+
+    /// 放入已经校验过的 chunk 数据 超出预算时淘汰最久没有使用的条目
+    /// 单个 chunk 就超过预算、或取得凭证后图片的缓存被重写时不缓存
+    pub fn insert(
+        &self,
+        file_path: &str,
+        chunk_x: u32,
+        chunk_y: u32,
+        ticket: &ChunkCacheTicket,
+        data: Arc<ChunkBytes>,
+    ) {
+        let mut state = self.lock();
+        if state.generation(file_path) != ticket.generation {
+            return;
+        }
+        let key = (file_path.to_string(), chunk_x, chunk_y);
+        state.remove(&key);
+        let size = data.len() as u64;
+        if size > state.budget {
+            return;
+        }
+        let target = state.budget - size;
+        state.evict_to(target);
+        state.tick += 1;
+        let tick = state.tick;
+        state.bytes += size;
+        state.order.insert(tick, key.clone());
+        state.entries.insert(
+            key,
+            CachedChunk {
+                data,
+                source: ticket.source,
+                last_used: tick,
+            },
+        );
+    }
+
+    /// 移除一个 chunk（chunk 文件被替换时调用）
+    pub fn invalidate_chunk(&self, file_path: &str, chunk_x: u32, chunk_y: u32) {
+        let mut state = self.lock();
+        state.bump_generation(file_path);
+        state.remove(&(file_path.to_string(), chunk_x, chunk_y));
+    }
+
+    /// 移除一张图片的所有 chunk（重新预处理或清理缓存时调用）
+    pub fn invalidate_image(&self, file_path: &str) {
+        let mut state = self.lock();
+        state.bump_generation(file_path);
+        let keys: Vec<ChunkKey> = state
+            .entries
+            .keys()
+            .filter(|(path, _, _)| path == file_path)
+            .cloned()
+            .collect();
+        for key in &keys {
+            state.remove(key);
+        }
+    }
+
+    /// 移除所有 chunk
+    pub fn clear(&self) {
+        let mut state = self.lock();
+        state.evict_to(0);
+        // 所有图片的代数都变化 之前取得的凭证都不能再放入
+        state.next_generation += 1;
+        state.base_generation = state.next_generation;
+        state.generations.clear();
+    }
+
+    /// 调整字节预算 超出新预算的部分立即淘汰
+    pub fn set_budget(&self, budget_bytes: u64) {
+        let mut state = self.lock();
+        state.budget = budget_bytes;
+        state.evict_to(budget_bytes);
+    }
+
+    /// 缓存统计
+    pub fn stats(&self) -> ChunkCacheStats {
+        let state = self.lock();
+        ChunkCacheStats {
+            budget_bytes: state.budget,
+            bytes_held: state.bytes,
+            entries: state.entries.len() as u32,
+            hits: state.hits,
+            misses: state.misses,
+        }
+    }
+}
+
+impl MemoryConsumer for ChunkMemoryCache {
+    fn name(&self) -> &str {
+        "chunk_cache"
+    }
+
+    fn bytes_held(&self) -> u64 {
+        self.lock().bytes
+    }
+
+    fn trim(&self, target_bytes: u64) -> u64 {
+        self.lock().evict_to(target_bytes)
+    }
+}
+
+static CHUNK_MEMORY_CACHE: OnceLock<Arc<ChunkMemoryCache>> = OnceLock::new();
+
+/// 获取全局的内存 chunk 缓存 首次调用时按环境变量设置预算并登记到内存统计中
+pub fn chunk_memory_cache() -> &'static Arc<ChunkMemoryCache> {
+    CHUNK_MEMORY_CACHE.get_or_init(|| {
+        let budget = std::env::var(CHUNK_CACHE_BUDGET_ENV)
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(|megabytes| megabytes.saturating_mul(1024 * 1024))
+            .unwrap_or(DEFAULT_CHUNK_CACHE_BYTES);
+        let cache = Arc::new(ChunkMemoryCache::new(budget));
+        register_memory_consumer(cache.clone());
+        cache
+    })
+}
+
+/// 获取内存 chunk 缓存的统计
+#[tauri::command]
+pub fn get_chunk_cache_stats(
+    cache: State<'_, Arc<ChunkMemoryCache>>,
+) -> Result<ChunkCacheStats, String> {
+    Ok(cache.stats())
+}
+
+/// 设置内存 chunk 缓存的字节预算 设为 0 时关闭缓存
+/// # Arguments
+/// * `megabytes` - 预算（MB）
+/// # Returns
+/// * `Result<ChunkCacheStats, String>` - 调整后的统计
+#[tauri::command]
+pub fn set_chunk_cache_budget(
+    cache: State<'_, Arc<ChunkMemoryCache>>,
+    megabytes: u64,
+) -> Result<ChunkCacheStats, String> {
+    cache.set_budget(megabytes.saturating_mul(1024 * 1024));
+    println!("[RUST] 内存 chunk 缓存预算设置为 {megabytes} MB");
+    Ok(cache.stats())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(len: usize) -> Arc<ChunkBytes> {
+        Arc::new(ChunkBytes::Owned(vec![0; len]))
+    }
+
+    // 不存在的源文件 凭证中没有源文件状态
+    const IMAGE: &str = "/nonexistent/chunk_cache_test.png";
+
+    fn insert(cache: &ChunkMemoryCache, chunk_x: u32, len: usize) {
+        let ticket = cache.ticket(IMAGE);
+        cache.insert(IMAGE, chunk_x, 0, &ticket, chunk(len));
+    }
+
+    fn cached(cache: &ChunkMemoryCache, chunk_x: u32) -> bool {
+        let ticket = cache.ticket(IMAGE);
+        cache.get(IMAGE, chunk_x, 0, &ticket).is_some()
+    }
+
+    #[test]
+    fn evicts_least_recently_used_chunks() {
+        let cache = ChunkMemoryCache::new(300);
+        insert(&cache, 0, 100);
+        insert(&cache, 1, 100);
+        insert(&cache, 2, 100);
+        // 访问 0 之后 1 成为最久没有使用的
+        assert!(cached(&cache, 0));
+        insert(&cache, 3, 100);
+        assert!(!cached(&cache, 1));
+        assert!(cached(&cache, 0) && cached(&cache, 2) && cached(&cache, 3));
+
+        let stats = cache.stats();
+        assert_eq!((stats.bytes_held, stats.entries), (300, 3));
+        assert_eq!((stats.hits, stats.misses), (4, 1));
+
+        // 替换同一个 chunk 不重复计算大小
+        insert(&cache, 3, 50);
+        assert_eq!(cache.stats().bytes_held, 250);
+    }
+
+    #[test]
+    fn skips_chunks_larger_than_the_budget() {
+        let cache = ChunkMemoryCache::new(100);
+        insert(&cache, 0, 60);
+        insert(&cache, 1, 101);
+        assert!(!cached(&cache, 1));
+        assert!(cached(&cache, 0));
+
+        cache.set_budget(0);
+        assert_eq!(cache.stats().bytes_held, 0);
+        insert(&cache, 0, 1);
+        assert!(!cached(&cache, 0));
+    }
+
+    #[test]
+    fn trims_to_the_requested_size() {
+        let cache = ChunkMemoryCache::new(1000);
+        for chunk_x in 0..5 {
+            insert(&cache, chunk_x, 100);
+        }
+        assert_eq!(cache.trim(250), 300);
+        assert_eq!(cache.bytes_held(), 200);
+        assert!(cached(&cache, 3) && cached(&cache, 4));
+        cache.set_budget(100);
+        assert!(!cached(&cache, 3) && cached(&cache, 4));
+    }
+
+    #[test]
+    fn rejects_chunks_read_before_invalidation() {
+        let cache = ChunkMemoryCache::new(1000);
+        let ticket = cache.ticket(IMAGE);
+        cache.invalidate_image(IMAGE);
+        cache.insert(IMAGE, 0, 0, &ticket, chunk(10));
+        assert!(!cached(&cache, 0));
+
+        let ticket = cache.ticket(IMAGE);
+        cache.invalidate_chunk(IMAGE, 5, 5);
+        cache.insert(IMAGE, 0, 0, &ticket, chunk(10));
+        assert!(!cached(&cache, 0));
+
+        let ticket = cache.ticket(IMAGE);
+        cache.clear();
+        cache.insert(IMAGE, 0, 0, &ticket, chunk(10));
+        assert!(!cached(&cache, 0));
+
+        insert(&cache, 0, 10);
+        insert(&cache, 1, 10);
+        cache.invalidate_image(IMAGE);
+        assert!(!cached(&cache, 0) && !cached(&cache, 1));
+        assert_eq!(cache.stats().bytes_held, 0);
+    }
+
+    #[test]
+    fn drops_chunks_when_the_source_changes() {
+        let path = std::env::temp_dir().join(format!("chunk_cache_source_{}", std::process::id()));
+        let file_path = path.to_string_lossy().to_string();
+        fs::write(&path, b"before").unwrap();
+        let cache = ChunkMemoryCache::new(1000);
+        let ticket = cache.ticket(&file_path);
+        cache.insert(&file_path, 0, 0, &ticket, chunk(10));
+        assert!(cache.get(&file_path, 0, 0, &ticket).is_some());
+
+        fs::write(&path, b"after the change").unwrap();
+        let ticket = cache.ticket(&file_path);
+        assert!(cache.get(&file_path, 0, 0, &ticket).is_none());
+        assert_eq!(cache.stats().entries, 0);
+        let _ = fs::remove_file(&path);
+    }
 }
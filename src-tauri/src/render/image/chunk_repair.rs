@@ -0,0 +1,154 @@
+use std::fs;
+use std::path::Path;
+
+use tauri::{AppHandle, Emitter};
+
+use super::cache::{
+    ensure_cache_writable, image_cache_dir, is_cache_read_only, load_cached_metadata,
+};
+use super::chunk_processing::{chunk_memory_cache, process_single_chunk_parallel};
+use super::config::{CHUNK_SIZE_X, CHUNK_SIZE_Y, MAX_CHUNK_SIZE};
+use super::preprocessing::build_chunk_infos;
+use super::region_decode::decode_source_region;
+use super::types::{ChunkInfo, ChunkWarning};
+use crate::utils::time::Stopwatch;
+
+// chunk 数据损坏（或已重新生成）时发出的事件
+pub const CHUNK_WARNING_EVENT: &str = "chunk://warning";
+
+/// 校验 chunk 文件数据是否完整
+/// 格式：宽度(4字节) + 高度(4字节) + 像素数据 像素数据长度必须与宽高一致
+pub fn validate_chunk_data(chunk_data: &[u8]) -> Result<(), String> {
+    if chunk_data.len() < 8 {
+        return Err("Chunk 文件格式错误：数据长度不足".to_string());
+    }
+
+    let width = u32::from_be_bytes([chunk_data[0], chunk_data[1], chunk_data[2], chunk_data[3]]);
+    let height = u32::from_be_bytes([chunk_data[4], chunk_data[5], chunk_data[6], chunk_data[7]]);
+    if width == 0 || height == 0 || width > MAX_CHUNK_SIZE || height > MAX_CHUNK_SIZE {
+        return Err(format!("Chunk 文件格式错误：尺寸 {width}x{height} 无效"));
+    }
+
+    let expected_len = 8 + width as usize * height as usize * 4;
+    if chunk_data.len() != expected_len {
+        return Err(format!(
+            "Chunk 文件格式错误：数据长度 {} 与尺寸 {}x{} 不匹配（应为 {}），文件可能被截断",
+            chunk_data.len(),
+            width,
+            height,
+            expected_len
+        ));
+    }
+
+    Ok(())
+}
+
+/// 从源文件重新生成单个 chunk
+/// 只解码 chunk 所在的区域（PNG 按行解码到 chunk 的最后一行） 不需要整张图的内存
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `chunk_x` - chunk 的 X 索引
+/// * `chunk_y` - chunk 的 Y 索引
+/// # Returns
+/// * `Result<(), String>` - 是否成功
+pub fn regenerate_chunk(file_path: &str, chunk_x: u32, chunk_y: u32) -> Result<(), String> {
+    ensure_cache_writable("重新生成 chunk")?;
+    if !Path::new(file_path).exists() {
+        return Err(format!("源文件不存在，无法重新生成 chunk: {file_path}"));
+    }
+
+    let metadata = load_cached_metadata(file_path)?;
+    let chunk_info = metadata
+        .chunks
+        .iter()
+        .find(|c| c.chunk_x == chunk_x && c.chunk_y == chunk_y)
+        .ok_or_else(|| format!("元数据中不存在 chunk ({chunk_x}, {chunk_y})"))?;
+
+    let (width, height) =
+        image::image_dimensions(file_path).map_err(|e| format!("读取图片尺寸失败: {e}"))?;
+    if width != metadata.total_width || height != metadata.total_height {
+        return Err(format!(
+            "源文件尺寸 {width}x{height} 与缓存元数据 {}x{} 不一致，源文件可能已被修改",
+            metadata.total_width, metadata.total_height
+        ));
+    }
+
+    let region = decode_chunk_region(file_path, chunk_info)?;
+    let local_info = ChunkInfo {
+        x: 0,
+        y: 0,
+        ..chunk_info.clone()
+    };
+
+    // 源文件没有变化 重新生成的像素哈希与记录的一致 不需要更新哈希文件
+    process_single_chunk_parallel(&region, &local_info, &image_cache_dir(file_path))?;
+    chunk_memory_cache().invalidate_chunk(file_path, chunk_x, chunk_y);
+    println!("[RUST] Chunk ({chunk_x}, {chunk_y}) 已从源文件重新生成");
+    Ok(())
+}
+
+fn decode_chunk_region(file_path: &str, info: &ChunkInfo) -> Result<image::RgbaImage, String> {
+    decode_source_region(file_path, info.x, info.y, info.width, info.height)
+}
+
+/// 读取 chunk 时缓存不存在（被清理或淘汰）或 chunk 文件丢失 源文件还在时按需生成这一个 chunk
+/// 有完整的缓存元数据时把 chunk 写回缓存（缓存只读时除外） 否则按默认 chunk 尺寸计算位置 只返回数据
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `chunk_x` / `chunk_y` - chunk 的列、行索引
+/// * `cached` - 图片是否有完整的缓存元数据
+/// # Returns
+/// * `Result<Vec<u8>, String>` - chunk 数据 格式与 chunk 文件相同
+pub fn generate_missing_chunk(
+    file_path: &str,
+    chunk_x: u32,
+    chunk_y: u32,
+    cached: bool,
+) -> Result<Vec<u8>, String> {
+    if !Path::new(file_path).is_file() {
+        return Err(format!("源文件不存在，无法生成 chunk: {file_path}"));
+    }
+    let stopwatch = Stopwatch::start();
+    let chunks = if cached {
+        load_cached_metadata(file_path)?.chunks
+    } else {
+        let (width, height) =
+            image::image_dimensions(file_path).map_err(|e| format!("读取图片尺寸失败: {e}"))?;
+        build_chunk_infos(width, height, CHUNK_SIZE_X, CHUNK_SIZE_Y)
+    };
+    let info = chunks
+        .iter()
+        .find(|c| c.chunk_x == chunk_x && c.chunk_y == chunk_y)
+        .ok_or_else(|| format!("chunk 不存在: ({chunk_x}, {chunk_y})"))?;
+
+    let data = if cached && !is_cache_read_only() {
+        regenerate_chunk(file_path, chunk_x, chunk_y)?;
+        let path = image_cache_dir(file_path).join(format!("chunk_{chunk_x}_{chunk_y}.bin"));
+        fs::read(&path).map_err(|e| format!("读取 chunk 文件失败: {e}"))?
+    } else {
+        let region = decode_chunk_region(file_path, info)?;
+        let mut data = Vec::with_capacity(8 + region.as_raw().len());
+        data.extend_from_slice(&info.width.to_be_bytes());
+        data.extend_from_slice(&info.height.to_be_bytes());
+        data.extend_from_slice(region.as_raw());
+        data
+    };
+    println!(
+        "[RUST] Chunk ({chunk_x}, {chunk_y}) 缓存未命中，已从源文件按需生成 (耗时: {}ms)",
+        stopwatch.elapsed_ms()
+    );
+    Ok(data)
+}
+
+/// 发出 chunk 警告事件 没有 AppHandle 时（例如压力测试）只打印日志
+pub fn emit_chunk_warning(app: Option<&AppHandle>, warning: ChunkWarning) {
+    println!(
+        "[RUST] Chunk ({}, {}) 警告: {} (已重新生成: {})",
+        warning.chunk_x, warning.chunk_y, warning.message, warning.regenerated
+    );
+    if let Some(app) = app {
+        if let Err(e) = app.emit(CHUNK_WARNING_EVENT, warning) {
+            println!("[RUST] 发送 chunk 警告事件失败: {e}");
+        }
+    }
+}
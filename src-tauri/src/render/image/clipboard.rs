@@ -0,0 +1,75 @@
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Window};
+
+use super::commands::load_user_image;
+use super::types::ImageMetadata;
+use super::utils::{app_data_subdir, save_rgba_png};
+use super::window_state::set_window_image;
+
+// 剪贴板图片在应用数据目录下的保存位置
+const CLIPBOARD_DIR: &str = "clipboard";
+
+/// 从系统剪贴板读取图片并走正常的预处理流程
+/// 图片会先保存为应用数据目录下的 PNG 文件 文件名取自像素内容的哈希
+/// 重复粘贴同一张图片时可以直接复用已有缓存
+/// 保存后的路径会记录为调用窗口当前打开的图片 前端可以通过 get_window_state 取得
+#[tauri::command]
+pub fn process_clipboard_image(app: AppHandle, window: Window) -> Result<ImageMetadata, String> {
+    let file_path = save_clipboard_image(&app)?;
+    let file_path = file_path.to_string_lossy().to_string();
+
+    let metadata = load_user_image(&file_path)?;
+    set_window_image(window.label(), &file_path);
+    Ok(metadata)
+}
+
+/// 把剪贴板中的图片保存为 PNG 文件
+/// # Arguments
+/// * `app` - 应用句柄 用于定位应用数据目录
+/// # Returns
+/// * `Result<PathBuf, String>` - 保存后的文件路径或错误信息
+fn save_clipboard_image(app: &AppHandle) -> Result<PathBuf, String> {
+    let (width, height, pixels) = backend::read_image()?;
+
+    println!("[RUST] 从剪贴板读取图片: {width}x{height}");
+
+    let hash = blake3::hash(&pixels).to_hex();
+
+    let dir = app_data_subdir(app, CLIPBOARD_DIR)?;
+
+    let file_path = dir.join(format!("clipboard_{}.png", &hash[..16]));
+    if file_path.exists() {
+        println!("[RUST] 剪贴板图片已存在: {}", file_path.display());
+        return Ok(file_path);
+    }
+
+    save_rgba_png(&file_path, width, height, pixels)?;
+
+    println!("[RUST] 剪贴板图片已保存: {}", file_path.display());
+    Ok(file_path)
+}
+
+#[cfg(feature = "clipboard")]
+mod backend {
+    /// 读取剪贴板中的图片 返回宽、高和 RGBA 像素
+    pub fn read_image() -> Result<(u32, u32, Vec<u8>), String> {
+        let mut clipboard =
+            arboard::Clipboard::new().map_err(|e| format!("打开剪贴板失败: {e}"))?;
+        let image = clipboard
+            .get_image()
+            .map_err(|e| format!("剪贴板中没有可用的图片: {e}"))?;
+        Ok((
+            image.width as u32,
+            image.height as u32,
+            image.bytes.into_owned(),
+        ))
+    }
+}
+
+#[cfg(not(feature = "clipboard"))]
+mod backend {
+    pub fn read_image() -> Result<(u32, u32, Vec<u8>), String> {
+        Err("未启用剪贴板支持 请使用 --features clipboard 重新编译".to_string())
+    }
+}
@@ -0,0 +1,87 @@
+use super::cache::load_cached_metadata;
+use super::export::compose_region;
+use super::types::{AverageColor, ImageRegion, PixelPoint, SampleShape};
+
+// 取色：对中心周围的像素求平均 照片中的噪点不会让取到的颜色跳来跳去
+// 采样范围可以跨越多个 chunk 从缓存的 chunk 中拼出包围矩形后再按形状筛选
+
+// 最大采样半径 对应 201x201 的正方形
+const MAX_SAMPLE_RADIUS: u32 = 100;
+
+/// 计算中心周围像素的平均颜色（需要先完成预处理）
+/// 采样范围超出图片的部分不参与平均
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `center` - 采样中心（像素坐标）
+/// * `radius` - 采样半径 0 表示只取中心一个像素
+/// * `shape` - 采样形状 默认为圆形
+/// # Returns
+/// * `Result<AverageColor, String>` - 各通道的平均值
+#[tauri::command]
+pub fn get_average_color(
+    file_path: String,
+    center: PixelPoint,
+    radius: u32,
+    shape: Option<SampleShape>,
+) -> Result<AverageColor, String> {
+    if radius > MAX_SAMPLE_RADIUS {
+        return Err(format!("采样半径不能超过 {MAX_SAMPLE_RADIUS}: {radius}"));
+    }
+    let metadata = load_cached_metadata(&file_path)?;
+    if center.x >= metadata.total_width || center.y >= metadata.total_height {
+        return Err(format!(
+            "采样中心超出图片范围: ({}, {}) (图片 {}x{})",
+            center.x, center.y, metadata.total_width, metadata.total_height
+        ));
+    }
+
+    // 采样形状的包围矩形 裁剪到图片范围内
+    let x0 = center.x.saturating_sub(radius);
+    let y0 = center.y.saturating_sub(radius);
+    let x1 = (center.x + radius + 1).min(metadata.total_width);
+    let y1 = (center.y + radius + 1).min(metadata.total_height);
+    let region = ImageRegion {
+        x: x0,
+        y: y0,
+        width: x1 - x0,
+        height: y1 - y0,
+    };
+    let pixels = compose_region(&file_path, &metadata, &region)?;
+
+    let shape = shape.unwrap_or(SampleShape::Disc);
+    let radius_squared = radius as i64 * radius as i64;
+    let mut sums = [0u64; 4];
+    let mut sample_count = 0u32;
+    for y in y0..y1 {
+        for x in x0..x1 {
+            if shape == SampleShape::Disc {
+                let dx = x as i64 - center.x as i64;
+                let dy = y as i64 - center.y as i64;
+                if dx * dx + dy * dy > radius_squared {
+                    continue;
+                }
+            }
+            let offset = ((y - y0) as usize * region.width as usize + (x - x0) as usize) * 4;
+            for (sum, value) in sums.iter_mut().zip(&pixels[offset..offset + 4]) {
+                *sum += *value as u64;
+            }
+            sample_count += 1;
+        }
+    }
+
+    // 中心一定在图片内 至少有一个采样
+    let [r, g, b, a] = sums.map(|sum| sum as f64 / sample_count as f64);
+    Ok(AverageColor {
+        r,
+        g,
+        b,
+        a,
+        hex: format!(
+            "#{:02X}{:02X}{:02X}",
+            r.round() as u8,
+            g.round() as u8,
+            b.round() as u8
+        ),
+        sample_count,
+    })
+}
@@ -1,52 +1,119 @@
-use crate::utils::time::get_time;
+use crate::utils::time::TimingSpan;
 use std::path::Path;
 use tauri::ipc::Response;
-
-use super::cache::{check_file_cache_exists, clear_file_cache};
+use tauri::{AppHandle, Window};
+
+use super::access_stats::record_chunk_access;
+use super::backpressure::ChunkRequestPermit;
+use super::cache::{
+    check_file_cache_exists, discard_preprocess_output, ensure_cache_writable, is_cache_read_only,
+    load_cached_metadata,
+};
+use super::catalog::record_catalog_image;
+use super::chunk_pages::apply_chunk_paging;
 use super::chunk_processing::get_image_chunk_sync;
-use super::config::get_thread_pool;
+use super::debug_overlay::{draw_chunk_debug_overlay, is_debug_overlay_enabled_by_env};
+use super::errors::{localized_error, ErrorCode};
+use super::lod_pyramid::load_level_chunk;
+use super::mobile::resolve_input_path;
+use super::preprocess_dedup::run_deduplicated;
+use super::preprocess_queue::{enqueue_user_image, preprocess_job_id};
 use super::preprocessing::preprocess_and_cache_chunks;
-use super::types::ImageMetadata;
+use super::proxy::{load_proxy_image, proxy_scale_for};
+use super::scheduler::run_scheduled;
+use super::startup_preload::record_recent_image;
+use super::timing_breakdown::{attach_timing_breakdown, request_timing_breakdown};
+use super::types::{ImageMetadata, TaskKind};
+use super::window_state::set_window_image;
+use super::write_tracker::begin_cache_write;
 
 /// 处理用户选择的图片文件
+/// 同时把图片记录为调用窗口当前打开的图片（content URI 时记录导入后的本地路径）
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `timings` - 为 true 时在返回的元数据中附带耗时分解（见 timing_breakdown）
+/// * `paged` - 为 true 时不返回 chunk 列表 通过 get_chunk_infos 按需读取（见 chunk_pages）
 #[tauri::command]
-pub fn process_user_image(file_path: String) -> Result<ImageMetadata, String> {
-    let start_time = get_time();
-    println!("[RUST] 开始处理用户选择的图片: {file_path}ms");
+pub fn process_user_image(
+    app: AppHandle,
+    window: Window,
+    file_path: String,
+    timings: Option<bool>,
+    paged: Option<bool>,
+) -> Result<ImageMetadata, String> {
+    // Android 上传入的可能是 content:// URI
+    let file_path = resolve_input_path(&app, &file_path)?;
+    let timing_request = timings
+        .unwrap_or(false)
+        .then(|| request_timing_breakdown(&file_path));
+    let metadata = open_user_image(&file_path)?;
+    set_window_image(window.label(), &file_path);
+    record_catalog_image(&app, &file_path);
+    record_recent_image(&app, &file_path);
+    let metadata = attach_timing_breakdown(timing_request, metadata);
+    Ok(apply_chunk_paging(paged, &file_path, metadata))
+}
 
-    // 检查文件是否存在
-    if !Path::new(&file_path).exists() {
-        return Err(format!("图片文件不存在: {file_path}"));
+/// 在后台处理用户选择的图片 立即返回任务 ID 不阻塞 IPC 线程
+/// 处理过程中发出 preprocess://progress 事件（百分比、完成的 chunk 数、预计剩余时间）
+/// 结束时发出 preprocess://completed 事件 其中包含元数据或错误信息
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `paged` - 为 true 时完成事件中的元数据不包含 chunk 列表 通过 get_chunk_infos 按需读取（见 chunk_pages）
+/// # Returns
+/// * `Result<String, String>` - 任务 ID（由图片路径决定 重复调用返回同一个 ID）
+#[tauri::command]
+pub fn process_user_image_async(
+    app: AppHandle,
+    window: Window,
+    file_path: String,
+    paged: Option<bool>,
+) -> Result<String, String> {
+    let file_path = resolve_input_path(&app, &file_path)?;
+    if !(is_cache_read_only() && check_file_cache_exists(&file_path)) {
+        validate_image_path(&file_path)?;
     }
+    enqueue_user_image(&app, &file_path, paged)?;
+    set_window_image(window.label(), &file_path);
+    record_recent_image(&app, &file_path);
+    Ok(preprocess_job_id(&file_path))
+}
 
-    // 检查文件扩展名
-    let path = Path::new(&file_path);
-    let extension = path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("")
-        .to_lowercase();
+/// 打开用户选择的图片 process_user_image、process_user_image_async 以及深层链接、书签等入口共用
+/// 内存受限时先使用低分辨率代理 完整分辨率在需要 1:1 查看时再生成
+/// 双击、重试、同步和后台同时打开等重复调用合并为一次加载
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<ImageMetadata, String>` - 图片元数据 使用代理副本时 proxy_scale 有值
+pub fn open_user_image(file_path: &str) -> Result<ImageMetadata, String> {
+    run_deduplicated(file_path, false, || match proxy_scale_for(file_path) {
+        Some(scale) => load_proxy_image(file_path, scale),
+        None => load_user_image(file_path),
+    })
+}
 
-    if !matches!(
-        extension.as_str(),
-        "png" | "jpg" | "jpeg" | "bmp" | "tiff" | "webp"
-    ) {
-        return Err(format!(
-            "不支持的图片格式: {extension}. 支持的格式: PNG, JPG, JPEG, BMP, TIFF, WEBP"
-        ));
+/// 加载用户图片 有缓存时直接读取元数据 否则进行预处理
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<ImageMetadata, String>` - 图片元数据或错误信息
+pub fn load_user_image(file_path: &str) -> Result<ImageMetadata, String> {
+    let span = TimingSpan::new("image.load");
+    println!("[RUST] 开始处理用户选择的图片: {file_path}");
+
+    // 检查文件是否存在以及扩展名
+    // 只读缓存的切片由服务器预处理 本机上可能没有源文件 已有缓存时不检查
+    if !(is_cache_read_only() && check_file_cache_exists(file_path)) {
+        validate_image_path(file_path)?;
     }
 
     // 先检查是否有这个文件对应的缓存
-    if check_file_cache_exists(&file_path) {
+    if check_file_cache_exists(file_path) {
         println!("[RUST] 发现现有缓存，从缓存加载元数据");
 
         // 从缓存文件加载元数据
-        let metadata_filepath = std::path::Path::new("chunk_cache").join("metadata.json");
-        let metadata_content = std::fs::read_to_string(metadata_filepath)
-            .map_err(|e| format!("读取缓存元数据失败: {e}"))?;
-
-        let metadata: ImageMetadata = serde_json::from_str(&metadata_content)
-            .map_err(|e| format!("解析缓存元数据失败: {e}"))?;
+        let metadata = load_cached_metadata(file_path)?;
 
         println!(
             "[RUST] 从缓存加载元数据成功: {}x{}, 共 {} 个 chunks",
@@ -61,41 +128,107 @@ pub fn process_user_image(file_path: String) -> Result<ImageMetadata, String> {
     println!("[RUST] 缓存不存在，开始预处理和缓存 chunks");
 
     // 使用用户选择的文件路径进行预处理
-    let metadata = preprocess_and_cache_chunks(&file_path)?;
+    let metadata = preprocess_and_cache_chunks(file_path)?;
 
-    let end_time = get_time();
-    println!(
-        "[RUST] 用户图片处理完成: {}ms (总耗时: {}ms)",
-        end_time,
-        end_time - start_time
-    );
+    println!("[RUST] 用户图片处理完成 (总耗时: {}ms)", span.elapsed_ms());
 
     Ok(metadata)
 }
 
+/// 检查图片路径是否存在且扩展名受支持
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<(), String>` - 不满足条件时返回错误信息
+pub fn validate_image_path(file_path: &str) -> Result<(), String> {
+    let path = Path::new(file_path);
+    if !path.exists() {
+        return Err(localized_error(
+            ErrorCode::FileNotFound,
+            &[("path", &file_path)],
+        ));
+    }
+
+    // 检查文件扩展名
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    if !matches!(
+        extension.as_str(),
+        "png" | "jpg" | "jpeg" | "bmp" | "tif" | "tiff" | "webp"
+    ) {
+        return Err(localized_error(
+            ErrorCode::UnsupportedFormat,
+            &[("format", &extension)],
+        ));
+    }
+
+    Ok(())
+}
+
 /// 获取特定 chunk 的像素数据（零拷贝版本，支持并行执行）
+/// `debug` 可选 为 true 时在像素中绘制 chunk 索引、层级和边框
+/// 未传入时由环境变量 IMAGES_GL_CHUNK_DEBUG 决定 方便在不改前端的情况下排查问题
+/// `level` 可选 大于 0 时读取多分辨率金字塔中该级别的 chunk 索引是该级别网格中的位置
 #[tauri::command]
-pub fn get_image_chunk(chunk_x: u32, chunk_y: u32, file_path: String) -> Result<Response, String> {
-    // 使用全局线程池让每个请求并行执行
-    // 这样前端多个 invoke 调用时，Rust 端可以并行处理
+pub fn get_image_chunk(
+    app: AppHandle,
+    chunk_x: u32,
+    chunk_y: u32,
+    file_path: String,
+    debug: Option<bool>,
+    level: Option<u32>,
+) -> Result<Response, String> {
+    let debug = debug.unwrap_or_else(is_debug_overlay_enabled_by_env);
+
+    // 概览级别（level > 0）的 chunk 见 metadata.levels
+    if let Some(level) = level.filter(|&level| level > 0) {
+        let _permit = ChunkRequestPermit::acquire(&file_path)?;
+        return run_scheduled(TaskKind::Read, &file_path, || {
+            let mut data = load_level_chunk(&file_path, level, chunk_x, chunk_y)?;
+            if debug {
+                draw_chunk_debug_overlay(&mut data, chunk_x, chunk_y, level)?;
+            }
+            Ok(data)
+        })?
+        .map(Response::new);
+    }
+
+    // 通过调度器在全局线程池中执行 让每个请求并行处理
+    // 这样前端多个 invoke 调用时，Rust 端可以并行处理 读取请求优先于后台任务
 
     // 零拷贝返回：直接传递原始数据，避免序列化和反序列化
     // 数据格式：宽度(4字节) + 高度(4字节) + 像素数据
     // 前端可以直接解析这个格式，无需额外的JSON序列化开销
-    get_thread_pool().install(|| get_image_chunk_sync(chunk_x, chunk_y, file_path))
+    // 记录访问 用于热力图和缓存淘汰
+    record_chunk_access(&file_path, chunk_x, chunk_y);
+    let _permit = ChunkRequestPermit::acquire(&file_path)?;
+    run_scheduled(TaskKind::Read, &file_path.clone(), || {
+        get_image_chunk_sync(chunk_x, chunk_y, file_path, debug, Some(&app))
+    })?
 }
 
 /// 手动触发预处理和缓存（用于测试或强制更新）
 #[tauri::command]
-pub fn force_preprocess_chunks(file_path: String) -> Result<ImageMetadata, String> {
+pub fn force_preprocess_chunks(window: Window, file_path: String) -> Result<ImageMetadata, String> {
     println!("[RUST] 手动触发预处理和缓存: {file_path}");
 
-    // 先清理现有缓存
-    let _ = clear_file_cache(file_path.clone());
+    // 同一张图片的重复调用合并 不会在预处理过程中删除缓存再并发预处理一次
+    let metadata = run_deduplicated(&file_path, true, || {
+        // 清理期间的读取同样返回 [CHUNK_NOT_READY] 而不是缓存不存在
+        let _writing = begin_cache_write(&file_path);
+        // 先删除预处理的产物 标注等用户数据保留
+        ensure_cache_writable("重新预处理")?;
+        discard_preprocess_output(&file_path)?;
 
-    // 重新预处理和缓存
-    let metadata = preprocess_and_cache_chunks(&file_path)?;
+        // 重新预处理和缓存
+        preprocess_and_cache_chunks(&file_path)
+    })?;
 
     println!("[RUST] 手动预处理完成");
+    set_window_image(window.label(), &file_path);
     Ok(metadata)
 }
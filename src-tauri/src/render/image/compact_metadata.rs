@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+
+use super::preprocessing::build_chunk_infos;
+use super::types::{ChunkInfo, ImageMetadata};
+
+// 紧凑的缓存元数据：chunk 的位置和尺寸完全可以由图片尺寸和 chunk 尺寸推算（见 build_chunk_infos）
+// chunk 较多时 metadata.json 中不保存完整的 chunks 只保存与推算结果不同的例外项
+// 读取时按网格重新生成列表再替换例外项 返回给前端的元数据与完整格式相同
+// chunk 很少时仍然保存完整列表 文件可读 也没有明显的体积差别
+// 旧版本无法读取紧凑格式 所以写入紧凑格式的缓存使用新的缓存格式版本（见 config）
+
+// chunk 数达到这个值时使用紧凑格式
+const COMPACT_METADATA_MIN_CHUNKS: usize = 256;
+
+// metadata.json 的存储格式
+#[derive(Serialize, Deserialize)]
+struct StoredMetadata {
+    #[serde(flatten)]
+    metadata: ImageMetadata,
+    // 紧凑格式时存在 chunks 为空 这里是与网格推算结果不同的 chunk
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    chunk_exceptions: Option<Vec<ChunkInfo>>,
+}
+
+/// 把元数据序列化为 metadata.json 的内容 chunk 较多且符合网格时使用紧凑格式
+/// # Arguments
+/// * `metadata` - 完整的元数据
+/// # Returns
+/// * `Result<String, String>` - JSON 内容
+pub fn encode_metadata(metadata: &ImageMetadata) -> Result<String, String> {
+    let exceptions = (metadata.chunks.len() >= COMPACT_METADATA_MIN_CHUNKS)
+        .then(|| grid_exceptions(metadata))
+        .flatten();
+    let stored = match exceptions {
+        Some(exceptions) => StoredMetadata {
+            metadata: ImageMetadata {
+                chunks: Vec::new(),
+                ..metadata.clone()
+            },
+            chunk_exceptions: Some(exceptions),
+        },
+        None => StoredMetadata {
+            metadata: metadata.clone(),
+            chunk_exceptions: None,
+        },
+    };
+    serde_json::to_string(&stored).map_err(|e| format!("序列化元数据失败: {e}"))
+}
+
+/// 解析 metadata.json 的内容 紧凑格式时按网格还原完整的 chunk 列表
+/// # Arguments
+/// * `content` - JSON 内容
+/// # Returns
+/// * `Result<ImageMetadata, String>` - 完整的元数据
+pub fn decode_metadata(content: &str) -> Result<ImageMetadata, String> {
+    let stored: StoredMetadata =
+        serde_json::from_str(content).map_err(|e| format!("解析元数据失败: {e}"))?;
+    let mut metadata = stored.metadata;
+    let Some(exceptions) = stored.chunk_exceptions else {
+        return Ok(metadata);
+    };
+    if metadata.chunk_size_x == 0 || metadata.chunk_size_y == 0 {
+        return Err("紧凑元数据中的 chunk 尺寸无效".to_string());
+    }
+    metadata.chunks = build_chunk_infos(
+        metadata.total_width,
+        metadata.total_height,
+        metadata.chunk_size_x,
+        metadata.chunk_size_y,
+    );
+    for exception in exceptions {
+        let index = (exception.chunk_y * metadata.col_count + exception.chunk_x) as usize;
+        match metadata.chunks.get_mut(index) {
+            Some(chunk) => *chunk = exception,
+            None => return Err(format!("紧凑元数据中的 chunk 超出网格: {exception:?}")),
+        }
+    }
+    Ok(metadata)
+}
+
+// 与网格推算结果逐项比较 返回不同的项 列表的形状与网格不一致时返回 None（保存完整列表）
+fn grid_exceptions(metadata: &ImageMetadata) -> Option<Vec<ChunkInfo>> {
+    if metadata.chunk_size_x == 0 || metadata.chunk_size_y == 0 {
+        return None;
+    }
+    let grid = build_chunk_infos(
+        metadata.total_width,
+        metadata.total_height,
+        metadata.chunk_size_x,
+        metadata.chunk_size_y,
+    );
+    let shaped = grid.len() == metadata.chunks.len()
+        && metadata.col_count == metadata.total_width.div_ceil(metadata.chunk_size_x)
+        && metadata.chunks.iter().zip(&grid).all(|(chunk, expected)| {
+            chunk.chunk_x == expected.chunk_x && chunk.chunk_y == expected.chunk_y
+        });
+    if !shaped {
+        return None;
+    }
+    Some(
+        metadata
+            .chunks
+            .iter()
+            .zip(&grid)
+            .filter(|(chunk, expected)| chunk != expected)
+            .map(|(chunk, _)| chunk.clone())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(width: u32, height: u32, chunk_size: u32) -> ImageMetadata {
+        ImageMetadata {
+            total_width: width,
+            total_height: height,
+            chunk_size_x: chunk_size,
+            chunk_size_y: chunk_size,
+            col_count: width.div_ceil(chunk_size),
+            row_count: height.div_ceil(chunk_size),
+            chunks: build_chunk_infos(width, height, chunk_size, chunk_size),
+            proxy_scale: None,
+            levels: Vec::new(),
+            timings: None,
+            chunk_count: None,
+        }
+    }
+
+    #[test]
+    fn small_grids_keep_the_full_list() {
+        let metadata = metadata(1000, 700, 256);
+        let json = encode_metadata(&metadata).unwrap();
+        assert!(!json.contains("chunk_exceptions"));
+        assert_eq!(decode_metadata(&json).unwrap().chunks, metadata.chunks);
+    }
+
+    #[test]
+    fn large_grids_store_only_exceptions() {
+        let mut metadata = metadata(10000, 7000, 256);
+        assert_eq!(grid_exceptions(&metadata), Some(Vec::new()));
+        metadata.chunks[5].width = 100;
+        let exceptions = grid_exceptions(&metadata).unwrap();
+        assert_eq!(exceptions, vec![metadata.chunks[5].clone()]);
+
+        let json = encode_metadata(&metadata).unwrap();
+        assert!(json.contains("\"chunks\":[]"));
+        let decoded = decode_metadata(&json).unwrap();
+        assert_eq!(decoded.chunks, metadata.chunks);
+        assert_eq!(decoded.col_count, metadata.col_count);
+    }
+
+    #[test]
+    fn lists_that_do_not_match_the_grid_are_kept() {
+        let mut metadata = metadata(10000, 7000, 256);
+        metadata.chunks.swap(0, 1);
+        assert_eq!(grid_exceptions(&metadata), None);
+        metadata.chunks.pop();
+        assert_eq!(grid_exceptions(&metadata), None);
+        let json = encode_metadata(&metadata).unwrap();
+        assert_eq!(decode_metadata(&json).unwrap().chunks, metadata.chunks);
+
+        metadata.chunk_size_x = 0;
+        assert_eq!(grid_exceptions(&metadata), None);
+    }
+
+    #[test]
+    fn rejects_invalid_compact_metadata() {
+        let mut metadata = metadata(10000, 7000, 256);
+        metadata.chunks[0].width = 1;
+        let json = encode_metadata(&metadata).unwrap();
+
+        // 例外项超出网格
+        let outside = json.replace(
+            "\"chunk_x\":0,\"chunk_y\":0",
+            "\"chunk_x\":0,\"chunk_y\":999",
+        );
+        assert_ne!(outside, json);
+        assert!(decode_metadata(&outside).is_err());
+
+        let zero_size = json.replace("\"chunk_size_x\":256", "\"chunk_size_x\":0");
+        assert_ne!(zero_size, json);
+        assert!(decode_metadata(&zero_size).is_err());
+
+        assert!(decode_metadata("{").is_err());
+    }
+}
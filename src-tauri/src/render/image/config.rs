@@ -1,15 +1,56 @@
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 use std::thread;
 
 // Chunk 缓存目录
 pub const CHUNK_CACHE_DIR: &str = "chunk_cache";
 
+// 缓存根目录 桌面端为工作目录下的 chunk_cache
+// 移动端的工作目录不可写 启动时改为应用沙盒中的缓存目录
+static CACHE_ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// 设置缓存根目录 只能在第一次访问缓存之前设置一次
+/// # Returns
+/// * `bool` - 是否设置成功
+pub fn set_cache_root(path: PathBuf) -> bool {
+    CACHE_ROOT.set(path).is_ok()
+}
+
+/// 获取缓存根目录 所有图片的缓存目录都在它下面
+pub fn cache_root() -> &'static Path {
+    CACHE_ROOT.get_or_init(|| PathBuf::from(CHUNK_CACHE_DIR))
+}
+
 // TODO 这个chunk可能不是最优的 后续需要进行实验 或者 这个尺寸应该是实时计算后确定的
+#[cfg(not(mobile))]
 pub const CHUNK_SIZE_X: u32 = 4096;
+#[cfg(not(mobile))]
 pub const CHUNK_SIZE_Y: u32 = 4096;
 // 单个chunk的内存大小应该为 4096 * 4096 * 4 = 67,108,864 字节
 // 约等于 67MB
 
+// 移动端内存和 GPU 纹理尺寸都更小 使用 2048 的 chunk（约 16MB）
+#[cfg(mobile)]
+pub const CHUNK_SIZE_X: u32 = 2048;
+#[cfg(mobile)]
+pub const CHUNK_SIZE_Y: u32 = 2048;
+
+// 允许的最大 chunk 边长 与常见 WebGL 实现的最大纹理尺寸一致
+#[cfg(not(mobile))]
+pub const MAX_CHUNK_SIZE: u32 = 16384;
+// 移动端 GPU 普遍只保证 4096 的纹理尺寸
+#[cfg(mobile)]
+pub const MAX_CHUNK_SIZE: u32 = 4096;
+// 允许的最小 chunk 边长 太小会导致 chunk 数量和 IPC 次数暴涨
+pub const MIN_CHUNK_SIZE: u32 = 256;
+
+// chunk 缓存的格式版本 记录在 source_info.json 中 chunk 文件或缓存元数据的格式变化时递增
+// 版本 1：大端 u32 宽度 + 大端 u32 高度 + RGBA8 像素
+// 版本 2：chunk 文件不变 metadata.json 可以是紧凑格式（见 compact_metadata）
+pub const CHUNK_FORMAT_VERSION: u32 = 2;
+// 可以读取的 chunk 缓存格式版本 其他版本的缓存视为不存在 需要重新预处理
+pub const SUPPORTED_CHUNK_FORMAT_VERSIONS: [u32; 2] = [1, 2];
+
 // 全局线程池，避免重复创建
 /*
  * OnceLock 类型来确保线程池只被初始化一次
@@ -0,0 +1,174 @@
+use std::env;
+
+// 调试叠加层：把 chunk 的索引、层级和边框直接画进像素里
+// 用于排查拼接缝隙、chunk 错位等问题 前端不需要任何改动
+
+// 环境变量 设置为 1 / true 时 所有 chunk 请求都会带上调试叠加层
+pub const CHUNK_DEBUG_ENV: &str = "IMAGES_GL_CHUNK_DEBUG";
+
+// 边框宽度（像素）
+const BORDER_WIDTH: u32 = 4;
+// 字形放大倍数 4096 的 chunk 缩放显示时也要能看清
+const GLYPH_SCALE: u32 = 12;
+// 字形尺寸 5x7 点阵
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+
+const BORDER_COLOR: [u8; 4] = [255, 0, 255, 255];
+const TEXT_COLOR: [u8; 4] = [255, 255, 0, 255];
+const TEXT_BG_COLOR: [u8; 4] = [0, 0, 0, 255];
+
+/// 是否通过环境变量开启了调试叠加层
+pub fn is_debug_overlay_enabled_by_env() -> bool {
+    env::var(CHUNK_DEBUG_ENV)
+        .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "on"))
+        .unwrap_or(false)
+}
+
+/// 在 chunk 数据上绘制调试信息
+/// # Arguments
+/// * `chunk_data` - chunk 数据 格式为 宽度(4字节) + 高度(4字节) + RGBA 像素数据
+/// * `chunk_x` - chunk 的 X 索引
+/// * `chunk_y` - chunk 的 Y 索引
+/// * `level` - 金字塔层级
+/// # Returns
+/// * `Result<(), String>` - 是否成功
+pub fn draw_chunk_debug_overlay(
+    chunk_data: &mut [u8],
+    chunk_x: u32,
+    chunk_y: u32,
+    level: u32,
+) -> Result<(), String> {
+    if chunk_data.len() < 8 {
+        return Err("Chunk 数据格式错误：数据长度不足".to_string());
+    }
+
+    let width = u32::from_be_bytes([chunk_data[0], chunk_data[1], chunk_data[2], chunk_data[3]]);
+    let height = u32::from_be_bytes([chunk_data[4], chunk_data[5], chunk_data[6], chunk_data[7]]);
+    let expected_len = width as usize * height as usize * 4;
+    let pixels = &mut chunk_data[8..];
+    if pixels.len() != expected_len {
+        return Err(format!(
+            "Chunk 数据格式错误：像素数据长度 {} 与尺寸 {}x{} 不匹配",
+            pixels.len(),
+            width,
+            height
+        ));
+    }
+
+    let mut canvas = Canvas {
+        pixels,
+        width,
+        height,
+    };
+
+    // 边框 四条边各画一次
+    canvas.fill_rect(0, 0, width, BORDER_WIDTH, BORDER_COLOR);
+    canvas.fill_rect(
+        0,
+        height.saturating_sub(BORDER_WIDTH),
+        width,
+        BORDER_WIDTH,
+        BORDER_COLOR,
+    );
+    canvas.fill_rect(0, 0, BORDER_WIDTH, height, BORDER_COLOR);
+    canvas.fill_rect(
+        width.saturating_sub(BORDER_WIDTH),
+        0,
+        BORDER_WIDTH,
+        height,
+        BORDER_COLOR,
+    );
+
+    // 文本 每行一项 放在左上角
+    let lines = [
+        format!("L{level}"),
+        format!("X{chunk_x}"),
+        format!("Y{chunk_y}"),
+    ];
+    let line_height = (GLYPH_HEIGHT + 2) * GLYPH_SCALE;
+    let origin = BORDER_WIDTH * 2;
+    for (i, line) in lines.iter().enumerate() {
+        canvas.draw_text(origin, origin + i as u32 * line_height, line);
+    }
+
+    Ok(())
+}
+
+// 对像素数据的简单包装 所有绘制都会裁剪到 chunk 范围内
+struct Canvas<'a> {
+    pixels: &'a mut [u8],
+    width: u32,
+    height: u32,
+}
+
+impl Canvas<'_> {
+    fn fill_rect(&mut self, x: u32, y: u32, w: u32, h: u32, color: [u8; 4]) {
+        let x_end = x.saturating_add(w).min(self.width);
+        let y_end = y.saturating_add(h).min(self.height);
+        for py in y.min(y_end)..y_end {
+            let row_start = (py as usize * self.width as usize + x as usize) * 4;
+            let row_end = (py as usize * self.width as usize + x_end as usize) * 4;
+            if row_start >= row_end {
+                continue;
+            }
+            for pixel in self.pixels[row_start..row_end].chunks_exact_mut(4) {
+                pixel.copy_from_slice(&color);
+            }
+        }
+    }
+
+    fn draw_text(&mut self, x: u32, y: u32, text: &str) {
+        let advance = (GLYPH_WIDTH + 1) * GLYPH_SCALE;
+        let char_count = text.chars().count() as u32;
+
+        // 先画背景 保证在任何图片内容上都能看清
+        self.fill_rect(
+            x,
+            y,
+            char_count * advance + GLYPH_SCALE,
+            (GLYPH_HEIGHT + 2) * GLYPH_SCALE,
+            TEXT_BG_COLOR,
+        );
+
+        for (i, ch) in text.chars().enumerate() {
+            let glyph = glyph_rows(ch);
+            let glyph_x = x + GLYPH_SCALE + i as u32 * advance;
+            let glyph_y = y + GLYPH_SCALE;
+            for (row, bits) in glyph.iter().enumerate() {
+                for col in 0..GLYPH_WIDTH {
+                    // 每一行的高位在左
+                    if bits & (1 << (GLYPH_WIDTH - 1 - col)) != 0 {
+                        self.fill_rect(
+                            glyph_x + col * GLYPH_SCALE,
+                            glyph_y + row as u32 * GLYPH_SCALE,
+                            GLYPH_SCALE,
+                            GLYPH_SCALE,
+                            TEXT_COLOR,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+// 5x7 点阵字形 只包含调试信息用到的字符
+fn glyph_rows(ch: char) -> [u8; 7] {
+    match ch {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x0A, 0x04, 0x04, 0x04, 0x04],
+        _ => [0; 7],
+    }
+}
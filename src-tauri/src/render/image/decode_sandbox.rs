@@ -0,0 +1,147 @@
+use std::env;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use image::{DynamicImage, RgbaImage};
+
+use super::errors::{current_locale, localized_error, ErrorCode, LOCALE_ENV};
+use super::preprocessing::decode_source_image_in_process;
+
+// 解码沙箱：在独立的子进程中解码不受信任的图片
+// 子进程就是应用自身的可执行文件 以 --decode-worker <path> 参数启动
+// 解码结果通过标准输出管道传回 子进程崩溃时转换为 DECODER_CRASHED 错误
+
+// 以解码子进程方式启动时的命令行参数
+pub const DECODE_WORKER_ARG: &str = "--decode-worker";
+
+// 设置为 1 / true 时默认开启解码沙箱
+pub const DECODE_SANDBOX_ENV: &str = "IMAGES_GL_DECODE_SANDBOX";
+
+// 子进程输出的魔数 用来确认输出确实来自解码子进程
+const WORKER_MAGIC: &[u8; 4] = b"IGDW";
+
+// 子进程正常报告解码错误时的退出码 其他非零退出码或被信号终止都视为崩溃
+const WORKER_DECODE_ERROR_EXIT: i32 = 2;
+
+static SANDBOX_ENABLED: AtomicBool = AtomicBool::new(false);
+static SANDBOX_INIT: OnceLock<()> = OnceLock::new();
+
+/// 是否开启了解码沙箱 首次调用时读取环境变量作为默认值
+pub fn is_decode_sandbox_enabled() -> bool {
+    SANDBOX_INIT.get_or_init(|| {
+        let enabled = env::var(DECODE_SANDBOX_ENV)
+            .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "on"))
+            .unwrap_or(false);
+        SANDBOX_ENABLED.store(enabled, Ordering::Relaxed);
+    });
+    SANDBOX_ENABLED.load(Ordering::Relaxed)
+}
+
+/// 开启或关闭解码沙箱
+#[tauri::command]
+pub fn set_decode_sandbox(enabled: bool) -> Result<bool, String> {
+    // 先触发一次初始化 避免之后读取环境变量覆盖这里的设置
+    is_decode_sandbox_enabled();
+    SANDBOX_ENABLED.store(enabled, Ordering::Relaxed);
+    println!("[RUST] 解码沙箱已{}", if enabled { "开启" } else { "关闭" });
+    Ok(enabled)
+}
+
+/// 获取解码沙箱是否开启
+#[tauri::command]
+pub fn get_decode_sandbox() -> Result<bool, String> {
+    Ok(is_decode_sandbox_enabled())
+}
+
+/// 当前进程是否以解码子进程方式启动
+/// 返回要解码的文件路径
+pub fn decode_worker_request() -> Option<String> {
+    let mut args = env::args().skip(1);
+    match args.next() {
+        Some(arg) if arg == DECODE_WORKER_ARG => args.next(),
+        _ => None,
+    }
+}
+
+/// 解码子进程的入口 解码完成后返回进程退出码
+/// 输出格式：魔数(4字节) + 宽度(4字节) + 高度(4字节) + RGBA 像素数据
+/// 解码失败时把错误信息写到标准错误
+pub fn run_decode_worker(file_path: &str) -> i32 {
+    let image = match decode_source_image_in_process(file_path) {
+        Ok(image) => image.to_rgba8(),
+        Err(e) => {
+            eprint!("{e}");
+            return WORKER_DECODE_ERROR_EXIT;
+        }
+    };
+
+    let mut stdout = io::stdout().lock();
+    let result = stdout
+        .write_all(WORKER_MAGIC)
+        .and_then(|_| stdout.write_all(&image.width().to_be_bytes()))
+        .and_then(|_| stdout.write_all(&image.height().to_be_bytes()))
+        .and_then(|_| stdout.write_all(image.as_raw()))
+        .and_then(|_| stdout.flush());
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprint!("写入解码结果失败: {e}");
+            WORKER_DECODE_ERROR_EXIT
+        }
+    }
+}
+
+/// 在子进程中解码图片
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<DynamicImage, String>` - 解码后的 RGBA 图片或错误信息
+pub fn decode_sandboxed(file_path: &str) -> Result<DynamicImage, String> {
+    let exe = env::current_exe().map_err(|e| format!("获取可执行文件路径失败: {e}"))?;
+    println!("[RUST] 在解码子进程中解码: {file_path}");
+
+    let output = Command::new(exe)
+        .arg(DECODE_WORKER_ARG)
+        .arg(file_path)
+        // 子进程中的错误信息原样返回给前端 使用与本进程相同的语言（可能已被 set_locale 修改）
+        .env(LOCALE_ENV, current_locale().as_str())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| format!("启动解码子进程失败: {e}"))?;
+
+    if !output.status.success() {
+        if output.status.code() == Some(WORKER_DECODE_ERROR_EXIT) {
+            // 子进程中已经是本地化的错误信息 原样返回
+            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+        return Err(localized_error(
+            ErrorCode::DecoderCrashed,
+            &[("status", &output.status)],
+        ));
+    }
+
+    let data = output.stdout;
+    if data.len() < 12 || &data[..4] != WORKER_MAGIC {
+        return Err(localized_error(
+            ErrorCode::DecoderCrashed,
+            &[("status", &"invalid output")],
+        ));
+    }
+    let width = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+    let height = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+
+    let mut pixels = data;
+    pixels.drain(..12);
+    let image = RgbaImage::from_raw(width, height, pixels).ok_or_else(|| {
+        localized_error(
+            ErrorCode::DecoderCrashed,
+            &[("status", &"truncated output")],
+        )
+    })?;
+    Ok(DynamicImage::ImageRgba8(image))
+}
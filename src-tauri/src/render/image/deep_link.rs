@@ -0,0 +1,124 @@
+use tauri::{AppHandle, Emitter, Window};
+use url::Url;
+
+use super::commands::{open_user_image, validate_image_path};
+use super::types::NavigationTarget;
+use super::window_state::set_window_image;
+
+// 深链接协议 例如 imagesgl://open?path=D:/scan.png&x=1200&y=3400&zoom=2
+pub const DEEP_LINK_SCHEME: &str = "imagesgl";
+// 导航事件 前端收到后跳转到对应位置
+pub const NAVIGATION_EVENT: &str = "navigation://goto";
+
+// 深链接中的原始参数
+struct DeepLinkParams {
+    path: String,
+    x: Option<f64>,
+    y: Option<f64>,
+    zoom: Option<f64>,
+}
+
+/// 打开深链接
+/// 解析并校验链接、加载（必要时预处理）图片 然后向调用窗口发出导航事件
+/// 图片会记录为调用窗口当前打开的图片
+/// # Arguments
+/// * `url` - 深链接 例如 imagesgl://open?path=...&x=...&y=...&zoom=...
+/// # Returns
+/// * `Result<NavigationTarget, String>` - 导航目标或错误信息
+#[tauri::command]
+pub fn open_deep_link(
+    app: AppHandle,
+    window: Window,
+    url: String,
+) -> Result<NavigationTarget, String> {
+    println!("[RUST] 打开深链接: {url}");
+
+    let params = parse_deep_link(&url)?;
+    validate_image_path(&params.path)?;
+
+    let metadata = open_user_image(&params.path)?;
+
+    // 未指定坐标时默认定位到图片中心
+    let x = params.x.unwrap_or(metadata.total_width as f64 / 2.0);
+    let y = params.y.unwrap_or(metadata.total_height as f64 / 2.0);
+    if x < 0.0 || x > metadata.total_width as f64 || y < 0.0 || y > metadata.total_height as f64 {
+        return Err(format!(
+            "深链接坐标 ({x}, {y}) 超出图片范围 {}x{}",
+            metadata.total_width, metadata.total_height
+        ));
+    }
+
+    let target = NavigationTarget {
+        file_path: params.path,
+        x,
+        y,
+        zoom: params.zoom.unwrap_or(1.0),
+        viewport: None,
+        metadata,
+    };
+
+    set_window_image(window.label(), &target.file_path);
+    // 只通知调用的窗口 其他窗口打开的是各自的图片
+    app.emit_to(window.label(), NAVIGATION_EVENT, target.clone())
+        .map_err(|e| format!("发送导航事件失败: {e}"))?;
+
+    println!(
+        "[RUST] 深链接导航: {} @ ({}, {}) x{}",
+        target.file_path, target.x, target.y, target.zoom
+    );
+
+    Ok(target)
+}
+
+// 解析深链接 只做语法层面的校验
+fn parse_deep_link(url: &str) -> Result<DeepLinkParams, String> {
+    let url = Url::parse(url).map_err(|e| format!("深链接格式错误: {e}"))?;
+
+    if url.scheme() != DEEP_LINK_SCHEME {
+        return Err(format!(
+            "不支持的深链接协议: {}, 应为 {DEEP_LINK_SCHEME}",
+            url.scheme()
+        ));
+    }
+    if url.host_str() != Some("open") {
+        return Err(format!(
+            "不支持的深链接操作: {}",
+            url.host_str().unwrap_or("")
+        ));
+    }
+
+    let mut path = None;
+    let mut x = None;
+    let mut y = None;
+    let mut zoom = None;
+    // query_pairs 会自动处理百分号编码
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "path" => path = Some(value.to_string()),
+            "x" => x = Some(parse_number("x", &value)?),
+            "y" => y = Some(parse_number("y", &value)?),
+            "zoom" => zoom = Some(parse_number("zoom", &value)?),
+            other => println!("[RUST] 忽略未知的深链接参数: {other}"),
+        }
+    }
+
+    let path = path
+        .filter(|p| !p.is_empty())
+        .ok_or_else(|| "深链接缺少 path 参数".to_string())?;
+
+    if let Some(zoom) = zoom {
+        if zoom <= 0.0 {
+            return Err(format!("深链接缩放比例必须为正数: {zoom}"));
+        }
+    }
+
+    Ok(DeepLinkParams { path, x, y, zoom })
+}
+
+fn parse_number(name: &str, value: &str) -> Result<f64, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| format!("深链接参数 {name} 不是有效数字: {value}"))
+}
@@ -0,0 +1,200 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use crate::utils::disk::available_space;
+
+use super::backend_info::{compiled_features, supported_decoders, OPTIONAL_FEATURES};
+use super::cache::is_cache_read_only;
+use super::config::{cache_root, get_thread_pool, CHUNK_SIZE_X, CHUNK_SIZE_Y};
+use super::gpu_compute::is_gpu_compute_available;
+use super::types::{
+    DecoderAvailability, DiagnosticCheck, DiagnosticStatus, DiagnosticsReport, ThreadPoolStatus,
+};
+
+// 磁盘剩余空间低于这个值时给出警告 至少能放下 16 个满尺寸 chunk
+const MIN_FREE_DISK_BYTES: u64 = CHUNK_SIZE_X as u64 * CHUNK_SIZE_Y as u64 * 4 * 16;
+
+/// 运行自检 返回结构化的诊断报告
+/// 用户反馈问题时首先让其运行这个命令
+#[tauri::command]
+pub fn run_diagnostics() -> Result<DiagnosticsReport, String> {
+    println!("[RUST] 开始运行自检");
+
+    let mut checks = Vec::new();
+
+    // 缓存目录
+    let cache_dir = absolute_cache_dir();
+    // 只读模式下不写探测文件 缓存目录可能挂载自服务器
+    let cache_dir_writable = if is_cache_read_only() {
+        checks.push(check(
+            "cache_dir_writable",
+            DiagnosticStatus::Warning,
+            "缓存处于只读模式 只能浏览已预处理的图片",
+        ));
+        false
+    } else {
+        match check_cache_dir_writable(&cache_dir) {
+            Ok(()) => {
+                checks.push(check(
+                    "cache_dir_writable",
+                    DiagnosticStatus::Ok,
+                    "缓存目录可写",
+                ));
+                true
+            }
+            Err(e) => {
+                checks.push(check("cache_dir_writable", DiagnosticStatus::Error, &e));
+                false
+            }
+        }
+    };
+
+    // 磁盘空间
+    let available_disk_bytes = match available_space(&cache_dir) {
+        Ok(bytes) => {
+            let status = if bytes < MIN_FREE_DISK_BYTES {
+                DiagnosticStatus::Warning
+            } else {
+                DiagnosticStatus::Ok
+            };
+            checks.push(check(
+                "available_disk",
+                status,
+                &format!("缓存磁盘可用空间: {} MB", bytes / 1024 / 1024),
+            ));
+            Some(bytes)
+        }
+        Err(e) => {
+            checks.push(check("available_disk", DiagnosticStatus::Warning, &e));
+            None
+        }
+    };
+
+    // 解码器 与 get_backend_info 报告的格式列表一致
+    let decoders: Vec<DecoderAvailability> = supported_decoders()
+        .into_iter()
+        .map(|decoder| DecoderAvailability {
+            format: decoder.format,
+            compiled: decoder.available,
+            // 预处理流程按内容识别格式 编译了的解码器都可以使用
+            pipeline_supported: decoder.available,
+        })
+        .collect();
+    for decoder in &decoders {
+        // 由可选特性提供的格式（pdf、dicom、video）没有编译进来是构建的选择 不算问题
+        let optional = OPTIONAL_FEATURES
+            .iter()
+            .any(|(feature, _)| *feature == decoder.format);
+        let (status, message) = match (decoder.compiled, decoder.pipeline_supported) {
+            (true, true) => (DiagnosticStatus::Ok, "解码器可用".to_string()),
+            (true, false) => (
+                DiagnosticStatus::Warning,
+                "解码器已编译 但预处理流程暂不支持该格式".to_string(),
+            ),
+            (false, _) if optional => (
+                DiagnosticStatus::Ok,
+                format!("未启用可选特性 {}", decoder.format),
+            ),
+            (false, _) => (
+                DiagnosticStatus::Warning,
+                "未编译该格式的解码器".to_string(),
+            ),
+        };
+        checks.push(check(
+            &format!("decoder_{}", decoder.format),
+            status,
+            &message,
+        ));
+    }
+
+    // 可选特性
+    let features = compiled_features();
+    checks.push(check(
+        "features",
+        DiagnosticStatus::Ok,
+        &if features.is_empty() {
+            "没有编译可选特性".to_string()
+        } else {
+            format!("编译的可选特性: {}", features.join(", "))
+        },
+    ));
+    // 编译了 gpu 特性但找不到显卡时预处理退回到 CPU
+    if cfg!(feature = "gpu") {
+        let (status, message) = if is_gpu_compute_available() {
+            (DiagnosticStatus::Ok, "GPU 计算可用")
+        } else {
+            (DiagnosticStatus::Warning, "没有可用的显卡 预处理使用 CPU")
+        };
+        checks.push(check("gpu_compute", status, message));
+    }
+
+    // 线程池
+    let available_parallelism = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(0);
+    let thread_pool = ThreadPoolStatus {
+        num_threads: get_thread_pool().current_num_threads(),
+        available_parallelism,
+    };
+    checks.push(check(
+        "thread_pool",
+        if thread_pool.num_threads > 0 {
+            DiagnosticStatus::Ok
+        } else {
+            DiagnosticStatus::Error
+        },
+        &format!(
+            "线程池大小: {}, 系统可用并行度: {}",
+            thread_pool.num_threads, thread_pool.available_parallelism
+        ),
+    ));
+
+    // 总体状态取最差的一项
+    let status = checks
+        .iter()
+        .map(|c| c.status)
+        .max_by_key(|s| match s {
+            DiagnosticStatus::Ok => 0,
+            DiagnosticStatus::Warning => 1,
+            DiagnosticStatus::Error => 2,
+        })
+        .unwrap_or(DiagnosticStatus::Ok);
+
+    println!("[RUST] 自检完成: {status:?}, 共 {} 项检查", checks.len());
+
+    Ok(DiagnosticsReport {
+        status,
+        cache_dir: cache_dir.to_string_lossy().to_string(),
+        cache_dir_writable,
+        available_disk_bytes,
+        decoders,
+        thread_pool,
+        checks,
+    })
+}
+
+// 桌面端的缓存目录是相对路径 报告中给出绝对路径方便用户定位
+fn absolute_cache_dir() -> PathBuf {
+    env::current_dir()
+        .map(|cwd| cwd.join(cache_root()))
+        .unwrap_or_else(|_| cache_root().to_path_buf())
+}
+
+// 通过实际写入并删除一个探测文件来检查可写性 只检查权限位在网络盘上不可靠
+fn check_cache_dir_writable(cache_dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(cache_dir).map_err(|e| format!("创建缓存目录失败: {e}"))?;
+    let probe_file = cache_dir.join(".diagnostics_probe");
+    fs::write(&probe_file, b"probe").map_err(|e| format!("缓存目录不可写: {e}"))?;
+    fs::remove_file(&probe_file).map_err(|e| format!("删除探测文件失败: {e}"))?;
+    Ok(())
+}
+
+fn check(name: &str, status: DiagnosticStatus, message: &str) -> DiagnosticCheck {
+    DiagnosticCheck {
+        name: name.to_string(),
+        status,
+        message: message.to_string(),
+    }
+}
@@ -0,0 +1,232 @@
+use std::path::Path;
+
+use tauri::{AppHandle, Window};
+
+use super::cache::fnv1a_hash;
+use super::commands::load_user_image;
+use super::types::{DicomInfo, ImageMetadata};
+use super::utils::{app_data_subdir, is_up_to_date, save_rgba_png};
+use super::window_state::set_window_image;
+
+// DICOM 转换结果在应用数据目录下的保存位置
+const DICOM_DIR: &str = "dicom";
+
+/// 读取 DICOM 头信息（尺寸、重缩放参数、默认窗宽窗位）
+/// 需要启用 dicom 特性
+#[tauri::command]
+pub fn get_dicom_info(file_path: String) -> Result<DicomInfo, String> {
+    backend::read_info(Path::new(&file_path))
+}
+
+/// 打开 DICOM 图片
+/// 像素值先按 Rescale Slope / Intercept 转换为模态值 再按窗宽窗位映射为 8 位灰度
+/// 之后保存为 PNG 走正常的预处理流程
+/// 保存后的路径会记录为调用窗口当前打开的图片 前端可以通过 get_window_state 取得
+/// # Arguments
+/// * `file_path` - DICOM 文件路径
+/// * `window_center` - 窗位 不传时使用头信息中的默认值
+/// * `window_width` - 窗宽 不传时使用头信息中的默认值
+#[tauri::command]
+pub fn process_dicom_image(
+    app: AppHandle,
+    window: Window,
+    file_path: String,
+    window_center: Option<f64>,
+    window_width: Option<f64>,
+) -> Result<ImageMetadata, String> {
+    let source = Path::new(&file_path);
+    if !source.exists() {
+        return Err(format!("DICOM 文件不存在: {file_path}"));
+    }
+
+    let info = backend::read_info(source)?;
+    let values = backend::read_modality_values(source, &info)?;
+
+    // 窗宽窗位优先级：参数 > 头信息 > 像素值范围
+    let (center, width) = match (
+        window_center.or(info.window_center),
+        window_width.or(info.window_width),
+    ) {
+        (Some(center), Some(width)) => (center, width),
+        _ => full_range_window(&values),
+    };
+
+    let dir = app_data_subdir(&app, DICOM_DIR)?;
+    let output = dir.join(format!(
+        "dicom_{:016x}_c{}_w{}.png",
+        fnv1a_hash(file_path.as_bytes()),
+        center.round() as i64,
+        width.round() as i64
+    ));
+
+    if is_up_to_date(source, &output) {
+        println!("[RUST] 复用已转换的 DICOM 图片: {}", output.display());
+    } else {
+        println!(
+            "[RUST] 转换 DICOM 图片: {}x{} 窗位 {center} 窗宽 {width}",
+            info.columns, info.rows
+        );
+        let pixels = if info.samples_per_pixel == 1 {
+            let invert = info.photometric_interpretation == "MONOCHROME1";
+            apply_window(&values, center, width, invert)
+        } else {
+            rgb_to_rgba(&values)
+        };
+        save_rgba_png(&output, info.columns, info.rows, pixels)?;
+    }
+
+    let output = output.to_string_lossy().to_string();
+    let metadata = load_user_image(&output)?;
+    set_window_image(window.label(), &output);
+    Ok(metadata)
+}
+
+/// 按窗宽窗位把模态值映射为 8 位灰度 RGBA
+/// 采用 DICOM 标准 (PS3.3 C.11.2.1.2) 中的线性 VOI LUT 公式
+/// # Arguments
+/// * `values` - 模态值（已经过重缩放）
+/// * `center` - 窗位
+/// * `width` - 窗宽
+/// * `invert` - 是否反色（MONOCHROME1）
+fn apply_window(values: &[f64], center: f64, width: f64, invert: bool) -> Vec<u8> {
+    let width = width.max(1.0);
+    let low = center - 0.5 - (width - 1.0) / 2.0;
+    let high = center - 0.5 + (width - 1.0) / 2.0;
+
+    let mut pixels = Vec::with_capacity(values.len() * 4);
+    for &value in values {
+        let gray = if value <= low {
+            0.0
+        } else if value > high {
+            255.0
+        } else {
+            ((value - (center - 0.5)) / (width - 1.0) + 0.5) * 255.0
+        };
+        let mut gray = gray.round().clamp(0.0, 255.0) as u8;
+        if invert {
+            gray = 255 - gray;
+        }
+        pixels.extend_from_slice(&[gray, gray, gray, 255]);
+    }
+    pixels
+}
+
+// 彩色 DICOM 不做窗宽窗位 直接截断到 8 位
+fn rgb_to_rgba(values: &[f64]) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(values.len() / 3 * 4);
+    for rgb in values.chunks_exact(3) {
+        for &channel in rgb {
+            pixels.push(channel.round().clamp(0.0, 255.0) as u8);
+        }
+        pixels.push(255);
+    }
+    pixels
+}
+
+// 头信息中没有窗宽窗位时 用像素值的完整范围
+fn full_range_window(values: &[f64]) -> (f64, f64) {
+    let (min, max) = values.iter().fold((f64::MAX, f64::MIN), |(min, max), &v| {
+        (min.min(v), max.max(v))
+    });
+    if min > max {
+        return (0.0, 1.0);
+    }
+    ((min + max) / 2.0, (max - min).max(1.0))
+}
+
+#[cfg(feature = "dicom")]
+mod backend {
+    use std::path::Path;
+
+    use dicom_dictionary_std::tags;
+    use dicom_object::{open_file, DefaultDicomObject};
+    use dicom_pixeldata::{ConvertOptions, ModalityLutOption, PixelDecoder};
+
+    use super::super::types::DicomInfo;
+
+    fn open(path: &Path) -> Result<DefaultDicomObject, String> {
+        open_file(path).map_err(|e| format!("打开 DICOM 文件失败: {e}"))
+    }
+
+    fn read_u32(obj: &DefaultDicomObject, tag: dicom_object::Tag) -> Option<u32> {
+        obj.element(tag).ok()?.to_int::<u32>().ok()
+    }
+
+    fn read_f64(obj: &DefaultDicomObject, tag: dicom_object::Tag) -> Option<f64> {
+        // 窗宽窗位可能有多个值 取第一个作为默认值
+        obj.element(tag)
+            .ok()?
+            .to_multi_float64()
+            .ok()?
+            .first()
+            .copied()
+    }
+
+    fn read_str(obj: &DefaultDicomObject, tag: dicom_object::Tag) -> Option<String> {
+        obj.element(tag)
+            .ok()?
+            .to_str()
+            .ok()
+            .map(|s| s.trim().to_string())
+    }
+
+    pub fn read_info(path: &Path) -> Result<DicomInfo, String> {
+        let obj = open(path)?;
+        let rows = read_u32(&obj, tags::ROWS).ok_or("DICOM 缺少 Rows")?;
+        let columns = read_u32(&obj, tags::COLUMNS).ok_or("DICOM 缺少 Columns")?;
+
+        Ok(DicomInfo {
+            rows,
+            columns,
+            frames: read_u32(&obj, tags::NUMBER_OF_FRAMES).unwrap_or(1),
+            samples_per_pixel: read_u32(&obj, tags::SAMPLES_PER_PIXEL).unwrap_or(1) as u16,
+            bits_stored: read_u32(&obj, tags::BITS_STORED).unwrap_or(8) as u16,
+            photometric_interpretation: read_str(&obj, tags::PHOTOMETRIC_INTERPRETATION)
+                .unwrap_or_else(|| "MONOCHROME2".to_string()),
+            modality: read_str(&obj, tags::MODALITY),
+            rescale_slope: read_f64(&obj, tags::RESCALE_SLOPE).unwrap_or(1.0),
+            rescale_intercept: read_f64(&obj, tags::RESCALE_INTERCEPT).unwrap_or(0.0),
+            window_center: read_f64(&obj, tags::WINDOW_CENTER),
+            window_width: read_f64(&obj, tags::WINDOW_WIDTH),
+        })
+    }
+
+    /// 解码第一帧 并按重缩放参数转换为模态值
+    pub fn read_modality_values(path: &Path, info: &DicomInfo) -> Result<Vec<f64>, String> {
+        let obj = open(path)?;
+        let decoded = obj
+            .decode_pixel_data()
+            .map_err(|e| format!("解码 DICOM 像素数据失败: {e}"))?;
+
+        // 关闭库内置的 Modality LUT 统一在这里按头信息做重缩放
+        let options = ConvertOptions::new().with_modality_lut(ModalityLutOption::None);
+        let raw: Vec<f64> = decoded
+            .to_vec_frame_with_options(0, &options)
+            .map_err(|e| format!("转换 DICOM 像素数据失败: {e}"))?;
+
+        if info.samples_per_pixel != 1 {
+            return Ok(raw);
+        }
+        Ok(raw
+            .into_iter()
+            .map(|v| v * info.rescale_slope + info.rescale_intercept)
+            .collect())
+    }
+}
+
+#[cfg(not(feature = "dicom"))]
+mod backend {
+    use std::path::Path;
+
+    use super::super::types::DicomInfo;
+
+    const DISABLED: &str = "未启用 DICOM 支持 请使用 --features dicom 重新编译";
+
+    pub fn read_info(_path: &Path) -> Result<DicomInfo, String> {
+        Err(DISABLED.to_string())
+    }
+
+    pub fn read_modality_values(_path: &Path, _info: &DicomInfo) -> Result<Vec<f64>, String> {
+        Err(DISABLED.to_string())
+    }
+}
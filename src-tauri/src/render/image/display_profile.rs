@@ -0,0 +1,70 @@
+use std::fs;
+
+use super::cache::{check_file_cache_exists, ensure_cache_writable, image_cache_dir};
+use super::types::DisplayProfile;
+use crate::utils::time::get_time;
+
+// 显示配置：窗宽窗位、伪彩色、处理流程、旋转等 保存在图片缓存目录的 display_profile.json 中
+// 与缓存一起清理 重新分块或增量刷新不会影响
+
+// 显示配置文件
+const DISPLAY_PROFILE_FILE: &str = "display_profile.json";
+
+/// 获取图片保存的显示配置
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<Option<DisplayProfile>, String>` - 没有保存过时为 None
+#[tauri::command]
+pub fn get_display_profile(file_path: String) -> Result<Option<DisplayProfile>, String> {
+    let path = image_cache_dir(&file_path).join(DISPLAY_PROFILE_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path).map_err(|e| format!("读取显示配置失败: {e}"))?;
+    let profile = serde_json::from_str(&content).map_err(|e| format!("解析显示配置失败: {e}"))?;
+    Ok(Some(profile))
+}
+
+/// 保存图片的显示配置 图片需要已经预处理
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `profile` - 显示配置 为 null 时删除已保存的配置
+/// # Returns
+/// * `Result<Option<DisplayProfile>, String>` - 保存后的配置
+#[tauri::command]
+pub fn set_display_profile(
+    file_path: String,
+    profile: Option<DisplayProfile>,
+) -> Result<Option<DisplayProfile>, String> {
+    ensure_cache_writable("保存显示配置")?;
+    if !check_file_cache_exists(&file_path) {
+        return Err(format!("图片尚未预处理: {file_path}"));
+    }
+    let path = image_cache_dir(&file_path).join(DISPLAY_PROFILE_FILE);
+
+    let Some(profile) = profile else {
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("删除显示配置失败: {e}"))?;
+        }
+        println!("[RUST] 已删除显示配置: {file_path}");
+        return Ok(None);
+    };
+
+    if profile.rotation % 90 != 0 {
+        return Err(format!("旋转角度必须是 90 的倍数: {}", profile.rotation));
+    }
+    if profile.window_width.is_some_and(|width| width <= 0.0) {
+        return Err("窗宽必须大于 0".to_string());
+    }
+    let profile = DisplayProfile {
+        rotation: profile.rotation % 360,
+        updated_ms: get_time() as u64,
+        ..profile
+    };
+
+    let json = serde_json::to_string(&profile).map_err(|e| format!("序列化显示配置失败: {e}"))?;
+    fs::write(&path, json).map_err(|e| format!("保存显示配置失败: {e}"))?;
+    println!("[RUST] 已保存显示配置: {file_path}");
+    Ok(Some(profile))
+}
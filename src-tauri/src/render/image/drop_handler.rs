@@ -0,0 +1,100 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tauri::AppHandle;
+
+use super::commands::validate_image_path;
+use super::preprocess_queue::enqueue_preprocess;
+use super::types::DroppedPathResult;
+
+/// 处理从系统拖放进来的路径
+/// 过滤不支持的格式、去重 然后把每个图片加入后台预处理队列
+/// 文件夹会展开其中的文件（不递归子文件夹）
+/// # Arguments
+/// * `paths` - 拖放的文件或文件夹路径
+/// # Returns
+/// * `Result<Vec<DroppedPathResult>, String>` - 每个文件的接受/拒绝结果
+#[tauri::command]
+pub fn handle_dropped_paths(
+    app: AppHandle,
+    paths: Vec<String>,
+) -> Result<Vec<DroppedPathResult>, String> {
+    println!("[RUST] 处理拖放路径: {} 个", paths.len());
+
+    let mut files: Vec<PathBuf> = Vec::new();
+    let mut results: Vec<DroppedPathResult> = Vec::new();
+
+    for path in &paths {
+        let path_buf = PathBuf::from(path);
+        if path_buf.is_dir() {
+            match list_folder_files(&path_buf) {
+                Ok(folder_files) => files.extend(folder_files),
+                Err(e) => results.push(rejected(path, e)),
+            }
+        } else {
+            files.push(path_buf);
+        }
+    }
+
+    // 同一个文件可能既被单独拖入 又在拖入的文件夹里 按规范化后的路径去重
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    for file in files {
+        let display_path = file.to_string_lossy().to_string();
+        let canonical = fs::canonicalize(&file).unwrap_or_else(|_| file.clone());
+        if !seen.insert(canonical) {
+            results.push(rejected(&display_path, "重复的文件".to_string()));
+            continue;
+        }
+
+        if let Err(e) = validate_image_path(&display_path) {
+            results.push(rejected(&display_path, e));
+            continue;
+        }
+
+        match enqueue_preprocess(&app, &display_path) {
+            Ok(true) => results.push(DroppedPathResult {
+                path: display_path,
+                accepted: true,
+                reason: None,
+            }),
+            Ok(false) => results.push(rejected(&display_path, "已在预处理队列中".to_string())),
+            Err(e) => results.push(rejected(&display_path, e)),
+        }
+    }
+
+    let accepted = results.iter().filter(|r| r.accepted).count();
+    println!(
+        "[RUST] 拖放处理完成: 接受 {accepted} 个, 拒绝 {} 个",
+        results.len() - accepted
+    );
+
+    Ok(results)
+}
+
+// 列出文件夹中的文件 跳过隐藏文件和子文件夹
+fn list_folder_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("读取文件夹失败: {e}"))?;
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            !path
+                .file_name()
+                .map(|name| name.to_string_lossy().starts_with('.'))
+                .unwrap_or(true)
+        })
+        .collect();
+    // 按文件名排序 让处理顺序和用户在文件管理器里看到的一致
+    files.sort();
+    Ok(files)
+}
+
+fn rejected(path: &str, reason: String) -> DroppedPathResult {
+    DroppedPathResult {
+        path: path.to_string(),
+        accepted: false,
+        reason: Some(reason),
+    }
+}
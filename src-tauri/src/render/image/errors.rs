@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+use super::telemetry::record_telemetry;
+use super::types::TelemetryEvent;
+
+// 面向用户的错误信息本地化
+// 错误码保持稳定 前端按错误码判断错误类型 展示给用户的文案随语言设置变化
+// 目前命令仍然返回 String 错误 错误码以 "[CODE] " 前缀的形式携带在字符串开头
+
+// 指定默认语言的环境变量 (zh / en)
+pub const LOCALE_ENV: &str = "IMAGES_GL_LOCALE";
+
+/// 支持的语言
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    Zh,
+    En,
+}
+
+impl Locale {
+    /// 语言代码 与 LOCALE_ENV 和 set_locale 接受的值一致
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::Zh => "zh",
+            Locale::En => "en",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Locale> {
+        let value = value.trim().to_lowercase();
+        if value.starts_with("zh") {
+            Some(Locale::Zh)
+        } else if value.starts_with("en") {
+            Some(Locale::En)
+        } else {
+            None
+        }
+    }
+}
+
+/// 稳定的错误码 新增错误码只能追加 不能修改已有的名称
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    FileNotFound,
+    UnsupportedFormat,
+    CacheMissing,
+    ChunkMissing,
+    FileOpenFailed,
+    DecodeFailed,
+    UnsupportedLocale,
+    DecoderCrashed,
+    CacheReadOnly,
+    Busy,
+    ChunkNotReady,
+    Cancelled,
+    BatchFull,
+}
+
+impl ErrorCode {
+    /// 错误码字符串 与 serde 序列化结果一致
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::FileNotFound => "FILE_NOT_FOUND",
+            ErrorCode::UnsupportedFormat => "UNSUPPORTED_FORMAT",
+            ErrorCode::CacheMissing => "CACHE_MISSING",
+            ErrorCode::ChunkMissing => "CHUNK_MISSING",
+            ErrorCode::FileOpenFailed => "FILE_OPEN_FAILED",
+            ErrorCode::DecodeFailed => "DECODE_FAILED",
+            ErrorCode::UnsupportedLocale => "UNSUPPORTED_LOCALE",
+            ErrorCode::DecoderCrashed => "DECODER_CRASHED",
+            ErrorCode::CacheReadOnly => "CACHE_READ_ONLY",
+            ErrorCode::Busy => "BUSY",
+            ErrorCode::ChunkNotReady => "CHUNK_NOT_READY",
+            ErrorCode::Cancelled => "CANCELLED",
+            ErrorCode::BatchFull => "BATCH_FULL",
+        }
+    }
+
+    // 文案模板 {name} 会被替换为对应参数
+    fn template(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (ErrorCode::FileNotFound, Locale::Zh) => "图片文件不存在: {path}",
+            (ErrorCode::FileNotFound, Locale::En) => "Image file not found: {path}",
+            (ErrorCode::UnsupportedFormat, Locale::Zh) => {
+                "不支持的图片格式: {format}. 支持的格式: PNG, JPG, JPEG, BMP, TIFF, WEBP"
+            }
+            (ErrorCode::UnsupportedFormat, Locale::En) => {
+                "Unsupported image format: {format}. Supported formats: PNG, JPG, JPEG, BMP, TIFF, WEBP"
+            }
+            (ErrorCode::CacheMissing, Locale::Zh) => {
+                "Chunk 缓存不存在，请先调用 get_image_metadata_for_file 进行预处理"
+            }
+            (ErrorCode::CacheMissing, Locale::En) => {
+                "Chunk cache not found, call get_image_metadata_for_file to preprocess the image first"
+            }
+            (ErrorCode::ChunkMissing, Locale::Zh) => "Chunk 文件不存在: {path}",
+            (ErrorCode::ChunkMissing, Locale::En) => "Chunk file not found: {path}",
+            (ErrorCode::FileOpenFailed, Locale::Zh) => "文件打开失败: {error} (路径: {path})",
+            (ErrorCode::FileOpenFailed, Locale::En) => "Failed to open file: {error} (path: {path})",
+            (ErrorCode::DecodeFailed, Locale::Zh) => "{format}解码失败: {error}",
+            (ErrorCode::DecodeFailed, Locale::En) => "Failed to decode {format}: {error}",
+            (ErrorCode::UnsupportedLocale, Locale::Zh) => "不支持的语言: {locale}, 支持: zh, en",
+            (ErrorCode::UnsupportedLocale, Locale::En) => {
+                "Unsupported locale: {locale}, supported: zh, en"
+            }
+            (ErrorCode::DecoderCrashed, Locale::Zh) => "解码进程异常退出: {status}",
+            (ErrorCode::DecoderCrashed, Locale::En) => "Decoder process crashed: {status}",
+            (ErrorCode::CacheReadOnly, Locale::Zh) => "缓存处于只读模式 不能{operation}",
+            (ErrorCode::CacheReadOnly, Locale::En) => "Cache is read-only: {operation} is disabled",
+            (ErrorCode::Busy, Locale::Zh) => "请求过多（同时处理上限 {limit}） 请稍后重试",
+            (ErrorCode::Busy, Locale::En) => "Too many requests (in-flight limit {limit}), retry later",
+            (ErrorCode::ChunkNotReady, Locale::Zh) => "Chunk ({x}, {y}) 还在生成中 请稍后重试",
+            (ErrorCode::ChunkNotReady, Locale::En) => {
+                "Chunk ({x}, {y}) is still being generated, retry later"
+            }
+            (ErrorCode::Cancelled, Locale::Zh) => "预处理已取消: {path}",
+            (ErrorCode::Cancelled, Locale::En) => "Preprocessing cancelled: {path}",
+            (ErrorCode::BatchFull, Locale::Zh) => {
+                "批量响应超过 {limit} 字节 请单独请求 chunk ({x}, {y})"
+            }
+            (ErrorCode::BatchFull, Locale::En) => {
+                "Batch response exceeds {limit} bytes, request chunk ({x}, {y}) separately"
+            }
+        }
+    }
+}
+
+// 当前语言 用 u8 存储以便无锁读取 0 = zh 1 = en
+static CURRENT_LOCALE: AtomicU8 = AtomicU8::new(0);
+static LOCALE_INIT: OnceLock<()> = OnceLock::new();
+
+/// 获取当前语言 首次调用时读取环境变量作为默认值
+pub fn current_locale() -> Locale {
+    LOCALE_INIT.get_or_init(|| {
+        if let Some(locale) = env::var(LOCALE_ENV).ok().and_then(|v| Locale::parse(&v)) {
+            store_locale(locale);
+        }
+    });
+    match CURRENT_LOCALE.load(Ordering::Relaxed) {
+        1 => Locale::En,
+        _ => Locale::Zh,
+    }
+}
+
+fn store_locale(locale: Locale) {
+    let value = match locale {
+        Locale::Zh => 0,
+        Locale::En => 1,
+    };
+    CURRENT_LOCALE.store(value, Ordering::Relaxed);
+}
+
+/// 生成本地化的错误信息 格式为 "[CODE] 文案"
+/// # Arguments
+/// * `code` - 错误码
+/// * `params` - 模板参数
+/// # Returns
+/// * `String` - 带错误码前缀的错误信息
+pub fn localized_error(code: ErrorCode, params: &[(&str, &dyn Display)]) -> String {
+    let mut message = code.template(current_locale()).to_string();
+    for (name, value) in params {
+        message = message.replace(&format!("{{{name}}}"), &value.to_string());
+    }
+    record_telemetry(TelemetryEvent::Error {
+        code: code.as_str().to_string(),
+    });
+    format!("[{}] {message}", code.as_str())
+}
+
+/// 设置错误信息使用的语言
+#[tauri::command]
+pub fn set_locale(locale: String) -> Result<Locale, String> {
+    // 先触发一次初始化 避免之后读取环境变量覆盖这里的设置
+    current_locale();
+    let parsed = Locale::parse(&locale)
+        .ok_or_else(|| localized_error(ErrorCode::UnsupportedLocale, &[("locale", &locale)]))?;
+    store_locale(parsed);
+    println!("[RUST] 错误信息语言已设置为: {parsed:?}");
+    Ok(parsed)
+}
+
+/// 获取错误信息当前使用的语言
+#[tauri::command]
+pub fn get_locale() -> Result<Locale, String> {
+    Ok(current_locale())
+}
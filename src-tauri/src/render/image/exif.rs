@@ -0,0 +1,191 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+// EXIF 读取：只解析目录需要的拍摄时间和叠加层需要的分辨率 不引入完整的 EXIF 库
+// 支持 JPEG（APP1 段）和 TIFF（文件本身就是 TIFF 结构）
+
+// 最多读取的字节数 JPEG 的 APP1 段在文件开头 大小不超过 64KB
+const MAX_EXIF_READ_BYTES: u64 = 1024 * 1024;
+
+const TAG_X_RESOLUTION: u16 = 0x011A;
+const TAG_RESOLUTION_UNIT: u16 = 0x0128;
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+
+/// 读取图片的拍摄时间 格式为 "YYYY-MM-DD HH:MM:SS"（相机本地时间）
+/// 优先使用 DateTimeOriginal 没有时使用 DateTime 读取失败或没有 EXIF 时返回 None
+pub fn read_capture_time(path: &Path) -> Option<String> {
+    let data = read_file_head(path)?;
+    parse_capture_time(find_tiff_data(&data)?)
+}
+
+/// 读取图片记录的水平分辨率 换算为每个像素的毫米数
+/// 没有记录、单位为"无"或数值无效时返回 None
+pub fn read_pixel_size_mm(path: &Path) -> Option<f64> {
+    let data = read_file_head(path)?;
+    parse_pixel_size_mm(find_tiff_data(&data)?)
+}
+
+fn read_file_head(path: &Path) -> Option<Vec<u8>> {
+    let mut data = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(MAX_EXIF_READ_BYTES)
+        .read_to_end(&mut data)
+        .ok()?;
+    Some(data)
+}
+
+// JPEG 的 EXIF 在 APP1 段中 TIFF 文件本身就是 TIFF 结构
+fn find_tiff_data(data: &[u8]) -> Option<&[u8]> {
+    if data.starts_with(&[0xFF, 0xD8]) {
+        find_jpeg_exif(data)
+    } else {
+        Some(data)
+    }
+}
+
+// 在 JPEG 的段中找到 "Exif\0\0" 开头的 APP1 段 返回其中的 TIFF 数据
+fn find_jpeg_exif(data: &[u8]) -> Option<&[u8]> {
+    let mut position = 2;
+    while position + 4 <= data.len() {
+        if data[position] != 0xFF {
+            return None;
+        }
+        let marker = data[position + 1];
+        // 图像数据开始后不会再有 APP 段
+        if marker == 0xDA || marker == 0xD9 {
+            return None;
+        }
+        let length = u16::from_be_bytes([data[position + 2], data[position + 3]]) as usize;
+        let segment = data.get(position + 4..position + 2 + length)?;
+        if marker == 0xE1 && segment.starts_with(b"Exif\0\0") {
+            return Some(&segment[6..]);
+        }
+        position += 2 + length;
+    }
+    None
+}
+
+// 按 TIFF 头中的字节序读取整数
+struct TiffReader<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> TiffReader<'a> {
+    // 检查 TIFF 头 返回读取器和第一个 IFD 的偏移
+    fn open(data: &'a [u8]) -> Option<(Self, usize)> {
+        let little_endian = match data.get(0..2)? {
+            b"II" => true,
+            b"MM" => false,
+            _ => return None,
+        };
+        let reader = TiffReader {
+            data,
+            little_endian,
+        };
+        if reader.u16_at(2)? != 42 {
+            return None;
+        }
+        let ifd0 = reader.u32_at(4)? as usize;
+        Some((reader, ifd0))
+    }
+
+    fn u16_at(&self, offset: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.data.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    // 在 IFD 中查找标签 返回值字段的偏移和数量
+    fn find_tag(&self, ifd_offset: usize, tag: u16) -> Option<(usize, u32)> {
+        let count = self.u16_at(ifd_offset)? as usize;
+        (0..count)
+            .map(|i| ifd_offset + 2 + i * 12)
+            .find(|&entry| self.u16_at(entry) == Some(tag))
+            .map(|entry| (entry + 8, self.u32_at(entry + 4).unwrap_or(0)))
+    }
+
+    // 读取 ASCII 类型的标签值 长度超过 4 字节时值字段保存偏移
+    fn ascii(&self, ifd_offset: usize, tag: u16) -> Option<String> {
+        let (value_field, count) = self.find_tag(ifd_offset, tag)?;
+        let start = if count > 4 {
+            self.u32_at(value_field)? as usize
+        } else {
+            value_field
+        };
+        let bytes = self.data.get(start..start + count as usize)?;
+        let text = String::from_utf8_lossy(bytes);
+        Some(text.trim_end_matches('\0').trim().to_string())
+    }
+
+    // 读取 SHORT 类型的标签值 单个值直接保存在值字段的前两个字节
+    fn short(&self, ifd_offset: usize, tag: u16) -> Option<u16> {
+        let (value_field, _) = self.find_tag(ifd_offset, tag)?;
+        self.u16_at(value_field)
+    }
+
+    // 读取 RATIONAL 类型的标签值 8 字节超过值字段 值字段保存偏移
+    fn rational(&self, ifd_offset: usize, tag: u16) -> Option<f64> {
+        let (value_field, _) = self.find_tag(ifd_offset, tag)?;
+        let start = self.u32_at(value_field)? as usize;
+        let numerator = self.u32_at(start)?;
+        let denominator = self.u32_at(start + 4)?;
+        (denominator != 0).then(|| numerator as f64 / denominator as f64)
+    }
+}
+
+fn parse_capture_time(tiff: &[u8]) -> Option<String> {
+    let (reader, ifd0) = TiffReader::open(tiff)?;
+
+    let original = reader
+        .find_tag(ifd0, TAG_EXIF_IFD)
+        .and_then(|(value_field, _)| reader.u32_at(value_field))
+        .and_then(|exif_ifd| reader.ascii(exif_ifd as usize, TAG_DATE_TIME_ORIGINAL));
+    original
+        .or_else(|| reader.ascii(ifd0, TAG_DATE_TIME))
+        .and_then(|raw| normalize_exif_time(&raw))
+}
+
+fn parse_pixel_size_mm(tiff: &[u8]) -> Option<f64> {
+    let (reader, ifd0) = TiffReader::open(tiff)?;
+    let resolution = reader.rational(ifd0, TAG_X_RESOLUTION)?;
+    // ResolutionUnit: 1 没有单位 2 英寸（默认） 3 厘米
+    let unit_mm = match reader.short(ifd0, TAG_RESOLUTION_UNIT).unwrap_or(2) {
+        2 => 25.4,
+        3 => 10.0,
+        _ => return None,
+    };
+    (resolution > 0.0).then(|| unit_mm / resolution)
+}
+
+// "YYYY:MM:DD HH:MM:SS" -> "YYYY-MM-DD HH:MM:SS" 全 0 或格式不对的时间视为没有
+fn normalize_exif_time(raw: &str) -> Option<String> {
+    let bytes = raw.as_bytes();
+    let valid = bytes.len() == 19
+        && bytes.iter().enumerate().all(|(i, b)| match i {
+            4 | 7 => *b == b':',
+            10 => *b == b' ',
+            13 | 16 => *b == b':',
+            _ => b.is_ascii_digit(),
+        });
+    if !valid || raw.starts_with("0000") {
+        return None;
+    }
+    Some(format!("{}-{}-{}", &raw[0..4], &raw[5..7], &raw[8..]))
+}
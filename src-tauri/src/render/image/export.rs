@@ -0,0 +1,484 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+
+use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use super::cache::{check_file_cache_exists, fnv1a_hash, image_cache_dir, load_cached_metadata};
+use super::chunk_repair::{regenerate_chunk, validate_chunk_data};
+use super::errors::{localized_error, ErrorCode};
+use super::export_encoder::create_stripe_encoder;
+use super::job_history::record_job;
+use super::jpeg_quality::validate_export_quality;
+use super::notifications::{job_notification, notify_job_finished};
+use super::pyramidal_export::run_pyramidal_export;
+use super::scheduler::run_scheduled;
+use super::tile_archive::run_tile_archive_export;
+use super::types::{
+    ExportCompleted, ExportProgress, ExportQuality, ImageMetadata, ImageRegion, JobKind, TaskKind,
+    TiffCompression, TileArchiveFormat,
+};
+use crate::utils::time::{get_time, Stopwatch};
+
+// 导出进度和导出结束事件
+pub const EXPORT_PROGRESS_EVENT: &str = "export://progress";
+pub const EXPORT_COMPLETED_EVENT: &str = "export://completed";
+
+// 每个条带的行数 条带是断点续传的最小单位
+pub(super) const EXPORT_STRIPE_HEIGHT: u32 = 1024;
+
+// 中间文件目录的后缀 位于输出文件旁边
+const EXPORT_PARTS_SUFFIX: &str = ".parts";
+const EXPORT_CHECKPOINT_FILE: &str = "checkpoint.json";
+
+// 断点信息 参数完全一致时才会从断点继续
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct ExportCheckpoint {
+    file_path: String,
+    region: ImageRegion,
+    stripe_height: u32,
+    completed_stripes: u32,
+}
+
+// 导出任务的种类
+pub(super) enum ExportKind {
+    // 区域导出 支持断点续传 JPEG 输出可以指定质量
+    Region(ImageRegion, Option<ExportQuality>),
+    // 多分辨率瓦片 TIFF
+    PyramidalTiff(TiffCompression),
+    // MBTiles / PMTiles 瓦片包
+    TileArchive(TileArchiveFormat),
+}
+
+pub(super) struct ExportTask {
+    pub app: AppHandle,
+    pub job_id: String,
+    pub file_path: String,
+    pub kind: ExportKind,
+    pub output_path: String,
+    pub cancel: Arc<AtomicBool>,
+}
+
+// 导出队列
+// 单个工作线程按顺序处理 大区域导出本身就会占满磁盘带宽 并发只会更慢
+struct ExportQueue {
+    sender: Mutex<Sender<ExportTask>>,
+    // 已排队或正在执行的任务 值为取消标志
+    jobs: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+static EXPORT_QUEUE: OnceLock<ExportQueue> = OnceLock::new();
+
+fn get_export_queue() -> &'static ExportQueue {
+    EXPORT_QUEUE.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<ExportTask>();
+
+        thread::Builder::new()
+            .name("export-queue".to_string())
+            .spawn(move || {
+                for task in receiver {
+                    let started_ms = get_time() as u64;
+                    let result =
+                        run_scheduled(TaskKind::Export, &task.file_path, || match task.kind {
+                            ExportKind::Region(region, quality) => {
+                                run_export(&task, region, quality)
+                            }
+                            ExportKind::PyramidalTiff(compression) => {
+                                run_pyramidal_export(&task, compression)
+                            }
+                            ExportKind::TileArchive(format) => {
+                                run_tile_archive_export(&task, format)
+                            }
+                        })
+                        .and_then(|result| result);
+                    let payload = match result {
+                        Ok(finished) => ExportCompleted {
+                            job_id: task.job_id.clone(),
+                            output_path: task.output_path.clone(),
+                            cancelled: !finished,
+                            error: None,
+                        },
+                        Err(e) => {
+                            println!("[RUST] 导出失败: {} ({e})", task.output_path);
+                            ExportCompleted {
+                                job_id: task.job_id.clone(),
+                                output_path: task.output_path.clone(),
+                                cancelled: false,
+                                error: Some(e),
+                            }
+                        }
+                    };
+
+                    if let Some(queue) = EXPORT_QUEUE.get() {
+                        if let Ok(mut jobs) = queue.jobs.lock() {
+                            jobs.remove(&task.job_id);
+                        }
+                    }
+
+                    let notification = job_notification(
+                        JobKind::Export,
+                        &task.job_id,
+                        &task.file_path,
+                        Some(&task.output_path),
+                        payload.error.as_deref().map_or(Ok(!payload.cancelled), Err),
+                        started_ms,
+                    );
+                    record_job(&task.app, &notification);
+                    notify_job_finished(&task.app, notification);
+                    if let Err(e) = task.app.emit(EXPORT_COMPLETED_EVENT, payload) {
+                        println!("[RUST] 发送导出完成事件失败: {e}");
+                    }
+                }
+            })
+            .expect("启动导出线程失败");
+
+        ExportQueue {
+            sender: Mutex::new(sender),
+            jobs: Mutex::new(HashMap::new()),
+        }
+    })
+}
+
+/// 导出图片的一个区域
+/// 任务加入后台导出队列 进度通过 export://progress 事件通知 结束时发出 export://completed
+/// 输出按条带写入输出文件旁边的 .parts 目录 每完成一个条带就记录断点
+/// 取消或程序崩溃后 用相同参数再次调用会从断点继续 而不是从头开始
+/// # Arguments
+/// * `file_path` - 图片文件路径（需要已经预处理）
+/// * `region` - 导出区域
+/// * `output_path` - 输出文件路径 格式由扩展名决定
+/// * `quality` - JPEG 质量 固定值或按目标 SSIM 自动选择 为空时使用默认质量
+/// # Returns
+/// * `Result<String, String>` - 任务 ID（由输出路径决定）
+#[tauri::command]
+pub fn export_region(
+    app: AppHandle,
+    file_path: String,
+    region: ImageRegion,
+    output_path: String,
+    quality: Option<ExportQuality>,
+) -> Result<String, String> {
+    if !check_file_cache_exists(&file_path) {
+        return Err(localized_error(ErrorCode::CacheMissing, &[]));
+    }
+    let metadata = load_cached_metadata(&file_path)?;
+    validate_region(&metadata, &region)?;
+    let format = image::ImageFormat::from_path(&output_path)
+        .map_err(|e| format!("无法根据扩展名确定输出格式: {e}"))?;
+    if let Some(quality) = &quality {
+        // WebP 编码器只支持无损 其他格式也没有质量参数
+        if format != image::ImageFormat::Jpeg {
+            return Err(format!("只有 JPEG 导出支持质量设置: {output_path}"));
+        }
+        validate_export_quality(quality)?;
+    }
+
+    enqueue_export(
+        app,
+        file_path,
+        output_path,
+        ExportKind::Region(region, quality),
+    )
+}
+
+/// 把导出任务加入后台导出队列
+/// # Returns
+/// * `Result<String, String>` - 任务 ID（由输出路径决定）
+pub(super) fn enqueue_export(
+    app: AppHandle,
+    file_path: String,
+    output_path: String,
+    kind: ExportKind,
+) -> Result<String, String> {
+    let job_id = format!("{:016x}", fnv1a_hash(output_path.as_bytes()));
+    let queue = get_export_queue();
+
+    let mut jobs = queue
+        .jobs
+        .lock()
+        .map_err(|e| format!("导出队列加锁失败: {e}"))?;
+    if jobs.contains_key(&job_id) {
+        return Err(format!("已有导出任务正在写入: {output_path}"));
+    }
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    jobs.insert(job_id.clone(), cancel.clone());
+
+    let sender = queue
+        .sender
+        .lock()
+        .map_err(|e| format!("导出队列加锁失败: {e}"))?;
+    if let Err(e) = sender.send(ExportTask {
+        app,
+        job_id: job_id.clone(),
+        file_path,
+        kind,
+        output_path,
+        cancel,
+    }) {
+        jobs.remove(&job_id);
+        return Err(format!("加入导出队列失败: {e}"));
+    }
+
+    Ok(job_id)
+}
+
+/// 取消导出任务 已完成的条带会保留 之后可以从断点继续
+#[tauri::command]
+pub fn cancel_export(job_id: String) -> Result<(), String> {
+    let jobs = get_export_queue()
+        .jobs
+        .lock()
+        .map_err(|e| format!("导出队列加锁失败: {e}"))?;
+    match jobs.get(&job_id) {
+        Some(cancel) => {
+            cancel.store(true, Ordering::Relaxed);
+            println!("[RUST] 已请求取消导出: {job_id}");
+            Ok(())
+        }
+        None => Err(format!("导出任务不存在: {job_id}")),
+    }
+}
+
+/// 导出队列是否空闲（没有排队或正在执行的任务）
+pub fn is_export_queue_idle() -> bool {
+    EXPORT_QUEUE
+        .get()
+        .and_then(|queue| queue.jobs.lock().ok().map(|jobs| jobs.is_empty()))
+        .unwrap_or(true)
+}
+
+/// 导出任务是否还在排队或执行
+pub fn is_export_job_active(job_id: &str) -> bool {
+    EXPORT_QUEUE
+        .get()
+        .and_then(|queue| queue.jobs.lock().ok().map(|jobs| jobs.contains_key(job_id)))
+        .unwrap_or(false)
+}
+
+/// 发送导出进度事件
+pub(super) fn emit_export_progress(task: &ExportTask, completed_stripes: u32, total_stripes: u32) {
+    let progress = ExportProgress {
+        job_id: task.job_id.clone(),
+        output_path: task.output_path.clone(),
+        completed_stripes,
+        total_stripes,
+    };
+    if let Err(e) = task.app.emit(EXPORT_PROGRESS_EVENT, progress) {
+        println!("[RUST] 发送导出进度事件失败: {e}");
+    }
+}
+
+pub(super) fn validate_region(
+    metadata: &ImageMetadata,
+    region: &ImageRegion,
+) -> Result<(), String> {
+    let right = region.x as u64 + region.width as u64;
+    let bottom = region.y as u64 + region.height as u64;
+    if region.width == 0
+        || region.height == 0
+        || right > metadata.total_width as u64
+        || bottom > metadata.total_height as u64
+    {
+        return Err(format!(
+            "导出区域超出图片范围: {region:?} (图片 {}x{})",
+            metadata.total_width, metadata.total_height
+        ));
+    }
+    Ok(())
+}
+
+fn parts_dir(output_path: &str) -> PathBuf {
+    PathBuf::from(format!("{output_path}{EXPORT_PARTS_SUFFIX}"))
+}
+
+fn stripe_path(parts_dir: &Path, index: u32) -> PathBuf {
+    parts_dir.join(format!("stripe_{index}.raw"))
+}
+
+// 先写临时文件再重命名 保证崩溃时不会留下写了一半的文件
+fn write_atomically(path: &Path, data: &[u8]) -> Result<(), String> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, data).map_err(|e| format!("写入文件失败: {e} ({tmp:?})"))?;
+    fs::rename(&tmp, path).map_err(|e| format!("重命名文件失败: {e} ({path:?})"))
+}
+
+fn load_checkpoint(parts_dir: &Path) -> Option<ExportCheckpoint> {
+    let content = fs::read_to_string(parts_dir.join(EXPORT_CHECKPOINT_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn save_checkpoint(parts_dir: &Path, checkpoint: &ExportCheckpoint) -> Result<(), String> {
+    let json =
+        serde_json::to_string_pretty(checkpoint).map_err(|e| format!("序列化导出断点失败: {e}"))?;
+    write_atomically(&parts_dir.join(EXPORT_CHECKPOINT_FILE), json.as_bytes())
+}
+
+/// 执行导出任务
+/// # Returns
+/// * `Result<bool, String>` - true 表示完成 false 表示被取消
+fn run_export(
+    task: &ExportTask,
+    region: ImageRegion,
+    quality: Option<ExportQuality>,
+) -> Result<bool, String> {
+    let stopwatch = Stopwatch::start();
+    let metadata = load_cached_metadata(&task.file_path)?;
+    validate_region(&metadata, &region)?;
+
+    let parts_dir = parts_dir(&task.output_path);
+    let total_stripes = region.height.div_ceil(EXPORT_STRIPE_HEIGHT);
+
+    // 参数一致时从断点继续 否则清空重新开始
+    let fresh = ExportCheckpoint {
+        file_path: task.file_path.clone(),
+        region,
+        stripe_height: EXPORT_STRIPE_HEIGHT,
+        completed_stripes: 0,
+    };
+    let mut checkpoint = match load_checkpoint(&parts_dir) {
+        Some(saved)
+            if ExportCheckpoint {
+                completed_stripes: 0,
+                ..saved.clone()
+            } == fresh =>
+        {
+            println!(
+                "[RUST] 从断点继续导出: {} ({}/{total_stripes})",
+                task.output_path, saved.completed_stripes
+            );
+            saved
+        }
+        _ => {
+            if parts_dir.exists() {
+                fs::remove_dir_all(&parts_dir).map_err(|e| format!("清理导出中间文件失败: {e}"))?;
+            }
+            fresh
+        }
+    };
+    fs::create_dir_all(&parts_dir).map_err(|e| format!("创建导出中间目录失败: {e}"))?;
+
+    while checkpoint.completed_stripes < total_stripes {
+        if task.cancel.load(Ordering::Relaxed) {
+            println!(
+                "[RUST] 导出已取消: {} ({}/{total_stripes})",
+                task.output_path, checkpoint.completed_stripes
+            );
+            return Ok(false);
+        }
+
+        let index = checkpoint.completed_stripes;
+        let stripe_y = region.y + index * EXPORT_STRIPE_HEIGHT;
+        let stripe_height = EXPORT_STRIPE_HEIGHT.min(region.y + region.height - stripe_y);
+        let stripe = ImageRegion {
+            x: region.x,
+            y: stripe_y,
+            width: region.width,
+            height: stripe_height,
+        };
+
+        let pixels = compose_region(&task.file_path, &metadata, &stripe)?;
+        write_atomically(&stripe_path(&parts_dir, index), &pixels)?;
+
+        checkpoint.completed_stripes += 1;
+        save_checkpoint(&parts_dir, &checkpoint)?;
+
+        emit_export_progress(task, checkpoint.completed_stripes, total_stripes);
+    }
+
+    write_output(
+        &task.output_path,
+        &parts_dir,
+        region,
+        total_stripes,
+        quality,
+    )?;
+    fs::remove_dir_all(&parts_dir).map_err(|e| format!("清理导出中间文件失败: {e}"))?;
+
+    println!(
+        "[RUST] 导出完成: {} {}x{} (耗时: {}ms)",
+        task.output_path,
+        region.width,
+        region.height,
+        stopwatch.elapsed_ms()
+    );
+    Ok(true)
+}
+
+/// 从缓存的 chunk 中拼出一个区域的 RGBA 像素
+/// chunk 损坏时从源图片重新生成
+pub fn compose_region(
+    file_path: &str,
+    metadata: &ImageMetadata,
+    region: &ImageRegion,
+) -> Result<Vec<u8>, String> {
+    let cache_dir = image_cache_dir(file_path);
+    let row_len = region.width as usize * 4;
+    let mut pixels = vec![0u8; row_len * region.height as usize];
+
+    let right = region.x + region.width;
+    let bottom = region.y + region.height;
+    let overlapping = metadata.chunks.iter().filter(|c| {
+        c.x < right && c.x + c.width > region.x && c.y < bottom && c.y + c.height > region.y
+    });
+
+    for info in overlapping {
+        let chunk_path = cache_dir.join(format!("chunk_{}_{}.bin", info.chunk_x, info.chunk_y));
+        let mmap = match map_chunk(&chunk_path) {
+            Ok(mmap) => mmap,
+            Err(e) => {
+                println!("[RUST] 导出时发现损坏的 chunk，重新生成: {e}");
+                regenerate_chunk(file_path, info.chunk_x, info.chunk_y)?;
+                map_chunk(&chunk_path)?
+            }
+        };
+        let data = &mmap[8..];
+
+        // 区域与 chunk 的交集
+        let x0 = region.x.max(info.x);
+        let x1 = right.min(info.x + info.width);
+        let y0 = region.y.max(info.y);
+        let y1 = bottom.min(info.y + info.height);
+        let copy_len = (x1 - x0) as usize * 4;
+
+        for y in y0..y1 {
+            let src = ((y - info.y) as usize * info.width as usize + (x0 - info.x) as usize) * 4;
+            let dst = (y - region.y) as usize * row_len + (x0 - region.x) as usize * 4;
+            pixels[dst..dst + copy_len].copy_from_slice(&data[src..src + copy_len]);
+        }
+    }
+
+    Ok(pixels)
+}
+
+pub(super) fn map_chunk(path: &Path) -> Result<Mmap, String> {
+    let file = fs::File::open(path).map_err(|e| format!("打开 chunk 文件失败: {e} ({path:?})"))?;
+    let mmap =
+        unsafe { Mmap::map(&file) }.map_err(|e| format!("映射 chunk 文件失败: {e} ({path:?})"))?;
+    validate_chunk_data(&mmap)?;
+    Ok(mmap)
+}
+
+/// 把所有条带依次交给流式编码器 写出最终的输出文件
+/// 内存中同时只有一个条带 输出可以远大于内存
+fn write_output(
+    output_path: &str,
+    parts_dir: &Path,
+    region: ImageRegion,
+    total_stripes: u32,
+    quality: Option<ExportQuality>,
+) -> Result<(), String> {
+    let mut encoder = create_stripe_encoder(output_path, region.width, region.height, quality)?;
+    for index in 0..total_stripes {
+        let stripe = fs::read(stripe_path(parts_dir, index))
+            .map_err(|e| format!("读取导出条带失败: {e}"))?;
+        encoder.write_rows(&stripe)?;
+    }
+    encoder.finish()
+}
@@ -0,0 +1,565 @@
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use image::codecs::jpeg::JpegEncoder;
+use image::{ColorType, ImageFormat};
+
+use super::jpeg_quality::{encode_jpeg, resolve_jpeg_quality};
+use super::types::{ExportQuality, TiffCompression};
+
+// 导出使用的流式编码器
+// 按条带（若干完整的行）依次写入 内存中只保留当前条带 输出可以远大于内存
+// PNG 和 TIFF 为流式实现 其他格式退回到整图编码
+
+// TIFF 瓦片尺寸
+pub const TIFF_TILE_SIZE: u32 = 256;
+
+// JPEG 压缩瓦片的质量
+const TIFF_JPEG_QUALITY: u8 = 90;
+
+// 超过这个大小时使用 BigTIFF 经典 TIFF 的偏移量只有 32 位
+const CLASSIC_TIFF_LIMIT: u64 = u32::MAX as u64 - (64 << 20);
+
+/// 按条带写入图片的编码器
+pub trait StripeEncoder {
+    /// 写入若干完整的行 RGBA 格式
+    fn write_rows(&mut self, pixels: &[u8]) -> Result<(), String>;
+
+    /// 所有行写完后调用 完成文件
+    fn finish(self: Box<Self>) -> Result<(), String>;
+}
+
+/// 根据输出路径的扩展名创建编码器
+/// # Arguments
+/// * `output_path` - 输出文件路径
+/// * `width` - 图片宽度
+/// * `height` - 图片高度
+/// * `quality` - JPEG 的质量 其他格式忽略
+pub fn create_stripe_encoder(
+    output_path: &str,
+    width: u32,
+    height: u32,
+    quality: Option<ExportQuality>,
+) -> Result<Box<dyn StripeEncoder>, String> {
+    let format = ImageFormat::from_path(output_path)
+        .map_err(|e| format!("无法根据扩展名确定输出格式: {e}"))?;
+
+    match format {
+        ImageFormat::Png => Ok(Box::new(PngStripeEncoder::new(output_path, width, height)?)),
+        ImageFormat::Tiff => Ok(Box::new(TiffStripeEncoder::new(
+            output_path,
+            width,
+            height,
+        )?)),
+        _ => Ok(Box::new(BufferedEncoder {
+            output_path: output_path.to_string(),
+            format,
+            width,
+            height,
+            quality,
+            pixels: Vec::new(),
+        })),
+    }
+}
+
+fn create_output(output_path: &str) -> Result<BufWriter<File>, String> {
+    let file = File::create(Path::new(output_path))
+        .map_err(|e| format!("创建导出文件失败: {e} ({output_path})"))?;
+    Ok(BufWriter::new(file))
+}
+
+// PNG 本身按行编码 直接用 png 库的流式写入
+struct PngStripeEncoder {
+    writer: png::StreamWriter<'static, BufWriter<File>>,
+}
+
+impl PngStripeEncoder {
+    fn new(output_path: &str, width: u32, height: u32) -> Result<Self, String> {
+        let mut encoder = png::Encoder::new(create_output(output_path)?, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let writer = encoder
+            .write_header()
+            .and_then(|w| w.into_stream_writer())
+            .map_err(|e| format!("写入 PNG 头失败: {e}"))?;
+        Ok(Self { writer })
+    }
+}
+
+impl StripeEncoder for PngStripeEncoder {
+    fn write_rows(&mut self, pixels: &[u8]) -> Result<(), String> {
+        self.writer
+            .write_all(pixels)
+            .map_err(|e| format!("写入 PNG 数据失败: {e}"))
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), String> {
+        self.writer
+            .finish()
+            .map_err(|e| format!("完成 PNG 文件失败: {e}"))
+    }
+}
+
+// 单层瓦片 TIFF 导出
+struct TiffStripeEncoder {
+    tiff: TiffWriter,
+}
+
+impl TiffStripeEncoder {
+    fn new(output_path: &str, width: u32, height: u32) -> Result<Self, String> {
+        Ok(Self {
+            tiff: TiffWriter::create(output_path, &[(width, height)], TiffCompression::None)?,
+        })
+    }
+}
+
+impl StripeEncoder for TiffStripeEncoder {
+    fn write_rows(&mut self, pixels: &[u8]) -> Result<(), String> {
+        self.tiff.write_rows(0, pixels)
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), String> {
+        self.tiff.finish()
+    }
+}
+
+// 每一层的写入状态
+struct TiffLevel {
+    width: u32,
+    height: u32,
+    // 还没凑满一行瓦片的像素行
+    pending_rows: Vec<u8>,
+    rows_written: u32,
+    tile_offsets: Vec<u64>,
+    tile_byte_counts: Vec<u64>,
+}
+
+/// 瓦片 TIFF 写入器 支持多层（金字塔）和压缩
+/// 每一层攒够一行瓦片就压缩写出 各层的瓦片可以交错写入同一个文件
+/// 文件布局：文件头 -> 瓦片数据 -> 瓦片偏移表 -> 各层 IFD 最后回写文件头中的第一个 IFD 偏移
+pub struct TiffWriter {
+    writer: BufWriter<File>,
+    big: bool,
+    compression: TiffCompression,
+    levels: Vec<TiffLevel>,
+    position: u64,
+    // 写入第 0 层的物理分辨率（每英寸像素数）
+    resolution_dpi: Option<u32>,
+}
+
+impl TiffWriter {
+    /// 创建 TIFF 文件
+    /// # Arguments
+    /// * `output_path` - 输出文件路径
+    /// * `level_sizes` - 每一层的尺寸 第 0 层为原图
+    /// * `compression` - 瓦片压缩方式
+    pub fn create(
+        output_path: &str,
+        level_sizes: &[(u32, u32)],
+        compression: TiffCompression,
+    ) -> Result<Self, String> {
+        let tile_bytes = TIFF_TILE_SIZE as u64 * TIFF_TILE_SIZE as u64 * 4;
+        let total_bytes: u64 = level_sizes
+            .iter()
+            .map(|&(w, h)| w.div_ceil(TIFF_TILE_SIZE) as u64 * h.div_ceil(TIFF_TILE_SIZE) as u64)
+            .sum::<u64>()
+            * tile_bytes;
+        let big = total_bytes > CLASSIC_TIFF_LIMIT;
+
+        let mut tiff = Self {
+            writer: create_output(output_path)?,
+            big,
+            compression,
+            levels: level_sizes
+                .iter()
+                .map(|&(width, height)| TiffLevel {
+                    width,
+                    height,
+                    pending_rows: Vec::new(),
+                    rows_written: 0,
+                    tile_offsets: Vec::new(),
+                    tile_byte_counts: Vec::new(),
+                })
+                .collect(),
+            position: 0,
+            resolution_dpi: None,
+        };
+
+        // 文件头 IFD 偏移先写 0 结束时回写
+        if big {
+            tiff.write(b"II")?;
+            tiff.write(&43u16.to_le_bytes())?;
+            tiff.write(&8u16.to_le_bytes())?;
+            tiff.write(&0u16.to_le_bytes())?;
+            tiff.write(&0u64.to_le_bytes())?;
+        } else {
+            tiff.write(b"II")?;
+            tiff.write(&42u16.to_le_bytes())?;
+            tiff.write(&0u32.to_le_bytes())?;
+        }
+        Ok(tiff)
+    }
+
+    /// 设置物理分辨率 打印时按这个分辨率换算成实际尺寸
+    pub fn set_resolution(&mut self, dpi: u32) {
+        self.resolution_dpi = Some(dpi);
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), String> {
+        self.writer
+            .write_all(data)
+            .map_err(|e| format!("写入 TIFF 数据失败: {e}"))?;
+        self.position += data.len() as u64;
+        Ok(())
+    }
+
+    // TIFF 要求数据从字边界开始
+    fn align(&mut self) -> Result<(), String> {
+        if !self.position.is_multiple_of(2) {
+            self.write(&[0])?;
+        }
+        Ok(())
+    }
+
+    /// 向指定层写入若干完整的行 RGBA 格式
+    pub fn write_rows(&mut self, level: usize, pixels: &[u8]) -> Result<(), String> {
+        let width = self
+            .levels
+            .get(level)
+            .ok_or_else(|| format!("TIFF 层级不存在: {level}"))?
+            .width;
+        let row_len = width as usize * 4;
+        let tile_row_bytes = row_len * TIFF_TILE_SIZE as usize;
+
+        let mut remaining = pixels;
+        while !remaining.is_empty() {
+            let state = &mut self.levels[level];
+            let take = (tile_row_bytes - state.pending_rows.len()).min(remaining.len());
+            state.pending_rows.extend_from_slice(&remaining[..take]);
+            remaining = &remaining[take..];
+            if state.pending_rows.len() == tile_row_bytes {
+                self.flush_tile_row(level)?;
+            }
+        }
+        self.levels[level].rows_written += (pixels.len() / row_len) as u32;
+        Ok(())
+    }
+
+    // 把某一层 pending_rows 中的行切成瓦片写出 不足一个瓦片的部分补 0
+    fn flush_tile_row(&mut self, level: usize) -> Result<(), String> {
+        let pending = std::mem::take(&mut self.levels[level].pending_rows);
+        let width = self.levels[level].width;
+        let row_len = width as usize * 4;
+        let rows = pending.len() / row_len;
+        let tile_row_len = TIFF_TILE_SIZE as usize * 4;
+        let mut tile = vec![0u8; tile_row_len * TIFF_TILE_SIZE as usize];
+
+        for tile_x in 0..width.div_ceil(TIFF_TILE_SIZE) {
+            let x0 = (tile_x * TIFF_TILE_SIZE) as usize * 4;
+            let copy_len = tile_row_len.min(row_len - x0);
+            tile.fill(0);
+            for y in 0..rows {
+                let src = y * row_len + x0;
+                tile[y * tile_row_len..y * tile_row_len + copy_len]
+                    .copy_from_slice(&pending[src..src + copy_len]);
+            }
+
+            let encoded = encode_tile(&tile, self.compression)?;
+            let offset = self.position;
+            self.write(&encoded)?;
+            let state = &mut self.levels[level];
+            state.tile_offsets.push(offset);
+            state.tile_byte_counts.push(encoded.len() as u64);
+        }
+        Ok(())
+    }
+
+    /// 所有层写完后调用 写出各层 IFD 完成文件
+    pub fn finish(mut self) -> Result<(), String> {
+        for level in 0..self.levels.len() {
+            let state = &self.levels[level];
+            if state.rows_written != state.height {
+                return Err(format!(
+                    "TIFF 第 {level} 层行数不匹配: 写入 {} 行 期望 {} 行",
+                    state.rows_written, state.height
+                ));
+            }
+            if !state.pending_rows.is_empty() {
+                self.flush_tile_row(level)?;
+            }
+        }
+
+        // 先写出所有 IFD 的内容 记录每个 IFD 中 "下一个 IFD" 字段的位置 最后统一回写
+        let mut ifd_offsets = Vec::with_capacity(self.levels.len());
+        let mut next_fields = Vec::with_capacity(self.levels.len());
+        for level in 0..self.levels.len() {
+            let entries = self.level_entries(level);
+            let (ifd_offset, next_field) = self.write_ifd(&entries)?;
+            ifd_offsets.push(ifd_offset);
+            next_fields.push(next_field);
+        }
+
+        // 文件头指向第 0 层 每一层指向下一层
+        let header_field = if self.big { 8 } else { 4 };
+        let mut links = vec![(header_field, ifd_offsets[0])];
+        for (i, &field) in next_fields.iter().enumerate() {
+            links.push((field, ifd_offsets.get(i + 1).copied().unwrap_or(0)));
+        }
+        for (field, value) in links {
+            let bytes = if self.big {
+                value.to_le_bytes().to_vec()
+            } else {
+                (value as u32).to_le_bytes().to_vec()
+            };
+            self.writer
+                .seek(SeekFrom::Start(field))
+                .and_then(|_| self.writer.write_all(&bytes))
+                .map_err(|e| format!("写入 TIFF IFD 偏移失败: {e}"))?;
+        }
+        self.writer
+            .flush()
+            .map_err(|e| format!("写入 TIFF 文件失败: {e}"))
+    }
+
+    // 一层的 IFD 项 按标签升序排列
+    fn level_entries(&self, level: usize) -> Vec<TiffEntry> {
+        let state = &self.levels[level];
+        let tile_count = state.tile_offsets.len() as u64;
+        let (offset_type, offsets, counts): (u16, Vec<u8>, Vec<u8>) = if self.big {
+            (
+                TIFF_LONG8,
+                state
+                    .tile_offsets
+                    .iter()
+                    .flat_map(|o| o.to_le_bytes())
+                    .collect(),
+                state
+                    .tile_byte_counts
+                    .iter()
+                    .flat_map(|c| c.to_le_bytes())
+                    .collect(),
+            )
+        } else {
+            (
+                TIFF_LONG,
+                state
+                    .tile_offsets
+                    .iter()
+                    .flat_map(|o| (*o as u32).to_le_bytes())
+                    .collect(),
+                state
+                    .tile_byte_counts
+                    .iter()
+                    .flat_map(|c| (*c as u32).to_le_bytes())
+                    .collect(),
+            )
+        };
+
+        // JPEG 不支持 alpha 只写 RGB 三个通道 按 YCbCr 编码
+        let is_jpeg = self.compression == TiffCompression::Jpeg;
+        let samples: u16 = if is_jpeg { 3 } else { 4 };
+        let compression_code: u16 = match self.compression {
+            TiffCompression::None => 1,
+            TiffCompression::Jpeg => 7,
+            TiffCompression::Deflate => 8,
+        };
+        let photometric: u16 = if is_jpeg { 6 } else { 2 };
+
+        let mut entries = Vec::new();
+        // 第 0 层之外都是缩小的图像
+        if level > 0 {
+            entries.push(TiffEntry::long(254, 1));
+        }
+        entries.push(TiffEntry::long(256, state.width));
+        entries.push(TiffEntry::long(257, state.height));
+        entries.push(TiffEntry::shorts(258, &vec![8; samples as usize]));
+        entries.push(TiffEntry::shorts(259, &[compression_code]));
+        entries.push(TiffEntry::shorts(262, &[photometric]));
+        entries.push(TiffEntry::shorts(277, &[samples]));
+        let resolution = self.resolution_dpi.filter(|_| level == 0);
+        if let Some(dpi) = resolution {
+            entries.push(TiffEntry::rational(282, dpi, 1));
+            entries.push(TiffEntry::rational(283, dpi, 1));
+        }
+        // 交错存储
+        entries.push(TiffEntry::shorts(284, &[1]));
+        if resolution.is_some() {
+            // 分辨率单位为英寸
+            entries.push(TiffEntry::shorts(296, &[2]));
+        }
+        entries.push(TiffEntry::long(322, TIFF_TILE_SIZE));
+        entries.push(TiffEntry::long(323, TIFF_TILE_SIZE));
+        entries.push(TiffEntry {
+            tag: 324,
+            field_type: offset_type,
+            count: tile_count,
+            data: offsets,
+        });
+        entries.push(TiffEntry {
+            tag: 325,
+            field_type: offset_type,
+            count: tile_count,
+            data: counts,
+        });
+        if !is_jpeg {
+            // 第四个通道为非预乘 alpha
+            entries.push(TiffEntry::shorts(338, &[2]));
+        } else {
+            // image 的 JPEG 编码器不对色度降采样（4:4:4） 与默认值 [2, 2] 不同 需要写明
+            entries.push(TiffEntry::shorts(530, &[1, 1]));
+        }
+        entries
+    }
+
+    /// 写出一个 IFD
+    /// # Returns
+    /// * `Result<(u64, u64), String>` - IFD 的偏移以及其中 "下一个 IFD" 字段的偏移
+    fn write_ifd(&mut self, entries: &[TiffEntry]) -> Result<(u64, u64), String> {
+        // 放不进 IFD 项的数据先写在 IFD 之前
+        let inline_size = if self.big { 8 } else { 4 };
+        let mut external_offsets = Vec::with_capacity(entries.len());
+        for entry in entries {
+            if entry.data.len() > inline_size {
+                self.align()?;
+                external_offsets.push(Some(self.position));
+                self.write(&entry.data)?;
+            } else {
+                external_offsets.push(None);
+            }
+        }
+
+        self.align()?;
+        let ifd_offset = self.position;
+        if self.big {
+            self.write(&(entries.len() as u64).to_le_bytes())?;
+        } else {
+            self.write(&(entries.len() as u16).to_le_bytes())?;
+        }
+        for (entry, external) in entries.iter().zip(external_offsets) {
+            self.write(&entry.tag.to_le_bytes())?;
+            self.write(&entry.field_type.to_le_bytes())?;
+            if self.big {
+                self.write(&entry.count.to_le_bytes())?;
+            } else {
+                self.write(&(entry.count as u32).to_le_bytes())?;
+            }
+            let mut value = match external {
+                Some(offset) if self.big => offset.to_le_bytes().to_vec(),
+                Some(offset) => (offset as u32).to_le_bytes().to_vec(),
+                None => entry.data.clone(),
+            };
+            value.resize(inline_size, 0);
+            self.write(&value)?;
+        }
+
+        let next_field = self.position;
+        self.write(&vec![0u8; inline_size])?;
+        Ok((ifd_offset, next_field))
+    }
+}
+
+// 按压缩方式编码一个 RGBA 瓦片
+fn encode_tile(tile: &[u8], compression: TiffCompression) -> Result<Vec<u8>, String> {
+    match compression {
+        TiffCompression::None => Ok(tile.to_vec()),
+        TiffCompression::Deflate => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder
+                .write_all(tile)
+                .and_then(|_| encoder.finish())
+                .map_err(|e| format!("压缩 TIFF 瓦片失败: {e}"))
+        }
+        TiffCompression::Jpeg => {
+            let rgb: Vec<u8> = tile
+                .chunks_exact(4)
+                .flat_map(|p| [p[0], p[1], p[2]])
+                .collect();
+            let mut output = Vec::new();
+            JpegEncoder::new_with_quality(&mut output, TIFF_JPEG_QUALITY)
+                .encode(&rgb, TIFF_TILE_SIZE, TIFF_TILE_SIZE, ColorType::Rgb8)
+                .map_err(|e| format!("JPEG 编码 TIFF 瓦片失败: {e}"))?;
+            Ok(output)
+        }
+    }
+}
+
+// IFD 中的一项 数据为小端序字节
+struct TiffEntry {
+    tag: u16,
+    field_type: u16,
+    count: u64,
+    data: Vec<u8>,
+}
+
+impl TiffEntry {
+    fn long(tag: u16, value: u32) -> Self {
+        Self {
+            tag,
+            field_type: TIFF_LONG,
+            count: 1,
+            data: value.to_le_bytes().to_vec(),
+        }
+    }
+
+    fn shorts(tag: u16, values: &[u16]) -> Self {
+        Self {
+            tag,
+            field_type: TIFF_SHORT,
+            count: values.len() as u64,
+            data: values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+        }
+    }
+
+    fn rational(tag: u16, numerator: u32, denominator: u32) -> Self {
+        Self {
+            tag,
+            field_type: TIFF_RATIONAL,
+            count: 1,
+            data: [numerator.to_le_bytes(), denominator.to_le_bytes()].concat(),
+        }
+    }
+}
+
+const TIFF_SHORT: u16 = 3;
+const TIFF_LONG: u16 = 4;
+const TIFF_RATIONAL: u16 = 5;
+const TIFF_LONG8: u16 = 16;
+
+// 其他格式的编码器需要完整的图片 先收集所有行再编码
+struct BufferedEncoder {
+    output_path: String,
+    format: ImageFormat,
+    width: u32,
+    height: u32,
+    quality: Option<ExportQuality>,
+    pixels: Vec<u8>,
+}
+
+impl StripeEncoder for BufferedEncoder {
+    fn write_rows(&mut self, pixels: &[u8]) -> Result<(), String> {
+        self.pixels.extend_from_slice(pixels);
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), String> {
+        // JPEG 按指定或自动选择的质量编码 image 库默认的质量偏低
+        if self.format == ImageFormat::Jpeg {
+            let quality =
+                resolve_jpeg_quality(&self.pixels, self.width, self.height, self.quality)?;
+            let data = encode_jpeg(&self.pixels, self.width, self.height, quality)?;
+            return std::fs::write(&self.output_path, data)
+                .map_err(|e| format!("写入导出文件失败: {e}"));
+        }
+        image::save_buffer(
+            &self.output_path,
+            &self.pixels,
+            self.width,
+            self.height,
+            image::ColorType::Rgba8,
+        )
+        .map_err(|e| format!("写入导出文件失败: {e}"))
+    }
+}
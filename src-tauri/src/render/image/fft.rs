@@ -0,0 +1,160 @@
+use std::f64::consts::PI;
+
+use rayon::prelude::*;
+use tauri::ipc::Response;
+
+use super::cache::load_cached_metadata;
+use super::export::{compose_region, validate_region};
+use super::types::ImageRegion;
+use crate::utils::time::Stopwatch;
+
+// 频谱分析：对区域的亮度做二维 FFT 返回对数幅度谱 零频率在中心
+// 显微镜用户据此检查对焦和像散 摄影师用来查找传感器的周期性伪影
+// 区域补零到 2 的幂 超过最大尺寸时取中心部分（不缩小 保留高频信息）
+
+// 频谱的最大边长
+const MAX_FFT_SIZE: u32 = 1024;
+
+/// 计算区域的幅度谱（需要先完成预处理）
+/// 先减去平均亮度并加汉宁窗 减少区域边缘造成的十字形伪影
+/// 数据格式与 chunk 相同：宽度(4字节) + 高度(4字节) + RGBA 数据 灰度为 log(1 + 幅度) 归一化到 0-255
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `rect` - 区域 超过 1024x1024 时只分析中心的 1024x1024
+#[tauri::command]
+pub fn get_fft(file_path: String, rect: ImageRegion) -> Result<Response, String> {
+    let stopwatch = Stopwatch::start();
+    let metadata = load_cached_metadata(&file_path)?;
+    validate_region(&metadata, &rect)?;
+
+    let region = ImageRegion {
+        x: rect.x + rect.width.saturating_sub(MAX_FFT_SIZE) / 2,
+        y: rect.y + rect.height.saturating_sub(MAX_FFT_SIZE) / 2,
+        width: rect.width.min(MAX_FFT_SIZE),
+        height: rect.height.min(MAX_FFT_SIZE),
+    };
+    let pixels = compose_region(&file_path, &metadata, &region)?;
+    let size = region.width.max(region.height).next_power_of_two() as usize;
+    let spectrum = magnitude_spectrum(&pixels, region.width as usize, region.height as usize, size);
+
+    let mut output = Vec::with_capacity(8 + size * size * 4);
+    output.extend_from_slice(&(size as u32).to_be_bytes());
+    output.extend_from_slice(&(size as u32).to_be_bytes());
+    for value in spectrum {
+        output.extend_from_slice(&[value, value, value, 255]);
+    }
+    println!(
+        "[RUST] 频谱计算完成: {}x{} -> {size}x{size} (耗时: {}ms)",
+        region.width,
+        region.height,
+        stopwatch.elapsed_ms()
+    );
+    Ok(Response::new(output))
+}
+
+// 计算 size x size 的对数幅度谱 区域放在左上角 其余补零
+fn magnitude_spectrum(pixels: &[u8], width: usize, height: usize, size: usize) -> Vec<u8> {
+    let luma: Vec<f64> = pixels
+        .chunks_exact(4)
+        .map(|p| 0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64)
+        .collect();
+    let mean = luma.iter().sum::<f64>() / luma.len() as f64;
+    let window = |i: usize, n: usize| {
+        if n < 2 {
+            1.0
+        } else {
+            0.5 - 0.5 * (2.0 * PI * i as f64 / (n - 1) as f64).cos()
+        }
+    };
+
+    let mut re = vec![0.0; size * size];
+    let mut im = vec![0.0; size * size];
+    for y in 0..height {
+        let wy = window(y, height);
+        for x in 0..width {
+            re[y * size + x] = (luma[y * width + x] - mean) * wy * window(x, width);
+        }
+    }
+
+    // 先对每一行做 FFT 转置后再对每一行（原来的列）做一次
+    fft_rows(&mut re, &mut im, size);
+    transpose(&mut re, size);
+    transpose(&mut im, size);
+    fft_rows(&mut re, &mut im, size);
+
+    let magnitude: Vec<f64> = re
+        .iter()
+        .zip(&im)
+        .map(|(r, i)| r.hypot(*i).ln_1p())
+        .collect();
+    let max = magnitude.iter().cloned().fold(0.0, f64::max);
+    let scale = if max > 0.0 { 255.0 / max } else { 0.0 };
+
+    // 转置回来 同时把零频率移到中心
+    let half = size / 2;
+    let mut output = vec![0u8; size * size];
+    for (y, row) in output.chunks_exact_mut(size).enumerate() {
+        let v = (y + half) % size;
+        for (x, value) in row.iter_mut().enumerate() {
+            let u = (x + half) % size;
+            *value = (magnitude[u * size + v] * scale).round() as u8;
+        }
+    }
+    output
+}
+
+fn fft_rows(re: &mut [f64], im: &mut [f64], size: usize) {
+    let twiddles: Vec<(f64, f64)> = (0..size / 2)
+        .map(|k| {
+            let angle = -2.0 * PI * k as f64 / size as f64;
+            (angle.cos(), angle.sin())
+        })
+        .collect();
+    re.par_chunks_mut(size)
+        .zip(im.par_chunks_mut(size))
+        .for_each(|(row_re, row_im)| fft_in_place(row_re, row_im, &twiddles));
+}
+
+// 迭代的基 2 FFT 长度必须是 2 的幂
+fn fft_in_place(re: &mut [f64], im: &mut [f64], twiddles: &[(f64, f64)]) {
+    let n = re.len();
+    if n < 2 {
+        return;
+    }
+    // 按位反转的顺序重新排列
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut length = 2;
+    while length <= n {
+        let half = length / 2;
+        let stride = n / length;
+        for start in (0..n).step_by(length) {
+            for k in 0..half {
+                let (wr, wi) = twiddles[k * stride];
+                let (a, b) = (start + k, start + k + half);
+                let tr = re[b] * wr - im[b] * wi;
+                let ti = re[b] * wi + im[b] * wr;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        length *= 2;
+    }
+}
+
+fn transpose(data: &mut [f64], size: usize) {
+    for y in 0..size {
+        for x in y + 1..size {
+            data.swap(y * size + x, x * size + y);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use super::cache::{cache_dir_size, is_cache_read_only, load_source_info};
+use super::config::cache_root;
+use super::types::{DuplicateEntry, DuplicateGroup};
+
+// 内容指纹：源文件字节的 BLAKE3 哈希 记录在每个缓存目录的 source_info.json 中
+// 同一份扫描件被复制到不同位置时路径不同 缓存目录也不同 只有指纹能识别出来
+
+/// 计算文件内容的 BLAKE3 指纹
+/// # Arguments
+/// * `file_path` - 文件路径
+/// # Returns
+/// * `Result<String, String>` - 十六进制指纹
+pub fn compute_content_fingerprint(file_path: &str) -> Result<String, String> {
+    let mut file = fs::File::open(file_path).map_err(|e| format!("打开文件失败: {e}"))?;
+    let mut hasher = blake3::Hasher::new();
+    io::copy(&mut file, &mut hasher).map_err(|e| format!("计算内容指纹失败: {e}"))?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+// 源文件的长度和修改时间（毫秒） 用于判断记录的指纹是否仍然有效
+fn source_stamp(file_path: &str) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(file_path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((metadata.len(), modified.as_millis() as u64))
+}
+
+/// 生成写入 source_info.json 的指纹字段
+/// 源文件的长度和修改时间与上次记录一致时直接沿用旧指纹 避免重新切分 chunk 时再读一遍整个文件
+/// # Arguments
+/// * `cache_dir` - 该图片的缓存目录
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<serde_json::Value, String>` - content_hash / source_len / source_modified_ms
+pub fn fingerprint_fields(cache_dir: &Path, file_path: &str) -> Result<serde_json::Value, String> {
+    let stamp = source_stamp(file_path);
+    let previous = load_source_info(cache_dir);
+    let reusable = previous.as_ref().and_then(|info| {
+        let recorded = (
+            info.get("source_len")?.as_u64()?,
+            info.get("source_modified_ms")?.as_u64()?,
+        );
+        (Some(recorded) == stamp && info.get("file_path")?.as_str()? == file_path)
+            .then(|| info.get("content_hash")?.as_str().map(str::to_string))
+            .flatten()
+    });
+
+    let content_hash = match reusable {
+        Some(hash) => hash,
+        None => compute_content_fingerprint(file_path)?,
+    };
+    let (source_len, source_modified_ms) = stamp.unwrap_or_default();
+    Ok(serde_json::json!({
+        "content_hash": content_hash,
+        "source_len": source_len,
+        "source_modified_ms": source_modified_ms,
+    }))
+}
+
+// 扫描所有缓存目录 按指纹分组
+// 旧版本生成的缓存没有指纹 源文件还在时补算并写回 之后不用再算
+fn scan_cached_fingerprints() -> HashMap<String, Vec<DuplicateEntry>> {
+    let mut groups: HashMap<String, Vec<DuplicateEntry>> = HashMap::new();
+    let Ok(entries) = fs::read_dir(cache_root()) else {
+        return groups;
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let cache_dir = entry.path();
+        let Some(mut info) = load_source_info(&cache_dir) else {
+            continue;
+        };
+        let Some(file_path) = info
+            .get("file_path")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+        else {
+            continue;
+        };
+        let source_exists = Path::new(&file_path).exists();
+
+        let recorded = info
+            .get("content_hash")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+        let content_hash = match recorded {
+            Some(hash) => hash,
+            None if source_exists => match fingerprint_fields(&cache_dir, &file_path) {
+                Ok(fields) => {
+                    if let (Some(info), Some(fields)) = (info.as_object_mut(), fields.as_object()) {
+                        info.extend(fields.clone());
+                    }
+                    // 只读缓存不回写 下次仍然重新计算
+                    if !is_cache_read_only() {
+                        if let Ok(json) = serde_json::to_string(&info) {
+                            let _ = fs::write(cache_dir.join("source_info.json"), json);
+                        }
+                    }
+                    fields["content_hash"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string()
+                }
+                Err(e) => {
+                    println!("[RUST] 计算内容指纹失败: {file_path} ({e})");
+                    continue;
+                }
+            },
+            None => continue,
+        };
+
+        groups
+            .entry(content_hash)
+            .or_default()
+            .push(DuplicateEntry {
+                file_path,
+                cache_dir: cache_dir.to_string_lossy().to_string(),
+                cache_size_bytes: cache_dir_size(&cache_dir),
+                source_exists,
+            });
+    }
+    groups
+}
+
+/// 查找与指定文件内容完全相同的其他已处理图片
+/// 指定文件本身不需要已经预处理
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<Vec<DuplicateEntry>, String>` - 内容相同的其他图片 没有时为空
+#[tauri::command]
+pub fn find_duplicate(file_path: String) -> Result<Vec<DuplicateEntry>, String> {
+    let content_hash = compute_content_fingerprint(&file_path)?;
+    let duplicates = scan_cached_fingerprints()
+        .remove(&content_hash)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|entry| entry.file_path != file_path)
+        .collect::<Vec<_>>();
+
+    println!(
+        "[RUST] 查找重复图片: {file_path} 找到 {} 个",
+        duplicates.len()
+    );
+    Ok(duplicates)
+}
+
+/// 列出缓存中所有内容重复的图片
+/// # Returns
+/// * `Result<Vec<DuplicateGroup>, String>` - 每组至少两项 按可回收的缓存大小从大到小排序
+#[tauri::command]
+pub fn list_duplicates() -> Result<Vec<DuplicateGroup>, String> {
+    let mut groups: Vec<DuplicateGroup> = scan_cached_fingerprints()
+        .into_iter()
+        .filter(|(_, entries)| entries.len() > 1)
+        .map(|(content_hash, mut entries)| {
+            entries.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+            // 保留一份 其余缓存都可以回收
+            let total: u64 = entries.iter().map(|entry| entry.cache_size_bytes).sum();
+            let largest = entries
+                .iter()
+                .map(|entry| entry.cache_size_bytes)
+                .max()
+                .unwrap_or(0);
+            DuplicateGroup {
+                content_hash,
+                entries,
+                reclaimable_bytes: total - largest,
+            }
+        })
+        .collect();
+    groups.sort_by_key(|group| std::cmp::Reverse(group.reclaimable_bytes));
+
+    println!("[RUST] 共找到 {} 组重复图片", groups.len());
+    Ok(groups)
+}
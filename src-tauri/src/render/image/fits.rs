@@ -0,0 +1,526 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+use memmap2::Mmap;
+use tauri::{AppHandle, Window};
+
+use super::cache::fnv1a_hash;
+use super::commands::load_user_image;
+use super::types::{FitsHdu, FitsStretch, ImageMetadata};
+use super::utils::{app_data_subdir, is_up_to_date, save_rgba_png};
+use super::window_state::set_window_image;
+
+// FITS 转换结果在应用数据目录下的保存位置
+const FITS_DIR: &str = "fits";
+
+// FITS 文件由 2880 字节的块组成 头部每张卡片 80 字节
+const BLOCK_SIZE: usize = 2880;
+const CARD_SIZE: usize = 80;
+
+// 标准允许的最大维数
+const MAX_NAXIS: i64 = 999;
+// 一个平面整体读入内存（每个像素 8 字节的物理值 + 4 字节 RGBA） 超过这个像素数时拒绝
+const MAX_PLANE_PIXELS: u64 = 1 << 28;
+
+// 拉伸前按百分位裁剪 避免热像素和饱和星点把动态范围撑开
+const CLIP_LOW_PERCENTILE: f64 = 0.0025;
+const CLIP_HIGH_PERCENTILE: f64 = 0.9975;
+// 计算百分位时最多采样的像素数
+const MAX_PERCENTILE_SAMPLES: usize = 1 << 20;
+
+// 对数和 asinh 拉伸的强度
+const LOG_STRETCH_FACTOR: f64 = 1000.0;
+const ASINH_STRETCH_FACTOR: f64 = 10.0;
+
+// 解析后的 HDU 以及读取数据需要的信息
+struct HduLayout {
+    info: FitsHdu,
+    bscale: f64,
+    bzero: f64,
+    blank: Option<i64>,
+    data_offset: usize,
+    data_len: usize,
+}
+
+/// 列出 FITS 文件中的所有 HDU
+#[tauri::command]
+pub fn list_fits_hdus(file_path: String) -> Result<Vec<FitsHdu>, String> {
+    let mmap = map_file(Path::new(&file_path))?;
+    Ok(parse_hdus(&mmap)?.into_iter().map(|hdu| hdu.info).collect())
+}
+
+/// 打开 FITS 图像
+/// 读取指定 HDU 的一个平面 按百分位裁剪后做线性 / 对数 / asinh 拉伸
+/// 之后保存为 PNG 走正常的预处理流程 NaN 和 BLANK 像素为透明
+/// 保存后的路径会记录为调用窗口当前打开的图片 前端可以通过 get_window_state 取得
+/// # Arguments
+/// * `file_path` - FITS 文件路径
+/// * `hdu` - HDU 序号 不传时使用第一个图像 HDU
+/// * `plane` - 三维数据立方体中的平面序号 默认为 0
+/// * `stretch` - 拉伸方式 默认为 asinh
+#[tauri::command]
+pub fn process_fits_image(
+    app: AppHandle,
+    window: Window,
+    file_path: String,
+    hdu: Option<u32>,
+    plane: Option<u32>,
+    stretch: Option<FitsStretch>,
+) -> Result<ImageMetadata, String> {
+    let source = Path::new(&file_path);
+    let mmap = map_file(source)?;
+    let hdus = parse_hdus(&mmap)?;
+
+    let layout = match hdu {
+        Some(index) => hdus
+            .iter()
+            .find(|h| h.info.index == index)
+            .ok_or_else(|| format!("FITS 中不存在 HDU {index}"))?,
+        None => hdus
+            .iter()
+            .find(|h| h.info.is_image)
+            .ok_or_else(|| "FITS 中没有图像 HDU".to_string())?,
+    };
+    if !layout.info.is_image {
+        return Err(format!("HDU {} 不是图像", layout.info.index));
+    }
+
+    let plane = plane.unwrap_or(0);
+    let stretch = stretch.unwrap_or(FitsStretch::Asinh);
+    let width = layout.info.axes[0] as u32;
+    let height = layout.info.axes[1] as u32;
+
+    let dir = app_data_subdir(&app, FITS_DIR)?;
+    let output = dir.join(format!(
+        "fits_{:016x}_h{}_p{}_{}.png",
+        fnv1a_hash(file_path.as_bytes()),
+        layout.info.index,
+        plane,
+        stretch_name(stretch)
+    ));
+
+    if is_up_to_date(source, &output) {
+        println!("[RUST] 复用已转换的 FITS 图片: {}", output.display());
+    } else {
+        println!(
+            "[RUST] 转换 FITS 图片: HDU {} 平面 {plane} {width}x{height} BITPIX {}",
+            layout.info.index, layout.info.bitpix
+        );
+        let values = read_plane(&mmap, layout, plane)?;
+        let pixels = stretch_to_rgba(&values, width as usize, stretch);
+        save_rgba_png(&output, width, height, pixels)?;
+    }
+
+    let output = output.to_string_lossy().to_string();
+    let metadata = load_user_image(&output)?;
+    set_window_image(window.label(), &output);
+    Ok(metadata)
+}
+
+fn stretch_name(stretch: FitsStretch) -> &'static str {
+    match stretch {
+        FitsStretch::Linear => "linear",
+        FitsStretch::Log => "log",
+        FitsStretch::Asinh => "asinh",
+    }
+}
+
+fn map_file(path: &Path) -> Result<Mmap, String> {
+    let file = File::open(path).map_err(|e| format!("打开 FITS 文件失败: {e} ({path:?})"))?;
+    unsafe { Mmap::map(&file) }.map_err(|e| format!("映射 FITS 文件失败: {e} ({path:?})"))
+}
+
+/// 依次解析所有 HDU 的头部 并计算数据区的位置
+fn parse_hdus(data: &[u8]) -> Result<Vec<HduLayout>, String> {
+    let mut hdus = Vec::new();
+    let mut offset = 0;
+
+    while offset + BLOCK_SIZE <= data.len() {
+        let (cards, header_len) = parse_header(&data[offset..])?;
+        let index = hdus.len() as u32;
+
+        let int_card = |key: &str| cards.get(key).and_then(|v| v.parse::<i64>().ok());
+        let float_card = |key: &str| cards.get(key).and_then(|v| v.parse::<f64>().ok());
+
+        let bitpix = int_card("BITPIX").ok_or_else(|| format!("HDU {index} 缺少 BITPIX"))? as i32;
+        if !matches!(bitpix, 8 | 16 | 32 | 64 | -32 | -64) {
+            return Err(format!("HDU {index} 的 BITPIX 无效: {bitpix}"));
+        }
+        let naxis = int_card("NAXIS").ok_or_else(|| format!("HDU {index} 缺少 NAXIS"))?;
+        if !(0..=MAX_NAXIS).contains(&naxis) {
+            return Err(format!("HDU {index} 的 NAXIS 无效: {naxis}"));
+        }
+        let axes = (1..=naxis)
+            .map(|n| match int_card(&format!("NAXIS{n}")) {
+                Some(value) if value >= 0 => Ok(value as u64),
+                Some(value) => Err(format!("HDU {index} 的 NAXIS{n} 无效: {value}")),
+                None => Err(format!("HDU {index} 缺少 NAXIS{n}")),
+            })
+            .collect::<Result<Vec<u64>, String>>()?;
+        // 主 HDU 和 IMAGE 扩展才是图像 二进制表等跳过
+        // 图像的轴长必须为正 只有表（没有行）和随机组（NAXIS1 = 0）的轴长可以为 0
+        let xtension = cards.get("XTENSION").map(|v| v.as_str());
+        let image_hdu = matches!(xtension, None | Some("IMAGE"))
+            && cards.get("GROUPS").map(|v| v.as_str()) != Some("T");
+        if image_hdu && axes.contains(&0) {
+            return Err(format!("HDU {index} 的图像轴长为 0: {axes:?}"));
+        }
+
+        // 数据区长度 = |BITPIX| / 8 * GCOUNT * (PCOUNT + NAXIS1 * NAXIS2 * ...)
+        let pcount = u64::try_from(int_card("PCOUNT").unwrap_or(0))
+            .map_err(|_| format!("HDU {index} 的 PCOUNT 无效"))?;
+        let gcount = u64::try_from(int_card("GCOUNT").unwrap_or(1))
+            .map_err(|_| format!("HDU {index} 的 GCOUNT 无效"))?;
+        let data_len = if axes.is_empty() {
+            Some(0)
+        } else {
+            axes.iter()
+                .try_fold(1u64, |product, &axis| product.checked_mul(axis))
+                .and_then(|product| product.checked_add(pcount))
+                .and_then(|count| count.checked_mul(gcount))
+                .and_then(|count| count.checked_mul(bitpix.unsigned_abs() as u64 / 8))
+        }
+        .and_then(|len| usize::try_from(len).ok())
+        .ok_or_else(|| format!("HDU {index} 的数据区过大"))?;
+
+        let is_image = image_hdu && axes.len() >= 2;
+
+        let data_offset = offset + header_len;
+        hdus.push(HduLayout {
+            info: FitsHdu {
+                index,
+                bitpix,
+                axes,
+                extname: cards.get("EXTNAME").cloned(),
+                is_image,
+            },
+            bscale: float_card("BSCALE").unwrap_or(1.0),
+            bzero: float_card("BZERO").unwrap_or(0.0),
+            blank: int_card("BLANK"),
+            data_offset,
+            data_len,
+        });
+
+        offset = data_len
+            .div_ceil(BLOCK_SIZE)
+            .checked_mul(BLOCK_SIZE)
+            .and_then(|len| len.checked_add(data_offset))
+            .ok_or_else(|| format!("HDU {index} 的数据区过大"))?;
+    }
+
+    if hdus.is_empty() {
+        return Err("不是有效的 FITS 文件".to_string());
+    }
+    Ok(hdus)
+}
+
+/// 解析一个头部 返回关键字到值的映射以及头部占用的字节数（按块对齐）
+fn parse_header(data: &[u8]) -> Result<(HashMap<String, String>, usize), String> {
+    let mut cards = HashMap::new();
+
+    // 头部应当只有 ASCII 字符 按字节切分 其他字符不会影响列的位置
+    for (i, card) in data.chunks_exact(CARD_SIZE).enumerate() {
+        let key = String::from_utf8_lossy(&card[..8]).trim().to_string();
+
+        if key == "END" {
+            let header_len = ((i + 1) * CARD_SIZE).div_ceil(BLOCK_SIZE) * BLOCK_SIZE;
+            return Ok((cards, header_len));
+        }
+        if i == 0 && key != "SIMPLE" && key != "XTENSION" {
+            return Err("不是有效的 FITS 头部".to_string());
+        }
+        // 只有第 9、10 列为 "= " 的卡片才有值
+        if &card[8..10] == b"= " {
+            cards.insert(key, parse_card_value(&String::from_utf8_lossy(&card[10..])));
+        }
+    }
+
+    Err("FITS 头部缺少 END".to_string())
+}
+
+// 去掉注释和字符串引号 字符串中的 '' 表示一个单引号
+fn parse_card_value(raw: &str) -> String {
+    let raw = raw.trim_start();
+    if let Some(rest) = raw.strip_prefix('\'') {
+        let mut value = String::new();
+        let mut chars = rest.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\'' {
+                if chars.peek() == Some(&'\'') {
+                    chars.next();
+                    value.push('\'');
+                } else {
+                    break;
+                }
+            } else {
+                value.push(c);
+            }
+        }
+        return value.trim_end().to_string();
+    }
+    raw.split('/').next().unwrap_or("").trim().to_string()
+}
+
+/// 读取一个平面的物理值（已应用 BSCALE / BZERO） BLANK 像素为 NaN
+fn read_plane(data: &[u8], layout: &HduLayout, plane: u32) -> Result<Vec<f64>, String> {
+    let axes = &layout.info.axes;
+    let plane_count: u64 = axes[2..].iter().product();
+    if plane as u64 >= plane_count {
+        return Err(format!("平面序号超出范围: {plane} (共 {plane_count} 个)"));
+    }
+
+    let plane_pixels = axes[0] * axes[1];
+    if plane_pixels > MAX_PLANE_PIXELS {
+        return Err(format!(
+            "FITS 图像过大: {}x{} 超过 {MAX_PLANE_PIXELS} 像素",
+            axes[0], axes[1]
+        ));
+    }
+
+    // 平面都在数据区内（数据区长度已经检查过溢出）
+    let bytes_per_value = layout.info.bitpix.unsigned_abs() as usize / 8;
+    let plane_len = plane_pixels as usize;
+    let start = layout.data_offset + plane as usize * plane_len * bytes_per_value;
+    let end = start + plane_len * bytes_per_value;
+    if end > data.len() || end > layout.data_offset + layout.data_len {
+        return Err("FITS 数据区不完整".to_string());
+    }
+
+    let scale = |raw: i64| -> f64 {
+        if layout.blank == Some(raw) {
+            f64::NAN
+        } else {
+            raw as f64 * layout.bscale + layout.bzero
+        }
+    };
+
+    // FITS 数据为大端序
+    let bytes = &data[start..end];
+    let values = match layout.info.bitpix {
+        8 => bytes.iter().map(|&b| scale(b as i64)).collect(),
+        16 => bytes
+            .chunks_exact(2)
+            .map(|b| scale(i16::from_be_bytes([b[0], b[1]]) as i64))
+            .collect(),
+        32 => bytes
+            .chunks_exact(4)
+            .map(|b| scale(i32::from_be_bytes([b[0], b[1], b[2], b[3]]) as i64))
+            .collect(),
+        64 => bytes
+            .chunks_exact(8)
+            .map(|b| scale(i64::from_be_bytes(b.try_into().unwrap_or([0; 8]))))
+            .collect(),
+        -32 => bytes
+            .chunks_exact(4)
+            .map(|b| {
+                f32::from_be_bytes([b[0], b[1], b[2], b[3]]) as f64 * layout.bscale + layout.bzero
+            })
+            .collect(),
+        _ => bytes
+            .chunks_exact(8)
+            .map(|b| {
+                f64::from_be_bytes(b.try_into().unwrap_or([0; 8])) * layout.bscale + layout.bzero
+            })
+            .collect(),
+    };
+    Ok(values)
+}
+
+/// 计算裁剪范围 对有限值采样后取百分位
+fn clip_range(values: &[f64]) -> Option<(f64, f64)> {
+    let step = (values.len() / MAX_PERCENTILE_SAMPLES).max(1);
+    let mut samples: Vec<f64> = values
+        .iter()
+        .step_by(step)
+        .copied()
+        .filter(|v| v.is_finite())
+        .collect();
+    if samples.is_empty() {
+        return None;
+    }
+
+    let last = samples.len() - 1;
+    let low_index = (last as f64 * CLIP_LOW_PERCENTILE) as usize;
+    let high_index = (last as f64 * CLIP_HIGH_PERCENTILE) as usize;
+    let low = *samples.select_nth_unstable_by(low_index, f64::total_cmp).1;
+    let high = *samples.select_nth_unstable_by(high_index, f64::total_cmp).1;
+    Some((low, high))
+}
+
+/// 拉伸为 8 位灰度 RGBA
+/// FITS 的第一行在图像底部 输出时上下翻转
+fn stretch_to_rgba(values: &[f64], width: usize, stretch: FitsStretch) -> Vec<u8> {
+    let (low, high) = clip_range(values).unwrap_or((0.0, 1.0));
+    let range = if high > low { high - low } else { 1.0 };
+
+    let map = |value: f64| -> f64 {
+        let t = ((value - low) / range).clamp(0.0, 1.0);
+        match stretch {
+            FitsStretch::Linear => t,
+            FitsStretch::Log => {
+                (1.0 + LOG_STRETCH_FACTOR * t).ln() / (1.0 + LOG_STRETCH_FACTOR).ln()
+            }
+            FitsStretch::Asinh => (ASINH_STRETCH_FACTOR * t).asinh() / ASINH_STRETCH_FACTOR.asinh(),
+        }
+    };
+
+    let mut pixels = Vec::with_capacity(values.len() * 4);
+    for row in values.chunks_exact(width).rev() {
+        for &value in row {
+            if value.is_finite() {
+                let gray = (map(value) * 255.0).round() as u8;
+                pixels.extend_from_slice(&[gray, gray, gray, 255]);
+            } else {
+                pixels.extend_from_slice(&[0, 0, 0, 0]);
+            }
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 按 FITS 的格式拼出一个头部：每张卡片补齐到 80 字节 整个头部补齐到块
+    fn header(cards: &[&str]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for card in cards.iter().chain(std::iter::once(&"END")) {
+            data.extend_from_slice(format!("{card:<80}").as_bytes());
+        }
+        data.resize(data.len().div_ceil(BLOCK_SIZE) * BLOCK_SIZE, b' ');
+        data
+    }
+
+    fn with_data(mut data: Vec<u8>, values: &[u8]) -> Vec<u8> {
+        data.extend_from_slice(values);
+        data.resize(data.len().div_ceil(BLOCK_SIZE) * BLOCK_SIZE, 0);
+        data
+    }
+
+    #[test]
+    fn parses_primary_image_and_applies_bzero() {
+        let data = with_data(
+            header(&[
+                "SIMPLE  =                    T",
+                "BITPIX  =                   16",
+                "NAXIS   =                    2",
+                "NAXIS1  =                    3",
+                "NAXIS2  =                    2",
+                "BZERO   =                32768",
+            ]),
+            &[
+                0x80, 0x00, 0x80, 0x01, 0xff, 0xff, 0x00, 0x00, 0x00, 0x02, 0x7f, 0xff,
+            ],
+        );
+        let hdus = parse_hdus(&data).unwrap();
+        assert_eq!(hdus.len(), 1);
+        assert!(hdus[0].info.is_image);
+        assert_eq!(hdus[0].info.axes, vec![3, 2]);
+        let values = read_plane(&data, &hdus[0], 0).unwrap();
+        assert_eq!(values, vec![0.0, 1.0, 32767.0, 32768.0, 32770.0, 65535.0]);
+        assert!(read_plane(&data, &hdus[0], 1).is_err());
+    }
+
+    #[test]
+    fn skips_table_extensions_without_rows() {
+        let mut data = header(&[
+            "SIMPLE  =                    T",
+            "BITPIX  =                    8",
+            "NAXIS   =                    0",
+        ]);
+        data.extend(header(&[
+            "XTENSION= 'BINTABLE'           / binary table",
+            "BITPIX  =                    8",
+            "NAXIS   =                    2",
+            "NAXIS1  =                   16",
+            "NAXIS2  =                    0",
+            "PCOUNT  =                    0",
+            "GCOUNT  =                    1",
+            "EXTNAME = 'EVENTS'",
+        ]));
+        let hdus = parse_hdus(&data).unwrap();
+        assert_eq!(hdus.len(), 2);
+        assert!(!hdus[0].info.is_image);
+        assert!(!hdus[1].info.is_image);
+        assert_eq!(hdus[1].info.extname.as_deref(), Some("EVENTS"));
+    }
+
+    #[test]
+    fn rejects_invalid_headers() {
+        let zero_axis = header(&[
+            "SIMPLE  =                    T",
+            "BITPIX  =                    8",
+            "NAXIS   =                    2",
+            "NAXIS1  =                    0",
+            "NAXIS2  =                   10",
+        ]);
+        assert!(parse_hdus(&zero_axis).is_err());
+
+        let bad_bitpix = header(&[
+            "SIMPLE  =                    T",
+            "BITPIX  =                   12",
+            "NAXIS   =                    0",
+        ]);
+        assert!(parse_hdus(&bad_bitpix).is_err());
+
+        let overflow = header(&[
+            "SIMPLE  =                    T",
+            "BITPIX  =                  -64",
+            "NAXIS   =                    3",
+            "NAXIS1  =        1099511627776",
+            "NAXIS2  =        1099511627776",
+            "NAXIS3  =        1099511627776",
+        ]);
+        assert!(parse_hdus(&overflow).is_err());
+
+        let mut no_end = vec![b' '; BLOCK_SIZE];
+        no_end[..30].copy_from_slice(b"SIMPLE  =                    T");
+        assert!(parse_hdus(&no_end).is_err());
+
+        assert!(parse_hdus(b"not a fits file").is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let data = header(&[
+            "SIMPLE  =                    T",
+            "BITPIX  =                   16",
+            "NAXIS   =                    2",
+            "NAXIS1  =                  100",
+            "NAXIS2  =                  100",
+        ]);
+        let hdus = parse_hdus(&data).unwrap();
+        assert!(read_plane(&data, &hdus[0], 0).is_err());
+    }
+
+    #[test]
+    fn parses_card_values() {
+        assert_eq!(parse_card_value(" 'O''Brien '  / observer"), "O'Brien");
+        assert_eq!(parse_card_value("   42 / answer"), "42");
+        assert_eq!(parse_card_value("   T"), "T");
+    }
+
+    #[test]
+    fn blank_pixels_are_transparent_and_rows_are_flipped() {
+        let data = with_data(
+            header(&[
+                "SIMPLE  =                    T",
+                "BITPIX  =                    8",
+                "NAXIS   =                    2",
+                "NAXIS1  =                    2",
+                "NAXIS2  =                    2",
+                "BLANK   =                    7",
+            ]),
+            &[0, 7, 255, 255],
+        );
+        let hdus = parse_hdus(&data).unwrap();
+        let values = read_plane(&data, &hdus[0], 0).unwrap();
+        assert!(values[1].is_nan());
+        let pixels = stretch_to_rgba(&values, 2, FitsStretch::Linear);
+        // 第一行在底部
+        assert_eq!(&pixels[..8], &[255, 255, 255, 255, 255, 255, 255, 255]);
+        assert_eq!(&pixels[8..16], &[0, 0, 0, 255, 0, 0, 0, 0]);
+    }
+}
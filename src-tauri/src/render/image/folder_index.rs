@@ -0,0 +1,536 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::UNIX_EPOCH;
+
+use image::imageops::FilterType;
+use rayon::prelude::*;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use super::cache::{check_file_cache_exists, fnv1a_hash};
+use super::catalog::{open_catalog, upsert_catalog_image};
+use super::power::{throttled_pool, wait_for_background_slot};
+use super::types::{BackgroundPolicy, FolderIndexCompleted, FolderIndexEntry, FolderIndexProgress};
+use super::utils::app_data_subdir;
+use crate::utils::time::{get_time, Stopwatch};
+
+// 文件夹索引：遍历文件夹 读取图片头部的尺寸和 EXIF 生成缩略图 结果保存在图片目录数据库中
+// 缩略图需要完整解码 同时解码的图片按像素预算限制 避免并行解码多张大图占满内存
+// 图库视图不需要预处理每一张图片 只有打开时才分块
+// 每一批文件处理完就写入数据库 中断后重新索引时跳过大小和修改时间都没变的文件
+// 排队中的文件夹保存在应用数据目录 下次启动时继续
+
+// 索引进度和索引结束事件
+pub const FOLDER_INDEX_PROGRESS_EVENT: &str = "folder_index://progress";
+pub const FOLDER_INDEX_COMPLETED_EVENT: &str = "folder_index://completed";
+
+// 排队中的文件夹列表 位于应用数据目录的 queue 子目录下
+const PENDING_FOLDERS_FILE: &str = "folder_index_pending.json";
+
+// 会被索引的扩展名 与 validate_image_path 一致
+const INDEXED_EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "bmp", "tif", "tiff", "webp"];
+
+// 缩略图的最长边
+const THUMBNAIL_SIZE: u32 = 256;
+// 超过这个像素数的图片不生成缩略图 完整解码占用的内存太多
+const MAX_THUMBNAIL_SOURCE_PIXELS: u64 = 100_000_000;
+// 同时完整解码的缩略图源图片的像素数之和的上限 批内并行生成时按这个预算排队
+// 一张接近 MAX_THUMBNAIL_SOURCE_PIXELS 的大图独占预算 小图可以同时解码多张
+const THUMBNAIL_DECODE_BUDGET_PIXELS: u64 = MAX_THUMBNAIL_SOURCE_PIXELS;
+// 每批处理的文件数 一批处理完写入数据库并发出进度事件
+const INDEX_BATCH_SIZE: usize = 32;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+struct PendingFolder {
+    folder: String,
+    recursive: bool,
+}
+
+struct FolderIndexTask {
+    app: AppHandle,
+    folder: PendingFolder,
+}
+
+// 文件夹索引队列 单个工作线程按顺序处理 缩略图在线程内部用 rayon 并行生成
+struct FolderIndexQueue {
+    sender: Mutex<Sender<FolderIndexTask>>,
+    // 已排队但还没处理完的文件夹 用于去重
+    pending: Mutex<HashSet<PendingFolder>>,
+}
+
+static FOLDER_INDEX_QUEUE: OnceLock<FolderIndexQueue> = OnceLock::new();
+
+fn get_folder_index_queue() -> &'static FolderIndexQueue {
+    FOLDER_INDEX_QUEUE.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<FolderIndexTask>();
+
+        thread::Builder::new()
+            .name("folder-index".to_string())
+            .spawn(move || {
+                for task in receiver {
+                    let policy = wait_for_background_slot();
+                    let folder = task.folder.folder.clone();
+                    println!("[RUST] 文件夹索引开始: {folder} ({policy:?})");
+                    let result = match policy {
+                        BackgroundPolicy::Throttled => {
+                            throttled_pool().install(|| run_folder_index(&task))
+                        }
+                        _ => run_folder_index(&task),
+                    };
+                    let payload = result.unwrap_or_else(|e| {
+                        println!("[RUST] 文件夹索引失败: {folder} ({e})");
+                        FolderIndexCompleted {
+                            folder: folder.clone(),
+                            indexed: 0,
+                            unchanged: 0,
+                            removed: 0,
+                            failed: 0,
+                            error: Some(e),
+                        }
+                    });
+
+                    if let Some(queue) = FOLDER_INDEX_QUEUE.get() {
+                        if let Ok(mut pending) = queue.pending.lock() {
+                            pending.remove(&task.folder);
+                            save_pending(&task.app, &pending);
+                        }
+                    }
+                    if let Err(e) = task.app.emit(FOLDER_INDEX_COMPLETED_EVENT, payload) {
+                        println!("[RUST] 发送文件夹索引完成事件失败: {e}");
+                    }
+                }
+            })
+            .expect("启动文件夹索引线程失败");
+
+        FolderIndexQueue {
+            sender: Mutex::new(sender),
+            pending: Mutex::new(HashSet::new()),
+        }
+    })
+}
+
+fn enqueue_folder(app: &AppHandle, folder: PendingFolder) -> Result<bool, String> {
+    let queue = get_folder_index_queue();
+    let mut pending = queue
+        .pending
+        .lock()
+        .map_err(|e| format!("文件夹索引队列加锁失败: {e}"))?;
+    if !pending.insert(folder.clone()) {
+        return Ok(false);
+    }
+    let sender = queue
+        .sender
+        .lock()
+        .map_err(|e| format!("文件夹索引队列加锁失败: {e}"))?;
+    if let Err(e) = sender.send(FolderIndexTask {
+        app: app.clone(),
+        folder: folder.clone(),
+    }) {
+        pending.remove(&folder);
+        return Err(format!("加入文件夹索引队列失败: {e}"));
+    }
+    save_pending(app, &pending);
+    Ok(true)
+}
+
+// 保存排队中的文件夹列表 失败只记录日志
+fn save_pending(app: &AppHandle, pending: &HashSet<PendingFolder>) {
+    let mut folders: Vec<&PendingFolder> = pending.iter().collect();
+    folders.sort_by(|a, b| a.folder.cmp(&b.folder));
+    let result = app_data_subdir(app, "queue").and_then(|dir| {
+        let json = serde_json::to_string(&folders)
+            .map_err(|e| format!("序列化文件夹索引队列失败: {e}"))?;
+        fs::write(dir.join(PENDING_FOLDERS_FILE), json)
+            .map_err(|e| format!("保存文件夹索引队列失败: {e}"))
+    });
+    if let Err(e) = result {
+        println!("[RUST] {e}");
+    }
+}
+
+/// 把文件夹加入索引队列 已经在队列中时返回 false
+pub(super) fn enqueue_folder_index(
+    app: &AppHandle,
+    folder: String,
+    recursive: bool,
+) -> Result<bool, String> {
+    enqueue_folder(app, PendingFolder { folder, recursive })
+}
+
+/// 启动时调用 把上次退出时还没索引完的文件夹重新加入队列
+pub fn resume_folder_indexing(app: &AppHandle) {
+    let folders: Vec<PendingFolder> = app_data_subdir(app, "queue")
+        .ok()
+        .and_then(|dir| fs::read_to_string(dir.join(PENDING_FOLDERS_FILE)).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    for folder in folders {
+        if !Path::new(&folder.folder).is_dir() {
+            continue;
+        }
+        let name = folder.folder.clone();
+        match enqueue_folder(app, folder) {
+            Ok(_) => println!("[RUST] 继续上次未完成的文件夹索引: {name}"),
+            Err(e) => println!("[RUST] {e}"),
+        }
+    }
+}
+
+// 文件夹路径统一为绝对路径 同一个文件夹用不同写法传入时共用索引
+pub(super) fn normalize_folder(path: &str) -> Result<String, String> {
+    let folder = fs::canonicalize(path).map_err(|e| format!("无法访问文件夹: {e} ({path})"))?;
+    if !folder.is_dir() {
+        return Err(format!("不是文件夹: {path}"));
+    }
+    Ok(folder.to_string_lossy().to_string())
+}
+
+pub(super) fn is_indexed_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| INDEXED_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+// 收集文件夹中的图片文件 不跟随符号链接的目录 跳过隐藏文件和目录
+pub(super) fn collect_images(folder: &Path, recursive: bool, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(folder) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = entry.path();
+        if file_type.is_dir() {
+            if recursive {
+                collect_images(&path, recursive, files);
+            }
+        } else if is_indexed_image(&path) {
+            files.push(path);
+        }
+    }
+}
+
+// 文件的大小和修改时间 用于判断是否需要重新索引
+pub(super) fn file_stamp(path: &Path) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified_ms = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    Some((metadata.len(), modified_ms))
+}
+
+pub(super) fn thumbnail_path(thumbnail_dir: &Path, file_path: &str) -> PathBuf {
+    thumbnail_dir.join(format!("{:016x}.png", fnv1a_hash(file_path.as_bytes())))
+}
+
+// 生成缩略图 图片过大时返回 None
+pub(super) fn generate_thumbnail(path: &Path, output: &Path) -> Result<Option<String>, String> {
+    let (width, height) =
+        image::image_dimensions(path).map_err(|e| format!("读取图片尺寸失败: {e}"))?;
+    let pixels = width as u64 * height as u64;
+    if pixels > MAX_THUMBNAIL_SOURCE_PIXELS {
+        return Ok(None);
+    }
+    let _permit = ThumbnailDecodePermit::acquire(pixels);
+    let img = image::open(path).map_err(|e| format!("解码图片失败: {e}"))?;
+    img.resize(THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Triangle)
+        .save(output)
+        .map_err(|e| format!("保存缩略图失败: {e}"))?;
+    Ok(Some(output.to_string_lossy().to_string()))
+}
+
+// 缩略图解码的像素预算 持有期间计入正在解码的像素数
+struct ThumbnailDecodePermit {
+    pixels: u64,
+}
+
+struct ThumbnailDecodeBudget {
+    decoding: Mutex<u64>,
+    released: Condvar,
+}
+
+fn thumbnail_decode_budget() -> &'static ThumbnailDecodeBudget {
+    static BUDGET: OnceLock<ThumbnailDecodeBudget> = OnceLock::new();
+    BUDGET.get_or_init(|| ThumbnailDecodeBudget {
+        decoding: Mutex::new(0),
+        released: Condvar::new(),
+    })
+}
+
+impl ThumbnailDecodePermit {
+    // 等到预算足够时返回 没有其他解码时总是可以开始
+    fn acquire(pixels: u64) -> Self {
+        let budget = thumbnail_decode_budget();
+        let mut decoding = budget.decoding.lock().unwrap_or_else(|e| e.into_inner());
+        while *decoding > 0 && *decoding + pixels > THUMBNAIL_DECODE_BUDGET_PIXELS {
+            decoding = budget
+                .released
+                .wait(decoding)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        *decoding += pixels;
+        Self { pixels }
+    }
+}
+
+impl Drop for ThumbnailDecodePermit {
+    fn drop(&mut self) {
+        let budget = thumbnail_decode_budget();
+        let mut decoding = budget.decoding.lock().unwrap_or_else(|e| e.into_inner());
+        *decoding -= self.pixels;
+        budget.released.notify_all();
+    }
+}
+
+/// 把一个文件写入图片目录和文件夹索引
+/// # Arguments
+/// * `stamp` - 文件大小和修改时间（毫秒）
+/// * `thumbnail` - 缩略图路径 图片过大没有缩略图时为 None
+pub(super) fn store_folder_file(
+    connection: &Connection,
+    folder: &str,
+    file_path: &str,
+    stamp: (u64, u64),
+    thumbnail: Option<&str>,
+) -> Result<(), String> {
+    upsert_catalog_image(connection, file_path)?;
+    connection
+        .execute(
+            "INSERT OR REPLACE INTO folder_files
+                (file_path, folder, file_size, modified_ms, thumbnail, indexed_ms)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                file_path,
+                folder,
+                stamp.0 as i64,
+                stamp.1 as i64,
+                thumbnail,
+                get_time() as i64,
+            ],
+        )
+        .map_err(|e| format!("写入文件夹索引失败: {e}"))?;
+    Ok(())
+}
+
+// 上次索引时记录的文件状态
+struct IndexedFile {
+    file_size: u64,
+    modified_ms: u64,
+    thumbnail: Option<String>,
+}
+
+fn load_indexed_files(
+    connection: &Connection,
+    folder: &str,
+) -> Result<HashMap<String, IndexedFile>, String> {
+    let mut statement = connection
+        .prepare("SELECT file_path, file_size, modified_ms, thumbnail FROM folder_files WHERE folder = ?1")
+        .map_err(|e| format!("读取文件夹索引失败: {e}"))?;
+    let rows = statement
+        .query_map(params![folder], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                IndexedFile {
+                    file_size: row.get::<_, i64>(1)? as u64,
+                    modified_ms: row.get::<_, i64>(2)? as u64,
+                    thumbnail: row.get(3)?,
+                },
+            ))
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<HashMap<_, _>>>())
+        .map_err(|e| format!("读取文件夹索引失败: {e}"))?;
+    Ok(rows)
+}
+
+fn run_folder_index(task: &FolderIndexTask) -> Result<FolderIndexCompleted, String> {
+    let stopwatch = Stopwatch::start();
+    let folder = &task.folder.folder;
+    let mut files = Vec::new();
+    collect_images(Path::new(folder), task.folder.recursive, &mut files);
+    files.sort();
+    let total = files.len() as u32;
+
+    let thumbnail_dir = app_data_subdir(&task.app, "thumbnails")?;
+    let mut connection = open_catalog(&task.app)?;
+    let mut indexed = load_indexed_files(&connection, folder)?;
+    let mut result = FolderIndexCompleted {
+        folder: folder.clone(),
+        indexed: 0,
+        unchanged: 0,
+        removed: 0,
+        failed: 0,
+        error: None,
+    };
+
+    let mut processed = 0u32;
+    for batch in files.chunks(INDEX_BATCH_SIZE) {
+        // 大小和修改时间都没变并且缩略图还在的文件跳过
+        let changed: Vec<(String, u64, u64)> = batch
+            .iter()
+            .filter_map(|path| {
+                let file_path = path.to_string_lossy().to_string();
+                let Some((file_size, modified_ms)) = file_stamp(path) else {
+                    // 只计为失败 保留之前的索引 不再当作已删除的文件移除
+                    indexed.remove(&file_path);
+                    result.failed += 1;
+                    return None;
+                };
+                let unchanged = indexed.remove(&file_path).is_some_and(|previous| {
+                    previous.file_size == file_size
+                        && previous.modified_ms == modified_ms
+                        && previous
+                            .thumbnail
+                            .is_none_or(|thumbnail| Path::new(&thumbnail).exists())
+                });
+                if unchanged {
+                    result.unchanged += 1;
+                    return None;
+                }
+                Some((file_path, file_size, modified_ms))
+            })
+            .collect();
+
+        let thumbnails: Vec<Result<Option<String>, String>> = changed
+            .par_iter()
+            .map(|(file_path, _, _)| {
+                generate_thumbnail(
+                    Path::new(file_path),
+                    &thumbnail_path(&thumbnail_dir, file_path),
+                )
+            })
+            .collect();
+
+        let transaction = connection
+            .transaction()
+            .map_err(|e| format!("写入文件夹索引失败: {e}"))?;
+        for ((file_path, file_size, modified_ms), thumbnail) in changed.iter().zip(thumbnails) {
+            let stored = thumbnail.and_then(|thumbnail| {
+                store_folder_file(
+                    &transaction,
+                    folder,
+                    file_path,
+                    (*file_size, *modified_ms),
+                    thumbnail.as_deref(),
+                )
+            });
+            match stored {
+                Ok(_) => result.indexed += 1,
+                Err(e) => {
+                    println!("[RUST] 索引图片失败: {file_path} ({e})");
+                    result.failed += 1;
+                }
+            }
+        }
+        transaction
+            .commit()
+            .map_err(|e| format!("写入文件夹索引失败: {e}"))?;
+
+        processed += batch.len() as u32;
+        let progress = FolderIndexProgress {
+            folder: folder.clone(),
+            processed,
+            total,
+        };
+        if let Err(e) = task.app.emit(FOLDER_INDEX_PROGRESS_EVENT, progress) {
+            println!("[RUST] 发送文件夹索引进度失败: {e}");
+        }
+    }
+
+    // 剩下的是这次没有找到的文件 从索引中移除
+    for (file_path, previous) in indexed {
+        if let Some(thumbnail) = previous.thumbnail {
+            let _ = fs::remove_file(thumbnail);
+        }
+        connection
+            .execute(
+                "DELETE FROM folder_files WHERE file_path = ?1",
+                params![file_path],
+            )
+            .map_err(|e| format!("更新文件夹索引失败: {e}"))?;
+        if !Path::new(&file_path).exists() {
+            connection
+                .execute(
+                    "DELETE FROM images WHERE file_path = ?1",
+                    params![file_path],
+                )
+                .map_err(|e| format!("更新图片目录失败: {e}"))?;
+        }
+        result.removed += 1;
+    }
+
+    println!(
+        "[RUST] 文件夹索引完成: {folder} 新增或更新 {} 跳过 {} 移除 {} 失败 {} (耗时: {}ms)",
+        result.indexed,
+        result.unchanged,
+        result.removed,
+        result.failed,
+        stopwatch.elapsed_ms()
+    );
+    Ok(result)
+}
+
+/// 索引文件夹中的图片 在后台执行
+/// 进度通过 folder_index://progress 事件通知 结束时发出 folder_index://completed
+/// 再次索引同一个文件夹时只处理新增或修改过的文件 并移除已经不存在的文件
+/// # Arguments
+/// * `path` - 文件夹路径
+/// * `recursive` - 是否包含子文件夹 默认为 true
+/// # Returns
+/// * `Result<bool, String>` - true 表示已加入队列 false 表示已经在队列中
+#[tauri::command]
+pub fn index_folder(app: AppHandle, path: String, recursive: Option<bool>) -> Result<bool, String> {
+    let folder = PendingFolder {
+        folder: normalize_folder(&path)?,
+        recursive: recursive.unwrap_or(true),
+    };
+    enqueue_folder(&app, folder)
+}
+
+/// 获取文件夹索引的结果 按路径排序
+/// # Arguments
+/// * `path` - 文件夹路径（与 index_folder 传入的相同）
+/// # Returns
+/// * `Result<Vec<FolderIndexEntry>, String>` - 已索引的图片
+#[tauri::command]
+pub fn get_folder_index(app: AppHandle, path: String) -> Result<Vec<FolderIndexEntry>, String> {
+    let folder = normalize_folder(&path)?;
+    let connection = open_catalog(&app)?;
+    let mut statement = connection
+        .prepare(
+            "SELECT f.file_path, i.width, i.height, f.file_size, f.modified_ms, f.thumbnail,
+                i.capture_time
+             FROM folder_files f JOIN images i ON i.file_path = f.file_path
+             WHERE f.folder = ?1
+             ORDER BY f.file_path",
+        )
+        .map_err(|e| format!("读取文件夹索引失败: {e}"))?;
+    let entries = statement
+        .query_map(params![folder], |row| {
+            let file_path: String = row.get(0)?;
+            Ok(FolderIndexEntry {
+                cached: check_file_cache_exists(&file_path),
+                file_path,
+                width: row.get(1)?,
+                height: row.get(2)?,
+                file_size: row.get::<_, i64>(3)? as u64,
+                modified_ms: row.get::<_, i64>(4)? as u64,
+                thumbnail_path: row.get(5)?,
+                capture_time: row.get(6)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("读取文件夹索引失败: {e}"))?;
+    Ok(entries)
+}
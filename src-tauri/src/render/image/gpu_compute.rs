@@ -0,0 +1,461 @@
+use image::{DynamicImage, RgbaImage};
+use rayon::prelude::*;
+
+// GPU 计算：预处理中的 2x2 缩小和像素格式转换（RGB8 / RGB16 / RGBA16 -> RGBA8）
+// 启用 gpu 特性并且能找到可用的显卡时使用 wgpu 计算着色器 否则自动回退到 rayon 的 CPU 实现
+// 两条路径的结果逐字节一致：缩小时边缘像素与自身平均 16 位转 8 位的舍入与 image 库相同
+
+// 像素数少于这个值时直接用 CPU 上传和读回的开销比计算本身还大
+const MIN_GPU_PIXELS: u64 = 4 * 1024 * 1024;
+
+/// 2x2 平均缩小 宽高向上取整 奇数边缘的像素与自身平均
+/// # Arguments
+/// * `img` - RGBA8 图片
+/// # Returns
+/// * `RgbaImage` - 缩小后的图片
+pub fn downsample_half(img: &RgbaImage) -> RgbaImage {
+    let (width, height) = img.dimensions();
+    if width as u64 * height as u64 >= MIN_GPU_PIXELS {
+        match backend::downsample_half(img.as_raw(), width, height) {
+            Ok(Some(pixels)) => {
+                if let Some(output) =
+                    RgbaImage::from_raw(width.div_ceil(2), height.div_ceil(2), pixels)
+                {
+                    return output;
+                }
+            }
+            Ok(None) => {}
+            Err(e) => println!("[RUST] GPU 缩小失败 改用 CPU: {e}"),
+        }
+    }
+    downsample_half_cpu(img)
+}
+
+/// 转换为 RGBA8 RGB8 和 16 位图片在 GPU 可用时用 GPU 转换 其他格式交给 image 库
+/// # Arguments
+/// * `img` - 解码后的图片
+/// # Returns
+/// * `RgbaImage` - RGBA8 图片
+pub fn convert_to_rgba8(img: &DynamicImage) -> RgbaImage {
+    let (width, height) = (img.width(), img.height());
+    if width as u64 * height as u64 >= MIN_GPU_PIXELS {
+        let converted = match img {
+            DynamicImage::ImageRgb8(rgb) => {
+                backend::convert_to_rgba8(rgb.as_raw(), SourceFormat::Rgb8, width, height)
+            }
+            DynamicImage::ImageRgb16(rgb) => backend::convert_to_rgba8(
+                bytemuck::cast_slice(rgb.as_raw()),
+                SourceFormat::Rgb16,
+                width,
+                height,
+            ),
+            DynamicImage::ImageRgba16(rgba) => backend::convert_to_rgba8(
+                bytemuck::cast_slice(rgba.as_raw()),
+                SourceFormat::Rgba16,
+                width,
+                height,
+            ),
+            _ => Ok(None),
+        };
+        match converted {
+            Ok(Some(pixels)) => {
+                if let Some(output) = RgbaImage::from_raw(width, height, pixels) {
+                    return output;
+                }
+            }
+            Ok(None) => {}
+            Err(e) => println!("[RUST] GPU 格式转换失败 改用 CPU: {e}"),
+        }
+    }
+    img.to_rgba8()
+}
+
+/// GPU 计算是否可用（编译了 gpu 特性并且找到了显卡）
+pub fn is_gpu_compute_available() -> bool {
+    backend::is_available()
+}
+
+// GPU 可以转换的源格式 16 位数据按本机字节序排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(feature = "gpu"), allow(dead_code))]
+enum SourceFormat {
+    Rgb8 = 0,
+    Rgb16 = 1,
+    Rgba16 = 2,
+}
+
+fn downsample_half_cpu(img: &RgbaImage) -> RgbaImage {
+    let (width, height) = img.dimensions();
+    let (dst_width, dst_height) = (width.div_ceil(2), height.div_ceil(2));
+    let src = img.as_raw();
+    let row_len = width as usize * 4;
+    let mut output = vec![0u8; dst_width as usize * dst_height as usize * 4];
+    output
+        .par_chunks_mut(dst_width as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            let y0 = (y * 2).min(height as usize - 1) * row_len;
+            let y1 = (y * 2 + 1).min(height as usize - 1) * row_len;
+            for x in 0..dst_width as usize {
+                let x0 = (x * 2).min(width as usize - 1) * 4;
+                let x1 = (x * 2 + 1).min(width as usize - 1) * 4;
+                for c in 0..4 {
+                    let sum = src[y0 + x0 + c] as u32
+                        + src[y0 + x1 + c] as u32
+                        + src[y1 + x0 + c] as u32
+                        + src[y1 + x1 + c] as u32;
+                    row[x * 4 + c] = ((sum + 2) / 4) as u8;
+                }
+            }
+        });
+    RgbaImage::from_raw(dst_width, dst_height, output).expect("缩小后的像素长度与尺寸一致")
+}
+
+#[cfg(feature = "gpu")]
+mod backend {
+    use std::borrow::Cow;
+    use std::sync::{mpsc, OnceLock};
+
+    use wgpu::util::DeviceExt;
+
+    use super::SourceFormat;
+
+    const DOWNSAMPLE_SHADER: &str = r#"
+struct Params { src_width: u32, src_height: u32, dst_width: u32, dst_height: u32 }
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> src: array<u32>;
+@group(0) @binding(2) var<storage, read_write> dst: array<u32>;
+
+fn channels(p: u32) -> vec4<u32> {
+    return vec4<u32>(p & 0xffu, (p >> 8u) & 0xffu, (p >> 16u) & 0xffu, p >> 24u);
+}
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.dst_width || id.y >= params.dst_height) {
+        return;
+    }
+    let w = params.src_width;
+    let x0 = min(id.x * 2u, w - 1u);
+    let x1 = min(id.x * 2u + 1u, w - 1u);
+    let y0 = min(id.y * 2u, params.src_height - 1u);
+    let y1 = min(id.y * 2u + 1u, params.src_height - 1u);
+    let sum = channels(src[y0 * w + x0]) + channels(src[y0 * w + x1])
+        + channels(src[y1 * w + x0]) + channels(src[y1 * w + x1]);
+    let avg = (sum + vec4<u32>(2u)) / 4u;
+    dst[id.y * params.dst_width + id.x] = avg.x | (avg.y << 8u) | (avg.z << 16u) | (avg.w << 24u);
+}
+"#;
+
+    const CONVERT_SHADER: &str = r#"
+struct Params { pixel_count: u32, format: u32, row_pixels: u32, _pad: u32 }
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> src: array<u32>;
+@group(0) @binding(2) var<storage, read_write> dst: array<u32>;
+
+fn byte_at(i: u32) -> u32 {
+    return (src[i / 4u] >> ((i % 4u) * 8u)) & 0xffu;
+}
+
+fn sample16(i: u32) -> u32 {
+    let value = byte_at(i * 2u) | (byte_at(i * 2u + 1u) << 8u);
+    return (value + 128u) / 257u;
+}
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.y * params.row_pixels + id.x;
+    if (id.x >= params.row_pixels || index >= params.pixel_count) {
+        return;
+    }
+    var rgba = vec4<u32>(0u, 0u, 0u, 255u);
+    if (params.format == 0u) {
+        rgba = vec4<u32>(byte_at(index * 3u), byte_at(index * 3u + 1u), byte_at(index * 3u + 2u), 255u);
+    } else if (params.format == 1u) {
+        rgba = vec4<u32>(sample16(index * 3u), sample16(index * 3u + 1u), sample16(index * 3u + 2u), 255u);
+    } else {
+        rgba = vec4<u32>(sample16(index * 4u), sample16(index * 4u + 1u), sample16(index * 4u + 2u), sample16(index * 4u + 3u));
+    }
+    dst[index] = rgba.x | (rgba.y << 8u) | (rgba.z << 16u) | (rgba.w << 24u);
+}
+"#;
+
+    // 每个工作组 16x16 个线程
+    const WORKGROUP_SIZE: u32 = 16;
+    // 转换时每行的线程数 一维数据按这个宽度排成二维 避免超过单个维度的工作组数量上限
+    const CONVERT_ROW_PIXELS: u32 = 4096;
+
+    struct GpuContext {
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        downsample: wgpu::ComputePipeline,
+        convert: wgpu::ComputePipeline,
+        // 单个存储缓冲区的最大字节数 大图按条带分批处理
+        max_binding_bytes: u64,
+    }
+
+    // 第一次使用时初始化 找不到显卡时为 None 之后一直使用 CPU
+    fn context() -> Option<&'static GpuContext> {
+        static CONTEXT: OnceLock<Option<GpuContext>> = OnceLock::new();
+        CONTEXT
+            .get_or_init(|| {
+                let context = create_context();
+                match &context {
+                    Some(_) => println!("[RUST] GPU 计算已启用"),
+                    None => println!("[RUST] 没有可用的 GPU 预处理使用 CPU"),
+                }
+                context
+            })
+            .as_ref()
+    }
+
+    fn create_context() -> Option<GpuContext> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        }))?;
+        // 软件渲染器比 rayon 还慢
+        if adapter.get_info().device_type == wgpu::DeviceType::Cpu {
+            return None;
+        }
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("images-gl-compute"),
+                required_features: wgpu::Features::empty(),
+                required_limits: adapter.limits(),
+                memory_hints: wgpu::MemoryHints::Performance,
+            },
+            None,
+        ))
+        .ok()?;
+        let pipeline = |source: &str, label: &str| {
+            let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(label),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(source)),
+            });
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: None,
+                module: &module,
+                entry_point: "main",
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let downsample = pipeline(DOWNSAMPLE_SHADER, "downsample");
+        let convert = pipeline(CONVERT_SHADER, "convert");
+        let limits = device.limits();
+        let max_binding_bytes = (limits.max_storage_buffer_binding_size as u64)
+            .min(limits.max_buffer_size)
+            .min(1 << 30);
+        Some(GpuContext {
+            device,
+            queue,
+            downsample,
+            convert,
+            max_binding_bytes,
+        })
+    }
+
+    pub fn is_available() -> bool {
+        context().is_some()
+    }
+
+    // 执行一次计算 返回输出缓冲区的内容
+    fn dispatch(
+        context: &GpuContext,
+        pipeline: &wgpu::ComputePipeline,
+        params: [u32; 4],
+        input: &[u8],
+        output_bytes: u64,
+        workgroups: (u32, u32),
+    ) -> Result<Vec<u8>, String> {
+        let device = &context.device;
+        // 存储缓冲区按 u32 访问 长度补齐到 4 的倍数
+        let mut padded;
+        let input = if input.len().is_multiple_of(4) {
+            input
+        } else {
+            padded = input.to_vec();
+            padded.resize(input.len().next_multiple_of(4), 0);
+            &padded
+        };
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&params),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let input_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: input,
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let output_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: output_bytes,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: output_bytes,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: input_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: output_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(workgroups.0, workgroups.1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&output_buffer, 0, &readback, 0, output_bytes);
+        context.queue.submit(Some(encoder.finish()));
+
+        let slice = readback.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .map_err(|e| format!("等待 GPU 结果失败: {e}"))?
+            .map_err(|e| format!("读取 GPU 结果失败: {e}"))?;
+        let data = slice.get_mapped_range().to_vec();
+        readback.unmap();
+        Ok(data)
+    }
+
+    pub fn downsample_half(
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<Option<Vec<u8>>, String> {
+        let Some(context) = context() else {
+            return Ok(None);
+        };
+        let dst_width = width.div_ceil(2);
+        let row_bytes = width as u64 * 4;
+        // 每批的源行数取偶数 保证 2x2 的配对不跨批
+        let band_rows = ((context.max_binding_bytes / row_bytes) as u32 & !1).min(height);
+        if band_rows < 2 && height > 1 {
+            return Ok(None);
+        }
+        let mut output = Vec::with_capacity(dst_width as usize * height.div_ceil(2) as usize * 4);
+        let mut y = 0;
+        while y < height {
+            let rows = band_rows.max(1).min(height - y);
+            let dst_rows = rows.div_ceil(2);
+            let start = y as usize * row_bytes as usize;
+            let end = start + rows as usize * row_bytes as usize;
+            let band = dispatch(
+                context,
+                &context.downsample,
+                [width, rows, dst_width, dst_rows],
+                &pixels[start..end],
+                dst_width as u64 * dst_rows as u64 * 4,
+                (
+                    dst_width.div_ceil(WORKGROUP_SIZE),
+                    dst_rows.div_ceil(WORKGROUP_SIZE),
+                ),
+            )?;
+            output.extend_from_slice(&band);
+            y += rows;
+        }
+        Ok(Some(output))
+    }
+
+    pub fn convert_to_rgba8(
+        pixels: &[u8],
+        format: SourceFormat,
+        width: u32,
+        height: u32,
+    ) -> Result<Option<Vec<u8>>, String> {
+        let Some(context) = context() else {
+            return Ok(None);
+        };
+        let bytes_per_pixel: u64 = match format {
+            SourceFormat::Rgb8 => 3,
+            SourceFormat::Rgb16 => 6,
+            SourceFormat::Rgba16 => 8,
+        };
+        let row_bytes = width as u64 * bytes_per_pixel;
+        // 输入和输出都不能超过单个缓冲区的上限 输入每像素最多 8 字节
+        let band_rows = (context.max_binding_bytes / row_bytes.max(width as u64 * 4)) as u32;
+        if band_rows == 0 {
+            return Ok(None);
+        }
+        let mut output = Vec::with_capacity(width as usize * height as usize * 4);
+        let mut y = 0;
+        while y < height {
+            let rows = band_rows.min(height - y);
+            let pixel_count = width * rows;
+            let start = y as usize * row_bytes as usize;
+            let end = start + rows as usize * row_bytes as usize;
+            let band = dispatch(
+                context,
+                &context.convert,
+                [pixel_count, format as u32, CONVERT_ROW_PIXELS, 0],
+                &pixels[start..end],
+                pixel_count as u64 * 4,
+                (
+                    CONVERT_ROW_PIXELS / WORKGROUP_SIZE,
+                    pixel_count
+                        .div_ceil(CONVERT_ROW_PIXELS)
+                        .div_ceil(WORKGROUP_SIZE),
+                ),
+            )?;
+            output.extend_from_slice(&band);
+            y += rows;
+        }
+        Ok(Some(output))
+    }
+}
+
+#[cfg(not(feature = "gpu"))]
+mod backend {
+    use super::SourceFormat;
+
+    pub fn is_available() -> bool {
+        false
+    }
+
+    pub fn downsample_half(
+        _pixels: &[u8],
+        _width: u32,
+        _height: u32,
+    ) -> Result<Option<Vec<u8>>, String> {
+        Ok(None)
+    }
+
+    pub fn convert_to_rgba8(
+        _pixels: &[u8],
+        _format: SourceFormat,
+        _width: u32,
+        _height: u32,
+    ) -> Result<Option<Vec<u8>>, String> {
+        Ok(None)
+    }
+}
@@ -0,0 +1,190 @@
+use std::path::Path;
+
+use tauri::ipc::Response;
+
+use super::cache::load_cached_metadata;
+use super::exif::read_pixel_size_mm;
+use super::types::{OverlayKind, OverlaySpec, OverlayUnit};
+
+// 网格和标尺叠加层：按 chunk 生成透明背景的叠加图 前端像普通 chunk 一样叠在图片上显示
+// 没有画布叠加逻辑的前端也能显示校准过的网格 间距可以用像素或物理单位（需要比例信息）
+// 所有线都按图片坐标计算 相邻 chunk 拼起来是连续的
+
+// 线间距的最小像素数 再小就整片都是线了
+const MIN_OVERLAY_SPACING: f64 = 4.0;
+// 最大线宽
+const MAX_OVERLAY_LINE_WIDTH: u32 = 64;
+// 标尺每个主刻度之间的小格数
+const RULER_SUBDIVISIONS: u64 = 10;
+// 标尺刻度的长度（像素）：主刻度、半刻度、小刻度
+const RULER_MAJOR_TICK: u32 = 48;
+const RULER_HALF_TICK: u32 = 32;
+const RULER_MINOR_TICK: u32 = 16;
+// 默认颜色
+const DEFAULT_OVERLAY_COLOR: [u8; 4] = [0, 255, 255, 255];
+
+/// 获取图片每个像素对应的物理尺寸（毫米）
+/// 从图片的分辨率信息（EXIF / TIFF 的 XResolution）读取 没有时返回 None
+/// # Arguments
+/// * `file_path` - 图片文件路径
+#[tauri::command]
+pub fn get_pixel_size(file_path: String) -> Option<f64> {
+    read_pixel_size_mm(Path::new(&file_path))
+}
+
+/// 获取一个 chunk 位置的网格或标尺叠加层（需要先完成预处理）
+/// 网格覆盖整张图片 标尺只沿图片的上边和左边绘制 其余位置是透明的
+/// 数据格式与 chunk 相同：宽度(4字节) + 高度(4字节) + RGBA 数据
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `x` / `y` - chunk 的列、行索引
+/// * `spec` - 叠加层的样式和间距
+#[tauri::command]
+pub fn get_overlay_chunk(
+    file_path: String,
+    x: u32,
+    y: u32,
+    spec: OverlaySpec,
+) -> Result<Response, String> {
+    let metadata = load_cached_metadata(&file_path)?;
+    let info = metadata
+        .chunks
+        .iter()
+        .find(|c| c.chunk_x == x && c.chunk_y == y)
+        .ok_or_else(|| format!("chunk 不存在: ({x}, {y})"))?;
+    let step = spacing_in_pixels(&file_path, &spec)?;
+    let line_width = spec.line_width.unwrap_or(1);
+    if !(1..=MAX_OVERLAY_LINE_WIDTH).contains(&line_width) {
+        return Err(format!(
+            "线宽必须在 1 到 {MAX_OVERLAY_LINE_WIDTH} 之间: {line_width}"
+        ));
+    }
+    let color = match &spec.color {
+        Some(hex) => parse_hex_color(hex)?,
+        None => DEFAULT_OVERLAY_COLOR,
+    };
+
+    // 每列（行）上的线长度 网格线贯穿整张图片 标尺刻度从边缘向内延伸
+    let (columns, rows) = match spec.kind {
+        OverlayKind::Grid => (
+            line_coverage(info.x, info.width, step, line_width, |_| u32::MAX),
+            line_coverage(info.y, info.height, step, line_width, |_| u32::MAX),
+        ),
+        OverlayKind::Ruler => {
+            // 小格太密时只画主刻度
+            let subdivisions = if step / RULER_SUBDIVISIONS as f64 >= MIN_OVERLAY_SPACING {
+                RULER_SUBDIVISIONS
+            } else {
+                1
+            };
+            let tick_length = |index: u64| {
+                if index.is_multiple_of(subdivisions) {
+                    RULER_MAJOR_TICK
+                } else if index % subdivisions == subdivisions / 2 {
+                    RULER_HALF_TICK
+                } else {
+                    RULER_MINOR_TICK
+                }
+            };
+            let minor_step = step / subdivisions as f64;
+            (
+                line_coverage(info.x, info.width, minor_step, line_width, tick_length),
+                line_coverage(info.y, info.height, minor_step, line_width, tick_length),
+            )
+        }
+    };
+
+    let mut output = Vec::with_capacity(8 + info.width as usize * info.height as usize * 4);
+    output.extend_from_slice(&info.width.to_be_bytes());
+    output.extend_from_slice(&info.height.to_be_bytes());
+    for (row, &row_length) in rows.iter().enumerate() {
+        let py = info.y + row as u32;
+        for (column, &column_length) in columns.iter().enumerate() {
+            let px = info.x + column as u32;
+            let visible = match spec.kind {
+                OverlayKind::Grid => row_length > 0 || column_length > 0,
+                // 上边的刻度是竖线 左边的刻度是横线 再加上沿边缘的基线
+                OverlayKind::Ruler => {
+                    py < column_length || px < row_length || py < line_width || px < line_width
+                }
+            };
+            output.extend_from_slice(&if visible { color } else { [0; 4] });
+        }
+    }
+    Ok(Response::new(output))
+}
+
+// 把间距换算为像素
+fn spacing_in_pixels(file_path: &str, spec: &OverlaySpec) -> Result<f64, String> {
+    if !spec.spacing.is_finite() || spec.spacing <= 0.0 {
+        return Err(format!("间距必须大于 0: {}", spec.spacing));
+    }
+    let unit_mm = match spec.unit {
+        OverlayUnit::Pixel => None,
+        OverlayUnit::Millimeter => Some(1.0),
+        OverlayUnit::Micrometer => Some(0.001),
+    };
+    let step = match unit_mm {
+        None => spec.spacing,
+        Some(unit_mm) => {
+            let pixel_size = spec
+                .pixel_size_mm
+                .or_else(|| read_pixel_size_mm(Path::new(file_path)))
+                .ok_or("图片没有分辨率信息 请指定每个像素的物理尺寸")?;
+            if !pixel_size.is_finite() || pixel_size <= 0.0 {
+                return Err(format!("像素尺寸无效: {pixel_size}"));
+            }
+            spec.spacing * unit_mm / pixel_size
+        }
+    };
+    if step < MIN_OVERLAY_SPACING {
+        return Err(format!(
+            "间距换算后只有 {step:.2} 像素 至少需要 {MIN_OVERLAY_SPACING} 像素"
+        ));
+    }
+    Ok(step)
+}
+
+// 计算 [start, start + len) 中每个像素被哪条线覆盖 值为 length_of(线的序号) 没有线时为 0
+// 第 k 条线从 round(k * step) 开始 宽 line_width 像素
+fn line_coverage(
+    start: u32,
+    len: u32,
+    step: f64,
+    line_width: u32,
+    length_of: impl Fn(u64) -> u32,
+) -> Vec<u32> {
+    let mut coverage = vec![0u32; len as usize];
+    let end = start as u64 + len as u64;
+    let first = ((start as f64 - line_width as f64) / step).floor().max(0.0) as u64;
+    let mut index = first;
+    loop {
+        let line_start = (index as f64 * step).round() as u64;
+        if line_start >= end {
+            break;
+        }
+        let length = length_of(index);
+        let from = line_start.max(start as u64);
+        let to = (line_start + line_width as u64).min(end);
+        for position in from..to {
+            let value = &mut coverage[(position - start as u64) as usize];
+            *value = (*value).max(length);
+        }
+        index += 1;
+    }
+    coverage
+}
+
+// 解析 #rrggbb 或 #rrggbbaa
+fn parse_hex_color(hex: &str) -> Result<[u8; 4], String> {
+    let digits = hex.strip_prefix('#').unwrap_or(hex);
+    if !(digits.len() == 6 || digits.len() == 8) || !digits.is_ascii() {
+        return Err(format!("颜色格式无效: {hex}"));
+    }
+    let mut color = [255u8; 4];
+    for (i, value) in color.iter_mut().take(digits.len() / 2).enumerate() {
+        *value = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16)
+            .map_err(|_| format!("颜色格式无效: {hex}"))?;
+    }
+    Ok(color)
+}
@@ -0,0 +1,335 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+use tauri::{AppHandle, Window};
+
+use super::cache::clear_file_cache;
+use super::catalog::forget_catalog_image;
+use super::commands::load_user_image;
+use super::startup_preload::forget_recent_image;
+use super::types::ImageMetadata;
+use super::utils::app_data_subdir;
+use super::window_state::{open_images, set_window_image};
+use crate::utils::time::{get_time, Stopwatch};
+
+// 前端传入的图片数据：粘贴、JS 下载、canvas 生成的图片不需要前端自己写临时文件
+// 数据保存到应用数据目录的 uploads 子目录下 文件名取自内容的哈希 之后与普通图片一样预处理
+// 重复传入同一张图片时直接复用已有文件和缓存
+// 大文件可以分段传入：begin_image_upload -> append_image_upload（多次） -> finish_image_upload
+// 传输中的数据写入 .part 文件 完成后改名 取消、超时或应用崩溃留下的 .part 文件会被清理
+// 已完成的文件总大小超过上限时 从最久没有使用的开始删除 窗口中打开着的图片保留
+// 删除的图片同时清理它的缓存、最近打开的记录和目录中的记录
+
+// 传入的图片在应用数据目录下的保存位置
+const UPLOAD_DIR: &str = "uploads";
+// 传输中的文件后缀
+const PART_SUFFIX: &str = ".part";
+// 分段传输超过这个时间没有新数据时视为放弃
+const STALE_UPLOAD: Duration = Duration::from_secs(60 * 60);
+// 已完成文件的总大小上限
+const MAX_UPLOAD_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+
+// 分段传输中的图片
+struct PendingUpload {
+    name: String,
+    part_path: PathBuf,
+    writer: BufWriter<File>,
+    hasher: blake3::Hasher,
+    // 第一段数据的开头 用于识别格式
+    head: Vec<u8>,
+    bytes: u64,
+    last_activity: Stopwatch,
+}
+
+// 每个传输单独加锁 写文件时不占用全局的传输表 结束或取消后为 None
+type SharedUpload = Arc<Mutex<Option<PendingUpload>>>;
+
+static PENDING_UPLOADS: OnceLock<Mutex<HashMap<String, SharedUpload>>> = OnceLock::new();
+// 同一毫秒内开始多个传输时区分
+static UPLOAD_SEQ: AtomicU32 = AtomicU32::new(0);
+
+fn pending_uploads() -> &'static Mutex<HashMap<String, SharedUpload>> {
+    PENDING_UPLOADS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// 从传输表中取出传输 remove 为 true 时同时移除
+fn find_upload(upload_id: &str, remove: bool) -> Result<Option<SharedUpload>, String> {
+    let mut uploads = pending_uploads()
+        .lock()
+        .map_err(|e| format!("获取传输状态锁失败: {e}"))?;
+    Ok(if remove {
+        uploads.remove(upload_id)
+    } else {
+        uploads.get(upload_id).cloned()
+    })
+}
+
+// 等待正在写入的分段结束 取出传输 已经结束时返回 None
+fn take_upload(upload: &SharedUpload) -> Option<PendingUpload> {
+    upload.lock().unwrap_or_else(|e| e.into_inner()).take()
+}
+
+/// 处理前端传入的图片数据
+/// 数据保存为应用数据目录下的文件后走正常的预处理流程
+/// 保存后的路径会记录为调用窗口当前打开的图片 前端可以通过 get_window_state 取得
+/// # Arguments
+/// * `data` - 图片文件的完整内容（PNG、TIFF 等编码后的数据）
+/// * `name` - 原始文件名 无法从内容识别格式时使用其中的扩展名
+/// # Returns
+/// * `Result<ImageMetadata, String>` - 图片元数据
+#[tauri::command]
+pub fn process_image_bytes(
+    app: AppHandle,
+    window: Window,
+    data: Vec<u8>,
+    name: String,
+) -> Result<ImageMetadata, String> {
+    println!("[RUST] 收到图片数据: {name} ({} 字节)", data.len());
+    let dir = app_data_subdir(&app, UPLOAD_DIR)?;
+    let part_path = dir.join(format!("{}{PART_SUFFIX}", new_upload_id()));
+    fs::write(&part_path, &data).map_err(|e| format!("保存图片数据失败: {e}"))?;
+    let hash = blake3::hash(&data);
+    let extension = upload_extension(&name, &data);
+    let file_path = complete_upload(&app, &dir, &part_path, &hash, &extension)?;
+    open_upload(&window, &file_path)
+}
+
+/// 开始分段传入图片数据
+/// # Arguments
+/// * `name` - 原始文件名 无法从内容识别格式时使用其中的扩展名
+/// # Returns
+/// * `Result<String, String>` - 传输 ID 之后的 append / finish / cancel 使用
+#[tauri::command]
+pub fn begin_image_upload(app: AppHandle, name: String) -> Result<String, String> {
+    let dir = app_data_subdir(&app, UPLOAD_DIR)?;
+    prune_stale_uploads(&dir);
+
+    let upload_id = new_upload_id();
+    let part_path = dir.join(format!("{upload_id}{PART_SUFFIX}"));
+    let file = File::create(&part_path).map_err(|e| format!("创建临时文件失败: {e}"))?;
+    pending_uploads()
+        .lock()
+        .map_err(|e| format!("获取传输状态锁失败: {e}"))?
+        .insert(
+            upload_id.clone(),
+            Arc::new(Mutex::new(Some(PendingUpload {
+                name,
+                part_path,
+                writer: BufWriter::new(file),
+                hasher: blake3::Hasher::new(),
+                head: Vec::new(),
+                bytes: 0,
+                last_activity: Stopwatch::start(),
+            }))),
+        );
+    Ok(upload_id)
+}
+
+/// 追加一段图片数据
+/// # Arguments
+/// * `upload_id` - begin_image_upload 返回的传输 ID
+/// * `data` - 按顺序传入的一段数据
+/// # Returns
+/// * `Result<u64, String>` - 目前已收到的字节数
+#[tauri::command]
+pub fn append_image_upload(upload_id: String, data: Vec<u8>) -> Result<u64, String> {
+    // 只在查找时持有全局锁 其他传输的分段可以同时写入
+    let shared = find_upload(&upload_id, false)?
+        .ok_or_else(|| format!("传输不存在或已结束: {upload_id}"))?;
+    let mut guard = shared.lock().unwrap_or_else(|e| e.into_inner());
+    let upload = guard
+        .as_mut()
+        .ok_or_else(|| format!("传输不存在或已结束: {upload_id}"))?;
+    upload
+        .writer
+        .write_all(&data)
+        .map_err(|e| format!("写入图片数据失败: {e}"))?;
+    upload.hasher.update(&data);
+    if upload.head.len() < 64 {
+        let needed = (64 - upload.head.len()).min(data.len());
+        upload.head.extend_from_slice(&data[..needed]);
+    }
+    upload.bytes += data.len() as u64;
+    upload.last_activity = Stopwatch::start();
+    Ok(upload.bytes)
+}
+
+/// 结束分段传输并处理图片
+/// 保存后的路径会记录为调用窗口当前打开的图片
+/// # Arguments
+/// * `upload_id` - begin_image_upload 返回的传输 ID
+/// # Returns
+/// * `Result<ImageMetadata, String>` - 图片元数据
+#[tauri::command]
+pub fn finish_image_upload(
+    app: AppHandle,
+    window: Window,
+    upload_id: String,
+) -> Result<ImageMetadata, String> {
+    let upload = find_upload(&upload_id, true)?
+        .and_then(|shared| take_upload(&shared))
+        .ok_or_else(|| format!("传输不存在或已结束: {upload_id}"))?;
+    println!(
+        "[RUST] 分段传输完成: {} ({} 字节)",
+        upload.name, upload.bytes
+    );
+    let PendingUpload {
+        name,
+        part_path,
+        writer,
+        hasher,
+        head,
+        ..
+    } = upload;
+    if let Err(e) = writer.into_inner().map_err(|e| e.into_error()) {
+        let _ = fs::remove_file(&part_path);
+        return Err(format!("写入图片数据失败: {e}"));
+    }
+
+    let dir = app_data_subdir(&app, UPLOAD_DIR)?;
+    let extension = upload_extension(&name, &head);
+    let file_path = complete_upload(&app, &dir, &part_path, &hasher.finalize(), &extension)?;
+    open_upload(&window, &file_path)
+}
+
+/// 取消分段传输 删除已收到的数据
+#[tauri::command]
+pub fn cancel_image_upload(upload_id: String) -> Result<(), String> {
+    if let Some(upload) = find_upload(&upload_id, true)?.and_then(|shared| take_upload(&shared)) {
+        drop(upload.writer);
+        let _ = fs::remove_file(&upload.part_path);
+        println!("[RUST] 分段传输已取消: {}", upload.name);
+    }
+    Ok(())
+}
+
+fn new_upload_id() -> String {
+    let seq = UPLOAD_SEQ.fetch_add(1, Ordering::Relaxed) & 0xffff;
+    format!("upload_{:x}{seq:04x}", get_time())
+}
+
+// 优先按内容识别格式 识别不了时使用文件名中的扩展名 是否支持由 validate_image_path 判断
+fn upload_extension(name: &str, head: &[u8]) -> String {
+    image::guess_format(head)
+        .ok()
+        .and_then(|format| format.extensions_str().first().copied())
+        .map(str::to_string)
+        .or_else(|| {
+            Path::new(name)
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(str::to_ascii_lowercase)
+        })
+        .unwrap_or_default()
+}
+
+// 把传输完成的 .part 文件改名为按内容哈希命名的文件 已有相同内容的文件时直接复用
+// 复用时更新文件的修改时间 清理时按修改时间判断最近是否使用过
+fn complete_upload(
+    app: &AppHandle,
+    dir: &Path,
+    part_path: &Path,
+    hash: &blake3::Hash,
+    extension: &str,
+) -> Result<String, String> {
+    let file_path = dir.join(format!("upload_{}.{extension}", &hash.to_hex()[..16]));
+    if file_path.exists() {
+        let _ = fs::remove_file(part_path);
+        println!("[RUST] 图片数据已存在: {}", file_path.display());
+        let touched = File::options()
+            .write(true)
+            .open(&file_path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        if let Err(e) = touched {
+            println!("[RUST] 更新图片修改时间失败: {e}");
+        }
+    } else {
+        fs::rename(part_path, &file_path).map_err(|e| {
+            let _ = fs::remove_file(part_path);
+            format!("保存图片数据失败: {e}")
+        })?;
+        println!("[RUST] 图片数据已保存: {}", file_path.display());
+        prune_completed_uploads(app, dir, &file_path);
+    }
+    Ok(file_path.to_string_lossy().to_string())
+}
+
+fn open_upload(window: &Window, file_path: &str) -> Result<ImageMetadata, String> {
+    let metadata = load_user_image(file_path)?;
+    set_window_image(window.label(), file_path);
+    Ok(metadata)
+}
+
+// 清理放弃的分段传输 以及应用崩溃时留下的 .part 文件
+fn prune_stale_uploads(dir: &Path) {
+    if let Ok(mut uploads) = pending_uploads().lock() {
+        uploads.retain(|_, shared| {
+            // 正在写入的传输不会超时 不等待它的锁
+            let Ok(mut guard) = shared.try_lock() else {
+                return true;
+            };
+            let Some(upload) = guard.as_ref() else {
+                return false;
+            };
+            if upload.last_activity.elapsed() < STALE_UPLOAD {
+                return true;
+            }
+            if let Some(upload) = guard.take() {
+                println!("[RUST] 分段传输超时，已放弃: {}", upload.name);
+                drop(upload.writer);
+                let _ = fs::remove_file(&upload.part_path);
+            }
+            false
+        });
+    }
+    let now = SystemTime::now();
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let is_part = path.to_string_lossy().ends_with(PART_SUFFIX);
+        let modified = entry.metadata().and_then(|m| m.modified()).ok();
+        let stale = modified
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age >= STALE_UPLOAD);
+        if is_part && stale {
+            let _ = fs::remove_file(&path);
+        }
+    }
+}
+
+// 已完成的文件总大小超过上限时 从最久没有修改的开始删除 刚保存的文件和窗口中打开着的图片保留
+fn prune_completed_uploads(app: &AppHandle, dir: &Path, keep: &Path) {
+    let open: HashSet<PathBuf> = open_images().into_iter().map(PathBuf::from).collect();
+    let mut files: Vec<(SystemTime, u64, PathBuf)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| !entry.path().to_string_lossy().ends_with(PART_SUFFIX))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+        })
+        .collect();
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    files.sort_by_key(|(modified, _, _)| *modified);
+    for (_, size, path) in files {
+        if total <= MAX_UPLOAD_BYTES {
+            break;
+        }
+        if path == keep || open.contains(&path) || fs::remove_file(&path).is_err() {
+            continue;
+        }
+        println!("[RUST] 删除较早传入的图片: {}", path.display());
+        total -= size;
+        let file_path = path.to_string_lossy().to_string();
+        if let Err(e) = clear_file_cache(file_path.clone()) {
+            println!("[RUST] 清理已删除图片的缓存失败: {e}");
+        }
+        forget_recent_image(app, &file_path);
+        forget_catalog_image(app, &file_path);
+    }
+}
@@ -0,0 +1,153 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use rusqlite::{params, Connection};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tauri::AppHandle;
+
+use super::cache::{image_cache_dir, load_source_info};
+use super::types::{JobNotification, JobRecord};
+use super::utils::app_data_subdir;
+
+// 任务历史：每个后台预处理和导出任务结束时记录一条 保存在应用数据目录的 SQLite 数据库中
+// 记录处理了什么、何时、耗时多久、由哪个应用版本处理、源文件的内容指纹
+// 用于实验室复现结果和排查问题 只追加不修改
+
+// 数据库文件 位于应用数据目录的 history 子目录下
+const JOB_HISTORY_DB: &str = "jobs.sqlite";
+// 其他连接正在写入时的等待时间
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+// get_job_history 默认返回的条数
+const DEFAULT_HISTORY_LIMIT: u32 = 200;
+
+fn open_history(app: &AppHandle) -> Result<Connection, String> {
+    let path: PathBuf = app_data_subdir(app, "history")?.join(JOB_HISTORY_DB);
+    let connection = Connection::open(&path).map_err(|e| format!("打开任务历史失败: {e}"))?;
+    connection
+        .busy_timeout(BUSY_TIMEOUT)
+        .map_err(|e| format!("打开任务历史失败: {e}"))?;
+    connection
+        .execute_batch(
+            "CREATE TABLE IF NOT EXISTS jobs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                kind TEXT NOT NULL,
+                status TEXT NOT NULL,
+                job_id TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                output_path TEXT,
+                error TEXT,
+                started_ms INTEGER NOT NULL,
+                finished_ms INTEGER NOT NULL,
+                duration_ms INTEGER NOT NULL,
+                app_version TEXT NOT NULL,
+                source_hash TEXT
+            );
+            CREATE INDEX IF NOT EXISTS jobs_file_path ON jobs (file_path);",
+        )
+        .map_err(|e| format!("初始化任务历史失败: {e}"))?;
+    Ok(connection)
+}
+
+// 枚举按 serde 的名称保存 与前端看到的一致
+fn enum_to_text<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn enum_from_text<T: DeserializeOwned>(text: String) -> rusqlite::Result<T> {
+    serde_json::from_value(serde_json::Value::String(text)).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, e.into())
+    })
+}
+
+/// 把结束的任务写入任务历史 失败只记录日志 不影响任务本身
+/// # Arguments
+/// * `app` - 应用句柄
+/// * `notification` - 任务结束通知（与 webhook 发送的内容相同）
+pub fn record_job(app: &AppHandle, notification: &JobNotification) {
+    // 内容指纹在预处理时记录在缓存中 预处理失败时可能还没有
+    let source_hash =
+        load_source_info(&image_cache_dir(&notification.file_path)).and_then(|info| {
+            info.get("content_hash")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        });
+    let result = open_history(app).and_then(|connection| {
+        connection
+            .execute(
+                "INSERT INTO jobs (kind, status, job_id, file_path, output_path, error,
+                    started_ms, finished_ms, duration_ms, app_version, source_hash)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                params![
+                    enum_to_text(&notification.kind),
+                    enum_to_text(&notification.status),
+                    notification.job_id,
+                    notification.file_path,
+                    notification.output_path,
+                    notification.error,
+                    notification
+                        .finished_ms
+                        .saturating_sub(notification.duration_ms) as i64,
+                    notification.finished_ms as i64,
+                    notification.duration_ms as i64,
+                    env!("CARGO_PKG_VERSION"),
+                    source_hash,
+                ],
+            )
+            .map_err(|e| format!("写入任务历史失败: {e}"))
+    });
+    if let Err(e) = result {
+        println!("[RUST] {e}");
+    }
+}
+
+/// 查询任务历史 按结束时间从新到旧排列
+/// # Arguments
+/// * `file_path` - 只返回这张图片的任务 不传时返回所有任务
+/// * `limit` - 最多返回的条数 默认 200
+/// # Returns
+/// * `Result<Vec<JobRecord>, String>` - 任务记录
+#[tauri::command]
+pub fn get_job_history(
+    app: AppHandle,
+    file_path: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<JobRecord>, String> {
+    let connection = open_history(&app)?;
+    let mut statement = connection
+        .prepare(
+            "SELECT id, kind, status, job_id, file_path, output_path, error,
+                started_ms, finished_ms, duration_ms, app_version, source_hash
+             FROM jobs
+             WHERE ?1 IS NULL OR file_path = ?1
+             ORDER BY id DESC
+             LIMIT ?2",
+        )
+        .map_err(|e| format!("查询任务历史失败: {e}"))?;
+    let records = statement
+        .query_map(
+            params![file_path, limit.unwrap_or(DEFAULT_HISTORY_LIMIT)],
+            |row| {
+                Ok(JobRecord {
+                    id: row.get(0)?,
+                    kind: enum_from_text(row.get(1)?)?,
+                    status: enum_from_text(row.get(2)?)?,
+                    job_id: row.get(3)?,
+                    file_path: row.get(4)?,
+                    output_path: row.get(5)?,
+                    error: row.get(6)?,
+                    started_ms: row.get::<_, i64>(7)? as u64,
+                    finished_ms: row.get::<_, i64>(8)? as u64,
+                    duration_ms: row.get::<_, i64>(9)? as u64,
+                    app_version: row.get(10)?,
+                    source_hash: row.get(11)?,
+                })
+            },
+        )
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| format!("查询任务历史失败: {e}"))?;
+    Ok(records)
+}
@@ -0,0 +1,205 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::ColorType;
+use rayon::prelude::*;
+
+use super::types::ExportQuality;
+
+// 自动 JPEG 质量：在图片上均匀抽取若干瓦片 用不同质量编码再解码 计算与原图的 SSIM
+// 二分查找所有瓦片的最小 SSIM 仍达到目标的最低质量 文件尽量小又看不出压缩痕迹
+
+// 没有指定时的目标 SSIM
+pub const DEFAULT_TARGET_SSIM: f64 = 0.98;
+
+// 固定质量和没有指定质量时使用的默认值
+pub const DEFAULT_JPEG_QUALITY: u8 = 90;
+
+// 自动质量的查找范围
+const MIN_AUTO_QUALITY: u8 = 40;
+const MAX_AUTO_QUALITY: u8 = 95;
+
+// 抽样瓦片的边长和每个方向的数量
+const SAMPLE_TILE_SIZE: u32 = 256;
+const SAMPLE_GRID: u32 = 4;
+
+// SSIM 的窗口大小和步长
+const SSIM_WINDOW: usize = 8;
+const SSIM_STEP: usize = 4;
+
+/// 检查质量参数
+pub fn validate_export_quality(quality: &ExportQuality) -> Result<(), String> {
+    match *quality {
+        ExportQuality::Fixed { quality } if !(1..=100).contains(&quality) => {
+            Err(format!("JPEG 质量必须在 1 到 100 之间: {quality}"))
+        }
+        ExportQuality::Auto {
+            target_ssim: Some(target),
+        } if !(target > 0.0 && target < 1.0) => {
+            Err(format!("目标 SSIM 必须在 0 到 1 之间: {target}"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 根据质量参数确定 JPEG 编码质量
+/// # Arguments
+/// * `pixels` - RGBA 像素
+/// * `width` / `height` - 图片尺寸
+/// * `quality` - 质量参数 为空时使用默认质量
+pub fn resolve_jpeg_quality(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    quality: Option<ExportQuality>,
+) -> Result<u8, String> {
+    match quality {
+        None => Ok(DEFAULT_JPEG_QUALITY),
+        Some(ExportQuality::Fixed { quality }) => Ok(quality),
+        Some(ExportQuality::Auto { target_ssim }) => choose_jpeg_quality(
+            pixels,
+            width,
+            height,
+            target_ssim.unwrap_or(DEFAULT_TARGET_SSIM),
+        ),
+    }
+}
+
+/// 查找抽样瓦片的 SSIM 都不低于目标的最低 JPEG 质量
+/// 最高质量也达不到目标时返回最高质量
+pub fn choose_jpeg_quality(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    target_ssim: f64,
+) -> Result<u8, String> {
+    let tiles = sample_tiles(pixels, width, height);
+    let meets_target = |quality: u8| -> Result<bool, String> {
+        let scores = tiles
+            .par_iter()
+            .map(|tile| tile_ssim(tile, quality))
+            .collect::<Result<Vec<f64>, String>>()?;
+        Ok(scores.into_iter().fold(1.0, f64::min) >= target_ssim)
+    };
+
+    let (mut low, mut high) = (MIN_AUTO_QUALITY, MAX_AUTO_QUALITY);
+    while low < high {
+        let middle = (low + high) / 2;
+        if meets_target(middle)? {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    println!("[RUST] 自动 JPEG 质量: {low} (目标 SSIM {target_ssim})");
+    Ok(low)
+}
+
+/// 把 RGBA 像素编码为 JPEG（丢弃 alpha）
+pub fn encode_jpeg(pixels: &[u8], width: u32, height: u32, quality: u8) -> Result<Vec<u8>, String> {
+    let rgb: Vec<u8> = pixels
+        .chunks_exact(4)
+        .flat_map(|p| [p[0], p[1], p[2]])
+        .collect();
+    let mut output = Vec::new();
+    JpegEncoder::new_with_quality(&mut output, quality)
+        .encode(&rgb, width, height, ColorType::Rgb8)
+        .map_err(|e| format!("JPEG 编码失败: {e}"))?;
+    Ok(output)
+}
+
+// 抽样瓦片 保存 RGBA 像素和尺寸
+struct SampleTile {
+    pixels: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+// 在图片上均匀取 SAMPLE_GRID x SAMPLE_GRID 个瓦片 小图直接整张作为一个瓦片
+fn sample_tiles(pixels: &[u8], width: u32, height: u32) -> Vec<SampleTile> {
+    let tile_width = SAMPLE_TILE_SIZE.min(width);
+    let tile_height = SAMPLE_TILE_SIZE.min(height);
+    let columns = SAMPLE_GRID.min(width / tile_width);
+    let rows = SAMPLE_GRID.min(height / tile_height);
+
+    let mut tiles = Vec::with_capacity((columns * rows) as usize);
+    for row in 0..rows {
+        for column in 0..columns {
+            // 瓦片在各自的格子中居中
+            let x = (width - tile_width) * (2 * column + 1) / (2 * columns).max(1);
+            let x = x.min(width - tile_width);
+            let y = (height - tile_height) * (2 * row + 1) / (2 * rows).max(1);
+            let y = y.min(height - tile_height);
+            let mut tile = Vec::with_capacity(tile_width as usize * tile_height as usize * 4);
+            for ty in y..y + tile_height {
+                let start = (ty as usize * width as usize + x as usize) * 4;
+                tile.extend_from_slice(&pixels[start..start + tile_width as usize * 4]);
+            }
+            tiles.push(SampleTile {
+                pixels: tile,
+                width: tile_width,
+                height: tile_height,
+            });
+        }
+    }
+    tiles
+}
+
+// 用指定质量编码再解码一个瓦片 计算亮度通道的 SSIM
+fn tile_ssim(tile: &SampleTile, quality: u8) -> Result<f64, String> {
+    let encoded = encode_jpeg(&tile.pixels, tile.width, tile.height, quality)?;
+    let decoded = image::load_from_memory_with_format(&encoded, image::ImageFormat::Jpeg)
+        .map_err(|e| format!("JPEG 解码失败: {e}"))?
+        .to_rgb8();
+    let original = luma(tile.pixels.chunks_exact(4));
+    let compressed = luma(decoded.as_raw().chunks_exact(3));
+    Ok(ssim(
+        &original,
+        &compressed,
+        tile.width as usize,
+        tile.height as usize,
+    ))
+}
+
+fn luma<'a>(pixels: impl Iterator<Item = &'a [u8]>) -> Vec<f64> {
+    pixels
+        .map(|p| 0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64)
+        .collect()
+}
+
+// 滑动窗口 SSIM 的平均值 窗口内均匀加权
+fn ssim(a: &[f64], b: &[f64], width: usize, height: usize) -> f64 {
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+    let window = SSIM_WINDOW.min(width).min(height);
+    let count = (window * window) as f64;
+
+    let mut total = 0.0;
+    let mut windows = 0usize;
+    for y in (0..=height - window).step_by(SSIM_STEP) {
+        for x in (0..=width - window).step_by(SSIM_STEP) {
+            let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) =
+                (0.0, 0.0, 0.0, 0.0, 0.0);
+            for row in y..y + window {
+                for index in row * width + x..row * width + x + window {
+                    let (va, vb) = (a[index], b[index]);
+                    sum_a += va;
+                    sum_b += vb;
+                    sum_aa += va * va;
+                    sum_bb += vb * vb;
+                    sum_ab += va * vb;
+                }
+            }
+            let (mean_a, mean_b) = (sum_a / count, sum_b / count);
+            let var_a = sum_aa / count - mean_a * mean_a;
+            let var_b = sum_bb / count - mean_b * mean_b;
+            let covariance = sum_ab / count - mean_a * mean_b;
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+            windows += 1;
+        }
+    }
+    if windows == 0 {
+        1.0
+    } else {
+        total / windows as f64
+    }
+}
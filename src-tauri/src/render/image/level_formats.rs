@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use image::codecs::jpeg::JpegEncoder;
+use image::{ColorType, RgbaImage};
+
+use super::chunk_processing::{process_single_chunk_parallel, CHUNK_TMP_SUFFIX};
+use super::chunk_repair::validate_chunk_data;
+use super::errors::{localized_error, ErrorCode};
+use super::lod_pyramid::MAX_LOD_LEVEL;
+use super::types::{ChunkInfo, ChunkStorageFormat, LevelStorageFormat};
+
+// 分辨率级别的存储格式：概览级别（代理副本）可以存为有损 JPEG 缓存体积大幅缩小
+// 完整分辨率（第 0 级）始终是未压缩的原始像素 1:1 查看不受影响
+// JPEG chunk 保存为 chunk_x_y.jpg 读取时解码成与 .bin 相同的格式 前端不需要区分
+// 没有单独设置的级别沿用更精细的级别中最近的设置 例如只设置第 1 级时所有概览级别都使用它
+// 修改格式后新生成的代理副本生效 已有的代理副本在重新生成之前保持原来的格式
+// 有损 WebP 需要 libwebp 编码器 依赖中的 image 只能编码无损 WebP 所以有损格式只提供 JPEG
+
+static LEVEL_FORMATS: OnceLock<Mutex<HashMap<u32, ChunkStorageFormat>>> = OnceLock::new();
+
+fn level_formats() -> &'static Mutex<HashMap<u32, ChunkStorageFormat>> {
+    LEVEL_FORMATS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 分辨率级别的存储格式 沿用不超过该级别的最近一次设置 都没有设置时为原始格式
+pub fn level_format(level: u32) -> ChunkStorageFormat {
+    level_formats()
+        .lock()
+        .ok()
+        .and_then(|formats| {
+            formats
+                .iter()
+                .filter(|(configured, _)| **configured <= level)
+                .max_by_key(|(configured, _)| **configured)
+                .map(|(_, format)| *format)
+        })
+        .unwrap_or(ChunkStorageFormat::Raw)
+}
+
+/// 设置分辨率级别的存储格式 之后生成的代理副本和概览级别使用新的格式
+/// 更粗的级别没有单独设置时也使用这个格式
+/// # Arguments
+/// * `level` - 级别 1 为 1/2 2 为 1/4 ... 完整分辨率（0）只能使用原始格式
+/// * `format` - 存储格式
+/// # Returns
+/// * `Result<Vec<LevelStorageFormat>, String>` - 设置后所有单独设置过的级别的存储格式
+#[tauri::command]
+pub fn set_level_format(
+    level: u32,
+    format: ChunkStorageFormat,
+) -> Result<Vec<LevelStorageFormat>, String> {
+    if level > MAX_LOD_LEVEL {
+        return Err(format!(
+            "不支持的级别 {level} 可用的级别为 0 到 {MAX_LOD_LEVEL}"
+        ));
+    }
+    if level == 0 && format != ChunkStorageFormat::Raw {
+        return Err("完整分辨率（第 0 级）只能使用无损的原始格式".to_string());
+    }
+    if let ChunkStorageFormat::Jpeg { quality } = format {
+        if !(1..=100).contains(&quality) {
+            return Err(format!("JPEG 质量必须在 1 到 100 之间: {quality}"));
+        }
+    }
+    level_formats()
+        .lock()
+        .map_err(|e| format!("获取存储格式锁失败: {e}"))?
+        .insert(level, format);
+    println!("[RUST] 第 {level} 级的存储格式已设置为 {format:?}");
+    get_level_formats()
+}
+
+/// 获取单独设置过存储格式的级别 始终包含完整分辨率（第 0 级）
+#[tauri::command]
+pub fn get_level_formats() -> Result<Vec<LevelStorageFormat>, String> {
+    let mut levels: Vec<u32> = level_formats()
+        .lock()
+        .map_err(|e| format!("获取存储格式锁失败: {e}"))?
+        .keys()
+        .copied()
+        .chain([0])
+        .collect();
+    levels.sort_unstable();
+    levels.dedup();
+    Ok(levels
+        .into_iter()
+        .map(|level| LevelStorageFormat {
+            level,
+            format: level_format(level),
+        })
+        .collect())
+}
+
+/// 按存储格式把图片中的一个 chunk 写入目录
+/// # Arguments
+/// * `img` - 整个级别的图片
+/// * `chunk_info` - chunk 信息
+/// * `dir` - 级别的缓存目录
+/// * `format` - 存储格式
+pub fn write_level_chunk(
+    img: &RgbaImage,
+    chunk_info: &ChunkInfo,
+    dir: &Path,
+    format: ChunkStorageFormat,
+) -> Result<(), String> {
+    let ChunkStorageFormat::Jpeg { quality } = format else {
+        return process_single_chunk_parallel(img, chunk_info, dir).map(|_| ());
+    };
+    let region = image::imageops::crop_imm(
+        img,
+        chunk_info.x,
+        chunk_info.y,
+        chunk_info.width,
+        chunk_info.height,
+    )
+    .to_image();
+    let rgb = image::DynamicImage::ImageRgba8(region).to_rgb8();
+    let mut encoded = Vec::new();
+    JpegEncoder::new_with_quality(&mut encoded, quality)
+        .encode(rgb.as_raw(), rgb.width(), rgb.height(), ColorType::Rgb8)
+        .map_err(|e| format!("编码 JPEG chunk 失败: {e}"))?;
+
+    // 与原始格式一样先写临时文件再改名
+    let path = dir.join(format!(
+        "chunk_{}_{}.jpg",
+        chunk_info.chunk_x, chunk_info.chunk_y
+    ));
+    let tmp_path = dir.join(format!(
+        "chunk_{}_{}.jpg{CHUNK_TMP_SUFFIX}",
+        chunk_info.chunk_x, chunk_info.chunk_y
+    ));
+    fs::write(&tmp_path, encoded).map_err(|e| format!("写入 chunk 文件失败: {e}"))?;
+    fs::rename(&tmp_path, &path).map_err(|e| format!("写入 chunk 文件失败: {e}"))
+}
+
+/// 读取级别目录中的 chunk 有损格式解码为与 chunk 文件相同的格式
+/// # Arguments
+/// * `dir` - 级别的缓存目录
+/// * `chunk_x` / `chunk_y` - chunk 索引
+/// # Returns
+/// * `Result<Vec<u8>, String>` - 宽度 + 高度 + RGBA8 像素
+pub fn read_level_chunk(dir: &Path, chunk_x: u32, chunk_y: u32) -> Result<Vec<u8>, String> {
+    let raw_path = dir.join(format!("chunk_{chunk_x}_{chunk_y}.bin"));
+    if raw_path.exists() {
+        let data = fs::read(&raw_path).map_err(|e| format!("读取 chunk 文件失败: {e}"))?;
+        validate_chunk_data(&data)?;
+        return Ok(data);
+    }
+    let jpeg_path = dir.join(format!("chunk_{chunk_x}_{chunk_y}.jpg"));
+    if !jpeg_path.exists() {
+        return Err(localized_error(
+            ErrorCode::ChunkMissing,
+            &[("path", &raw_path.display())],
+        ));
+    }
+    let img = image::open(&jpeg_path)
+        .map_err(|e| format!("解码 JPEG chunk 失败: {e} ({jpeg_path:?})"))?
+        .to_rgba8();
+    let mut data = Vec::with_capacity(8 + img.as_raw().len());
+    data.extend_from_slice(&img.width().to_be_bytes());
+    data.extend_from_slice(&img.height().to_be_bytes());
+    data.extend_from_slice(img.as_raw());
+    Ok(data)
+}
@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+
+use super::cache::{image_cache_dir, load_cached_metadata};
+use super::types::{ImagePoint, LineProfile, ProfileChannel};
+use super::viewport_render::{load_chunk_grid, ChunkGrid, RenderLevel};
+
+// 强度曲线：在线段上等间距采样 双线性插值得到亚像素位置的强度 用于绘制剖面图
+// 只映射线段经过的 chunk 很长的斜线也不会映射整个包围矩形
+
+// 最多采样点数
+const MAX_PROFILE_SAMPLES: u32 = 100_000;
+
+/// 沿线段采样强度（需要先完成预处理）
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `p0` / `p1` - 线段的起点和终点 必须在图片范围内
+/// * `samples` - 采样点数（包括两个端点） 至少为 2
+/// * `channel` - 采样的通道
+/// # Returns
+/// * `Result<LineProfile, String>` - 从 p0 到 p1 的强度
+#[tauri::command]
+pub fn get_line_profile(
+    file_path: String,
+    p0: ImagePoint,
+    p1: ImagePoint,
+    samples: u32,
+    channel: ProfileChannel,
+) -> Result<LineProfile, String> {
+    if !(2..=MAX_PROFILE_SAMPLES).contains(&samples) {
+        return Err(format!(
+            "采样点数必须在 2 到 {MAX_PROFILE_SAMPLES} 之间: {samples}"
+        ));
+    }
+    let metadata = load_cached_metadata(&file_path)?;
+    let (width, height) = (metadata.total_width, metadata.total_height);
+    for point in [&p0, &p1] {
+        let inside = point.x.is_finite()
+            && point.y.is_finite()
+            && (0.0..=(width - 1) as f64).contains(&point.x)
+            && (0.0..=(height - 1) as f64).contains(&point.y);
+        if !inside {
+            return Err(format!(
+                "端点超出图片范围: ({}, {}) (图片 {width}x{height})",
+                point.x, point.y
+            ));
+        }
+    }
+
+    let positions: Vec<(f64, f64)> = (0..samples)
+        .map(|i| {
+            let t = i as f64 / (samples - 1) as f64;
+            (p0.x + (p1.x - p0.x) * t, p0.y + (p1.y - p0.y) * t)
+        })
+        .collect();
+
+    // 插值用到的像素所在的 chunk
+    let (chunk_size_x, chunk_size_y) = (metadata.chunk_size_x, metadata.chunk_size_y);
+    let mut touched = HashSet::new();
+    for &(x, y) in &positions {
+        let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+        for px in [x0, (x0 + 1).min(width - 1)] {
+            for py in [y0, (y0 + 1).min(height - 1)] {
+                touched.insert((px / chunk_size_x, py / chunk_size_y));
+            }
+        }
+    }
+    let level = RenderLevel {
+        scale: 1,
+        dir: image_cache_dir(&file_path),
+        metadata,
+    };
+    let grid = load_chunk_grid(&file_path, &level, |c| {
+        touched.contains(&(c.chunk_x, c.chunk_y))
+    })?;
+
+    let values = positions
+        .iter()
+        .map(|&(x, y)| sample(&grid, (width, height), x, y, channel))
+        .collect();
+    let length = (p1.x - p0.x).hypot(p1.y - p0.y);
+    Ok(LineProfile {
+        values,
+        length,
+        spacing: length / (samples - 1) as f64,
+    })
+}
+
+fn channel_value(pixel: [u8; 4], channel: ProfileChannel) -> f64 {
+    match channel {
+        ProfileChannel::Red => pixel[0] as f64,
+        ProfileChannel::Green => pixel[1] as f64,
+        ProfileChannel::Blue => pixel[2] as f64,
+        ProfileChannel::Alpha => pixel[3] as f64,
+        ProfileChannel::Luminance => {
+            0.299 * pixel[0] as f64 + 0.587 * pixel[1] as f64 + 0.114 * pixel[2] as f64
+        }
+    }
+}
+
+// 双线性插值 先取通道值再插值 亮度不会因为先取整而失真
+fn sample(
+    grid: &ChunkGrid,
+    (width, height): (u32, u32),
+    x: f64,
+    y: f64,
+    channel: ProfileChannel,
+) -> f64 {
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+    let value = |px: u32, py: u32| channel_value(grid.pixel(px, py), channel);
+    let top = value(x0, y0) * (1.0 - fx) + value(x1, y0) * fx;
+    let bottom = value(x0, y1) * (1.0 - fx) + value(x1, y1) * fx;
+    top * (1.0 - fy) + bottom * fy
+}
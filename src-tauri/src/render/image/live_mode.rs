@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use tauri::{AppHandle, Emitter};
+
+use super::cache::check_file_cache_exists;
+use super::refresh::refresh_cache_sync;
+use super::types::ChunksUpdated;
+
+// chunk 有更新时发出的事件 前端据此重新获取对应的 chunk
+pub const CHUNK_UPDATED_EVENT: &str = "chunk://updated";
+
+// 轮询间隔的上下限
+const MIN_POLL_INTERVAL_MS: u64 = 200;
+const MAX_POLL_INTERVAL_MS: u64 = 60 * 60 * 1000;
+
+// 正在实时监视的图片 值为停止标志
+static LIVE_IMAGES: OnceLock<Mutex<HashMap<String, Arc<AtomicBool>>>> = OnceLock::new();
+
+fn live_images() -> &'static Mutex<HashMap<String, Arc<AtomicBool>>> {
+    LIVE_IMAGES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// 用修改时间和大小判断文件是否变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+fn file_stamp(file_path: &str) -> Option<FileStamp> {
+    fs::metadata(file_path).ok().map(|meta| FileStamp {
+        modified: meta.modified().ok(),
+        len: meta.len(),
+    })
+}
+
+/// 开启实时模式
+/// 定期检查源文件（例如由其他程序持续写入的拼接结果） 变化后增量刷新缓存
+/// 并通过 chunk://updated 事件通知前端哪些 chunk 需要重新获取
+/// # Arguments
+/// * `file_path` - 图片文件路径（需要已经预处理）
+/// * `interval_ms` - 轮询间隔（毫秒）
+#[tauri::command]
+pub fn start_live_mode(app: AppHandle, file_path: String, interval_ms: u64) -> Result<(), String> {
+    if !check_file_cache_exists(&file_path) {
+        return Err("实时模式需要先完成预处理".to_string());
+    }
+    let interval =
+        Duration::from_millis(interval_ms.clamp(MIN_POLL_INTERVAL_MS, MAX_POLL_INTERVAL_MS));
+
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    {
+        let mut images = live_images()
+            .lock()
+            .map_err(|e| format!("实时模式状态加锁失败: {e}"))?;
+        if images.contains_key(&file_path) {
+            return Err(format!("图片已处于实时模式: {file_path}"));
+        }
+        images.insert(file_path.clone(), stop_flag.clone());
+    }
+
+    let thread_path = file_path.clone();
+    let spawn_result = thread::Builder::new()
+        .name("live-mode".to_string())
+        .spawn(move || watch_loop(app, thread_path, interval, stop_flag));
+
+    if let Err(e) = spawn_result {
+        if let Ok(mut images) = live_images().lock() {
+            images.remove(&file_path);
+        }
+        return Err(format!("启动实时模式线程失败: {e}"));
+    }
+
+    println!(
+        "[RUST] 实时模式已开启: {file_path} (间隔 {}ms)",
+        interval.as_millis()
+    );
+    Ok(())
+}
+
+/// 关闭实时模式
+#[tauri::command]
+pub fn stop_live_mode(file_path: String) -> Result<(), String> {
+    let flag = live_images()
+        .lock()
+        .map_err(|e| format!("实时模式状态加锁失败: {e}"))?
+        .remove(&file_path);
+    match flag {
+        Some(flag) => {
+            flag.store(true, Ordering::Relaxed);
+            println!("[RUST] 实时模式已关闭: {file_path}");
+            Ok(())
+        }
+        None => Err(format!("图片未处于实时模式: {file_path}")),
+    }
+}
+
+/// 列出所有处于实时模式的图片
+#[tauri::command]
+pub fn list_live_images() -> Result<Vec<String>, String> {
+    let images = live_images()
+        .lock()
+        .map_err(|e| format!("实时模式状态加锁失败: {e}"))?;
+    let mut paths: Vec<String> = images.keys().cloned().collect();
+    paths.sort();
+    Ok(paths)
+}
+
+fn watch_loop(app: AppHandle, file_path: String, interval: Duration, stop_flag: Arc<AtomicBool>) {
+    let mut last_refreshed = file_stamp(&file_path);
+    let mut last_seen = last_refreshed;
+
+    while !stop_flag.load(Ordering::Relaxed) {
+        thread::sleep(interval);
+        if stop_flag.load(Ordering::Relaxed) {
+            break;
+        }
+
+        let current = file_stamp(&file_path);
+        // 文件还在写入时两次轮询之间会继续变化 等到稳定后再刷新 避免读到写了一半的文件
+        let stable = current == last_seen;
+        last_seen = current;
+        if current.is_none() || !stable || current == last_refreshed {
+            continue;
+        }
+
+        match refresh_cache_sync(&file_path) {
+            Ok(report) => {
+                last_refreshed = current;
+                if report.changed_chunks.is_empty() {
+                    continue;
+                }
+                let payload = ChunksUpdated {
+                    file_path: file_path.clone(),
+                    chunks: report.changed_chunks,
+                    full_rebuild: report.full_rebuild,
+                    metadata: report.metadata,
+                };
+                if let Err(e) = app.emit(CHUNK_UPDATED_EVENT, payload) {
+                    println!("[RUST] 发送 chunk 更新事件失败: {e}");
+                }
+            }
+            // 文件可能仍在被写入（例如解码失败） 下一轮再试
+            Err(e) => println!("[RUST] 实时模式刷新失败，稍后重试: {e}"),
+        }
+    }
+
+    println!("[RUST] 实时模式线程退出: {file_path}");
+}
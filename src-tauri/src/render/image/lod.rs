@@ -0,0 +1,140 @@
+use std::env;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+use super::bookmarks::validate_viewport;
+use super::errors::{localized_error, ErrorCode};
+use super::types::{LodSelection, Viewport};
+use super::viewport_render::{available_levels, RenderLevel};
+
+// 分辨率级别（LOD）选择策略：离屏渲染和前端显示使用同一套规则 后端缓存的级别与前端请求的一致
+// 每个设备像素对应的源像素数（考虑设备像素比）决定可以使用多粗的级别
+// 清晰度偏好按 2 的幂调整这个比例：正数偏向更粗的级别（更快） 负数偏向更精细的级别（更清晰）
+
+// 设置后作为默认的清晰度偏好
+pub const LOD_BIAS_ENV: &str = "IMAGES_GL_LOD_BIAS";
+
+// 清晰度偏好的范围
+const MAX_LOD_BIAS: f64 = 2.0;
+
+// f64 的位模式 默认为 0
+static LOD_BIAS: AtomicU64 = AtomicU64::new(0);
+static LOD_BIAS_INIT: OnceLock<()> = OnceLock::new();
+
+fn is_valid_bias(bias: f64) -> bool {
+    bias.is_finite() && (-MAX_LOD_BIAS..=MAX_LOD_BIAS).contains(&bias)
+}
+
+/// 当前的清晰度偏好 首次调用时读取环境变量 没有设置时为 0
+pub fn lod_bias() -> f64 {
+    LOD_BIAS_INIT.get_or_init(|| {
+        let bias = env::var(LOD_BIAS_ENV)
+            .ok()
+            .and_then(|v| v.trim().parse::<f64>().ok())
+            .filter(|&bias| is_valid_bias(bias))
+            .unwrap_or(0.0);
+        LOD_BIAS.store(bias.to_bits(), Ordering::Relaxed);
+    });
+    f64::from_bits(LOD_BIAS.load(Ordering::Relaxed))
+}
+
+/// 设置清晰度偏好
+/// # Arguments
+/// * `bias` - -2 到 2 正数偏向更粗的级别 负数偏向更精细的级别
+/// # Returns
+/// * `Result<f64, String>` - 设置后的清晰度偏好
+#[tauri::command]
+pub fn set_lod_bias(bias: f64) -> Result<f64, String> {
+    if !is_valid_bias(bias) {
+        return Err(format!(
+            "清晰度偏好必须在 -{MAX_LOD_BIAS} 到 {MAX_LOD_BIAS} 之间: {bias}"
+        ));
+    }
+    // 先触发一次初始化 避免之后读取环境变量覆盖这里的设置
+    lod_bias();
+    LOD_BIAS.store(bias.to_bits(), Ordering::Relaxed);
+    println!("[RUST] 清晰度偏好已设置为 {bias}");
+    Ok(bias)
+}
+
+/// 获取清晰度偏好
+#[tauri::command]
+pub fn get_lod_bias() -> Result<f64, String> {
+    Ok(lod_bias())
+}
+
+/// 选择不超过缩小比例的最粗级别 放大显示时使用最精细的级别
+/// levels 需要按缩小倍数从小到大排列
+/// # Arguments
+/// * `source_per_output` - 每个输出（设备）像素对应的源像素数
+pub(super) fn select_level(
+    levels: Vec<RenderLevel>,
+    source_per_output: f64,
+) -> Option<RenderLevel> {
+    let limit = source_per_output * lod_bias().exp2();
+    let mut levels = levels.into_iter();
+    let first = levels.next()?;
+    Some(
+        levels
+            .take_while(|level| level.scale as f64 <= limit)
+            .last()
+            .unwrap_or(first),
+    )
+}
+
+/// 为视口选择分辨率级别 并列出该级别中与视口相交的 chunk
+/// 前端按返回的级别请求 chunk（缩小倍数为 1 时用 get_image_chunk 否则用代理副本）
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `viewport` - 视口（原图像素坐标）
+/// * `screen_width` / `screen_height` - 视口在屏幕上的尺寸（CSS 像素）
+/// * `device_pixel_ratio` - 设备像素比
+/// # Returns
+/// * `Result<LodSelection, String>` - 选中的级别和需要的 chunk
+#[tauri::command]
+pub fn choose_level(
+    file_path: String,
+    viewport: Viewport,
+    screen_width: f64,
+    screen_height: f64,
+    device_pixel_ratio: f64,
+) -> Result<LodSelection, String> {
+    validate_viewport(&viewport)?;
+    let valid = |v: f64| v.is_finite() && v > 0.0;
+    if !valid(screen_width) || !valid(screen_height) || !valid(device_pixel_ratio) {
+        return Err(format!(
+            "无效的屏幕尺寸或设备像素比: {screen_width}x{screen_height} @ {device_pixel_ratio}"
+        ));
+    }
+
+    let source_per_device_pixel =
+        (viewport.width / screen_width).min(viewport.height / screen_height) / device_pixel_ratio;
+    let level = select_level(available_levels(&file_path), source_per_device_pixel)
+        .ok_or_else(|| localized_error(ErrorCode::CacheMissing, &[]))?;
+
+    // 视口在该级别中的范围
+    let scale = level.scale as f64;
+    let (x0, y0) = (viewport.x / scale, viewport.y / scale);
+    let (x1, y1) = (
+        (viewport.x + viewport.width) / scale,
+        (viewport.y + viewport.height) / scale,
+    );
+    let chunks = level
+        .metadata
+        .chunks
+        .iter()
+        .filter(|c| {
+            (c.x as f64) < x1
+                && ((c.x + c.width) as f64) > x0
+                && (c.y as f64) < y1
+                && ((c.y + c.height) as f64) > y0
+        })
+        .cloned()
+        .collect();
+    Ok(LodSelection {
+        scale: level.scale,
+        source_per_device_pixel,
+        bias: lod_bias(),
+        chunks,
+    })
+}
@@ -0,0 +1,206 @@
+use std::fs;
+use std::path::Path;
+
+use image::RgbaImage;
+use rayon::prelude::*;
+
+use super::config::{CHUNK_SIZE_X, CHUNK_SIZE_Y};
+use super::errors::{localized_error, ErrorCode};
+use super::gpu_compute::downsample_half;
+use super::level_formats::{level_format, read_level_chunk, write_level_chunk};
+use super::preprocessing::build_chunk_infos;
+use super::proxy::{proxy_cache_dir, write_proxy, write_proxy_metadata};
+use super::types::{ChunkInfo, ImageMetadata, LevelDescriptor};
+use super::write_tracker::chunk_write_state;
+use crate::utils::time::Stopwatch;
+
+// 多分辨率金字塔：预处理时在完整分辨率的 chunk 之外生成 1/2、1/4、1/8 ... 的概览级别
+// 逐级减半 直到整个级别能放进一个 chunk 为止 缩小使用内存中已解码的图片 不需要再次解码源文件
+// 各级别与代理副本共用目录（proxy_{倍数}）和存储格式设置（见 level_formats）
+// 缩小显示时前端按 metadata.levels 选择级别 通过 get_image_chunk 的 level 参数读取 chunk
+
+// 支持的最粗级别 1/65536 对任何图片都足够了
+pub const MAX_LOD_LEVEL: u32 = 16;
+
+/// 金字塔中概览级别的数量 逐级减半直到整个级别不超过一个 chunk
+/// # Arguments
+/// * `width` / `height` - 原图尺寸
+/// * `chunk_size_x` / `chunk_size_y` - chunk 尺寸
+pub fn pyramid_level_count(width: u32, height: u32, chunk_size_x: u32, chunk_size_y: u32) -> u32 {
+    let (mut w, mut h, mut count) = (width, height, 0);
+    while (w > chunk_size_x || h > chunk_size_y) && count < MAX_LOD_LEVEL {
+        w = w.div_ceil(2);
+        h = h.div_ceil(2);
+        count += 1;
+    }
+    count
+}
+
+/// 写入一个概览级别的 chunk 返回级别描述
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `level` - 级别 n 为原图的 1/2^n
+/// * `img` - 该级别的图片
+pub fn write_lod_level(
+    file_path: &str,
+    level: u32,
+    img: &RgbaImage,
+) -> Result<LevelDescriptor, String> {
+    let scale = 1 << level;
+    let metadata = write_proxy(&proxy_cache_dir(file_path, scale), img, scale)?;
+    Ok(level_descriptor(level, &metadata))
+}
+
+/// 逐个 chunk 写入一个概览级别 每个 chunk 只读取它覆盖的区域 整个级别不会同时在内存中
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `level` - 级别 n 为原图的 1/2^n
+/// * `width` / `height` - 该级别的尺寸
+/// * `read_region` - 读取该级别中的一个区域 参数为 (x, y, width, height)
+pub fn write_lod_level_by_chunks(
+    file_path: &str,
+    level: u32,
+    (width, height): (u32, u32),
+    read_region: impl Fn(u32, u32, u32, u32) -> Result<RgbaImage, String> + Sync,
+) -> Result<LevelDescriptor, String> {
+    let scale = 1 << level;
+    let dir = proxy_cache_dir(file_path, scale);
+    // 旧的级别可能网格不同 先删除
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).map_err(|e| format!("创建概览级别目录失败: {e}"))?;
+    let format = level_format(level);
+    build_chunk_infos(width, height, CHUNK_SIZE_X, CHUNK_SIZE_Y)
+        .par_iter()
+        .map(|chunk| {
+            let region = read_region(chunk.x, chunk.y, chunk.width, chunk.height)?;
+            let local_info = ChunkInfo {
+                x: 0,
+                y: 0,
+                ..chunk.clone()
+            };
+            write_level_chunk(&region, &local_info, &dir, format)
+        })
+        .collect::<Result<(), String>>()?;
+    let metadata = write_proxy_metadata(&dir, width, height, scale)?;
+    Ok(level_descriptor(level, &metadata))
+}
+
+/// 从已经写入的级别中读取一个区域 只读取与区域重叠的 chunk
+/// # Arguments
+/// * `dir` - 级别的缓存目录 第 0 级为图片的缓存目录
+/// * `chunk_size_x` / `chunk_size_y` - 该级别的 chunk 尺寸
+/// * `x` / `y` / `width` / `height` - 区域 需要在级别范围内
+pub fn read_stored_region(
+    dir: &Path,
+    (chunk_size_x, chunk_size_y): (u32, u32),
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<RgbaImage, String> {
+    let mut output = RgbaImage::new(width, height);
+    if width == 0 || height == 0 {
+        return Ok(output);
+    }
+    let row_len = width as usize * 4;
+    for chunk_y in y / chunk_size_y..=(y + height - 1) / chunk_size_y {
+        for chunk_x in x / chunk_size_x..=(x + width - 1) / chunk_size_x {
+            let data = read_level_chunk(dir, chunk_x, chunk_y)?;
+            let chunk_width = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+            let chunk_height = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+            let (left, top) = (chunk_x * chunk_size_x, chunk_y * chunk_size_y);
+            // 区域与 chunk 的交集
+            let x0 = x.max(left);
+            let x1 = (x + width).min(left + chunk_width);
+            let y0 = y.max(top);
+            let y1 = (y + height).min(top + chunk_height);
+            if x0 >= x1 || y0 >= y1 {
+                return Err(format!(
+                    "chunk ({chunk_x}, {chunk_y}) 的尺寸 {chunk_width}x{chunk_height} 与级别的网格不一致"
+                ));
+            }
+            let copy_len = (x1 - x0) as usize * 4;
+            let pixels = output.as_mut();
+            for py in y0..y1 {
+                let src =
+                    8 + ((py - top) as usize * chunk_width as usize + (x0 - left) as usize) * 4;
+                let dst = (py - y) as usize * row_len + (x0 - x) as usize * 4;
+                pixels[dst..dst + copy_len].copy_from_slice(&data[src..src + copy_len]);
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// 由级别的元数据生成级别描述
+pub(super) fn level_descriptor(level: u32, metadata: &ImageMetadata) -> LevelDescriptor {
+    LevelDescriptor {
+        level,
+        scale: 1 << level,
+        width: metadata.total_width,
+        height: metadata.total_height,
+        chunk_size_x: metadata.chunk_size_x,
+        chunk_size_y: metadata.chunk_size_y,
+        col_count: metadata.col_count,
+        row_count: metadata.row_count,
+    }
+}
+
+/// 从完整分辨率的图片逐级缩小 生成所有概览级别
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `base` - 完整分辨率的图片
+/// * `chunk_size_x` / `chunk_size_y` - 完整分辨率的 chunk 尺寸 决定级别数量
+/// # Returns
+/// * `Result<Vec<LevelDescriptor>, String>` - 从精细到粗糙的级别描述
+pub fn build_lod_levels(
+    file_path: &str,
+    base: &RgbaImage,
+    chunk_size_x: u32,
+    chunk_size_y: u32,
+) -> Result<Vec<LevelDescriptor>, String> {
+    let stopwatch = Stopwatch::start();
+    let count = pyramid_level_count(base.width(), base.height(), chunk_size_x, chunk_size_y);
+    let mut levels = Vec::with_capacity(count as usize);
+    let mut previous: Option<RgbaImage> = None;
+    for level in 1..=count {
+        let current = downsample_half(previous.as_ref().unwrap_or(base));
+        levels.push(write_lod_level(file_path, level, &current)?);
+        previous = Some(current);
+    }
+    if count > 0 {
+        println!(
+            "[RUST] 多分辨率金字塔生成完成: {count} 个概览级别 (耗时: {}ms)",
+            stopwatch.elapsed_ms()
+        );
+    }
+    Ok(levels)
+}
+
+/// 读取概览级别中的 chunk 格式与完整分辨率的 chunk 相同
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `level` - 级别 需要大于 0
+/// * `chunk_x` / `chunk_y` - 该级别中的 chunk 索引
+pub fn load_level_chunk(
+    file_path: &str,
+    level: u32,
+    chunk_x: u32,
+    chunk_y: u32,
+) -> Result<Vec<u8>, String> {
+    if level == 0 || level > MAX_LOD_LEVEL {
+        return Err(format!("无效的概览级别: {level}"));
+    }
+    let dir = proxy_cache_dir(file_path, 1 << level);
+    if !dir.join("metadata.json").exists() {
+        // 预处理期间级别还没有生成 前端稍后重试
+        if chunk_write_state(file_path, chunk_x, chunk_y).is_some() {
+            return Err(localized_error(
+                ErrorCode::ChunkNotReady,
+                &[("x", &chunk_x), ("y", &chunk_y)],
+            ));
+        }
+        return Err(localized_error(ErrorCode::CacheMissing, &[]));
+    }
+    read_level_chunk(&dir, chunk_x, chunk_y)
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+use memmap2::Mmap;
+use tauri::ipc::Response;
+
+use super::cache::{image_cache_dir, load_cached_metadata};
+use super::chunk_repair::regenerate_chunk;
+use super::export::map_chunk;
+use super::types::{ImageMetadata, ImagePoint};
+
+// 放大镜：用户移动放大镜工具时每秒调用 30-60 次 返回光标周围的完整分辨率小图
+// 1. 缓存：元数据和最近用过的 chunk 映射留在内存里 连续移动时几乎不访问磁盘
+// 2. 合并：来不及处理的旧请求直接放弃 只渲染最新的位置
+
+// 输出的最大边长
+const MAX_MAGNIFIER_SIZE: u32 = 1024;
+// 放大倍数的范围
+const MIN_MAGNIFIER_ZOOM: f64 = 1.0;
+const MAX_MAGNIFIER_ZOOM: f64 = 64.0;
+// 缓存的 chunk 映射数 放大镜一般只跨越几个 chunk
+const MAGNIFIER_CACHED_CHUNKS: usize = 16;
+
+// 最新一次请求的序号
+static LATEST_REQUEST: AtomicU64 = AtomicU64::new(0);
+static MAGNIFIER_CACHE: OnceLock<Mutex<Option<MagnifierCache>>> = OnceLock::new();
+
+// 一张图片的放大镜缓存 切换图片或缓存被重写（metadata.json 更新）后重建
+// 重新分块、清理缓存时直接丢弃（见 forget_magnifier_cache）
+struct MagnifierCache {
+    file_path: String,
+    metadata_modified: Option<SystemTime>,
+    metadata: ImageMetadata,
+    // chunk 位置 -> (映射, chunk 宽度, 最后使用的请求序号)
+    chunks: HashMap<(u32, u32), (Arc<Mmap>, u32, u64)>,
+}
+
+impl MagnifierCache {
+    fn chunk(&mut self, cx: u32, cy: u32, request: u64) -> Result<(Arc<Mmap>, u32), String> {
+        if let Some((mmap, width, last_used)) = self.chunks.get_mut(&(cx, cy)) {
+            *last_used = request;
+            return Ok((mmap.clone(), *width));
+        }
+        let info = self
+            .metadata
+            .chunks
+            .iter()
+            .find(|c| c.chunk_x == cx && c.chunk_y == cy)
+            .ok_or_else(|| format!("chunk 不存在: ({cx}, {cy})"))?;
+        let path = image_cache_dir(&self.file_path).join(format!("chunk_{cx}_{cy}.bin"));
+        let mmap = match map_chunk(&path) {
+            Ok(mmap) => mmap,
+            Err(e) => {
+                println!("[RUST] 放大镜发现损坏的 chunk，重新生成: {e}");
+                regenerate_chunk(&self.file_path, cx, cy)?;
+                map_chunk(&path)?
+            }
+        };
+        // 超出容量时去掉最久没用过的
+        if self.chunks.len() >= MAGNIFIER_CACHED_CHUNKS {
+            let oldest = self
+                .chunks
+                .iter()
+                .min_by_key(|(_, (_, _, last_used))| *last_used)
+                .map(|(key, _)| *key);
+            if let Some(key) = oldest {
+                self.chunks.remove(&key);
+            }
+        }
+        let mmap = Arc::new(mmap);
+        self.chunks
+            .insert((cx, cy), (mmap.clone(), info.width, request));
+        Ok((mmap, info.width))
+    }
+}
+
+fn metadata_modified(file_path: &str) -> Option<SystemTime> {
+    fs::metadata(image_cache_dir(file_path).join("metadata.json"))
+        .and_then(|m| m.modified())
+        .ok()
+}
+
+fn magnifier_cache() -> &'static Mutex<Option<MagnifierCache>> {
+    MAGNIFIER_CACHE.get_or_init(|| Mutex::new(None))
+}
+
+/// 图片的缓存被重写或删除时调用 丢弃放大镜中的元数据和 chunk 映射
+pub fn forget_magnifier_cache(file_path: &str) {
+    let mut guard = magnifier_cache().lock().unwrap_or_else(|e| e.into_inner());
+    if guard.as_ref().is_some_and(|c| c.file_path == file_path) {
+        *guard = None;
+    }
+}
+
+/// 清理所有缓存时调用
+pub fn clear_magnifier_cache() {
+    *magnifier_cache().lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// 获取放大镜的图像（需要先完成预处理）
+/// 始终使用完整分辨率 按最近邻放大 放大后能看清每个像素 超出图片的部分是透明的
+/// 有更新的请求在等待时放弃这次请求 前端忽略这个错误即可
+/// 数据格式与 chunk 相同：宽度(4字节) + 高度(4字节) + RGBA 数据
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `center` - 放大镜中心（图片坐标）
+/// * `zoom` - 放大倍数 输出像素 / 图片像素 范围 1-64
+/// * `size` - 输出的边长（像素）
+#[tauri::command]
+pub fn get_magnifier(
+    file_path: String,
+    center: ImagePoint,
+    zoom: f64,
+    size: u32,
+) -> Result<Response, String> {
+    if !(MIN_MAGNIFIER_ZOOM..=MAX_MAGNIFIER_ZOOM).contains(&zoom) {
+        return Err(format!(
+            "放大倍数必须在 {MIN_MAGNIFIER_ZOOM} 到 {MAX_MAGNIFIER_ZOOM} 之间: {zoom}"
+        ));
+    }
+    if size == 0 || size > MAX_MAGNIFIER_SIZE {
+        return Err(format!(
+            "放大镜尺寸必须在 1 到 {MAX_MAGNIFIER_SIZE} 之间: {size}"
+        ));
+    }
+    if !center.x.is_finite() || !center.y.is_finite() {
+        return Err(format!("放大镜中心无效: ({}, {})", center.x, center.y));
+    }
+
+    let request = LATEST_REQUEST.fetch_add(1, Ordering::SeqCst) + 1;
+    let mut guard = magnifier_cache()
+        .lock()
+        .map_err(|e| format!("获取放大镜缓存锁失败: {e}"))?;
+    // 等锁期间来了更新的请求 这次的结果已经没用了
+    if LATEST_REQUEST.load(Ordering::SeqCst) != request {
+        return Err("放大镜请求已被更新的请求取代".to_string());
+    }
+
+    let modified = metadata_modified(&file_path);
+    let stale = guard
+        .as_ref()
+        .is_none_or(|c| c.file_path != file_path || c.metadata_modified != modified);
+    if stale {
+        *guard = Some(MagnifierCache {
+            metadata: load_cached_metadata(&file_path)?,
+            file_path,
+            metadata_modified: modified,
+            chunks: HashMap::new(),
+        });
+    }
+    let Some(cache) = guard.as_mut() else {
+        return Err("放大镜缓存未初始化".to_string());
+    };
+
+    let (width, height) = (cache.metadata.total_width, cache.metadata.total_height);
+    let (chunk_size_x, chunk_size_y) = (cache.metadata.chunk_size_x, cache.metadata.chunk_size_y);
+    // 每个输出像素中心对应的图片坐标 超出图片时为 None
+    let source = |output: u32, center: f64, limit: u32| {
+        let position = center + (output as f64 + 0.5 - size as f64 / 2.0) / zoom;
+        (position >= 0.0 && position < limit as f64).then_some(position as u32)
+    };
+    let columns: Vec<Option<u32>> = (0..size).map(|ox| source(ox, center.x, width)).collect();
+
+    let mut output = Vec::with_capacity(8 + size as usize * size as usize * 4);
+    output.extend_from_slice(&size.to_be_bytes());
+    output.extend_from_slice(&size.to_be_bytes());
+    for oy in 0..size {
+        let Some(py) = source(oy, center.y, height) else {
+            output.resize(output.len() + size as usize * 4, 0);
+            continue;
+        };
+        // 同一行中相邻的像素大多在同一个 chunk 里
+        let mut current: Option<(u32, Arc<Mmap>, u32)> = None;
+        for px in &columns {
+            let Some(px) = *px else {
+                output.extend_from_slice(&[0; 4]);
+                continue;
+            };
+            let cx = px / chunk_size_x;
+            if current.as_ref().is_none_or(|(x, _, _)| *x != cx) {
+                let (mmap, chunk_width) = cache.chunk(cx, py / chunk_size_y, request)?;
+                current = Some((cx, mmap, chunk_width));
+            }
+            if let Some((_, mmap, chunk_width)) = &current {
+                let offset = 8
+                    + ((py % chunk_size_y) as usize * *chunk_width as usize
+                        + (px % chunk_size_x) as usize)
+                        * 4;
+                let pixel = mmap
+                    .get(offset..offset + 4)
+                    .ok_or_else(|| format!("chunk ({cx}, {}) 的数据不完整", py / chunk_size_y))?;
+                output.extend_from_slice(pixel);
+            }
+        }
+    }
+    Ok(Response::new(output))
+}
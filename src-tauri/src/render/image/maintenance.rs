@@ -0,0 +1,323 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use tauri::{AppHandle, Emitter};
+
+use super::access_stats::{flush_access_stats, last_activity_ms};
+use super::cache::{ensure_cache_writable, is_cache_read_only, load_source_info};
+use super::cache_manager::{apply_retention_rules, enforce_cache_limit};
+use super::chunk_processing::CHUNK_TMP_SUFFIX;
+use super::chunk_repair::{regenerate_chunk, validate_chunk_data};
+use super::compact_metadata::decode_metadata;
+use super::config::cache_root;
+use super::export::is_export_queue_idle;
+use super::power::background_policy;
+use super::preprocess_queue::is_preprocess_queue_idle;
+use super::preprocessing::decode_source_image;
+use super::proxy::has_proxy;
+use super::rechunk::RECHUNK_TMP_DIR;
+use super::similarity::record_perceptual_hashes;
+use super::types::{BackgroundPolicy, ImageMetadata, MaintenanceConfig, MaintenanceReport};
+use crate::utils::time::{get_time, Stopwatch};
+
+// 后台维护：空闲时定期清理残留文件、抽查 chunk 完整性、补算概览数据、限制缓存大小
+// 每次执行完成后发出 maintenance://completed 事件 说明做了什么
+
+pub const MAINTENANCE_COMPLETED_EVENT: &str = "maintenance://completed";
+
+// 调度线程检查的间隔
+const SCHEDULER_TICK: Duration = Duration::from_secs(10);
+// 最近这么久没有 chunk 访问、且没有后台任务时才认为空闲
+const IDLE_THRESHOLD_MS: u64 = 60_000;
+// 残缺的缓存目录超过这个时间才清理 避免误删正在预处理的目录
+const STALE_DIR_AGE: Duration = Duration::from_secs(3600);
+// 每次最多补算多少张图片的概览数据 需要完整解码源文件 代价较高
+const OVERVIEW_REFRESH_PER_RUN: usize = 1;
+
+static MAINTENANCE_CONFIG: OnceLock<Mutex<MaintenanceConfig>> = OnceLock::new();
+// 同一时间只执行一次维护（调度线程和手动触发可能重叠）
+static MAINTENANCE_RUNNING: Mutex<()> = Mutex::new(());
+
+fn maintenance_config() -> &'static Mutex<MaintenanceConfig> {
+    MAINTENANCE_CONFIG.get_or_init(|| {
+        Mutex::new(MaintenanceConfig {
+            enabled: true,
+            interval_secs: 30 * 60,
+            cache_limit_bytes: None,
+            verify_sample_size: 16,
+        })
+    })
+}
+
+fn current_config() -> MaintenanceConfig {
+    maintenance_config()
+        .lock()
+        .map(|config| config.clone())
+        .unwrap_or_else(|e| e.into_inner().clone())
+}
+
+/// 获取后台维护配置
+#[tauri::command]
+pub fn get_maintenance_config() -> Result<MaintenanceConfig, String> {
+    Ok(current_config())
+}
+
+/// 修改后台维护配置 下一次调度时生效
+/// # Arguments
+/// * `config` - 新配置 间隔至少 60 秒
+/// # Returns
+/// * `Result<MaintenanceConfig, String>` - 生效的配置
+#[tauri::command]
+pub fn set_maintenance_config(config: MaintenanceConfig) -> Result<MaintenanceConfig, String> {
+    let config = MaintenanceConfig {
+        interval_secs: config.interval_secs.max(60),
+        ..config
+    };
+    let mut current = maintenance_config()
+        .lock()
+        .map_err(|e| format!("获取维护配置锁失败: {e}"))?;
+    *current = config.clone();
+    println!("[RUST] 后台维护配置已更新: {config:?}");
+    Ok(config)
+}
+
+/// 立即执行一次维护 不检查是否空闲 缓存只读时不能执行
+#[tauri::command]
+pub fn run_maintenance_now(app: AppHandle) -> Result<MaintenanceReport, String> {
+    ensure_cache_writable("执行后台维护")?;
+    let report = run_maintenance(&current_config());
+    emit_report(&app, &report);
+    Ok(report)
+}
+
+/// 启动后台维护调度线程（只会启动一次）
+pub fn start_maintenance_scheduler(app: AppHandle) {
+    static STARTED: OnceLock<()> = OnceLock::new();
+    if STARTED.set(()).is_err() {
+        return;
+    }
+
+    let spawn_result = thread::Builder::new()
+        .name("maintenance".to_string())
+        .spawn(move || {
+            let mut last_run = Stopwatch::start();
+            loop {
+                thread::sleep(SCHEDULER_TICK);
+                let config = current_config();
+                let now = get_time() as u64;
+                if !config.enabled || last_run.elapsed().as_secs() < config.interval_secs {
+                    continue;
+                }
+                if !is_idle(now) {
+                    continue;
+                }
+
+                let report = run_maintenance(&config);
+                emit_report(&app, &report);
+                last_run = Stopwatch::start();
+            }
+        });
+
+    if let Err(e) = spawn_result {
+        println!("[RUST] 启动后台维护线程失败: {e}");
+    }
+}
+
+fn is_idle(now_ms: u64) -> bool {
+    now_ms.saturating_sub(last_activity_ms()) >= IDLE_THRESHOLD_MS
+        && is_preprocess_queue_idle()
+        && is_export_queue_idle()
+        // 维护的每一项都要写缓存 只读模式下跳过
+        && !is_cache_read_only()
+        // 维护可以推迟 使用电池或过热时不执行
+        && background_policy() == BackgroundPolicy::Normal
+}
+
+fn emit_report(app: &AppHandle, report: &MaintenanceReport) {
+    if let Err(e) = app.emit(MAINTENANCE_COMPLETED_EVENT, report) {
+        println!("[RUST] 发送维护完成事件失败: {e}");
+    }
+}
+
+/// 执行一次维护
+/// # Arguments
+/// * `config` - 维护配置
+/// # Returns
+/// * `MaintenanceReport` - 维护结果 单项失败只记录在 errors 中 不影响其他项
+pub fn run_maintenance(config: &MaintenanceConfig) -> MaintenanceReport {
+    let _guard = MAINTENANCE_RUNNING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    let started_ms = get_time() as u64;
+    let stopwatch = Stopwatch::start();
+    println!("[RUST] 开始后台维护");
+
+    let mut report = MaintenanceReport {
+        started_ms,
+        ..Default::default()
+    };
+    flush_access_stats();
+
+    let cache_dirs: Vec<PathBuf> = fs::read_dir(cache_root())
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    collect_garbage(&cache_dirs, &mut report);
+    verify_sample(&cache_dirs, config.verify_sample_size, &mut report);
+    refresh_overviews(&cache_dirs, &mut report);
+
+    match apply_retention_rules() {
+        Ok(expired) => report.expired = Some(expired),
+        Err(e) => report.errors.push(format!("执行保留规则失败: {e}")),
+    }
+    if let Some(limit) = config.cache_limit_bytes {
+        match enforce_cache_limit(limit) {
+            Ok(eviction) => report.eviction = Some(eviction),
+            Err(e) => report.errors.push(format!("限制缓存大小失败: {e}")),
+        }
+    }
+
+    report.duration_ms = stopwatch.elapsed_ms() as u64;
+    println!(
+        "[RUST] 后台维护完成: 清理 {} 项, 抽查 {} 个 chunk (修复 {}, 失败 {}), 补算 {} 张图片 (耗时: {}ms)",
+        report.gc_removed.len(),
+        report.chunks_verified,
+        report.chunks_repaired,
+        report.chunks_failed,
+        report.overviews_refreshed.len(),
+        report.duration_ms
+    );
+    report
+}
+
+fn is_stale(path: &Path) -> bool {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age >= STALE_DIR_AGE)
+}
+
+// 清理中断留下的残缺缓存目录、重新分块的临时目录和没写完的 chunk 临时文件
+fn collect_garbage(cache_dirs: &[PathBuf], report: &mut MaintenanceReport) {
+    for cache_dir in cache_dirs {
+        let tmp_chunks = fs::read_dir(cache_dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.to_string_lossy().ends_with(CHUNK_TMP_SUFFIX) && is_stale(path));
+        for path in tmp_chunks {
+            match fs::remove_file(&path) {
+                Ok(()) => report.gc_removed.push(path.to_string_lossy().to_string()),
+                Err(e) => report
+                    .errors
+                    .push(format!("清理 {} 失败: {e}", path.display())),
+            }
+        }
+
+        // 只有代理副本的目录不算残缺
+        let target = if !cache_dir.join("source_info.json").exists() && !has_proxy(cache_dir) {
+            cache_dir.clone()
+        } else {
+            cache_dir.join(RECHUNK_TMP_DIR)
+        };
+        if !target.exists() || !is_stale(&target) {
+            continue;
+        }
+        match fs::remove_dir_all(&target) {
+            Ok(()) => report.gc_removed.push(target.to_string_lossy().to_string()),
+            Err(e) => report
+                .errors
+                .push(format!("清理 {} 失败: {e}", target.display())),
+        }
+    }
+}
+
+// 简单的 xorshift 随机数 抽样不需要密码学强度
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+// 随机抽查若干 chunk 的完整性 损坏且源文件还在时重新生成
+fn verify_sample(cache_dirs: &[PathBuf], sample_size: u32, report: &mut MaintenanceReport) {
+    let images: Vec<(String, ImageMetadata, &PathBuf)> = cache_dirs
+        .iter()
+        .filter_map(|cache_dir| {
+            let file_path = load_source_info(cache_dir)?
+                .get("file_path")?
+                .as_str()?
+                .to_string();
+            let content = fs::read_to_string(cache_dir.join("metadata.json")).ok()?;
+            let metadata = decode_metadata(&content).ok()?;
+            (!metadata.chunks.is_empty()).then_some((file_path, metadata, cache_dir))
+        })
+        .collect();
+    if images.is_empty() {
+        return;
+    }
+
+    let mut state = get_time() as u64 | 1;
+    for _ in 0..sample_size {
+        let (file_path, metadata, cache_dir) =
+            &images[(next_random(&mut state) % images.len() as u64) as usize];
+        let chunk =
+            &metadata.chunks[(next_random(&mut state) % metadata.chunks.len() as u64) as usize];
+        let chunk_path = cache_dir.join(format!("chunk_{}_{}.bin", chunk.chunk_x, chunk.chunk_y));
+
+        report.chunks_verified += 1;
+        let valid = fs::read(&chunk_path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| validate_chunk_data(&data));
+        if valid.is_ok() {
+            continue;
+        }
+        match regenerate_chunk(file_path, chunk.chunk_x, chunk.chunk_y) {
+            Ok(()) => report.chunks_repaired += 1,
+            Err(e) => {
+                report.chunks_failed += 1;
+                report.errors.push(format!(
+                    "{file_path} 的 chunk ({}, {}) 损坏且无法修复: {e}",
+                    chunk.chunk_x, chunk.chunk_y
+                ));
+            }
+        }
+    }
+}
+
+// 给旧版本生成的缓存补算感知哈希等概览数据
+fn refresh_overviews(cache_dirs: &[PathBuf], report: &mut MaintenanceReport) {
+    let candidates = cache_dirs.iter().filter_map(|cache_dir| {
+        let info = load_source_info(cache_dir)?;
+        if info.get("phash").is_some() {
+            return None;
+        }
+        let file_path = info.get("file_path")?.as_str()?.to_string();
+        Path::new(&file_path)
+            .exists()
+            .then_some((file_path, cache_dir))
+    });
+
+    for (file_path, cache_dir) in candidates.take(OVERVIEW_REFRESH_PER_RUN) {
+        let result = decode_source_image(&file_path)
+            .and_then(|img| record_perceptual_hashes(cache_dir, &img.to_rgba8()));
+        match result {
+            Ok(()) => report.overviews_refreshed.push(file_path),
+            Err(e) => report
+                .errors
+                .push(format!("补算 {file_path} 的概览数据失败: {e}")),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use sysinfo::{get_current_pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+use super::types::{MemoryConsumerUsage, MemoryUsage};
+
+// 内存压力检测间隔
+const PRESSURE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+// 系统可用内存低于总内存的这个比例时认为处于内存压力下
+#[cfg(not(mobile))]
+const PRESSURE_AVAILABLE_RATIO: f64 = 0.1;
+// 或者可用内存低于这个绝对值
+#[cfg(not(mobile))]
+const PRESSURE_AVAILABLE_BYTES: u64 = 512 * 1024 * 1024;
+// 移动端系统会直接杀掉占用内存多的后台应用 需要更早释放
+#[cfg(mobile)]
+const PRESSURE_AVAILABLE_RATIO: f64 = 0.25;
+#[cfg(mobile)]
+const PRESSURE_AVAILABLE_BYTES: u64 = 768 * 1024 * 1024;
+
+/// 可以统计和释放内存的占用方
+/// 内存中的 chunk 缓存、像素缓冲池等都应实现这个 trait 并登记
+pub trait MemoryConsumer: Send + Sync {
+    /// 占用方名称
+    fn name(&self) -> &str;
+    /// 当前占用的字节数
+    fn bytes_held(&self) -> u64;
+    /// 收缩到不超过 target_bytes 返回释放的字节数
+    fn trim(&self, target_bytes: u64) -> u64;
+}
+
+static MEMORY_CONSUMERS: OnceLock<Mutex<Vec<Arc<dyn MemoryConsumer>>>> = OnceLock::new();
+
+fn consumers() -> &'static Mutex<Vec<Arc<dyn MemoryConsumer>>> {
+    MEMORY_CONSUMERS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// 登记一个内存占用方
+pub fn register_memory_consumer(consumer: Arc<dyn MemoryConsumer>) {
+    if let Ok(mut list) = consumers().lock() {
+        list.push(consumer);
+    }
+}
+
+/// 获取内存使用情况
+#[tauri::command]
+pub fn get_memory_usage() -> Result<MemoryUsage, String> {
+    Ok(collect_memory_usage(0))
+}
+
+/// 主动释放内存 把所有占用方收缩到原来的一半
+/// 前端在切换图片或收到系统低内存通知时可以调用
+#[tauri::command]
+pub fn trim_memory() -> Result<MemoryUsage, String> {
+    let freed = trim_all_consumers();
+    println!("[RUST] 手动释放内存: {} MB", freed / 1024 / 1024);
+    Ok(collect_memory_usage(freed))
+}
+
+/// 启动内存压力监控线程（只会启动一次）
+/// 系统可用内存过低时自动收缩所有占用方
+pub fn start_memory_pressure_monitor() {
+    static STARTED: OnceLock<()> = OnceLock::new();
+    if STARTED.set(()).is_err() {
+        return;
+    }
+
+    let spawn_result = thread::Builder::new()
+        .name("memory-pressure".to_string())
+        .spawn(|| {
+            let mut system = System::new();
+            loop {
+                thread::sleep(PRESSURE_CHECK_INTERVAL);
+                system.refresh_memory();
+                let total = system.total_memory();
+                let available = system.available_memory();
+                if is_under_pressure(total, available) {
+                    let freed = trim_all_consumers();
+                    if freed > 0 {
+                        println!(
+                            "[RUST] 检测到内存压力 (可用 {} MB / 总计 {} MB)，已释放 {} MB",
+                            available / 1024 / 1024,
+                            total / 1024 / 1024,
+                            freed / 1024 / 1024
+                        );
+                    }
+                }
+            }
+        });
+
+    if let Err(e) = spawn_result {
+        println!("[RUST] 启动内存压力监控线程失败: {e}");
+    }
+}
+
+fn is_under_pressure(total: u64, available: u64) -> bool {
+    if total == 0 {
+        return false;
+    }
+    (available as f64) < total as f64 * PRESSURE_AVAILABLE_RATIO
+        || available < PRESSURE_AVAILABLE_BYTES
+}
+
+// 每个占用方收缩到当前的一半 连续的压力信号会逐步释放
+fn trim_all_consumers() -> u64 {
+    // 先复制一份列表再释放锁 避免 trim 期间阻塞登记
+    let list: Vec<Arc<dyn MemoryConsumer>> = match consumers().lock() {
+        Ok(list) => list.clone(),
+        Err(_) => return 0,
+    };
+    list.iter()
+        .map(|consumer| consumer.trim(consumer.bytes_held() / 2))
+        .sum()
+}
+
+fn collect_memory_usage(freed_bytes: u64) -> MemoryUsage {
+    let list: Vec<Arc<dyn MemoryConsumer>> = consumers()
+        .lock()
+        .map(|list| list.clone())
+        .unwrap_or_default();
+    let consumers: Vec<MemoryConsumerUsage> = list
+        .iter()
+        .map(|consumer| MemoryConsumerUsage {
+            name: consumer.name().to_string(),
+            bytes: consumer.bytes_held(),
+        })
+        .collect();
+
+    let mut system = System::new();
+    system.refresh_memory();
+    let process_resident_bytes = get_current_pid().ok().and_then(|pid| {
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[pid]),
+            false,
+            ProcessRefreshKind::nothing().with_memory(),
+        );
+        system.process(pid).map(|process| process.memory())
+    });
+
+    MemoryUsage {
+        tracked_bytes: consumers.iter().map(|c| c.bytes).sum(),
+        consumers,
+        process_resident_bytes,
+        system_available_bytes: system.available_memory(),
+        freed_bytes,
+    }
+}
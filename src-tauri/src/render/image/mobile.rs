@@ -0,0 +1,100 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, Manager};
+use tauri_plugin_fs::{FilePath, FsExt, OpenOptions};
+
+use super::cache::fnv1a_hash;
+use super::config::{cache_root, set_cache_root, CHUNK_CACHE_DIR};
+use super::utils::app_data_subdir;
+
+// 移动端（iOS/Android）适配
+// 工作目录不可写 缓存放到应用沙盒的缓存目录中 被系统清理后重新预处理即可
+// chunk 尺寸和内存压力阈值见 config.rs 和 memory.rs 中的 cfg(mobile) 常量
+// Android 的文件选择器返回 content:// URI 不能当作文件路径直接读取 先复制到应用沙盒中
+
+// content URI 复制后的保存位置 放在应用数据目录中 避免源文件被系统当作缓存清理
+const CONTENT_IMPORT_DIR: &str = "imports";
+
+/// 启动时调用 移动端把缓存根目录设置到应用沙盒中 桌面端保持工作目录下的 chunk_cache
+pub fn init_mobile_storage(app: &AppHandle) {
+    if !cfg!(mobile) {
+        return;
+    }
+    match app.path().app_cache_dir() {
+        Ok(dir) => {
+            if set_cache_root(dir.join(CHUNK_CACHE_DIR)) {
+                println!("[RUST] 移动端缓存目录: {}", cache_root().display());
+            }
+        }
+        Err(e) => println!("[RUST] 获取应用缓存目录失败: {e}"),
+    }
+}
+
+/// 把前端传入的路径转换为可以直接读取的本地文件路径
+/// content:// URI 会复制到应用数据目录 其他路径原样返回
+/// 同一个 URI 再次打开时 大小没有变化就复用之前的副本 这样缓存也能复用
+/// # Arguments
+/// * `app` - 应用句柄
+/// * `file_path` - 文件路径或 content URI
+/// # Returns
+/// * `Result<String, String>` - 本地文件路径
+pub fn resolve_input_path(app: &AppHandle, file_path: &str) -> Result<String, String> {
+    if !file_path.starts_with("content://") {
+        return Ok(file_path.to_string());
+    }
+    let url = url::Url::parse(file_path).map_err(|e| format!("无效的 content URI: {e}"))?;
+    let mut options = OpenOptions::new();
+    options.read(true);
+    let mut source = app
+        .fs()
+        .open(FilePath::Url(url), options)
+        .map_err(|e| format!("打开 content URI 失败: {e}"))?;
+    let size = source.metadata().map(|m| m.len()).ok();
+
+    let dir = app_data_subdir(app, CONTENT_IMPORT_DIR)?;
+    let stem = format!("content_{:016x}", fnv1a_hash(file_path.as_bytes()));
+    if let (Some(size), Some(existing)) = (size, find_import(&dir, &stem)) {
+        if fs::metadata(&existing).is_ok_and(|m| m.len() == size) {
+            println!("[RUST] 复用已导入的文件: {}", existing.display());
+            return Ok(existing.to_string_lossy().to_string());
+        }
+    }
+
+    // URI 中没有扩展名 复制完成后根据文件头识别格式
+    let partial = dir.join(format!("{stem}.part"));
+    let mut target = File::create(&partial).map_err(|e| format!("创建导入文件失败: {e}"))?;
+    io::copy(&mut source, &mut target).map_err(|e| format!("复制 content URI 失败: {e}"))?;
+    drop(target);
+
+    let extension = image::io::Reader::open(&partial)
+        .and_then(|reader| reader.with_guessed_format())
+        .ok()
+        .and_then(|reader| reader.format())
+        .and_then(|format| format.extensions_str().first().copied())
+        .unwrap_or("bin");
+    if let Some(existing) = find_import(&dir, &stem) {
+        let _ = fs::remove_file(existing);
+    }
+    let output = dir.join(format!("{stem}.{extension}"));
+    fs::rename(&partial, &output).map_err(|e| format!("保存导入文件失败: {e}"))?;
+
+    println!(
+        "[RUST] 已导入 content URI: {file_path} -> {}",
+        output.display()
+    );
+    Ok(output.to_string_lossy().to_string())
+}
+
+// 查找之前导入的副本 扩展名取决于当时识别的格式
+fn find_import(dir: &Path, stem: &str) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.file_stem().is_some_and(|s| s == stem)
+                && path.extension().is_some_and(|e| e != "part")
+        })
+}
@@ -1,12 +1,180 @@
+pub mod access_stats;
+pub mod annotation_history;
+pub mod annotation_interop;
+pub mod annotation_locks;
+pub mod annotations;
+pub mod arrow_export;
+pub mod backend_info;
+pub mod backpressure;
+pub mod blend;
+pub mod bookmarks;
+pub mod buffer_pool;
 pub mod cache;
+pub mod cache_manager;
+pub mod camera_import;
+pub mod catalog;
+pub mod change_detection;
+pub mod chunk_batch;
+pub mod chunk_coords;
+pub mod chunk_order;
+pub mod chunk_pages;
 pub mod chunk_processing;
+pub mod chunk_repair;
+pub mod clipboard;
+pub mod color_picker;
 pub mod commands;
+pub mod compact_metadata;
 pub mod config;
+pub mod debug_overlay;
+pub mod decode_sandbox;
+pub mod deep_link;
+pub mod diagnostics;
+pub mod dicom;
+pub mod display_profile;
+pub mod drop_handler;
+pub mod errors;
+pub mod exif;
+pub mod export;
+pub mod export_encoder;
+pub mod fft;
+pub mod fingerprint;
+pub mod fits;
+pub mod folder_index;
+pub mod gpu_compute;
+pub mod grid_overlay;
+pub mod image_bytes;
+pub mod job_history;
+pub mod jpeg_quality;
+pub mod level_formats;
+pub mod line_profile;
+pub mod live_mode;
+pub mod lod;
+pub mod lod_pyramid;
+pub mod magnifier;
+pub mod maintenance;
+pub mod memory;
+pub mod mobile;
+pub mod navigation_history;
+pub mod notifications;
+pub mod pan_simulation;
+pub mod pdf;
+pub mod portable_cache;
+pub mod power;
+pub mod preprocess_checkpoint;
+pub mod preprocess_dedup;
+pub mod preprocess_progress;
+pub mod preprocess_queue;
 pub mod preprocessing;
+pub mod print_export;
+pub mod progressive_chunks;
+pub mod proxy;
+pub mod psd;
+pub mod pyramid_import;
+pub mod pyramidal_export;
+pub mod quality_metrics;
+pub mod rechunk;
+pub mod refresh;
+pub mod region_decode;
+pub mod rpc_server;
+pub mod scheduler;
+pub mod screen_capture;
+pub mod security_bookmarks;
+pub mod similarity;
+pub mod startup_open;
+pub mod startup_preload;
+pub mod stitch_artifacts;
+pub mod stripe_processing;
+pub mod system_info;
+pub mod telemetry;
+pub mod texture;
+pub mod tiff_input;
+pub mod tile_archive;
+pub mod timing_breakdown;
+pub mod tours;
 pub mod types;
 pub mod utils;
+pub mod video;
+pub mod viewport_render;
+pub mod watch_folders;
+pub mod window_state;
+pub mod write_tracker;
 
 // 重新导出公共接口，保持API兼容性
+pub use access_stats::*;
+pub use annotation_history::*;
+pub use annotation_interop::*;
+pub use annotation_locks::*;
+pub use annotations::*;
+pub use arrow_export::*;
+pub use backend_info::*;
+pub use backpressure::*;
+pub use blend::*;
+pub use bookmarks::*;
 pub use cache::*;
+pub use cache_manager::*;
+pub use camera_import::*;
+pub use catalog::*;
+pub use change_detection::*;
+pub use chunk_batch::*;
+pub use chunk_coords::*;
+pub use chunk_order::*;
+pub use chunk_pages::*;
+pub use chunk_processing::*;
+pub use clipboard::*;
+pub use color_picker::*;
 pub use commands::*;
+pub use decode_sandbox::*;
+pub use deep_link::*;
+pub use diagnostics::*;
+pub use dicom::*;
+pub use display_profile::*;
+pub use drop_handler::*;
+pub use errors::*;
+pub use export::*;
+pub use fft::*;
+pub use fingerprint::*;
+pub use fits::*;
+pub use folder_index::*;
+pub use grid_overlay::*;
+pub use image_bytes::*;
+pub use job_history::*;
+pub use level_formats::*;
+pub use line_profile::*;
+pub use live_mode::*;
+pub use lod::*;
+pub use magnifier::*;
+pub use maintenance::*;
+pub use memory::*;
+pub use mobile::*;
+pub use navigation_history::*;
+pub use notifications::*;
+pub use pan_simulation::*;
+pub use pdf::*;
+pub use portable_cache::*;
+pub use power::*;
+pub use preprocess_queue::*;
 pub use preprocessing::*;
+pub use print_export::*;
+pub use progressive_chunks::*;
+pub use proxy::*;
+pub use psd::*;
+pub use pyramidal_export::*;
+pub use quality_metrics::*;
+pub use rechunk::*;
+pub use refresh::*;
+pub use rpc_server::*;
+pub use scheduler::*;
+pub use screen_capture::*;
+pub use similarity::*;
+pub use startup_open::*;
+pub use startup_preload::*;
+pub use stitch_artifacts::*;
+pub use system_info::*;
+pub use telemetry::*;
+pub use texture::*;
+pub use tile_archive::*;
+pub use tours::*;
+pub use video::*;
+pub use viewport_render::*;
+pub use watch_folders::*;
+pub use window_state::*;
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::cache::{
+    check_file_cache_exists, image_cache_dir, is_cache_read_only, load_cached_metadata,
+};
+use super::chunk_order::{chunk_order, ordered_chunks};
+use super::chunk_processing::{chunk_memory_cache, map_chunk_file};
+use super::chunk_repair::validate_chunk_data;
+use super::errors::{localized_error, ErrorCode};
+use super::types::{ChunkInfo, Viewport, WarmCacheReport};
+use crate::utils::time::Stopwatch;
+
+// 浏览轨迹：记录每次打开图片后最先看的几个视口 保存在图片缓存目录的 navigation_history.json 中
+// 重新打开图片时按历史轨迹预先读取用户最先会看的 chunk（例如中心和常看的角落）
+// 预读的 chunk 放进内存中的 chunk 缓存（见 chunk_processing） 第一帧不用等磁盘
+
+// 浏览轨迹文件
+const NAVIGATION_HISTORY_FILE: &str = "navigation_history.json";
+// 保留最近几次浏览
+const MAX_NAVIGATION_SESSIONS: usize = 10;
+// 每次浏览只记录最先看的几个视口 之后的视口对首帧没有帮助
+const MAX_SESSION_VIEWPORTS: usize = 20;
+// 两次上报间隔超过这个时间算作新的一次浏览
+const SESSION_GAP_MS: u128 = 10 * 60 * 1000;
+// 预热最多读取的字节数和 chunk 数
+const WARM_CACHE_MAX_BYTES: u64 = 512 * 1024 * 1024;
+const WARM_CACHE_MAX_CHUNKS: usize = 16;
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct NavigationHistory {
+    // 每次浏览按时间顺序记录的视口 最新的一次在最后
+    sessions: Vec<Vec<Viewport>>,
+}
+
+// 每张图片最近一次上报视口的时间 用于划分浏览
+static LAST_RECORDED: OnceLock<Mutex<HashMap<String, Stopwatch>>> = OnceLock::new();
+
+fn last_recorded() -> &'static Mutex<HashMap<String, Stopwatch>> {
+    LAST_RECORDED.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn load_history(file_path: &str) -> NavigationHistory {
+    fs::read_to_string(image_cache_dir(file_path).join(NAVIGATION_HISTORY_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// 记录图片的视口 前端上报视口时调用
+/// 只保存每次浏览最先看的几个视口 后面的视口直接忽略
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `viewport` - 视口（原图像素坐标）
+pub fn record_viewport(file_path: &str, viewport: Viewport) {
+    let new_session = match last_recorded().lock() {
+        Ok(mut last) => {
+            let previous = last.insert(file_path.to_string(), Stopwatch::start());
+            previous.is_none_or(|previous| previous.elapsed_ms() >= SESSION_GAP_MS)
+        }
+        Err(_) => return,
+    };
+
+    let cache_dir = image_cache_dir(file_path);
+    // 还没有缓存时不创建目录 只读缓存不写入
+    if !cache_dir.exists() || is_cache_read_only() {
+        return;
+    }
+    let mut history = load_history(file_path);
+    if new_session || history.sessions.is_empty() {
+        history.sessions.push(Vec::new());
+        let excess = history
+            .sessions
+            .len()
+            .saturating_sub(MAX_NAVIGATION_SESSIONS);
+        history.sessions.drain(..excess);
+    }
+    let Some(session) = history.sessions.last_mut() else {
+        return;
+    };
+    if session.len() >= MAX_SESSION_VIEWPORTS {
+        return;
+    }
+    session.push(viewport);
+    match serde_json::to_string(&history) {
+        Ok(json) => {
+            if let Err(e) = fs::write(cache_dir.join(NAVIGATION_HISTORY_FILE), json) {
+                println!("[RUST] 保存浏览轨迹失败: {e}");
+            }
+        }
+        Err(e) => println!("[RUST] 序列化浏览轨迹失败: {e}"),
+    }
+}
+
+fn intersects(chunk: &ChunkInfo, viewport: &Viewport) -> bool {
+    (chunk.x as f64) < viewport.x + viewport.width
+        && ((chunk.x + chunk.width) as f64) > viewport.x
+        && (chunk.y as f64) < viewport.y + viewport.height
+        && ((chunk.y + chunk.height) as f64) > viewport.y
+}
+
+/// 按历史浏览轨迹预先读取 chunk 缩短重新打开图片时第一帧的等待时间（需要先完成预处理）
+/// 每次浏览中越早看到的视口权重越高 没有历史时从图片中心开始向外读取
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<WarmCacheReport, String>` - 预热的 chunk 数和字节数
+#[tauri::command]
+pub fn warm_cache(file_path: String) -> Result<WarmCacheReport, String> {
+    let stopwatch = Stopwatch::start();
+    // 先取得凭证再检查缓存是否过期 之后源文件变化时读到的 chunk 不会被当作新的
+    let ticket = chunk_memory_cache().ticket(&file_path);
+    if !check_file_cache_exists(&file_path) {
+        return Err(localized_error(ErrorCode::CacheMissing, &[]));
+    }
+    let metadata = load_cached_metadata(&file_path)?;
+    let history = load_history(&file_path);
+
+    let mut scores = vec![0.0f64; metadata.chunks.len()];
+    for session in &history.sessions {
+        for (order, viewport) in session.iter().enumerate() {
+            let weight = 1.0 / (order + 1) as f64;
+            for (score, chunk) in scores.iter_mut().zip(&metadata.chunks) {
+                if intersects(chunk, viewport) {
+                    *score += weight;
+                }
+            }
+        }
+    }
+    let from_history = scores.iter().any(|&score| score > 0.0);
+    if !from_history {
+        // 离图片中心越近分数越高
+        let (center_x, center_y) = (
+            metadata.total_width as f64 / 2.0,
+            metadata.total_height as f64 / 2.0,
+        );
+        for (score, chunk) in scores.iter_mut().zip(&metadata.chunks) {
+            let dx = chunk.x as f64 + chunk.width as f64 / 2.0 - center_x;
+            let dy = chunk.y as f64 + chunk.height as f64 / 2.0 - center_y;
+            *score = 1.0 / (1.0 + dx.hypot(dy));
+        }
+    }
+
+    // 分数相同的 chunk 按配置的处理顺序读取（排序是稳定的）
+    let mut ranked: Vec<(&ChunkInfo, f64)> =
+        ordered_chunks(&metadata.chunks, metadata.col_count, chunk_order())
+            .into_iter()
+            .map(|chunk| {
+                let index = (chunk.chunk_y * metadata.col_count + chunk.chunk_x) as usize;
+                (chunk, scores[index])
+            })
+            .filter(|(_, score)| *score > 0.0)
+            .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let mut selected = Vec::new();
+    let mut budget = 0u64;
+    for (chunk, _) in ranked.into_iter().take(WARM_CACHE_MAX_CHUNKS) {
+        let bytes = 8 + chunk.width as u64 * chunk.height as u64 * 4;
+        if budget + bytes > WARM_CACHE_MAX_BYTES && !selected.is_empty() {
+            break;
+        }
+        budget += bytes;
+        selected.push(chunk);
+    }
+
+    // 校验通过的 chunk 放进内存缓存 损坏的留给正常读取时修复
+    let cache_dir = image_cache_dir(&file_path);
+    let memory_cache = chunk_memory_cache();
+    let bytes: u64 = selected
+        .par_iter()
+        .map(|chunk| {
+            let path = cache_dir.join(format!("chunk_{}_{}.bin", chunk.chunk_x, chunk.chunk_y));
+            match map_chunk_file(&file_path, &path) {
+                Ok(data) if validate_chunk_data(&data).is_ok() => {
+                    data.prefetch();
+                    let len = data.len() as u64;
+                    memory_cache.insert(
+                        &file_path,
+                        chunk.chunk_x,
+                        chunk.chunk_y,
+                        &ticket,
+                        Arc::new(data),
+                    );
+                    len
+                }
+                _ => 0,
+            }
+        })
+        .sum();
+
+    let report = WarmCacheReport {
+        chunks: selected.len() as u32,
+        bytes,
+        from_history,
+    };
+    let source = if from_history {
+        "浏览轨迹"
+    } else {
+        "图片中心"
+    };
+    println!(
+        "[RUST] 缓存预热完成: {} 个 chunk, {} MB（按{source}） (耗时: {}ms)",
+        report.chunks,
+        bytes / 1024 / 1024,
+        stopwatch.elapsed_ms()
+    );
+    Ok(report)
+}
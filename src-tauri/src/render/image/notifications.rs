@@ -0,0 +1,256 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use tauri::AppHandle;
+
+use super::types::{JobKind, JobNotification, JobStatus, NotificationConfig};
+use super::utils::app_data_subdir;
+use crate::utils::time::get_time;
+
+// 任务结束通知：后台预处理和导出任务结束（成功、失败或取消）时调用 webhook 或本地程序
+// 批量处理切片库时 可以在通宵任务结束后收到 Slack 等消息
+// webhook 收到的 JSON 带有 text 字段 可以直接用于 Slack Incoming Webhook
+// 本地程序通过标准输入收到同样的 JSON 并通过环境变量 IMAGES_GL_JOB_* 收到主要字段
+// 通知在独立线程中发送 失败只记录日志 不影响任务本身
+
+// 配置文件 位于应用数据目录的 settings 子目录下
+const NOTIFICATION_CONFIG_FILE: &str = "notifications.json";
+// webhook 请求的超时时间
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+static NOTIFICATION_CONFIG: OnceLock<Mutex<NotificationConfig>> = OnceLock::new();
+
+fn config_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_subdir(app, "settings")?.join(NOTIFICATION_CONFIG_FILE))
+}
+
+fn notification_config(app: &AppHandle) -> &'static Mutex<NotificationConfig> {
+    NOTIFICATION_CONFIG.get_or_init(|| {
+        let config = config_path(app)
+            .ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Mutex::new(config)
+    })
+}
+
+fn current_config(app: &AppHandle) -> NotificationConfig {
+    notification_config(app)
+        .lock()
+        .map(|config| config.clone())
+        .unwrap_or_else(|e| e.into_inner().clone())
+}
+
+/// 获取任务结束通知的配置
+#[tauri::command]
+pub fn get_notification_config(app: AppHandle) -> Result<NotificationConfig, String> {
+    Ok(current_config(&app))
+}
+
+/// 修改任务结束通知的配置 保存在应用数据目录中 重启后仍然有效
+/// # Arguments
+/// * `config` - 新配置 webhook 地址需要是 http/https 地址 空字符串表示不使用
+/// # Returns
+/// * `Result<NotificationConfig, String>` - 生效的配置
+#[tauri::command]
+pub fn set_notification_config(
+    app: AppHandle,
+    config: NotificationConfig,
+) -> Result<NotificationConfig, String> {
+    let non_empty = |value: Option<String>| {
+        value
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let config = NotificationConfig {
+        webhook_url: non_empty(config.webhook_url),
+        script: non_empty(config.script),
+        ..config
+    };
+    if let Some(webhook_url) = &config.webhook_url {
+        let url = url::Url::parse(webhook_url).map_err(|e| format!("无效的 webhook 地址: {e}"))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("webhook 地址只支持 http/https: {webhook_url}"));
+        }
+    }
+
+    let json =
+        serde_json::to_string_pretty(&config).map_err(|e| format!("序列化通知配置失败: {e}"))?;
+    fs::write(config_path(&app)?, json).map_err(|e| format!("保存通知配置失败: {e}"))?;
+    *notification_config(&app)
+        .lock()
+        .map_err(|e| format!("获取通知配置锁失败: {e}"))? = config.clone();
+    println!("[RUST] 任务通知配置已更新: {config:?}");
+    Ok(config)
+}
+
+/// 发送一条测试通知 同步等待结果 用于检查配置是否正确
+#[tauri::command]
+pub fn test_notification(app: AppHandle) -> Result<(), String> {
+    let config = current_config(&app);
+    if config.webhook_url.is_none() && config.script.is_none() {
+        return Err("没有配置 webhook 或本地程序".to_string());
+    }
+    let notification = JobNotification {
+        text: "images-gl 测试通知".to_string(),
+        kind: JobKind::Export,
+        status: JobStatus::Succeeded,
+        job_id: "test".to_string(),
+        file_path: String::new(),
+        output_path: None,
+        error: None,
+        duration_ms: 0,
+        finished_ms: get_time() as u64,
+    };
+    deliver(&config, &notification)
+}
+
+/// 生成任务结束通知
+/// # Arguments
+/// * `kind` - 任务类型
+/// * `job_id` - 任务 ID
+/// * `file_path` - 图片文件路径
+/// * `output_path` - 导出任务的输出路径
+/// * `result` - 任务结果 Ok(false) 表示被取消
+/// * `started_ms` - 任务开始时间
+pub fn job_notification(
+    kind: JobKind,
+    job_id: &str,
+    file_path: &str,
+    output_path: Option<&str>,
+    result: Result<bool, &str>,
+    started_ms: u64,
+) -> JobNotification {
+    let finished_ms = get_time() as u64;
+    let duration_ms = finished_ms.saturating_sub(started_ms);
+    let status = match result {
+        Ok(true) => JobStatus::Succeeded,
+        Ok(false) => JobStatus::Cancelled,
+        Err(_) => JobStatus::Failed,
+    };
+    let action = match kind {
+        JobKind::Preprocess => "预处理",
+        JobKind::Export => "导出",
+    };
+    let outcome = match status {
+        JobStatus::Succeeded => "完成",
+        JobStatus::Failed => "失败",
+        JobStatus::Cancelled => "已取消",
+    };
+    let target = output_path.unwrap_or(file_path);
+    let mut text = format!(
+        "images-gl {action}{outcome}: {target} (耗时 {:.1}s)",
+        duration_ms as f64 / 1000.0
+    );
+    if let Err(e) = result {
+        text.push_str(&format!("\n{e}"));
+    }
+
+    JobNotification {
+        text,
+        kind,
+        status,
+        job_id: job_id.to_string(),
+        file_path: file_path.to_string(),
+        output_path: output_path.map(str::to_string),
+        error: result.err().map(str::to_string),
+        duration_ms,
+        finished_ms,
+    }
+}
+
+/// 任务结束时调用 按配置在后台线程中发送通知
+pub fn notify_job_finished(app: &AppHandle, notification: JobNotification) {
+    let config = current_config(app);
+    let wanted = match notification.status {
+        JobStatus::Succeeded => config.on_success,
+        JobStatus::Failed | JobStatus::Cancelled => config.on_failure,
+    };
+    if !wanted || (config.webhook_url.is_none() && config.script.is_none()) {
+        return;
+    }
+
+    let spawn_result = thread::Builder::new()
+        .name("job-notification".to_string())
+        .spawn(move || {
+            if let Err(e) = deliver(&config, &notification) {
+                println!("[RUST] 发送任务通知失败: {} ({e})", notification.job_id);
+            }
+        });
+    if let Err(e) = spawn_result {
+        println!("[RUST] 启动任务通知线程失败: {e}");
+    }
+}
+
+// 依次发送 webhook 和执行本地程序 一个失败不影响另一个
+fn deliver(config: &NotificationConfig, notification: &JobNotification) -> Result<(), String> {
+    let json =
+        serde_json::to_string(notification).map_err(|e| format!("序列化任务通知失败: {e}"))?;
+    let mut errors = Vec::new();
+    if let Some(webhook_url) = &config.webhook_url {
+        if let Err(e) = post_webhook(webhook_url, &json) {
+            errors.push(e);
+        }
+    }
+    if let Some(script) = &config.script {
+        if let Err(e) = run_script(script, notification, &json) {
+            errors.push(e);
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+fn post_webhook(webhook_url: &str, json: &str) -> Result<(), String> {
+    ureq::post(webhook_url)
+        .timeout(WEBHOOK_TIMEOUT)
+        .set("Content-Type", "application/json")
+        .send_string(json)
+        .map_err(|e| format!("调用 webhook 失败: {e}"))?;
+    Ok(())
+}
+
+fn run_script(script: &str, notification: &JobNotification, json: &str) -> Result<(), String> {
+    let status = match notification.status {
+        JobStatus::Succeeded => "succeeded",
+        JobStatus::Failed => "failed",
+        JobStatus::Cancelled => "cancelled",
+    };
+    let kind = match notification.kind {
+        JobKind::Preprocess => "preprocess",
+        JobKind::Export => "export",
+    };
+    let mut child = Command::new(script)
+        .env("IMAGES_GL_JOB_KIND", kind)
+        .env("IMAGES_GL_JOB_STATUS", status)
+        .env("IMAGES_GL_JOB_ID", &notification.job_id)
+        .env("IMAGES_GL_JOB_FILE", &notification.file_path)
+        .env(
+            "IMAGES_GL_JOB_OUTPUT",
+            notification.output_path.as_deref().unwrap_or_default(),
+        )
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("启动通知程序失败: {script} ({e})"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // 程序不读取标准输入时写入会失败 不算错误
+        let _ = stdin.write_all(json.as_bytes());
+    }
+    let exit = child
+        .wait()
+        .map_err(|e| format!("等待通知程序失败: {script} ({e})"))?;
+    if !exit.success() {
+        return Err(format!("通知程序退出码异常: {script} ({exit})"));
+    }
+    Ok(())
+}
@@ -0,0 +1,196 @@
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::time::Instant;
+
+use super::cache::load_cached_metadata;
+use super::chunk_processing::get_image_chunk_sync;
+use super::scheduler::run_scheduled;
+use super::types::{ImageMetadata, PanSimulationReport, TaskKind, Viewport};
+
+// 插值后的最大帧数 每一帧都要读取 chunk 过长的轨迹或过小的速度会让测试停不下来
+const MAX_SIMULATION_FRAMES: u64 = 100_000;
+
+/// 平移压力测试
+/// 按脚本化的视口轨迹回放请求 统计 chunk 命中率、读取耗时和失败次数
+/// 用于客观比较不同配置（chunk 尺寸、压缩、预取）的效果
+/// # Arguments
+/// * `file_path` - 图片文件路径（需要已经预处理）
+/// * `path` - 视口关键帧 相邻关键帧之间线性插值
+/// * `speed` - 每帧移动的图片像素数
+/// # Returns
+/// * `Result<PanSimulationReport, String>` - 测试报告
+#[tauri::command]
+pub fn simulate_pan(
+    file_path: String,
+    path: Vec<Viewport>,
+    speed: f64,
+) -> Result<PanSimulationReport, String> {
+    if path.is_empty() {
+        return Err("视口轨迹不能为空".to_string());
+    }
+    if !speed.is_finite() || speed <= 0.0 {
+        return Err(format!("移动速度必须为正数: {speed}"));
+    }
+
+    let metadata = load_cached_metadata(&file_path)?;
+    let frames = interpolate_path(&path, speed)?;
+    println!(
+        "[RUST] 开始平移压力测试: {} 个关键帧, {} 帧, 速度 {speed} 像素/帧",
+        path.len(),
+        frames.len()
+    );
+
+    let start = Instant::now();
+    // 模拟前端已经加载过的 chunk
+    let mut loaded: HashSet<(u32, u32)> = HashSet::new();
+    let mut chunk_requests = 0;
+    let mut frontend_cache_hits = 0;
+    let mut backend_misses = 0;
+    let mut latencies: Vec<f64> = Vec::new();
+
+    for viewport in &frames {
+        let visible = visible_chunks(&metadata, viewport);
+        let to_fetch: Vec<(u32, u32)> = visible
+            .iter()
+            .copied()
+            .filter(|chunk| !loaded.contains(chunk))
+            .collect();
+        chunk_requests += visible.len() as u32;
+        frontend_cache_hits += (visible.len() - to_fetch.len()) as u32;
+
+        // 前端对同一帧的 chunk 是并发 invoke 的 这里同样并行读取
+        let results: Vec<((u32, u32), f64, bool)> =
+            run_scheduled(TaskKind::Analysis, &file_path, || {
+                to_fetch
+                    .par_iter()
+                    .map(|&(chunk_x, chunk_y)| {
+                        let read_start = Instant::now();
+                        let ok =
+                            get_image_chunk_sync(chunk_x, chunk_y, file_path.clone(), false, None)
+                                .is_ok();
+                        let elapsed = read_start.elapsed().as_secs_f64() * 1000.0;
+                        ((chunk_x, chunk_y), elapsed, ok)
+                    })
+                    .collect()
+            })?;
+
+        for (chunk, elapsed, ok) in results {
+            if ok {
+                latencies.push(elapsed);
+                loaded.insert(chunk);
+            } else {
+                backend_misses += 1;
+            }
+        }
+    }
+
+    let total_time_ms = start.elapsed().as_secs_f64() * 1000.0;
+    let backend_reads = latencies.len() as u32 + backend_misses;
+
+    latencies.sort_by(|a, b| a.total_cmp(b));
+    let percentile = |p: f64| -> f64 {
+        if latencies.is_empty() {
+            return 0.0;
+        }
+        let index = ((latencies.len() - 1) as f64 * p).round() as usize;
+        latencies[index]
+    };
+
+    let report = PanSimulationReport {
+        frames: frames.len() as u32,
+        chunk_requests,
+        frontend_cache_hits,
+        backend_reads,
+        backend_misses,
+        hit_rate: if chunk_requests > 0 {
+            frontend_cache_hits as f64 / chunk_requests as f64
+        } else {
+            0.0
+        },
+        min_latency_ms: latencies.first().copied().unwrap_or(0.0),
+        avg_latency_ms: if latencies.is_empty() {
+            0.0
+        } else {
+            latencies.iter().sum::<f64>() / latencies.len() as f64
+        },
+        p95_latency_ms: percentile(0.95),
+        max_latency_ms: latencies.last().copied().unwrap_or(0.0),
+        total_time_ms,
+    };
+
+    println!(
+        "[RUST] 平移压力测试完成: 命中率 {:.1}%, 后端读取 {} 次, 失败 {} 次, 平均耗时 {:.2}ms, P95 {:.2}ms",
+        report.hit_rate * 100.0,
+        report.backend_reads,
+        report.backend_misses,
+        report.avg_latency_ms,
+        report.p95_latency_ms
+    );
+
+    Ok(report)
+}
+
+/// 在关键帧之间按速度线性插值 生成逐帧的视口序列
+/// 总帧数超过 MAX_SIMULATION_FRAMES 时返回错误
+pub fn interpolate_path(path: &[Viewport], speed: f64) -> Result<Vec<Viewport>, String> {
+    // 先算出每一段的帧数 确认总数不超过上限后再分配
+    let mut segment_steps = Vec::with_capacity(path.len().saturating_sub(1));
+    let mut total_frames = 1.0;
+    for pair in path.windows(2) {
+        let (from, to) = (pair[0], pair[1]);
+        let distance = ((to.x - from.x).powi(2) + (to.y - from.y).powi(2)).sqrt();
+        let steps = (distance / speed).ceil().max(1.0);
+        total_frames += steps;
+        if total_frames > MAX_SIMULATION_FRAMES as f64 {
+            return Err(format!(
+                "平移轨迹插值后超过 {MAX_SIMULATION_FRAMES} 帧 请缩短轨迹或提高速度"
+            ));
+        }
+        segment_steps.push(steps as u32);
+    }
+
+    let mut frames = Vec::with_capacity(total_frames as usize);
+    frames.push(path[0]);
+    for (pair, &steps) in path.windows(2).zip(&segment_steps) {
+        let (from, to) = (pair[0], pair[1]);
+        for step in 1..=steps {
+            let t = step as f64 / steps as f64;
+            frames.push(Viewport {
+                x: from.x + (to.x - from.x) * t,
+                y: from.y + (to.y - from.y) * t,
+                width: from.width + (to.width - from.width) * t,
+                height: from.height + (to.height - from.height) * t,
+            });
+        }
+    }
+    Ok(frames)
+}
+
+/// 计算视口覆盖到的 chunk 索引
+pub fn visible_chunks(metadata: &ImageMetadata, viewport: &Viewport) -> Vec<(u32, u32)> {
+    let left = viewport.x.max(0.0);
+    let top = viewport.y.max(0.0);
+    let right = (viewport.x + viewport.width).min(metadata.total_width as f64);
+    let bottom = (viewport.y + viewport.height).min(metadata.total_height as f64);
+    if right <= left || bottom <= top {
+        return Vec::new();
+    }
+
+    let first_col = (left / metadata.chunk_size_x as f64) as u32;
+    let first_row = (top / metadata.chunk_size_y as f64) as u32;
+    // 右下边界是开区间 所以向上取整后减一
+    let last_col = ((right / metadata.chunk_size_x as f64).ceil() as u32)
+        .saturating_sub(1)
+        .min(metadata.col_count.saturating_sub(1));
+    let last_row = ((bottom / metadata.chunk_size_y as f64).ceil() as u32)
+        .saturating_sub(1)
+        .min(metadata.row_count.saturating_sub(1));
+
+    let mut chunks = Vec::new();
+    for chunk_y in first_row..=last_row {
+        for chunk_x in first_col..=last_col {
+            chunks.push((chunk_x, chunk_y));
+        }
+    }
+    chunks
+}
@@ -0,0 +1,165 @@
+use std::path::Path;
+
+use tauri::{AppHandle, Window};
+
+use super::cache::fnv1a_hash;
+use super::commands::load_user_image;
+use super::types::ImageMetadata;
+use super::utils::{app_data_subdir, is_up_to_date};
+use super::window_state::set_window_image;
+
+// PDF 页面渲染结果在应用数据目录下的保存位置
+const PDF_PAGE_DIR: &str = "pdf_pages";
+
+// 默认渲染 DPI 以及允许的范围
+const DEFAULT_PDF_DPI: f32 = 300.0;
+const MIN_PDF_DPI: f32 = 36.0;
+const MAX_PDF_DPI: f32 = 2400.0;
+
+// 渲染结果单边的最大像素数 A0 图纸在 1200 DPI 下约 56000 像素
+const MAX_RENDER_DIMENSION: f32 = 65535.0;
+
+/// 获取 PDF 的页数
+/// 需要启用 pdf 特性 运行时还需要能找到 pdfium 动态库
+#[tauri::command]
+pub fn get_pdf_page_count(file_path: String) -> Result<u32, String> {
+    backend::page_count(Path::new(&file_path))
+}
+
+/// 把 PDF 的指定页面按给定 DPI 栅格化 然后走正常的预处理流程
+/// 渲染结果保存为应用数据目录下的 PNG 文件 同一页面和 DPI 在源文件未变化时直接复用
+/// 保存后的路径会记录为调用窗口当前打开的图片 前端可以通过 get_window_state 取得
+/// # Arguments
+/// * `file_path` - PDF 文件路径
+/// * `page` - 页码 从 0 开始
+/// * `dpi` - 渲染 DPI 不传时为 300
+#[tauri::command]
+pub fn process_pdf_page(
+    app: AppHandle,
+    window: Window,
+    file_path: String,
+    page: u32,
+    dpi: Option<f32>,
+) -> Result<ImageMetadata, String> {
+    let dpi = dpi
+        .unwrap_or(DEFAULT_PDF_DPI)
+        .clamp(MIN_PDF_DPI, MAX_PDF_DPI);
+    let source = Path::new(&file_path);
+    if !source.exists() {
+        return Err(format!("PDF 文件不存在: {file_path}"));
+    }
+
+    let dir = app_data_subdir(&app, PDF_PAGE_DIR)?;
+
+    let output = dir.join(format!(
+        "pdf_{:016x}_p{}_{}dpi.png",
+        fnv1a_hash(file_path.as_bytes()),
+        page,
+        dpi.round() as u32
+    ));
+
+    if is_up_to_date(source, &output) {
+        println!("[RUST] 复用已渲染的 PDF 页面: {}", output.display());
+    } else {
+        backend::render_page(source, page, dpi, &output)?;
+    }
+
+    let output = output.to_string_lossy().to_string();
+    let metadata = load_user_image(&output)?;
+    set_window_image(window.label(), &output);
+    Ok(metadata)
+}
+
+// 根据渲染 DPI 计算输出尺寸 PDF 的单位是点（1/72 英寸）
+#[cfg_attr(not(feature = "pdf"), allow(dead_code))]
+fn target_size(width_points: f32, height_points: f32, dpi: f32) -> Result<(i32, i32), String> {
+    let width = (width_points / 72.0 * dpi).round();
+    let height = (height_points / 72.0 * dpi).round();
+    if width < 1.0 || height < 1.0 {
+        return Err("PDF 页面尺寸无效".to_string());
+    }
+    if width > MAX_RENDER_DIMENSION || height > MAX_RENDER_DIMENSION {
+        return Err(format!(
+            "渲染尺寸 {width}x{height} 超过上限 {MAX_RENDER_DIMENSION} 请降低 DPI"
+        ));
+    }
+    Ok((width as i32, height as i32))
+}
+
+#[cfg(feature = "pdf")]
+mod backend {
+    use std::path::Path;
+
+    use pdfium_render::prelude::*;
+
+    use super::super::utils::save_rgba_png;
+    use super::target_size;
+
+    fn load_pdfium() -> Result<Pdfium, String> {
+        // 优先使用可执行文件旁边的 pdfium 其次是系统库
+        let bindings = Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path("./"))
+            .or_else(|_| Pdfium::bind_to_system_library())
+            .map_err(|e| format!("加载 pdfium 库失败: {e}"))?;
+        Ok(Pdfium::new(bindings))
+    }
+
+    pub fn page_count(path: &Path) -> Result<u32, String> {
+        let pdfium = load_pdfium()?;
+        let document = pdfium
+            .load_pdf_from_file(path, None)
+            .map_err(|e| format!("打开 PDF 失败: {e}"))?;
+        Ok(document.pages().len() as u32)
+    }
+
+    pub fn render_page(path: &Path, page: u32, dpi: f32, output: &Path) -> Result<(), String> {
+        let pdfium = load_pdfium()?;
+        let document = pdfium
+            .load_pdf_from_file(path, None)
+            .map_err(|e| format!("打开 PDF 失败: {e}"))?;
+
+        let pages = document.pages();
+        let page_count = pages.len() as u32;
+        let index = PdfPageIndex::try_from(page)
+            .ok()
+            .filter(|_| page < page_count)
+            .ok_or_else(|| format!("页码超出范围: {page} (共 {page_count} 页)"))?;
+        let pdf_page = pages
+            .get(index)
+            .map_err(|e| format!("读取 PDF 页面失败: {e}"))?;
+
+        let (width, height) = target_size(pdf_page.width().value, pdf_page.height().value, dpi)?;
+        println!("[RUST] 渲染 PDF 第 {page} 页: {width}x{height} @ {dpi}dpi");
+
+        let config = PdfRenderConfig::new()
+            .set_target_width(width)
+            .set_maximum_height(height);
+        let bitmap = pdf_page
+            .render_with_config(&config)
+            .map_err(|e| format!("渲染 PDF 页面失败: {e}"))?;
+
+        save_rgba_png(
+            output,
+            bitmap.width() as u32,
+            bitmap.height() as u32,
+            bitmap.as_rgba_bytes(),
+        )?;
+
+        println!("[RUST] PDF 页面已保存: {}", output.display());
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "pdf"))]
+mod backend {
+    use std::path::Path;
+
+    const DISABLED: &str = "未启用 PDF 支持 请使用 --features pdf 重新编译";
+
+    pub fn page_count(_path: &Path) -> Result<u32, String> {
+        Err(DISABLED.to_string())
+    }
+
+    pub fn render_page(_path: &Path, _page: u32, _dpi: f32, _output: &Path) -> Result<(), String> {
+        Err(DISABLED.to_string())
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use super::cache::{app_data_cache_dir, ensure_cache_writable};
+use super::write_tracker::chunk_write_state;
+
+// 便携缓存：把单张图片的缓存放在源文件旁边的 `image.png.igl-cache/` 目录中 而不是应用数据目录
+// 图片在网络共享盘、移动硬盘上时 缓存跟着数据走 换一台电脑或换一个盘符打开都可以直接使用
+// 旁边存在这个目录就使用它 不需要额外的配置文件 目录被删除后自动回到应用数据目录
+// 便携缓存不参与缓存大小限制和保留规则的淘汰 由用户自己管理
+// 每次获取缓存目录都要判断是否使用便携缓存 源文件在网络共享盘上时检查目录很慢
+// 判断结果在内存中保留几秒 目录被外部删除或创建后最多几秒生效 通过 set_portable_cache 修改时立即生效
+
+/// 便携缓存目录的后缀
+pub const PORTABLE_CACHE_SUFFIX: &str = ".igl-cache";
+
+// 判断结果的有效期
+const PORTABLE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+// 最多记住的图片数 超过时全部丢弃
+const MAX_REMEMBERED_IMAGES: usize = 1024;
+
+// 图片路径 -> (是否使用便携缓存, 判断的时间)
+static PORTABLE_DECISIONS: OnceLock<Mutex<HashMap<String, (bool, Instant)>>> = OnceLock::new();
+
+fn portable_decisions() -> &'static Mutex<HashMap<String, (bool, Instant)>> {
+    PORTABLE_DECISIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn remember_portable(file_path: &str, portable: bool) {
+    let mut decisions = portable_decisions()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if decisions.len() >= MAX_REMEMBERED_IMAGES && !decisions.contains_key(file_path) {
+        decisions.clear();
+    }
+    decisions.insert(file_path.to_string(), (portable, Instant::now()));
+}
+
+/// 图片的便携缓存目录（源文件路径加上 .igl-cache 后缀）
+pub fn portable_cache_dir(file_path: &str) -> PathBuf {
+    PathBuf::from(format!("{file_path}{PORTABLE_CACHE_SUFFIX}"))
+}
+
+/// 图片是否使用便携缓存 最近判断过时直接使用之前的结果
+pub fn is_portable_cache(file_path: &str) -> bool {
+    let remembered = portable_decisions()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(file_path)
+        .filter(|(_, checked)| checked.elapsed() < PORTABLE_CHECK_INTERVAL)
+        .map(|(portable, _)| *portable);
+    if let Some(portable) = remembered {
+        return portable;
+    }
+    let portable = portable_cache_dir(file_path).is_dir();
+    remember_portable(file_path, portable);
+    portable
+}
+
+/// 获取图片是否使用便携缓存
+#[tauri::command]
+pub fn get_portable_cache(file_path: String) -> Result<bool, String> {
+    Ok(is_portable_cache(&file_path))
+}
+
+/// 开启或关闭图片的便携缓存 已有的缓存会移动到新的位置 不需要重新预处理
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `enabled` - true 时缓存放到源文件旁边 false 时放回应用数据目录
+/// # Returns
+/// * `Result<bool, String>` - 设置后是否使用便携缓存
+#[tauri::command]
+pub fn set_portable_cache(file_path: String, enabled: bool) -> Result<bool, String> {
+    ensure_cache_writable("移动缓存")?;
+    if enabled == portable_cache_dir(&file_path).is_dir() {
+        remember_portable(&file_path, enabled);
+        return Ok(enabled);
+    }
+    if chunk_write_state(&file_path, 0, 0).is_some() {
+        return Err(format!("图片正在预处理 请完成后再移动缓存: {file_path}"));
+    }
+
+    let (from, to) = if enabled {
+        (
+            app_data_cache_dir(&file_path),
+            portable_cache_dir(&file_path),
+        )
+    } else {
+        (
+            portable_cache_dir(&file_path),
+            app_data_cache_dir(&file_path),
+        )
+    };
+    if to.exists() {
+        fs::remove_dir_all(&to).map_err(|e| format!("清理旧缓存目录失败: {e}"))?;
+    }
+    if from.is_dir() {
+        move_dir(&from, &to)?;
+    }
+    // 还没有缓存时创建空目录 之后的预处理直接写到源文件旁边
+    if enabled {
+        fs::create_dir_all(&to).map_err(|e| format!("创建便携缓存目录失败: {e}"))?;
+    }
+    remember_portable(&file_path, enabled);
+    println!(
+        "[RUST] 图片 {file_path} 的缓存已移动到{}: {}",
+        if enabled {
+            "源文件旁边"
+        } else {
+            "应用数据目录"
+        },
+        to.display()
+    );
+    Ok(enabled)
+}
+
+// 移动目录 不在同一个磁盘上时复制后删除
+fn move_dir(from: &Path, to: &Path) -> Result<(), String> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_dir(from, to)?;
+    fs::remove_dir_all(from).map_err(|e| format!("删除原缓存目录失败: {e}"))
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<(), String> {
+    fs::create_dir_all(to).map_err(|e| format!("创建缓存目录失败: {e}"))?;
+    let entries = fs::read_dir(from).map_err(|e| format!("读取缓存目录失败: {e}"))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("读取缓存目录失败: {e}"))?;
+        let target = to.join(entry.file_name());
+        if entry.path().is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)
+                .map_err(|e| format!("复制缓存文件失败 {}: {e}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
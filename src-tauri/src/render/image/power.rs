@@ -0,0 +1,265 @@
+use std::env;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::Duration;
+
+use super::types::{BackgroundPolicy, PowerMode, PowerStatus};
+
+// 电源感知：笔记本用电池供电或过热降频时 减慢或暂停后台预处理
+// 前台操作（用户打开图片、请求 chunk）不受影响
+
+// 移动端应用切到后台后 系统随时可能挂起或杀掉进程
+// Tauri 没有提供 iOS beginBackgroundTask / Android WorkManager 这类接口
+// 前端在页面 visibilitychange 时调用 set_app_backgrounded 后台期间不开始新的预处理任务
+// 正在进行的任务继续运行 被杀掉后由预处理断点和持久化的队列在下次启动时继续
+
+// 设置为 auto / performance / power_saver 时作为默认的电源模式
+pub const POWER_MODE_ENV: &str = "IMAGES_GL_POWER_MODE";
+
+// 限速时后台预处理使用的线程数
+const THROTTLED_THREADS: usize = 2;
+// 暂停时重新检查电源状态的间隔
+const PAUSED_RECHECK_INTERVAL: Duration = Duration::from_secs(30);
+// 任意温度传感器超过这个温度（毫摄氏度）时视为过热
+#[cfg(target_os = "linux")]
+const THERMAL_LIMIT_MILLI_CELSIUS: i64 = 90_000;
+
+static POWER_MODE: AtomicU8 = AtomicU8::new(PowerMode::Auto as u8);
+static POWER_MODE_INIT: OnceLock<()> = OnceLock::new();
+static APP_BACKGROUNDED: AtomicBool = AtomicBool::new(false);
+
+fn mode_from_u8(value: u8) -> PowerMode {
+    match value {
+        1 => PowerMode::Performance,
+        2 => PowerMode::PowerSaver,
+        _ => PowerMode::Auto,
+    }
+}
+
+/// 当前的电源模式 首次调用时读取环境变量作为默认值
+pub fn power_mode() -> PowerMode {
+    POWER_MODE_INIT.get_or_init(|| {
+        let mode =
+            env::var(POWER_MODE_ENV)
+                .ok()
+                .and_then(|v| match v.trim().to_lowercase().as_str() {
+                    "auto" => Some(PowerMode::Auto),
+                    "performance" => Some(PowerMode::Performance),
+                    "power_saver" | "powersaver" => Some(PowerMode::PowerSaver),
+                    _ => None,
+                });
+        if let Some(mode) = mode {
+            POWER_MODE.store(mode as u8, Ordering::Relaxed);
+        }
+    });
+    mode_from_u8(POWER_MODE.load(Ordering::Relaxed))
+}
+
+/// 设置电源模式
+/// # Arguments
+/// * `mode` - auto 跟随系统电源状态 / performance 从不限速 / power_saver 始终限速 用电池时暂停
+/// # Returns
+/// * `Result<PowerStatus, String>` - 设置后的电源状态
+#[tauri::command]
+pub fn set_power_mode(mode: PowerMode) -> Result<PowerStatus, String> {
+    // 先触发一次初始化 避免之后读取环境变量覆盖这里的设置
+    power_mode();
+    POWER_MODE.store(mode as u8, Ordering::Relaxed);
+    println!("[RUST] 电源模式已设置为 {mode:?}");
+    Ok(collect_power_status())
+}
+
+/// 获取电源状态和后台任务策略
+#[tauri::command]
+pub fn get_power_status() -> Result<PowerStatus, String> {
+    Ok(collect_power_status())
+}
+
+/// 前端通知应用切换到后台或回到前台
+/// 移动端在后台时暂停开始新的后台预处理 桌面端只记录状态
+/// # Arguments
+/// * `backgrounded` - 是否在后台
+/// # Returns
+/// * `Result<PowerStatus, String>` - 设置后的电源状态
+#[tauri::command]
+pub fn set_app_backgrounded(backgrounded: bool) -> Result<PowerStatus, String> {
+    APP_BACKGROUNDED.store(backgrounded, Ordering::Relaxed);
+    println!(
+        "[RUST] 应用已{}",
+        if backgrounded {
+            "切换到后台"
+        } else {
+            "回到前台"
+        }
+    );
+    Ok(collect_power_status())
+}
+
+fn collect_power_status() -> PowerStatus {
+    let mode = power_mode();
+    let on_battery = query_on_battery();
+    let thermal_limited = query_thermal_limited();
+    let app_backgrounded = APP_BACKGROUNDED.load(Ordering::Relaxed);
+    let policy = if cfg!(mobile) && app_backgrounded {
+        BackgroundPolicy::Paused
+    } else {
+        decide_policy(mode, on_battery.unwrap_or(false), thermal_limited)
+    };
+    PowerStatus {
+        mode,
+        on_battery,
+        thermal_limited,
+        app_backgrounded,
+        policy,
+    }
+}
+
+fn decide_policy(mode: PowerMode, on_battery: bool, thermal_limited: bool) -> BackgroundPolicy {
+    match mode {
+        PowerMode::Performance => BackgroundPolicy::Normal,
+        PowerMode::PowerSaver if on_battery || thermal_limited => BackgroundPolicy::Paused,
+        PowerMode::PowerSaver => BackgroundPolicy::Throttled,
+        PowerMode::Auto if thermal_limited => BackgroundPolicy::Paused,
+        PowerMode::Auto if on_battery => BackgroundPolicy::Throttled,
+        PowerMode::Auto => BackgroundPolicy::Normal,
+    }
+}
+
+/// 当前后台任务应采用的策略
+pub fn background_policy() -> BackgroundPolicy {
+    collect_power_status().policy
+}
+
+/// 在后台任务开始前调用 暂停状态下一直等待 直到可以运行
+/// # Returns
+/// * `BackgroundPolicy` - 开始运行时的策略（Normal 或 Throttled）
+pub fn wait_for_background_slot() -> BackgroundPolicy {
+    let mut logged = false;
+    loop {
+        let policy = background_policy();
+        if policy != BackgroundPolicy::Paused {
+            return policy;
+        }
+        if !logged {
+            println!("[RUST] 使用电池、过热或应用在后台 后台预处理暂停");
+            logged = true;
+        }
+        thread::sleep(PAUSED_RECHECK_INTERVAL);
+    }
+}
+
+/// 限速时使用的小线程池
+pub fn throttled_pool() -> &'static rayon::ThreadPool {
+    static POOL: OnceLock<rayon::ThreadPool> = OnceLock::new();
+    POOL.get_or_init(|| {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(THROTTLED_THREADS)
+            .thread_name(|i| format!("throttled-{i}"))
+            .build()
+            .expect("创建限速线程池失败")
+    })
+}
+
+// 是否使用电池供电 无法判断时为 None（例如台式机或不支持的平台）
+#[cfg(target_os = "linux")]
+fn query_on_battery() -> Option<bool> {
+    let entries = std::fs::read_dir("/sys/class/power_supply").ok()?;
+    let read = |path: std::path::PathBuf| {
+        std::fs::read_to_string(path)
+            .map(|v| v.trim().to_string())
+            .unwrap_or_default()
+    };
+
+    let mut has_battery = false;
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        match read(path.join("type")).as_str() {
+            "Mains" | "USB" if read(path.join("online")) == "1" => return Some(false),
+            "Battery" => {
+                has_battery = true;
+                if read(path.join("status")) == "Discharging" {
+                    return Some(true);
+                }
+            }
+            _ => {}
+        }
+    }
+    has_battery.then_some(false)
+}
+
+#[cfg(target_os = "macos")]
+fn query_on_battery() -> Option<bool> {
+    let output = std::process::Command::new("pmset")
+        .args(["-g", "batt"])
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    Some(text.contains("'Battery Power'"))
+}
+
+#[cfg(windows)]
+fn query_on_battery() -> Option<bool> {
+    use windows_sys::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status: SYSTEM_POWER_STATUS = unsafe { std::mem::zeroed() };
+    if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+        return None;
+    }
+    // ACLineStatus: 0 离线 1 在线 255 未知；BatteryFlag 128 表示没有电池
+    match (status.ACLineStatus, status.BatteryFlag) {
+        (_, 128) | (255, _) => None,
+        (line, _) => Some(line == 0),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn query_on_battery() -> Option<bool> {
+    None
+}
+
+// 是否因为过热而降频
+#[cfg(target_os = "linux")]
+fn query_thermal_limited() -> bool {
+    let Ok(entries) = std::fs::read_dir("/sys/class/thermal") else {
+        return false;
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with("thermal_zone")
+        })
+        .filter_map(|entry| std::fs::read_to_string(entry.path().join("temp")).ok())
+        .filter_map(|temp| temp.trim().parse::<i64>().ok())
+        .any(|temp| temp >= THERMAL_LIMIT_MILLI_CELSIUS)
+}
+
+#[cfg(target_os = "macos")]
+fn query_thermal_limited() -> bool {
+    // CPU_Speed_Limit 小于 100 表示系统正在因温度限制 CPU 频率
+    std::process::Command::new("pmset")
+        .args(["-g", "therm"])
+        .output()
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|line| line.split_once("CPU_Speed_Limit"))
+                .filter_map(|(_, value)| {
+                    value
+                        .trim_start_matches([' ', '='])
+                        .trim()
+                        .parse::<u32>()
+                        .ok()
+                })
+                .any(|limit| limit < 100)
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn query_thermal_limited() -> bool {
+    false
+}
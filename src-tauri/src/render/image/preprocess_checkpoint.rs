@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use super::cache::chunk_hash_key;
+use super::types::ChunkInfo;
+
+// 预处理断点：每个 chunk 写入磁盘后追加一行记录
+// 预处理被中断（移动端切到后台被系统杀掉、崩溃、断电）后 下次预处理同一张图片时
+// 跳过已经完成的 chunk 只需要重新解码源图片 不需要重新写入所有 chunk
+// 全部完成并写入元数据后删除断点文件
+
+// 断点文件 位于该图片的缓存目录中 第一行是参数 之后每行是一个完成的 chunk
+pub(super) const PREPROCESS_PROGRESS_FILE: &str = "preprocess_progress.jsonl";
+
+// 断点参数 完全一致时才会从断点继续
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct ProgressHeader {
+    file_path: String,
+    source_len: u64,
+    source_modified_ms: u64,
+    total_width: u32,
+    total_height: u32,
+    chunk_size_x: u32,
+    chunk_size_y: u32,
+}
+
+// 一个已完成的 chunk
+#[derive(Debug, Serialize, Deserialize)]
+struct ProgressEntry {
+    key: String,
+    hash: String,
+}
+
+/// 一次预处理的断点记录
+pub struct PreprocessCheckpoint {
+    path: PathBuf,
+    file: Mutex<File>,
+    // 之前已经完成的 chunk 的像素哈希
+    completed: HashMap<String, String>,
+}
+
+impl PreprocessCheckpoint {
+    /// 打开断点记录 参数与之前的记录一致时保留已完成的 chunk 否则重新开始
+    /// # Arguments
+    /// * `cache_dir` - 该图片的缓存目录
+    /// * `file_path` - 图片文件路径
+    /// * `total_width` / `total_height` - 图片尺寸
+    /// * `chunk_size_x` / `chunk_size_y` - chunk 尺寸
+    pub fn open(
+        cache_dir: &Path,
+        file_path: &str,
+        (total_width, total_height): (u32, u32),
+        (chunk_size_x, chunk_size_y): (u32, u32),
+    ) -> Result<Self, String> {
+        let source = fs::metadata(file_path).map_err(|e| format!("读取源文件信息失败: {e}"))?;
+        let header = ProgressHeader {
+            file_path: file_path.to_string(),
+            source_len: source.len(),
+            source_modified_ms: source
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            total_width,
+            total_height,
+            chunk_size_x,
+            chunk_size_y,
+        };
+
+        let path = cache_dir.join(PREPROCESS_PROGRESS_FILE);
+        let mut completed = HashMap::new();
+        let resumable = fs::read_to_string(&path).ok().and_then(|content| {
+            let mut lines = content.lines();
+            let previous: ProgressHeader = serde_json::from_str(lines.next()?).ok()?;
+            (previous == header).then_some(lines.map(str::to_string).collect::<Vec<_>>())
+        });
+
+        let file = match resumable {
+            Some(lines) => {
+                // 进程被杀掉时最后一行可能不完整 跳过无法解析的行
+                for entry in lines
+                    .iter()
+                    .filter_map(|line| serde_json::from_str::<ProgressEntry>(line).ok())
+                {
+                    if cache_dir.join(format!("chunk_{}.bin", entry.key)).exists() {
+                        completed.insert(entry.key, entry.hash);
+                    }
+                }
+                let mut file = OpenOptions::new()
+                    .append(true)
+                    .open(&path)
+                    .map_err(|e| format!("打开预处理断点失败: {e}"))?;
+                // 补上可能缺失的换行 避免下一条记录接在不完整的行后面
+                file.write_all(b"\n")
+                    .map_err(|e| format!("写入预处理断点失败: {e}"))?;
+                file
+            }
+            None => {
+                let mut file =
+                    File::create(&path).map_err(|e| format!("创建预处理断点失败: {e}"))?;
+                let line = serde_json::to_string(&header)
+                    .map_err(|e| format!("序列化预处理断点失败: {e}"))?;
+                writeln!(file, "{line}").map_err(|e| format!("写入预处理断点失败: {e}"))?;
+                file
+            }
+        };
+
+        if !completed.is_empty() {
+            println!(
+                "[RUST] 从断点继续预处理: {file_path} 已完成 {} 个 chunk",
+                completed.len()
+            );
+        }
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+            completed,
+        })
+    }
+
+    /// 之前已经完成的 chunk 返回其像素哈希
+    pub fn completed_hash(&self, chunk: &ChunkInfo) -> Option<String> {
+        self.completed
+            .get(&chunk_hash_key(chunk.chunk_x, chunk.chunk_y))
+            .cloned()
+    }
+
+    /// 记录一个已写入磁盘的 chunk
+    pub fn record(&self, chunk: &ChunkInfo, hash: &str) -> Result<(), String> {
+        let entry = ProgressEntry {
+            key: chunk_hash_key(chunk.chunk_x, chunk.chunk_y),
+            hash: hash.to_string(),
+        };
+        let line =
+            serde_json::to_string(&entry).map_err(|e| format!("序列化预处理断点失败: {e}"))?;
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|e| format!("获取预处理断点锁失败: {e}"))?;
+        writeln!(file, "{line}").map_err(|e| format!("写入预处理断点失败: {e}"))
+    }
+
+    /// 预处理全部完成后删除断点文件
+    pub fn finish(self) {
+        drop(self.file);
+        let _ = fs::remove_file(&self.path);
+    }
+}
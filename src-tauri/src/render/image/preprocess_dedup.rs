@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::time::Duration;
+
+use super::types::ImageMetadata;
+use crate::utils::time::Stopwatch;
+
+// 重复预处理请求的合并：双击、前端重试循环会在很短时间内对同一张图片重复调用
+// process_user_image / force_preprocess_chunks 第二次调用会删掉正在写入的缓存 再并发预处理一次
+// 这里按文件路径合并：已有任务在执行时加入等待它的结果 刚完成的重建在短时间内直接复用
+// 重新分块等独占的重建不与其他任务合并 等同一张图片正在执行的任务结束后再执行
+
+// 重建完成后这段时间内的重复重建请求直接返回上次的结果
+const REBUILD_DEBOUNCE: Duration = Duration::from_secs(2);
+
+// 一次正在执行的加载或重建
+struct Flight {
+    // 是否是删除缓存后的重建
+    rebuild: bool,
+    result: Mutex<Option<Result<ImageMetadata, String>>>,
+    done: Condvar,
+}
+
+impl Flight {
+    fn wait(&self) -> Result<ImageMetadata, String> {
+        let mut result = self
+            .result
+            .lock()
+            .map_err(|e| format!("获取预处理任务锁失败: {e}"))?;
+        loop {
+            if let Some(result) = result.as_ref() {
+                return result.clone();
+            }
+            result = self
+                .done
+                .wait(result)
+                .map_err(|e| format!("获取预处理任务锁失败: {e}"))?;
+        }
+    }
+}
+
+#[derive(Default)]
+struct DedupState {
+    flights: HashMap<String, Arc<Flight>>,
+    // 最近完成的重建 (完成后的计时, 结果)
+    recent_rebuilds: HashMap<String, (Stopwatch, ImageMetadata)>,
+}
+
+static DEDUP_STATE: OnceLock<Mutex<DedupState>> = OnceLock::new();
+
+fn dedup_state() -> &'static Mutex<DedupState> {
+    DEDUP_STATE.get_or_init(|| Mutex::new(DedupState::default()))
+}
+
+// 执行者结束（包括 panic）时发布结果并移除任务 等待的调用不会永远阻塞
+struct FlightGuard {
+    file_path: String,
+    flight: Arc<Flight>,
+    result: Option<Result<ImageMetadata, String>>,
+}
+
+impl Drop for FlightGuard {
+    fn drop(&mut self) {
+        let result = self
+            .result
+            .take()
+            .unwrap_or_else(|| Err("预处理任务异常结束".to_string()));
+        let mut state = dedup_state().lock().unwrap_or_else(|e| e.into_inner());
+        state.flights.remove(&self.file_path);
+        match &result {
+            Ok(metadata) if self.flight.rebuild => {
+                state
+                    .recent_rebuilds
+                    .retain(|_, (finished, _)| finished.elapsed() < REBUILD_DEBOUNCE);
+                state.recent_rebuilds.insert(
+                    self.file_path.clone(),
+                    (Stopwatch::start(), metadata.clone()),
+                );
+            }
+            _ => {}
+        }
+        drop(state);
+        let mut slot = self.flight.result.lock().unwrap_or_else(|e| e.into_inner());
+        *slot = Some(result);
+        self.flight.done.notify_all();
+    }
+}
+
+/// 加载或重建图片缓存 同一张图片已有任务在执行时合并
+/// 加载请求加入任何正在执行的任务；重建请求加入正在执行的重建 遇到加载任务时等它结束再重建
+/// 刚完成的重建在 2 秒内被再次请求时直接返回上次的结果
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `rebuild` - 是否是删除缓存后的重建
+/// * `run` - 没有可以合并的任务时实际执行的操作
+/// # Returns
+/// * `Result<ImageMetadata, String>` - 自己或合并的任务的结果
+pub fn run_deduplicated(
+    file_path: &str,
+    rebuild: bool,
+    run: impl FnOnce() -> Result<ImageMetadata, String>,
+) -> Result<ImageMetadata, String> {
+    run_flight(file_path, rebuild, false, run)
+}
+
+/// 独占地重建图片缓存（例如按新的 chunk 尺寸重新分块）
+/// 等同一张图片正在执行的加载或重建结束后再执行 执行期间的加载和重建请求加入它的结果
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `run` - 实际执行的操作
+/// # Returns
+/// * `Result<ImageMetadata, String>` - 执行的结果
+pub fn run_exclusive(
+    file_path: &str,
+    run: impl FnOnce() -> Result<ImageMetadata, String>,
+) -> Result<ImageMetadata, String> {
+    run_flight(file_path, true, true, run)
+}
+
+fn run_flight(
+    file_path: &str,
+    rebuild: bool,
+    exclusive: bool,
+    run: impl FnOnce() -> Result<ImageMetadata, String>,
+) -> Result<ImageMetadata, String> {
+    let flight = loop {
+        let mut state = dedup_state()
+            .lock()
+            .map_err(|e| format!("获取预处理任务锁失败: {e}"))?;
+        if let Some(existing) = state.flights.get(file_path).cloned() {
+            drop(state);
+            if !exclusive && (!rebuild || existing.rebuild) {
+                println!("[RUST] 同一张图片的预处理正在进行，等待它完成: {file_path}");
+                return existing.wait();
+            }
+            // 不能在加载过程中删除缓存 等它结束再重建
+            let _ = existing.wait();
+            continue;
+        }
+        if rebuild && !exclusive {
+            if let Some((finished, metadata)) = state.recent_rebuilds.get(file_path) {
+                if finished.elapsed() < REBUILD_DEBOUNCE {
+                    println!("[RUST] 刚完成重建，忽略重复的重建请求: {file_path}");
+                    return Ok(metadata.clone());
+                }
+            }
+        }
+        let flight = Arc::new(Flight {
+            rebuild,
+            result: Mutex::new(None),
+            done: Condvar::new(),
+        });
+        state.flights.insert(file_path.to_string(), flight.clone());
+        break flight;
+    };
+
+    let mut guard = FlightGuard {
+        file_path: file_path.to_string(),
+        flight,
+        result: None,
+    };
+    let result = run();
+    guard.result = Some(result.clone());
+    result
+}
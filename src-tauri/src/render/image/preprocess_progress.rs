@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use tauri::{AppHandle, Emitter};
+
+use super::types::PreprocessProgress;
+use crate::utils::time::Stopwatch;
+
+// 预处理进度：后台预处理任务登记图片后 每写完一个 chunk（见 mark_chunk_written）更新一次进度
+// 进度事件按时间节流 几十万个 chunk 的图片也不会让前端收到过多的事件
+// 解码整张图片期间 chunk 总数还没有确定 事件中的完成数为 0 预计剩余时间为空
+
+// 预处理进度事件
+pub const PREPROCESS_PROGRESS_EVENT: &str = "preprocess://progress";
+
+// 两次进度事件之间的最短间隔（毫秒）
+const PROGRESS_EMIT_INTERVAL_MS: u128 = 200;
+
+struct ProgressState {
+    app: AppHandle,
+    job_id: String,
+    total_chunks: u32,
+    chunks_done: u32,
+    // 开始写入 chunk 后的计时 用于估计剩余时间
+    chunks_started: Stopwatch,
+    // 上一次发送事件后的计时 还没有发送过时为空
+    last_emit: Option<Stopwatch>,
+}
+
+static PREPROCESS_PROGRESS: OnceLock<Mutex<HashMap<String, ProgressState>>> = OnceLock::new();
+
+fn preprocess_progress() -> &'static Mutex<HashMap<String, ProgressState>> {
+    PREPROCESS_PROGRESS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 图片的预处理进度登记 结束（包括出错）时自动移除
+pub struct ProgressGuard {
+    file_path: String,
+}
+
+impl Drop for ProgressGuard {
+    fn drop(&mut self) {
+        let mut progress = preprocess_progress()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        progress.remove(&self.file_path);
+    }
+}
+
+/// 开始报告图片的预处理进度 之后写完的 chunk 会通过 preprocess://progress 事件通知前端
+/// # Arguments
+/// * `app` - 用于发送事件
+/// * `job_id` - 后台预处理任务 ID
+/// * `file_path` - 图片文件路径
+pub fn track_preprocess_progress(app: &AppHandle, job_id: &str, file_path: &str) -> ProgressGuard {
+    let mut progress = preprocess_progress()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    progress.insert(
+        file_path.to_string(),
+        ProgressState {
+            app: app.clone(),
+            job_id: job_id.to_string(),
+            total_chunks: 0,
+            chunks_done: 0,
+            chunks_started: Stopwatch::start(),
+            last_emit: None,
+        },
+    );
+    ProgressGuard {
+        file_path: file_path.to_string(),
+    }
+}
+
+/// 确定了 chunk 总数后调用（解码完成、开始写入 chunk 时）
+pub fn set_preprocess_total(file_path: &str, total_chunks: u32) {
+    let mut progress = preprocess_progress()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some(state) = progress.get_mut(file_path) {
+        state.total_chunks = total_chunks;
+        state.chunks_done = 0;
+        state.chunks_started = Stopwatch::start();
+        emit_progress(file_path, state);
+    }
+}
+
+/// 一个 chunk 写入完成 没有登记进度的图片直接忽略
+pub fn report_chunk_done(file_path: &str) {
+    let mut progress = preprocess_progress()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some(state) = progress
+        .get_mut(file_path)
+        .filter(|state| state.total_chunks > 0)
+    {
+        state.chunks_done = (state.chunks_done + 1).min(state.total_chunks);
+        let finished = state.chunks_done == state.total_chunks;
+        let due = state
+            .last_emit
+            .is_none_or(|last| last.elapsed_ms() >= PROGRESS_EMIT_INTERVAL_MS);
+        if finished || due {
+            emit_progress(file_path, state);
+        }
+    }
+}
+
+fn emit_progress(file_path: &str, state: &mut ProgressState) {
+    state.last_emit = Some(Stopwatch::start());
+    let (done, total) = (state.chunks_done, state.total_chunks);
+    let eta_ms = (done > 0 && total > 0).then(|| {
+        let elapsed = state.chunks_started.elapsed_ms();
+        (elapsed * (total - done) as u128 / done as u128) as u64
+    });
+    let payload = PreprocessProgress {
+        job_id: state.job_id.clone(),
+        file_path: file_path.to_string(),
+        percent: if total > 0 {
+            done as f32 / total as f32 * 100.0
+        } else {
+            0.0
+        },
+        chunks_done: done,
+        total_chunks: total,
+        eta_ms,
+    };
+    if let Err(e) = state.app.emit(PREPROCESS_PROGRESS_EVENT, payload) {
+        println!("[RUST] 发送预处理进度事件失败: {e}");
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+
+use tauri::{AppHandle, Emitter};
+
+use super::cache::{check_file_cache_exists, discard_preprocess_output, fnv1a_hash};
+use super::catalog::record_catalog_image;
+use super::chunk_pages::apply_chunk_paging;
+use super::commands::{load_user_image, open_user_image};
+use super::errors::{localized_error, ErrorCode};
+use super::job_history::record_job;
+use super::notifications::{job_notification, notify_job_finished};
+use super::power::{throttled_pool, wait_for_background_slot};
+use super::preprocess_dedup::run_deduplicated;
+use super::preprocess_progress::track_preprocess_progress;
+use super::scheduler::run_scheduled;
+use super::types::{BackgroundPolicy, JobKind, PreprocessCompleted, TaskKind};
+use super::utils::app_data_subdir;
+use crate::utils::time::get_time;
+
+// 后台预处理完成（成功或失败）时发出的事件
+pub const PREPROCESS_COMPLETED_EVENT: &str = "preprocess://completed";
+
+// 排队中的文件列表 位于应用数据目录的 queue 子目录下
+// 进程被杀掉（例如移动端切到后台）后 下次启动时重新排队 配合预处理断点继续
+const PENDING_QUEUE_FILE: &str = "preprocess_pending.json";
+
+struct PreprocessTask {
+    app: AppHandle,
+    file_path: String,
+    // 完成事件中的元数据是否分页返回 chunk 信息（见 chunk_pages）
+    paged: Option<bool>,
+    // 请求完整分辨率时总是生成完整分辨率的 chunk
+    // 其他任务（打开、拖放、文件夹监视等）与 process_user_image 一样加载（内存受限时使用代理副本）
+    // 两种任务都与同一张图片的其他加载合并
+    full_resolution: bool,
+}
+
+// 后台预处理队列
+// 单个工作线程按顺序处理 每张图片内部已经用 rayon 并行 再并发多张只会互相抢内存
+struct PreprocessQueue {
+    sender: Mutex<Sender<PreprocessTask>>,
+    // 已排队但还没处理完的文件 用于去重
+    pending: Mutex<HashSet<String>>,
+    // 已请求取消的文件
+    cancelled: Mutex<HashSet<String>>,
+}
+
+static PREPROCESS_QUEUE: OnceLock<PreprocessQueue> = OnceLock::new();
+
+fn get_preprocess_queue() -> &'static PreprocessQueue {
+    PREPROCESS_QUEUE.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<PreprocessTask>();
+
+        thread::Builder::new()
+            .name("preprocess-queue".to_string())
+            .spawn(move || {
+                for task in receiver {
+                    // 使用电池或过热时减少线程数或暂停 等电源状态恢复后再处理
+                    let policy = wait_for_background_slot();
+                    println!("[RUST] 后台预处理开始: {} ({policy:?})", task.file_path);
+                    let started_ms = get_time() as u64;
+                    let progress = track_preprocess_progress(
+                        &task.app,
+                        &preprocess_job_id(&task.file_path),
+                        &task.file_path,
+                    );
+                    // 排队期间已经取消的任务直接跳过
+                    let result = ensure_not_cancelled(&task.file_path).and_then(|_| {
+                        let load = || {
+                            if task.full_resolution {
+                                run_deduplicated(&task.file_path, false, || {
+                                    load_user_image(&task.file_path)
+                                })
+                            } else {
+                                open_user_image(&task.file_path)
+                            }
+                        };
+                        run_scheduled(TaskKind::Preprocess, &task.file_path, || match policy {
+                            BackgroundPolicy::Throttled => throttled_pool().install(load),
+                            _ => load(),
+                        })
+                        .and_then(|result| result)
+                    });
+                    drop(progress);
+                    if result.is_err() && is_preprocess_cancelled(&task.file_path) {
+                        // 写入线程都已经结束 删除写了一半的 chunk 和断点
+                        if let Err(e) = discard_preprocess_output(&task.file_path) {
+                            println!("[RUST] {e}");
+                        }
+                    }
+                    let payload = match result {
+                        Ok(metadata) => {
+                            record_catalog_image(&task.app, &task.file_path);
+                            PreprocessCompleted {
+                                file_path: task.file_path.clone(),
+                                metadata: Some(apply_chunk_paging(
+                                    task.paged,
+                                    &task.file_path,
+                                    metadata,
+                                )),
+                                error: None,
+                            }
+                        }
+                        Err(e) => {
+                            println!("[RUST] 后台预处理失败: {} ({e})", task.file_path);
+                            PreprocessCompleted {
+                                file_path: task.file_path.clone(),
+                                metadata: None,
+                                error: Some(e),
+                            }
+                        }
+                    };
+
+                    if let Some(queue) = PREPROCESS_QUEUE.get() {
+                        if let Ok(mut pending) = queue.pending.lock() {
+                            pending.remove(&task.file_path);
+                            save_pending(&task.app, &pending);
+                        }
+                        if let Ok(mut cancelled) = queue.cancelled.lock() {
+                            cancelled.remove(&task.file_path);
+                        }
+                    }
+
+                    let notification = job_notification(
+                        JobKind::Preprocess,
+                        &task.file_path,
+                        &task.file_path,
+                        None,
+                        payload.error.as_deref().map_or(Ok(true), Err),
+                        started_ms,
+                    );
+                    record_job(&task.app, &notification);
+                    notify_job_finished(&task.app, notification);
+                    if let Err(e) = task.app.emit(PREPROCESS_COMPLETED_EVENT, payload) {
+                        println!("[RUST] 发送预处理完成事件失败: {e}");
+                    }
+                }
+            })
+            .expect("启动后台预处理线程失败");
+
+        PreprocessQueue {
+            sender: Mutex::new(sender),
+            pending: Mutex::new(HashSet::new()),
+            cancelled: Mutex::new(HashSet::new()),
+        }
+    })
+}
+
+/// 后台预处理任务的 ID 由图片路径决定 进度事件中使用
+pub fn preprocess_job_id(file_path: &str) -> String {
+    format!("{:016x}", fnv1a_hash(file_path.as_bytes()))
+}
+
+/// 把图片加入后台预处理队列 与 process_user_image 使用相同的加载方式
+/// # Returns
+/// * `Result<bool, String>` - true 表示新加入 false 表示已经在队列中
+pub fn enqueue_preprocess(app: &AppHandle, file_path: &str) -> Result<bool, String> {
+    enqueue_task(app, file_path, None, false)
+}
+
+/// 把用户打开的图片加入后台预处理队列 完成事件中的元数据可以分页返回
+/// 图片已经在队列中时沿用第一次加入时的设置
+/// # Arguments
+/// * `paged` - 与 process_user_image 的 paged 参数相同
+/// # Returns
+/// * `Result<bool, String>` - true 表示新加入 false 表示已经在队列中
+pub fn enqueue_user_image(
+    app: &AppHandle,
+    file_path: &str,
+    paged: Option<bool>,
+) -> Result<bool, String> {
+    enqueue_task(app, file_path, paged, false)
+}
+
+/// 把图片加入后台预处理队列 生成完整分辨率的 chunk（不使用代理副本）
+/// # Returns
+/// * `Result<bool, String>` - true 表示新加入 false 表示已经在队列中
+pub fn enqueue_full_resolution(app: &AppHandle, file_path: &str) -> Result<bool, String> {
+    enqueue_task(app, file_path, None, true)
+}
+
+fn enqueue_task(
+    app: &AppHandle,
+    file_path: &str,
+    paged: Option<bool>,
+    full_resolution: bool,
+) -> Result<bool, String> {
+    let queue = get_preprocess_queue();
+
+    let mut pending = queue
+        .pending
+        .lock()
+        .map_err(|e| format!("预处理队列加锁失败: {e}"))?;
+    if !pending.insert(file_path.to_string()) {
+        return Ok(false);
+    }
+
+    let sender = queue
+        .sender
+        .lock()
+        .map_err(|e| format!("预处理队列加锁失败: {e}"))?;
+    if let Err(e) = sender.send(PreprocessTask {
+        app: app.clone(),
+        file_path: file_path.to_string(),
+        paged,
+        full_resolution,
+    }) {
+        pending.remove(file_path);
+        return Err(format!("加入预处理队列失败: {e}"));
+    }
+    save_pending(app, &pending);
+
+    Ok(true)
+}
+
+/// 取消后台预处理任务
+/// 还在排队的任务直接跳过 正在处理的任务在下一个 chunk 之前停止 并删除写了一半的缓存
+/// 两种情况都会发出 preprocess://completed 事件 错误信息以 [CANCELLED] 开头
+/// # Arguments
+/// * `job_id` - process_user_image_async 返回的任务 ID
+#[tauri::command]
+pub fn cancel_preprocess(job_id: String) -> Result<(), String> {
+    let queue = get_preprocess_queue();
+    let file_path = queue
+        .pending
+        .lock()
+        .map_err(|e| format!("预处理队列加锁失败: {e}"))?
+        .iter()
+        .find(|file_path| preprocess_job_id(file_path) == job_id)
+        .cloned()
+        .ok_or_else(|| format!("预处理任务不存在: {job_id}"))?;
+    queue
+        .cancelled
+        .lock()
+        .map_err(|e| format!("预处理队列加锁失败: {e}"))?
+        .insert(file_path.clone());
+    println!("[RUST] 已请求取消预处理: {file_path} ({job_id})");
+    Ok(())
+}
+
+/// 图片的后台预处理是否已被取消
+pub fn is_preprocess_cancelled(file_path: &str) -> bool {
+    PREPROCESS_QUEUE
+        .get()
+        .and_then(|queue| {
+            queue
+                .cancelled
+                .lock()
+                .ok()
+                .map(|cancelled| cancelled.contains(file_path))
+        })
+        .unwrap_or(false)
+}
+
+/// 预处理被取消时返回 [CANCELLED] 错误 预处理在各个阶段之间和每个 chunk 之前调用
+pub fn ensure_not_cancelled(file_path: &str) -> Result<(), String> {
+    if is_preprocess_cancelled(file_path) {
+        return Err(localized_error(
+            ErrorCode::Cancelled,
+            &[("path", &file_path)],
+        ));
+    }
+    Ok(())
+}
+
+// 保存排队中的文件列表 失败只记录日志
+fn save_pending(app: &AppHandle, pending: &HashSet<String>) {
+    let mut files: Vec<&String> = pending.iter().collect();
+    files.sort();
+    let result = app_data_subdir(app, "queue").and_then(|dir| {
+        let json =
+            serde_json::to_string(&files).map_err(|e| format!("序列化预处理队列失败: {e}"))?;
+        fs::write(dir.join(PENDING_QUEUE_FILE), json)
+            .map_err(|e| format!("保存预处理队列失败: {e}"))
+    });
+    if let Err(e) = result {
+        println!("[RUST] {e}");
+    }
+}
+
+/// 启动时调用 把上次退出时还没处理完的图片重新加入队列
+/// 源文件已经不存在或已经有完整缓存的图片会被跳过
+pub fn resume_preprocess_queue(app: &AppHandle) {
+    let files: Vec<String> = app_data_subdir(app, "queue")
+        .ok()
+        .and_then(|dir| fs::read_to_string(dir.join(PENDING_QUEUE_FILE)).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    for file_path in files {
+        if !Path::new(&file_path).exists() || check_file_cache_exists(&file_path) {
+            continue;
+        }
+        match enqueue_preprocess(app, &file_path) {
+            Ok(_) => println!("[RUST] 继续上次未完成的预处理: {file_path}"),
+            Err(e) => println!("[RUST] {e}"),
+        }
+    }
+}
+
+/// 图片是否已在后台预处理队列中（排队或正在处理）
+pub fn is_preprocess_pending(file_path: &str) -> bool {
+    PREPROCESS_QUEUE
+        .get()
+        .and_then(|queue| {
+            queue
+                .pending
+                .lock()
+                .ok()
+                .map(|pending| pending.contains(file_path))
+        })
+        .unwrap_or(false)
+}
+
+/// 后台预处理队列是否空闲（没有排队或正在处理的图片）
+pub fn is_preprocess_queue_idle() -> bool {
+    PREPROCESS_QUEUE
+        .get()
+        .and_then(|queue| queue.pending.lock().ok().map(|pending| pending.is_empty()))
+        .unwrap_or(true)
+}
@@ -1,45 +1,85 @@
-use crate::utils::time::get_time;
-use image::GenericImageView;
+use crate::utils::time::{Stopwatch, TimingSpan};
+use image::codecs::bmp::BmpDecoder;
+use image::codecs::jpeg::JpegDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::tiff::TiffDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{GenericImageView, ImageDecoder, ImageFormat};
 use rayon::prelude::*;
 use serde_json;
 use std::cmp;
+use std::collections::HashMap;
 use std::env;
 use std::fs;
-use std::io;
+use std::io::{self, Read};
 use std::path::Path;
-
-use super::cache::check_file_cache_exists;
+use tauri::Window;
+
+use super::cache::{
+    check_file_cache_exists, chunk_hash_key, ensure_cache_writable, image_cache_dir,
+    load_cached_metadata, load_source_info, save_chunk_hashes,
+};
+use super::chunk_order::{chunk_order, ordered_chunks};
+use super::chunk_pages::apply_chunk_paging;
 use super::chunk_processing::process_single_chunk_parallel;
-use super::config::{CHUNK_CACHE_DIR, CHUNK_SIZE_X, CHUNK_SIZE_Y};
-use super::types::{ChunkInfo, ImageMetadata};
+use super::compact_metadata::encode_metadata;
+use super::config::{CHUNK_FORMAT_VERSION, CHUNK_SIZE_X, CHUNK_SIZE_Y};
+use super::decode_sandbox::{decode_sandboxed, is_decode_sandbox_enabled};
+use super::errors::{localized_error, ErrorCode};
+use super::fingerprint::fingerprint_fields;
+use super::gpu_compute::convert_to_rgba8;
+use super::lod_pyramid::build_lod_levels;
+use super::preprocess_checkpoint::PreprocessCheckpoint;
+use super::preprocess_progress::set_preprocess_total;
+use super::preprocess_queue::ensure_not_cancelled;
+use super::pyramid_import::{find_dzi_pyramid, import_tile_pyramid};
+use super::similarity::record_perceptual_hashes;
+use super::stripe_processing::{preprocess_in_stripes, should_process_in_stripes};
+use super::telemetry::record_telemetry;
+use super::tiff_input::open_tiled_tiff;
+use super::timing_breakdown::{
+    attach_timing_breakdown, request_timing_breakdown, store_timing_breakdown,
+};
+use super::types::{ChunkInfo, ImageMetadata, TelemetryEvent, TimingBreakdown};
+use super::window_state::set_window_image;
+use super::write_tracker::{begin_cache_write, mark_chunk_written};
+
+// 识别格式时读取的文件开头字节数
+const FORMAT_SNIFF_BYTES: u64 = 32;
 
 /// 获取特定图片文件的 chunk 元数据
 /// # Arguments
 /// * `file_path` - 图片文件路径
+/// * `timings` - 为 true 时在返回的元数据中附带耗时分解（见 timing_breakdown）
+/// * `paged` - 为 true 时不返回 chunk 列表 通过 get_chunk_infos 按需读取（见 chunk_pages）
 /// # Returns
 /// * `Result<ImageMetadata, String>` - 图片元数据或错误信息
 #[tauri::command] // 这个宏 声明了这个函数是 tauri command，表示这个函数可以被前端调用
-pub fn get_image_metadata_for_file(file_path: String) -> Result<ImageMetadata, String> {
+pub fn get_image_metadata_for_file(
+    window: Window,
+    file_path: String,
+    timings: Option<bool>,
+    paged: Option<bool>,
+) -> Result<ImageMetadata, String> {
     println!("[RUST] 开始获取图片元数据: {file_path}");
+    let timing_request = timings
+        .unwrap_or(false)
+        .then(|| request_timing_breakdown(&file_path));
 
     // 检查文件是否存在
     if !Path::new(&file_path).exists() {
-        return Err(format!("图片文件不存在: {file_path}"));
+        return Err(localized_error(
+            ErrorCode::FileNotFound,
+            &[("path", &file_path)],
+        ));
     }
 
     // 检查是否有这个文件对应的缓存
     if check_file_cache_exists(&file_path) {
         println!("[RUST] 发现现有缓存，从缓存加载元数据");
 
-        // 从缓存文件加载元数据 缓存文件是json格式 位于缓存目录下 文件名为metadata.json
-        // TODO 这个地方 缓存文件是统一的一个 当已经被缓存过的文件多了之后 这个文件会变得很大 需要优化 最好是每个图片对应的metadata.json都不一样
-        let metadata_filepath = Path::new(CHUNK_CACHE_DIR).join("metadata.json");
-        // 读取缓存文件成字符串
-        let metadata_content = fs::read_to_string(metadata_filepath)
-            .map_err(|e| format!("读取缓存元数据失败: {e}"))?;
-        // 将字符串反序列化为json
-        let metadata: ImageMetadata = serde_json::from_str(&metadata_content)
-            .map_err(|e| format!("解析缓存元数据失败: {e}"))?;
+        // 从缓存文件加载元数据 缓存文件是json格式 位于该图片的缓存目录下 文件名为metadata.json
+        let metadata = load_cached_metadata(&file_path)?;
 
         println!(
             "[RUST] 从缓存加载元数据成功: {}x{}, 共 {} 个 chunks",
@@ -47,8 +87,10 @@ pub fn get_image_metadata_for_file(file_path: String) -> Result<ImageMetadata, S
             metadata.total_height,
             metadata.chunks.len()
         );
-        // 给前端返回元数据
-        return Ok(metadata);
+        // 记录为当前窗口打开的图片 然后给前端返回元数据
+        set_window_image(window.label(), &file_path);
+        let metadata = attach_timing_breakdown(timing_request, metadata);
+        return Ok(apply_chunk_paging(paged, &file_path, metadata));
     }
 
     println!("[RUST] 缓存不存在，开始预处理和缓存 chunks");
@@ -58,7 +100,90 @@ pub fn get_image_metadata_for_file(file_path: String) -> Result<ImageMetadata, S
 
     println!("[RUST] 预处理完成，元数据已缓存");
 
-    Ok(metadata)
+    set_window_image(window.label(), &file_path);
+    let metadata = attach_timing_breakdown(timing_request, metadata);
+    Ok(apply_chunk_paging(paged, &file_path, metadata))
+}
+
+/// 解码源图片
+/// 开启解码沙箱时在独立的子进程中解码 解码器崩溃不会影响主进程
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<image::DynamicImage, String>` - 解码后的图片或错误信息
+pub fn decode_source_image(file_path: &str) -> Result<image::DynamicImage, String> {
+    if is_decode_sandbox_enabled() {
+        return decode_sandboxed(file_path);
+    }
+    decode_source_image_in_process(file_path)
+}
+
+/// 识别源图片的格式 优先按文件开头的魔数识别 识别不了时按扩展名
+/// 扩展名与内容不一致时（例如下载的 JPEG 被保存为 .png）以内容为准
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<ImageFormat, String>` - 图片格式
+pub fn detect_source_format(file_path: &str) -> Result<ImageFormat, String> {
+    let mut head = Vec::with_capacity(FORMAT_SNIFF_BYTES as usize);
+    fs::File::open(file_path)
+        .and_then(|file| file.take(FORMAT_SNIFF_BYTES).read_to_end(&mut head))
+        .map_err(|e| {
+            localized_error(
+                ErrorCode::FileOpenFailed,
+                &[("error", &e), ("path", &file_path)],
+            )
+        })?;
+    image::guess_format(&head)
+        .or_else(|_| ImageFormat::from_path(file_path))
+        .map_err(|_| {
+            let extension = Path::new(file_path)
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            localized_error(ErrorCode::UnsupportedFormat, &[("format", &extension)])
+        })
+}
+
+/// 在当前进程中解码源图片 解码子进程也调用这个函数
+/// 按识别出的格式选择解码器（见 detect_source_format）
+pub fn decode_source_image_in_process(file_path: &str) -> Result<image::DynamicImage, String> {
+    let format = detect_source_format(file_path)?;
+    let file = fs::File::open(file_path).map_err(|e| {
+        localized_error(
+            ErrorCode::FileOpenFailed,
+            &[("error", &e), ("path", &file_path)],
+        )
+    })?;
+    let reader = io::BufReader::new(file);
+
+    // TIFF 按条带存储时只能整张解码 分块存储的 TIFF 在预处理时按块读取（见 tiff_input）
+    match format {
+        ImageFormat::Png => decode_with(PngDecoder::new(reader), "PNG"),
+        ImageFormat::Jpeg => decode_with(JpegDecoder::new(reader), "JPEG"),
+        ImageFormat::WebP => decode_with(WebPDecoder::new(reader), "WebP"),
+        ImageFormat::Tiff => decode_with(TiffDecoder::new(reader), "TIFF"),
+        ImageFormat::Bmp => decode_with(BmpDecoder::new(reader), "BMP"),
+        other => Err(localized_error(
+            ErrorCode::UnsupportedFormat,
+            &[("format", &format!("{other:?}"))],
+        )),
+    }
+}
+
+// 从解码器中获取动态 image 对象
+fn decode_with<'a>(
+    decoder: image::ImageResult<impl ImageDecoder<'a>>,
+    format: &str,
+) -> Result<image::DynamicImage, String> {
+    decoder
+        .and_then(image::DynamicImage::from_decoder)
+        .map_err(|e| {
+            localized_error(
+                ErrorCode::DecodeFailed,
+                &[("format", &format), ("error", &e)],
+            )
+        })
 }
 
 /// 预处理图片并缓存所有 chunks
@@ -67,39 +192,67 @@ pub fn get_image_metadata_for_file(file_path: String) -> Result<ImageMetadata, S
 /// # Returns
 /// * `Result<ImageMetadata, String>` - 图片元数据或错误信息
 pub fn preprocess_and_cache_chunks(file_path: &str) -> Result<ImageMetadata, String> {
-    let start_time = get_time();
-    println!("[RUST] 开始预处理和缓存 chunks 从路径: {file_path}ms");
+    preprocess_and_cache_chunks_with_size(file_path, CHUNK_SIZE_X, CHUNK_SIZE_Y)
+}
 
-    let decode_start = get_time();
+/// 按指定的 chunk 尺寸预处理图片并缓存所有 chunks
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `chunk_size_x` - chunk 宽度
+/// * `chunk_size_y` - chunk 高度
+/// # Returns
+/// * `Result<ImageMetadata, String>` - 图片元数据或错误信息
+pub fn preprocess_and_cache_chunks_with_size(
+    file_path: &str,
+    chunk_size_x: u32,
+    chunk_size_y: u32,
+) -> Result<ImageMetadata, String> {
+    ensure_cache_writable("预处理图片")?;
+    let total_span = TimingSpan::new("preprocess.total");
+    println!("[RUST] 开始预处理和缓存 chunks 从路径: {file_path}");
+    // 预处理期间的读取只返回已经写完的 chunk
+    let _writing = begin_cache_write(file_path);
+
+    let decode_span = TimingSpan::new("preprocess.decode");
 
     // 检查文件是否存在
     if !Path::new(file_path).exists() {
-        return Err(format!(
-            "图片文件不存在: {} (当前工作目录: {:?})",
+        println!(
+            "[RUST] 图片文件不存在: {} (当前工作目录: {:?})",
             file_path,
             env::current_dir().unwrap_or_default()
+        );
+        return Err(localized_error(
+            ErrorCode::FileNotFound,
+            &[("path", &file_path)],
         ));
     }
 
-    let file =
-        fs::File::open(file_path).map_err(|e| format!("文件打开失败: {e} (路径: {file_path})"))?;
-    let reader = io::BufReader::new(file);
+    // 源文件旁边已有 DZI 金字塔时直接用其中的瓦片生成 chunk 不解码整张图片
+    if let Some(pyramid) = find_dzi_pyramid(file_path) {
+        return import_tile_pyramid(file_path, &pyramid, chunk_size_x, chunk_size_y);
+    }
 
-    // TODO 这里后续还会支持更加适合lod的图片格式 tiff
-    // 创建解码器
-    let decoder =
-        image::codecs::png::PngDecoder::new(reader).map_err(|e| format!("PNG解码失败: {e}"))?;
-    // 从解码器中获取动态image对象
-    let img =
-        image::DynamicImage::from_decoder(decoder).map_err(|e| format!("PNG解码失败: {e}"))?;
+    // 分块存储的 TIFF 只读取覆盖每个 chunk 的块 概览级别使用 TIFF 中已有的层
+    if let Some(tiff) = open_tiled_tiff(file_path) {
+        return import_tile_pyramid(file_path, &tiff, chunk_size_x, chunk_size_y);
+    }
 
-    let decode_end = get_time();
+    // 非隔行扫描的 PNG 按条带流式处理 不在内存中保留整张图片
+    if should_process_in_stripes(file_path) {
+        return preprocess_in_stripes(file_path, chunk_size_x, chunk_size_y);
+    }
 
-    println!(
-        "[RUST] PNG直接解码完成: {}ms (耗时: {}ms)",
-        decode_end,
-        decode_end - decode_start
-    );
+    let img = decode_source_image(file_path)?;
+    ensure_not_cancelled(file_path)?;
+
+    let decode_ms = decode_span.elapsed_ms();
+    drop(decode_span);
+
+    match detect_source_format(file_path) {
+        Ok(format) => println!("[RUST] {format:?} 解码完成 (耗时: {decode_ms}ms)"),
+        Err(_) => println!("[RUST] 图片解码完成 (耗时: {decode_ms}ms)"),
+    }
 
     // 获取图片尺寸
     let (total_width, total_height) = img.dimensions();
@@ -140,18 +293,187 @@ pub fn preprocess_and_cache_chunks(file_path: &str) -> Result<ImageMetadata, Str
     // 如果本身就是在情况1的状况下total_width减去1不影响结果
     // 因此 更加通用的表达式为 (total_width - 1) / chunk_size + 1 与代码里面的表达式等效
 
-    let col_count = total_width.div_ceil(CHUNK_SIZE_X);
-    let row_count = total_height.div_ceil(CHUNK_SIZE_Y);
+    let col_count = total_width.div_ceil(chunk_size_x);
+    let row_count = total_height.div_ceil(chunk_size_y);
 
     println!(
-        "[RUST] Chunk 配置: {col_count}x{row_count} chunks, 每个 {CHUNK_SIZE_X}x{CHUNK_SIZE_Y}"
+        "[RUST] Chunk 配置: {col_count}x{row_count} chunks, 每个 {chunk_size_x}x{chunk_size_y}"
     );
 
     // 创建缓存目录
-    let cache_dir = Path::new(CHUNK_CACHE_DIR);
+    let cache_dir_buf = image_cache_dir(file_path);
+    let cache_dir = cache_dir_buf.as_path();
     if !cache_dir.exists() {
-        fs::create_dir(cache_dir).map_err(|e| format!("创建缓存目录失败: {e}"))?;
+        fs::create_dir_all(cache_dir).map_err(|e| format!("创建缓存目录失败: {e}"))?;
+    }
+
+    // 生成所有 chunk 信息
+    let chunks = build_chunk_infos(total_width, total_height, chunk_size_x, chunk_size_y);
+
+    println!("[RUST] 生成了 {} 个 chunk 信息，开始并行处理", chunks.len());
+
+    // 显示并行配置信息
+    let num_threads = rayon::current_num_threads();
+    println!("[RUST] 并行配置：使用 {num_threads} 个线程");
+
+    // 将图片转换为 RGBA8 格式（只转换一次，避免每个chunk重复转换 有可用的 GPU 时在 GPU 上转换）
+    // 本身是 RGBA8 时直接取出像素 其他格式转换后立即释放解码结果 不让两份完整的图片同时留在内存中
+    let rgba_conversion_span = TimingSpan::new("preprocess.rgba_conversion");
+    let rgba_img = match img {
+        image::DynamicImage::ImageRgba8(rgba) => rgba,
+        img => convert_to_rgba8(&img),
+    };
+    let convert_ms = rgba_conversion_span.elapsed_ms();
+    drop(rgba_conversion_span);
+    println!("[RUST] 图片转换为RGBA8格式完成 (耗时: {convert_ms}ms)");
+
+    // 并行处理所有 chunks 并保存为单独的文件
+    let parallel_span = TimingSpan::new("preprocess.chunks");
+
+    // 之前被中断的预处理留下的断点 已完成的 chunk 直接跳过
+    let checkpoint = PreprocessCheckpoint::open(
+        cache_dir,
+        file_path,
+        (total_width, total_height),
+        (chunk_size_x, chunk_size_y),
+    )?;
+    set_preprocess_total(file_path, chunks.len() as u32);
+
+    // 写入一个 chunk 返回像素哈希
+    let process_chunk = |chunk_info: &ChunkInfo| -> Result<String, String> {
+        ensure_not_cancelled(file_path)?;
+        let hash = match checkpoint.completed_hash(chunk_info) {
+            Some(hash) => hash,
+            None => {
+                let hash = process_single_chunk_parallel(&rgba_img, chunk_info, cache_dir)?;
+                checkpoint.record(chunk_info, &hash)?;
+                hash
+            }
+        };
+        mark_chunk_written(file_path, chunk_info.chunk_x, chunk_info.chunk_y);
+        Ok(hash)
+    };
+
+    // 使用 rayon 并行处理，为每个chunk生成单独的文件
+    // 工作线程按配置的顺序依次领取 chunk（见 chunk_order） 先完成的区域可以先显示
+    let chunk_results: Vec<(&ChunkInfo, Result<String, String>)> =
+        ordered_chunks(&chunks, col_count, chunk_order())
+            .into_iter()
+            .par_bridge() // 将有序的迭代器转换为并行迭代器
+            .map(|chunk_info| (chunk_info, process_chunk(chunk_info)))
+            .collect();
+
+    let chunking_ms = parallel_span.elapsed_ms();
+    drop(parallel_span);
+    println!("[RUST] 并行处理完成 (耗时: {chunking_ms}ms)");
+
+    // 取消时其余 chunk 都会失败 返回取消错误而不是其中某个 chunk 的错误
+    ensure_not_cancelled(file_path)?;
+
+    // 检查是否有错误 同时收集每个 chunk 的像素哈希
+    let total_chunks = chunks.len();
+    let mut chunk_hashes = HashMap::with_capacity(total_chunks);
+    for (chunk_info, result) in chunk_results {
+        match result {
+            Ok(hash) => {
+                chunk_hashes.insert(chunk_hash_key(chunk_info.chunk_x, chunk_info.chunk_y), hash);
+            }
+            Err(e) => {
+                return Err(format!(
+                    "Chunk ({}, {}) 处理失败: {e}",
+                    chunk_info.chunk_x, chunk_info.chunk_y
+                ))
+            }
+        }
     }
+    println!("[RUST] 所有 {total_chunks} 个 chunks 处理成功");
+
+    // 缩小显示时使用的概览级别 直接从内存中的图片缩小
+    let levels_stopwatch = Stopwatch::start();
+    let levels = build_lod_levels(file_path, &rgba_img, chunk_size_x, chunk_size_y)?;
+    let levels_ms = levels_stopwatch.elapsed_ms();
+
+    // 保存元数据到文件
+    let metadata = ImageMetadata {
+        total_width,
+        total_height,
+        chunk_size_x,
+        chunk_size_y,
+        col_count,
+        row_count,
+        chunks: chunks.clone(),
+        proxy_scale: None,
+        levels,
+        timings: None,
+        chunk_count: None,
+    };
+
+    let metadata_write_stopwatch = Stopwatch::start();
+    save_chunk_hashes(cache_dir, &chunk_hashes)?;
+    write_cache_metadata(cache_dir, file_path, &metadata)?;
+    checkpoint.finish();
+    record_perceptual_hashes(cache_dir, &rgba_img)?;
+
+    let total_ms = total_span.elapsed_ms();
+    record_preprocess_telemetry(file_path, &metadata, decode_ms, total_ms);
+    store_timing_breakdown(
+        file_path,
+        TimingBreakdown {
+            preprocessed: true,
+            decode_ms: decode_ms as u64,
+            convert_ms: convert_ms as u64,
+            chunking_ms: chunking_ms as u64,
+            levels_ms: levels_ms as u64,
+            metadata_write_ms: metadata_write_stopwatch.elapsed_ms() as u64,
+            total_ms: total_ms as u64,
+        },
+    );
+    println!("[RUST] 预处理和缓存完成 (总耗时: {total_ms}ms), 共 {total_chunks} 个 chunks");
+
+    Ok(metadata)
+}
+
+/// 记录一次预处理的遥测事件
+/// # Arguments
+/// * `file_path` - 图片文件路径（只记录扩展名）
+/// * `metadata` - 预处理生成的元数据
+/// * `decode_ms` / `total_ms` - 解码耗时和总耗时
+pub fn record_preprocess_telemetry(
+    file_path: &str,
+    metadata: &ImageMetadata,
+    decode_ms: u128,
+    total_ms: u128,
+) {
+    record_telemetry(TelemetryEvent::Preprocess {
+        format: Path::new(file_path)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default(),
+        width: metadata.total_width,
+        height: metadata.total_height,
+        chunk_size: metadata.chunk_size_x,
+        chunk_count: metadata.chunks.len() as u32,
+        decode_ms: decode_ms as u64,
+        total_ms: total_ms as u64,
+    });
+}
+
+/// 生成覆盖整张图片的 chunk 网格信息（行优先）
+/// # Arguments
+/// * `total_width` - 图片宽度
+/// * `total_height` - 图片高度
+/// * `chunk_size_x` - chunk 宽度
+/// * `chunk_size_y` - chunk 高度
+/// # Returns
+/// * `Vec<ChunkInfo>` - 所有 chunk 信息
+pub fn build_chunk_infos(
+    total_width: u32,
+    total_height: u32,
+    chunk_size_x: u32,
+    chunk_size_y: u32,
+) -> Vec<ChunkInfo> {
+    let col_count = total_width.div_ceil(chunk_size_x);
+    let row_count = total_height.div_ceil(chunk_size_y);
 
     // NOTE
     // Vec 动态数组
@@ -163,15 +485,14 @@ pub fn preprocess_and_cache_chunks(file_path: &str) -> Result<ImageMetadata, Str
     // 如果 Result 类型是 Ok，则返回 Ok 中的值
     // 如果 Result 类型是 Err，则 panic
 
-    // 生成所有 chunk 信息
     let chunks_count = usize::try_from(col_count * row_count).unwrap();
     let mut chunks = Vec::with_capacity(chunks_count);
     for chunk_y in 0..row_count {
         for chunk_x in 0..col_count {
-            let x = chunk_x * CHUNK_SIZE_X;
-            let y = chunk_y * CHUNK_SIZE_Y;
-            let width = cmp::min(CHUNK_SIZE_X, total_width - x);
-            let height = cmp::min(CHUNK_SIZE_Y, total_height - y);
+            let x = chunk_x * chunk_size_x;
+            let y = chunk_y * chunk_size_y;
+            let width = cmp::min(chunk_size_x, total_width - x);
+            let height = cmp::min(chunk_size_y, total_height - y);
 
             let chunk_info = ChunkInfo {
                 x,
@@ -186,88 +507,59 @@ pub fn preprocess_and_cache_chunks(file_path: &str) -> Result<ImageMetadata, Str
         }
     }
 
-    println!("[RUST] 生成了 {} 个 chunk 信息，开始并行处理", chunks.len());
-
-    // 显示并行配置信息
-    let num_threads = rayon::current_num_threads();
-    println!("[RUST] 并行配置：使用 {num_threads} 个线程");
-
-    // 将图片转换为 RGBA8 格式（只转换一次，避免每个chunk重复转换）
-    let rgba_conversion_start = get_time();
-    let rgba_img = img.to_rgba8();
-    let rgba_conversion_end = get_time();
-    println!(
-        "[RUST] 图片转换为RGBA8格式完成: {}ms (耗时: {}ms)",
-        rgba_conversion_end,
-        rgba_conversion_end - rgba_conversion_start
-    );
-
-    // 并行处理所有 chunks 并保存为单独的文件
-    let parallel_start = get_time();
-
-    // 使用 rayon 并行处理，为每个chunk生成单独的文件
-    let chunk_results: Vec<Result<(), String>> = chunks
-        .par_iter() // 将chunks迭代器转换为并行迭代器
-        .map(|chunk_info| process_single_chunk_parallel(&rgba_img, chunk_info, cache_dir))
-        .collect();
-
-    let parallel_end = get_time();
-    println!(
-        "[RUST] 并行处理完成: {}ms (耗时: {}ms)",
-        parallel_end,
-        parallel_end - parallel_start
-    );
-
-    // 检查是否有错误
-    let total_chunks = chunks.len();
-    for (i, result) in chunk_results.iter().enumerate() {
-        if let Err(e) = result {
-            return Err(format!("Chunk {i} 处理失败: {e}"));
-        }
-    }
-
-    println!("[RUST] 所有 {total_chunks} 个 chunks 处理成功");
-
-    // 保存元数据到文件
-    let metadata = ImageMetadata {
-        total_width,
-        total_height,
-        chunk_size_x: CHUNK_SIZE_X,
-        chunk_size_y: CHUNK_SIZE_Y,
-        col_count,
-        row_count,
-        chunks: chunks.clone(),
-    };
+    chunks
+}
 
-    let metadata_json =
-        serde_json::to_string(&metadata).map_err(|e| format!("序列化元数据失败: {e}"))?;
+/// 把元数据和源文件信息写入缓存目录
+/// # Arguments
+/// * `cache_dir` - 该图片的缓存目录
+/// * `file_path` - 图片文件路径
+/// * `metadata` - 图片元数据
+/// # Returns
+/// * `Result<(), String>` - 是否成功
+pub fn write_cache_metadata(
+    cache_dir: &Path,
+    file_path: &str,
+    metadata: &ImageMetadata,
+) -> Result<(), String> {
+    ensure_cache_writable("写入缓存元数据")?;
+    let metadata_json = encode_metadata(metadata)?;
 
     let metadata_filepath = cache_dir.join("metadata.json");
     fs::write(&metadata_filepath, metadata_json).map_err(|e| format!("保存元数据失败: {e}"))?;
 
-    // 保存源文件信息
-    let source_info = serde_json::json!({
+    // 保存源文件信息 附带内容指纹
+    let mut source_info = serde_json::json!({
         "file_path": file_path,
-        "total_width": total_width,
-        "total_height": total_height,
-        "chunk_size_x": CHUNK_SIZE_X,
-        "chunk_size_y": CHUNK_SIZE_Y,
-        "col_count": col_count,
-        "row_count": row_count,
+        "total_width": metadata.total_width,
+        "total_height": metadata.total_height,
+        "chunk_size_x": metadata.chunk_size_x,
+        "chunk_size_y": metadata.chunk_size_y,
+        "col_count": metadata.col_count,
+        "row_count": metadata.row_count,
+        "chunk_format_version": CHUNK_FORMAT_VERSION,
     });
+    if let (Some(info), serde_json::Value::Object(fields)) = (
+        source_info.as_object_mut(),
+        fingerprint_fields(cache_dir, file_path)?,
+    ) {
+        info.extend(fields);
+    }
+    // 内容没有变化时保留之前记录的其他字段（例如感知哈希）
+    if let (Some(info), Some(serde_json::Value::Object(previous))) =
+        (source_info.as_object_mut(), load_source_info(cache_dir))
+    {
+        if previous.get("content_hash") == info.get("content_hash") {
+            for (key, value) in previous {
+                info.entry(key).or_insert(value);
+            }
+        }
+    }
     let source_info_json =
         serde_json::to_string(&source_info).map_err(|e| format!("序列化源文件信息失败: {e}"))?;
     let source_info_filepath = cache_dir.join("source_info.json");
     fs::write(&source_info_filepath, source_info_json)
         .map_err(|e| format!("保存源文件信息失败: {e}"))?;
 
-    let end_time = get_time();
-    println!(
-        "[RUST] 预处理和缓存完成: {}ms (总耗时: {}ms), 共 {} 个 chunks",
-        end_time,
-        end_time - start_time,
-        total_chunks
-    );
-
-    Ok(metadata)
+    Ok(())
 }
@@ -0,0 +1,362 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use super::cache::load_cached_metadata;
+use super::export::{validate_region, EXPORT_STRIPE_HEIGHT};
+use super::export_encoder::TiffWriter;
+use super::types::{
+    ImageRegion, PaperSize, PrintExportResult, RenderPipeline, TiffCompression, Viewport,
+};
+use super::viewport_render::{render_viewport_into, validate_pipeline, validate_render_size};
+use crate::utils::time::Stopwatch;
+
+// 打印导出：把图片的一个区域按纸张尺寸等比缩放到可打印范围内 以指定 DPI 重采样
+// 输出 TIFF（写入分辨率标签）或单页 PDF（图片居中放在纸张上）
+// 按条带渲染和写出 内存中只保留一个条带 透明部分合成到白色背景上
+
+const MIN_PRINT_DPI: u32 = 72;
+const MAX_PRINT_DPI: u32 = 1200;
+
+// 没有指定时纸张四周留白的宽度
+const DEFAULT_MARGIN_MM: f64 = 10.0;
+
+const MM_PER_INCH: f64 = 25.4;
+
+// 纸张的纵向尺寸（毫米）
+fn paper_dimensions_mm(paper_size: PaperSize) -> (f64, f64) {
+    match paper_size {
+        PaperSize::A3 => (297.0, 420.0),
+        PaperSize::A4 => (210.0, 297.0),
+        PaperSize::A5 => (148.0, 210.0),
+        PaperSize::Letter => (215.9, 279.4),
+        PaperSize::Legal => (215.9, 355.6),
+        PaperSize::Tabloid => (279.4, 431.8),
+    }
+}
+
+// 输出格式 由扩展名决定
+enum PrintFormat {
+    Tiff,
+    Pdf,
+}
+
+fn print_format(output_path: &str) -> Result<PrintFormat, String> {
+    let extension = Path::new(output_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("tif" | "tiff") => Ok(PrintFormat::Tiff),
+        Some("pdf") => Ok(PrintFormat::Pdf),
+        _ => Err(format!("打印导出只支持 .tif / .tiff / .pdf: {output_path}")),
+    }
+}
+
+/// 导出用于打印的图片
+/// 区域按纸张的可打印范围等比缩放 纸张方向自动选择能放得更大的一边
+/// # Arguments
+/// * `file_path` - 图片文件路径 需要已经预处理
+/// * `rect` - 要打印的区域（原图像素坐标）
+/// * `paper_size` - 纸张尺寸
+/// * `dpi` - 打印分辨率（72 - 1200）
+/// * `output_path` - 输出路径 扩展名为 .tif / .tiff / .pdf
+/// * `margin_mm` - 四周留白 默认 10mm
+/// * `pipeline` - 处理流程 为空时不做处理
+/// # Returns
+/// * `Result<PrintExportResult, String>` - 输出的像素尺寸和物理尺寸
+#[tauri::command]
+pub fn export_for_print(
+    file_path: String,
+    rect: ImageRegion,
+    paper_size: PaperSize,
+    dpi: u32,
+    output_path: String,
+    margin_mm: Option<f64>,
+    pipeline: Option<RenderPipeline>,
+) -> Result<PrintExportResult, String> {
+    let format = print_format(&output_path)?;
+    if !(MIN_PRINT_DPI..=MAX_PRINT_DPI).contains(&dpi) {
+        return Err(format!(
+            "DPI 必须在 {MIN_PRINT_DPI} 到 {MAX_PRINT_DPI} 之间: {dpi}"
+        ));
+    }
+    let metadata = load_cached_metadata(&file_path)?;
+    validate_region(&metadata, &rect)?;
+    let pipeline = pipeline.unwrap_or_default();
+    validate_pipeline(&pipeline)?;
+
+    let margin_mm = margin_mm.unwrap_or(DEFAULT_MARGIN_MM);
+    let (paper_short, paper_long) = paper_dimensions_mm(paper_size);
+    if !margin_mm.is_finite() || margin_mm < 0.0 || margin_mm * 2.0 >= paper_short {
+        return Err(format!("无效的页边距: {margin_mm}mm"));
+    }
+
+    // 纵向和横向各算一次 取打印尺寸更大的方向
+    let fit = |(paper_width, paper_height): (f64, f64)| {
+        let printable_width = paper_width - margin_mm * 2.0;
+        let printable_height = paper_height - margin_mm * 2.0;
+        (printable_width / rect.width as f64).min(printable_height / rect.height as f64)
+    };
+    let portrait_scale = fit((paper_short, paper_long));
+    let landscape_scale = fit((paper_long, paper_short));
+    let landscape = landscape_scale > portrait_scale;
+    let mm_per_pixel = portrait_scale.max(landscape_scale);
+    let (paper_width_mm, paper_height_mm) = if landscape {
+        (paper_long, paper_short)
+    } else {
+        (paper_short, paper_long)
+    };
+
+    let width_mm = rect.width as f64 * mm_per_pixel;
+    let height_mm = rect.height as f64 * mm_per_pixel;
+    let width = ((width_mm / MM_PER_INCH * dpi as f64).round() as u32).max(1);
+    let height = ((height_mm / MM_PER_INCH * dpi as f64).round() as u32).max(1);
+    // 整张输出受纸张和 DPI 限制 按条带渲染 只需要每个条带不超过视口渲染的上限
+    validate_render_size(width, EXPORT_STRIPE_HEIGHT.min(height))?;
+
+    let stopwatch = Stopwatch::start();
+    println!(
+        "[RUST] 打印导出开始: {output_path} {width}x{height} @ {dpi}dpi ({width_mm:.1}x{height_mm:.1}mm)"
+    );
+
+    let mut writer: Box<dyn PrintWriter> = match format {
+        PrintFormat::Tiff => {
+            let mut tiff =
+                TiffWriter::create(&output_path, &[(width, height)], TiffCompression::Deflate)?;
+            tiff.set_resolution(dpi);
+            Box::new(tiff)
+        }
+        PrintFormat::Pdf => Box::new(PdfWriter::create(
+            &output_path,
+            (width, height),
+            (paper_width_mm, paper_height_mm),
+            (width_mm, height_mm),
+        )?),
+    };
+
+    // 按条带渲染 条带的视口与整张输出的采样位置一致
+    let step_y = rect.height as f64 / height as f64;
+    let mut stripe = Vec::new();
+    let mut y = 0;
+    while y < height {
+        let rows = EXPORT_STRIPE_HEIGHT.min(height - y);
+        let viewport = Viewport {
+            x: rect.x as f64,
+            y: rect.y as f64 + y as f64 * step_y,
+            width: rect.width as f64,
+            height: rows as f64 * step_y,
+        };
+        stripe.resize(width as usize * rows as usize * 4, 0);
+        render_viewport_into(&file_path, &viewport, (width, rows), &pipeline, &mut stripe)?;
+        flatten_on_white(&mut stripe);
+        writer.write_rows(&stripe)?;
+        y += rows;
+    }
+    writer.finish()?;
+
+    println!(
+        "[RUST] 打印导出完成: {output_path} (耗时: {}ms)",
+        stopwatch.elapsed_ms()
+    );
+    Ok(PrintExportResult {
+        output_path,
+        width,
+        height,
+        dpi,
+        width_mm,
+        height_mm,
+        landscape,
+    })
+}
+
+// 打印没有透明 按 alpha 与白色混合
+fn flatten_on_white(pixels: &mut [u8]) {
+    for pixel in pixels.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+        if alpha == 255 {
+            continue;
+        }
+        for c in &mut pixel[..3] {
+            *c = ((*c as u32 * alpha + 255 * (255 - alpha) + 127) / 255) as u8;
+        }
+        pixel[3] = 255;
+    }
+}
+
+// 按条带写出打印文件
+trait PrintWriter {
+    fn write_rows(&mut self, pixels: &[u8]) -> Result<(), String>;
+    fn finish(self: Box<Self>) -> Result<(), String>;
+}
+
+impl PrintWriter for TiffWriter {
+    fn write_rows(&mut self, pixels: &[u8]) -> Result<(), String> {
+        TiffWriter::write_rows(self, 0, pixels)
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), String> {
+        TiffWriter::finish(*self)
+    }
+}
+
+// 单页 PDF 页面中居中放置一张 RGB 图片 图片数据用 Flate 压缩流式写入
+// 对象：1 目录 / 2 页面树 / 3 页面 / 4 图片 / 5 图片数据长度 / 6 页面内容
+struct PdfWriter {
+    writer: CountingWriter,
+    // 各对象在文件中的偏移 下标为对象编号减一
+    object_offsets: Vec<u64>,
+    image_start: u64,
+    encoder: Option<ZlibEncoder<Vec<u8>>>,
+    // 页面内容流 图片数据之后写出
+    content: String,
+}
+
+// 记录已写入字节数 用于生成交叉引用表
+struct CountingWriter {
+    inner: BufWriter<File>,
+    position: u64,
+}
+
+impl CountingWriter {
+    fn write(&mut self, data: &[u8]) -> Result<(), String> {
+        self.inner
+            .write_all(data)
+            .map_err(|e| format!("写入 PDF 文件失败: {e}"))?;
+        self.position += data.len() as u64;
+        Ok(())
+    }
+}
+
+impl PdfWriter {
+    fn create(
+        output_path: &str,
+        (width, height): (u32, u32),
+        (paper_width_mm, paper_height_mm): (f64, f64),
+        (image_width_mm, image_height_mm): (f64, f64),
+    ) -> Result<Self, String> {
+        let file = File::create(output_path).map_err(|e| format!("创建 PDF 文件失败: {e}"))?;
+        let mut pdf = Self {
+            writer: CountingWriter {
+                inner: BufWriter::new(file),
+                position: 0,
+            },
+            object_offsets: Vec::new(),
+            image_start: 0,
+            encoder: None,
+            content: String::new(),
+        };
+
+        // PDF 的长度单位为 1/72 英寸
+        let points = |mm: f64| mm / MM_PER_INCH * 72.0;
+        let (page_width, page_height) = (points(paper_width_mm), points(paper_height_mm));
+        let (image_width, image_height) = (points(image_width_mm), points(image_height_mm));
+        pdf.content = format!(
+            "q {image_width:.3} 0 0 {image_height:.3} {:.3} {:.3} cm /Im0 Do Q\n",
+            (page_width - image_width) / 2.0,
+            (page_height - image_height) / 2.0
+        );
+
+        pdf.writer.write(b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n")?;
+        pdf.begin_object()?;
+        pdf.writer
+            .write(b"<< /Type /Catalog /Pages 2 0 R >>\nendobj\n")?;
+        pdf.begin_object()?;
+        pdf.writer
+            .write(b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>\nendobj\n")?;
+        pdf.begin_object()?;
+        pdf.writer.write(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {page_width:.3} {page_height:.3}] \
+                 /Resources << /XObject << /Im0 4 0 R >> >> /Contents 6 0 R >>\nendobj\n"
+            )
+            .as_bytes(),
+        )?;
+        pdf.begin_object()?;
+        pdf.writer.write(
+            format!(
+                "<< /Type /XObject /Subtype /Image /Width {width} /Height {height} \
+                 /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /FlateDecode \
+                 /Length 5 0 R >>\nstream\n"
+            )
+            .as_bytes(),
+        )?;
+        pdf.image_start = pdf.writer.position;
+        pdf.encoder = Some(ZlibEncoder::new(Vec::new(), Compression::default()));
+        Ok(pdf)
+    }
+
+    fn begin_object(&mut self) -> Result<(), String> {
+        self.object_offsets.push(self.writer.position);
+        let number = self.object_offsets.len();
+        self.writer.write(format!("{number} 0 obj\n").as_bytes())
+    }
+
+    // 把压缩器中已经产生的数据写到文件
+    fn drain_encoder(&mut self) -> Result<(), String> {
+        if let Some(encoder) = self.encoder.as_mut() {
+            let compressed = std::mem::take(encoder.get_mut());
+            self.writer.write(&compressed)?;
+        }
+        Ok(())
+    }
+}
+
+impl PrintWriter for PdfWriter {
+    fn write_rows(&mut self, pixels: &[u8]) -> Result<(), String> {
+        let rgb: Vec<u8> = pixels
+            .chunks_exact(4)
+            .flat_map(|p| [p[0], p[1], p[2]])
+            .collect();
+        self.encoder
+            .as_mut()
+            .ok_or("PDF 图片数据已经写完")?
+            .write_all(&rgb)
+            .map_err(|e| format!("压缩 PDF 图片数据失败: {e}"))?;
+        self.drain_encoder()
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), String> {
+        let encoder = self.encoder.take().ok_or("PDF 图片数据已经写完")?;
+        let rest = encoder
+            .finish()
+            .map_err(|e| format!("压缩 PDF 图片数据失败: {e}"))?;
+        self.writer.write(&rest)?;
+        let image_length = self.writer.position - self.image_start;
+        self.writer.write(b"\nendstream\nendobj\n")?;
+
+        self.begin_object()?;
+        self.writer
+            .write(format!("{image_length}\nendobj\n").as_bytes())?;
+        let content = std::mem::take(&mut self.content);
+        self.begin_object()?;
+        self.writer.write(
+            format!(
+                "<< /Length {} >>\nstream\n{content}endstream\nendobj\n",
+                content.len()
+            )
+            .as_bytes(),
+        )?;
+
+        let xref_offset = self.writer.position;
+        let mut xref = format!(
+            "xref\n0 {}\n0000000000 65535 f \n",
+            self.object_offsets.len() + 1
+        );
+        for offset in &self.object_offsets {
+            xref.push_str(&format!("{offset:010} 00000 n \n"));
+        }
+        xref.push_str(&format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref_offset}\n%%EOF\n",
+            self.object_offsets.len() + 1
+        ));
+        self.writer.write(xref.as_bytes())?;
+        self.writer
+            .inner
+            .flush()
+            .map_err(|e| format!("写入 PDF 文件失败: {e}"))
+    }
+}
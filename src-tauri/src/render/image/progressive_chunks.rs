@@ -0,0 +1,226 @@
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::BinaryHeap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::thread;
+
+use image::RgbaImage;
+use tauri::ipc::{Channel, Response};
+use tauri::AppHandle;
+
+use super::access_stats::record_chunk_access;
+use super::backpressure::ChunkRequestPermit;
+use super::chunk_processing::load_chunk_bytes;
+use super::gpu_compute::downsample_half;
+use super::jpeg_quality::encode_jpeg;
+use super::scheduler::run_scheduled;
+use super::types::TaskKind;
+
+// 渐进式 chunk：快速缩放时先返回低质量的 JPEG 预览 再在带宽空闲时推送无损版本
+// 无损版本按优先级排队（前端传入 一般是到视口中心的距离） 由单个后台线程依次发送
+// 还有预览请求在处理时后台线程先等待 预览始终优先 用户看到画面的延迟大大降低
+// 预览先缩小再编码 编码量只有完整 chunk 的一小部分 前端按 chunk 的尺寸拉伸显示
+
+// 预览的默认 JPEG 质量
+const DEFAULT_PREVIEW_QUALITY: u8 = 40;
+// 预览每次缩小一半的次数 2 表示边长缩小为 1/4
+const PREVIEW_DOWNSCALE_STEPS: u32 = 2;
+
+// 排队顺序 优先级相同时先到先处理
+static REFINE_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+static REFINE_QUEUE: OnceLock<RefineQueue> = OnceLock::new();
+
+// 一个等待发送的无损 chunk
+struct Refinement {
+    priority: u32,
+    sequence: u64,
+    file_path: String,
+    chunk_x: u32,
+    chunk_y: u32,
+    channel: Channel<Response>,
+}
+
+// BinaryHeap 是最大堆 优先级数值小、排队早的排在堆顶
+impl Ord for Refinement {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        (other.priority, other.sequence).cmp(&(self.priority, self.sequence))
+    }
+}
+
+impl PartialOrd for Refinement {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Refinement {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == CmpOrdering::Equal
+    }
+}
+
+impl Eq for Refinement {}
+
+struct RefineQueue {
+    heap: Mutex<BinaryHeap<Refinement>>,
+    wakeup: Condvar,
+    // 正在处理的预览请求数 减到 0 时唤醒后台线程
+    previews: Mutex<usize>,
+    previews_done: Condvar,
+}
+
+// 预览请求计数 请求结束（包括出错）时自动减一
+struct PreviewGuard;
+
+impl PreviewGuard {
+    fn new() -> Self {
+        *lock_previews(get_refine_queue()) += 1;
+        PreviewGuard
+    }
+}
+
+impl Drop for PreviewGuard {
+    fn drop(&mut self) {
+        let queue = get_refine_queue();
+        let mut previews = lock_previews(queue);
+        *previews -= 1;
+        if *previews == 0 {
+            queue.previews_done.notify_all();
+        }
+    }
+}
+
+fn lock_previews(queue: &RefineQueue) -> std::sync::MutexGuard<'_, usize> {
+    queue.previews.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn get_refine_queue() -> &'static RefineQueue {
+    REFINE_QUEUE.get_or_init(|| {
+        thread::Builder::new()
+            .name("chunk-refine".to_string())
+            .spawn(run_refine_worker)
+            .expect("创建渐进式 chunk 线程失败");
+        RefineQueue {
+            heap: Mutex::new(BinaryHeap::new()),
+            wakeup: Condvar::new(),
+            previews: Mutex::new(0),
+            previews_done: Condvar::new(),
+        }
+    })
+}
+
+fn run_refine_worker() {
+    let queue = get_refine_queue();
+    loop {
+        let refinement = {
+            let Ok(mut heap) = queue.heap.lock() else {
+                return;
+            };
+            loop {
+                if let Some(refinement) = heap.pop() {
+                    break refinement;
+                }
+                heap = match queue.wakeup.wait(heap) {
+                    Ok(heap) => heap,
+                    Err(_) => return,
+                };
+            }
+        };
+
+        // 等预览都发出去再占用带宽
+        let mut previews = lock_previews(queue);
+        while *previews > 0 {
+            previews = queue
+                .previews_done
+                .wait(previews)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        drop(previews);
+        let Refinement {
+            file_path,
+            chunk_x,
+            chunk_y,
+            channel,
+            ..
+        } = refinement;
+        let result = load_chunk_bytes(chunk_x, chunk_y, file_path, false, None)
+            .and_then(|data| channel.send(Response::new(data)).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            println!("[RUST] 发送无损 chunk 失败: ({chunk_x}, {chunk_y}) {e}");
+        }
+    }
+}
+
+/// 获取 chunk 的低质量预览 并把无损版本加入优先级队列
+/// 预览直接返回 JPEG 文件数据（不含宽高头部 前端按图片解码）
+/// 预览的边长缩小为 chunk 的 1/4（向上取整） 前端按 chunk 的尺寸拉伸显示
+/// 无损版本准备好后通过 on_refined 发送 格式与 get_image_chunk 相同
+/// 同一个 chunk 重复请求时只保留最新的一次
+/// # Arguments
+/// * `chunk_x` / `chunk_y` - chunk 的列、行索引
+/// * `file_path` - 图片文件路径
+/// * `priority` - 无损版本的优先级 数值越小越先发送
+/// * `quality` - 预览的 JPEG 质量（1-100） 默认为 40
+/// * `on_refined` - 接收无损版本的通道
+#[tauri::command]
+pub fn get_progressive_chunk(
+    app: AppHandle,
+    chunk_x: u32,
+    chunk_y: u32,
+    file_path: String,
+    priority: u32,
+    quality: Option<u8>,
+    on_refined: Channel<Response>,
+) -> Result<Response, String> {
+    let quality = quality.unwrap_or(DEFAULT_PREVIEW_QUALITY);
+    if !(1..=100).contains(&quality) {
+        return Err(format!("预览质量必须在 1 到 100 之间: {quality}"));
+    }
+    let _permit = ChunkRequestPermit::acquire(&file_path)?;
+    let _guard = PreviewGuard::new();
+    record_chunk_access(&file_path, chunk_x, chunk_y);
+    let mut data = run_scheduled(TaskKind::Read, &file_path, || {
+        load_chunk_bytes(chunk_x, chunk_y, file_path.clone(), false, Some(&app))
+    })??;
+    let width = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+    let height = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+    let mut preview = RgbaImage::from_raw(width, height, data.split_off(8))
+        .ok_or_else(|| "chunk 数据长度不匹配".to_string())?;
+    for _ in 0..PREVIEW_DOWNSCALE_STEPS {
+        preview = downsample_half(&preview);
+    }
+    let preview = encode_jpeg(preview.as_raw(), preview.width(), preview.height(), quality)?;
+
+    let queue = get_refine_queue();
+    let mut heap = queue
+        .heap
+        .lock()
+        .map_err(|e| format!("获取渐进式 chunk 队列锁失败: {e}"))?;
+    heap.retain(|r| !(r.file_path == file_path && r.chunk_x == chunk_x && r.chunk_y == chunk_y));
+    heap.push(Refinement {
+        priority,
+        sequence: REFINE_SEQUENCE.fetch_add(1, Ordering::SeqCst),
+        file_path,
+        chunk_x,
+        chunk_y,
+        channel: on_refined,
+    });
+    queue.wakeup.notify_one();
+    Ok(Response::new(preview))
+}
+
+/// 取消图片所有还没发送的无损 chunk（例如切换图片或视口快速移走时）
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<usize, String>` - 取消的数量
+#[tauri::command]
+pub fn cancel_chunk_refinements(file_path: String) -> Result<usize, String> {
+    let mut heap = get_refine_queue()
+        .heap
+        .lock()
+        .map_err(|e| format!("获取渐进式 chunk 队列锁失败: {e}"))?;
+    let before = heap.len();
+    heap.retain(|r| r.file_path != file_path);
+    Ok(before - heap.len())
+}
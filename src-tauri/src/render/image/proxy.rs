@@ -0,0 +1,432 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::OnceLock;
+
+use image::{ImageFormat, RgbaImage};
+use rayon::prelude::*;
+use sysinfo::System;
+use tauri::ipc::Response;
+use tauri::AppHandle;
+
+use super::cache::{
+    check_file_cache_exists, ensure_cache_writable, image_cache_dir, is_cache_read_only,
+};
+use super::commands::{load_user_image, validate_image_path};
+use super::compact_metadata::decode_metadata;
+use super::config::{CHUNK_SIZE_X, CHUNK_SIZE_Y};
+use super::decode_sandbox::is_decode_sandbox_enabled;
+use super::errors::{localized_error, ErrorCode};
+use super::gpu_compute::{convert_to_rgba8, downsample_half};
+use super::level_formats::{level_format, read_level_chunk, write_level_chunk};
+use super::lod_pyramid::{pyramid_level_count, write_lod_level_by_chunks};
+use super::preprocess_dedup::run_deduplicated;
+use super::preprocess_queue::enqueue_full_resolution;
+use super::preprocessing::{build_chunk_infos, decode_source_image, detect_source_format};
+use super::pyramid_import::{find_dzi_pyramid, TilePyramid};
+use super::region_decode::decode_source_region;
+use super::scheduler::run_scheduled;
+use super::stripe_processing::{should_stream_png, write_proxy_in_stripes};
+use super::tiff_input::open_tiled_tiff;
+use super::types::{ImageMetadata, TaskKind};
+use super::utils::is_up_to_date;
+use crate::utils::time::Stopwatch;
+
+// 代理模式：内存受限的设备上先生成 1/2 或 1/4 分辨率的代理副本 浏览时只使用代理
+// 完整分辨率的分块推迟到用户需要 1:1 查看时 通过 request_full_resolution 在后台生成
+// 代理副本保存在图片缓存目录下的 proxy_{倍数} 子目录中 元数据中的 proxy_scale 标明缩小倍数
+// 前端按 proxy_scale 放大显示 坐标乘以 proxy_scale 即为原图坐标
+// 磁盘空间紧张时可以通过 preprocess_levels 只生成概览级别 完整分辨率推迟到放大到 1:1 时
+
+// 设置为 1 / 2 / 4 时作为默认的代理倍数 1 表示不使用代理
+pub const PROXY_SCALE_ENV: &str = "IMAGES_GL_PROXY_SCALE";
+
+// 没有设置时 总内存低于这个值的设备默认使用 1/2 代理
+const LOW_MEMORY_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+// 像素数据不超过这个大小的图片直接完整分块 代理没有意义
+const MIN_PROXY_IMAGE_BYTES: u64 = CHUNK_SIZE_X as u64 * CHUNK_SIZE_Y as u64 * 4;
+// 分辨率级别 n 对应 1/2^n 的代理副本 小图片也可以生成到第 2 级（1/4）
+const MAX_PROXY_LEVEL: u32 = 2;
+// 按区域生成代理时每次从原图读取的行数 每个线程同时只保留一个条带
+const PROXY_SOURCE_BAND_ROWS: u32 = 256;
+
+static PROXY_SCALE: AtomicU32 = AtomicU32::new(1);
+static PROXY_SCALE_INIT: OnceLock<()> = OnceLock::new();
+
+fn is_valid_scale(scale: u32) -> bool {
+    matches!(scale, 1 | 2 | 4)
+}
+
+/// 当前的代理倍数 1 表示不使用代理
+/// 首次调用时读取环境变量 没有设置时移动端和小内存设备默认为 2
+pub fn proxy_scale() -> u32 {
+    PROXY_SCALE_INIT.get_or_init(|| {
+        let scale = env::var(PROXY_SCALE_ENV)
+            .ok()
+            .and_then(|v| v.trim().parse::<u32>().ok())
+            .filter(|&scale| is_valid_scale(scale))
+            .unwrap_or_else(|| {
+                let mut system = System::new();
+                system.refresh_memory();
+                if cfg!(mobile) || system.total_memory() < LOW_MEMORY_BYTES {
+                    2
+                } else {
+                    1
+                }
+            });
+        PROXY_SCALE.store(scale, Ordering::Relaxed);
+    });
+    PROXY_SCALE.load(Ordering::Relaxed)
+}
+
+/// 设置代理倍数
+/// # Arguments
+/// * `scale` - 1 不使用代理 / 2 使用 1/2 分辨率 / 4 使用 1/4 分辨率
+/// # Returns
+/// * `Result<u32, String>` - 设置后的代理倍数
+#[tauri::command]
+pub fn set_proxy_scale(scale: u32) -> Result<u32, String> {
+    if !is_valid_scale(scale) {
+        return Err(format!("代理倍数只支持 1、2、4: {scale}"));
+    }
+    // 先触发一次初始化 避免之后读取环境变量覆盖这里的设置
+    proxy_scale();
+    PROXY_SCALE.store(scale, Ordering::Relaxed);
+    println!("[RUST] 代理倍数已设置为 {scale}");
+    Ok(scale)
+}
+
+/// 获取代理倍数
+#[tauri::command]
+pub fn get_proxy_scale() -> Result<u32, String> {
+    Ok(proxy_scale())
+}
+
+/// 代理副本的缓存目录
+pub fn proxy_cache_dir(file_path: &str, scale: u32) -> PathBuf {
+    image_cache_dir(file_path).join(format!("proxy_{scale}"))
+}
+
+/// 缓存目录中是否有代理副本 维护时不把只有代理副本的目录当作残缺缓存清理
+pub fn has_proxy(cache_dir: &Path) -> bool {
+    [2, 4].iter().any(|scale| {
+        cache_dir
+            .join(format!("proxy_{scale}/metadata.json"))
+            .exists()
+    })
+}
+
+/// 打开图片时应该使用的代理倍数 不需要代理时为 None
+/// 已有完整分辨率缓存、缓存只读、图片本身不大时不使用代理
+pub fn proxy_scale_for(file_path: &str) -> Option<u32> {
+    let scale = proxy_scale();
+    if scale <= 1 || check_file_cache_exists(file_path) || is_cache_read_only() {
+        return None;
+    }
+    let (width, height) = image::image_dimensions(file_path).ok()?;
+    (width as u64 * height as u64 * 4 > MIN_PROXY_IMAGE_BYTES).then_some(scale)
+}
+
+pub(super) fn load_proxy_metadata(proxy_dir: &Path) -> Result<ImageMetadata, String> {
+    let content = fs::read_to_string(proxy_dir.join("metadata.json"))
+        .map_err(|e| format!("读取代理元数据失败: {e}"))?;
+    decode_metadata(&content).map_err(|e| format!("解析代理元数据失败: {e}"))
+}
+
+/// 加载图片的代理副本 没有或源文件更新过时重新生成
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `scale` - 缩小倍数（2 或 4）
+/// # Returns
+/// * `Result<ImageMetadata, String>` - 代理副本的元数据（proxy_scale 有值）
+pub fn load_proxy_image(file_path: &str, scale: u32) -> Result<ImageMetadata, String> {
+    validate_image_path(file_path)?;
+    let proxy_dir = proxy_cache_dir(file_path, scale);
+    if is_up_to_date(Path::new(file_path), &proxy_dir.join("metadata.json")) {
+        if let Ok(metadata) = load_proxy_metadata(&proxy_dir) {
+            println!("[RUST] 从缓存加载代理副本: 1/{scale}");
+            return Ok(metadata);
+        }
+    }
+
+    ensure_cache_writable("生成代理副本")?;
+    let stopwatch = Stopwatch::start();
+    let metadata = generate_proxy(file_path, &proxy_dir, scale)?;
+    println!(
+        "[RUST] 代理副本生成完成: 1/{scale} {}x{}, 共 {} 个 chunks (耗时: {}ms)",
+        metadata.total_width,
+        metadata.total_height,
+        metadata.chunks.len(),
+        stopwatch.elapsed_ms()
+    );
+    Ok(metadata)
+}
+
+// 生成代理副本 尽量不整张解码原图
+// 1. DZI 金字塔、分块 TIFF 中有这一层时直接读取 没有时按条带读取原图区域再缩小
+// 2. 非隔行扫描的 PNG 按行流式缩小（见 stripe_processing）
+// 3. BMP 按条带读取矩形区域再缩小（见 region_decode）
+// 4. 其他格式整张解码后每次缩小一半 倍数只有 2 和 4 有可用的 GPU 时在 GPU 上缩小
+fn generate_proxy(file_path: &str, proxy_dir: &Path, scale: u32) -> Result<ImageMetadata, String> {
+    if let Some(metadata) = generate_proxy_streamed(file_path, proxy_dir, scale)? {
+        return Ok(metadata);
+    }
+    let mut proxy = convert_to_rgba8(&decode_source_image(file_path)?);
+    for _ in 0..scale.trailing_zeros() {
+        proxy = downsample_half(&proxy);
+    }
+    write_proxy(proxy_dir, &proxy, scale)
+}
+
+// 不整张解码源图片生成代理（金字塔、条带读取 PNG、按区域读取 BMP）
+// 源图片只能整张解码时返回 None
+fn generate_proxy_streamed(
+    file_path: &str,
+    proxy_dir: &Path,
+    scale: u32,
+) -> Result<Option<ImageMetadata>, String> {
+    if let Some(pyramid) = find_dzi_pyramid(file_path) {
+        return write_proxy_from_pyramid(file_path, proxy_dir, &pyramid, scale).map(Some);
+    }
+    if let Some(tiff) = open_tiled_tiff(file_path) {
+        return write_proxy_from_pyramid(file_path, proxy_dir, &tiff, scale).map(Some);
+    }
+    if should_stream_png(file_path) {
+        return write_proxy_in_stripes(file_path, proxy_dir, scale).map(Some);
+    }
+    if !is_decode_sandbox_enabled() && detect_source_format(file_path) == Ok(ImageFormat::Bmp) {
+        let size =
+            image::image_dimensions(file_path).map_err(|e| format!("读取图片尺寸失败: {e}"))?;
+        write_lod_level_by_chunks(
+            file_path,
+            scale.trailing_zeros(),
+            (size.0.div_ceil(scale), size.1.div_ceil(scale)),
+            |x, y, width, height| {
+                read_proxy_region(
+                    |sx, sy, sw, sh| decode_source_region(file_path, sx, sy, sw, sh),
+                    size,
+                    scale,
+                    (x, y, width, height),
+                )
+            },
+        )?;
+        return load_proxy_metadata(proxy_dir).map(Some);
+    }
+    Ok(None)
+}
+
+// 从 DZI 金字塔或分块 TIFF 生成代理 逐个 chunk 只读取覆盖的瓦片
+fn write_proxy_from_pyramid(
+    file_path: &str,
+    proxy_dir: &Path,
+    pyramid: &impl TilePyramid,
+    scale: u32,
+) -> Result<ImageMetadata, String> {
+    let level = scale.trailing_zeros();
+    match pyramid.downscaled_size(level) {
+        Some(size) => write_lod_level_by_chunks(file_path, level, size, |x, y, width, height| {
+            pyramid.read_downscaled_region(level, x, y, width, height)
+        })?,
+        None => {
+            let size = pyramid.size();
+            write_lod_level_by_chunks(
+                file_path,
+                level,
+                (size.0.div_ceil(scale), size.1.div_ceil(scale)),
+                |x, y, width, height| {
+                    read_proxy_region(
+                        |sx, sy, sw, sh| pyramid.read_region(sx, sy, sw, sh),
+                        size,
+                        scale,
+                        (x, y, width, height),
+                    )
+                },
+            )?
+        }
+    };
+    load_proxy_metadata(proxy_dir)
+}
+
+// 读取代理中的一个区域 原图按条带读取后缩小 内存中只有一个条带
+// 条带的起点是 scale 的倍数 结果与整张图片逐次缩小一半完全一致
+fn read_proxy_region(
+    read_source: impl Fn(u32, u32, u32, u32) -> Result<RgbaImage, String>,
+    (source_width, source_height): (u32, u32),
+    scale: u32,
+    (x, y, width, height): (u32, u32, u32, u32),
+) -> Result<RgbaImage, String> {
+    let band_rows = PROXY_SOURCE_BAND_ROWS.next_multiple_of(scale);
+    let source_x = x * scale;
+    let source_region_width = (width * scale).min(source_width - source_x);
+    let source_end_y = ((y + height) * scale).min(source_height);
+    let mut output = RgbaImage::new(width, height);
+    let mut source_y = y * scale;
+    while source_y < source_end_y {
+        let rows = band_rows.min(source_end_y - source_y);
+        let mut band = read_source(source_x, source_y, source_region_width, rows)?;
+        for _ in 0..scale.trailing_zeros() {
+            band = downsample_half(&band);
+        }
+        image::imageops::replace(&mut output, &band, 0, (source_y / scale - y) as i64);
+        source_y += rows;
+    }
+    Ok(output)
+}
+
+/// 把缩小后的图片分块写入代理目录 多分辨率金字塔的概览级别也使用这个格式
+pub(super) fn write_proxy(
+    proxy_dir: &Path,
+    proxy: &RgbaImage,
+    scale: u32,
+) -> Result<ImageMetadata, String> {
+    let (width, height) = proxy.dimensions();
+
+    // 旧的代理可能网格不同 先删除
+    let _ = fs::remove_dir_all(proxy_dir);
+    fs::create_dir_all(proxy_dir).map_err(|e| format!("创建代理目录失败: {e}"))?;
+    let chunks = build_chunk_infos(width, height, CHUNK_SIZE_X, CHUNK_SIZE_Y);
+    // 概览级别可以配置为有损格式 见 level_formats
+    let format = level_format(scale.trailing_zeros());
+    chunks
+        .par_iter()
+        .map(|chunk| write_level_chunk(proxy, chunk, proxy_dir, format))
+        .collect::<Result<(), String>>()?;
+    write_proxy_metadata(proxy_dir, width, height, scale)
+}
+
+/// 所有 chunk 写完后写入代理的元数据
+pub(super) fn write_proxy_metadata(
+    proxy_dir: &Path,
+    width: u32,
+    height: u32,
+    scale: u32,
+) -> Result<ImageMetadata, String> {
+    let metadata = ImageMetadata {
+        total_width: width,
+        total_height: height,
+        chunk_size_x: CHUNK_SIZE_X,
+        chunk_size_y: CHUNK_SIZE_Y,
+        col_count: width.div_ceil(CHUNK_SIZE_X),
+        row_count: height.div_ceil(CHUNK_SIZE_Y),
+        chunks: build_chunk_infos(width, height, CHUNK_SIZE_X, CHUNK_SIZE_Y),
+        proxy_scale: Some(scale),
+        levels: Vec::new(),
+        timings: None,
+        chunk_count: None,
+    };
+    // 元数据最后写入 中断时不会留下看起来完整的代理
+    let json = serde_json::to_string(&metadata).map_err(|e| format!("序列化元数据失败: {e}"))?;
+    fs::write(proxy_dir.join("metadata.json"), json)
+        .map_err(|e| format!("保存代理元数据失败: {e}"))?;
+    Ok(metadata)
+}
+
+/// 只生成指定的分辨率级别 磁盘空间紧张时可以先生成体积小、速度快的概览级别
+/// 完整分辨率（第 0 级）推迟到真正需要 1:1 查看时再生成（见 request_full_resolution）
+/// 需要生成的概览级别共用一次源图片解码 已经是最新的级别直接返回
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `levels` - 级别 0 为完整分辨率 1 为 1/2 2 为 1/4
+/// # Returns
+/// * `Result<Vec<ImageMetadata>, String>` - 按 levels 的顺序返回各级别的元数据
+#[tauri::command]
+pub fn preprocess_levels(
+    file_path: String,
+    levels: Vec<u32>,
+) -> Result<Vec<ImageMetadata>, String> {
+    validate_image_path(&file_path)?;
+    // 大图片可以生成到多分辨率金字塔的最粗级别
+    let (width, height) =
+        image::image_dimensions(&file_path).map_err(|e| format!("读取图片尺寸失败: {e}"))?;
+    let max_level =
+        pyramid_level_count(width, height, CHUNK_SIZE_X, CHUNK_SIZE_Y).max(MAX_PROXY_LEVEL);
+    if let Some(level) = levels.iter().find(|&&level| level > max_level) {
+        return Err(format!(
+            "不支持的级别 {level} 可用的级别为 0 到 {max_level}"
+        ));
+    }
+    run_scheduled(TaskKind::Preprocess, &file_path, || {
+        build_levels(&file_path, &levels)
+    })?
+}
+
+fn build_levels(file_path: &str, levels: &[u32]) -> Result<Vec<ImageMetadata>, String> {
+    let stopwatch = Stopwatch::start();
+    let stale: Vec<u32> = levels
+        .iter()
+        .copied()
+        .filter(|&level| level > 0)
+        .filter(|level| {
+            let proxy_dir = proxy_cache_dir(file_path, 1 << level);
+            !is_up_to_date(Path::new(file_path), &proxy_dir.join("metadata.json"))
+                || load_proxy_metadata(&proxy_dir).is_err()
+        })
+        .collect();
+    if !stale.is_empty() {
+        ensure_cache_writable("生成代理副本")?;
+        // 能按区域读取的源图片逐个级别生成 内存中只有一个条带
+        let mut decode_levels = Vec::new();
+        for &level in &stale {
+            let proxy_dir = proxy_cache_dir(file_path, 1 << level);
+            if generate_proxy_streamed(file_path, &proxy_dir, 1 << level)?.is_none() {
+                decode_levels.push(level);
+            }
+        }
+        // 其余的源图片只解码一次 逐次缩小一半生成所有级别
+        if let Some(&coarsest) = decode_levels.iter().max() {
+            let mut proxy = convert_to_rgba8(&decode_source_image(file_path)?);
+            for level in 1..=coarsest {
+                proxy = downsample_half(&proxy);
+                if decode_levels.contains(&level) {
+                    write_proxy(&proxy_cache_dir(file_path, 1 << level), &proxy, 1 << level)?;
+                }
+            }
+        }
+        println!(
+            "[RUST] 概览级别 {stale:?} 生成完成 (耗时: {}ms)",
+            stopwatch.elapsed_ms()
+        );
+    }
+
+    levels
+        .iter()
+        .map(|&level| match level {
+            0 => run_deduplicated(file_path, false, || load_user_image(file_path)),
+            _ => load_proxy_metadata(&proxy_cache_dir(file_path, 1 << level)),
+        })
+        .collect()
+}
+
+/// 获取代理副本的 chunk 数据 格式与 get_image_chunk 相同
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `scale` - 代理的缩小倍数（元数据中的 proxy_scale）
+/// * `chunk_x` / `chunk_y` - chunk 索引
+#[tauri::command]
+pub fn get_proxy_chunk(
+    file_path: String,
+    scale: u32,
+    chunk_x: u32,
+    chunk_y: u32,
+) -> Result<Response, String> {
+    let proxy_dir = proxy_cache_dir(&file_path, scale);
+    if !proxy_dir.join("metadata.json").exists() {
+        return Err(localized_error(ErrorCode::CacheMissing, &[]));
+    }
+    let chunk_data = read_level_chunk(&proxy_dir, chunk_x, chunk_y)?;
+    Ok(Response::new(chunk_data))
+}
+
+/// 请求完整分辨率 在后台预处理队列中生成完整分辨率的 chunk
+/// 完成后发出 preprocess://completed 事件 前端收到后切换到完整分辨率的元数据
+/// # Returns
+/// * `Result<bool, String>` - true 表示已加入队列 false 表示已经有完整分辨率缓存或已在队列中
+#[tauri::command]
+pub fn request_full_resolution(app: AppHandle, file_path: String) -> Result<bool, String> {
+    if check_file_cache_exists(&file_path) {
+        return Ok(false);
+    }
+    println!("[RUST] 请求完整分辨率: {file_path}");
+    enqueue_full_resolution(&app, &file_path)
+}
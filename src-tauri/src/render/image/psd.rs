@@ -0,0 +1,498 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::path::Path;
+
+use memmap2::Mmap;
+use tauri::{AppHandle, Window};
+
+use super::cache::fnv1a_hash;
+use super::commands::load_user_image;
+use super::types::ImageMetadata;
+use super::utils::{app_data_subdir, is_up_to_date, save_rgba_png};
+use super::window_state::set_window_image;
+
+// PSD 转换结果在应用数据目录下的保存位置
+const PSD_DIR: &str = "psd";
+
+// 颜色模式
+const COLOR_MODE_GRAYSCALE: u16 = 1;
+const COLOR_MODE_INDEXED: u16 = 2;
+const COLOR_MODE_RGB: u16 = 3;
+const COLOR_MODE_CMYK: u16 = 4;
+
+// 规范允许的最大通道数
+const MAX_CHANNELS: usize = 56;
+
+// 合并图像整张解码到内存 超过这个像素数（约 10 亿 RGBA 4GB）时拒绝 PSB 本身不限制尺寸
+const MAX_COMPOSITE_PIXELS: u64 = 1 << 30;
+
+// 图像数据的压缩方式
+const COMPRESSION_RAW: u16 = 0;
+const COMPRESSION_RLE: u16 = 1;
+
+// PackBits 的最大展开倍数 2 个字节的重复段最多展开为 128 个字节
+const PACKBITS_MAX_RATIO: usize = 64;
+
+/// 打开 PSD / PSB 文件
+/// 只读取文件末尾的合并图像（Photoshop 保存时开启了“最大兼容”才会有完整内容）
+/// 不解析图层 PSB 的尺寸可以超过 30000 像素 普通解码器无法处理
+/// 转换为 PNG 后走正常的预处理流程
+/// 保存后的路径会记录为调用窗口当前打开的图片 前端可以通过 get_window_state 取得
+#[tauri::command]
+pub fn process_psd_image(
+    app: AppHandle,
+    window: Window,
+    file_path: String,
+) -> Result<ImageMetadata, String> {
+    let source = Path::new(&file_path);
+    let dir = app_data_subdir(&app, PSD_DIR)?;
+    let output = dir.join(format!("psd_{:016x}.png", fnv1a_hash(file_path.as_bytes())));
+
+    if is_up_to_date(source, &output) {
+        println!("[RUST] 复用已转换的 PSD 图片: {}", output.display());
+    } else {
+        let file =
+            File::open(source).map_err(|e| format!("打开 PSD 文件失败: {e} ({source:?})"))?;
+        let mmap = unsafe { Mmap::map(&file) }
+            .map_err(|e| format!("映射 PSD 文件失败: {e} ({source:?})"))?;
+        let (width, height, pixels) = decode_composite(&mmap)?;
+        save_rgba_png(&output, width, height, pixels)?;
+    }
+
+    let output = output.to_string_lossy().to_string();
+    let metadata = load_user_image(&output)?;
+    set_window_image(window.label(), &output);
+    Ok(metadata)
+}
+
+// 按大端序顺序读取字节
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| "PSD 文件不完整".to_string())?;
+        let slice = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        let b = self.bytes(8)?;
+        Ok(u64::from_be_bytes(b.try_into().unwrap_or([0; 8])))
+    }
+}
+
+/// 解码合并图像
+/// # Returns
+/// * `Result<(u32, u32, Vec<u8>), String>` - 宽度、高度和 RGBA 像素数据
+fn decode_composite(data: &[u8]) -> Result<(u32, u32, Vec<u8>), String> {
+    let mut reader = Reader { data, pos: 0 };
+
+    // 文件头
+    if reader.bytes(4)? != b"8BPS" {
+        return Err("不是有效的 PSD 文件".to_string());
+    }
+    let version = reader.u16()?;
+    let is_psb = match version {
+        1 => false,
+        2 => true,
+        _ => return Err(format!("不支持的 PSD 版本: {version}")),
+    };
+    reader.bytes(6)?;
+    let channels = reader.u16()? as usize;
+    let height = reader.u32()?;
+    let width = reader.u32()?;
+    let depth = reader.u16()?;
+    let color_mode = reader.u16()?;
+
+    println!(
+        "[RUST] 解析 {}: {width}x{height} {channels} 通道 {depth} 位 颜色模式 {color_mode}",
+        if is_psb { "PSB" } else { "PSD" }
+    );
+
+    if !matches!(depth, 8 | 16 | 32) {
+        return Err(format!("不支持的位深: {depth}"));
+    }
+    // 颜色模式需要的最少通道数 以及转换时用到的通道数（其余为额外的 alpha 或专色通道）
+    let (min_channels, used_channels) = match color_mode {
+        COLOR_MODE_GRAYSCALE => (1, channels.min(2)),
+        COLOR_MODE_INDEXED => (1, 1),
+        COLOR_MODE_RGB => (3, channels.min(4)),
+        COLOR_MODE_CMYK => (4, channels.min(5)),
+        _ => return Err(format!("不支持的颜色模式: {color_mode}")),
+    };
+    if channels < min_channels || channels > MAX_CHANNELS {
+        return Err(format!("颜色模式 {color_mode} 的通道数无效: {channels}"));
+    }
+    if color_mode == COLOR_MODE_INDEXED && depth != 8 {
+        return Err(format!("索引色只支持 8 位: {depth}"));
+    }
+    let pixel_count = (width as u64)
+        .checked_mul(height as u64)
+        .filter(|&count| count > 0 && count <= MAX_COMPOSITE_PIXELS)
+        .ok_or_else(|| format!("PSD 图片尺寸无效或过大: {width}x{height}"))?
+        as usize;
+
+    // 颜色模式数据（索引色的调色板）
+    let color_data_len = reader.u32()? as usize;
+    let palette = reader.bytes(color_data_len)?;
+
+    // 图像资源 跳过
+    let resources_len = reader.u32()? as usize;
+    reader.bytes(resources_len)?;
+
+    // 图层和蒙版信息 跳过 PSB 中长度为 8 字节
+    let layers_len = if is_psb {
+        reader.u64()? as usize
+    } else {
+        reader.u32()? as usize
+    };
+    reader.bytes(layers_len)?;
+
+    // 合并图像数据 各通道平面存储 只保留转换时用到的通道
+    let compression = reader.u16()?;
+    let bytes_per_sample = depth as usize / 8;
+    let row_len = width as usize * bytes_per_sample;
+    let rows = height as usize * channels;
+    let plane_len = pixel_count
+        .checked_mul(bytes_per_sample)
+        .ok_or("PSD 图片尺寸过大")?;
+    let used_len = plane_len
+        .checked_mul(used_channels)
+        .ok_or("PSD 图片尺寸过大")?;
+    let planes = match compression {
+        COMPRESSION_RAW => {
+            let all_len = plane_len.checked_mul(channels).ok_or("PSD 图片尺寸过大")?;
+            Cow::Borrowed(&reader.bytes(all_len)?[..used_len])
+        }
+        COMPRESSION_RLE => {
+            // 每一行压缩后的长度 PSB 中为 4 字节
+            // 长度都来自文件头 分配之前先确认文件中确实有这么多数据
+            let size_len = if is_psb { 4 } else { 2 };
+            if rows
+                .checked_mul(size_len)
+                .is_none_or(|len| len > reader.remaining())
+            {
+                return Err("PSD 文件不完整".to_string());
+            }
+            let mut row_sizes = Vec::with_capacity(rows);
+            for _ in 0..rows {
+                row_sizes.push(if is_psb {
+                    reader.u32()? as usize
+                } else {
+                    reader.u16()? as usize
+                });
+            }
+            // 压缩数据按最大展开倍数也不够时不可能解出完整的平面
+            let used_sizes = &row_sizes[..height as usize * used_channels];
+            let compressed_len = used_sizes
+                .iter()
+                .fold(0usize, |sum, size| sum.saturating_add(*size));
+            if compressed_len > reader.remaining()
+                || compressed_len.saturating_mul(PACKBITS_MAX_RATIO) < used_len
+            {
+                return Err("PSD 文件不完整".to_string());
+            }
+            let mut planes = Vec::with_capacity(used_len);
+            for size in used_sizes {
+                unpack_bits(reader.bytes(*size)?, row_len, &mut planes)?;
+            }
+            Cow::Owned(planes)
+        }
+        _ => return Err(format!("不支持的压缩方式: {compression}")),
+    };
+
+    let channel = |index: usize, pixel: usize| -> u8 {
+        let offset = index * plane_len + pixel * bytes_per_sample;
+        sample_to_u8(&planes[offset..offset + bytes_per_sample], depth)
+    };
+
+    let mut pixels = Vec::with_capacity(pixel_count * 4);
+    for i in 0..pixel_count {
+        let rgba = match color_mode {
+            COLOR_MODE_GRAYSCALE => {
+                let gray = channel(0, i);
+                let alpha = if channels > 1 { channel(1, i) } else { 255 };
+                [gray, gray, gray, alpha]
+            }
+            COLOR_MODE_INDEXED => {
+                // 调色板为 256 个 R 后接 256 个 G 再接 256 个 B
+                let index = channel(0, i) as usize;
+                let entry = |c: usize| palette.get(c * 256 + index).copied().unwrap_or(0);
+                [entry(0), entry(1), entry(2), 255]
+            }
+            COLOR_MODE_RGB => {
+                let alpha = if channels > 3 { channel(3, i) } else { 255 };
+                [channel(0, i), channel(1, i), channel(2, i), alpha]
+            }
+            _ => {
+                // CMYK 中数值 0 表示 100% 油墨 所以直接相乘即可
+                let k = channel(3, i) as u32;
+                let convert = |c: u8| (c as u32 * k / 255) as u8;
+                let alpha = if channels > 4 { channel(4, i) } else { 255 };
+                [
+                    convert(channel(0, i)),
+                    convert(channel(1, i)),
+                    convert(channel(2, i)),
+                    alpha,
+                ]
+            }
+        };
+        pixels.extend_from_slice(&rgba);
+    }
+
+    Ok((width, height, pixels))
+}
+
+/// PackBits 解压一行 追加到 output
+fn unpack_bits(input: &[u8], row_len: usize, output: &mut Vec<u8>) -> Result<(), String> {
+    let start = output.len();
+    let mut i = 0;
+    while i < input.len() && output.len() - start < row_len {
+        let header = input[i] as i8;
+        i += 1;
+        if header >= 0 {
+            // 后面 header + 1 个字节原样复制
+            let count = header as usize + 1;
+            let literal = input
+                .get(i..i + count)
+                .ok_or_else(|| "PSD RLE 数据损坏".to_string())?;
+            output.extend_from_slice(literal);
+            i += count;
+        } else if header != -128 {
+            // 下一个字节重复 1 - header 次
+            let value = *input.get(i).ok_or_else(|| "PSD RLE 数据损坏".to_string())?;
+            output.extend(std::iter::repeat_n(value, (1 - header as isize) as usize));
+            i += 1;
+        }
+    }
+
+    if output.len() - start != row_len {
+        return Err("PSD RLE 行长度不匹配".to_string());
+    }
+    Ok(())
+}
+
+// 把一个采样转换为 8 位
+fn sample_to_u8(sample: &[u8], depth: u16) -> u8 {
+    match depth {
+        8 => sample[0],
+        // 16 位取高字节
+        16 => sample[0],
+        _ => {
+            let value = f32::from_be_bytes([sample[0], sample[1], sample[2], sample[3]]);
+            (value.clamp(0.0, 1.0) * 255.0).round() as u8
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 拼出一个只有合并图像的 PSD / PSB 文件
+    fn psd(
+        version: u16,
+        channels: u16,
+        (width, height): (u32, u32),
+        depth: u16,
+        color_mode: u16,
+        palette: &[u8],
+        image_data: &[u8],
+    ) -> Vec<u8> {
+        let mut data = b"8BPS".to_vec();
+        data.extend_from_slice(&version.to_be_bytes());
+        data.extend_from_slice(&[0; 6]);
+        data.extend_from_slice(&channels.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&depth.to_be_bytes());
+        data.extend_from_slice(&color_mode.to_be_bytes());
+        data.extend_from_slice(&(palette.len() as u32).to_be_bytes());
+        data.extend_from_slice(palette);
+        // 图像资源、图层信息都为空
+        data.extend_from_slice(&0u32.to_be_bytes());
+        if version == 2 {
+            data.extend_from_slice(&0u64.to_be_bytes());
+        } else {
+            data.extend_from_slice(&0u32.to_be_bytes());
+        }
+        data.extend_from_slice(image_data);
+        data
+    }
+
+    fn raw(planes: &[u8]) -> Vec<u8> {
+        let mut data = COMPRESSION_RAW.to_be_bytes().to_vec();
+        data.extend_from_slice(planes);
+        data
+    }
+
+    #[test]
+    fn decodes_raw_rgba() {
+        // 2x1 R、G、B、A 四个平面
+        let file = psd(
+            1,
+            4,
+            (2, 1),
+            8,
+            COLOR_MODE_RGB,
+            &[],
+            &raw(&[10, 20, 30, 40, 50, 60, 255, 128]),
+        );
+        let (width, height, pixels) = decode_composite(&file).unwrap();
+        assert_eq!((width, height), (2, 1));
+        assert_eq!(pixels, vec![10, 30, 50, 255, 20, 40, 60, 128]);
+    }
+
+    #[test]
+    fn decodes_rle_grayscale_psb() {
+        // 3x2 两行都是 PackBits：第一行重复 3 次 7 第二行原样 1 2 3
+        let mut image_data = COMPRESSION_RLE.to_be_bytes().to_vec();
+        image_data.extend_from_slice(&2u32.to_be_bytes());
+        image_data.extend_from_slice(&4u32.to_be_bytes());
+        image_data.extend_from_slice(&[0xfe, 7, 2, 1, 2, 3]);
+        let file = psd(2, 1, (3, 2), 8, COLOR_MODE_GRAYSCALE, &[], &image_data);
+        let (_, _, pixels) = decode_composite(&file).unwrap();
+        let gray: Vec<u8> = pixels.chunks_exact(4).map(|p| p[0]).collect();
+        assert_eq!(gray, vec![7, 7, 7, 1, 2, 3]);
+        assert!(pixels.chunks_exact(4).all(|p| p[3] == 255));
+    }
+
+    #[test]
+    fn decodes_indexed_and_cmyk() {
+        let mut palette = vec![0u8; 768];
+        palette[1] = 200;
+        palette[256 + 1] = 100;
+        palette[512 + 1] = 50;
+        let file = psd(1, 1, (1, 1), 8, COLOR_MODE_INDEXED, &palette, &raw(&[1]));
+        assert_eq!(decode_composite(&file).unwrap().2, vec![200, 100, 50, 255]);
+
+        // 数值 255 表示没有油墨
+        let file = psd(
+            1,
+            4,
+            (1, 1),
+            8,
+            COLOR_MODE_CMYK,
+            &[],
+            &raw(&[255, 0, 255, 255]),
+        );
+        assert_eq!(decode_composite(&file).unwrap().2, vec![255, 0, 255, 255]);
+    }
+
+    #[test]
+    fn converts_high_bit_depths() {
+        let file = psd(
+            1,
+            1,
+            (1, 1),
+            16,
+            COLOR_MODE_GRAYSCALE,
+            &[],
+            &raw(&[0xab, 0xcd]),
+        );
+        assert_eq!(decode_composite(&file).unwrap().2[0], 0xab);
+        let file = psd(
+            1,
+            1,
+            (1, 1),
+            32,
+            COLOR_MODE_GRAYSCALE,
+            &[],
+            &raw(&0.5f32.to_be_bytes()),
+        );
+        assert_eq!(decode_composite(&file).unwrap().2[0], 128);
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        assert!(decode_composite(b"8BPX").is_err());
+        assert!(
+            decode_composite(&psd(3, 3, (1, 1), 8, COLOR_MODE_RGB, &[], &raw(&[0; 3]))).is_err()
+        );
+        // 尺寸为 0 或过大
+        assert!(decode_composite(&psd(1, 3, (0, 1), 8, COLOR_MODE_RGB, &[], &raw(&[]))).is_err());
+        assert!(decode_composite(&psd(
+            1,
+            3,
+            (1 << 16, 1 << 16),
+            8,
+            COLOR_MODE_RGB,
+            &[],
+            &raw(&[])
+        ))
+        .is_err());
+        // 通道数不够
+        assert!(
+            decode_composite(&psd(1, 2, (1, 1), 8, COLOR_MODE_RGB, &[], &raw(&[0; 2]))).is_err()
+        );
+        // 不支持的位深
+        assert!(
+            decode_composite(&psd(1, 1, (1, 1), 1, COLOR_MODE_GRAYSCALE, &[], &raw(&[0]))).is_err()
+        );
+        // 数据不完整
+        assert!(
+            decode_composite(&psd(1, 3, (4, 4), 8, COLOR_MODE_RGB, &[], &raw(&[0; 10]))).is_err()
+        );
+        // RLE 的行长度表和压缩数据不足以覆盖文件头声明的尺寸 分配之前拒绝
+        assert!(decode_composite(&psd(
+            1,
+            1,
+            (30000, 30000),
+            8,
+            COLOR_MODE_GRAYSCALE,
+            &[],
+            &[0, 1, 0, 2]
+        ))
+        .is_err());
+        let mut rle = vec![0, 1];
+        rle.extend([0, 2].repeat(1000));
+        rle.extend([0x81, 0].repeat(1000));
+        assert!(decode_composite(&psd(
+            1,
+            1,
+            (30000, 1000),
+            8,
+            COLOR_MODE_GRAYSCALE,
+            &[],
+            &rle
+        ))
+        .is_err());
+        // 未知的压缩方式
+        assert!(
+            decode_composite(&psd(1, 1, (1, 1), 8, COLOR_MODE_GRAYSCALE, &[], &[0, 2, 0])).is_err()
+        );
+    }
+
+    #[test]
+    fn unpack_bits_checks_row_length() {
+        let mut output = Vec::new();
+        // -128 不做任何事
+        unpack_bits(&[0x80, 1, 9, 8, 0xff, 5], 4, &mut output).unwrap();
+        assert_eq!(output, vec![9, 8, 5, 5]);
+        assert!(unpack_bits(&[0xfd, 1], 3, &mut Vec::new()).is_err());
+        assert!(unpack_bits(&[5, 1, 2], 6, &mut Vec::new()).is_err());
+        assert!(unpack_bits(&[0xff], 2, &mut Vec::new()).is_err());
+    }
+}
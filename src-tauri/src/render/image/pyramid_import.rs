@@ -0,0 +1,441 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::RgbaImage;
+use rayon::prelude::*;
+
+use super::cache::{chunk_hash_key, image_cache_dir, save_chunk_hashes};
+use super::chunk_order::{chunk_order, ordered_chunks};
+use super::chunk_processing::process_single_chunk_parallel;
+use super::decode_sandbox::is_decode_sandbox_enabled;
+use super::gpu_compute::downsample_half;
+use super::lod_pyramid::{pyramid_level_count, read_stored_region, write_lod_level_by_chunks};
+use super::preprocess_checkpoint::PreprocessCheckpoint;
+use super::preprocess_progress::set_preprocess_total;
+use super::preprocess_queue::ensure_not_cancelled;
+use super::preprocessing::{build_chunk_infos, write_cache_metadata};
+use super::proxy::proxy_cache_dir;
+use super::similarity::record_perceptual_hashes;
+use super::timing_breakdown::store_timing_breakdown;
+use super::types::{ChunkInfo, ImageMetadata, TimingBreakdown};
+use super::write_tracker::mark_chunk_written;
+use crate::utils::time::{Stopwatch, TimingSpan};
+
+// 导入已有的金字塔：源文件旁边已经有 Deep Zoom（DZI）金字塔时 直接用其中的瓦片生成 chunk
+// 例如 `slide.png` 旁边的 `slide.dzi` 和 `slide_files/` 目录（OpenSeadragon、vips dzsave 生成）
+// 每个 chunk 只解码覆盖它的瓦片 不需要解码整张图片 概览级别和感知哈希使用金字塔中的低分辨率层
+// 金字塔的尺寸与源文件不一致、或源文件比金字塔新时认为金字塔已过期 回到普通的预处理
+// chunk 的像素来自金字塔的瓦片 瓦片是 JPEG 等有损格式时与源文件会有细微差别
+// 分块的（多分辨率）TIFF 本身就是金字塔 同样通过这里导入（见 tiff_input）
+// 概览级别逐个 chunk 生成 金字塔中缺少的级别由已经写入的上一级缩小得到
+
+/// 可以按区域读取的已有金字塔（DZI 瓦片目录、分块 TIFF）
+pub trait TilePyramid: Sync {
+    /// 原图尺寸
+    fn size(&self) -> (u32, u32);
+
+    /// 日志中显示的说明
+    fn describe(&self) -> String;
+
+    /// 从原图尺寸的层中读取一个矩形区域
+    /// # Arguments
+    /// * `x` / `y` - 区域左上角（像素）
+    /// * `width` / `height` - 区域尺寸 需要在图片范围内
+    fn read_region(&self, x: u32, y: u32, width: u32, height: u32) -> Result<RgbaImage, String>;
+
+    /// 缩小 2^level 倍的层（即多分辨率金字塔的第 level 级）的尺寸 金字塔中没有这一层时返回 None
+    fn downscaled_size(&self, level: u32) -> Option<(u32, u32)>;
+
+    /// 从第 level 级中读取一个矩形区域 这一层需要存在（见 downscaled_size）
+    /// # Arguments
+    /// * `level` - 级别 n 为原图的 1/2^n
+    /// * `x` / `y` - 区域左上角（该级别中的像素）
+    /// * `width` / `height` - 区域尺寸 需要在该级别范围内
+    fn read_downscaled_region(
+        &self,
+        level: u32,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<RgbaImage, String>;
+}
+
+/// 源文件旁边的 Deep Zoom 金字塔
+pub struct DziPyramid {
+    tiles_dir: PathBuf,
+    tile_size: u32,
+    overlap: u32,
+    format: String,
+    width: u32,
+    height: u32,
+}
+
+impl DziPyramid {
+    // 最精细的层 即原图尺寸所在的层
+    fn max_level(&self) -> u32 {
+        let side = self.width.max(self.height).max(1);
+        u32::BITS - (side - 1).leading_zeros()
+    }
+
+    fn level_size(&self, level: u32) -> (u32, u32) {
+        let scale = 1u64 << (self.max_level() - level);
+        (
+            (self.width as u64).div_ceil(scale) as u32,
+            (self.height as u64).div_ceil(scale) as u32,
+        )
+    }
+
+    fn tile_path(&self, level: u32, col: u32, row: u32) -> PathBuf {
+        self.tiles_dir
+            .join(level.to_string())
+            .join(format!("{col}_{row}.{}", self.format))
+    }
+
+    // 拼出某一层中的一个区域 相邻瓦片重叠的部分像素相同 重复写入即可
+    fn read_level_region(
+        &self,
+        level: u32,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<RgbaImage, String> {
+        let (level_width, level_height) = self.level_size(level);
+        if width == 0 || height == 0 || x + width > level_width || y + height > level_height {
+            return Err(format!(
+                "区域 ({x}, {y}, {width}x{height}) 超出金字塔第 {level} 层范围 {level_width}x{level_height}"
+            ));
+        }
+        // 除第一行、第一列外 瓦片向左上方多出 overlap 个像素
+        let origin = |index: u32| match index {
+            0 => 0,
+            _ => index * self.tile_size - self.overlap,
+        };
+        let mut output = RgbaImage::new(width, height);
+        for row in y / self.tile_size..=(y + height - 1) / self.tile_size {
+            for col in x / self.tile_size..=(x + width - 1) / self.tile_size {
+                let path = self.tile_path(level, col, row);
+                let tile = image::open(&path)
+                    .map_err(|e| format!("读取金字塔瓦片失败 {}: {e}", path.display()))?
+                    .to_rgba8();
+                let (tile_x, tile_y) = (origin(col), origin(row));
+                let left = tile_x.max(x);
+                let top = tile_y.max(y);
+                let right = (tile_x + tile.width()).min(x + width);
+                let bottom = (tile_y + tile.height()).min(y + height);
+                for py in top..bottom {
+                    for px in left..right {
+                        output.put_pixel(px - x, py - y, *tile.get_pixel(px - tile_x, py - tile_y));
+                    }
+                }
+            }
+        }
+        Ok(output)
+    }
+}
+
+impl TilePyramid for DziPyramid {
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "DZI 金字塔 {} (瓦片 {}, 重叠 {}, 格式 {})",
+            self.tiles_dir.display(),
+            self.tile_size,
+            self.overlap,
+            self.format
+        )
+    }
+
+    fn read_region(&self, x: u32, y: u32, width: u32, height: u32) -> Result<RgbaImage, String> {
+        self.read_level_region(self.max_level(), x, y, width, height)
+    }
+
+    fn downscaled_size(&self, level: u32) -> Option<(u32, u32)> {
+        let dzi_level = self.max_level().checked_sub(level)?;
+        Some(self.level_size(dzi_level))
+    }
+
+    fn read_downscaled_region(
+        &self,
+        level: u32,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<RgbaImage, String> {
+        let dzi_level = self
+            .max_level()
+            .checked_sub(level)
+            .ok_or_else(|| format!("DZI 金字塔中没有第 {level} 级"))?;
+        self.read_level_region(dzi_level, x, y, width, height)
+    }
+}
+
+// 读取 XML 元素的属性 DZI 文件很小 结构固定 不需要完整的 XML 解析
+fn xml_attr(xml: &str, element: &str, name: &str) -> Option<String> {
+    let start = xml.find(&format!("<{element}"))?;
+    let tag = &xml[start..start + xml[start..].find('>')?];
+    [format!(" {name}=\""), format!(" {name}='")]
+        .iter()
+        .find_map(|prefix| {
+            let value_start = tag.find(prefix.as_str())? + prefix.len();
+            let quote = prefix.chars().last()?;
+            let value_len = tag[value_start..].find(quote)?;
+            Some(tag[value_start..value_start + value_len].to_string())
+        })
+}
+
+fn parse_dzi(content: &str, tiles_dir: PathBuf) -> Result<DziPyramid, String> {
+    let number = |element: &str, name: &str| -> Result<u32, String> {
+        xml_attr(content, element, name)
+            .ok_or_else(|| format!("DZI 文件缺少 {element}.{name}"))?
+            .trim()
+            .parse()
+            .map_err(|e| format!("DZI 文件的 {element}.{name} 无效: {e}"))
+    };
+    let pyramid = DziPyramid {
+        tiles_dir,
+        tile_size: number("Image", "TileSize")?,
+        overlap: number("Image", "Overlap")?,
+        format: xml_attr(content, "Image", "Format").ok_or("DZI 文件缺少 Image.Format")?,
+        width: number("Size", "Width")?,
+        height: number("Size", "Height")?,
+    };
+    if pyramid.tile_size == 0 || pyramid.width == 0 || pyramid.height == 0 {
+        return Err("DZI 文件的瓦片或图片尺寸为 0".to_string());
+    }
+    Ok(pyramid)
+}
+
+/// 查找源文件旁边可以使用的 DZI 金字塔（`<文件名>.dzi` 和 `<文件名>_files/`）
+/// 尺寸与源文件不一致、或源文件比金字塔新时返回 None
+/// 开启解码沙箱时瓦片只能在子进程中解码 同样返回 None
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Option<DziPyramid>` - 可以使用的金字塔
+pub fn find_dzi_pyramid(file_path: &str) -> Option<DziPyramid> {
+    if is_decode_sandbox_enabled() {
+        return None;
+    }
+    let source = Path::new(file_path);
+    let stem = source.file_stem()?.to_string_lossy().to_string();
+    let dzi_path = source.with_file_name(format!("{stem}.dzi"));
+    let tiles_dir = source.with_file_name(format!("{stem}_files"));
+    if !dzi_path.is_file() || !tiles_dir.is_dir() {
+        return None;
+    }
+
+    let pyramid = fs::read_to_string(&dzi_path)
+        .map_err(|e| format!("读取 DZI 文件失败: {e}"))
+        .and_then(|content| parse_dzi(&content, tiles_dir));
+    let pyramid = match pyramid {
+        Ok(pyramid) => pyramid,
+        Err(e) => {
+            println!("[RUST] 忽略金字塔 {}: {e}", dzi_path.display());
+            return None;
+        }
+    };
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    if modified(source) > modified(&dzi_path) {
+        println!(
+            "[RUST] 源文件比金字塔新，忽略金字塔: {}",
+            dzi_path.display()
+        );
+        return None;
+    }
+    match image::image_dimensions(file_path) {
+        Ok(size) if size == (pyramid.width, pyramid.height) => Some(pyramid),
+        Ok((width, height)) => {
+            println!(
+                "[RUST] 金字塔尺寸 {}x{} 与源文件 {width}x{height} 不一致，忽略金字塔: {}",
+                pyramid.width,
+                pyramid.height,
+                dzi_path.display()
+            );
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+// 已经写入缓存的级别 生成下一级时从这里读取
+struct StoredLevel {
+    dir: PathBuf,
+    chunk_size: (u32, u32),
+    size: (u32, u32),
+}
+
+/// 用已有的金字塔生成 chunk 缓存 代替解码整张源图片
+/// 与普通预处理一样支持断点 写入相同的元数据、chunk 哈希和感知哈希
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `pyramid` - 已有的金字塔 见 find_dzi_pyramid 和 open_tiled_tiff
+/// * `chunk_size_x` / `chunk_size_y` - chunk 尺寸
+/// # Returns
+/// * `Result<ImageMetadata, String>` - 图片元数据
+pub fn import_tile_pyramid(
+    file_path: &str,
+    pyramid: &impl TilePyramid,
+    chunk_size_x: u32,
+    chunk_size_y: u32,
+) -> Result<ImageMetadata, String> {
+    let span = TimingSpan::new("preprocess.pyramid_import");
+    let (total_width, total_height) = pyramid.size();
+    println!("[RUST] 使用已有的{}生成缓存", pyramid.describe());
+
+    let cache_dir_buf = image_cache_dir(file_path);
+    let cache_dir = cache_dir_buf.as_path();
+    fs::create_dir_all(cache_dir).map_err(|e| format!("创建缓存目录失败: {e}"))?;
+
+    let chunks = build_chunk_infos(total_width, total_height, chunk_size_x, chunk_size_y);
+    let checkpoint = PreprocessCheckpoint::open(
+        cache_dir,
+        file_path,
+        (total_width, total_height),
+        (chunk_size_x, chunk_size_y),
+    )?;
+    set_preprocess_total(file_path, chunks.len() as u32);
+
+    // 从金字塔读取一个 chunk 的区域并写入缓存 返回像素哈希
+    let import_chunk = |chunk_info: &ChunkInfo| -> Result<String, String> {
+        ensure_not_cancelled(file_path)?;
+        let hash = match checkpoint.completed_hash(chunk_info) {
+            Some(hash) => hash,
+            None => {
+                let region = pyramid.read_region(
+                    chunk_info.x,
+                    chunk_info.y,
+                    chunk_info.width,
+                    chunk_info.height,
+                )?;
+                let local_info = ChunkInfo {
+                    x: 0,
+                    y: 0,
+                    ..chunk_info.clone()
+                };
+                let hash = process_single_chunk_parallel(&region, &local_info, cache_dir)?;
+                checkpoint.record(chunk_info, &hash)?;
+                hash
+            }
+        };
+        mark_chunk_written(file_path, chunk_info.chunk_x, chunk_info.chunk_y);
+        Ok(hash)
+    };
+
+    // 按配置的顺序导入 chunk（见 chunk_order）
+    let col_count = total_width.div_ceil(chunk_size_x);
+    let chunk_results: Vec<(&ChunkInfo, Result<String, String>)> =
+        ordered_chunks(&chunks, col_count, chunk_order())
+            .into_iter()
+            .par_bridge()
+            .map(|chunk_info| (chunk_info, import_chunk(chunk_info)))
+            .collect();
+
+    let chunking_ms = span.elapsed_ms();
+    ensure_not_cancelled(file_path)?;
+    let mut chunk_hashes = HashMap::with_capacity(chunks.len());
+    for (chunk_info, result) in chunk_results {
+        let hash = result.map_err(|e| {
+            format!(
+                "Chunk ({}, {}) 导入失败: {e}",
+                chunk_info.chunk_x, chunk_info.chunk_y
+            )
+        })?;
+        chunk_hashes.insert(chunk_hash_key(chunk_info.chunk_x, chunk_info.chunk_y), hash);
+    }
+
+    // 概览级别逐个 chunk 写入 金字塔中有对应的层时按 chunk 读取这一层
+    // 没有的层由已经写入的上一级缩小 每个 chunk 只读取上一级中对应的区域 不需要整层在内存中
+    let levels_stopwatch = Stopwatch::start();
+    let level_count = pyramid_level_count(total_width, total_height, chunk_size_x, chunk_size_y);
+    let mut levels = Vec::with_capacity(level_count as usize);
+    let mut previous = StoredLevel {
+        dir: cache_dir_buf.clone(),
+        chunk_size: (chunk_size_x, chunk_size_y),
+        size: (total_width, total_height),
+    };
+    for level in 1..=level_count {
+        ensure_not_cancelled(file_path)?;
+        let descriptor = match pyramid.downscaled_size(level) {
+            Some(size) => {
+                write_lod_level_by_chunks(file_path, level, size, |x, y, width, height| {
+                    pyramid.read_downscaled_region(level, x, y, width, height)
+                })?
+            }
+            None => {
+                let (previous_width, previous_height) = previous.size;
+                let size = (previous_width.div_ceil(2), previous_height.div_ceil(2));
+                write_lod_level_by_chunks(file_path, level, size, |x, y, width, height| {
+                    let source = read_stored_region(
+                        &previous.dir,
+                        previous.chunk_size,
+                        x * 2,
+                        y * 2,
+                        (width * 2).min(previous_width - x * 2),
+                        (height * 2).min(previous_height - y * 2),
+                    )?;
+                    Ok(downsample_half(&source))
+                })?
+            }
+        };
+        previous = StoredLevel {
+            dir: proxy_cache_dir(file_path, descriptor.scale),
+            chunk_size: (descriptor.chunk_size_x, descriptor.chunk_size_y),
+            size: (descriptor.width, descriptor.height),
+        };
+        levels.push(descriptor);
+    }
+    let levels_ms = levels_stopwatch.elapsed_ms();
+
+    let metadata_write_stopwatch = Stopwatch::start();
+    save_chunk_hashes(cache_dir, &chunk_hashes)?;
+    let metadata = ImageMetadata {
+        total_width,
+        total_height,
+        chunk_size_x,
+        chunk_size_y,
+        col_count: total_width.div_ceil(chunk_size_x),
+        row_count: total_height.div_ceil(chunk_size_y),
+        chunks,
+        proxy_scale: None,
+        levels,
+        timings: None,
+        chunk_count: None,
+    };
+    write_cache_metadata(cache_dir, file_path, &metadata)?;
+    checkpoint.finish();
+    // 最粗的一层不超过一个完整分辨率的 chunk 直接用来计算感知哈希
+    let coarsest = read_stored_region(
+        &previous.dir,
+        previous.chunk_size,
+        0,
+        0,
+        previous.size.0,
+        previous.size.1,
+    )?;
+    record_perceptual_hashes(cache_dir, &coarsest)?;
+
+    let total_ms = span.elapsed_ms();
+    store_timing_breakdown(
+        file_path,
+        TimingBreakdown {
+            preprocessed: true,
+            chunking_ms: chunking_ms as u64,
+            levels_ms: levels_ms as u64,
+            metadata_write_ms: metadata_write_stopwatch.elapsed_ms() as u64,
+            total_ms: total_ms as u64,
+            ..TimingBreakdown::default()
+        },
+    );
+    println!(
+        "[RUST] 金字塔导入完成: {} 个 chunks (耗时: {total_ms}ms)",
+        metadata.chunks.len()
+    );
+    Ok(metadata)
+}
@@ -0,0 +1,237 @@
+use std::fs;
+
+use tauri::AppHandle;
+
+use super::cache::{check_file_cache_exists, load_cached_metadata};
+use super::errors::{localized_error, ErrorCode};
+use super::export::{
+    compose_region, emit_export_progress, enqueue_export, ExportKind, ExportTask,
+    EXPORT_STRIPE_HEIGHT,
+};
+use super::export_encoder::{TiffWriter, TIFF_TILE_SIZE};
+use super::types::{ImageMetadata, ImageRegion, TiffCompression};
+use crate::utils::time::Stopwatch;
+
+/// 导出多分辨率瓦片 TIFF（QuPath / ASAP 等工具可以直接打开）
+/// 第 0 层为原图 之后每层缩小一半 直到单个瓦片能放下为止 各层依次存放在 IFD 链中
+/// 任务加入后台导出队列 进度和结束事件与 export_region 相同
+/// 与 export_region 不同 取消后不保留断点 再次导出会从头开始
+/// # Arguments
+/// * `file_path` - 图片文件路径（需要已经预处理）
+/// * `out_path` - 输出文件路径
+/// * `compression` - 瓦片压缩方式 默认为 JPEG
+/// # Returns
+/// * `Result<String, String>` - 任务 ID
+#[tauri::command]
+pub fn export_pyramidal_tiff(
+    app: AppHandle,
+    file_path: String,
+    out_path: String,
+    compression: Option<TiffCompression>,
+) -> Result<String, String> {
+    if !check_file_cache_exists(&file_path) {
+        return Err(localized_error(ErrorCode::CacheMissing, &[]));
+    }
+    let compression = compression.unwrap_or(TiffCompression::Jpeg);
+    enqueue_export(
+        app,
+        file_path,
+        out_path,
+        ExportKind::PyramidalTiff(compression),
+    )
+}
+
+/// 各层尺寸 第 0 层为原图 逐层减半直到单个瓦片能放下为止
+pub(super) fn pyramid_level_sizes(width: u32, height: u32) -> Vec<(u32, u32)> {
+    let mut sizes = vec![(width, height)];
+    let (mut w, mut h) = (width, height);
+    while w > TIFF_TILE_SIZE || h > TIFF_TILE_SIZE {
+        w = w.div_ceil(2);
+        h = h.div_ceil(2);
+        sizes.push((w, h));
+    }
+    sizes
+}
+
+/// 接收金字塔各层像素行的输出端
+/// 每一层的行按从上到下的顺序到达 不同层之间交错
+pub(super) trait PyramidSink {
+    fn write_rows(&mut self, level: usize, pixels: &[u8]) -> Result<(), String>;
+}
+
+impl PyramidSink for TiffWriter {
+    fn write_rows(&mut self, level: usize, pixels: &[u8]) -> Result<(), String> {
+        TiffWriter::write_rows(self, level, pixels)
+    }
+}
+
+/// 从 chunk 缓存流式生成整个金字塔
+/// 原图按条带从 chunk 中拼出 每一层的行同时经 2x2 平均缩小后送入下一层
+/// 内存中只保留当前条带和每层待配对的一行
+/// # Arguments
+/// * `task` - 导出任务 用于检查取消和发送进度
+/// * `metadata` - 图片元数据
+/// * `level_sizes` - 各层尺寸 见 pyramid_level_sizes
+/// * `sink` - 输出端
+/// # Returns
+/// * `Result<bool, String>` - true 表示完成 false 表示被取消
+pub(super) fn stream_pyramid(
+    task: &ExportTask,
+    metadata: &ImageMetadata,
+    level_sizes: &[(u32, u32)],
+    sink: &mut dyn PyramidSink,
+) -> Result<bool, String> {
+    let width = metadata.total_width;
+    let height = metadata.total_height;
+    let mut downsamplers = level_downsamplers(level_sizes);
+
+    let total_stripes = height.div_ceil(EXPORT_STRIPE_HEIGHT);
+    for index in 0..total_stripes {
+        if task.cancel.load(std::sync::atomic::Ordering::Relaxed) {
+            return Ok(false);
+        }
+
+        let stripe_y = index * EXPORT_STRIPE_HEIGHT;
+        let stripe = ImageRegion {
+            x: 0,
+            y: stripe_y,
+            width,
+            height: EXPORT_STRIPE_HEIGHT.min(height - stripe_y),
+        };
+        let pixels = compose_region(&task.file_path, metadata, &stripe)?;
+        feed_level(sink, &mut downsamplers, 0, &pixels)?;
+
+        emit_export_progress(task, index + 1, total_stripes);
+    }
+
+    finish_levels(sink, &mut downsamplers)?;
+    Ok(true)
+}
+
+/// 执行多分辨率 TIFF 导出 所有层的瓦片交错写入同一个文件
+/// # Returns
+/// * `Result<bool, String>` - true 表示完成 false 表示被取消
+pub(super) fn run_pyramidal_export(
+    task: &ExportTask,
+    compression: TiffCompression,
+) -> Result<bool, String> {
+    let stopwatch = Stopwatch::start();
+    let metadata = load_cached_metadata(&task.file_path)?;
+    let level_sizes = pyramid_level_sizes(metadata.total_width, metadata.total_height);
+    println!(
+        "[RUST] 开始导出多分辨率 TIFF: {} {}x{} 共 {} 层 ({compression:?})",
+        task.output_path,
+        metadata.total_width,
+        metadata.total_height,
+        level_sizes.len()
+    );
+
+    let mut tiff = TiffWriter::create(&task.output_path, &level_sizes, compression)?;
+    if !stream_pyramid(task, &metadata, &level_sizes, &mut tiff)? {
+        drop(tiff);
+        let _ = fs::remove_file(&task.output_path);
+        println!("[RUST] 多分辨率 TIFF 导出已取消: {}", task.output_path);
+        return Ok(false);
+    }
+    tiff.finish()?;
+
+    println!(
+        "[RUST] 多分辨率 TIFF 导出完成: {} (耗时: {}ms)",
+        task.output_path,
+        stopwatch.elapsed_ms()
+    );
+    Ok(true)
+}
+
+/// 相邻两层之间的流式缩小器 第 i 个把第 i 层缩小为第 i + 1 层
+pub(super) fn level_downsamplers(level_sizes: &[(u32, u32)]) -> Vec<Downsampler> {
+    level_sizes
+        .windows(2)
+        .map(|pair| Downsampler::new(pair[0].0, pair[1].0))
+        .collect()
+}
+
+/// 把行写入某一层 并缩小后继续送入下一层
+pub(super) fn feed_level(
+    sink: &mut dyn PyramidSink,
+    downsamplers: &mut [Downsampler],
+    level: usize,
+    pixels: &[u8],
+) -> Result<(), String> {
+    sink.write_rows(level, pixels)?;
+    if let Some(downsampler) = downsamplers.get_mut(level) {
+        let rows = downsampler.push(pixels);
+        if !rows.is_empty() {
+            feed_level(sink, downsamplers, level + 1, &rows)?;
+        }
+    }
+    Ok(())
+}
+
+/// 第 0 层的行全部送入后调用 行数为奇数时每层最后还剩一行没有输出
+pub(super) fn finish_levels(
+    sink: &mut dyn PyramidSink,
+    downsamplers: &mut [Downsampler],
+) -> Result<(), String> {
+    for level in 0..downsamplers.len() {
+        let rows = downsamplers[level].finish();
+        if !rows.is_empty() {
+            feed_level(sink, downsamplers, level + 1, &rows)?;
+        }
+    }
+    Ok(())
+}
+
+/// 流式 2x2 平均缩小 每收到两行输出一行
+pub(super) struct Downsampler {
+    src_width: u32,
+    dst_width: u32,
+    // 等待配对的上一行
+    pending: Option<Vec<u8>>,
+}
+
+impl Downsampler {
+    fn new(src_width: u32, dst_width: u32) -> Self {
+        Self {
+            src_width,
+            dst_width,
+            pending: None,
+        }
+    }
+
+    fn push(&mut self, pixels: &[u8]) -> Vec<u8> {
+        let row_len = self.src_width as usize * 4;
+        let mut output = Vec::new();
+        for row in pixels.chunks_exact(row_len) {
+            match self.pending.take() {
+                Some(previous) => self.average_rows(&previous, row, &mut output),
+                None => self.pending = Some(row.to_vec()),
+            }
+        }
+        output
+    }
+
+    // 剩下的单独一行与自身平均
+    fn finish(&mut self) -> Vec<u8> {
+        let mut output = Vec::new();
+        if let Some(row) = self.pending.take() {
+            self.average_rows(&row, &row, &mut output);
+        }
+        output
+    }
+
+    fn average_rows(&self, top: &[u8], bottom: &[u8], output: &mut Vec<u8>) {
+        let last = self.src_width as usize - 1;
+        for x in 0..self.dst_width as usize {
+            let x0 = (x * 2).min(last) * 4;
+            let x1 = (x * 2 + 1).min(last) * 4;
+            for c in 0..4 {
+                let sum = top[x0 + c] as u32
+                    + top[x1 + c] as u32
+                    + bottom[x0 + c] as u32
+                    + bottom[x1 + c] as u32;
+                output.push(((sum + 2) / 4) as u8);
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::f64::consts::PI;
+
+use rayon::prelude::*;
+
+use super::cache::load_cached_metadata;
+use super::export::{compose_region, validate_region};
+use super::types::{ImageRegion, QualityMetrics};
+use crate::utils::time::Stopwatch;
+
+// 画质指标：清晰度（拉普拉斯方差）、噪声估计（Immerkær 快速噪声估计）和溢出比例
+// 按条带读取区域 十亿像素级的区域也不会一次占用过多内存
+// 卷积需要上下相邻的行 每个条带多读上下各一行
+
+// 每个条带的最大像素数
+const METRICS_STRIPE_PIXELS: u64 = 16 * 1024 * 1024;
+
+// 各项统计的累计值
+#[derive(Default)]
+struct MetricTotals {
+    laplacian_sum: f64,
+    laplacian_squares: f64,
+    noise_sum: f64,
+    interior: u64,
+    highlights: u64,
+    shadows: u64,
+}
+
+impl MetricTotals {
+    fn merge(mut self, other: MetricTotals) -> MetricTotals {
+        self.laplacian_sum += other.laplacian_sum;
+        self.laplacian_squares += other.laplacian_squares;
+        self.noise_sum += other.noise_sum;
+        self.interior += other.interior;
+        self.highlights += other.highlights;
+        self.shadows += other.shadows;
+        self
+    }
+}
+
+/// 计算区域的清晰度、噪声和溢出比例（需要先完成预处理）
+/// 清晰度和噪声只在亮度上计算 区域最外一圈像素不参与卷积
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `rect` - 区域 至少为 3x3
+/// # Returns
+/// * `Result<QualityMetrics, String>` - 画质指标
+#[tauri::command]
+pub fn get_quality_metrics(file_path: String, rect: ImageRegion) -> Result<QualityMetrics, String> {
+    let stopwatch = Stopwatch::start();
+    let metadata = load_cached_metadata(&file_path)?;
+    validate_region(&metadata, &rect)?;
+    if rect.width < 3 || rect.height < 3 {
+        return Err(format!("区域至少需要 3x3: {}x{}", rect.width, rect.height));
+    }
+
+    let width = rect.width as usize;
+    let bottom = rect.y + rect.height;
+    let stripe_rows =
+        (METRICS_STRIPE_PIXELS / rect.width as u64).clamp(1, rect.height as u64) as u32;
+    let mut totals = MetricTotals::default();
+    let mut y = rect.y;
+    while y < bottom {
+        let rows = stripe_rows.min(bottom - y);
+        let top = y.saturating_sub(1).max(rect.y);
+        let end = (y + rows + 1).min(bottom);
+        let stripe = ImageRegion {
+            x: rect.x,
+            y: top,
+            width: rect.width,
+            height: end - top,
+        };
+        let pixels = compose_region(&file_path, &metadata, &stripe)?;
+        let luma: Vec<f64> = pixels
+            .par_chunks_exact(4)
+            .map(|p| 0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64)
+            .collect();
+
+        let stripe_totals = (y..y + rows)
+            .into_par_iter()
+            .map(|row| {
+                let index = (row - top) as usize;
+                let mut totals = MetricTotals::default();
+                for p in pixels[index * width * 4..(index + 1) * width * 4].chunks_exact(4) {
+                    if p[..3].contains(&255) {
+                        totals.highlights += 1;
+                    }
+                    if p[..3].contains(&0) {
+                        totals.shadows += 1;
+                    }
+                }
+                // 区域最上和最下的行没有完整的邻域
+                if row == rect.y || row == bottom - 1 {
+                    return totals;
+                }
+                let (above, current, below) = (
+                    &luma[(index - 1) * width..index * width],
+                    &luma[index * width..(index + 1) * width],
+                    &luma[(index + 1) * width..(index + 2) * width],
+                );
+                for x in 1..width - 1 {
+                    let laplacian =
+                        above[x] + below[x] + current[x - 1] + current[x + 1] - 4.0 * current[x];
+                    // Immerkær 的噪声核 [1 -2 1; -2 4 -2; 1 -2 1] 对边缘和平滑渐变的响应很小
+                    let noise = above[x - 1] + above[x + 1] + below[x - 1] + below[x + 1]
+                        - 2.0 * (above[x] + below[x] + current[x - 1] + current[x + 1])
+                        + 4.0 * current[x];
+                    totals.laplacian_sum += laplacian;
+                    totals.laplacian_squares += laplacian * laplacian;
+                    totals.noise_sum += noise.abs();
+                    totals.interior += 1;
+                }
+                totals
+            })
+            .reduce(MetricTotals::default, MetricTotals::merge);
+        totals = totals.merge(stripe_totals);
+        y += rows;
+    }
+
+    let pixel_count = rect.width as u64 * rect.height as u64;
+    let interior = totals.interior as f64;
+    let mean = totals.laplacian_sum / interior;
+    let metrics = QualityMetrics {
+        sharpness: (totals.laplacian_squares / interior - mean * mean).max(0.0),
+        noise: (PI / 2.0).sqrt() * totals.noise_sum / (6.0 * interior),
+        clipped_highlights_percent: totals.highlights as f64 * 100.0 / pixel_count as f64,
+        clipped_shadows_percent: totals.shadows as f64 * 100.0 / pixel_count as f64,
+        pixel_count,
+    };
+    println!(
+        "[RUST] 画质指标: 清晰度 {:.2} 噪声 {:.2} (耗时: {}ms)",
+        metrics.sharpness,
+        metrics.noise,
+        stopwatch.elapsed_ms()
+    );
+    Ok(metrics)
+}
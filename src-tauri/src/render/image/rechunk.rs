@@ -0,0 +1,222 @@
+use memmap2::Mmap;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::utils::time::Stopwatch;
+
+use super::access_stats::reset_access_stats;
+use super::cache::{
+    chunk_hash_key, discard_preprocess_output, ensure_cache_writable, image_cache_dir,
+    load_cached_metadata, save_chunk_hashes,
+};
+use super::chunk_processing::hash_pixels;
+use super::chunk_repair::validate_chunk_data;
+use super::config::{MAX_CHUNK_SIZE, MIN_CHUNK_SIZE};
+use super::preprocess_dedup::run_exclusive;
+use super::preprocessing::{
+    build_chunk_infos, preprocess_and_cache_chunks_with_size, write_cache_metadata,
+};
+use super::scheduler::run_scheduled;
+use super::types::{ChunkInfo, ImageMetadata, TaskKind};
+use super::write_tracker::begin_cache_write;
+
+// 重新分块时新 chunk 文件的临时目录 全部生成成功后才替换旧文件
+pub(super) const RECHUNK_TMP_DIR: &str = "rechunk_tmp";
+
+/// 按新的 chunk 尺寸重新分块
+/// 优先从现有 chunk 文件拼接出新的网格 不需要重新解码源图片
+/// 现有缓存不完整时退回到从源文件重新预处理
+/// 同一张图片正在加载或重建时等它结束 重新分块期间 chunk 请求返回 [CHUNK_NOT_READY]
+/// 不会按旧网格生成缺失的 chunk
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `new_chunk_size` - 新的 chunk 边长（正方形）
+/// # Returns
+/// * `Result<ImageMetadata, String>` - 新的图片元数据或错误信息
+#[tauri::command]
+pub fn rechunk_image(file_path: String, new_chunk_size: u32) -> Result<ImageMetadata, String> {
+    ensure_cache_writable("重新分块")?;
+    if !(MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&new_chunk_size) {
+        return Err(format!(
+            "chunk 尺寸 {new_chunk_size} 超出范围 {MIN_CHUNK_SIZE}..={MAX_CHUNK_SIZE}"
+        ));
+    }
+    run_exclusive(&file_path, || rechunk_exclusive(&file_path, new_chunk_size))
+}
+
+// 独占执行的重新分块 整个过程中标记为正在写入缓存
+fn rechunk_exclusive(file_path: &str, new_chunk_size: u32) -> Result<ImageMetadata, String> {
+    let _writing = begin_cache_write(file_path);
+    let metadata = load_cached_metadata(file_path)?;
+    if metadata.chunk_size_x == new_chunk_size && metadata.chunk_size_y == new_chunk_size {
+        println!("[RUST] chunk 尺寸未变化，无需重新分块");
+        return Ok(metadata);
+    }
+
+    let stopwatch = Stopwatch::start();
+    println!(
+        "[RUST] 开始重新分块: {}x{} -> {new_chunk_size}x{new_chunk_size}",
+        metadata.chunk_size_x, metadata.chunk_size_y
+    );
+
+    let result = run_scheduled(TaskKind::Preprocess, file_path, || {
+        rebuild_from_chunks(file_path, &metadata, new_chunk_size)
+    })?;
+    let new_metadata = match result {
+        Ok(new_metadata) => new_metadata,
+        Err(e) => {
+            // 临时目录和旧网格的 chunk 文件名可能不会被新网格覆盖 删除所有预处理的产物后重新预处理
+            println!("[RUST] 无法从现有 chunk 重新分块 ({e})，改为从源文件重新预处理");
+            discard_preprocess_output(file_path)?;
+            preprocess_and_cache_chunks_with_size(file_path, new_chunk_size, new_chunk_size)?
+        }
+    };
+
+    println!(
+        "[RUST] 重新分块完成: {}x{} chunks (耗时: {}ms)",
+        new_metadata.col_count,
+        new_metadata.row_count,
+        stopwatch.elapsed_ms()
+    );
+
+    Ok(new_metadata)
+}
+
+// 从现有 chunk 文件拼接出新的网格
+fn rebuild_from_chunks(
+    file_path: &str,
+    metadata: &ImageMetadata,
+    new_chunk_size: u32,
+) -> Result<ImageMetadata, String> {
+    let cache_dir = image_cache_dir(file_path);
+
+    // 映射所有旧 chunk 文件 只读映射不会把整个文件读进内存
+    let mut old_chunks: HashMap<(u32, u32), Mmap> = HashMap::new();
+    for info in &metadata.chunks {
+        let chunk_path = cache_dir.join(format!("chunk_{}_{}.bin", info.chunk_x, info.chunk_y));
+        let file = fs::File::open(&chunk_path)
+            .map_err(|e| format!("打开旧 chunk 文件失败: {e} ({chunk_path:?})"))?;
+        let mmap = unsafe { Mmap::map(&file) }
+            .map_err(|e| format!("映射旧 chunk 文件失败: {e} ({chunk_path:?})"))?;
+        validate_chunk_data(&mmap)?;
+        old_chunks.insert((info.chunk_x, info.chunk_y), mmap);
+    }
+
+    let tmp_dir = cache_dir.join(RECHUNK_TMP_DIR);
+    if tmp_dir.exists() {
+        fs::remove_dir_all(&tmp_dir).map_err(|e| format!("清理临时目录失败: {e}"))?;
+    }
+    fs::create_dir_all(&tmp_dir).map_err(|e| format!("创建临时目录失败: {e}"))?;
+
+    let new_chunks = build_chunk_infos(
+        metadata.total_width,
+        metadata.total_height,
+        new_chunk_size,
+        new_chunk_size,
+    );
+
+    let new_hashes = new_chunks
+        .par_iter()
+        .map(|info| assemble_chunk(info, metadata, &old_chunks, &tmp_dir))
+        .collect::<Result<Vec<String>, String>>()?;
+
+    // 所有新 chunk 都生成成功后再替换 先释放映射 (Windows 上被映射的文件无法删除)
+    // 旧网格中按需生成的 chunk 可能不在元数据的列表中 删除所有 chunk 文件
+    drop(old_chunks);
+    remove_chunk_files(&cache_dir)?;
+    for info in &new_chunks {
+        let name = format!("chunk_{}_{}.bin", info.chunk_x, info.chunk_y);
+        fs::rename(tmp_dir.join(&name), cache_dir.join(&name))
+            .map_err(|e| format!("移动新 chunk 文件失败: {e}"))?;
+    }
+    fs::remove_dir_all(&tmp_dir).map_err(|e| format!("清理临时目录失败: {e}"))?;
+
+    let new_metadata = ImageMetadata {
+        total_width: metadata.total_width,
+        total_height: metadata.total_height,
+        chunk_size_x: new_chunk_size,
+        chunk_size_y: new_chunk_size,
+        col_count: metadata.total_width.div_ceil(new_chunk_size),
+        row_count: metadata.total_height.div_ceil(new_chunk_size),
+        chunks: new_chunks,
+        proxy_scale: None,
+        // 概览级别有自己的网格 不受完整分辨率 chunk 尺寸的影响
+        levels: metadata.levels.clone(),
+        timings: None,
+        chunk_count: None,
+    };
+    write_cache_metadata(&cache_dir, file_path, &new_metadata)?;
+
+    // 网格变了 旧的 chunk 哈希全部失效
+    let hashes: HashMap<String, String> = new_metadata
+        .chunks
+        .iter()
+        .zip(new_hashes)
+        .map(|(info, hash)| (chunk_hash_key(info.chunk_x, info.chunk_y), hash))
+        .collect();
+    save_chunk_hashes(&cache_dir, &hashes)?;
+    reset_access_stats(file_path);
+
+    Ok(new_metadata)
+}
+
+// 拼接单个新 chunk 逐行从覆盖它的旧 chunk 中复制像素
+fn assemble_chunk(
+    info: &ChunkInfo,
+    metadata: &ImageMetadata,
+    old_chunks: &HashMap<(u32, u32), Mmap>,
+    tmp_dir: &Path,
+) -> Result<String, String> {
+    let row_bytes = info.width as usize * 4;
+    let mut data = Vec::with_capacity(8 + row_bytes * info.height as usize);
+    data.extend_from_slice(&info.width.to_be_bytes());
+    data.extend_from_slice(&info.height.to_be_bytes());
+
+    for y in info.y..info.y + info.height {
+        let old_chunk_y = y / metadata.chunk_size_y;
+        let local_y = y % metadata.chunk_size_y;
+
+        // 一行可能横跨多个旧 chunk
+        let mut x = info.x;
+        let x_end = info.x + info.width;
+        while x < x_end {
+            let old_chunk_x = x / metadata.chunk_size_x;
+            let old_chunk = old_chunks
+                .get(&(old_chunk_x, old_chunk_y))
+                .ok_or_else(|| format!("缺少旧 chunk ({old_chunk_x}, {old_chunk_y})"))?;
+            let old_width =
+                u32::from_be_bytes([old_chunk[0], old_chunk[1], old_chunk[2], old_chunk[3]]);
+
+            let local_x = x % metadata.chunk_size_x;
+            let copy_width = (old_width - local_x).min(x_end - x);
+            let start = 8 + (local_y as usize * old_width as usize + local_x as usize) * 4;
+            let end = start + copy_width as usize * 4;
+            data.extend_from_slice(&old_chunk[start..end]);
+
+            x += copy_width;
+        }
+    }
+
+    let pixel_hash = hash_pixels(&data[8..]);
+    let chunk_path = tmp_dir.join(format!("chunk_{}_{}.bin", info.chunk_x, info.chunk_y));
+    fs::write(&chunk_path, data).map_err(|e| {
+        format!(
+            "写入新 chunk ({}, {}) 失败: {e}",
+            info.chunk_x, info.chunk_y
+        )
+    })?;
+    Ok(pixel_hash)
+}
+
+// 删除缓存目录中的所有 chunk 文件
+fn remove_chunk_files(cache_dir: &Path) -> Result<(), String> {
+    let entries = fs::read_dir(cache_dir).map_err(|e| format!("读取缓存目录失败: {e}"))?;
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        if entry.file_name().to_string_lossy().starts_with("chunk_") {
+            fs::remove_file(entry.path()).map_err(|e| format!("删除旧 chunk 文件失败: {e}"))?;
+        }
+    }
+    Ok(())
+}
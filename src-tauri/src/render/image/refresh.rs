@@ -0,0 +1,216 @@
+use image::{imageops, RgbaImage};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::utils::time::Stopwatch;
+
+use super::buffer_pool::get_buffer_pool;
+use super::cache::{
+    chunk_hash_key, discard_preprocess_output, ensure_cache_writable, image_cache_dir,
+    load_cached_metadata, load_chunk_hashes, save_chunk_hashes,
+};
+use super::chunk_processing::{
+    chunk_memory_cache, extract_chunk_pixels, hash_pixels, process_single_chunk_parallel,
+};
+use super::preprocessing::{
+    decode_source_image, preprocess_and_cache_chunks_with_size, write_cache_metadata,
+};
+use super::region_decode::{decode_source_region, supports_region_decode};
+use super::scheduler::run_scheduled;
+use super::similarity::record_perceptual_hashes;
+use super::stripe_processing::{for_each_png_band, should_stream_png};
+use super::types::{CacheRefreshReport, ChunkInfo, ImageMetadata, TaskKind};
+
+// 逐段刷新时拼接的缩略图宽度 用于计算感知哈希
+const OVERVIEW_WIDTH: u32 = 256;
+
+/// 源图片有小幅修改后增量刷新缓存
+/// 按 chunk 比较新源图片的像素哈希与记录的哈希 只重新生成真正变化的 chunk
+/// 适合反复导出同一张渲染图的场景 比 force_preprocess_chunks 整体重建快得多
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<CacheRefreshReport, String>` - 刷新结果
+#[tauri::command]
+pub fn refresh_cache(file_path: String) -> Result<CacheRefreshReport, String> {
+    run_scheduled(TaskKind::Preprocess, &file_path, || {
+        refresh_cache_sync(&file_path)
+    })?
+}
+
+/// 增量刷新缓存（同步版本 供后台任务复用）
+/// 源图片按 chunk 行逐段读取（按行流式解码的 PNG、可以读取任意区域的格式）
+/// 其他格式整张解码一次 内存中只有转换后的 RGBA
+pub fn refresh_cache_sync(file_path: &str) -> Result<CacheRefreshReport, String> {
+    ensure_cache_writable("刷新缓存")?;
+    let stopwatch = Stopwatch::start();
+    println!("[RUST] 开始增量刷新缓存: {file_path}");
+
+    let metadata = load_cached_metadata(file_path)?;
+    let (width, height) =
+        image::image_dimensions(file_path).map_err(|e| format!("读取图片尺寸失败: {e}"))?;
+
+    // 尺寸变化时网格对不上 只能完整重建
+    if width != metadata.total_width || height != metadata.total_height {
+        println!(
+            "[RUST] 源图片尺寸由 {}x{} 变为 {width}x{height}，进行完整重建",
+            metadata.total_width, metadata.total_height
+        );
+        discard_preprocess_output(file_path)?;
+        let new_metadata = preprocess_and_cache_chunks_with_size(
+            file_path,
+            metadata.chunk_size_x,
+            metadata.chunk_size_y,
+        )?;
+        let changed_chunks = new_metadata
+            .chunks
+            .iter()
+            .map(|c| (c.chunk_x, c.chunk_y))
+            .collect();
+        return Ok(CacheRefreshReport {
+            file_path: file_path.to_string(),
+            full_rebuild: true,
+            changed_chunks,
+            unchanged_chunks: 0,
+            metadata: new_metadata,
+        });
+    }
+
+    let cache_dir = image_cache_dir(file_path);
+    let mut hashes = load_chunk_hashes(&cache_dir);
+    let mut written = Vec::new();
+    let band_height = metadata.chunk_size_y;
+    if should_stream_png(file_path) || supports_region_decode(file_path) {
+        let mut overview = BandOverview::new(width, height);
+        let mut refresh_band = |band_y: u32, band: RgbaImage| -> Result<(), String> {
+            written.extend(refresh_chunks_in_band(
+                file_path, &metadata, &hashes, &cache_dir, band_y, &band,
+            )?);
+            overview.add(band_y, &band);
+            Ok(())
+        };
+        if should_stream_png(file_path) {
+            for_each_png_band(file_path, band_height, refresh_band)?;
+        } else {
+            for band_y in (0..height).step_by(band_height as usize) {
+                let rows = band_height.min(height - band_y);
+                refresh_band(
+                    band_y,
+                    decode_source_region(file_path, 0, band_y, width, rows)?,
+                )?;
+            }
+        }
+        // 源文件已经变化 更新内容指纹
+        write_cache_metadata(&cache_dir, file_path, &metadata)?;
+        record_perceptual_hashes(&cache_dir, &overview.image)?;
+    } else {
+        let rgba_img = decode_source_image(file_path)?.into_rgba8();
+        written = refresh_chunks_in_band(file_path, &metadata, &hashes, &cache_dir, 0, &rgba_img)?;
+        write_cache_metadata(&cache_dir, file_path, &metadata)?;
+        record_perceptual_hashes(&cache_dir, &rgba_img)?;
+    }
+
+    let mut changed_chunks = Vec::new();
+    for (chunk_x, chunk_y, hash) in written {
+        hashes.insert(chunk_hash_key(chunk_x, chunk_y), hash);
+        changed_chunks.push((chunk_x, chunk_y));
+    }
+    if !changed_chunks.is_empty() {
+        save_chunk_hashes(&cache_dir, &hashes)?;
+    }
+
+    let unchanged_chunks = (metadata.chunks.len() - changed_chunks.len()) as u32;
+    println!(
+        "[RUST] 增量刷新完成: {} 个 chunk 变化, {} 个未变化 (耗时: {}ms)",
+        changed_chunks.len(),
+        unchanged_chunks,
+        stopwatch.elapsed_ms()
+    );
+
+    Ok(CacheRefreshReport {
+        file_path: file_path.to_string(),
+        full_rebuild: false,
+        changed_chunks,
+        unchanged_chunks,
+        metadata,
+    })
+}
+
+// 并行计算一段中每个 chunk 的新哈希 只有变化的才写文件
+// band 是源图片从 band_y 开始的若干行 返回重新写入的 chunk 和新的哈希
+fn refresh_chunks_in_band(
+    file_path: &str,
+    metadata: &ImageMetadata,
+    hashes: &HashMap<String, String>,
+    cache_dir: &Path,
+    band_y: u32,
+    band: &RgbaImage,
+) -> Result<Vec<(u32, u32, String)>, String> {
+    let band_end = band_y + band.height();
+    let written = metadata
+        .chunks
+        .par_iter()
+        .filter(|chunk_info| chunk_info.y >= band_y && chunk_info.y + chunk_info.height <= band_end)
+        .map(|chunk_info| {
+            let local_info = ChunkInfo {
+                y: chunk_info.y - band_y,
+                ..chunk_info.clone()
+            };
+            let pixels = extract_chunk_pixels(
+                band,
+                local_info.x,
+                local_info.y,
+                local_info.width,
+                local_info.height,
+            );
+            let new_hash = hash_pixels(&pixels);
+            get_buffer_pool().release(pixels);
+            let key = chunk_hash_key(chunk_info.chunk_x, chunk_info.chunk_y);
+            if hashes.get(&key) == Some(&new_hash) {
+                return Ok(None);
+            }
+            let written_hash = process_single_chunk_parallel(band, &local_info, cache_dir)?;
+            chunk_memory_cache().invalidate_chunk(
+                file_path,
+                chunk_info.chunk_x,
+                chunk_info.chunk_y,
+            );
+            Ok(Some((chunk_info.chunk_x, chunk_info.chunk_y, written_hash)))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(written.into_iter().flatten().collect())
+}
+
+// 逐段刷新时把每一段缩小后拼接成缩略图 不需要整张图片在内存中
+struct BandOverview {
+    image: RgbaImage,
+    source_height: u32,
+    scale: f64,
+}
+
+impl BandOverview {
+    fn new(width: u32, height: u32) -> Self {
+        let overview_width = width.min(OVERVIEW_WIDTH);
+        let scale = overview_width as f64 / width as f64;
+        let overview_height = ((height as f64 * scale).round() as u32).max(1);
+        Self {
+            image: RgbaImage::new(overview_width, overview_height),
+            source_height: height,
+            scale,
+        }
+    }
+
+    fn add(&mut self, band_y: u32, band: &RgbaImage) {
+        let top = (band_y as f64 * self.scale).round() as u32;
+        let bottom = if band_y + band.height() >= self.source_height {
+            self.image.height()
+        } else {
+            ((band_y + band.height()) as f64 * self.scale).round() as u32
+        };
+        if bottom > top {
+            let small = imageops::thumbnail(band, self.image.width(), bottom - top);
+            imageops::replace(&mut self.image, &small, 0, top as i64);
+        }
+    }
+}
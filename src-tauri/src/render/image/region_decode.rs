@@ -0,0 +1,176 @@
+use std::fs::File;
+use std::io::BufReader;
+
+use image::codecs::bmp::BmpDecoder;
+use image::{ColorType, ImageDecoder, ImageDecoderRect, ImageFormat, RgbaImage};
+
+use super::decode_sandbox::is_decode_sandbox_enabled;
+use super::errors::{localized_error, ErrorCode};
+use super::preprocessing::{decode_source_image, detect_source_format};
+use super::pyramid_import::{find_dzi_pyramid, TilePyramid};
+use super::tiff_input::open_tiled_tiff;
+
+// 只解码源图片的一个区域 用于按需生成单个 chunk（缓存被清理、chunk 被淘汰或损坏）
+// 0. 源文件旁边有 DZI 金字塔时只读取覆盖区域的瓦片（见 pyramid_import） 分块 TIFF 只读取覆盖区域的块（见 tiff_input）
+// 1. BMP 支持随机访问 直接读取矩形区域
+// 2. PNG 不支持随机访问 按行流式解码到区域的最后一行为止 只保留区域内的像素 不需要整张图的内存
+// 3. 其他格式（以及隔行扫描的 PNG）整张解码后裁剪
+// 开启解码沙箱时源文件不在本进程中解析 同样整张解码（在子进程中）后裁剪
+
+fn open_source(file_path: &str) -> Result<BufReader<File>, String> {
+    File::open(file_path).map(BufReader::new).map_err(|e| {
+        localized_error(
+            ErrorCode::FileOpenFailed,
+            &[("error", &e), ("path", &file_path)],
+        )
+    })
+}
+
+fn decode_failed(format: &str, e: impl std::fmt::Display) -> String {
+    localized_error(
+        ErrorCode::DecodeFailed,
+        &[("format", &format), ("error", &e)],
+    )
+}
+
+/// 解码源图片中的一个矩形区域 转换为 RGBA8
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `x` / `y` - 区域左上角（像素）
+/// * `width` / `height` - 区域尺寸 需要在图片范围内
+/// # Returns
+/// * `Result<RgbaImage, String>` - 区域的像素
+pub fn decode_source_region(
+    file_path: &str,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<RgbaImage, String> {
+    if is_decode_sandbox_enabled() {
+        return decode_full_and_crop(file_path, x, y, width, height);
+    }
+    if let Some(pyramid) = find_dzi_pyramid(file_path) {
+        return pyramid.read_region(x, y, width, height);
+    }
+    if let Some(tiff) = open_tiled_tiff(file_path) {
+        return tiff.read_region(x, y, width, height);
+    }
+    let region = match detect_source_format(file_path)? {
+        ImageFormat::Png => decode_png_band(file_path, x, y, width, height)?,
+        ImageFormat::Bmp => Some(decode_bmp_rect(file_path, x, y, width, height)?),
+        _ => None,
+    };
+    match region {
+        Some(region) => Ok(region),
+        None => decode_full_and_crop(file_path, x, y, width, height),
+    }
+}
+
+/// 判断源图片是否可以直接读取任意区域（金字塔、分块 TIFF、BMP）不需要从头解码
+/// PNG 读取靠下的区域需要解码前面所有的行 逐段处理整张图片时应该按行流式读取
+pub fn supports_region_decode(file_path: &str) -> bool {
+    !is_decode_sandbox_enabled()
+        && (find_dzi_pyramid(file_path).is_some()
+            || open_tiled_tiff(file_path).is_some()
+            || detect_source_format(file_path) == Ok(ImageFormat::Bmp))
+}
+
+// 整张解码后裁剪出区域
+fn decode_full_and_crop(
+    file_path: &str,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<RgbaImage, String> {
+    let img = decode_source_image(file_path)?;
+    if x + width > img.width() || y + height > img.height() {
+        return Err(format!(
+            "区域 ({x}, {y}, {width}x{height}) 超出图片范围 {}x{}",
+            img.width(),
+            img.height()
+        ));
+    }
+    Ok(img.crop_imm(x, y, width, height).to_rgba8())
+}
+
+// 按行解码 PNG 隔行扫描的图片无法按行读取 返回 None
+fn decode_png_band(
+    file_path: &str,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<Option<RgbaImage>, String> {
+    let mut decoder = png::Decoder::new(open_source(file_path)?);
+    // 调色板、低位深展开为 8 位 16 位截断为 8 位
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| decode_failed("PNG", e))?;
+    let info = reader.info();
+    if info.interlaced {
+        return Ok(None);
+    }
+    if x + width > info.width || y + height > info.height {
+        return Err(format!(
+            "区域 ({x}, {y}, {width}x{height}) 超出图片范围 {}x{}",
+            info.width, info.height
+        ));
+    }
+    let channels = match reader.output_color_type().0 {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        png::ColorType::Indexed => return Ok(None),
+    };
+
+    let mut output = RgbaImage::new(width, height);
+    let mut row_index = 0;
+    while row_index < y + height {
+        let Some(row) = reader.next_row().map_err(|e| decode_failed("PNG", e))? else {
+            return Err(decode_failed("PNG", "图片数据提前结束"));
+        };
+        if row_index >= y {
+            let data = &row.data()[x as usize * channels..(x + width) as usize * channels];
+            for (px, pixel) in data.chunks_exact(channels).enumerate() {
+                let rgba = match channels {
+                    1 => [pixel[0], pixel[0], pixel[0], 255],
+                    2 => [pixel[0], pixel[0], pixel[0], pixel[1]],
+                    3 => [pixel[0], pixel[1], pixel[2], 255],
+                    _ => [pixel[0], pixel[1], pixel[2], pixel[3]],
+                };
+                output.put_pixel(px as u32, row_index - y, image::Rgba(rgba));
+            }
+        }
+        row_index += 1;
+    }
+    Ok(Some(output))
+}
+
+// 直接读取 BMP 的矩形区域
+fn decode_bmp_rect(
+    file_path: &str,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<RgbaImage, String> {
+    let mut decoder =
+        BmpDecoder::new(open_source(file_path)?).map_err(|e| decode_failed("BMP", e))?;
+    let color_type = decoder.color_type();
+    let mut buffer =
+        vec![0u8; width as usize * height as usize * color_type.bytes_per_pixel() as usize];
+    decoder
+        .read_rect(x, y, width, height, &mut buffer)
+        .map_err(|e| decode_failed("BMP", e))?;
+    let region = match color_type {
+        ColorType::Rgb8 => image::RgbImage::from_raw(width, height, buffer)
+            .map(|rgb| image::DynamicImage::ImageRgb8(rgb).to_rgba8()),
+        ColorType::Rgba8 => RgbaImage::from_raw(width, height, buffer),
+        ColorType::L8 => image::GrayImage::from_raw(width, height, buffer)
+            .map(|gray| image::DynamicImage::ImageLuma8(gray).to_rgba8()),
+        other => return Err(decode_failed("BMP", format!("不支持的颜色类型 {other:?}"))),
+    };
+    region.ok_or_else(|| decode_failed("BMP", "区域数据长度不匹配"))
+}
@@ -0,0 +1,505 @@
+use std::collections::hash_map::RandomState;
+use std::env;
+use std::fs;
+use std::hash::BuildHasher;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use base64::Engine;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tauri::AppHandle;
+
+use super::access_stats::record_chunk_access;
+use super::arrow_export::encode_chunks_arrow;
+use super::backpressure::ChunkRequestPermit;
+use super::cache::{check_file_cache_exists, load_cached_metadata};
+use super::chunk_processing::load_chunk_bytes;
+use super::commands::open_user_image;
+use super::export::{cancel_export, export_region, is_export_job_active};
+use super::preprocess_queue::{enqueue_preprocess, is_preprocess_pending};
+use super::scheduler::run_scheduled;
+use super::types::{ExportQuality, ImageRegion, RpcServerStatus, TaskKind};
+use super::utils::app_data_subdir;
+use crate::utils::time::get_time;
+
+// 本地 JSON-RPC 控制接口：外部脚本（例如 Python 分析笔记本）在界面打开时驱动后端
+// 与界面共用同一份缓存、预处理队列和导出队列
+// 协议：只监听 127.0.0.1 的 TCP 连接 每行一个 JSON-RPC 2.0 请求 每行一个响应
+// 每个连接先调用 authenticate 传入令牌 令牌和端口写在应用数据目录的 rpc/server.json 中
+
+// 设置后启动时自动开启 RPC 服务 值为端口号 0 表示由系统分配
+pub const RPC_PORT_ENV: &str = "IMAGES_GL_RPC_PORT";
+
+// 端口和令牌的记录文件 位于应用数据目录的 rpc 子目录下
+const RPC_INFO_FILE: &str = "server.json";
+// 单个请求的最大字节数
+const MAX_REQUEST_BYTES: u64 = 1024 * 1024;
+
+// JSON-RPC 错误码
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+// 后端操作失败 message 为后端返回的错误信息
+const BACKEND_ERROR: i64 = -32000;
+const UNAUTHORIZED: i64 = -32001;
+
+// 支持的方法
+const METHODS: &[&str] = &[
+    "authenticate",
+    "list_methods",
+    "preprocess",
+    "preprocess_status",
+    "get_metadata",
+    "get_chunk",
+    "export_region",
+    "export_chunks_arrow",
+    "export_status",
+    "cancel_export",
+];
+
+struct RpcServer {
+    port: u16,
+    token: String,
+    info_path: Option<PathBuf>,
+    stop: Arc<AtomicBool>,
+}
+
+static RPC_SERVER: Mutex<Option<RpcServer>> = Mutex::new(None);
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<String> for RpcError {
+    fn from(message: String) -> Self {
+        Self::new(BACKEND_ERROR, message)
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenParams {
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct FileParams {
+    file_path: String,
+}
+
+#[derive(Deserialize)]
+struct PreprocessParams {
+    file_path: String,
+    // true 时加入后台预处理队列后立即返回
+    #[serde(default)]
+    background: bool,
+}
+
+#[derive(Deserialize)]
+struct ChunkParams {
+    file_path: String,
+    chunk_x: u32,
+    chunk_y: u32,
+}
+
+#[derive(Deserialize)]
+struct ExportParams {
+    file_path: String,
+    region: ImageRegion,
+    output_path: String,
+    #[serde(default)]
+    quality: Option<ExportQuality>,
+}
+
+#[derive(Deserialize)]
+struct ArrowParams {
+    file_path: String,
+    rect: ImageRegion,
+    // 写入的文件路径 不传时以 base64 返回数据
+    out_path: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JobParams {
+    job_id: String,
+}
+
+// 生成连接令牌 RandomState 的种子由系统随机数初始化 其他用户无法猜到
+fn generate_token(port: u16) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&RandomState::new().hash_one(port).to_le_bytes());
+    hasher.update(&RandomState::new().hash_one(get_time()).to_le_bytes());
+    hasher.update(&std::process::id().to_le_bytes());
+    hasher.finalize().to_hex()[..32].to_string()
+}
+
+// 服务信息中有令牌 只允许当前用户读取
+// 已有的文件可能是旧版本以默认权限创建的 先删除再以 0600 新建 Windows 上应用数据目录本身只属于当前用户
+fn write_private_file(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let _ = fs::remove_file(path);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(content)
+}
+
+// 比较令牌 耗时与第一个不同字符的位置无关 不能通过响应时间逐位猜出令牌
+fn token_matches(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn status_of(server: Option<&RpcServer>) -> RpcServerStatus {
+    match server {
+        Some(server) => RpcServerStatus {
+            running: true,
+            port: Some(server.port),
+            token: Some(server.token.clone()),
+            info_path: server
+                .info_path
+                .as_ref()
+                .map(|path| path.to_string_lossy().to_string()),
+        },
+        None => RpcServerStatus::default(),
+    }
+}
+
+/// 启动本地 JSON-RPC 服务 已经启动时返回当前状态
+/// # Arguments
+/// * `port` - 监听端口 不传或为 0 时由系统分配
+/// # Returns
+/// * `Result<RpcServerStatus, String>` - 服务状态 包含端口和连接令牌
+#[tauri::command]
+pub fn start_rpc_server(app: AppHandle, port: Option<u16>) -> Result<RpcServerStatus, String> {
+    let mut server = RPC_SERVER
+        .lock()
+        .map_err(|e| format!("获取 RPC 服务锁失败: {e}"))?;
+    if server.is_some() {
+        return Ok(status_of(server.as_ref()));
+    }
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port.unwrap_or(0)))
+        .map_err(|e| format!("启动 RPC 服务失败: {e}"))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| format!("获取 RPC 服务端口失败: {e}"))?
+        .port();
+    let token = generate_token(port);
+
+    // 记录端口和令牌 外部脚本从这里读取 写入失败不影响服务本身
+    let info_path = app_data_subdir(&app, "rpc").and_then(|dir| {
+        let path = dir.join(RPC_INFO_FILE);
+        let info = json!({
+            "port": port,
+            "token": token,
+            "pid": std::process::id(),
+        });
+        write_private_file(&path, info.to_string().as_bytes())
+            .map_err(|e| format!("写入 RPC 服务信息失败: {e}"))?;
+        Ok(path)
+    });
+    let info_path = match info_path {
+        Ok(path) => Some(path),
+        Err(e) => {
+            println!("[RUST] {e}");
+            None
+        }
+    };
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let accept_stop = stop.clone();
+    let accept_token = token.clone();
+    thread::Builder::new()
+        .name("rpc-server".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                if accept_stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let app = app.clone();
+                let token = accept_token.clone();
+                let stop = accept_stop.clone();
+                let spawn_result = thread::Builder::new()
+                    .name("rpc-connection".to_string())
+                    .spawn(move || handle_connection(app, stream, &token, &stop));
+                if let Err(e) = spawn_result {
+                    println!("[RUST] 启动 RPC 连接线程失败: {e}");
+                }
+            }
+            println!("[RUST] RPC 服务已停止");
+        })
+        .map_err(|e| format!("启动 RPC 服务线程失败: {e}"))?;
+
+    println!("[RUST] RPC 服务已启动: 127.0.0.1:{port}");
+    *server = Some(RpcServer {
+        port,
+        token,
+        info_path,
+        stop,
+    });
+    Ok(status_of(server.as_ref()))
+}
+
+/// 停止本地 JSON-RPC 服务 已建立的连接在下一个请求时断开
+#[tauri::command]
+pub fn stop_rpc_server() -> Result<RpcServerStatus, String> {
+    let server = RPC_SERVER
+        .lock()
+        .map_err(|e| format!("获取 RPC 服务锁失败: {e}"))?
+        .take();
+    if let Some(server) = server {
+        server.stop.store(true, Ordering::Relaxed);
+        // 连接一次唤醒阻塞在 accept 上的线程
+        let _ = TcpStream::connect((Ipv4Addr::LOCALHOST, server.port));
+        if let Some(path) = &server.info_path {
+            let _ = fs::remove_file(path);
+        }
+    }
+    Ok(RpcServerStatus::default())
+}
+
+/// 获取本地 JSON-RPC 服务状态
+#[tauri::command]
+pub fn get_rpc_server_status() -> Result<RpcServerStatus, String> {
+    let server = RPC_SERVER
+        .lock()
+        .map_err(|e| format!("获取 RPC 服务锁失败: {e}"))?;
+    Ok(status_of(server.as_ref()))
+}
+
+/// 设置了 IMAGES_GL_RPC_PORT 时在启动时开启 RPC 服务
+pub fn start_rpc_server_from_env(app: &AppHandle) {
+    let Ok(value) = env::var(RPC_PORT_ENV) else {
+        return;
+    };
+    let port = match value.trim().parse::<u16>() {
+        Ok(port) => port,
+        Err(e) => {
+            println!("[RUST] {RPC_PORT_ENV} 不是有效的端口号: {value} ({e})");
+            return;
+        }
+    };
+    if let Err(e) = start_rpc_server(app.clone(), Some(port)) {
+        println!("[RUST] {e}");
+    }
+}
+
+// 处理一个连接上的所有请求 连接断开、请求过大或服务停止时返回
+fn handle_connection(app: AppHandle, stream: TcpStream, token: &str, stop: &AtomicBool) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(stream);
+    let mut authenticated = false;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        match reader
+            .by_ref()
+            .take(MAX_REQUEST_BYTES + 1)
+            .read_line(&mut line)
+        {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+        if stop.load(Ordering::Relaxed) {
+            return;
+        }
+        if line.len() as u64 > MAX_REQUEST_BYTES {
+            let error = RpcError::new(INVALID_REQUEST, "请求过大");
+            let _ = write_response(&mut writer, Value::Null, Err(error));
+            return;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let request: Value = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                let error = RpcError::new(PARSE_ERROR, format!("解析请求失败: {e}"));
+                if write_response(&mut writer, Value::Null, Err(error)).is_err() {
+                    return;
+                }
+                continue;
+            }
+        };
+        // 没有 id 的是通知 执行但不回复
+        let id = request.get("id").cloned();
+        let result = handle_request(&app, request, token, &mut authenticated);
+        if let Some(id) = id {
+            if write_response(&mut writer, id, result).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn handle_request(
+    app: &AppHandle,
+    request: Value,
+    token: &str,
+    authenticated: &mut bool,
+) -> Result<Value, RpcError> {
+    let method = request
+        .get("method")
+        .and_then(|m| m.as_str())
+        .ok_or_else(|| RpcError::new(INVALID_REQUEST, "缺少 method"))?
+        .to_string();
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+    if method == "authenticate" {
+        let params: TokenParams = parse_params(params)?;
+        if !token_matches(&params.token, token) {
+            return Err(RpcError::new(UNAUTHORIZED, "令牌错误"));
+        }
+        *authenticated = true;
+        return Ok(Value::Bool(true));
+    }
+    if !*authenticated {
+        return Err(RpcError::new(UNAUTHORIZED, "需要先调用 authenticate"));
+    }
+    dispatch(app, &method, params)
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params)
+        .map_err(|e| RpcError::new(INVALID_PARAMS, format!("参数错误: {e}")))
+}
+
+fn to_value<T: serde::Serialize>(value: T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|e| RpcError::from(format!("序列化结果失败: {e}")))
+}
+
+// 执行一个方法 与对应的 Tauri 命令行为一致
+fn dispatch(app: &AppHandle, method: &str, params: Value) -> Result<Value, RpcError> {
+    match method {
+        "list_methods" => to_value(METHODS),
+        "preprocess" => {
+            let params: PreprocessParams = parse_params(params)?;
+            if params.background {
+                let queued = enqueue_preprocess(app, &params.file_path)?;
+                return Ok(json!({ "queued": queued }));
+            }
+            to_value(open_user_image(&params.file_path)?)
+        }
+        "preprocess_status" => {
+            let params: FileParams = parse_params(params)?;
+            Ok(json!({
+                "pending": is_preprocess_pending(&params.file_path),
+                "cached": check_file_cache_exists(&params.file_path),
+            }))
+        }
+        "get_metadata" => {
+            let params: FileParams = parse_params(params)?;
+            to_value(load_cached_metadata(&params.file_path)?)
+        }
+        "get_chunk" => {
+            let params: ChunkParams = parse_params(params)?;
+            record_chunk_access(&params.file_path, params.chunk_x, params.chunk_y);
+            let _permit = ChunkRequestPermit::acquire(&params.file_path)?;
+            let data = run_scheduled(TaskKind::Read, &params.file_path.clone(), || {
+                load_chunk_bytes(
+                    params.chunk_x,
+                    params.chunk_y,
+                    params.file_path,
+                    false,
+                    Some(app),
+                )
+            })??;
+            // 数据已经校验过 至少有 8 字节的头部
+            let width = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+            let height = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+            Ok(json!({
+                "width": width,
+                "height": height,
+                "pixels": base64::engine::general_purpose::STANDARD.encode(&data[8..]),
+            }))
+        }
+        "export_region" => {
+            let params: ExportParams = parse_params(params)?;
+            let job_id = export_region(
+                app.clone(),
+                params.file_path,
+                params.region,
+                params.output_path,
+                params.quality,
+            )?;
+            Ok(json!({ "job_id": job_id }))
+        }
+        "export_chunks_arrow" => {
+            let params: ArrowParams = parse_params(params)?;
+            let (data, rows) = encode_chunks_arrow(&params.file_path, &params.rect)?;
+            match params.out_path {
+                Some(out_path) => {
+                    fs::write(&out_path, &data).map_err(|e| format!("写入 Arrow 文件失败: {e}"))?;
+                    Ok(json!({ "rows": rows, "path": out_path }))
+                }
+                None => Ok(json!({
+                    "rows": rows,
+                    "data": base64::engine::general_purpose::STANDARD.encode(&data),
+                })),
+            }
+        }
+        "export_status" => {
+            let params: JobParams = parse_params(params)?;
+            Ok(json!({ "active": is_export_job_active(&params.job_id) }))
+        }
+        "cancel_export" => {
+            let params: JobParams = parse_params(params)?;
+            cancel_export(params.job_id)?;
+            Ok(Value::Bool(true))
+        }
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("不支持的方法: {method}"),
+        )),
+    }
+}
+
+fn write_response(
+    writer: &mut TcpStream,
+    id: Value,
+    result: Result<Value, RpcError>,
+) -> std::io::Result<()> {
+    let response = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": error.code, "message": error.message },
+        }),
+    };
+    let mut line = response.to_string();
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+    writer.flush()
+}
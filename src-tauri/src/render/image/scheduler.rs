@@ -0,0 +1,254 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Condvar, Mutex, OnceLock};
+
+use super::config::get_thread_pool;
+use super::errors::{localized_error, ErrorCode};
+use super::types::{SchedulerKindStatus, SchedulerStatus, TaskKind};
+
+// 任务调度：chunk 读取、预处理、导出和分析任务共用全局线程池 由调度器决定谁先执行
+// 1. 优先级：读取 > 分析 > 预处理 > 导出 后台任务最多占用一半的执行名额 始终给读取留出位置
+// 2. 公平：优先级相同时先执行正在运行任务最少的图片 一张大图的任务不会挤占其他图片
+// 3. 有界：每类任务的排队数有上限 超出时立即返回 [BUSY] 错误 不会无限堆积线程
+// 4. 取消：排队中的任务可以按图片取消 已经开始执行的任务不受影响
+// 调度器只负责准入 任务本身在全局线程池中执行 内部仍可以用 rayon 并行
+
+// 每类任务最多排队的数量
+const MAX_QUEUED_READS: usize = 512;
+const MAX_QUEUED_BACKGROUND: usize = 64;
+
+static SCHEDULER: OnceLock<Scheduler> = OnceLock::new();
+
+struct QueuedTask {
+    id: u64,
+    kind: TaskKind,
+    file_path: String,
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    next_id: u64,
+    // 按加入顺序排列
+    queued: Vec<QueuedTask>,
+    // 被取消但等待线程还没发现的任务
+    cancelled: HashSet<u64>,
+    // 每类任务、每张图片正在执行的数量
+    running: HashMap<TaskKind, usize>,
+    running_per_image: HashMap<String, usize>,
+}
+
+struct Scheduler {
+    state: Mutex<SchedulerState>,
+    changed: Condvar,
+    capacity: usize,
+}
+
+// 数值越小越先执行
+fn priority(kind: TaskKind) -> u8 {
+    match kind {
+        TaskKind::Read => 0,
+        TaskKind::Analysis => 1,
+        TaskKind::Preprocess => 2,
+        TaskKind::Export => 3,
+    }
+}
+
+fn max_queued(kind: TaskKind) -> usize {
+    match kind {
+        TaskKind::Read => MAX_QUEUED_READS,
+        _ => MAX_QUEUED_BACKGROUND,
+    }
+}
+
+fn get_scheduler() -> &'static Scheduler {
+    SCHEDULER.get_or_init(|| Scheduler {
+        state: Mutex::new(SchedulerState::default()),
+        changed: Condvar::new(),
+        capacity: get_thread_pool().current_num_threads().max(1),
+    })
+}
+
+impl SchedulerState {
+    fn running_total(&self) -> usize {
+        self.running.values().sum()
+    }
+
+    fn running_background(&self) -> usize {
+        self.running
+            .iter()
+            .filter(|(kind, _)| **kind != TaskKind::Read)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    // 下一个可以开始的任务 没有空闲名额时为 None
+    fn next_task(&self, capacity: usize) -> Option<u64> {
+        if self.running_total() >= capacity {
+            return None;
+        }
+        let background_full = self.running_background() >= (capacity / 2).max(1);
+        self.queued
+            .iter()
+            .enumerate()
+            .filter(|(_, task)| task.kind == TaskKind::Read || !background_full)
+            .min_by_key(|(order, task)| {
+                let running = self
+                    .running_per_image
+                    .get(&task.file_path)
+                    .copied()
+                    .unwrap_or(0);
+                (priority(task.kind), running, *order)
+            })
+            .map(|(_, task)| task.id)
+    }
+
+    fn finish(&mut self, kind: TaskKind, file_path: &str) {
+        if let Some(count) = self.running.get_mut(&kind) {
+            *count = count.saturating_sub(1);
+        }
+        if let Some(count) = self.running_per_image.get_mut(file_path) {
+            *count -= 1;
+            if *count == 0 {
+                self.running_per_image.remove(file_path);
+            }
+        }
+    }
+}
+
+// 任务结束（包括 panic）时归还执行名额
+struct RunningGuard<'a> {
+    kind: TaskKind,
+    file_path: &'a str,
+}
+
+impl Drop for RunningGuard<'_> {
+    fn drop(&mut self) {
+        let scheduler = get_scheduler();
+        let mut state = scheduler.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.finish(self.kind, self.file_path);
+        scheduler.changed.notify_all();
+    }
+}
+
+/// 通过调度器在全局线程池中执行任务 排队等待执行名额 直到任务完成才返回
+/// 已经在线程池中执行的任务（嵌套调用或 rayon 子任务）直接执行 避免占着名额等待自己
+/// # Arguments
+/// * `kind` - 任务类型 决定优先级
+/// * `file_path` - 任务所属的图片 用于公平调度和取消
+/// * `task` - 要执行的任务
+/// # Returns
+/// * `Result<T, String>` - 任务的返回值 队列已满或被取消时返回错误
+pub fn run_scheduled<T: Send>(
+    kind: TaskKind,
+    file_path: &str,
+    task: impl FnOnce() -> T + Send,
+) -> Result<T, String> {
+    let pool = get_thread_pool();
+    if pool.current_thread_index().is_some() {
+        return Ok(task());
+    }
+
+    let scheduler = get_scheduler();
+    let mut state = scheduler
+        .state
+        .lock()
+        .map_err(|e| format!("获取调度器锁失败: {e}"))?;
+    let queued = state.queued.iter().filter(|t| t.kind == kind).count();
+    if queued >= max_queued(kind) {
+        return Err(localized_error(
+            ErrorCode::Busy,
+            &[("limit", &max_queued(kind))],
+        ));
+    }
+    let id = state.next_id;
+    state.next_id += 1;
+    state.queued.push(QueuedTask {
+        id,
+        kind,
+        file_path: file_path.to_string(),
+    });
+
+    loop {
+        if state.cancelled.remove(&id) {
+            return Err(format!("任务已取消: {file_path}"));
+        }
+        if state.next_task(scheduler.capacity) == Some(id) {
+            state.queued.retain(|t| t.id != id);
+            *state.running.entry(kind).or_insert(0) += 1;
+            *state
+                .running_per_image
+                .entry(file_path.to_string())
+                .or_insert(0) += 1;
+            break;
+        }
+        state = scheduler
+            .changed
+            .wait(state)
+            .map_err(|e| format!("获取调度器锁失败: {e}"))?;
+    }
+    drop(state);
+    // 开始执行后排在后面的任务可能也可以开始了（例如名额还有空余）
+    scheduler.changed.notify_all();
+
+    let _guard = RunningGuard { kind, file_path };
+    Ok(pool.install(task))
+}
+
+/// 取消图片排队中的任务 已经开始执行的任务不受影响
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `kind` - 只取消这一类任务 为空时取消所有类型
+/// # Returns
+/// * `Result<usize, String>` - 取消的任务数
+#[tauri::command]
+pub fn cancel_scheduled_tasks(file_path: String, kind: Option<TaskKind>) -> Result<usize, String> {
+    let scheduler = get_scheduler();
+    let mut state = scheduler
+        .state
+        .lock()
+        .map_err(|e| format!("获取调度器锁失败: {e}"))?;
+    let matches =
+        |task: &QueuedTask| task.file_path == file_path && kind.is_none_or(|k| k == task.kind);
+    let cancelled: Vec<u64> = state
+        .queued
+        .iter()
+        .filter(|task| matches(task))
+        .map(|task| task.id)
+        .collect();
+    state.queued.retain(|task| !matches(task));
+    state.cancelled.extend(&cancelled);
+    scheduler.changed.notify_all();
+    if !cancelled.is_empty() {
+        println!(
+            "[RUST] 已取消 {} 个排队中的任务: {file_path}",
+            cancelled.len()
+        );
+    }
+    Ok(cancelled.len())
+}
+
+/// 获取调度器的状态：执行名额和每类任务正在执行、排队的数量
+#[tauri::command]
+pub fn get_scheduler_status() -> Result<SchedulerStatus, String> {
+    let scheduler = get_scheduler();
+    let state = scheduler
+        .state
+        .lock()
+        .map_err(|e| format!("获取调度器锁失败: {e}"))?;
+    let tasks = [
+        TaskKind::Read,
+        TaskKind::Analysis,
+        TaskKind::Preprocess,
+        TaskKind::Export,
+    ]
+    .into_iter()
+    .map(|kind| SchedulerKindStatus {
+        kind,
+        running: state.running.get(&kind).copied().unwrap_or(0) as u32,
+        queued: state.queued.iter().filter(|t| t.kind == kind).count() as u32,
+    })
+    .collect();
+    Ok(SchedulerStatus {
+        capacity: scheduler.capacity as u32,
+        tasks,
+    })
+}
@@ -0,0 +1,128 @@
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Window};
+
+use super::commands::load_user_image;
+use super::types::{ImageMetadata, MonitorInfo};
+use super::utils::{app_data_subdir, save_rgba_png};
+use super::window_state::set_window_image;
+use crate::utils::time::get_time;
+
+// 截图在应用数据目录下的保存位置
+const SCREENSHOT_DIR: &str = "screenshots";
+
+/// 列出可以截图的显示器
+#[tauri::command]
+pub fn list_monitors() -> Result<Vec<MonitorInfo>, String> {
+    backend::list_monitors()
+}
+
+/// 截取整个显示器并在查看器中打开
+/// 截图保存为应用数据目录下的 PNG 文件 之后与普通图片一样预处理和分块
+/// 保存后的路径会记录为调用窗口当前打开的图片 前端可以通过 get_window_state 取得
+/// # Arguments
+/// * `monitor` - 显示器名称 不传时截取主显示器
+#[tauri::command]
+pub fn capture_screen(
+    app: AppHandle,
+    window: Window,
+    monitor: Option<String>,
+) -> Result<ImageMetadata, String> {
+    let file_path = save_screenshot(&app, monitor.as_deref())?;
+    let file_path = file_path.to_string_lossy().to_string();
+
+    let metadata = load_user_image(&file_path)?;
+    set_window_image(window.label(), &file_path);
+    Ok(metadata)
+}
+
+/// 截图并保存为 PNG 文件
+/// # Arguments
+/// * `app` - 应用句柄 用于定位应用数据目录
+/// * `monitor` - 显示器名称
+/// # Returns
+/// * `Result<PathBuf, String>` - 保存后的文件路径或错误信息
+fn save_screenshot(app: &AppHandle, monitor: Option<&str>) -> Result<PathBuf, String> {
+    let (width, height, pixels) = backend::capture(monitor)?;
+    println!("[RUST] 截图完成: {width}x{height}");
+
+    let dir = app_data_subdir(app, SCREENSHOT_DIR)?;
+
+    let file_path = dir.join(format!("screenshot_{}.png", get_time()));
+    save_rgba_png(&file_path, width, height, pixels)?;
+
+    println!("[RUST] 截图已保存: {}", file_path.display());
+    Ok(file_path)
+}
+
+#[cfg(feature = "screen-capture")]
+mod backend {
+    use xcap::Monitor;
+
+    use super::super::types::MonitorInfo;
+
+    pub fn list_monitors() -> Result<Vec<MonitorInfo>, String> {
+        let monitors = Monitor::all().map_err(|e| format!("获取显示器列表失败: {e}"))?;
+        monitors.iter().map(monitor_info).collect()
+    }
+
+    /// 截取显示器 返回宽、高和 RGBA 像素
+    pub fn capture(monitor: Option<&str>) -> Result<(u32, u32, Vec<u8>), String> {
+        let monitor = select_monitor(monitor)?;
+        let capture = monitor
+            .capture_image()
+            .map_err(|e| format!("截图失败: {e}"))?;
+        // xcap 使用的 image 版本与本项目不同 通过原始 RGBA 数据转换
+        Ok((capture.width(), capture.height(), capture.into_raw()))
+    }
+
+    fn monitor_info(monitor: &Monitor) -> Result<MonitorInfo, String> {
+        let err = |e: xcap::XCapError| format!("读取显示器信息失败: {e}");
+        Ok(MonitorInfo {
+            name: monitor.name().map_err(err)?,
+            width: monitor.width().map_err(err)?,
+            height: monitor.height().map_err(err)?,
+            scale_factor: monitor.scale_factor().map_err(err)?,
+            is_primary: monitor.is_primary().map_err(err)?,
+        })
+    }
+
+    /// 选择要截图的显示器
+    /// 指定名称时按名称匹配 否则取主显示器 没有主显示器时取第一个
+    fn select_monitor(name: Option<&str>) -> Result<Monitor, String> {
+        let monitors = Monitor::all().map_err(|e| format!("获取显示器列表失败: {e}"))?;
+
+        let found = match name {
+            Some(name) => monitors
+                .into_iter()
+                .find(|m| m.name().map(|n| n == name).unwrap_or(false)),
+            None => {
+                let primary = monitors
+                    .iter()
+                    .position(|m| m.is_primary().unwrap_or(false))
+                    .unwrap_or(0);
+                monitors.into_iter().nth(primary)
+            }
+        };
+
+        found.ok_or_else(|| match name {
+            Some(name) => format!("未找到显示器: {name}"),
+            None => "没有可用的显示器".to_string(),
+        })
+    }
+}
+
+#[cfg(not(feature = "screen-capture"))]
+mod backend {
+    use super::super::types::MonitorInfo;
+
+    const DISABLED: &str = "未启用截图支持 请使用 --features screen-capture 重新编译";
+
+    pub fn list_monitors() -> Result<Vec<MonitorInfo>, String> {
+        Err(DISABLED.to_string())
+    }
+
+    pub fn capture(_monitor: Option<&str>) -> Result<(u32, u32, Vec<u8>), String> {
+        Err(DISABLED.to_string())
+    }
+}
@@ -0,0 +1,199 @@
+// 安全范围书签：macOS 沙盒中通过对话框选择的文件只在本次运行期间可以访问
+// 重启后需要通过打开时创建的书签重新取得访问权限 否则缓存过的图片也无法再打开
+// 书签随最近打开的图片一起保存（见 startup_preload） 启动时解析并开始访问
+// 访问权限保持到进程退出 其他平台上书签为空 解析总是失败
+
+/// 解析书签的结果
+pub struct ResolvedBookmark {
+    /// 书签指向的文件路径 文件被移动或改名后与保存时不同
+    pub file_path: String,
+    /// 书签已经过期 需要用新路径重新创建
+    pub stale: bool,
+}
+
+/// 为文件创建安全范围书签 需要在还能访问文件时调用（例如刚从对话框打开）
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Option<Vec<u8>>` - 书签数据 当前平台不需要或创建失败时为 None
+#[cfg(target_os = "macos")]
+pub fn create_bookmark(file_path: &str) -> Option<Vec<u8>> {
+    macos::create_bookmark(file_path)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn create_bookmark(_file_path: &str) -> Option<Vec<u8>> {
+    None
+}
+
+/// 解析书签并开始访问它指向的文件
+/// # Arguments
+/// * `bookmark` - create_bookmark 返回的书签数据
+/// # Returns
+/// * `Option<ResolvedBookmark>` - 书签指向的文件 解析失败或文件已删除时为 None
+#[cfg(target_os = "macos")]
+pub fn resolve_bookmark(bookmark: &[u8]) -> Option<ResolvedBookmark> {
+    macos::resolve_bookmark(bookmark)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn resolve_bookmark(_bookmark: &[u8]) -> Option<ResolvedBookmark> {
+    None
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use std::ffi::{c_void, CStr};
+    use std::os::raw::c_char;
+    use std::ptr;
+
+    use super::ResolvedBookmark;
+
+    type CFTypeRef = *const c_void;
+    type CFAllocatorRef = *const c_void;
+    type CFURLRef = *const c_void;
+    type CFDataRef = *const c_void;
+    type CFArrayRef = *const c_void;
+    type CFErrorRef = *const c_void;
+    type CFIndex = isize;
+    type CFOptionFlags = usize;
+    type Boolean = u8;
+
+    // CFURLBookmarkCreationOptions / CFURLBookmarkResolutionOptions
+    const BOOKMARK_CREATION_WITH_SECURITY_SCOPE: CFOptionFlags = 1 << 11;
+    const BOOKMARK_RESOLUTION_WITH_SECURITY_SCOPE: CFOptionFlags = 1 << 10;
+    // 文件路径的最大长度（PATH_MAX）
+    const MAX_PATH_BYTES: usize = 1024;
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFURLCreateFromFileSystemRepresentation(
+            allocator: CFAllocatorRef,
+            buffer: *const u8,
+            length: CFIndex,
+            is_directory: Boolean,
+        ) -> CFURLRef;
+        fn CFURLCreateBookmarkData(
+            allocator: CFAllocatorRef,
+            url: CFURLRef,
+            options: CFOptionFlags,
+            resource_properties: CFArrayRef,
+            relative_to: CFURLRef,
+            error: *mut CFErrorRef,
+        ) -> CFDataRef;
+        fn CFURLCreateByResolvingBookmarkData(
+            allocator: CFAllocatorRef,
+            bookmark: CFDataRef,
+            options: CFOptionFlags,
+            relative_to: CFURLRef,
+            resource_properties: CFArrayRef,
+            is_stale: *mut Boolean,
+            error: *mut CFErrorRef,
+        ) -> CFURLRef;
+        fn CFURLStartAccessingSecurityScopedResource(url: CFURLRef) -> Boolean;
+        fn CFURLGetFileSystemRepresentation(
+            url: CFURLRef,
+            resolve_against_base: Boolean,
+            buffer: *mut u8,
+            max_length: CFIndex,
+        ) -> Boolean;
+        fn CFDataCreate(allocator: CFAllocatorRef, bytes: *const u8, length: CFIndex) -> CFDataRef;
+        fn CFDataGetLength(data: CFDataRef) -> CFIndex;
+        fn CFDataGetBytePtr(data: CFDataRef) -> *const u8;
+        fn CFRelease(cf: CFTypeRef);
+    }
+
+    // 持有 Core Foundation 对象 离开作用域时释放
+    struct Owned(CFTypeRef);
+
+    impl Owned {
+        fn new(cf: CFTypeRef) -> Option<Self> {
+            (!cf.is_null()).then_some(Owned(cf))
+        }
+    }
+
+    impl Drop for Owned {
+        fn drop(&mut self) {
+            unsafe { CFRelease(self.0) }
+        }
+    }
+
+    // 创建或解析失败时的错误对象 只需要释放
+    fn release_error(error: CFErrorRef) {
+        if !error.is_null() {
+            unsafe { CFRelease(error) }
+        }
+    }
+
+    pub fn create_bookmark(file_path: &str) -> Option<Vec<u8>> {
+        let url = Owned::new(unsafe {
+            CFURLCreateFromFileSystemRepresentation(
+                ptr::null(),
+                file_path.as_ptr(),
+                file_path.len() as CFIndex,
+                0,
+            )
+        })?;
+        let mut error: CFErrorRef = ptr::null();
+        let data = unsafe {
+            CFURLCreateBookmarkData(
+                ptr::null(),
+                url.0,
+                BOOKMARK_CREATION_WITH_SECURITY_SCOPE,
+                ptr::null(),
+                ptr::null(),
+                &mut error,
+            )
+        };
+        release_error(error);
+        let Some(data) = Owned::new(data) else {
+            println!("[RUST] 创建安全范围书签失败: {file_path}");
+            return None;
+        };
+        let bytes = unsafe {
+            std::slice::from_raw_parts(CFDataGetBytePtr(data.0), CFDataGetLength(data.0) as usize)
+        };
+        Some(bytes.to_vec())
+    }
+
+    pub fn resolve_bookmark(bookmark: &[u8]) -> Option<ResolvedBookmark> {
+        let data = Owned::new(unsafe {
+            CFDataCreate(ptr::null(), bookmark.as_ptr(), bookmark.len() as CFIndex)
+        })?;
+        let mut stale: Boolean = 0;
+        let mut error: CFErrorRef = ptr::null();
+        let url = unsafe {
+            CFURLCreateByResolvingBookmarkData(
+                ptr::null(),
+                data.0,
+                BOOKMARK_RESOLUTION_WITH_SECURITY_SCOPE,
+                ptr::null(),
+                ptr::null(),
+                &mut stale,
+                &mut error,
+            )
+        };
+        release_error(error);
+        let url = Owned::new(url)?;
+
+        let mut buffer = [0u8; MAX_PATH_BYTES];
+        let ok = unsafe {
+            CFURLGetFileSystemRepresentation(url.0, 1, buffer.as_mut_ptr(), buffer.len() as CFIndex)
+        };
+        if ok == 0 {
+            return None;
+        }
+        let file_path = unsafe { CStr::from_ptr(buffer.as_ptr() as *const c_char) }
+            .to_string_lossy()
+            .to_string();
+        // 访问权限保持到进程退出 不调用 CFURLStopAccessingSecurityScopedResource
+        if unsafe { CFURLStartAccessingSecurityScopedResource(url.0) } == 0 {
+            println!("[RUST] 无法访问书签指向的文件: {file_path}");
+            return None;
+        }
+        Some(ResolvedBookmark {
+            file_path,
+            stale: stale != 0,
+        })
+    }
+}
@@ -0,0 +1,171 @@
+use std::f64::consts::PI;
+use std::fs;
+use std::path::Path;
+
+use image::imageops::{self, FilterType};
+use image::{GrayImage, RgbaImage};
+
+use super::cache::{
+    check_file_cache_exists, ensure_cache_writable, image_cache_dir, load_source_info,
+};
+use super::config::cache_root;
+use super::preprocessing::decode_source_image;
+use super::types::SimilarImage;
+
+// 感知哈希：从整图的缩略图计算 记录在 source_info.json 中
+// 内容指纹只能识别完全相同的文件 重新导出、改变压缩或轻微调色后的同一张扫描件要靠感知哈希
+
+// 计算 pHash 使用的缩略图边长
+const PHASH_SIZE: u32 = 32;
+// pHash 取 DCT 左上角低频部分的边长 8x8 = 64 位
+const PHASH_LOW_FREQ: usize = 8;
+
+// find_similar 默认的 pHash 汉明距离阈值（64 位中不同的位数）
+const DEFAULT_SIMILARITY_THRESHOLD: u32 = 10;
+
+/// 计算图片的感知哈希
+/// # Arguments
+/// * `img` - RGBA 图片
+/// # Returns
+/// * `(u64, u64)` - (dHash, pHash)
+pub fn compute_perceptual_hashes(img: &RgbaImage) -> (u64, u64) {
+    // thumbnail 对每个像素只读一次 对超大图比 resize 快得多
+    let overview = imageops::thumbnail(img, PHASH_SIZE, PHASH_SIZE);
+    let gray: GrayImage = image::DynamicImage::ImageRgba8(overview).to_luma8();
+    (dhash(&gray), phash(&gray))
+}
+
+// dHash：缩小到 9x8 每一行比较相邻像素的亮度
+fn dhash(gray: &GrayImage) -> u64 {
+    let small = imageops::resize(gray, 9, 8, FilterType::Triangle);
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let bit = small.get_pixel(x, y)[0] < small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | u64::from(bit);
+        }
+    }
+    hash
+}
+
+// pHash：32x32 的二维 DCT 取左上角 8x8 的低频系数 与中位数比较
+fn phash(gray: &GrayImage) -> u64 {
+    let n = PHASH_SIZE as usize;
+    let pixels: Vec<f64> = gray.pixels().map(|p| p[0] as f64).collect();
+
+    // 可分离的 DCT-II 先按行再按列 只需要计算低频部分
+    let cosines: Vec<f64> = (0..PHASH_LOW_FREQ)
+        .flat_map(|u| {
+            (0..n).map(move |x| ((2 * x + 1) as f64 * u as f64 * PI / (2 * n) as f64).cos())
+        })
+        .collect();
+    let mut rows = vec![0.0; n * PHASH_LOW_FREQ];
+    for y in 0..n {
+        for u in 0..PHASH_LOW_FREQ {
+            rows[y * PHASH_LOW_FREQ + u] =
+                (0..n).map(|x| pixels[y * n + x] * cosines[u * n + x]).sum();
+        }
+    }
+    let mut coefficients = Vec::with_capacity(PHASH_LOW_FREQ * PHASH_LOW_FREQ);
+    for v in 0..PHASH_LOW_FREQ {
+        for u in 0..PHASH_LOW_FREQ {
+            coefficients.push(
+                (0..n)
+                    .map(|y| rows[y * PHASH_LOW_FREQ + u] * cosines[v * n + y])
+                    .sum::<f64>(),
+            );
+        }
+    }
+
+    // 直流分量只反映整体亮度 不参与中位数
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let median = sorted[sorted.len() / 2];
+    coefficients
+        .iter()
+        .fold(0u64, |hash, &c| (hash << 1) | u64::from(c > median))
+}
+
+/// 把感知哈希写入缓存目录的 source_info.json
+/// # Arguments
+/// * `cache_dir` - 该图片的缓存目录
+/// * `img` - 已解码的 RGBA 图片
+/// # Returns
+/// * `Result<(), String>` - 是否成功
+pub fn record_perceptual_hashes(cache_dir: &Path, img: &RgbaImage) -> Result<(), String> {
+    ensure_cache_writable("保存感知哈希")?;
+    let (dhash, phash) = compute_perceptual_hashes(img);
+    let mut info = load_source_info(cache_dir).ok_or("源文件信息不存在")?;
+    if let Some(info) = info.as_object_mut() {
+        info.insert("dhash".to_string(), format!("{dhash:016x}").into());
+        info.insert("phash".to_string(), format!("{phash:016x}").into());
+    }
+    let json = serde_json::to_string(&info).map_err(|e| format!("序列化源文件信息失败: {e}"))?;
+    fs::write(cache_dir.join("source_info.json"), json)
+        .map_err(|e| format!("保存源文件信息失败: {e}"))
+}
+
+// 从源文件信息中读取记录的感知哈希
+fn recorded_hashes(info: &serde_json::Value) -> Option<(u64, u64)> {
+    let parse = |key: &str| u64::from_str_radix(info.get(key)?.as_str()?, 16).ok();
+    Some((parse("dhash")?, parse("phash")?))
+}
+
+/// 在已处理的图片中查找与指定图片相似的图片
+/// 指定图片已经预处理时直接使用记录的哈希 否则解码源文件计算
+/// 旧版本生成的缓存没有感知哈希 不参与比较 重新预处理后才能被找到
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `threshold` - pHash 汉明距离阈值（0-64）默认为 10
+/// # Returns
+/// * `Result<Vec<SimilarImage>, String>` - 相似的图片 按距离从小到大排序
+#[tauri::command]
+pub fn find_similar(
+    file_path: String,
+    threshold: Option<u32>,
+) -> Result<Vec<SimilarImage>, String> {
+    let threshold = threshold.unwrap_or(DEFAULT_SIMILARITY_THRESHOLD);
+
+    let cached = check_file_cache_exists(&file_path)
+        .then(|| load_source_info(&image_cache_dir(&file_path)))
+        .flatten()
+        .and_then(|info| recorded_hashes(&info));
+    let (dhash, phash) = match cached {
+        Some(hashes) => hashes,
+        None => compute_perceptual_hashes(&decode_source_image(&file_path)?.to_rgba8()),
+    };
+
+    let mut similar = Vec::new();
+    if let Ok(entries) = fs::read_dir(cache_root()) {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let Some(info) = load_source_info(&entry.path()) else {
+                continue;
+            };
+            let Some(other_path) = info.get("file_path").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            if other_path == file_path {
+                continue;
+            }
+            let Some((other_dhash, other_phash)) = recorded_hashes(&info) else {
+                continue;
+            };
+
+            let phash_distance = (phash ^ other_phash).count_ones();
+            if phash_distance <= threshold {
+                similar.push(SimilarImage {
+                    file_path: other_path.to_string(),
+                    phash_distance,
+                    dhash_distance: (dhash ^ other_dhash).count_ones(),
+                });
+            }
+        }
+    }
+    similar.sort_by_key(|image| (image.phash_distance, image.dhash_distance));
+
+    println!(
+        "[RUST] 查找相似图片: {file_path} 阈值 {threshold} 找到 {} 个",
+        similar.len()
+    );
+    Ok(similar)
+}
@@ -0,0 +1,106 @@
+use std::env;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+
+use tauri::{AppHandle, Emitter};
+
+use super::commands::{open_user_image, validate_image_path};
+use super::types::PreprocessCompleted;
+
+// 通过"打开方式"/文件关联启动 或 macOS 打开文件事件传入的图片处理完成后发出的事件
+pub const FILE_OPENED_EVENT: &str = "file://opened";
+
+// 最近一次通过文件关联打开的图片的处理结果
+// 启动时前端可能还没开始监听事件 可以通过 get_startup_image 主动获取
+static STARTUP_IMAGE: OnceLock<Mutex<Option<PreprocessCompleted>>> = OnceLock::new();
+
+fn startup_image() -> &'static Mutex<Option<PreprocessCompleted>> {
+    STARTUP_IMAGE.get_or_init(|| Mutex::new(None))
+}
+
+/// 处理启动参数中的图片路径
+/// 取第一个不是命令行选项的参数 校验通过后立即开始预处理
+pub fn handle_startup_args(app: &AppHandle) {
+    let candidate = env::args()
+        .skip(1)
+        .find(|arg| !arg.starts_with('-') && Path::new(arg).is_file());
+
+    if let Some(file_path) = candidate {
+        println!("[RUST] 通过启动参数打开图片: {file_path}");
+        open_file_on_startup(app, file_path);
+    }
+}
+
+/// 处理系统传入的文件 URL（macOS 的打开文件事件）
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub fn handle_opened_urls(app: &AppHandle, urls: &[url::Url]) {
+    for url in urls {
+        match url.to_file_path() {
+            Ok(path) => {
+                let file_path = path.to_string_lossy().to_string();
+                println!("[RUST] 通过系统打开文件事件打开图片: {file_path}");
+                open_file_on_startup(app, file_path);
+            }
+            Err(_) => println!("[RUST] 忽略非本地文件的 URL: {url}"),
+        }
+    }
+}
+
+/// 校验路径并在后台线程中加载图片 完成后发出 file://opened 事件
+pub fn open_file_on_startup(app: &AppHandle, file_path: String) {
+    if let Err(e) = validate_image_path(&file_path) {
+        println!("[RUST] 启动图片校验失败: {e}");
+        finish(
+            app,
+            PreprocessCompleted {
+                file_path,
+                metadata: None,
+                error: Some(e),
+            },
+        );
+        return;
+    }
+
+    let app = app.clone();
+    let spawn_result = thread::Builder::new()
+        .name("startup-open".to_string())
+        .spawn(move || {
+            let payload = match open_user_image(&file_path) {
+                Ok(metadata) => PreprocessCompleted {
+                    file_path,
+                    metadata: Some(metadata),
+                    error: None,
+                },
+                Err(e) => PreprocessCompleted {
+                    file_path,
+                    metadata: None,
+                    error: Some(e),
+                },
+            };
+            finish(&app, payload);
+        });
+
+    if let Err(e) = spawn_result {
+        println!("[RUST] 启动图片加载线程失败: {e}");
+    }
+}
+
+fn finish(app: &AppHandle, payload: PreprocessCompleted) {
+    if let Ok(mut slot) = startup_image().lock() {
+        *slot = Some(payload.clone());
+    }
+    if let Err(e) = app.emit(FILE_OPENED_EVENT, payload) {
+        println!("[RUST] 发送文件打开事件失败: {e}");
+    }
+}
+
+/// 获取通过文件关联打开的图片的处理结果
+/// 还在处理中或者没有通过文件关联启动时返回 None
+#[tauri::command]
+pub fn get_startup_image() -> Result<Option<PreprocessCompleted>, String> {
+    startup_image()
+        .lock()
+        .map(|slot| slot.clone())
+        .map_err(|e| format!("读取启动图片失败: {e}"))
+}
@@ -0,0 +1,215 @@
+use std::fs;
+use std::path::Path;
+use std::thread;
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use super::cache::check_file_cache_exists;
+use super::navigation_history::warm_cache;
+use super::security_bookmarks::{create_bookmark, resolve_bookmark};
+use super::types::StartupPreloadConfig;
+use super::utils::app_data_subdir;
+use crate::utils::time::Stopwatch;
+
+// 启动预热：记录最近打开的图片 开启后应用启动时立即在后台检查它的缓存并预先读取 chunk
+// 前端还没发出第一个请求时就开始准备 重新打开上次的图片时第一帧更快
+// 配置保存在应用数据目录 默认关闭
+// macOS 沙盒中最近打开的图片同时保存安全范围书签 启动时解析书签重新取得访问权限（见 security_bookmarks）
+
+// 保存书签的最近打开的图片数
+const MAX_RECENT_BOOKMARKS: usize = 20;
+
+// 配置文件 位于应用数据目录的 startup 子目录下
+const STARTUP_PRELOAD_FILE: &str = "startup_preload.json";
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct StartupPreloadState {
+    enabled: bool,
+    // 最近一次打开的图片
+    recent_image: Option<String>,
+    // 最近打开的图片的安全范围书签 最近打开的在前
+    #[serde(default)]
+    bookmarks: Vec<RecentBookmark>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RecentBookmark {
+    file_path: String,
+    // base64 编码的书签数据
+    bookmark: String,
+}
+
+fn load_state(app: &AppHandle) -> StartupPreloadState {
+    app_data_subdir(app, "startup")
+        .ok()
+        .and_then(|dir| fs::read_to_string(dir.join(STARTUP_PRELOAD_FILE)).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(app: &AppHandle, state: &StartupPreloadState) -> Result<(), String> {
+    let dir = app_data_subdir(app, "startup")?;
+    let json = serde_json::to_string(state).map_err(|e| format!("序列化启动预热配置失败: {e}"))?;
+    fs::write(dir.join(STARTUP_PRELOAD_FILE), json)
+        .map_err(|e| format!("保存启动预热配置失败: {e}"))
+}
+
+fn to_config(state: StartupPreloadState) -> StartupPreloadConfig {
+    StartupPreloadConfig {
+        enabled: state.enabled,
+        recent_image: state.recent_image,
+    }
+}
+
+/// 记录最近打开的图片 用户打开图片时调用 失败只记录日志
+/// 需要时同时保存安全范围书签 下次启动后仍然可以访问
+pub fn record_recent_image(app: &AppHandle, file_path: &str) {
+    let mut state = load_state(app);
+    let bookmarked = state.bookmarks.first().map(|b| b.file_path.as_str()) == Some(file_path);
+    let bookmark = (!bookmarked).then(|| create_bookmark(file_path)).flatten();
+    if state.recent_image.as_deref() == Some(file_path) && bookmark.is_none() {
+        return;
+    }
+    state.recent_image = Some(file_path.to_string());
+    if let Some(bookmark) = bookmark {
+        state.bookmarks.retain(|b| b.file_path != file_path);
+        state.bookmarks.insert(
+            0,
+            RecentBookmark {
+                file_path: file_path.to_string(),
+                bookmark: base64::engine::general_purpose::STANDARD.encode(bookmark),
+            },
+        );
+        state.bookmarks.truncate(MAX_RECENT_BOOKMARKS);
+    }
+    if let Err(e) = save_state(app, &state) {
+        println!("[RUST] {e}");
+    }
+}
+
+/// 图片文件被删除后移除最近打开的记录和它的书签 失败只记录日志
+pub fn forget_recent_image(app: &AppHandle, file_path: &str) {
+    let mut state = load_state(app);
+    let bookmarks = state.bookmarks.len();
+    state.bookmarks.retain(|b| b.file_path != file_path);
+    let recent = state.recent_image.as_deref() == Some(file_path);
+    if recent {
+        state.recent_image = None;
+    }
+    if !recent && state.bookmarks.len() == bookmarks {
+        return;
+    }
+    if let Err(e) = save_state(app, &state) {
+        println!("[RUST] {e}");
+    }
+}
+
+// 解析保存的书签 重新取得最近打开的图片的访问权限
+// 文件被移动后更新记录的路径 过期的书签重新创建 解析失败的书签丢弃
+fn restore_bookmark_access(app: &AppHandle, state: &mut StartupPreloadState) {
+    if state.bookmarks.is_empty() {
+        return;
+    }
+    let mut changed = false;
+    let mut restored = Vec::new();
+    for entry in std::mem::take(&mut state.bookmarks) {
+        let resolved = base64::engine::general_purpose::STANDARD
+            .decode(&entry.bookmark)
+            .ok()
+            .and_then(|bookmark| resolve_bookmark(&bookmark));
+        let Some(resolved) = resolved else {
+            println!("[RUST] 书签已失效: {}", entry.file_path);
+            changed = true;
+            continue;
+        };
+        if resolved.file_path != entry.file_path {
+            println!(
+                "[RUST] 图片已移动: {} -> {}",
+                entry.file_path, resolved.file_path
+            );
+            if state.recent_image.as_deref() == Some(entry.file_path.as_str()) {
+                state.recent_image = Some(resolved.file_path.clone());
+            }
+        }
+        let bookmark = if resolved.stale {
+            create_bookmark(&resolved.file_path)
+                .map(|bookmark| base64::engine::general_purpose::STANDARD.encode(bookmark))
+                .unwrap_or(entry.bookmark)
+        } else {
+            entry.bookmark
+        };
+        changed |= resolved.stale || resolved.file_path != entry.file_path;
+        restored.push(RecentBookmark {
+            file_path: resolved.file_path,
+            bookmark,
+        });
+    }
+    println!(
+        "[RUST] 已恢复 {} 个最近打开的图片的访问权限",
+        restored.len()
+    );
+    state.bookmarks = restored;
+    if changed {
+        if let Err(e) = save_state(app, state) {
+            println!("[RUST] {e}");
+        }
+    }
+}
+
+/// 获取启动预热配置和最近打开的图片
+#[tauri::command]
+pub fn get_startup_preload_config(app: AppHandle) -> Result<StartupPreloadConfig, String> {
+    Ok(to_config(load_state(&app)))
+}
+
+/// 开启或关闭启动预热 下次启动时生效
+/// # Arguments
+/// * `enabled` - 是否在启动时预热最近打开的图片
+/// # Returns
+/// * `Result<StartupPreloadConfig, String>` - 保存后的配置
+#[tauri::command]
+pub fn set_startup_preload(app: AppHandle, enabled: bool) -> Result<StartupPreloadConfig, String> {
+    let mut state = load_state(&app);
+    state.enabled = enabled;
+    save_state(&app, &state)?;
+    println!("[RUST] 启动预热已{}", if enabled { "开启" } else { "关闭" });
+    Ok(to_config(state))
+}
+
+/// 启动时调用 先恢复最近打开的图片的访问权限
+/// 开启了启动预热时在后台线程中检查最近打开的图片的缓存并预热
+/// 源文件已经不存在或缓存已失效时跳过 等用户打开时再重新预处理
+pub fn start_startup_preload(app: &AppHandle) {
+    let mut state = load_state(app);
+    restore_bookmark_access(app, &mut state);
+    let Some(file_path) = state.recent_image.filter(|_| state.enabled) else {
+        return;
+    };
+
+    let spawn_result = thread::Builder::new()
+        .name("startup-preload".to_string())
+        .spawn(move || {
+            let stopwatch = Stopwatch::start();
+            if !Path::new(&file_path).is_file() {
+                println!("[RUST] 最近打开的图片已不存在，跳过启动预热: {file_path}");
+                return;
+            }
+            if !check_file_cache_exists(&file_path) {
+                println!("[RUST] 最近打开的图片缓存已失效，跳过启动预热: {file_path}");
+                return;
+            }
+            match warm_cache(file_path.clone()) {
+                Ok(report) => println!(
+                    "[RUST] 启动预热完成: {file_path} ({} 个 chunk) (耗时: {}ms)",
+                    report.chunks,
+                    stopwatch.elapsed_ms()
+                ),
+                Err(e) => println!("[RUST] 启动预热失败: {file_path} ({e})"),
+            }
+        });
+    if let Err(e) = spawn_result {
+        println!("[RUST] 启动预热线程失败: {e}");
+    }
+}
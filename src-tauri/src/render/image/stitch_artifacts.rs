@@ -0,0 +1,335 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use rayon::prelude::*;
+
+use super::cache::load_cached_metadata;
+use super::export::compose_region;
+use super::scheduler::run_scheduled;
+use super::types::{ImageRegion, StitchArtifact, StitchArtifactKind, TaskKind};
+use crate::utils::time::Stopwatch;
+
+// 拼接瑕疵检测：全景图和玻片扫描由很多小图拼成 拼接处常见两类问题
+// 1. 曝光台阶：相邻小图的亮度不同 拼接线两侧有一条笔直的亮度跳变
+// 2. 重复条带：拼接时同一条内容出现两次 相隔几个像素的两列（行）完全相同
+// 按块并行扫描全分辨率的亮度 每块向左、向上多读一个像素 块边界上的拼接线也能检测到
+
+// 扫描块的边长
+const SCAN_BLOCK_SIZE: u32 = 512;
+// 块内的拼接线至少要这么长 太短的跳变多半是图片内容
+const MIN_SEAM_LENGTH: u32 = 64;
+// 曝光台阶的最小亮度差（0-255）
+const MIN_EXPOSURE_STEP: f32 = 4.0;
+// 跳变方向一致的比例 真实的边缘通常有起伏 拼接线的方向几乎处处相同
+const MIN_STEP_CONSISTENCY: f32 = 0.8;
+// 跳变与附近的平均亮度变化的最小比值
+const MIN_STEP_CONTRAST: f32 = 3.0;
+// 计算附近的平均亮度变化时两侧各取的线数
+const STEP_NEIGHBOURS: usize = 8;
+// 重复条带的最大间隔（像素）
+const MAX_DUPLICATE_SHIFT: usize = 128;
+// 重复条带至少要连续这么多列（行）
+const MIN_DUPLICATE_RUN: usize = 4;
+// 参与重复检测的列（行）的最小亮度标准差 平坦的背景处处相同 不算重复
+const MIN_DUPLICATE_STD: f32 = 4.0;
+
+/// 扫描拼接图片中可疑的拼接瑕疵（需要先完成预处理）
+/// 结果只是可疑区域 图片内容本身的笔直边缘也可能被报告 需要用户确认
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<Vec<StitchArtifact>, String>` - 可疑区域 按类型分组 同一类型按分数从高到低排列
+#[tauri::command]
+pub fn detect_stitching_artifacts(file_path: String) -> Result<Vec<StitchArtifact>, String> {
+    run_scheduled(TaskKind::Analysis, &file_path, || {
+        find_stitching_artifacts(&file_path)
+    })?
+}
+
+fn find_stitching_artifacts(file_path: &str) -> Result<Vec<StitchArtifact>, String> {
+    let stopwatch = Stopwatch::start();
+    let metadata = load_cached_metadata(file_path)?;
+    let columns = metadata.total_width.div_ceil(SCAN_BLOCK_SIZE);
+    let rows = metadata.total_height.div_ceil(SCAN_BLOCK_SIZE);
+
+    let found = (0..columns * rows)
+        .into_par_iter()
+        .map(|index| {
+            let x0 = index % columns * SCAN_BLOCK_SIZE;
+            let y0 = index / columns * SCAN_BLOCK_SIZE;
+            let block = ImageRegion {
+                x: x0,
+                y: y0,
+                width: SCAN_BLOCK_SIZE.min(metadata.total_width - x0),
+                height: SCAN_BLOCK_SIZE.min(metadata.total_height - y0),
+            };
+            let extended = ImageRegion {
+                x: x0.saturating_sub(1),
+                y: y0.saturating_sub(1),
+                width: block.width + (x0 > 0) as u32,
+                height: block.height + (y0 > 0) as u32,
+            };
+            let pixels = compose_region(file_path, &metadata, &extended)?;
+            let luma = LumaBlock {
+                data: pixels
+                    .chunks_exact(4)
+                    .map(|p| 0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32)
+                    .collect(),
+                region: extended,
+            };
+            Ok(scan_block(&luma, &block))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut artifacts = merge_artifacts(found.into_iter().flatten().collect());
+    artifacts.sort_by(|a, b| {
+        (a.kind as u8)
+            .cmp(&(b.kind as u8))
+            .then(b.score.total_cmp(&a.score))
+    });
+    println!(
+        "[RUST] 拼接瑕疵检测完成: {} 个可疑区域 (耗时: {}ms)",
+        artifacts.len(),
+        stopwatch.elapsed_ms()
+    );
+    Ok(artifacts)
+}
+
+// 一块区域的亮度 可以按行或按列读取
+struct LumaBlock {
+    data: Vec<f32>,
+    region: ImageRegion,
+}
+
+impl LumaBlock {
+    fn at(&self, x: u32, y: u32) -> f32 {
+        self.data[((y - self.region.y) * self.region.width + (x - self.region.x)) as usize]
+    }
+}
+
+// 在块内检测两个方向的瑕疵 纵向和横向共用同一套算法 只是交换坐标
+fn scan_block(luma: &LumaBlock, block: &ImageRegion) -> Vec<StitchArtifact> {
+    let mut artifacts = Vec::new();
+    for vertical in [true, false] {
+        // lines 是与拼接线平行的方向（纵向拼接线的每一列） along 是沿拼接线的方向
+        let (line_start, line_end, along_start, along_end) = if vertical {
+            (
+                block.x,
+                block.x + block.width,
+                block.y,
+                block.y + block.height,
+            )
+        } else {
+            (
+                block.y,
+                block.y + block.height,
+                block.x,
+                block.x + block.width,
+            )
+        };
+        let value = |line: u32, along: u32| {
+            if vertical {
+                luma.at(line, along)
+            } else {
+                luma.at(along, line)
+            }
+        };
+        let to_rect = |line: u32, line_count: u32| {
+            if vertical {
+                (line, along_start, line_count, along_end - along_start)
+            } else {
+                (along_start, line, along_end - along_start, line_count)
+            }
+        };
+
+        if along_end - along_start >= MIN_SEAM_LENGTH {
+            for (line, step) in exposure_steps(&value, line_start, line_end, along_start, along_end)
+            {
+                let (x, y, width, height) = to_rect(line - 1, 2);
+                artifacts.push(StitchArtifact {
+                    kind: StitchArtifactKind::ExposureStep,
+                    x,
+                    y,
+                    width,
+                    height,
+                    score: step as f64,
+                });
+            }
+        }
+        for (first, end, length) in
+            duplicated_runs(&value, line_start, line_end, along_start, along_end)
+        {
+            let (x, y, width, height) = to_rect(first, end - first);
+            artifacts.push(StitchArtifact {
+                kind: StitchArtifactKind::DuplicatedStrip,
+                x,
+                y,
+                width,
+                height,
+                score: length as f64,
+            });
+        }
+    }
+    artifacts
+}
+
+// 找出曝光台阶 返回 (台阶右侧/下侧的线, 平均亮度差)
+fn exposure_steps(
+    value: &impl Fn(u32, u32) -> f32,
+    line_start: u32,
+    line_end: u32,
+    along_start: u32,
+    along_end: u32,
+) -> Vec<(u32, f32)> {
+    // 每条边界（line - 1 和 line 之间）的平均差和方向一致的比例
+    let first = line_start.max(1);
+    let count = (along_end - along_start) as f32;
+    let boundaries: Vec<(u32, f32, f32)> = (first..line_end)
+        .map(|line| {
+            let (mut sum, mut positive) = (0.0, 0.0);
+            for along in along_start..along_end {
+                let difference = value(line, along) - value(line - 1, along);
+                sum += difference;
+                if difference > 0.0 {
+                    positive += 1.0;
+                }
+            }
+            let consistency = f32::max(positive, count - positive) / count;
+            (line, sum / count, consistency)
+        })
+        .collect();
+
+    let mut steps = Vec::new();
+    for (i, &(line, mean, consistency)) in boundaries.iter().enumerate() {
+        let step = mean.abs();
+        if step < MIN_EXPOSURE_STEP || consistency < MIN_STEP_CONSISTENCY {
+            continue;
+        }
+        // 附近边界的平均差的中位数 纹理和噪声沿线平均后接近 0 渐变的背景不为 0
+        let mut nearby: Vec<f32> = boundaries
+            [i.saturating_sub(STEP_NEIGHBOURS)..(i + STEP_NEIGHBOURS + 1).min(boundaries.len())]
+            .iter()
+            .filter(|b| b.0 != line)
+            .map(|b| b.1.abs())
+            .collect();
+        if nearby.is_empty() {
+            continue;
+        }
+        nearby.sort_by(f32::total_cmp);
+        let background = nearby[nearby.len() / 2];
+        if step >= MIN_STEP_CONTRAST * background {
+            steps.push((line, step));
+        }
+    }
+    steps
+}
+
+// 找出重复条带 返回 (第一份的起始线, 第二份的结束线（不含）, 重复的线数)
+fn duplicated_runs(
+    value: &impl Fn(u32, u32) -> f32,
+    line_start: u32,
+    line_end: u32,
+    along_start: u32,
+    along_end: u32,
+) -> Vec<(u32, u32, usize)> {
+    // 每条线量化后的哈希 量化可以容忍很小的误差 平坦的线没有哈希
+    let count = (along_end - along_start) as f32;
+    let hashes: Vec<Option<u64>> = (line_start..line_end)
+        .map(|line| {
+            let values: Vec<f32> = (along_start..along_end).map(|a| value(line, a)).collect();
+            let mean = values.iter().sum::<f32>() / count;
+            let variance = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / count;
+            if variance.sqrt() < MIN_DUPLICATE_STD {
+                return None;
+            }
+            let mut hasher = DefaultHasher::new();
+            for v in values {
+                ((v / 8.0) as u8).hash(&mut hasher);
+            }
+            Some(hasher.finish())
+        })
+        .collect();
+
+    // 每条线与前面最近的相同线的间隔
+    let mut last_seen: HashMap<u64, usize> = HashMap::new();
+    let shifts: Vec<Option<usize>> = hashes
+        .iter()
+        .enumerate()
+        .map(|(i, hash)| {
+            let hash = (*hash)?;
+            let shift = last_seen.insert(hash, i).map(|previous| i - previous);
+            shift.filter(|s| (2..=MAX_DUPLICATE_SHIFT).contains(s))
+        })
+        .collect();
+
+    // 间隔相同的连续线组成一段重复条带
+    let mut runs = Vec::new();
+    let mut i = 0;
+    while i < shifts.len() {
+        let Some(shift) = shifts[i] else {
+            i += 1;
+            continue;
+        };
+        let start = i;
+        while i < shifts.len() && shifts[i] == Some(shift) {
+            i += 1;
+        }
+        if i - start >= MIN_DUPLICATE_RUN {
+            let first = line_start + (start - shift) as u32;
+            runs.push((first, line_start + i as u32, i - start));
+        }
+    }
+    runs
+}
+
+// 合并同一类型的相交或相邻的区域 跨越多个块的拼接线合并成一条
+// 合并后的区域变大 可能与之前不相交的区域相交 扫描到没有区域再合并为止 通常一到两遍
+fn merge_artifacts(mut artifacts: Vec<StitchArtifact>) -> Vec<StitchArtifact> {
+    loop {
+        let before = artifacts.len();
+        artifacts = sweep_merge(artifacts);
+        if artifacts.len() == before {
+            return artifacts;
+        }
+    }
+}
+
+fn touches(a: &StitchArtifact, b: &StitchArtifact) -> bool {
+    a.kind == b.kind
+        && a.x <= b.x + b.width
+        && b.x <= a.x + a.width
+        && a.y <= b.y + b.height
+        && b.y <= a.y + a.height
+}
+
+// 按左边界排序后扫描一遍 每个区域只和右边界还没有越过它左边界的区域比较
+fn sweep_merge(mut artifacts: Vec<StitchArtifact>) -> Vec<StitchArtifact> {
+    artifacts.sort_by_key(|artifact| artifact.x);
+    let mut finished = Vec::with_capacity(artifacts.len());
+    let mut active: Vec<StitchArtifact> = Vec::new();
+    for mut current in artifacts {
+        // 之后的区域左边界只会更大 不会再与这些区域相交
+        let mut i = 0;
+        while i < active.len() {
+            if active[i].x + active[i].width < current.x {
+                finished.push(active.swap_remove(i));
+            } else {
+                i += 1;
+            }
+        }
+        while let Some(i) = active.iter().position(|other| touches(other, &current)) {
+            let other = active.swap_remove(i);
+            let right = (current.x + current.width).max(other.x + other.width);
+            let bottom = (current.y + current.height).max(other.y + other.height);
+            current.x = current.x.min(other.x);
+            current.y = current.y.min(other.y);
+            current.width = right - current.x;
+            current.height = bottom - current.y;
+            current.score = current.score.max(other.score);
+        }
+        active.push(current);
+    }
+    finished.extend(active);
+    finished
+}
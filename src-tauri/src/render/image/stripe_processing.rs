@@ -0,0 +1,589 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use image::{ImageFormat, RgbaImage};
+use rayon::prelude::*;
+use sysinfo::System;
+
+use super::cache::{chunk_hash_key, image_cache_dir, save_chunk_hashes};
+use super::chunk_processing::CHUNK_TMP_SUFFIX;
+use super::config::{CHUNK_SIZE_X, CHUNK_SIZE_Y};
+use super::decode_sandbox::is_decode_sandbox_enabled;
+use super::level_formats::{level_format, read_level_chunk, write_level_chunk};
+use super::lod_pyramid::{level_descriptor, pyramid_level_count};
+use super::preprocess_checkpoint::PreprocessCheckpoint;
+use super::preprocess_progress::set_preprocess_total;
+use super::preprocess_queue::ensure_not_cancelled;
+use super::preprocessing::{
+    build_chunk_infos, detect_source_format, record_preprocess_telemetry, write_cache_metadata,
+};
+use super::proxy::{proxy_cache_dir, write_proxy_metadata};
+use super::pyramidal_export::{feed_level, finish_levels, level_downsamplers, PyramidSink};
+use super::similarity::record_perceptual_hashes;
+use super::timing_breakdown::store_timing_breakdown;
+use super::types::{ChunkInfo, ChunkStorageFormat, ImageMetadata, TimingBreakdown};
+use super::write_tracker::mark_chunk_written;
+use crate::utils::time::{Stopwatch, TimingSpan};
+
+// 条带预处理：整张解码时解码结果和转换后的 RGBA 同时在内存中 40000x40000 的图片需要约 13 GB
+// 整行扫描的切片图片可以达到 500000x5000 同样需要巨大的连续内存
+// PNG 按行流式解码 内存中只保留一个水平条带（高度按内存预算计算） 峰值内存与图片高度无关
+// 条带中的行直接追加到这一行 chunk 各自的文件中 一行 chunk 写完后改名、记录哈希和断点
+// 概览级别由条带逐级 2x2 缩小得到（与多分辨率 TIFF 导出相同的流式缩小） 同样按行写入
+// 条带中同一行的 chunk 写入不同的文件 由线程池并行写入
+// chunk 按解码顺序逐行完成 不使用配置的处理顺序（见 chunk_order）
+// 所以只在整张解码放不进内存预算时使用条带模式 放得下时仍走并行切分 按配置的顺序处理
+// 只支持非隔行扫描的 PNG 隔行扫描的 PNG 需要读完最后一遍才能得到完整的行 仍然整张解码
+// 整张解码（解码结果加 RGBA 副本）超过系统可用内存的 1/FULL_DECODE_MEMORY_DIVISOR 时使用条带模式
+const FULL_DECODE_MEMORY_DIVISOR: u64 = 2;
+// 一个条带最多占用系统可用内存的 1/STRIPE_MEMORY_DIVISOR
+const STRIPE_MEMORY_DIVISOR: u64 = 8;
+// 条带内存预算的上下限
+const STRIPE_MIN_BYTES: u64 = 16 * 1024 * 1024;
+const STRIPE_MAX_BYTES: u64 = 512 * 1024 * 1024;
+
+/// 判断图片是否应该按条带预处理
+/// 非隔行扫描的 PNG 且整张解码放不进内存预算时返回 true
+/// 开启解码沙箱时源文件只在子进程中解码 不使用条带模式
+pub fn should_process_in_stripes(file_path: &str) -> bool {
+    if is_decode_sandbox_enabled() {
+        return false;
+    }
+    let Some((width, height, bytes_per_pixel)) = streamable_png_info(file_path) else {
+        return false;
+    };
+    let full_decode_bytes =
+        (width as u64 * height as u64).saturating_mul(bytes_per_pixel as u64 + 4);
+    full_decode_bytes > available_memory() / FULL_DECODE_MEMORY_DIVISOR
+}
+
+// 可以按行解码的 PNG 的尺寸和每个像素的字节数
+fn streamable_png_info(file_path: &str) -> Option<(u32, u32, usize)> {
+    if detect_source_format(file_path) != Ok(ImageFormat::Png) {
+        return None;
+    }
+    let file = File::open(file_path).ok()?;
+    let reader = png::Decoder::new(BufReader::new(file)).read_info().ok()?;
+    let info = reader.info();
+    (!info.interlaced).then(|| (info.width, info.height, info.bytes_per_pixel()))
+}
+
+fn available_memory() -> u64 {
+    let mut system = System::new();
+    system.refresh_memory();
+    system.available_memory()
+}
+
+/// 按条带流式预处理 PNG 生成完整分辨率的 chunk 和所有概览级别
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `chunk_size_x` / `chunk_size_y` - chunk 尺寸
+/// # Returns
+/// * `Result<ImageMetadata, String>` - 图片元数据
+pub fn preprocess_in_stripes(
+    file_path: &str,
+    chunk_size_x: u32,
+    chunk_size_y: u32,
+) -> Result<ImageMetadata, String> {
+    let span = TimingSpan::new("preprocess.stripes");
+    let mut stripes = PngStripes::open(file_path, chunk_size_y)?;
+    let (total_width, total_height) = (stripes.width, stripes.height);
+    println!(
+        "[RUST] 按条带预处理: {file_path} {total_width}x{total_height} 每个条带 {} 行",
+        stripes.stripe_height
+    );
+
+    let cache_dir = image_cache_dir(file_path);
+    fs::create_dir_all(&cache_dir).map_err(|e| format!("创建缓存目录失败: {e}"))?;
+    let checkpoint = PreprocessCheckpoint::open(
+        &cache_dir,
+        file_path,
+        (total_width, total_height),
+        (chunk_size_x, chunk_size_y),
+    )?;
+    set_preprocess_total(
+        file_path,
+        total_width.div_ceil(chunk_size_x) * total_height.div_ceil(chunk_size_y),
+    );
+
+    // 第 0 层为完整分辨率 之后每层缩小一半 直到整个级别能放进一个 chunk
+    let level_count = pyramid_level_count(total_width, total_height, chunk_size_x, chunk_size_y);
+    let mut level_sizes = vec![(total_width, total_height)];
+    for _ in 0..level_count {
+        let (w, h) = level_sizes[level_sizes.len() - 1];
+        level_sizes.push((w.div_ceil(2), h.div_ceil(2)));
+    }
+    let mut writers = vec![LevelWriter::new(
+        cache_dir.clone(),
+        total_width,
+        total_height,
+        (chunk_size_x, chunk_size_y),
+        ChunkStorageFormat::Raw,
+    )];
+    for level in 1..=level_count {
+        let dir = proxy_cache_dir(file_path, 1 << level);
+        // 旧的概览级别可能网格不同 先删除
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).map_err(|e| format!("创建概览级别目录失败: {e}"))?;
+        let (width, height) = level_sizes[level as usize];
+        writers.push(LevelWriter::new(
+            dir,
+            width,
+            height,
+            (CHUNK_SIZE_X, CHUNK_SIZE_Y),
+            level_format(level),
+        ));
+    }
+    let mut sink = StripeSink {
+        file_path,
+        checkpoint: &checkpoint,
+        writers,
+        chunk_hashes: HashMap::new(),
+        coarsest: Vec::new(),
+    };
+    let mut downsamplers = level_downsamplers(&level_sizes);
+
+    stripes.for_each(|stripe| {
+        ensure_not_cancelled(file_path)?;
+        feed_level(&mut sink, &mut downsamplers, 0, stripe)
+    })?;
+    finish_levels(&mut sink, &mut downsamplers)?;
+    // 概览级别随条带一起写入 耗时都计入切分
+    let chunking_ms = span.elapsed_ms();
+    let metadata_write_stopwatch = Stopwatch::start();
+    let StripeSink {
+        chunk_hashes,
+        coarsest,
+        ..
+    } = sink;
+
+    save_chunk_hashes(&cache_dir, &chunk_hashes)?;
+    let levels = (1..=level_count)
+        .map(|level| {
+            let (width, height) = level_sizes[level as usize];
+            let metadata = write_proxy_metadata(
+                &proxy_cache_dir(file_path, 1 << level),
+                width,
+                height,
+                1 << level,
+            )?;
+            Ok(level_descriptor(level, &metadata))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let metadata = ImageMetadata {
+        total_width,
+        total_height,
+        chunk_size_x,
+        chunk_size_y,
+        col_count: total_width.div_ceil(chunk_size_x),
+        row_count: total_height.div_ceil(chunk_size_y),
+        chunks: build_chunk_infos(total_width, total_height, chunk_size_x, chunk_size_y),
+        proxy_scale: None,
+        levels,
+        timings: None,
+        chunk_count: None,
+    };
+    write_cache_metadata(&cache_dir, file_path, &metadata)?;
+    checkpoint.finish();
+
+    // 最粗的一层不超过一个 chunk 直接用来计算感知哈希
+    let (width, height) = level_sizes[level_count as usize];
+    if let Some(coarsest) = RgbaImage::from_raw(width, height, coarsest) {
+        record_perceptual_hashes(&cache_dir, &coarsest)?;
+    }
+
+    // 解码和写入交替进行 解码耗时按总耗时记录
+    let total_ms = span.elapsed_ms();
+    record_preprocess_telemetry(file_path, &metadata, total_ms, total_ms);
+    store_timing_breakdown(
+        file_path,
+        TimingBreakdown {
+            preprocessed: true,
+            chunking_ms: chunking_ms as u64,
+            metadata_write_ms: metadata_write_stopwatch.elapsed_ms() as u64,
+            total_ms: total_ms as u64,
+            ..TimingBreakdown::default()
+        },
+    );
+    println!(
+        "[RUST] 条带预处理完成: {} 个 chunks {level_count} 个概览级别 (耗时: {total_ms}ms)",
+        metadata.chunks.len()
+    );
+    Ok(metadata)
+}
+
+/// 按条带流式生成代理副本 原图的行逐级 2x2 缩小 只写入缩小 scale 倍的一层
+/// # Arguments
+/// * `file_path` - 图片文件路径 需要是可以按行解码的 PNG（见 should_stream_png）
+/// * `proxy_dir` - 代理目录
+/// * `scale` - 缩小倍数 2 的幂
+/// # Returns
+/// * `Result<ImageMetadata, String>` - 代理副本的元数据
+pub fn write_proxy_in_stripes(
+    file_path: &str,
+    proxy_dir: &Path,
+    scale: u32,
+) -> Result<ImageMetadata, String> {
+    let mut stripes = PngStripes::open(file_path, CHUNK_SIZE_Y * scale)?;
+    let level = scale.trailing_zeros() as usize;
+    let mut level_sizes = vec![(stripes.width, stripes.height)];
+    for _ in 0..level {
+        let (w, h) = level_sizes[level_sizes.len() - 1];
+        level_sizes.push((w.div_ceil(2), h.div_ceil(2)));
+    }
+    let (width, height) = level_sizes[level];
+
+    // 旧的代理可能网格不同 先删除
+    let _ = fs::remove_dir_all(proxy_dir);
+    fs::create_dir_all(proxy_dir).map_err(|e| format!("创建代理目录失败: {e}"))?;
+    let mut sink = ProxySink {
+        level,
+        writer: LevelWriter::new(
+            proxy_dir.to_path_buf(),
+            width,
+            height,
+            (CHUNK_SIZE_X, CHUNK_SIZE_Y),
+            level_format(level as u32),
+        ),
+    };
+    let mut downsamplers = level_downsamplers(&level_sizes);
+    stripes.for_each(|stripe| feed_level(&mut sink, &mut downsamplers, 0, stripe))?;
+    finish_levels(&mut sink, &mut downsamplers)?;
+    write_proxy_metadata(proxy_dir, width, height, scale)
+}
+
+/// 判断 PNG 是否可以按行流式解码 不考虑内存预算
+/// 非隔行扫描的 PNG 并且没有开启解码沙箱时返回 true
+pub fn should_stream_png(file_path: &str) -> bool {
+    !is_decode_sandbox_enabled() && streamable_png_info(file_path).is_some()
+}
+
+/// 按行流式解码 PNG 每凑满 band_height 行（最后一段可能不足）转换为 RGBA 交给调用方
+/// 用于按 chunk 行处理整张图片 内存中只有一段
+/// # Arguments
+/// * `file_path` - 图片文件路径 需要是可以按行解码的 PNG（见 should_stream_png）
+/// * `band_height` - 每一段的行数
+/// * `on_band` - 接收每一段的起始行和像素
+pub fn for_each_png_band(
+    file_path: &str,
+    band_height: u32,
+    mut on_band: impl FnMut(u32, RgbaImage) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut stripes = PngStripes::open(file_path, band_height)?;
+    let (width, height) = (stripes.width, stripes.height);
+    let row_bytes = width as usize * 4;
+    let band_bytes = band_height as usize * row_bytes;
+    let mut band = Vec::with_capacity(band_bytes);
+    let mut band_y = 0;
+    stripes.for_each(|mut stripe| {
+        // 条带高度按内存预算计算 一个条带可能跨越两段
+        while !stripe.is_empty() {
+            let take = (band_bytes - band.len()).min(stripe.len());
+            band.extend_from_slice(&stripe[..take]);
+            stripe = &stripe[take..];
+            let rows = (band.len() / row_bytes) as u32;
+            if rows == band_height || band_y + rows == height {
+                let pixels = std::mem::replace(&mut band, Vec::with_capacity(band_bytes));
+                let image = RgbaImage::from_raw(width, rows, pixels).ok_or("PNG 条带长度不匹配")?;
+                on_band(band_y, image)?;
+                band_y += rows;
+            }
+        }
+        Ok(())
+    })
+}
+
+// 按行解码 PNG 每凑满一个条带转换为 RGBA 交给调用方
+struct PngStripes {
+    reader: png::Reader<BufReader<File>>,
+    channels: usize,
+    width: u32,
+    height: u32,
+    stripe_height: u32,
+}
+
+impl PngStripes {
+    // 条带高度按内存预算计算 不超过 max_stripe_height
+    fn open(file_path: &str, max_stripe_height: u32) -> Result<Self, String> {
+        let file = File::open(file_path).map_err(|e| format!("打开图片文件失败: {e}"))?;
+        let mut decoder = png::Decoder::new(BufReader::new(file));
+        // 调色板、低位深展开为 8 位 16 位截断为 8 位
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let reader = decoder
+            .read_info()
+            .map_err(|e| format!("读取 PNG 信息失败: {e}"))?;
+        let (width, height) = (reader.info().width, reader.info().height);
+        let channels = match reader.output_color_type().0 {
+            png::ColorType::Grayscale => 1,
+            png::ColorType::GrayscaleAlpha => 2,
+            png::ColorType::Rgb => 3,
+            png::ColorType::Rgba => 4,
+            png::ColorType::Indexed => return Err("PNG 调色板没有展开".to_string()),
+        };
+        let budget =
+            (available_memory() / STRIPE_MEMORY_DIVISOR).clamp(STRIPE_MIN_BYTES, STRIPE_MAX_BYTES);
+        let stripe_height = (budget / (width as u64 * 4)).clamp(1, max_stripe_height as u64) as u32;
+        Ok(Self {
+            reader,
+            channels,
+            width,
+            height,
+            stripe_height,
+        })
+    }
+
+    fn for_each(
+        &mut self,
+        mut on_stripe: impl FnMut(&[u8]) -> Result<(), String>,
+    ) -> Result<(), String> {
+        let channels = self.channels;
+        let mut stripe = Vec::with_capacity(self.stripe_height as usize * self.width as usize * 4);
+        for row_index in 0..self.height {
+            let Some(row) = self
+                .reader
+                .next_row()
+                .map_err(|e| format!("解码 PNG 失败: {e}"))?
+            else {
+                return Err("解码 PNG 失败: 图片数据提前结束".to_string());
+            };
+            for pixel in row.data().chunks_exact(channels) {
+                stripe.extend_from_slice(&match channels {
+                    1 => [pixel[0], pixel[0], pixel[0], 255],
+                    2 => [pixel[0], pixel[0], pixel[0], pixel[1]],
+                    3 => [pixel[0], pixel[1], pixel[2], 255],
+                    _ => [pixel[0], pixel[1], pixel[2], pixel[3]],
+                });
+            }
+            if (row_index + 1).is_multiple_of(self.stripe_height) || row_index + 1 == self.height {
+                on_stripe(&stripe)?;
+                stripe.clear();
+            }
+        }
+        Ok(())
+    }
+}
+
+// 代理只需要其中一层 其他层的行只用于继续缩小
+struct ProxySink {
+    level: usize,
+    writer: LevelWriter,
+}
+
+impl PyramidSink for ProxySink {
+    fn write_rows(&mut self, level: usize, pixels: &[u8]) -> Result<(), String> {
+        if level == self.level {
+            self.writer.write_rows(pixels, None)?;
+        }
+        Ok(())
+    }
+}
+
+// 接收各层的行 写入对应级别的 chunk 文件
+struct StripeSink<'a> {
+    file_path: &'a str,
+    checkpoint: &'a PreprocessCheckpoint,
+    writers: Vec<LevelWriter>,
+    chunk_hashes: HashMap<String, String>,
+    // 最粗一层的全部像素
+    coarsest: Vec<u8>,
+}
+
+impl PyramidSink for StripeSink<'_> {
+    fn write_rows(&mut self, level: usize, pixels: &[u8]) -> Result<(), String> {
+        if level + 1 == self.writers.len() {
+            self.coarsest.extend_from_slice(pixels);
+        }
+        // 只有完整分辨率的 chunk 参与断点续传和像素哈希
+        let checkpoint = (level == 0).then_some(self.checkpoint);
+        let finished = self.writers[level].write_rows(pixels, checkpoint)?;
+        if level == 0 {
+            for (chunk_info, hash) in finished {
+                mark_chunk_written(self.file_path, chunk_info.chunk_x, chunk_info.chunk_y);
+                self.chunk_hashes
+                    .insert(chunk_hash_key(chunk_info.chunk_x, chunk_info.chunk_y), hash);
+            }
+        }
+        Ok(())
+    }
+}
+
+// 一个级别的 chunk 写入状态 同一时间只打开当前一行 chunk 的文件
+struct LevelWriter {
+    dir: PathBuf,
+    width: u32,
+    height: u32,
+    chunk_size_x: u32,
+    chunk_size_y: u32,
+    format: ChunkStorageFormat,
+    chunks: Vec<ChunkInfo>,
+    // 下一个到达的行在这个级别中的位置
+    next_row: u32,
+    open: Vec<OpenChunk>,
+}
+
+struct OpenChunk {
+    info: ChunkInfo,
+    target: ChunkTarget,
+}
+
+enum ChunkTarget {
+    // 断点中已经完成的 chunk 不再写入 只沿用之前的哈希
+    Completed(String),
+    Writing(BufWriter<File>, Box<blake3::Hasher>),
+}
+
+impl LevelWriter {
+    fn new(
+        dir: PathBuf,
+        width: u32,
+        height: u32,
+        (chunk_size_x, chunk_size_y): (u32, u32),
+        format: ChunkStorageFormat,
+    ) -> Self {
+        Self {
+            chunks: build_chunk_infos(width, height, chunk_size_x, chunk_size_y),
+            dir,
+            width,
+            height,
+            chunk_size_x,
+            chunk_size_y,
+            format,
+            next_row: 0,
+            open: Vec::new(),
+        }
+    }
+
+    fn chunk_path(&self, info: &ChunkInfo) -> PathBuf {
+        self.dir
+            .join(format!("chunk_{}_{}.bin", info.chunk_x, info.chunk_y))
+    }
+
+    fn tmp_path(&self, info: &ChunkInfo) -> PathBuf {
+        self.dir.join(format!(
+            "chunk_{}_{}.bin{CHUNK_TMP_SUFFIX}",
+            info.chunk_x, info.chunk_y
+        ))
+    }
+
+    // 写入若干行 返回写完的 chunk 及其像素哈希
+    fn write_rows(
+        &mut self,
+        pixels: &[u8],
+        checkpoint: Option<&PreprocessCheckpoint>,
+    ) -> Result<Vec<(ChunkInfo, String)>, String> {
+        let row_bytes = self.width as usize * 4;
+        let mut finished = Vec::new();
+        let mut rest = pixels;
+        while rest.len() >= row_bytes {
+            if self.next_row.is_multiple_of(self.chunk_size_y) {
+                self.open_chunk_row(self.next_row / self.chunk_size_y, checkpoint)?;
+            }
+            // 属于当前这行 chunk 的行
+            let chunk_row_end = (self.next_row / self.chunk_size_y + 1) * self.chunk_size_y;
+            let rows = ((chunk_row_end.min(self.height) - self.next_row) as usize)
+                .min(rest.len() / row_bytes);
+            let (segment, remaining) = rest.split_at(rows * row_bytes);
+            rest = remaining;
+            self.open.par_iter_mut().try_for_each(|chunk| {
+                let ChunkTarget::Writing(file, hasher) = &mut chunk.target else {
+                    return Ok(());
+                };
+                let start = chunk.info.x as usize * 4;
+                let end = start + chunk.info.width as usize * 4;
+                for row in segment.chunks_exact(row_bytes) {
+                    file.write_all(&row[start..end])
+                        .map_err(|e| format!("写入 chunk 文件失败: {e}"))?;
+                    hasher.update(&row[start..end]);
+                }
+                Ok::<(), String>(())
+            })?;
+            self.next_row += rows as u32;
+            if self.next_row.is_multiple_of(self.chunk_size_y) || self.next_row == self.height {
+                finished.extend(self.close_chunk_row(checkpoint)?);
+            }
+        }
+        Ok(finished)
+    }
+
+    fn open_chunk_row(
+        &mut self,
+        chunk_y: u32,
+        checkpoint: Option<&PreprocessCheckpoint>,
+    ) -> Result<(), String> {
+        let col_count = self.width.div_ceil(self.chunk_size_x) as usize;
+        let start = chunk_y as usize * col_count;
+        let mut open = Vec::with_capacity(col_count);
+        for info in &self.chunks[start..start + col_count] {
+            let target = match checkpoint.and_then(|checkpoint| checkpoint.completed_hash(info)) {
+                Some(hash) => ChunkTarget::Completed(hash),
+                None => {
+                    let mut file = BufWriter::new(
+                        File::create(self.tmp_path(info))
+                            .map_err(|e| format!("创建 chunk 文件失败: {e}"))?,
+                    );
+                    file.write_all(&info.width.to_be_bytes())
+                        .and_then(|_| file.write_all(&info.height.to_be_bytes()))
+                        .map_err(|e| format!("写入 chunk 文件失败: {e}"))?;
+                    ChunkTarget::Writing(file, Box::default())
+                }
+            };
+            open.push(OpenChunk {
+                info: info.clone(),
+                target,
+            });
+        }
+        self.open = open;
+        Ok(())
+    }
+
+    // 一行 chunk 的所有行都已写入 改名为正式文件 有损格式再转换一次
+    fn close_chunk_row(
+        &mut self,
+        checkpoint: Option<&PreprocessCheckpoint>,
+    ) -> Result<Vec<(ChunkInfo, String)>, String> {
+        let open = std::mem::take(&mut self.open);
+        let writer = &*self;
+        open.into_par_iter()
+            .map(|chunk| {
+                let hash = match chunk.target {
+                    ChunkTarget::Completed(hash) => hash,
+                    ChunkTarget::Writing(file, hasher) => {
+                        let info = &chunk.info;
+                        file.into_inner()
+                            .map_err(|e| format!("写入 chunk 文件失败: {e}"))?
+                            .sync_all()
+                            .map_err(|e| format!("同步 chunk 文件失败: {e}"))?;
+                        let path = writer.chunk_path(info);
+                        fs::rename(writer.tmp_path(info), &path)
+                            .map_err(|e| format!("替换 chunk 文件失败: {e}"))?;
+                        let hash = hasher.finalize().to_hex().to_string();
+                        if let Some(checkpoint) = checkpoint {
+                            checkpoint.record(info, &hash)?;
+                        }
+                        if writer.format != ChunkStorageFormat::Raw {
+                            writer.convert_chunk(info, &path)?;
+                        }
+                        hash
+                    }
+                };
+                Ok((chunk.info, hash))
+            })
+            .collect()
+    }
+
+    // 把写好的原始 chunk 转换为有损格式 每次只需要一个 chunk 的内存
+    fn convert_chunk(&self, info: &ChunkInfo, raw_path: &Path) -> Result<(), String> {
+        let data = read_level_chunk(&self.dir, info.chunk_x, info.chunk_y)?;
+        let img = RgbaImage::from_raw(info.width, info.height, data[8..].to_vec())
+            .ok_or_else(|| "chunk 数据长度不匹配".to_string())?;
+        let local = ChunkInfo {
+            x: 0,
+            y: 0,
+            ..info.clone()
+        };
+        write_level_chunk(&img, &local, &self.dir, self.format)?;
+        fs::remove_file(raw_path).map_err(|e| format!("删除原始 chunk 失败: {e}"))
+    }
+}
@@ -0,0 +1,89 @@
+use std::thread;
+
+use sysinfo::System;
+
+use crate::utils::disk::available_space;
+
+use super::config::cache_root;
+use super::types::SystemInfo;
+
+/// 获取系统能力报告
+/// 包含 CPU 核心数、SIMD 指令集、内存和缓存磁盘空间等信息
+#[tauri::command]
+pub fn get_system_info() -> Result<SystemInfo, String> {
+    let info = collect_system_info();
+    println!(
+        "[RUST] 系统信息: {} {}, {} 核, 内存 {}/{} MB",
+        info.os,
+        info.arch,
+        info.logical_cores,
+        info.available_memory_bytes / 1024 / 1024,
+        info.total_memory_bytes / 1024 / 1024
+    );
+    Ok(info)
+}
+
+/// 收集系统信息（供后端其他模块直接调用）
+pub fn collect_system_info() -> SystemInfo {
+    // 只刷新内存信息 完整刷新会枚举所有进程 开销较大
+    let mut system = System::new();
+    system.refresh_memory();
+
+    SystemInfo {
+        os: std::env::consts::OS.to_string(),
+        os_version: System::long_os_version(),
+        arch: std::env::consts::ARCH.to_string(),
+        logical_cores: thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1),
+        physical_cores: System::physical_core_count(),
+        simd_features: detect_simd_features(),
+        total_memory_bytes: system.total_memory(),
+        available_memory_bytes: system.available_memory(),
+        cache_disk_available_bytes: available_space(cache_root()).ok(),
+    }
+}
+
+/// 运行时检测 CPU 支持的 SIMD 指令集
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub fn detect_simd_features() -> Vec<String> {
+    let mut features = Vec::new();
+    if is_x86_feature_detected!("sse2") {
+        features.push("sse2".to_string());
+    }
+    if is_x86_feature_detected!("sse4.1") {
+        features.push("sse4.1".to_string());
+    }
+    if is_x86_feature_detected!("avx") {
+        features.push("avx".to_string());
+    }
+    if is_x86_feature_detected!("avx2") {
+        features.push("avx2".to_string());
+    }
+    if is_x86_feature_detected!("fma") {
+        features.push("fma".to_string());
+    }
+    if is_x86_feature_detected!("avx512f") {
+        features.push("avx512f".to_string());
+    }
+    features
+}
+
+/// 运行时检测 CPU 支持的 SIMD 指令集
+#[cfg(target_arch = "aarch64")]
+pub fn detect_simd_features() -> Vec<String> {
+    let mut features = Vec::new();
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        features.push("neon".to_string());
+    }
+    if std::arch::is_aarch64_feature_detected!("sve") {
+        features.push("sve".to_string());
+    }
+    features
+}
+
+/// 运行时检测 CPU 支持的 SIMD 指令集
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+pub fn detect_simd_features() -> Vec<String> {
+    Vec::new()
+}
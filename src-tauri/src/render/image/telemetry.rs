@@ -0,0 +1,244 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::thread;
+
+use tauri::AppHandle;
+
+use super::types::{TelemetryEvent, TelemetryRecord};
+use super::utils::app_data_subdir;
+use crate::utils::metrics::{reset_timings, timing_snapshot, TimingMetric};
+use crate::utils::time::get_time;
+
+// 遥测：用户主动开启后 在本地记录匿名的性能和崩溃数据（解码耗时、chunk 尺寸、错误码、panic 位置）
+// 数据只保存在应用数据目录中 不会自动上传 用户通过 export_telemetry 导出后自行分享
+// 记录中不包含文件路径 只保留扩展名 panic 信息中的路径会被替换
+
+// 事件文件 位于应用数据目录的 telemetry 子目录下 每行一条 JSON 记录
+const TELEMETRY_FILE: &str = "events.jsonl";
+// 事件文件超过这个大小后轮换 只保留上一份
+const MAX_TELEMETRY_BYTES: u64 = 4 * 1024 * 1024;
+// 开关的记录文件 位于应用数据目录的 settings 子目录下
+const TELEMETRY_SETTINGS_FILE: &str = "telemetry.json";
+// 导出文件的格式版本
+const TELEMETRY_EXPORT_VERSION: u32 = 1;
+
+static TELEMETRY_ENABLED: AtomicBool = AtomicBool::new(false);
+static TELEMETRY_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_subdir(app, "settings")?.join(TELEMETRY_SETTINGS_FILE))
+}
+
+fn rotated_path(dir: &Path) -> PathBuf {
+    dir.join(TELEMETRY_FILE).with_extension("1.jsonl")
+}
+
+/// 初始化遥测：读取保存的开关并安装 panic 钩子 启动时调用一次
+pub fn init_telemetry(app: &AppHandle) {
+    match app_data_subdir(app, "telemetry") {
+        Ok(dir) => {
+            let _ = TELEMETRY_DIR.set(dir);
+        }
+        Err(e) => println!("[RUST] 初始化遥测目录失败: {e}"),
+    }
+    let enabled = settings_path(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|settings| settings.get("enabled")?.as_bool())
+        .unwrap_or(false);
+    TELEMETRY_ENABLED.store(enabled, Ordering::Relaxed);
+
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}", l.file(), l.line()))
+            .unwrap_or_default();
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        record_telemetry(TelemetryEvent::Crash {
+            location,
+            message: anonymize(&message),
+            thread: thread::current().name().unwrap_or("unnamed").to_string(),
+        });
+        previous(info);
+    }));
+}
+
+// 把看起来像路径的片段替换掉
+fn anonymize(message: &str) -> String {
+    message
+        .split(' ')
+        .map(|word| {
+            if word.contains('/') || word.contains('\\') {
+                "<path>"
+            } else {
+                word
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 遥测是否已开启
+pub fn is_telemetry_enabled() -> bool {
+    TELEMETRY_ENABLED.load(Ordering::Relaxed)
+}
+
+/// 记录一条遥测事件 未开启时什么都不做 写入失败时忽略
+/// panic 钩子中也会调用 这里不能加锁 每条记录用一次追加写入完成
+pub fn record_telemetry(event: TelemetryEvent) {
+    if !is_telemetry_enabled() {
+        return;
+    }
+    let Some(dir) = TELEMETRY_DIR.get() else {
+        return;
+    };
+    let record = TelemetryRecord {
+        timestamp_ms: get_time() as u64,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        event,
+    };
+    let Ok(mut line) = serde_json::to_string(&record) else {
+        return;
+    };
+    line.push('\n');
+
+    let path = dir.join(TELEMETRY_FILE);
+    if fs::metadata(&path).is_ok_and(|m| m.len() > MAX_TELEMETRY_BYTES) {
+        let _ = fs::rename(&path, rotated_path(dir));
+    }
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&path) {
+        let _ = file.write_all(line.as_bytes());
+    }
+}
+
+/// 开启或关闭遥测 设置保存在应用数据目录中
+#[tauri::command]
+pub fn set_telemetry_enabled(app: AppHandle, enabled: bool) -> Result<bool, String> {
+    let settings = serde_json::json!({ "enabled": enabled });
+    fs::write(settings_path(&app)?, settings.to_string())
+        .map_err(|e| format!("保存遥测设置失败: {e}"))?;
+    TELEMETRY_ENABLED.store(enabled, Ordering::Relaxed);
+    println!("[RUST] 遥测已{}", if enabled { "开启" } else { "关闭" });
+    Ok(enabled)
+}
+
+/// 获取遥测是否开启
+#[tauri::command]
+pub fn get_telemetry_enabled() -> Result<bool, String> {
+    Ok(is_telemetry_enabled())
+}
+
+fn load_records() -> Vec<TelemetryRecord> {
+    let Some(dir) = TELEMETRY_DIR.get() else {
+        return Vec::new();
+    };
+    [rotated_path(dir), dir.join(TELEMETRY_FILE)]
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .flat_map(|content| {
+            content
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect::<Vec<TelemetryRecord>>()
+        })
+        .collect()
+}
+
+/// 导出本地记录的遥测数据 附带平台信息和汇总
+/// # Arguments
+/// * `out_path` - 输出文件路径 不传时只返回 JSON 文本
+/// # Returns
+/// * `Result<String, String>` - 导出的 JSON 文本
+#[tauri::command]
+pub fn export_telemetry(out_path: Option<String>) -> Result<String, String> {
+    let records = load_records();
+
+    let mut decode_ms_by_format: BTreeMap<String, Vec<u64>> = Default::default();
+    let mut error_counts: BTreeMap<String, u32> = Default::default();
+    let mut crashes = 0;
+    for record in &records {
+        match &record.event {
+            TelemetryEvent::Preprocess {
+                format, decode_ms, ..
+            } => decode_ms_by_format
+                .entry(format.clone())
+                .or_default()
+                .push(*decode_ms),
+            TelemetryEvent::Error { code } => *error_counts.entry(code.clone()).or_default() += 1,
+            TelemetryEvent::Crash { .. } => crashes += 1,
+        }
+    }
+    let decode_summary: serde_json::Map<String, serde_json::Value> = decode_ms_by_format
+        .into_iter()
+        .map(|(format, times)| {
+            let average = times.iter().sum::<u64>() / times.len() as u64;
+            (
+                format,
+                serde_json::json!({ "count": times.len(), "average_decode_ms": average }),
+            )
+        })
+        .collect();
+
+    let json = serde_json::to_string_pretty(&serde_json::json!({
+        "version": TELEMETRY_EXPORT_VERSION,
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "cpu_count": thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        "summary": {
+            "decode": decode_summary,
+            "errors": error_counts,
+            "crashes": crashes,
+        },
+        "records": records,
+    }))
+    .map_err(|e| format!("序列化遥测数据失败: {e}"))?;
+
+    if let Some(out_path) = out_path {
+        fs::write(&out_path, &json).map_err(|e| format!("写入遥测文件失败: {e}"))?;
+        println!("[RUST] 导出遥测数据: {} 条 -> {out_path}", records.len());
+    }
+    Ok(json)
+}
+
+/// 删除本地记录的遥测数据
+#[tauri::command]
+pub fn clear_telemetry() -> Result<(), String> {
+    if let Some(dir) = TELEMETRY_DIR.get() {
+        for path in [dir.join(TELEMETRY_FILE), rotated_path(dir)] {
+            if path.exists() {
+                fs::remove_file(&path).map_err(|e| format!("删除遥测数据失败: {e}"))?;
+            }
+        }
+    }
+    println!("[RUST] 遥测数据已清除");
+    Ok(())
+}
+
+/// 获取本次运行的计时统计（预处理各阶段、chunk 写入和读取的耗时）
+/// 与遥测开关无关 只保存在内存中
+/// # Returns
+/// * `Result<Vec<TimingMetric>, String>` - 按名称排列的计时统计
+#[tauri::command]
+pub fn get_timing_metrics() -> Result<Vec<TimingMetric>, String> {
+    Ok(timing_snapshot())
+}
+
+/// 清空计时统计
+#[tauri::command]
+pub fn reset_timing_metrics() -> Result<(), String> {
+    reset_timings();
+    Ok(())
+}
@@ -0,0 +1,544 @@
+use std::fs;
+use std::path::Path;
+
+use ddsfile::{D3DFormat, Dds, DxgiFormat};
+use ktx2::Format;
+use tauri::ipc::Response;
+use tauri::{AppHandle, Window};
+
+use super::cache::fnv1a_hash;
+use super::commands::load_user_image;
+use super::types::{ImageMetadata, TextureInfo};
+use super::utils::{app_data_subdir, is_up_to_date, save_rgba_png};
+use super::window_state::set_window_image;
+
+// 纹理解压结果在应用数据目录下的保存位置
+const TEXTURE_DIR: &str = "textures";
+
+// 纹理中的像素格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextureFormat {
+    Bc1,
+    Bc2,
+    Bc3,
+    Bc4,
+    Bc5,
+    Bc7,
+    Rgba8,
+    Bgra8,
+    Unsupported,
+}
+
+impl TextureFormat {
+    fn name(self) -> &'static str {
+        match self {
+            TextureFormat::Bc1 => "bc1",
+            TextureFormat::Bc2 => "bc2",
+            TextureFormat::Bc3 => "bc3",
+            TextureFormat::Bc4 => "bc4",
+            TextureFormat::Bc5 => "bc5",
+            TextureFormat::Bc7 => "bc7",
+            TextureFormat::Rgba8 => "rgba8",
+            TextureFormat::Bgra8 => "bgra8",
+            TextureFormat::Unsupported => "unsupported",
+        }
+    }
+
+    // 每个 4x4 块的字节数 非块压缩格式返回 None
+    fn block_bytes(self) -> Option<usize> {
+        match self {
+            TextureFormat::Bc1 | TextureFormat::Bc4 => Some(8),
+            TextureFormat::Bc2 | TextureFormat::Bc3 | TextureFormat::Bc5 | TextureFormat::Bc7 => {
+                Some(16)
+            }
+            _ => None,
+        }
+    }
+
+    // 后端能否解压 BC6H/BC7 等格式只能交给前端的 GPU 处理
+    fn decodable(self) -> bool {
+        !matches!(self, TextureFormat::Bc7 | TextureFormat::Unsupported)
+    }
+
+    // 指定尺寸的一级 mip 占用的字节数
+    fn level_size(self, width: u32, height: u32) -> usize {
+        match self.block_bytes() {
+            Some(block_bytes) => {
+                width.div_ceil(4).max(1) as usize * height.div_ceil(4).max(1) as usize * block_bytes
+            }
+            None => width as usize * height as usize * 4,
+        }
+    }
+}
+
+// 解析后的纹理 只保留第 0 层（数组的第一个元素 / 立方体贴图的第一个面）
+struct Texture {
+    info: TextureInfo,
+    format: TextureFormat,
+    levels: Vec<Vec<u8>>,
+}
+
+impl Texture {
+    fn level(&self, mip_level: u32) -> Result<(u32, u32, &[u8]), String> {
+        let data = self.levels.get(mip_level as usize).ok_or_else(|| {
+            format!(
+                "mip 级别超出范围: {mip_level} (共 {} 级)",
+                self.info.mip_levels
+            )
+        })?;
+        let width = (self.info.width >> mip_level).max(1);
+        let height = (self.info.height >> mip_level).max(1);
+        Ok((width, height, data))
+    }
+}
+
+/// 读取 KTX2 / DDS 纹理的基本信息
+#[tauri::command]
+pub fn get_texture_info(file_path: String) -> Result<TextureInfo, String> {
+    Ok(load_texture(Path::new(&file_path))?.info)
+}
+
+/// 返回指定 mip 级别的原始数据（块压缩格式不解压）
+/// 前端支持对应的压缩纹理扩展时可以直接上传到 GPU
+/// 数据格式与 chunk 相同：宽度(4字节) + 高度(4字节) + 数据
+#[tauri::command]
+pub fn get_texture_level(file_path: String, mip_level: u32) -> Result<Response, String> {
+    let texture = load_texture(Path::new(&file_path))?;
+    let (width, height, data) = texture.level(mip_level)?;
+
+    let mut result = Vec::with_capacity(8 + data.len());
+    result.extend_from_slice(&width.to_be_bytes());
+    result.extend_from_slice(&height.to_be_bytes());
+    result.extend_from_slice(data);
+    Ok(Response::new(result))
+}
+
+/// 打开 KTX2 / DDS 纹理
+/// 在后端把指定 mip 级别解压为 RGBA 并保存为 PNG 之后走正常的预处理流程
+/// 后端无法解压的格式（例如 BC7）请使用 get_texture_level 交给前端处理
+/// 保存后的路径会记录为调用窗口当前打开的图片 前端可以通过 get_window_state 取得
+/// # Arguments
+/// * `file_path` - 纹理文件路径
+/// * `mip_level` - mip 级别 默认为 0
+#[tauri::command]
+pub fn process_texture_image(
+    app: AppHandle,
+    window: Window,
+    file_path: String,
+    mip_level: Option<u32>,
+) -> Result<ImageMetadata, String> {
+    let source = Path::new(&file_path);
+    let mip_level = mip_level.unwrap_or(0);
+
+    let dir = app_data_subdir(&app, TEXTURE_DIR)?;
+    let output = dir.join(format!(
+        "texture_{:016x}_m{}.png",
+        fnv1a_hash(file_path.as_bytes()),
+        mip_level
+    ));
+
+    if is_up_to_date(source, &output) {
+        println!("[RUST] 复用已解压的纹理: {}", output.display());
+    } else {
+        let texture = load_texture(source)?;
+        if !texture.format.decodable() {
+            return Err(format!(
+                "后端不支持解压 {} 格式 请使用 get_texture_level 交给前端处理",
+                texture.info.format
+            ));
+        }
+        let (width, height, data) = texture.level(mip_level)?;
+        println!(
+            "[RUST] 解压纹理: {} {width}x{height} (mip {mip_level})",
+            texture.info.format
+        );
+        let pixels = decode_level(texture.format, width, height, data)?;
+        save_rgba_png(&output, width, height, pixels)?;
+    }
+
+    let output = output.to_string_lossy().to_string();
+    let metadata = load_user_image(&output)?;
+    set_window_image(window.label(), &output);
+    Ok(metadata)
+}
+
+fn load_texture(path: &Path) -> Result<Texture, String> {
+    let data = fs::read(path).map_err(|e| format!("读取纹理文件失败: {e} ({path:?})"))?;
+    if data.starts_with(b"DDS ") {
+        load_dds(&data)
+    } else {
+        load_ktx2(&data)
+    }
+}
+
+fn load_dds(data: &[u8]) -> Result<Texture, String> {
+    let dds = Dds::read(data).map_err(|e| format!("解析 DDS 失败: {e}"))?;
+
+    let format = match (dds.get_dxgi_format(), dds.get_d3d_format()) {
+        (Some(DxgiFormat::BC1_UNorm | DxgiFormat::BC1_UNorm_sRGB), _) => TextureFormat::Bc1,
+        (Some(DxgiFormat::BC2_UNorm | DxgiFormat::BC2_UNorm_sRGB), _) => TextureFormat::Bc2,
+        (Some(DxgiFormat::BC3_UNorm | DxgiFormat::BC3_UNorm_sRGB), _) => TextureFormat::Bc3,
+        (Some(DxgiFormat::BC4_UNorm), _) => TextureFormat::Bc4,
+        (Some(DxgiFormat::BC5_UNorm), _) => TextureFormat::Bc5,
+        (Some(DxgiFormat::BC7_UNorm | DxgiFormat::BC7_UNorm_sRGB), _) => TextureFormat::Bc7,
+        (Some(DxgiFormat::R8G8B8A8_UNorm | DxgiFormat::R8G8B8A8_UNorm_sRGB), _)
+        | (_, Some(D3DFormat::A8B8G8R8)) => TextureFormat::Rgba8,
+        (Some(DxgiFormat::B8G8R8A8_UNorm | DxgiFormat::B8G8R8A8_UNorm_sRGB), _)
+        | (_, Some(D3DFormat::A8R8G8B8)) => TextureFormat::Bgra8,
+        _ => TextureFormat::Unsupported,
+    };
+
+    let width = dds.get_width();
+    let height = dds.get_height();
+    let mip_levels = dds.get_num_mipmap_levels().max(1);
+
+    // 第 0 层的数据中依次存放各级 mip
+    let mut levels = Vec::new();
+    if format != TextureFormat::Unsupported {
+        let layer = dds
+            .get_data(0)
+            .map_err(|e| format!("读取 DDS 数据失败: {e}"))?;
+        let mut offset = 0;
+        for level in 0..mip_levels {
+            let size = format.level_size((width >> level).max(1), (height >> level).max(1));
+            match layer.get(offset..offset + size) {
+                Some(bytes) => levels.push(bytes.to_vec()),
+                None => break,
+            }
+            offset += size;
+        }
+    }
+
+    Ok(Texture {
+        info: TextureInfo {
+            container: "dds".to_string(),
+            format: match format {
+                TextureFormat::Unsupported => dds
+                    .get_dxgi_format()
+                    .map(|f| format!("{f:?}"))
+                    .or_else(|| dds.get_d3d_format().map(|f| format!("{f:?}")))
+                    .unwrap_or_else(|| "unknown".to_string()),
+                _ => format.name().to_string(),
+            },
+            width,
+            height,
+            mip_levels,
+            array_layers: dds.get_num_array_layers().max(1),
+            block_compressed: format.block_bytes().is_some(),
+            decodable: format.decodable(),
+        },
+        format,
+        levels,
+    })
+}
+
+fn load_ktx2(data: &[u8]) -> Result<Texture, String> {
+    let reader = ktx2::Reader::new(data).map_err(|e| format!("解析 KTX2 失败: {e}"))?;
+    let header = reader.header();
+    if let Some(scheme) = header.supercompression_scheme {
+        return Err(format!("不支持超压缩的 KTX2 文件: {scheme:?}"));
+    }
+
+    let format = match header.format {
+        Some(Format::BC1_RGB_UNORM_BLOCK)
+        | Some(Format::BC1_RGB_SRGB_BLOCK)
+        | Some(Format::BC1_RGBA_UNORM_BLOCK)
+        | Some(Format::BC1_RGBA_SRGB_BLOCK) => TextureFormat::Bc1,
+        Some(Format::BC2_UNORM_BLOCK) | Some(Format::BC2_SRGB_BLOCK) => TextureFormat::Bc2,
+        Some(Format::BC3_UNORM_BLOCK) | Some(Format::BC3_SRGB_BLOCK) => TextureFormat::Bc3,
+        Some(Format::BC4_UNORM_BLOCK) => TextureFormat::Bc4,
+        Some(Format::BC5_UNORM_BLOCK) => TextureFormat::Bc5,
+        Some(Format::BC7_UNORM_BLOCK) | Some(Format::BC7_SRGB_BLOCK) => TextureFormat::Bc7,
+        Some(Format::R8G8B8A8_UNORM) | Some(Format::R8G8B8A8_SRGB) => TextureFormat::Rgba8,
+        Some(Format::B8G8R8A8_UNORM) | Some(Format::B8G8R8A8_SRGB) => TextureFormat::Bgra8,
+        _ => TextureFormat::Unsupported,
+    };
+
+    let width = header.pixel_width;
+    let height = header.pixel_height.max(1);
+
+    // 每一级的数据中依次存放各层和各面 只取第一个
+    let mut levels = Vec::new();
+    if format != TextureFormat::Unsupported {
+        for (level, data) in reader.levels().enumerate() {
+            let size = format.level_size((width >> level).max(1), (height >> level).max(1));
+            match data.data.get(..size) {
+                Some(bytes) => levels.push(bytes.to_vec()),
+                None => break,
+            }
+        }
+    }
+
+    Ok(Texture {
+        info: TextureInfo {
+            container: "ktx2".to_string(),
+            format: match format {
+                TextureFormat::Unsupported => header
+                    .format
+                    .map(|f| format!("{f:?}"))
+                    .unwrap_or_else(|| "unknown".to_string()),
+                _ => format.name().to_string(),
+            },
+            width,
+            height,
+            mip_levels: header.level_count.max(1),
+            array_layers: header.layer_count.max(1),
+            block_compressed: format.block_bytes().is_some(),
+            decodable: format.decodable(),
+        },
+        format,
+        levels,
+    })
+}
+
+/// 把一级 mip 解压为 RGBA
+fn decode_level(
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    data: &[u8],
+) -> Result<Vec<u8>, String> {
+    let expected = format.level_size(width, height);
+    if data.len() < expected {
+        return Err("纹理数据长度不足".to_string());
+    }
+
+    let width = width as usize;
+    let height = height as usize;
+    match format {
+        TextureFormat::Rgba8 => return Ok(data[..expected].to_vec()),
+        TextureFormat::Bgra8 => {
+            let mut pixels = data[..expected].to_vec();
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+            return Ok(pixels);
+        }
+        _ => {}
+    }
+
+    let block_bytes = format.block_bytes().unwrap_or(16);
+    let blocks_x = width.div_ceil(4);
+    let mut pixels = vec![0u8; width * height * 4];
+    let mut block_pixels = [[0u8; 4]; 16];
+
+    for (index, block) in data[..expected].chunks_exact(block_bytes).enumerate() {
+        let block_x = index % blocks_x * 4;
+        let block_y = index / blocks_x * 4;
+
+        match format {
+            TextureFormat::Bc1 => decode_bc1_color(block, &mut block_pixels, true),
+            TextureFormat::Bc2 => {
+                decode_bc1_color(&block[8..], &mut block_pixels, false);
+                // 显式 alpha 每个像素 4 位
+                for (i, pixel) in block_pixels.iter_mut().enumerate() {
+                    let nibble = (block[i / 2] >> ((i % 2) * 4)) & 0x0F;
+                    pixel[3] = nibble * 17;
+                }
+            }
+            TextureFormat::Bc3 => {
+                decode_bc1_color(&block[8..], &mut block_pixels, false);
+                decode_bc4_channel(&block[..8], &mut block_pixels, 3);
+            }
+            TextureFormat::Bc4 => {
+                decode_bc4_channel(block, &mut block_pixels, 0);
+                for pixel in block_pixels.iter_mut() {
+                    *pixel = [pixel[0], pixel[0], pixel[0], 255];
+                }
+            }
+            _ => {
+                // BC5 两个通道 一般为法线贴图的 X / Y
+                decode_bc4_channel(&block[..8], &mut block_pixels, 0);
+                decode_bc4_channel(&block[8..], &mut block_pixels, 1);
+                for pixel in block_pixels.iter_mut() {
+                    pixel[2] = 0;
+                    pixel[3] = 255;
+                }
+            }
+        }
+
+        // 写回图像 边缘的块可能超出图像范围
+        for (i, pixel) in block_pixels.iter().enumerate() {
+            let x = block_x + i % 4;
+            let y = block_y + i / 4;
+            if x < width && y < height {
+                let offset = (y * width + x) * 4;
+                pixels[offset..offset + 4].copy_from_slice(pixel);
+            }
+        }
+    }
+
+    Ok(pixels)
+}
+
+// RGB565 展开为 RGB888
+fn expand_565(color: u16) -> [u8; 3] {
+    let r = ((color >> 11) & 0x1F) as u32;
+    let g = ((color >> 5) & 0x3F) as u32;
+    let b = (color & 0x1F) as u32;
+    [
+        (r * 255 / 31) as u8,
+        (g * 255 / 63) as u8,
+        (b * 255 / 31) as u8,
+    ]
+}
+
+/// 解码 BC1 颜色块（8 字节）
+/// `allow_transparent` 为 true 时按 BC1 规则 c0 <= c1 表示三色加透明模式
+/// BC2 / BC3 中的颜色块总是四色模式
+fn decode_bc1_color(block: &[u8], out: &mut [[u8; 4]; 16], allow_transparent: bool) {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let rgb0 = expand_565(c0);
+    let rgb1 = expand_565(c1);
+
+    let mix = |a: u8, b: u8, wa: u32, wb: u32| ((a as u32 * wa + b as u32 * wb) / (wa + wb)) as u8;
+    let mut palette = [[0u8; 4]; 4];
+    palette[0] = [rgb0[0], rgb0[1], rgb0[2], 255];
+    palette[1] = [rgb1[0], rgb1[1], rgb1[2], 255];
+    if c0 > c1 || !allow_transparent {
+        for c in 0..3 {
+            palette[2][c] = mix(rgb0[c], rgb1[c], 2, 1);
+            palette[3][c] = mix(rgb0[c], rgb1[c], 1, 2);
+        }
+        palette[2][3] = 255;
+        palette[3][3] = 255;
+    } else {
+        for c in 0..3 {
+            palette[2][c] = mix(rgb0[c], rgb1[c], 1, 1);
+        }
+        palette[2][3] = 255;
+        palette[3] = [0, 0, 0, 0];
+    }
+
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    for (i, pixel) in out.iter_mut().enumerate() {
+        *pixel = palette[((indices >> (i * 2)) & 0x3) as usize];
+    }
+}
+
+/// 解码 BC4 单通道块（8 字节） 写入每个像素的 `channel` 通道
+/// BC3 的 alpha 和 BC5 的两个通道使用相同的编码
+fn decode_bc4_channel(block: &[u8], out: &mut [[u8; 4]; 16], channel: usize) {
+    let a0 = block[0] as u32;
+    let a1 = block[1] as u32;
+
+    let mut palette = [0u8; 8];
+    palette[0] = a0 as u8;
+    palette[1] = a1 as u8;
+    if a0 > a1 {
+        for i in 1..7 {
+            palette[i + 1] = (((7 - i as u32) * a0 + i as u32 * a1) / 7) as u8;
+        }
+    } else {
+        for i in 1..5 {
+            palette[i + 1] = (((5 - i as u32) * a0 + i as u32 * a1) / 5) as u8;
+        }
+        palette[6] = 0;
+        palette[7] = 255;
+    }
+
+    // 16 个 3 位索引 共 48 位
+    let mut bits = 0u64;
+    for (i, &byte) in block[2..8].iter().enumerate() {
+        bits |= (byte as u64) << (i * 8);
+    }
+    for (i, pixel) in out.iter_mut().enumerate() {
+        pixel[channel] = palette[((bits >> (i * 3)) & 0x7) as usize];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 像素 i 使用索引 i % 4 的 BC1 索引字节
+    const BC1_INDICES: [u8; 4] = [0xE4; 4];
+    const RED_565: u16 = 0xF800;
+    const BLUE_565: u16 = 0x001F;
+
+    fn bc1_block(c0: u16, c1: u16, indices: [u8; 4]) -> Vec<u8> {
+        let mut block = Vec::new();
+        block.extend_from_slice(&c0.to_le_bytes());
+        block.extend_from_slice(&c1.to_le_bytes());
+        block.extend_from_slice(&indices);
+        block
+    }
+
+    fn pixel(pixels: &[u8], width: usize, x: usize, y: usize) -> &[u8] {
+        &pixels[(y * width + x) * 4..][..4]
+    }
+
+    #[test]
+    fn decodes_bc1_four_color_block() {
+        let pixels = decode_level(
+            TextureFormat::Bc1,
+            4,
+            4,
+            &bc1_block(RED_565, BLUE_565, BC1_INDICES),
+        )
+        .unwrap();
+        assert_eq!(pixels.len(), 4 * 4 * 4);
+        for y in 0..4 {
+            assert_eq!(pixel(&pixels, 4, 0, y), [255, 0, 0, 255]);
+            assert_eq!(pixel(&pixels, 4, 1, y), [0, 0, 255, 255]);
+            assert_eq!(pixel(&pixels, 4, 2, y), [170, 0, 85, 255]);
+            assert_eq!(pixel(&pixels, 4, 3, y), [85, 0, 170, 255]);
+        }
+    }
+
+    #[test]
+    fn decodes_bc1_transparent_block() {
+        // c0 <= c1 时第 3 个颜色为中间色 第 4 个为透明
+        let block = bc1_block(BLUE_565, RED_565, BC1_INDICES);
+        let pixels = decode_level(TextureFormat::Bc1, 4, 4, &block).unwrap();
+        assert_eq!(pixel(&pixels, 4, 0, 0), [0, 0, 255, 255]);
+        assert_eq!(pixel(&pixels, 4, 1, 0), [255, 0, 0, 255]);
+        assert_eq!(pixel(&pixels, 4, 2, 0), [127, 0, 127, 255]);
+        assert_eq!(pixel(&pixels, 4, 3, 0), [0, 0, 0, 0]);
+
+        // BC2 / BC3 的颜色块总是四色模式
+        let mut out = [[0u8; 4]; 16];
+        decode_bc1_color(&block, &mut out, false);
+        assert_eq!(out[2], [85, 0, 170, 255]);
+        assert_eq!(out[3], [170, 0, 85, 255]);
+    }
+
+    #[test]
+    fn decodes_bc4_six_value_block() {
+        // a0 <= a1 时插值 4 个值 另外两个固定为 0 和 255 像素 i 使用索引 i % 8
+        let block = [50, 100, 0x88, 0xC6, 0xFA, 0x88, 0xC6, 0xFA];
+        let pixels = decode_level(TextureFormat::Bc4, 4, 4, &block).unwrap();
+        let expected = [50, 100, 60, 70, 80, 90, 0, 255];
+        for i in 0..16 {
+            let value = expected[i % 8];
+            assert_eq!(pixel(&pixels, 4, i % 4, i / 4), [value, value, value, 255]);
+        }
+
+        // a0 > a1 时插值 6 个值
+        let mut out = [[0u8; 4]; 16];
+        decode_bc4_channel(&[140, 0, 0x88, 0xC6, 0xFA, 0x88, 0xC6, 0xFA], &mut out, 3);
+        let alphas: Vec<u8> = out[..8].iter().map(|pixel| pixel[3]).collect();
+        assert_eq!(alphas, [140, 0, 120, 100, 80, 60, 40, 20]);
+    }
+
+    #[test]
+    fn crops_edge_blocks_to_level_size() {
+        // 5x3 需要 2x1 个块 右边的块只有第一列在图像内
+        let mut data = bc1_block(RED_565, BLUE_565, [0x00; 4]);
+        data.extend(bc1_block(RED_565, BLUE_565, [0x55; 4]));
+        let pixels = decode_level(TextureFormat::Bc1, 5, 3, &data).unwrap();
+        assert_eq!(pixels.len(), 5 * 3 * 4);
+        for y in 0..3 {
+            for x in 0..4 {
+                assert_eq!(pixel(&pixels, 5, x, y), [255, 0, 0, 255]);
+            }
+            assert_eq!(pixel(&pixels, 5, 4, y), [0, 0, 255, 255]);
+        }
+
+        // 小于一个块的 mip 仍然占用一个完整的块
+        let pixels = decode_level(TextureFormat::Bc1, 1, 1, &data[..8]).unwrap();
+        assert_eq!(pixels, [255, 0, 0, 255]);
+        assert!(decode_level(TextureFormat::Bc1, 5, 3, &data[..15]).is_err());
+    }
+}
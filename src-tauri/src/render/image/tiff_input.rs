@@ -0,0 +1,347 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use image::{ImageFormat, RgbaImage};
+use tiff::decoder::{ChunkType, Decoder, DecodingResult};
+use tiff::tags::Tag;
+use tiff::ColorType;
+
+use super::decode_sandbox::is_decode_sandbox_enabled;
+use super::lod_pyramid::MAX_LOD_LEVEL;
+use super::preprocessing::detect_source_format;
+use super::pyramid_import::TilePyramid;
+
+// TIFF 输入：普通（按条带存储）的 TIFF 整张解码 与其他格式相同（见 decode_source_image）
+// 分块存储的 TIFF 可以只读取覆盖某个区域的块 作为已有的金字塔导入（见 pyramid_import）
+// 多分辨率 TIFF（vips tiffsave --pyramid、Aperio SVS 等）后续的 IFD 中保存缩小的层
+// 尺寸接近原图 1/2^n 的分块 IFD 直接作为第 n 级概览级别 缺少的级别由上一级缩小
+// 标签图、宏观图等不是分块存储或尺寸不匹配的 IFD 忽略
+// 只支持 8/16 位的灰度、灰度+透明、RGB、RGBA 以及交错存储的通道 其他 TIFF 整张解码
+// 保存在 SubIFD 中的层（OME-TIFF）不会被读取 这时只使用完整分辨率的块
+
+// 金字塔中的一层 即 TIFF 中的一个分块 IFD
+struct TiffLevel {
+    ifd: usize,
+    level: u32,
+    width: u32,
+    height: u32,
+    tile_width: u32,
+    tile_height: u32,
+}
+
+/// 分块存储的 TIFF 以及其中的概览层
+pub struct TiledTiff {
+    path: PathBuf,
+    levels: Vec<TiffLevel>,
+}
+
+/// 打开分块存储的 TIFF 普通 TIFF 或不支持按块读取时返回 None
+/// 开启解码沙箱时源文件只在子进程中解码 同样返回 None
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Option<TiledTiff>` - 可以按块读取的 TIFF
+pub fn open_tiled_tiff(file_path: &str) -> Option<TiledTiff> {
+    if is_decode_sandbox_enabled() || detect_source_format(file_path) != Ok(ImageFormat::Tiff) {
+        return None;
+    }
+    match scan_tiff_levels(file_path) {
+        Ok(Some(levels)) => Some(TiledTiff {
+            path: PathBuf::from(file_path),
+            levels,
+        }),
+        Ok(None) => None,
+        Err(e) => {
+            println!("[RUST] 无法按块读取 TIFF，整张解码: {file_path}: {e}");
+            None
+        }
+    }
+}
+
+fn open_decoder(path: &Path) -> Result<Decoder<BufReader<File>>, String> {
+    let file = File::open(path).map_err(|e| format!("打开 TIFF 文件失败: {e}"))?;
+    Decoder::new(BufReader::new(file)).map_err(|e| format!("读取 TIFF 失败: {e}"))
+}
+
+// 当前 IFD 是否可以按块读取 返回块尺寸
+fn tile_layout(decoder: &mut Decoder<BufReader<File>>) -> Option<(u32, u32)> {
+    if decoder.get_chunk_type() != ChunkType::Tile {
+        return None;
+    }
+    // 通道分开存储（PlanarConfiguration = 2）时每个块只有一个通道
+    if decoder
+        .find_tag_unsigned::<u16>(Tag::PlanarConfiguration)
+        .ok()?
+        .is_some_and(|planar| planar != 1)
+    {
+        return None;
+    }
+    match decoder.colortype().ok()? {
+        ColorType::Gray(8 | 16)
+        | ColorType::GrayA(8 | 16)
+        | ColorType::RGB(8 | 16)
+        | ColorType::RGBA(8 | 16) => Some(decoder.chunk_dimensions()),
+        _ => None,
+    }
+}
+
+// 找出完整分辨率和各概览级别所在的 IFD 第一个 IFD 不是分块存储时返回 None
+fn scan_tiff_levels(file_path: &str) -> Result<Option<Vec<TiffLevel>>, String> {
+    let mut decoder = open_decoder(Path::new(file_path))?;
+    let (width, height) = decoder
+        .dimensions()
+        .map_err(|e| format!("读取 TIFF 尺寸失败: {e}"))?;
+    let Some((tile_width, tile_height)) = tile_layout(&mut decoder) else {
+        return Ok(None);
+    };
+    let mut levels = vec![TiffLevel {
+        ifd: 0,
+        level: 0,
+        width,
+        height,
+        tile_width,
+        tile_height,
+    }];
+
+    let mut ifd = 0;
+    while decoder.more_images() {
+        decoder
+            .next_image()
+            .map_err(|e| format!("读取 TIFF 的第 {} 个 IFD 失败: {e}", ifd + 1))?;
+        ifd += 1;
+        let Some((tile_width, tile_height)) = tile_layout(&mut decoder) else {
+            continue;
+        };
+        let Ok((level_width, level_height)) = decoder.dimensions() else {
+            continue;
+        };
+        // 不同工具缩小时取整方式不同 与逐级减半的尺寸相差不超过 1 个像素即可
+        let level = (1..=MAX_LOD_LEVEL).find(|&level| {
+            let scale = 1u32 << level;
+            level_width.abs_diff(width.div_ceil(scale)) <= 1
+                && level_height.abs_diff(height.div_ceil(scale)) <= 1
+        });
+        if let Some(level) = level.filter(|&level| levels.iter().all(|l| l.level != level)) {
+            levels.push(TiffLevel {
+                ifd,
+                level,
+                width: level_width,
+                height: level_height,
+                tile_width,
+                tile_height,
+            });
+        }
+    }
+    Ok(Some(levels))
+}
+
+impl TiledTiff {
+    // 拼出某一层中的一个区域
+    fn read_level_region(
+        &self,
+        level: &TiffLevel,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<RgbaImage, String> {
+        if width == 0 || height == 0 || x + width > level.width || y + height > level.height {
+            return Err(format!(
+                "区域 ({x}, {y}, {width}x{height}) 超出 TIFF 第 {} 级范围 {}x{}",
+                level.level, level.width, level.height
+            ));
+        }
+        // 每次读取使用单独的解码器 并行生成 chunk 时互不影响
+        let mut decoder = open_decoder(&self.path)?;
+        decoder
+            .seek_to_image(level.ifd)
+            .map_err(|e| format!("定位 TIFF 的第 {} 个 IFD 失败: {e}", level.ifd))?;
+        let channels = match decoder
+            .colortype()
+            .map_err(|e| format!("读取 TIFF 颜色类型失败: {e}"))?
+        {
+            ColorType::Gray(_) => 1,
+            ColorType::GrayA(_) => 2,
+            ColorType::RGB(_) => 3,
+            _ => 4,
+        };
+
+        let tiles_across = level.width.div_ceil(level.tile_width);
+        let mut output = RgbaImage::new(width, height);
+        for row in y / level.tile_height..=(y + height - 1) / level.tile_height {
+            for col in x / level.tile_width..=(x + width - 1) / level.tile_width {
+                let index = row * tiles_across + col;
+                let (tile_width, tile_height) = decoder.chunk_data_dimensions(index);
+                let samples = match decoder
+                    .read_chunk(index)
+                    .map_err(|e| format!("读取 TIFF 块 ({col}, {row}) 失败: {e}"))?
+                {
+                    DecodingResult::U8(samples) => samples,
+                    // 16 位截断为 8 位
+                    DecodingResult::U16(samples) => {
+                        samples.into_iter().map(|s| (s >> 8) as u8).collect()
+                    }
+                    _ => return Err(format!("TIFF 块 ({col}, {row}) 的采样格式不受支持")),
+                };
+
+                let (tile_x, tile_y) = (col * level.tile_width, row * level.tile_height);
+                let left = tile_x.max(x);
+                let top = tile_y.max(y);
+                let right = (tile_x + tile_width).min(x + width);
+                let bottom = (tile_y + tile_height).min(y + height);
+                for py in top..bottom {
+                    for px in left..right {
+                        let offset =
+                            ((py - tile_y) * tile_width + (px - tile_x)) as usize * channels;
+                        let pixel = &samples[offset..offset + channels];
+                        let rgba = match channels {
+                            1 => [pixel[0], pixel[0], pixel[0], 255],
+                            2 => [pixel[0], pixel[0], pixel[0], pixel[1]],
+                            3 => [pixel[0], pixel[1], pixel[2], 255],
+                            _ => [pixel[0], pixel[1], pixel[2], pixel[3]],
+                        };
+                        output.put_pixel(px - x, py - y, image::Rgba(rgba));
+                    }
+                }
+            }
+        }
+        Ok(output)
+    }
+}
+
+impl TilePyramid for TiledTiff {
+    fn size(&self) -> (u32, u32) {
+        (self.levels[0].width, self.levels[0].height)
+    }
+
+    fn describe(&self) -> String {
+        let base = &self.levels[0];
+        format!(
+            "分块 TIFF {} (块 {}x{}, {} 个概览层)",
+            self.path.display(),
+            base.tile_width,
+            base.tile_height,
+            self.levels.len() - 1
+        )
+    }
+
+    fn read_region(&self, x: u32, y: u32, width: u32, height: u32) -> Result<RgbaImage, String> {
+        self.read_level_region(&self.levels[0], x, y, width, height)
+    }
+
+    fn downscaled_size(&self, level: u32) -> Option<(u32, u32)> {
+        let tiff_level = self.levels.iter().find(|l| l.level == level)?;
+        Some((tiff_level.width, tiff_level.height))
+    }
+
+    fn read_downscaled_region(
+        &self,
+        level: u32,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<RgbaImage, String> {
+        let tiff_level = self
+            .levels
+            .iter()
+            .find(|l| l.level == level)
+            .ok_or_else(|| format!("TIFF 中没有第 {level} 级"))?;
+        self.read_level_region(tiff_level, x, y, width, height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::image::export_encoder::TiffWriter;
+    use crate::render::image::types::TiffCompression;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tiff_input_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // 写一个分块 TIFF 每一层的像素由 pixel(层序号, x, y) 决定
+    fn write_tiled(path: &Path, sizes: &[(u32, u32)], compression: TiffCompression) -> String {
+        let file_path = path.to_string_lossy().to_string();
+        let mut writer = TiffWriter::create(&file_path, sizes, compression).unwrap();
+        for (index, &(width, height)) in sizes.iter().enumerate() {
+            let level = RgbaImage::from_fn(width, height, |x, y| pixel(index, x, y));
+            writer.write_rows(index, level.as_raw()).unwrap();
+        }
+        writer.finish().unwrap();
+        file_path
+    }
+
+    fn pixel(index: usize, x: u32, y: u32) -> image::Rgba<u8> {
+        image::Rgba([x as u8, y as u8, index as u8, 255])
+    }
+
+    #[test]
+    fn reads_regions_across_tiles_and_levels() {
+        let dir = test_dir("levels");
+        // 第 2 个 IFD 的尺寸与任何级别都不匹配（例如标签图） 应当忽略
+        let file_path = write_tiled(
+            &dir.join("pyramid.tif"),
+            &[(600, 300), (37, 11), (300, 150)],
+            TiffCompression::Deflate,
+        );
+        let tiff = open_tiled_tiff(&file_path).unwrap();
+        assert_eq!(tiff.size(), (600, 300));
+        assert_eq!(tiff.downscaled_size(1), Some((300, 150)));
+        assert_eq!(tiff.downscaled_size(2), None);
+
+        // 跨越块的边界
+        let region = tiff.read_region(250, 200, 20, 60).unwrap();
+        for (x, y, value) in region.enumerate_pixels() {
+            assert_eq!(*value, pixel(0, 250 + x, 200 + y));
+        }
+        let region = tiff.read_downscaled_region(1, 299, 149, 1, 1).unwrap();
+        assert_eq!(*region.get_pixel(0, 0), pixel(2, 299, 149));
+
+        assert!(tiff.read_region(590, 0, 20, 1).is_err());
+        assert!(tiff.read_region(0, 0, 0, 1).is_err());
+        assert!(tiff.read_downscaled_region(2, 0, 0, 1, 1).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn ignores_stripped_and_invalid_files() {
+        let dir = test_dir("invalid");
+        let stripped = dir.join("stripped.tif");
+        RgbaImage::from_fn(40, 30, |x, y| pixel(0, x, y))
+            .save(&stripped)
+            .unwrap();
+        assert!(open_tiled_tiff(&stripped.to_string_lossy()).is_none());
+
+        // TIFF 文件头之后的内容不完整
+        let truncated = dir.join("truncated.tif");
+        std::fs::write(&truncated, b"II*\0\x08\0\0\0\x05\0").unwrap();
+        assert!(open_tiled_tiff(&truncated.to_string_lossy()).is_none());
+
+        assert!(open_tiled_tiff(&dir.join("missing.tif").to_string_lossy()).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn corrupted_tiles_return_errors() {
+        let dir = test_dir("corrupted");
+        let path = dir.join("corrupted.tif");
+        let file_path = write_tiled(&path, &[(300, 300)], TiffCompression::Deflate);
+        // 覆盖第一个块的压缩数据 文件头和 IFD 保持不变
+        let mut data = std::fs::read(&path).unwrap();
+        data[16..400].fill(0xff);
+        std::fs::write(&path, data).unwrap();
+
+        let tiff = open_tiled_tiff(&file_path).unwrap();
+        assert!(tiff.read_region(0, 0, 10, 10).is_err());
+        // 其他块不受影响
+        let region = tiff.read_region(290, 290, 10, 10).unwrap();
+        assert_eq!(*region.get_pixel(0, 0), pixel(0, 290, 290));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,517 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+use tauri::AppHandle;
+
+use super::cache::{check_file_cache_exists, load_cached_metadata};
+use super::chunk_order::hilbert_distance;
+use super::errors::{localized_error, ErrorCode};
+use super::export::{enqueue_export, ExportKind, ExportTask};
+use super::pyramidal_export::{pyramid_level_sizes, stream_pyramid, PyramidSink};
+use super::types::TileArchiveFormat;
+use crate::utils::time::Stopwatch;
+
+// 瓦片包导出：把金字塔打包成 MBTiles 或 PMTiles
+// 两者都使用 XYZ 瓦片寻址 缩放级别 0 为单个瓦片 最高级别为原图
+// 图片没有地理坐标 范围统一记录为整个 Web 墨卡托平面
+
+// 瓦片尺寸 地图客户端默认使用 256
+const TILE_SIZE: u32 = 256;
+
+// Web 墨卡托的纬度范围
+const MAX_LATITUDE: f64 = 85.051_128_78;
+
+// PMTiles 头部长度 头部和根目录合计不能超过 16 KiB
+const PMTILES_HEADER_LEN: usize = 127;
+const PMTILES_ROOT_LIMIT: usize = 16_384 - PMTILES_HEADER_LEN;
+
+/// 把已经预处理的图片导出为 MBTiles 或 PMTiles 瓦片包 GIS 工具可以直接加载
+/// 任务加入后台导出队列 进度和结束事件与 export_region 相同 取消后不保留断点
+/// # Arguments
+/// * `file_path` - 图片文件路径（需要已经预处理）
+/// * `out_path` - 输出文件路径
+/// * `format` - 瓦片包格式 为空时根据扩展名（.mbtiles / .pmtiles）判断
+/// # Returns
+/// * `Result<String, String>` - 任务 ID
+#[tauri::command]
+pub fn export_tile_archive(
+    app: AppHandle,
+    file_path: String,
+    out_path: String,
+    format: Option<TileArchiveFormat>,
+) -> Result<String, String> {
+    if !check_file_cache_exists(&file_path) {
+        return Err(localized_error(ErrorCode::CacheMissing, &[]));
+    }
+    let format = match format {
+        Some(format) => format,
+        None => format_from_extension(&out_path)?,
+    };
+    enqueue_export(app, file_path, out_path, ExportKind::TileArchive(format))
+}
+
+fn format_from_extension(out_path: &str) -> Result<TileArchiveFormat, String> {
+    let extension = Path::new(out_path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "mbtiles" => Ok(TileArchiveFormat::Mbtiles),
+        "pmtiles" => Ok(TileArchiveFormat::Pmtiles),
+        _ => Err(format!("无法根据扩展名判断瓦片包格式: {out_path}")),
+    }
+}
+
+/// 执行瓦片包导出
+/// # Returns
+/// * `Result<bool, String>` - true 表示完成 false 表示被取消
+pub(super) fn run_tile_archive_export(
+    task: &ExportTask,
+    format: TileArchiveFormat,
+) -> Result<bool, String> {
+    let stopwatch = Stopwatch::start();
+    let metadata = load_cached_metadata(&task.file_path)?;
+    let level_sizes = pyramid_level_sizes(metadata.total_width, metadata.total_height);
+    let max_zoom = (level_sizes.len() - 1) as u8;
+    println!(
+        "[RUST] 开始导出瓦片包: {} {}x{} 缩放级别 0-{max_zoom} ({format:?})",
+        task.output_path, metadata.total_width, metadata.total_height
+    );
+
+    let name = Path::new(&task.file_path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let description = format!(
+        "{}x{} ({})",
+        metadata.total_width, metadata.total_height, task.file_path
+    );
+    let archive: Box<dyn TileArchiveWriter> = match format {
+        TileArchiveFormat::Mbtiles => Box::new(MbtilesWriter::create(&task.output_path)?),
+        TileArchiveFormat::Pmtiles => Box::new(PmtilesWriter::create(&task.output_path)?),
+    };
+
+    let mut sink = TileSink {
+        level_sizes: level_sizes.clone(),
+        levels: vec![PendingRows::default(); level_sizes.len()],
+        archive,
+    };
+    if !stream_pyramid(task, &metadata, &level_sizes, &mut sink)? {
+        sink.archive.abort();
+        println!("[RUST] 瓦片包导出已取消: {}", task.output_path);
+        return Ok(false);
+    }
+
+    let info = ArchiveInfo {
+        name,
+        description,
+        max_zoom,
+    };
+    if let Err(e) = sink.archive.finish(&info) {
+        let _ = fs::remove_file(partial_path(&task.output_path));
+        return Err(e);
+    }
+
+    println!(
+        "[RUST] 瓦片包导出完成: {} (耗时: {}ms)",
+        task.output_path,
+        stopwatch.elapsed_ms()
+    );
+    Ok(true)
+}
+
+// 写入过程中使用的临时路径 完成后才重命名为输出路径
+fn partial_path(output_path: &str) -> String {
+    format!("{output_path}.partial")
+}
+
+// 瓦片包的描述信息
+struct ArchiveInfo {
+    name: String,
+    description: String,
+    max_zoom: u8,
+}
+
+// 瓦片包写入器
+trait TileArchiveWriter {
+    /// 写入一个瓦片 坐标为 XYZ 方案（y 向下）
+    fn add_tile(&mut self, zoom: u8, x: u32, y: u32, data: Vec<u8>) -> Result<(), String>;
+
+    /// 所有瓦片写完后调用 完成文件
+    fn finish(self: Box<Self>, info: &ArchiveInfo) -> Result<(), String>;
+
+    /// 放弃写入 删除临时文件
+    fn abort(self: Box<Self>);
+}
+
+// 每层攒够一行瓦片的像素后切成瓦片
+#[derive(Clone, Default)]
+struct PendingRows {
+    pixels: Vec<u8>,
+    rows: u32,
+    // 已经输出的行数（瓦片行 × TILE_SIZE）
+    emitted_rows: u32,
+}
+
+struct TileSink {
+    level_sizes: Vec<(u32, u32)>,
+    levels: Vec<PendingRows>,
+    archive: Box<dyn TileArchiveWriter>,
+}
+
+impl PyramidSink for TileSink {
+    fn write_rows(&mut self, level: usize, pixels: &[u8]) -> Result<(), String> {
+        let (width, height) = self.level_sizes[level];
+        let row_len = width as usize * 4;
+        let zoom = (self.level_sizes.len() - 1 - level) as u8;
+
+        let pending = &mut self.levels[level];
+        pending.pixels.extend_from_slice(pixels);
+        pending.rows += (pixels.len() / row_len) as u32;
+
+        loop {
+            let pending = &mut self.levels[level];
+            let remaining = height - pending.emitted_rows;
+            let tile_rows = TILE_SIZE.min(remaining);
+            if tile_rows == 0 || pending.rows < tile_rows {
+                return Ok(());
+            }
+
+            let rest = pending.pixels.split_off(tile_rows as usize * row_len);
+            let rows = std::mem::replace(&mut pending.pixels, rest);
+            pending.rows -= tile_rows;
+            let tile_y = pending.emitted_rows / TILE_SIZE;
+            pending.emitted_rows += tile_rows;
+
+            for tile_x in 0..width.div_ceil(TILE_SIZE) {
+                let data = encode_tile(&rows, width, tile_rows, tile_x * TILE_SIZE)?;
+                self.archive.add_tile(zoom, tile_x, tile_y, data)?;
+            }
+        }
+    }
+}
+
+// 从一行瓦片的像素中切出一个瓦片并编码为 PNG 边缘不足的部分透明
+fn encode_tile(rows: &[u8], width: u32, row_count: u32, x: u32) -> Result<Vec<u8>, String> {
+    let tile_width = TILE_SIZE.min(width - x) as usize;
+    let mut tile = vec![0u8; (TILE_SIZE * TILE_SIZE * 4) as usize];
+    for row in 0..row_count as usize {
+        let src = (row * width as usize + x as usize) * 4;
+        let dst = row * TILE_SIZE as usize * 4;
+        tile[dst..dst + tile_width * 4].copy_from_slice(&rows[src..src + tile_width * 4]);
+    }
+
+    let mut data = Vec::new();
+    let mut encoder = png::Encoder::new(&mut data, TILE_SIZE, TILE_SIZE);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&tile))
+        .map_err(|e| format!("编码瓦片失败: {e}"))?;
+    Ok(data)
+}
+
+// MBTiles：SQLite 数据库 tiles 表使用 TMS 行号（y 向上）
+struct MbtilesWriter {
+    output_path: String,
+    connection: Connection,
+}
+
+impl MbtilesWriter {
+    fn create(output_path: &str) -> Result<Self, String> {
+        let partial = partial_path(output_path);
+        let _ = fs::remove_file(&partial);
+        let connection =
+            Connection::open(&partial).map_err(|e| format!("创建 MBTiles 文件失败: {e}"))?;
+        connection
+            .execute_batch(
+                "PRAGMA journal_mode = OFF;
+                 PRAGMA synchronous = OFF;
+                 CREATE TABLE metadata (name TEXT, value TEXT);
+                 CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB);
+                 CREATE UNIQUE INDEX tile_index ON tiles (zoom_level, tile_column, tile_row);
+                 BEGIN;",
+            )
+            .map_err(|e| format!("初始化 MBTiles 文件失败: {e}"))?;
+        Ok(Self {
+            output_path: output_path.to_string(),
+            connection,
+        })
+    }
+}
+
+impl TileArchiveWriter for MbtilesWriter {
+    fn add_tile(&mut self, zoom: u8, x: u32, y: u32, data: Vec<u8>) -> Result<(), String> {
+        let tms_row = (1u32 << zoom) - 1 - y;
+        self.connection
+            .prepare_cached(
+                "INSERT INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+            )
+            .and_then(|mut statement| statement.execute(params![zoom, x, tms_row, data]))
+            .map_err(|e| format!("写入 MBTiles 瓦片失败: {e}"))?;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>, info: &ArchiveInfo) -> Result<(), String> {
+        let bounds = format!("-180,{},180,{}", -MAX_LATITUDE, MAX_LATITUDE);
+        let entries = [
+            ("name", info.name.clone()),
+            ("description", info.description.clone()),
+            ("format", "png".to_string()),
+            ("type", "overlay".to_string()),
+            ("minzoom", "0".to_string()),
+            ("maxzoom", info.max_zoom.to_string()),
+            ("bounds", bounds),
+        ];
+        for (name, value) in entries {
+            self.connection
+                .execute(
+                    "INSERT INTO metadata (name, value) VALUES (?1, ?2)",
+                    params![name, value],
+                )
+                .map_err(|e| format!("写入 MBTiles 元数据失败: {e}"))?;
+        }
+        self.connection
+            .execute_batch("COMMIT;")
+            .map_err(|e| format!("提交 MBTiles 文件失败: {e}"))?;
+        self.connection
+            .close()
+            .map_err(|(_, e)| format!("关闭 MBTiles 文件失败: {e}"))?;
+
+        fs::rename(partial_path(&self.output_path), &self.output_path)
+            .map_err(|e| format!("重命名 MBTiles 文件失败: {e}"))
+    }
+
+    fn abort(self: Box<Self>) {
+        let _ = self.connection.close();
+        let _ = fs::remove_file(partial_path(&self.output_path));
+    }
+}
+
+// PMTiles v3 目录项
+#[derive(Clone, Copy)]
+struct PmtilesEntry {
+    tile_id: u64,
+    offset: u64,
+    length: u32,
+    run_length: u32,
+}
+
+// PMTiles v3：头部 + 根目录 + 元数据 + 叶目录 + 瓦片数据
+// 瓦片数据先写入临时文件 目录要等所有瓦片写完并按瓦片 ID 排序后才能生成
+// 内容相同的瓦片只存一份
+struct PmtilesWriter {
+    output_path: String,
+    data_path: String,
+    data: BufWriter<File>,
+    data_len: u64,
+    entries: Vec<PmtilesEntry>,
+    contents: HashMap<blake3::Hash, (u64, u32)>,
+}
+
+impl PmtilesWriter {
+    fn create(output_path: &str) -> Result<Self, String> {
+        let data_path = format!("{output_path}.tiles.partial");
+        let file = File::create(&data_path).map_err(|e| format!("创建 PMTiles 文件失败: {e}"))?;
+        Ok(Self {
+            output_path: output_path.to_string(),
+            data_path,
+            data: BufWriter::new(file),
+            data_len: 0,
+            entries: Vec::new(),
+            contents: HashMap::new(),
+        })
+    }
+
+    fn write_archive(&mut self, info: &ArchiveInfo) -> Result<(), String> {
+        self.data
+            .flush()
+            .map_err(|e| format!("写入 PMTiles 瓦片失败: {e}"))?;
+
+        // 按瓦片 ID 排序 连续且内容相同的瓦片合并为一项
+        self.entries.sort_by_key(|entry| entry.tile_id);
+        let mut entries: Vec<PmtilesEntry> = Vec::with_capacity(self.entries.len());
+        for entry in &self.entries {
+            if let Some(last) = entries.last_mut() {
+                if last.offset == entry.offset
+                    && last.tile_id + last.run_length as u64 == entry.tile_id
+                {
+                    last.run_length += 1;
+                    continue;
+                }
+            }
+            entries.push(*entry);
+        }
+
+        let (root, leaves) = build_directories(&entries);
+        let metadata = serde_json::json!({
+            "name": info.name,
+            "description": info.description,
+            "type": "overlay",
+            "format": "png",
+        })
+        .to_string()
+        .into_bytes();
+
+        let root_offset = PMTILES_HEADER_LEN as u64;
+        let metadata_offset = root_offset + root.len() as u64;
+        let leaves_offset = metadata_offset + metadata.len() as u64;
+        let data_offset = leaves_offset + leaves.len() as u64;
+
+        let lon = 1_800_000_000i32;
+        let lat = (MAX_LATITUDE * 10_000_000.0) as i32;
+        let mut header = Vec::with_capacity(PMTILES_HEADER_LEN);
+        header.extend_from_slice(b"PMTiles");
+        header.push(3);
+        for value in [
+            root_offset,
+            root.len() as u64,
+            metadata_offset,
+            metadata.len() as u64,
+            leaves_offset,
+            leaves.len() as u64,
+            data_offset,
+            self.data_len,
+            self.entries.len() as u64,
+            entries.len() as u64,
+            self.contents.len() as u64,
+        ] {
+            header.extend_from_slice(&value.to_le_bytes());
+        }
+        // clustered / 目录压缩（1 = 不压缩）/ 瓦片压缩 / 瓦片类型（2 = PNG）/ 缩放范围
+        header.extend_from_slice(&[0, 1, 1, 2, 0, info.max_zoom]);
+        for value in [-lon, -lat, lon, lat] {
+            header.extend_from_slice(&value.to_le_bytes());
+        }
+        header.push(0);
+        header.extend_from_slice(&0i32.to_le_bytes());
+        header.extend_from_slice(&0i32.to_le_bytes());
+
+        let partial = partial_path(&self.output_path);
+        let file = File::create(&partial).map_err(|e| format!("创建 PMTiles 文件失败: {e}"))?;
+        let mut writer = BufWriter::new(file);
+        let mut tiles =
+            File::open(&self.data_path).map_err(|e| format!("读取 PMTiles 瓦片失败: {e}"))?;
+        writer
+            .write_all(&header)
+            .and_then(|_| writer.write_all(&root))
+            .and_then(|_| writer.write_all(&metadata))
+            .and_then(|_| writer.write_all(&leaves))
+            .and_then(|_| io::copy(&mut tiles, &mut writer).map(|_| ()))
+            .and_then(|_| writer.flush())
+            .map_err(|e| format!("写入 PMTiles 文件失败: {e}"))?;
+        drop(writer);
+
+        fs::rename(&partial, &self.output_path).map_err(|e| format!("重命名 PMTiles 文件失败: {e}"))
+    }
+}
+
+impl TileArchiveWriter for PmtilesWriter {
+    fn add_tile(&mut self, zoom: u8, x: u32, y: u32, data: Vec<u8>) -> Result<(), String> {
+        let hash = blake3::hash(&data);
+        let (offset, length) = match self.contents.get(&hash) {
+            Some(&existing) => existing,
+            None => {
+                self.data
+                    .write_all(&data)
+                    .map_err(|e| format!("写入 PMTiles 瓦片失败: {e}"))?;
+                let content = (self.data_len, data.len() as u32);
+                self.data_len += data.len() as u64;
+                self.contents.insert(hash, content);
+                content
+            }
+        };
+        self.entries.push(PmtilesEntry {
+            tile_id: zxy_to_tile_id(zoom, x, y),
+            offset,
+            length,
+            run_length: 1,
+        });
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>, info: &ArchiveInfo) -> Result<(), String> {
+        let result = self.write_archive(info);
+        let _ = fs::remove_file(&self.data_path);
+        result
+    }
+
+    fn abort(self: Box<Self>) {
+        let data_path = self.data_path.clone();
+        drop(self);
+        let _ = fs::remove_file(data_path);
+    }
+}
+
+// 瓦片 ID：之前所有缩放级别的瓦片数 + 当前级别内的 Hilbert 曲线序号
+fn zxy_to_tile_id(zoom: u8, x: u32, y: u32) -> u64 {
+    let base = ((1u64 << (2 * zoom as u64)) - 1) / 3;
+    base + hilbert_distance(1u64 << zoom, x as u64, y as u64)
+}
+
+// 根目录放不下时拆分为叶目录 根目录中只保留指向叶目录的项
+fn build_directories(entries: &[PmtilesEntry]) -> (Vec<u8>, Vec<u8>) {
+    let root = serialize_directory(entries);
+    if root.len() <= PMTILES_ROOT_LIMIT {
+        return (root, Vec::new());
+    }
+
+    let mut leaf_size = 4096;
+    loop {
+        let mut leaves = Vec::new();
+        let mut root_entries = Vec::new();
+        for chunk in entries.chunks(leaf_size) {
+            let leaf = serialize_directory(chunk);
+            root_entries.push(PmtilesEntry {
+                tile_id: chunk[0].tile_id,
+                offset: leaves.len() as u64,
+                length: leaf.len() as u32,
+                run_length: 0,
+            });
+            leaves.extend_from_slice(&leaf);
+        }
+        let root = serialize_directory(&root_entries);
+        if root.len() <= PMTILES_ROOT_LIMIT {
+            return (root, leaves);
+        }
+        leaf_size *= 2;
+    }
+}
+
+fn serialize_directory(entries: &[PmtilesEntry]) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    write_varint(&mut buffer, entries.len() as u64);
+
+    let mut last_id = 0;
+    for entry in entries {
+        write_varint(&mut buffer, entry.tile_id - last_id);
+        last_id = entry.tile_id;
+    }
+    for entry in entries {
+        write_varint(&mut buffer, entry.run_length as u64);
+    }
+    for entry in entries {
+        write_varint(&mut buffer, entry.length as u64);
+    }
+    for (i, entry) in entries.iter().enumerate() {
+        // 紧接上一项的数据记为 0 其余记为偏移 + 1
+        if i > 0 && entry.offset == entries[i - 1].offset + entries[i - 1].length as u64 {
+            write_varint(&mut buffer, 0);
+        } else {
+            write_varint(&mut buffer, entry.offset + 1);
+        }
+    }
+    buffer
+}
+
+fn write_varint(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push((value as u8 & 0x7F) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use super::types::{ImageMetadata, TimingBreakdown};
+use crate::utils::time::Stopwatch;
+
+// 耗时分解：加载图片的命令传入 timings 时 在返回的元数据中附带各阶段的耗时
+// 前端可以直接显示“慢在哪里” 不需要解析标准输出中的日志
+// 预处理结束时把耗时分解交给所有在等待这张图片的请求 没有请求在等待时直接丢弃
+// 每个请求有自己的 ID 同一张图片同时有多个命令在等待时互不影响
+// 耗时分解不写入缓存的 metadata.json
+
+// 正在等待耗时分解的请求：请求 ID -> (图片路径, 耗时分解) 预处理完成前耗时分解为空
+type TimingSlots = HashMap<u64, (String, Option<TimingBreakdown>)>;
+
+static TIMING_REQUESTS: OnceLock<Mutex<TimingSlots>> = OnceLock::new();
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(0);
+
+fn timing_requests() -> &'static Mutex<TimingSlots> {
+    TIMING_REQUESTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 图片的耗时分解请求 结束（包括出错）时自动移除
+pub struct TimingRequest {
+    id: u64,
+    stopwatch: Stopwatch,
+}
+
+impl TimingRequest {
+    /// 把耗时分解附加到加载结果中
+    /// 没有经过完整预处理（缓存命中、代理副本）时只有总耗时
+    pub fn attach(self, mut metadata: ImageMetadata) -> ImageMetadata {
+        let stored = timing_requests()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_mut(&self.id)
+            .and_then(|(_, breakdown)| breakdown.take());
+        let total_ms = self.stopwatch.elapsed_ms() as u64;
+        metadata.timings = Some(match stored {
+            Some(breakdown) => TimingBreakdown {
+                total_ms,
+                ..breakdown
+            },
+            None => TimingBreakdown {
+                total_ms,
+                ..TimingBreakdown::default()
+            },
+        });
+        metadata
+    }
+}
+
+impl Drop for TimingRequest {
+    fn drop(&mut self) {
+        let mut requests = timing_requests().lock().unwrap_or_else(|e| e.into_inner());
+        requests.remove(&self.id);
+    }
+}
+
+/// 开始收集图片的耗时分解 在加载图片之前调用
+/// # Arguments
+/// * `file_path` - 图片文件路径
+pub fn request_timing_breakdown(file_path: &str) -> TimingRequest {
+    let id = NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+    let mut requests = timing_requests().lock().unwrap_or_else(|e| e.into_inner());
+    requests.insert(id, (file_path.to_string(), None));
+    TimingRequest {
+        id,
+        stopwatch: Stopwatch::start(),
+    }
+}
+
+/// 有请求时把耗时分解附加到加载结果中 没有请求时原样返回
+pub fn attach_timing_breakdown(
+    request: Option<TimingRequest>,
+    metadata: ImageMetadata,
+) -> ImageMetadata {
+    match request {
+        Some(request) => request.attach(metadata),
+        None => metadata,
+    }
+}
+
+/// 预处理结束时保存耗时分解 没有命令在等待这张图片时什么都不做
+pub fn store_timing_breakdown(file_path: &str, breakdown: TimingBreakdown) {
+    let mut requests = timing_requests().lock().unwrap_or_else(|e| e.into_inner());
+    for (path, slot) in requests.values_mut() {
+        if path == file_path {
+            *slot = Some(breakdown.clone());
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Window};
+
+use super::bookmarks::{
+    bookmark_file_path, load_bookmark_file, navigate_to_viewport, save_bookmark_file,
+    validate_viewport, BookmarkFile, BOOKMARKS_LOCK,
+};
+use super::types::{NavigationTarget, Tour, TourStep};
+use crate::utils::time::get_time;
+
+// 导览：由书签扩展而来 按顺序排列的视口 每一步带说明文字和停留时间
+// 与书签保存在同一个文件中 可以导出为 JSON 分享给其他人或在另一台机器上导入
+
+// 导出文件的格式版本
+const TOUR_EXPORT_VERSION: u32 = 1;
+// 由书签生成导览时每一步默认的停留时间
+const DEFAULT_STEP_DURATION_MS: u64 = 5000;
+
+// 导出的导览 JSON
+#[derive(Debug, Serialize, Deserialize)]
+struct TourExport {
+    version: u32,
+    file_path: String,
+    tour: Tour,
+}
+
+fn validate_steps(steps: &[TourStep]) -> Result<(), String> {
+    if steps.is_empty() {
+        return Err("导览至少需要一步".to_string());
+    }
+    for (index, step) in steps.iter().enumerate() {
+        validate_viewport(&step.viewport).map_err(|e| format!("第 {} 步: {e}", index + 1))?;
+    }
+    Ok(())
+}
+
+// 在书签文件上执行一次读改写
+fn with_bookmark_file<T>(
+    app: &AppHandle,
+    file_path: &str,
+    f: impl FnOnce(&mut BookmarkFile) -> Result<T, String>,
+) -> Result<T, String> {
+    let _guard = BOOKMARKS_LOCK
+        .lock()
+        .map_err(|e| format!("获取书签锁失败: {e}"))?;
+    let path = bookmark_file_path(app, file_path)?;
+    let mut file = load_bookmark_file(&path, file_path)?;
+    let result = f(&mut file)?;
+    save_bookmark_file(&path, &file)?;
+    Ok(result)
+}
+
+fn insert_tour(file: &mut BookmarkFile, name: String, steps: Vec<TourStep>) -> Tour {
+    let now = get_time() as u64;
+    let tour = Tour {
+        id: file.tours.iter().map(|t| t.id + 1).max().unwrap_or(1),
+        name,
+        steps,
+        created_ms: now,
+        updated_ms: now,
+    };
+    file.tours.push(tour.clone());
+    tour
+}
+
+/// 列出图片的所有导览
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// # Returns
+/// * `Result<Vec<Tour>, String>` - 按创建顺序排列的导览
+#[tauri::command]
+pub fn list_tours(app: AppHandle, file_path: String) -> Result<Vec<Tour>, String> {
+    let _guard = BOOKMARKS_LOCK
+        .lock()
+        .map_err(|e| format!("获取书签锁失败: {e}"))?;
+    let path = bookmark_file_path(&app, &file_path)?;
+    Ok(load_bookmark_file(&path, &file_path)?.tours)
+}
+
+/// 创建导览
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `name` - 导览名称
+/// * `steps` - 按播放顺序排列的步骤
+/// # Returns
+/// * `Result<Tour, String>` - 新建的导览
+#[tauri::command]
+pub fn create_tour(
+    app: AppHandle,
+    file_path: String,
+    name: String,
+    steps: Vec<TourStep>,
+) -> Result<Tour, String> {
+    validate_steps(&steps)?;
+    let tour = with_bookmark_file(&app, &file_path, |file| {
+        Ok(insert_tour(file, name.trim().to_string(), steps))
+    })?;
+    println!(
+        "[RUST] 创建导览: {file_path} #{} {} ({} 步)",
+        tour.id,
+        tour.name,
+        tour.steps.len()
+    );
+    Ok(tour)
+}
+
+/// 按书签的顺序生成导览 书签名称作为每一步的说明文字
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `name` - 导览名称
+/// * `bookmark_ids` - 书签 ID 按播放顺序排列
+/// * `duration_ms` - 每一步的停留时间 默认为 5 秒
+/// # Returns
+/// * `Result<Tour, String>` - 新建的导览
+#[tauri::command]
+pub fn create_tour_from_bookmarks(
+    app: AppHandle,
+    file_path: String,
+    name: String,
+    bookmark_ids: Vec<u32>,
+    duration_ms: Option<u64>,
+) -> Result<Tour, String> {
+    let duration_ms = duration_ms.unwrap_or(DEFAULT_STEP_DURATION_MS);
+    let tour = with_bookmark_file(&app, &file_path, |file| {
+        let steps = bookmark_ids
+            .iter()
+            .map(|id| {
+                let bookmark = file
+                    .bookmarks
+                    .iter()
+                    .find(|b| b.id == *id)
+                    .ok_or_else(|| format!("书签不存在: #{id}"))?;
+                Ok(TourStep {
+                    viewport: bookmark.viewport,
+                    caption: bookmark.name.clone(),
+                    duration_ms,
+                    bookmark_id: Some(bookmark.id),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        validate_steps(&steps)?;
+        Ok(insert_tour(file, name.trim().to_string(), steps))
+    })?;
+    println!(
+        "[RUST] 由书签创建导览: {file_path} #{} {} ({} 步)",
+        tour.id,
+        tour.name,
+        tour.steps.len()
+    );
+    Ok(tour)
+}
+
+/// 修改导览 未传的字段保持不变
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `tour_id` - 导览 ID
+/// * `name` - 新名称
+/// * `steps` - 新的步骤列表 整体替换
+/// # Returns
+/// * `Result<Tour, String>` - 修改后的导览
+#[tauri::command]
+pub fn update_tour(
+    app: AppHandle,
+    file_path: String,
+    tour_id: u32,
+    name: Option<String>,
+    steps: Option<Vec<TourStep>>,
+) -> Result<Tour, String> {
+    if let Some(steps) = &steps {
+        validate_steps(steps)?;
+    }
+    let tour = with_bookmark_file(&app, &file_path, |file| {
+        let tour = file
+            .tours
+            .iter_mut()
+            .find(|t| t.id == tour_id)
+            .ok_or_else(|| format!("导览不存在: #{tour_id}"))?;
+        if let Some(name) = name {
+            tour.name = name.trim().to_string();
+        }
+        if let Some(steps) = steps {
+            tour.steps = steps;
+        }
+        tour.updated_ms = get_time() as u64;
+        Ok(tour.clone())
+    })?;
+    println!("[RUST] 修改导览: {file_path} #{tour_id}");
+    Ok(tour)
+}
+
+/// 删除导览
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `tour_id` - 导览 ID
+/// # Returns
+/// * `Result<Vec<Tour>, String>` - 删除后剩余的导览
+#[tauri::command]
+pub fn delete_tour(app: AppHandle, file_path: String, tour_id: u32) -> Result<Vec<Tour>, String> {
+    let tours = with_bookmark_file(&app, &file_path, |file| {
+        let before = file.tours.len();
+        file.tours.retain(|t| t.id != tour_id);
+        if file.tours.len() == before {
+            return Err(format!("导览不存在: #{tour_id}"));
+        }
+        Ok(file.tours.clone())
+    })?;
+    println!("[RUST] 删除导览: {file_path} #{tour_id}");
+    Ok(tours)
+}
+
+/// 把导览导出为 JSON
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `tour_id` - 导览 ID
+/// * `out_path` - 输出文件路径 不传时只返回 JSON 文本
+/// # Returns
+/// * `Result<String, String>` - 导出的 JSON 文本
+#[tauri::command]
+pub fn export_tour(
+    app: AppHandle,
+    file_path: String,
+    tour_id: u32,
+    out_path: Option<String>,
+) -> Result<String, String> {
+    let tour = list_tours(app, file_path.clone())?
+        .into_iter()
+        .find(|t| t.id == tour_id)
+        .ok_or_else(|| format!("导览不存在: #{tour_id}"))?;
+    let json = serde_json::to_string_pretty(&TourExport {
+        version: TOUR_EXPORT_VERSION,
+        file_path: file_path.clone(),
+        tour,
+    })
+    .map_err(|e| format!("序列化导览失败: {e}"))?;
+
+    if let Some(out_path) = out_path {
+        fs::write(&out_path, &json).map_err(|e| format!("写入导览文件失败: {e}"))?;
+        println!("[RUST] 导出导览: {file_path} #{tour_id} -> {out_path}");
+    }
+    Ok(json)
+}
+
+/// 从导出的 JSON 导入导览 导入后分配新的 ID
+/// 导览可以导入到另一张图片上（例如同一张图片移动了位置） 不校验 JSON 中记录的路径
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `json` - export_tour 导出的 JSON 文本
+/// # Returns
+/// * `Result<Tour, String>` - 导入后的导览
+#[tauri::command]
+pub fn import_tour(app: AppHandle, file_path: String, json: String) -> Result<Tour, String> {
+    let export: TourExport =
+        serde_json::from_str(&json).map_err(|e| format!("解析导览失败: {e}"))?;
+    if export.version > TOUR_EXPORT_VERSION {
+        return Err(format!("不支持的导览版本: {}", export.version));
+    }
+    validate_steps(&export.tour.steps)?;
+
+    // 书签 ID 只在原图片内有意义
+    let steps = export
+        .tour
+        .steps
+        .into_iter()
+        .map(|step| TourStep {
+            bookmark_id: None,
+            ..step
+        })
+        .collect();
+    let tour = with_bookmark_file(&app, &file_path, |file| {
+        Ok(insert_tour(file, export.tour.name, steps))
+    })?;
+    println!(
+        "[RUST] 导入导览: {file_path} #{} {} (来自 {})",
+        tour.id, tour.name, export.file_path
+    );
+    Ok(tour)
+}
+
+/// 跳转到导览的某一步
+/// # Arguments
+/// * `file_path` - 图片文件路径
+/// * `tour_id` - 导览 ID
+/// * `step` - 步骤序号 从 0 开始
+/// # Returns
+/// * `Result<NavigationTarget, String>` - 导航目标
+#[tauri::command]
+pub fn goto_tour_step(
+    app: AppHandle,
+    window: Window,
+    file_path: String,
+    tour_id: u32,
+    step: usize,
+) -> Result<NavigationTarget, String> {
+    let tour = list_tours(app.clone(), file_path.clone())?
+        .into_iter()
+        .find(|t| t.id == tour_id)
+        .ok_or_else(|| format!("导览不存在: #{tour_id}"))?;
+    let viewport = tour
+        .steps
+        .get(step)
+        .map(|s| s.viewport)
+        .ok_or_else(|| format!("导览 #{tour_id} 没有第 {step} 步"))?;
+    navigate_to_viewport(&app, &window, &file_path, viewport)
+}
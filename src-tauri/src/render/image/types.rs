@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 // Chunk 元数据结构
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct ChunkInfo {
     pub x: u32,       // chunk 在图片中的 X 坐标
     pub y: u32,       // chunk 在图片中的 Y 坐标
@@ -12,7 +12,7 @@ pub struct ChunkInfo {
 }
 
 // 图片元数据结构
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ImageMetadata {
     pub total_width: u32,       // 图片总宽度
     pub total_height: u32,      // 图片总高度
@@ -21,4 +21,1128 @@ pub struct ImageMetadata {
     pub col_count: u32,         // X 方向的 chunk 数量
     pub row_count: u32,         // Y 方向的 chunk 数量
     pub chunks: Vec<ChunkInfo>, // 所有 chunk 信息
+    #[serde(default)]
+    pub proxy_scale: Option<u32>, // 低分辨率代理副本的缩小倍数 完整分辨率时为空
+    #[serde(default)]
+    pub levels: Vec<LevelDescriptor>, // 多分辨率金字塔的概览级别（1/2、1/4 ...） 不含完整分辨率
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<TimingBreakdown>, // 加载的耗时分解 只在请求时返回 不写入缓存
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_count: Option<u32>, // 分页返回时 chunks 为空 这里是 chunk 总数 通过 get_chunk_infos 按需读取
+}
+
+// 多分辨率金字塔中的一个概览级别 通过 get_image_chunk 的 level 参数读取其中的 chunk
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LevelDescriptor {
+    pub level: u32,        // 级别 n 为原图的 1/2^n
+    pub scale: u32,        // 缩小倍数 2^n
+    pub width: u32,        // 该级别的宽度
+    pub height: u32,       // 该级别的高度
+    pub chunk_size_x: u32, // chunk 宽度
+    pub chunk_size_y: u32, // chunk 高度
+    pub col_count: u32,    // X 方向的 chunk 数量
+    pub row_count: u32,    // Y 方向的 chunk 数量
+}
+
+// 诊断检查项的状态
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticStatus {
+    Ok,
+    Warning,
+    Error,
+}
+
+// 单个诊断检查项
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiagnosticCheck {
+    pub name: String,             // 检查项名称
+    pub status: DiagnosticStatus, // 检查结果
+    pub message: String,          // 详细说明
+}
+
+// 解码器可用性
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DecoderAvailability {
+    pub format: String,           // 格式名称
+    pub compiled: bool,           // 是否编译了对应的解码器
+    pub pipeline_supported: bool, // 预处理流程是否支持该格式
+}
+
+// 线程池状态
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ThreadPoolStatus {
+    pub num_threads: usize,           // 线程池大小
+    pub available_parallelism: usize, // 系统可用并行度
+}
+
+// 自检报告
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiagnosticsReport {
+    pub status: DiagnosticStatus,           // 总体状态 取所有检查项中最差的
+    pub cache_dir: String,                  // 缓存目录（绝对路径）
+    pub cache_dir_writable: bool,           // 缓存目录是否可写
+    pub available_disk_bytes: Option<u64>,  // 缓存目录所在磁盘的可用空间
+    pub decoders: Vec<DecoderAvailability>, // 解码器可用性
+    pub thread_pool: ThreadPoolStatus,      // 线程池状态
+    pub checks: Vec<DiagnosticCheck>,       // 所有检查项
+}
+
+// 系统能力报告 供自动调优和前端性能面板使用
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SystemInfo {
+    pub os: String,                              // 操作系统 (windows / macos / linux ...)
+    pub os_version: Option<String>,              // 操作系统版本
+    pub arch: String,                            // CPU 架构
+    pub logical_cores: usize,                    // 逻辑核心数
+    pub physical_cores: Option<usize>,           // 物理核心数
+    pub simd_features: Vec<String>,              // 检测到的 SIMD 指令集
+    pub total_memory_bytes: u64,                 // 总内存
+    pub available_memory_bytes: u64,             // 可用内存
+    pub cache_disk_available_bytes: Option<u64>, // 缓存目录所在磁盘的可用空间
+}
+
+// 后端支持的一种输入格式
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackendDecoder {
+    pub format: String,          // 格式名称
+    pub extensions: Vec<String>, // 文件扩展名（小写 不带点）
+    pub command: String,         // 打开这种格式使用的命令
+    pub available: bool,         // 当前构建是否可以打开这种格式
+}
+
+// 后端版本和能力 供前端启动时握手
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackendInfo {
+    pub version: String,                           // 后端版本
+    pub chunk_format_version: u32,                 // 新写入的 chunk 缓存格式版本
+    pub supported_chunk_format_versions: Vec<u32>, // 可以读取的 chunk 缓存格式版本
+    pub decoders: Vec<BackendDecoder>,             // 支持的输入格式
+    pub features: Vec<String>,                     // 编译进来的可选特性
+    pub cache_read_only: bool,                     // 缓存是否处于只读模式
+    pub gpu_compute: bool,                         // 预处理是否使用 GPU 计算
+}
+
+// 单个内存占用方的统计
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MemoryConsumerUsage {
+    pub name: String, // 占用方名称 (chunk_cache / buffer_pool ...)
+    pub bytes: u64,   // 当前占用的字节数
+}
+
+// 内存使用报告
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MemoryUsage {
+    pub tracked_bytes: u64,                  // 所有已登记占用方的合计
+    pub consumers: Vec<MemoryConsumerUsage>, // 各占用方明细
+    pub process_resident_bytes: Option<u64>, // 进程常驻内存
+    pub system_available_bytes: u64,         // 系统可用内存
+    pub freed_bytes: u64,                    // 本次释放的字节数 (仅 trim_memory 返回时有意义)
+}
+
+// 内存中 chunk 缓存的统计
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChunkCacheStats {
+    pub budget_bytes: u64, // 字节预算
+    pub bytes_held: u64,   // 当前占用的字节数
+    pub entries: u32,      // 缓存的 chunk 数
+    pub hits: u64,         // 启动以来命中次数
+    pub misses: u64,       // 启动以来未命中次数
+}
+
+// 按行优先顺序分页读取的 chunk 信息
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChunkInfoPage {
+    pub start: u32,             // 第一个 chunk 的序号 (chunk_y * col_count + chunk_x)
+    pub total: u32,             // chunk 总数
+    pub chunks: Vec<ChunkInfo>, // 本页的 chunk 信息
+}
+
+// 视口 使用图片像素坐标
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct Viewport {
+    pub x: f64,      // 视口左上角 X
+    pub y: f64,      // 视口左上角 Y
+    pub width: f64,  // 视口宽度
+    pub height: f64, // 视口高度
+}
+
+// 平移压力测试报告
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PanSimulationReport {
+    pub frames: u32,              // 回放的帧数
+    pub chunk_requests: u32,      // 视口覆盖到的 chunk 总次数
+    pub frontend_cache_hits: u32, // 已经加载过的 chunk（模拟前端缓存命中）
+    pub backend_reads: u32,       // 实际向后端请求的次数
+    pub backend_misses: u32,      // 后端读取失败的次数
+    pub hit_rate: f64,            // 前端缓存命中率
+    pub min_latency_ms: f64,      // 单个 chunk 最小读取耗时
+    pub avg_latency_ms: f64,      // 单个 chunk 平均读取耗时
+    pub p95_latency_ms: f64,      // 单个 chunk 读取耗时 P95
+    pub max_latency_ms: f64,      // 单个 chunk 最大读取耗时
+    pub total_time_ms: f64,       // 回放总耗时
+}
+
+// 深链接解析后的导航目标
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NavigationTarget {
+    pub file_path: String,          // 图片文件路径
+    pub x: f64,                     // 视口中心 X（图片像素坐标）
+    pub y: f64,                     // 视口中心 Y（图片像素坐标）
+    pub zoom: f64,                  // 缩放比例
+    pub viewport: Option<Viewport>, // 需要完整显示的区域 存在时前端按它适配缩放 忽略 zoom
+    pub metadata: ImageMetadata,    // 图片元数据 前端可以直接用来初始化
+}
+
+// 单个窗口的图片状态
+// 不同窗口可以打开不同的图片 或者以不同的设置查看同一张图片
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct WindowImageState {
+    pub file_path: Option<String>,  // 当前打开的图片
+    pub viewport: Option<Viewport>, // 最近一次上报的视口
+    pub settings: serde_json::Map<String, serde_json::Value>, // 显示设置（调整参数等）
+}
+
+// 拖放文件的处理结果
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DroppedPathResult {
+    pub path: String,           // 文件路径
+    pub accepted: bool,         // 是否已加入预处理队列
+    pub reason: Option<String>, // 被拒绝的原因
+}
+
+// 后台预处理完成事件的载荷
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PreprocessCompleted {
+    pub file_path: String,               // 图片文件路径
+    pub metadata: Option<ImageMetadata>, // 成功时的元数据
+    pub error: Option<String>,           // 失败时的错误信息
+}
+
+// chunk 数据损坏时发出的警告事件载荷
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChunkWarning {
+    pub file_path: String, // 图片文件路径
+    pub chunk_x: u32,      // chunk 的 X 索引
+    pub chunk_y: u32,      // chunk 的 Y 索引
+    pub regenerated: bool, // 是否已经从源文件重新生成
+    pub message: String,   // 详细说明
+}
+
+// 增量刷新缓存的结果
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CacheRefreshReport {
+    pub file_path: String,               // 图片文件路径
+    pub full_rebuild: bool,              // 是否因为尺寸变化等原因进行了完整重建
+    pub changed_chunks: Vec<(u32, u32)>, // 重新生成的 chunk 索引
+    pub unchanged_chunks: u32,           // 未变化的 chunk 数量
+    pub metadata: ImageMetadata,         // 刷新后的元数据
+}
+
+// 实时模式下 chunk 更新事件的载荷
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChunksUpdated {
+    pub file_path: String,       // 图片文件路径
+    pub chunks: Vec<(u32, u32)>, // 需要重新获取的 chunk 索引
+    pub full_rebuild: bool,      // 是否完整重建（尺寸变化 前端需要重新获取元数据）
+    pub metadata: ImageMetadata, // 刷新后的元数据
+}
+
+// 可截图的显示器信息
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MonitorInfo {
+    pub name: String,      // 显示器名称（截图时用于指定显示器）
+    pub width: u32,        // 宽度（物理像素）
+    pub height: u32,       // 高度（物理像素）
+    pub scale_factor: f32, // 缩放比例
+    pub is_primary: bool,  // 是否为主显示器
+}
+
+// DICOM 头信息中与显示相关的部分
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DicomInfo {
+    pub rows: u32,                          // 行数（高度）
+    pub columns: u32,                       // 列数（宽度）
+    pub frames: u32,                        // 帧数
+    pub samples_per_pixel: u16,             // 每像素采样数 1 为灰度 3 为彩色
+    pub bits_stored: u16,                   // 有效位数
+    pub photometric_interpretation: String, // 光度解释 MONOCHROME1 需要反色
+    pub modality: Option<String>,           // 检查类型 例如 CR / DX / CT
+    pub rescale_slope: f64,                 // 重缩放斜率
+    pub rescale_intercept: f64,             // 重缩放截距
+    pub window_center: Option<f64>,         // 默认窗位
+    pub window_width: Option<f64>,          // 默认窗宽
+}
+
+// FITS 文件中的一个 HDU（头 + 数据单元）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FitsHdu {
+    pub index: u32,              // HDU 序号 0 为主 HDU
+    pub bitpix: i32,             // 数据类型 8/16/32/64 为整数 -32/-64 为浮点
+    pub axes: Vec<u64>,          // 各轴长度 NAXIS1 为宽度 NAXIS2 为高度 NAXIS3 为平面数
+    pub extname: Option<String>, // 扩展名称
+    pub is_image: bool,          // 是否为可显示的图像（至少两个轴）
+}
+
+// FITS 显示时的拉伸方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FitsStretch {
+    Linear,
+    Log,
+    Asinh,
+}
+
+// KTX2 / DDS 纹理的基本信息
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TextureInfo {
+    pub container: String,      // 容器格式 ktx2 / dds
+    pub format: String,         // 像素格式 例如 bc1 / bc7 / rgba8
+    pub width: u32,             // 第 0 级的宽度
+    pub height: u32,            // 第 0 级的高度
+    pub mip_levels: u32,        // mip 级数
+    pub array_layers: u32,      // 数组层数
+    pub block_compressed: bool, // 是否为块压缩格式
+    pub decodable: bool,        // 后端能否解压 不能时只能把压缩块直接交给前端
+}
+
+// 图片中的矩形区域（像素坐标）
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct ImageRegion {
+    pub x: u32,      // 左上角 X
+    pub y: u32,      // 左上角 Y
+    pub width: u32,  // 宽度
+    pub height: u32, // 高度
+}
+
+// 图片中的一个像素（像素坐标）
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct PixelPoint {
+    pub x: u32, // X
+    pub y: u32, // Y
+}
+
+// 取色的采样形状
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SampleShape {
+    Disc,   // 圆形 到中心的距离不超过半径
+    Square, // 边长为 2 * radius + 1 的正方形
+}
+
+// 取色结果 各通道为采样范围内的平均值（0-255）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AverageColor {
+    pub r: f64,            // 红
+    pub g: f64,            // 绿
+    pub b: f64,            // 蓝
+    pub a: f64,            // 透明度
+    pub hex: String,       // 四舍五入后的 "#RRGGBB"
+    pub sample_count: u32, // 参与平均的像素数（靠近边缘时少于完整形状）
+}
+
+// 图片中的一个点（像素坐标 可以有小数 像素中心为整数坐标）
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct ImagePoint {
+    pub x: f64, // X
+    pub y: f64, // Y
+}
+
+// 强度曲线使用的通道
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileChannel {
+    Red,
+    Green,
+    Blue,
+    Alpha,
+    Luminance, // 0.299 R + 0.587 G + 0.114 B
+}
+
+// 沿线段采样的强度曲线
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LineProfile {
+    pub values: Vec<f64>, // 各采样点的强度（0-255） 从 p0 到 p1 等间距
+    pub length: f64,      // 线段长度（像素）
+    pub spacing: f64,     // 相邻采样点的距离（像素）
+}
+
+// 区域的画质指标
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QualityMetrics {
+    pub sharpness: f64,                  // 清晰度：亮度拉普拉斯响应的方差 越大越清晰
+    pub noise: f64,                      // 噪声估计：亮度噪声的标准差（0-255）
+    pub clipped_highlights_percent: f64, // 高光溢出的像素比例（任一通道为 255）
+    pub clipped_shadows_percent: f64,    // 暗部溢出的像素比例（任一通道为 0）
+    pub pixel_count: u64,                // 区域的像素数
+}
+
+// 两张图片叠加的混合模式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BlendMode {
+    Normal,     // 上层覆盖下层
+    Multiply,   // 相乘 变暗
+    Difference, // 差的绝对值 相同的地方为黑色
+    Screen,     // 滤色 变亮
+}
+
+// 两张图片并排对比的方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum CompareMode {
+    Checkerboard { cell_size: u32 }, // 棋盘格交替显示 格子边长为像素数 左上角的格子为 A
+    Swipe { axis: SwipeAxis, position: f64 }, // 沿分割线分开 线之前（左边或上边）为 A 之后为 B
+}
+
+// 卷帘对比的分割线方向
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SwipeAxis {
+    Vertical,   // 竖线 position 为 X 坐标
+    Horizontal, // 横线 position 为 Y 坐标
+}
+
+// 变化检测找到的区域
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChangedRegion {
+    pub x: u32,              // 包围矩形左上角 X 坐标
+    pub y: u32,              // 包围矩形左上角 Y 坐标
+    pub width: u32,          // 包围矩形宽度
+    pub height: u32,         // 包围矩形高度
+    pub changed_pixels: u64, // 发生变化的像素数
+    pub score: f64,          // 变化程度：变化像素的平均通道差（0-1）
+}
+
+// 拼接瑕疵检测找到的可疑区域
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StitchArtifact {
+    pub kind: StitchArtifactKind, // 瑕疵类型
+    pub x: u32,                   // 区域左上角 X 坐标
+    pub y: u32,                   // 区域左上角 Y 坐标
+    pub width: u32,               // 区域宽度
+    pub height: u32,              // 区域高度
+    pub score: f64,               // 曝光台阶为亮度差（0-255） 重复条带为重复的列（行）数
+}
+
+// 拼接瑕疵的类型
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StitchArtifactKind {
+    ExposureStep,    // 拼接线两侧的亮度跳变
+    DuplicatedStrip, // 同一条内容出现了两次 区域包含两份
+}
+
+// 网格或标尺叠加层的样式
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OverlaySpec {
+    pub kind: OverlayKind,          // 网格或标尺
+    pub spacing: f64,               // 网格线（标尺主刻度）的间距 单位为 unit
+    pub unit: OverlayUnit,          // 间距的单位
+    pub pixel_size_mm: Option<f64>, // 每个像素的物理尺寸（毫米） 为空时从图片的分辨率信息读取
+    pub color: Option<String>,      // 线的颜色 #rrggbb 或 #rrggbbaa 默认为青色
+    pub line_width: Option<u32>,    // 线宽（像素） 默认为 1
+}
+
+// 叠加层的类型
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlayKind {
+    Grid,  // 覆盖整张图片的网格
+    Ruler, // 沿上边和左边的标尺刻度 每个主刻度分为 10 小格
+}
+
+// 叠加层间距的单位
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlayUnit {
+    Pixel,
+    Millimeter,
+    Micrometer,
+}
+
+// 为视口选择的分辨率级别
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LodSelection {
+    pub scale: u32,                   // 级别的缩小倍数 1 为完整分辨率 2 / 4 为代理副本
+    pub source_per_device_pixel: f64, // 每个设备像素对应的原图像素数
+    pub bias: f64,                    // 选择时使用的清晰度偏好
+    pub chunks: Vec<ChunkInfo>,       // 该级别中与视口相交的 chunk（该级别的坐标）
+}
+
+// 缓存预热的结果
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WarmCacheReport {
+    pub chunks: u32,        // 预热的 chunk 数
+    pub bytes: u64,         // 读取的字节数
+    pub from_history: bool, // 是否按浏览轨迹选择 没有轨迹时从图片中心开始
+}
+
+// 启动预热配置
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StartupPreloadConfig {
+    pub enabled: bool,                // 启动时是否预热最近打开的图片
+    pub recent_image: Option<String>, // 最近打开的图片
+}
+
+// 调度器中的任务类型 按优先级从高到低排列
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskKind {
+    Read,       // chunk 读取
+    Analysis,   // 分析（变化检测、瑕疵检测、平移模拟等）
+    Preprocess, // 预处理和缓存重建
+    Export,     // 导出
+}
+
+// 一类任务的调度状态
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchedulerKindStatus {
+    pub kind: TaskKind, // 任务类型
+    pub running: u32,   // 正在执行的数量
+    pub queued: u32,    // 排队中的数量
+}
+
+// 调度器的状态
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchedulerStatus {
+    pub capacity: u32,                   // 同时执行的任务数上限
+    pub tasks: Vec<SchedulerKindStatus>, // 每类任务的状态
+}
+
+// chunk 请求的背压统计
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChunkRequestStats {
+    pub in_flight: u32,               // 正在处理的请求数
+    pub max_in_flight: u32,           // 全局上限
+    pub max_in_flight_per_image: u32, // 每张图片的上限
+    pub peak_in_flight: u32,          // 启动以来同时处理的最大请求数
+    pub rejected_global: u64,         // 超出全局上限被拒绝的次数
+    pub rejected_per_image: u64,      // 超出单张图片上限被拒绝的次数
+}
+
+// 缓存保留策略
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum RetentionPolicy {
+    Keep,                      // 永久保留 不会被淘汰
+    ExpireAfter { days: u32 }, // 超过这么多天没有访问时淘汰
+    Default,                   // 按访问统计参与缓存大小限制
+}
+
+// 缓存保留规则 按顺序匹配 第一条匹配的规则生效
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RetentionRule {
+    pub pattern: String, // 图片路径的通配符 * 不跨目录 ** 可以跨目录 ? 匹配单个字符
+    pub policy: RetentionPolicy, // 保留策略
+}
+
+// chunk 的存储格式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum ChunkStorageFormat {
+    Raw,                  // 未压缩的 RGBA8 可以直接映射
+    Jpeg { quality: u8 }, // 有损 JPEG 读取时解码 不保留透明通道
+}
+
+// 一个分辨率级别的存储格式
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LevelStorageFormat {
+    pub level: u32,                 // 级别 0 为完整分辨率 n 为 1/2^n 的代理副本
+    pub format: ChunkStorageFormat, // 存储格式
+}
+
+// 图片中的点所在的 chunk
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChunkPosition {
+    pub level: u32,    // 分辨率级别
+    pub chunk_x: u32,  // chunk 的 X 索引
+    pub chunk_y: u32,  // chunk 的 Y 索引
+    pub offset_x: u32, // 点在 chunk 内的 X 坐标（该级别的像素）
+    pub offset_y: u32, // 点在 chunk 内的 Y 坐标（该级别的像素）
+}
+
+// chunk 的处理顺序（预处理和缓存预热）
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkOrder {
+    RowMajor,    // 行优先 适合水平平移的全景图
+    ColumnMajor, // 列优先 适合竖直滚动的文档
+    Hilbert,     // Hilbert 曲线 相邻的 chunk 先后完成
+}
+
+// 加载图片的耗时分解（毫秒） 没有经过的阶段为 0
+// 条带预处理时解码与写入 chunk 交替进行 解码耗时计入 chunking_ms
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TimingBreakdown {
+    pub preprocessed: bool, // 是否经过了完整的预处理 缓存命中时为 false 只有总耗时
+    pub decode_ms: u64,     // 解码源图片
+    pub convert_ms: u64,    // 转换为 RGBA8
+    pub chunking_ms: u64,   // 切分并写入 chunk
+    pub levels_ms: u64,     // 生成概览级别
+    pub metadata_write_ms: u64, // 写入元数据、像素哈希和感知哈希
+    pub total_ms: u64,      // 总耗时（命令从开始到返回）
+}
+
+// 预处理进度事件的载荷
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PreprocessProgress {
+    pub job_id: String,      // 后台预处理任务 ID
+    pub file_path: String,   // 图片文件路径
+    pub percent: f32,        // 完成百分比 0 到 100
+    pub chunks_done: u32,    // 已写入的 chunk 数
+    pub total_chunks: u32,   // chunk 总数 解码完成前为 0
+    pub eta_ms: Option<u64>, // 预计剩余时间（毫秒） 还无法估计时为空
+}
+
+// 导出进度事件的载荷
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportProgress {
+    pub job_id: String,         // 导出任务 ID
+    pub output_path: String,    // 输出文件路径
+    pub completed_stripes: u32, // 已完成的条带数
+    pub total_stripes: u32,     // 条带总数
+}
+
+// 导出结束（完成、取消或失败）事件的载荷
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportCompleted {
+    pub job_id: String,        // 导出任务 ID
+    pub output_path: String,   // 输出文件路径
+    pub cancelled: bool,       // 是否被取消 取消后再次导出会从断点继续
+    pub error: Option<String>, // 失败时的错误信息
+}
+
+// TIFF 瓦片的压缩方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TiffCompression {
+    None,
+    Deflate,
+    Jpeg,
+}
+
+// 有损格式（JPEG）导出的质量
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum ExportQuality {
+    Fixed { quality: u8 },             // 固定质量 1-100
+    Auto { target_ssim: Option<f64> }, // 在抽样瓦片上查找满足目标 SSIM 的最低质量
+}
+
+// 瓦片包格式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TileArchiveFormat {
+    Mbtiles, // SQLite 数据库 TMS 行号
+    Pmtiles, // PMTiles v3 单文件
+}
+
+// 打印纸张尺寸
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PaperSize {
+    A3,      // 297 x 420 mm
+    A4,      // 210 x 297 mm
+    A5,      // 148 x 210 mm
+    Letter,  // 8.5 x 11 英寸
+    Legal,   // 8.5 x 14 英寸
+    Tabloid, // 11 x 17 英寸
+}
+
+// 打印导出的结果
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PrintExportResult {
+    pub output_path: String, // 输出文件路径
+    pub width: u32,          // 输出图片宽度（像素）
+    pub height: u32,         // 输出图片高度（像素）
+    pub dpi: u32,            // 每英寸像素数
+    pub width_mm: f64,       // 打印宽度（毫米）
+    pub height_mm: f64,      // 打印高度（毫米）
+    pub landscape: bool,     // 是否横向放置纸张
+}
+
+// 内容重复的一张图片
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DuplicateEntry {
+    pub file_path: String,     // 图片文件路径
+    pub cache_dir: String,     // 缓存目录
+    pub cache_size_bytes: u64, // 缓存占用的字节数
+    pub source_exists: bool,   // 源文件是否还存在
+}
+
+// 一组内容完全相同的图片
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DuplicateGroup {
+    pub content_hash: String,         // BLAKE3 内容指纹
+    pub entries: Vec<DuplicateEntry>, // 组内的图片
+    pub reclaimable_bytes: u64,       // 只保留一份缓存时可以回收的字节数
+}
+
+// 与指定图片相似的一张图片
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SimilarImage {
+    pub file_path: String,   // 图片文件路径
+    pub phash_distance: u32, // pHash 汉明距离 越小越相似
+    pub dhash_distance: u32, // dHash 汉明距离
+}
+
+// chunk 访问热力图 按行优先排列
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccessHeatmap {
+    pub cols: u32,                // 热力图列数
+    pub rows: u32,                // 热力图行数
+    pub chunks_per_cell: u32,     // 每格在每个方向上覆盖的 chunk 数
+    pub counts: Vec<u64>,         // 每格的访问次数
+    pub last_access_ms: Vec<u64>, // 每格的最后访问时间（毫秒时间戳 0 表示从未访问）
+    pub max_count: u64,           // 单格最大访问次数 用于归一化颜色
+    pub total_count: u64,         // 总访问次数
+}
+
+// 缓存淘汰结果
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CacheEvictionReport {
+    pub evicted: Vec<String>, // 被淘汰的图片路径（残缺缓存为缓存目录）
+    pub freed_bytes: u64,     // 释放的字节数
+    pub remaining_bytes: u64, // 剩余的缓存大小
+}
+
+// 单个图片的缓存信息
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CacheInfo {
+    pub file_path: String,            // 图片文件路径
+    pub cached: bool,                 // 是否有完整的缓存
+    pub cache_dir: String,            // 缓存目录
+    pub size_bytes: u64,              // 缓存占用的字节数
+    pub chunk_count: u32,             // chunk 数量
+    pub pinned: bool,                 // 是否固定在缓存中 固定的图片不会被淘汰
+    pub last_access_ms: Option<u64>,  // 最后一次访问 chunk 的时间（毫秒时间戳）
+    pub content_hash: Option<String>, // BLAKE3 内容指纹
+}
+
+// 后台维护配置
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MaintenanceConfig {
+    pub enabled: bool,                  // 是否启用定期维护
+    pub interval_secs: u64,             // 两次维护之间的最小间隔（秒）
+    pub cache_limit_bytes: Option<u64>, // 缓存大小上限 为空时不限制
+    pub verify_sample_size: u32,        // 每次抽查的 chunk 数量
+}
+
+// 一次后台维护的结果
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MaintenanceReport {
+    pub started_ms: u64,                       // 开始时间（毫秒时间戳）
+    pub duration_ms: u64,                      // 耗时
+    pub gc_removed: Vec<String>,               // 清理掉的残留目录
+    pub chunks_verified: u32,                  // 抽查的 chunk 数
+    pub chunks_repaired: u32,                  // 损坏并已重新生成的 chunk 数
+    pub chunks_failed: u32,                    // 损坏且无法修复的 chunk 数
+    pub overviews_refreshed: Vec<String>,      // 补算了概览数据的图片
+    pub eviction: Option<CacheEvictionReport>, // 缓存大小限制的执行结果
+    pub expired: Option<CacheEvictionReport>,  // 保留规则的执行结果
+    pub errors: Vec<String>,                   // 各项中出现的错误
+}
+
+// 电源模式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerMode {
+    Auto,        // 跟随系统电源状态
+    Performance, // 从不限制后台任务
+    PowerSaver,  // 始终限制后台任务 使用电池时暂停
+}
+
+// 后台任务的执行策略
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BackgroundPolicy {
+    Normal,    // 正常运行
+    Throttled, // 减少并行线程数
+    Paused,    // 暂停 等待电源状态恢复
+}
+
+// 电源状态
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PowerStatus {
+    pub mode: PowerMode,          // 当前电源模式
+    pub on_battery: Option<bool>, // 是否使用电池供电 无法判断时为空
+    pub thermal_limited: bool,    // 是否因过热降频
+    pub app_backgrounded: bool,   // 应用是否在后台（前端通知）
+    pub policy: BackgroundPolicy, // 后台任务的执行策略
+}
+
+// 单张图片的显示配置 随缓存保存 重新打开时按上次的设置显示
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct DisplayProfile {
+    pub window_center: Option<f64>,  // 窗位
+    pub window_width: Option<f64>,   // 窗宽
+    pub colormap: Option<String>,    // 伪彩色映射名称
+    pub pipeline: serde_json::Value, // 前端的处理流程（调整步骤列表） 后端不解析
+    pub rotation: u32,               // 顺时针旋转角度 0/90/180/270
+    pub updated_ms: u64,             // 最后修改时间（毫秒时间戳）
+}
+
+// 后端渲染时使用的处理流程 按字段顺序依次应用 未设置的步骤跳过
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RenderPipeline {
+    pub window_center: Option<f64>, // 窗位（0-255）
+    pub window_width: Option<f64>,  // 窗宽 与窗位同时设置时生效
+    pub brightness: f64,            // 亮度偏移 -1.0 ~ 1.0
+    pub contrast: f64,              // 对比度倍数 1.0 为不变
+    pub gamma: f64,                 // 伽马 1.0 为不变
+    pub grayscale: bool,            // 转为灰度
+    pub invert: bool,               // 反色
+}
+
+impl Default for RenderPipeline {
+    fn default() -> Self {
+        Self {
+            window_center: None,
+            window_width: None,
+            brightness: 0.0,
+            contrast: 1.0,
+            gamma: 1.0,
+            grayscale: false,
+            invert: false,
+        }
+    }
+}
+
+// 图片目录中的一张图片
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CatalogEntry {
+    pub file_path: String,            // 图片文件路径
+    pub width: u32,                   // 原图宽度
+    pub height: u32,                  // 原图高度
+    pub format: Option<String>,       // 图片格式（扩展名 小写）
+    pub file_size: u64,               // 文件大小（字节）
+    pub capture_time: Option<String>, // EXIF 拍摄时间 "YYYY-MM-DD HH:MM:SS"
+    pub tags: Vec<String>,            // 用户标签
+    pub indexed_ms: u64,              // 加入目录的时间（毫秒时间戳）
+}
+
+// 图片目录的搜索条件 所有条件同时满足 未设置的条件不限制
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ImageSearchQuery {
+    pub text: Option<String>,            // 路径中包含的文字（不区分大小写）
+    pub tags: Vec<String>,               // 必须全部带有的标签
+    pub format: Option<String>,          // 图片格式 例如 "png"
+    pub min_width: Option<u32>,          // 最小宽度
+    pub max_width: Option<u32>,          // 最大宽度
+    pub min_height: Option<u32>,         // 最小高度
+    pub max_height: Option<u32>,         // 最大高度
+    pub captured_after: Option<String>,  // 拍摄时间不早于 "YYYY-MM-DD[ HH:MM:SS]"
+    pub captured_before: Option<String>, // 拍摄时间早于 "YYYY-MM-DD[ HH:MM:SS]"
+    pub limit: Option<u32>,              // 最多返回的条数 默认 500
+}
+
+// 文件夹索引中的一张图片
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FolderIndexEntry {
+    pub file_path: String,              // 图片文件路径
+    pub width: u32,                     // 原图宽度
+    pub height: u32,                    // 原图高度
+    pub file_size: u64,                 // 文件大小（字节）
+    pub modified_ms: u64,               // 文件修改时间（毫秒时间戳）
+    pub thumbnail_path: Option<String>, // 缩略图路径 图片过大或解码失败时为空
+    pub capture_time: Option<String>,   // EXIF 拍摄时间
+    pub cached: bool,                   // 是否已经预处理
+}
+
+// 文件夹索引进度事件的载荷
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FolderIndexProgress {
+    pub folder: String, // 正在索引的文件夹
+    pub processed: u32, // 已检查的文件数
+    pub total: u32,     // 找到的图片文件总数
+}
+
+// 文件夹索引结束事件的载荷
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FolderIndexCompleted {
+    pub folder: String,        // 索引的文件夹
+    pub indexed: u32,          // 新增或更新的图片数
+    pub unchanged: u32,        // 没有变化而跳过的图片数
+    pub removed: u32,          // 已不存在而移除的图片数
+    pub failed: u32,           // 无法读取的文件数
+    pub error: Option<String>, // 整个索引失败时的错误信息
+}
+
+// 监视文件夹的配置
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WatchedFolder {
+    pub folder: String,        // 文件夹绝对路径
+    pub recursive: bool,       // 是否包含子文件夹
+    pub auto_preprocess: bool, // 新图片是否自动加入预处理队列
+    pub interval_ms: u64,      // 轮询间隔（毫秒）
+}
+
+// 监视文件夹中出现新图片时的事件载荷
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WatchedImageAdded {
+    pub folder: String,                 // 监视的文件夹
+    pub file_path: String,              // 新图片路径
+    pub width: u32,                     // 原图宽度
+    pub height: u32,                    // 原图高度
+    pub thumbnail_path: Option<String>, // 缩略图路径 图片过大时为空
+    pub preprocess_queued: bool,        // 是否已加入预处理队列
+}
+
+// 连接的相机或扫描仪（PTP/MTP 设备）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CameraDevice {
+    pub device_id: String,             // 设备标识（USB 总线号-地址）
+    pub vendor_id: u16,                // USB 厂商 ID
+    pub product_id: u16,               // USB 产品 ID
+    pub manufacturer: Option<String>,  // 厂商名称 没有权限打开设备时为空
+    pub model: Option<String>,         // 型号
+    pub serial_number: Option<String>, // 序列号
+}
+
+// 相机上的一个图片文件
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CameraFile {
+    pub handle: u32,                  // PTP 对象句柄 导入时使用
+    pub storage_id: u32,              // 所在的存储（存储卡）
+    pub file_name: String,            // 文件名
+    pub file_size: u64,               // 文件大小（字节）
+    pub width: u32,                   // 图片宽度 相机没有提供时为 0
+    pub height: u32,                  // 图片高度 相机没有提供时为 0
+    pub capture_time: Option<String>, // 拍摄时间 "YYYY-MM-DD HH:MM:SS"
+}
+
+// 相机导入进度事件的载荷
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CameraImportProgress {
+    pub device_id: String,         // 设备标识
+    pub processed: u32,            // 已处理的文件数
+    pub total: u32,                // 要导入的文件总数
+    pub file_path: Option<String>, // 刚导入的文件路径 跳过或失败时为空
+}
+
+// 相机导入结束事件的载荷
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CameraImportCompleted {
+    pub device_id: String,     // 设备标识
+    pub destination: String,   // 导入的目标文件夹
+    pub imported: u32,         // 导入的文件数
+    pub skipped: u32,          // 目标文件夹中已经存在而跳过的文件数
+    pub failed: u32,           // 失败的文件数
+    pub error: Option<String>, // 整个导入失败时的错误信息
+}
+
+// 视口书签
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Bookmark {
+    pub id: u32,            // 书签 ID 在同一张图片内唯一
+    pub name: String,       // 书签名称
+    pub viewport: Viewport, // 书签记录的视口（图片像素坐标）
+    pub created_ms: u64,    // 创建时间（毫秒时间戳）
+}
+
+// 导览中的一步
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TourStep {
+    pub viewport: Viewport,       // 这一步显示的视口（图片像素坐标）
+    pub caption: String,          // 说明文字
+    pub duration_ms: u64,         // 自动播放时停留的时间
+    pub bookmark_id: Option<u32>, // 由书签生成时记录来源书签
+}
+
+// 导览：按顺序播放的一组视口 用于引导浏览超大图片
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Tour {
+    pub id: u32,              // 导览 ID 在同一张图片内唯一
+    pub name: String,         // 导览名称
+    pub steps: Vec<TourStep>, // 按播放顺序排列的步骤
+    pub created_ms: u64,      // 创建时间（毫秒时间戳）
+    pub updated_ms: u64,      // 最后修改时间（毫秒时间戳）
+}
+
+// 标注的几何形状（图片像素坐标）
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnnotationGeometry {
+    Point {
+        x: f64,
+        y: f64,
+    },
+    Rectangle {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    },
+    Polygon {
+        points: Vec<[f64; 2]>,
+    }, // 不需要重复首点
+    Polyline {
+        points: Vec<[f64; 2]>,
+    },
+}
+
+// 单个标注
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Annotation {
+    pub id: String,                   // 标注 ID 在同一张图片内唯一
+    pub geometry: AnnotationGeometry, // 几何形状
+    pub label: Option<String>,        // 分类标签
+    pub comment: Option<String>,      // 批注文字
+    pub color: Option<String>,        // 显示颜色 例如 #ff0000
+    pub author: Option<String>,       // 作者
+    pub created_ms: u64,              // 创建时间（毫秒时间戳）
+    pub updated_ms: u64,              // 最后修改时间（毫秒时间戳）
+}
+
+// 标注交换格式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationFormat {
+    Geojson, // GeoJSON FeatureCollection（QuPath 等）
+    W3c,     // W3C Web Annotation AnnotationPage（IIIF 等）
+}
+
+// 标注导入结果
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AnnotationImportReport {
+    pub imported: u32,        // 导入的标注数
+    pub replaced: u32,        // 其中覆盖了同 ID 已有标注的数量
+    pub skipped: Vec<String>, // 无法识别而跳过的条目及原因
+}
+
+// 标注的撤销/重做状态
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnnotationHistoryState {
+    pub annotations: Vec<Annotation>, // 当前的标注
+    pub undo_count: u32,              // 可以撤销的步数
+    pub redo_count: u32,              // 可以重做的步数
+    pub next_undo: Option<String>,    // 下一次撤销的操作说明
+    pub next_redo: Option<String>,    // 下一次重做的操作说明
+}
+
+// 多人审阅时的区域锁 锁定期间其他人不能修改区域内或指定的标注
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RegionLock {
+    pub id: String,                  // 锁 ID
+    pub owner: String,               // 持有者（审阅者名称）
+    pub region: Option<Viewport>,    // 锁定的区域（图片像素坐标）
+    pub annotation_ids: Vec<String>, // 锁定的标注
+    pub acquired_ms: u64,            // 获取时间（毫秒时间戳）
+    pub expires_ms: u64,             // 过期时间 过期后自动失效
+}
+
+// 本地 JSON-RPC 服务状态
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RpcServerStatus {
+    pub running: bool,             // 是否正在运行
+    pub port: Option<u16>,         // 监听端口（只监听 127.0.0.1）
+    pub token: Option<String>,     // 连接令牌 每个连接需要先用它调用 authenticate
+    pub info_path: Option<String>, // 记录端口和令牌的文件 外部脚本从这里读取
+}
+
+// 后台任务的类型
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    Preprocess, // 后台预处理
+    Export,     // 导出
+}
+
+// 后台任务的结束状态
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Succeeded, // 成功
+    Failed,    // 失败
+    Cancelled, // 被取消
+}
+
+// 任务结束通知的配置
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotificationConfig {
+    pub webhook_url: Option<String>, // 任务结束时 POST JSON 的地址（例如 Slack Incoming Webhook）
+    pub script: Option<String>,      // 任务结束时执行的本地程序 JSON 通过标准输入传入
+    pub on_success: bool,            // 成功时是否通知
+    pub on_failure: bool,            // 失败或取消时是否通知
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            webhook_url: None,
+            script: None,
+            on_success: true,
+            on_failure: true,
+        }
+    }
+}
+
+// 任务结束通知的内容
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JobNotification {
+    pub text: String,                // 一句话的说明 Slack 直接显示这个字段
+    pub kind: JobKind,               // 任务类型
+    pub status: JobStatus,           // 结束状态
+    pub job_id: String,              // 任务 ID（预处理任务为图片路径）
+    pub file_path: String,           // 图片文件路径
+    pub output_path: Option<String>, // 导出任务的输出路径
+    pub error: Option<String>,       // 失败时的错误信息
+    pub duration_ms: u64,            // 耗时
+    pub finished_ms: u64,            // 结束时间（毫秒时间戳）
+}
+
+// 任务历史中的一条记录
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JobRecord {
+    pub id: i64,                     // 记录 ID 按结束顺序递增
+    pub kind: JobKind,               // 任务类型
+    pub status: JobStatus,           // 结束状态
+    pub job_id: String,              // 任务 ID（预处理任务为图片路径）
+    pub file_path: String,           // 图片文件路径
+    pub output_path: Option<String>, // 导出任务的输出路径
+    pub error: Option<String>,       // 失败时的错误信息
+    pub started_ms: u64,             // 开始时间（毫秒时间戳）
+    pub finished_ms: u64,            // 结束时间（毫秒时间戳）
+    pub duration_ms: u64,            // 耗时
+    pub app_version: String,         // 执行任务的应用版本
+    pub source_hash: Option<String>, // 源文件的内容指纹 缓存中没有记录时为空
+}
+
+// 遥测事件 不包含文件路径等可以识别用户的信息
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TelemetryEvent {
+    // 一次预处理
+    Preprocess {
+        format: String,   // 源文件扩展名
+        width: u32,       // 图片宽度
+        height: u32,      // 图片高度
+        chunk_size: u32,  // chunk 边长
+        chunk_count: u32, // chunk 数量
+        decode_ms: u64,   // 解码耗时
+        total_ms: u64,    // 预处理总耗时
+    },
+    // 返回给前端的带错误码的错误
+    Error {
+        code: String, // 错误码
+    },
+    // 后端 panic
+    Crash {
+        location: String, // 源码位置
+        message: String,  // panic 信息 其中的路径已被替换
+        thread: String,   // 线程名
+    },
+}
+
+// 一条遥测记录
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TelemetryRecord {
+    pub timestamp_ms: u64,   // 记录时间（毫秒时间戳）
+    pub app_version: String, // 应用版本
+    #[serde(flatten)]
+    pub event: TelemetryEvent, // 事件内容
 }
@@ -1,3 +1,9 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::RgbaImage;
+use tauri::{AppHandle, Manager};
+
 // TODO 下面这个上面的extract_chunk_pixels函数的simd油优化版本 未经测试
 // use std::simd::*; // 需要使用 nightly Rust
 
@@ -36,3 +42,43 @@
 // }
 
 // 这里可以添加其他工具函数
+
+/// 获取应用数据目录下的子目录 不存在时创建
+/// 剪贴板、截图以及需要先转换为 PNG 的格式都把中间文件放在这里
+/// # Arguments
+/// * `app` - 应用句柄
+/// * `name` - 子目录名称
+/// # Returns
+/// * `Result<PathBuf, String>` - 子目录路径或错误信息
+pub fn app_data_subdir(app: &AppHandle, name: &str) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("获取应用数据目录失败: {e}"))?
+        .join(name);
+    fs::create_dir_all(&dir).map_err(|e| format!("创建目录失败 {}: {e}", dir.display()))?;
+    Ok(dir)
+}
+
+/// 把 RGBA 像素保存为 PNG 文件
+/// # Arguments
+/// * `path` - 输出路径
+/// * `width` - 宽度
+/// * `height` - 高度
+/// * `pixels` - RGBA 像素数据
+pub fn save_rgba_png(path: &Path, width: u32, height: u32, pixels: Vec<u8>) -> Result<(), String> {
+    let buffer = RgbaImage::from_raw(width, height, pixels)
+        .ok_or_else(|| format!("像素数据长度与尺寸 {width}x{height} 不匹配"))?;
+    buffer
+        .save(path)
+        .map_err(|e| format!("保存图片失败 {}: {e}", path.display()))
+}
+
+/// 转换结果是否比源文件新 是则可以直接复用
+pub fn is_up_to_date(source: &Path, output: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    match (modified(source), modified(output)) {
+        (Some(source), Some(output)) => output >= source,
+        _ => false,
+    }
+}
@@ -0,0 +1,175 @@
+use std::path::Path;
+
+use tauri::{AppHandle, Window};
+
+use super::cache::fnv1a_hash;
+use super::commands::load_user_image;
+use super::types::ImageMetadata;
+use super::utils::{app_data_subdir, is_up_to_date, save_rgba_png};
+use super::window_state::set_window_image;
+
+// 视频帧在应用数据目录下的保存位置
+const VIDEO_FRAME_DIR: &str = "video_frames";
+
+/// 从视频中精确解码一帧并在查看器中打开
+/// 先跳转到目标时间之前的关键帧 再逐帧解码到第一个显示时间不早于目标时间的帧
+/// 需要启用 video 特性（依赖系统的 ffmpeg 库）
+/// 保存后的路径会记录为调用窗口当前打开的图片 前端可以通过 get_window_state 取得
+/// # Arguments
+/// * `file_path` - 视频文件路径
+/// * `timestamp` - 目标时间（秒）
+#[tauri::command]
+pub fn open_video_frame(
+    app: AppHandle,
+    window: Window,
+    file_path: String,
+    timestamp: f64,
+) -> Result<ImageMetadata, String> {
+    if !timestamp.is_finite() || timestamp < 0.0 {
+        return Err(format!("时间无效: {timestamp}"));
+    }
+    let source = Path::new(&file_path);
+    if !source.exists() {
+        return Err(format!("视频文件不存在: {file_path}"));
+    }
+
+    let dir = app_data_subdir(&app, VIDEO_FRAME_DIR)?;
+    let output = dir.join(format!(
+        "video_{:016x}_{}ms.png",
+        fnv1a_hash(file_path.as_bytes()),
+        (timestamp * 1000.0).round() as u64
+    ));
+
+    if is_up_to_date(source, &output) {
+        println!("[RUST] 复用已解码的视频帧: {}", output.display());
+    } else {
+        let (width, height, pixels) = backend::decode_frame(source, timestamp)?;
+        save_rgba_png(&output, width, height, pixels)?;
+    }
+
+    let output = output.to_string_lossy().to_string();
+    let metadata = load_user_image(&output)?;
+    set_window_image(window.label(), &output);
+    Ok(metadata)
+}
+
+#[cfg(feature = "video")]
+mod backend {
+    use std::path::Path;
+
+    use ffmpeg::format::Pixel;
+    use ffmpeg::software::scaling::{context::Context as Scaler, flag::Flags};
+    use ffmpeg::util::frame::video::Video;
+    use ffmpeg_next as ffmpeg;
+
+    /// 解码指定时间的一帧
+    /// # Returns
+    /// * `Result<(u32, u32, Vec<u8>), String>` - 宽度、高度和 RGBA 像素数据
+    pub fn decode_frame(path: &Path, timestamp: f64) -> Result<(u32, u32, Vec<u8>), String> {
+        ffmpeg::init().map_err(|e| format!("初始化 ffmpeg 失败: {e}"))?;
+
+        let mut input = ffmpeg::format::input(&path).map_err(|e| format!("打开视频失败: {e}"))?;
+        let stream = input
+            .streams()
+            .best(ffmpeg::media::Type::Video)
+            .ok_or_else(|| "视频中没有视频流".to_string())?;
+        let stream_index = stream.index();
+
+        // 目标时间换算为视频流时间基下的 pts
+        let time_base = stream.time_base();
+        let start_time = stream.start_time().max(0);
+        let target_pts = start_time
+            + (timestamp * time_base.denominator() as f64 / time_base.numerator().max(1) as f64)
+                as i64;
+
+        let context = ffmpeg::codec::context::Context::from_parameters(stream.parameters())
+            .map_err(|e| format!("创建解码器失败: {e}"))?;
+        let mut decoder = context
+            .decoder()
+            .video()
+            .map_err(|e| format!("创建视频解码器失败: {e}"))?;
+
+        // 跳转到目标时间之前最近的关键帧
+        let position = (timestamp * ffmpeg::ffi::AV_TIME_BASE as f64) as i64;
+        input
+            .seek(position, ..=position)
+            .map_err(|e| format!("视频跳转失败: {e}"))?;
+        decoder.flush();
+
+        let mut scaler = Scaler::get(
+            decoder.format(),
+            decoder.width(),
+            decoder.height(),
+            Pixel::RGBA,
+            decoder.width(),
+            decoder.height(),
+            Flags::BILINEAR,
+        )
+        .map_err(|e| format!("创建像素格式转换失败: {e}"))?;
+
+        let mut decoded = Video::empty();
+        for (stream, packet) in input.packets() {
+            if stream.index() != stream_index {
+                continue;
+            }
+            decoder
+                .send_packet(&packet)
+                .map_err(|e| format!("视频解码失败: {e}"))?;
+            while decoder.receive_frame(&mut decoded).is_ok() {
+                if frame_pts(&decoded) >= target_pts {
+                    return convert_frame(&mut scaler, &decoded);
+                }
+            }
+        }
+
+        // 目标时间超过最后一帧时 取解码器中剩下的最后一帧
+        decoder
+            .send_eof()
+            .map_err(|e| format!("视频解码失败: {e}"))?;
+        let mut last = None;
+        while decoder.receive_frame(&mut decoded).is_ok() {
+            if frame_pts(&decoded) >= target_pts {
+                return convert_frame(&mut scaler, &decoded);
+            }
+            last = Some(decoded.clone());
+        }
+        match last {
+            Some(frame) => convert_frame(&mut scaler, &frame),
+            None => Err(format!("未能解码到 {timestamp} 秒处的帧")),
+        }
+    }
+
+    fn frame_pts(frame: &Video) -> i64 {
+        frame.timestamp().or(frame.pts()).unwrap_or(0)
+    }
+
+    // 转换为 RGBA 并去掉每行末尾的对齐填充
+    fn convert_frame(scaler: &mut Scaler, frame: &Video) -> Result<(u32, u32, Vec<u8>), String> {
+        let mut rgba = Video::empty();
+        scaler
+            .run(frame, &mut rgba)
+            .map_err(|e| format!("像素格式转换失败: {e}"))?;
+
+        let width = rgba.width();
+        let height = rgba.height();
+        let stride = rgba.stride(0);
+        let row_len = width as usize * 4;
+        let data = rgba.data(0);
+
+        let mut pixels = Vec::with_capacity(row_len * height as usize);
+        for row in 0..height as usize {
+            pixels.extend_from_slice(&data[row * stride..row * stride + row_len]);
+        }
+        println!("[RUST] 视频帧解码完成: {width}x{height}");
+        Ok((width, height, pixels))
+    }
+}
+
+#[cfg(not(feature = "video"))]
+mod backend {
+    use std::path::Path;
+
+    pub fn decode_frame(_path: &Path, _timestamp: f64) -> Result<(u32, u32, Vec<u8>), String> {
+        Err("未启用视频支持 请使用 --features video 重新编译".to_string())
+    }
+}
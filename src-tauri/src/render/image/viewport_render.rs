@@ -0,0 +1,302 @@
+use std::ops::Deref;
+use std::path::PathBuf;
+
+use memmap2::Mmap;
+use rayon::prelude::*;
+use tauri::ipc::Response;
+
+use super::bookmarks::validate_viewport;
+use super::cache::{check_file_cache_exists, image_cache_dir, load_cached_metadata};
+use super::chunk_repair::regenerate_chunk;
+use super::errors::{localized_error, ErrorCode};
+use super::export::map_chunk;
+use super::level_formats::read_level_chunk;
+use super::lod::select_level;
+use super::proxy::{load_proxy_metadata, proxy_cache_dir};
+use super::types::{ChunkInfo, ImageMetadata, RenderPipeline, Viewport};
+use crate::utils::time::Stopwatch;
+
+// 离屏渲染：不经过 webview 把视口渲染成一张 RGBA 图片 用于截图、打印和无界面渲染
+// 按 lod 模块的策略选择分辨率级别（完整分辨率或代理副本） 双线性采样后应用处理流程
+
+// 输出图片的最大像素数 避免一次请求占用过多内存
+const MAX_RENDER_PIXELS: u64 = 64 * 1024 * 1024;
+
+// 一个可用的分辨率级别
+pub(super) struct RenderLevel {
+    pub(super) scale: u32,
+    pub(super) dir: PathBuf,
+    pub(super) metadata: ImageMetadata,
+}
+
+// 按缩小倍数从小到大列出已经生成的分辨率级别
+pub(super) fn available_levels(file_path: &str) -> Vec<RenderLevel> {
+    let mut levels = Vec::new();
+    if check_file_cache_exists(file_path) {
+        if let Ok(metadata) = load_cached_metadata(file_path) {
+            levels.push(RenderLevel {
+                scale: 1,
+                dir: image_cache_dir(file_path),
+                metadata,
+            });
+        }
+    }
+    // 有多分辨率金字塔时使用其中的概览级别 否则使用代理副本
+    let scales: Vec<u32> = match levels.first() {
+        Some(full) if !full.metadata.levels.is_empty() => full
+            .metadata
+            .levels
+            .iter()
+            .map(|level| level.scale)
+            .collect(),
+        _ => vec![2, 4],
+    };
+    for scale in scales {
+        let dir = proxy_cache_dir(file_path, scale);
+        if let Ok(metadata) = load_proxy_metadata(&dir) {
+            levels.push(RenderLevel {
+                scale,
+                dir,
+                metadata,
+            });
+        }
+    }
+    levels
+}
+
+// 已经映射的 chunk 按网格位置索引 没有映射的位置读出透明像素
+pub(super) struct ChunkGrid {
+    col_count: u32,
+    chunk_size_x: u32,
+    chunk_size_y: u32,
+    chunks: Vec<Option<(ChunkBytes, u32)>>,
+}
+
+// 原始格式的 chunk 直接映射 有损格式的 chunk 解码到内存中
+enum ChunkBytes {
+    Mapped(Mmap),
+    Decoded(Vec<u8>),
+}
+
+impl Deref for ChunkBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            ChunkBytes::Mapped(mmap) => mmap,
+            ChunkBytes::Decoded(data) => data,
+        }
+    }
+}
+
+impl ChunkGrid {
+    pub(super) fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let (cx, cy) = (x / self.chunk_size_x, y / self.chunk_size_y);
+        let Some((data, chunk_width)) = &self.chunks[(cy * self.col_count + cx) as usize] else {
+            return [0; 4];
+        };
+        let offset = 8
+            + ((y % self.chunk_size_y) as usize * *chunk_width as usize
+                + (x % self.chunk_size_x) as usize)
+                * 4;
+        [
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ]
+    }
+}
+
+// 映射级别中 include 选中的 chunk
+// 完整分辨率的 chunk 损坏时从源图片重新生成
+pub(super) fn load_chunk_grid(
+    file_path: &str,
+    level: &RenderLevel,
+    include: impl Fn(&ChunkInfo) -> bool,
+) -> Result<ChunkGrid, String> {
+    let metadata = &level.metadata;
+    let mut chunks: Vec<Option<(ChunkBytes, u32)>> = (0..metadata.col_count * metadata.row_count)
+        .map(|_| None)
+        .collect();
+    for info in metadata.chunks.iter().filter(|c| include(c)) {
+        let path = level
+            .dir
+            .join(format!("chunk_{}_{}.bin", info.chunk_x, info.chunk_y));
+        let data = match map_chunk(&path) {
+            Ok(mmap) => ChunkBytes::Mapped(mmap),
+            Err(e) if level.scale == 1 => {
+                println!("[RUST] 渲染时发现损坏的 chunk，重新生成: {e}");
+                regenerate_chunk(file_path, info.chunk_x, info.chunk_y)?;
+                ChunkBytes::Mapped(map_chunk(&path)?)
+            }
+            // 代理副本的 chunk 可能是有损格式
+            Err(_) => {
+                ChunkBytes::Decoded(read_level_chunk(&level.dir, info.chunk_x, info.chunk_y)?)
+            }
+        };
+        chunks[(info.chunk_y * metadata.col_count + info.chunk_x) as usize] =
+            Some((data, info.width));
+    }
+    Ok(ChunkGrid {
+        col_count: metadata.col_count,
+        chunk_size_x: metadata.chunk_size_x,
+        chunk_size_y: metadata.chunk_size_y,
+        chunks,
+    })
+}
+
+// 处理流程中与通道值一一对应的步骤（窗宽窗位、亮度、对比度、伽马、反色）合并成查找表
+fn build_lut(pipeline: &RenderPipeline) -> [u8; 256] {
+    let mut lut = [0u8; 256];
+    for (i, entry) in lut.iter_mut().enumerate() {
+        let mut v = i as f64 / 255.0;
+        if let (Some(center), Some(width)) = (pipeline.window_center, pipeline.window_width) {
+            v = (i as f64 - (center - width / 2.0)) / width;
+        }
+        v = (v - 0.5) * pipeline.contrast + 0.5 + pipeline.brightness;
+        v = v.clamp(0.0, 1.0).powf(1.0 / pipeline.gamma);
+        if pipeline.invert {
+            v = 1.0 - v;
+        }
+        *entry = (v * 255.0).round() as u8;
+    }
+    lut
+}
+
+pub(super) fn validate_pipeline(pipeline: &RenderPipeline) -> Result<(), String> {
+    if pipeline.window_width.is_some_and(|width| width <= 0.0) {
+        return Err("窗宽必须大于 0".to_string());
+    }
+    if !(pipeline.gamma.is_finite() && pipeline.gamma > 0.0) {
+        return Err(format!("伽马必须大于 0: {}", pipeline.gamma));
+    }
+    if !pipeline.contrast.is_finite() || !pipeline.brightness.is_finite() {
+        return Err("亮度和对比度必须是有效的数值".to_string());
+    }
+    Ok(())
+}
+
+/// 把视口渲染到 output 中（RGBA 行优先 长度为 width * height * 4） 视口超出图片的部分为透明
+/// 调用方需要先检查视口和处理流程
+/// # Returns
+/// * `Result<u32, String>` - 使用的分辨率级别的缩小倍数
+pub(super) fn render_viewport_into(
+    file_path: &str,
+    viewport: &Viewport,
+    (width, height): (u32, u32),
+    pipeline: &RenderPipeline,
+    output: &mut [u8],
+) -> Result<u32, String> {
+    let step_x = viewport.width / width as f64;
+    let step_y = viewport.height / height as f64;
+    let level = select_level(available_levels(file_path), step_x.min(step_y))
+        .ok_or_else(|| localized_error(ErrorCode::CacheMissing, &[]))?;
+    let scale = level.scale as f64;
+    let (level_width, level_height) = (level.metadata.total_width, level.metadata.total_height);
+
+    // 视口在该级别中覆盖的源区域 双线性采样需要多取一个像素
+    let clamp_x = |v: f64| (v.max(0.0) as u32).min(level_width);
+    let clamp_y = |v: f64| (v.max(0.0) as u32).min(level_height);
+    let (x0, y0, x1, y1) = (
+        clamp_x(viewport.x / scale - 1.0),
+        clamp_y(viewport.y / scale - 1.0),
+        clamp_x(((viewport.x + viewport.width) / scale).ceil() + 1.0),
+        clamp_y(((viewport.y + viewport.height) / scale).ceil() + 1.0),
+    );
+    let grid = load_chunk_grid(file_path, &level, |c| {
+        c.x < x1 && c.x + c.width > x0 && c.y < y1 && c.y + c.height > y0
+    })?;
+    let lut = build_lut(pipeline);
+
+    let row_len = width as usize * 4;
+    output
+        .par_chunks_mut(row_len)
+        .enumerate()
+        .for_each(|(oy, row)| {
+            row.fill(0);
+            let sy = (viewport.y + (oy as f64 + 0.5) * step_y) / scale - 0.5;
+            if sy < -0.5 || sy >= level_height as f64 - 0.5 {
+                return;
+            }
+            let y0 = sy.floor().max(0.0) as u32;
+            let y1 = (y0 + 1).min(level_height - 1);
+            let fy = (sy - y0 as f64).clamp(0.0, 1.0);
+            for (ox, out) in row.chunks_exact_mut(4).enumerate() {
+                let sx = (viewport.x + (ox as f64 + 0.5) * step_x) / scale - 0.5;
+                if sx < -0.5 || sx >= level_width as f64 - 0.5 {
+                    continue;
+                }
+                let x0 = sx.floor().max(0.0) as u32;
+                let x1 = (x0 + 1).min(level_width - 1);
+                let fx = (sx - x0 as f64).clamp(0.0, 1.0);
+                let (p00, p10) = (grid.pixel(x0, y0), grid.pixel(x1, y0));
+                let (p01, p11) = (grid.pixel(x0, y1), grid.pixel(x1, y1));
+                let mut rgba = [0u8; 4];
+                for c in 0..4 {
+                    let top = p00[c] as f64 * (1.0 - fx) + p10[c] as f64 * fx;
+                    let bottom = p01[c] as f64 * (1.0 - fx) + p11[c] as f64 * fx;
+                    rgba[c] = (top * (1.0 - fy) + bottom * fy).round() as u8;
+                }
+                if pipeline.grayscale {
+                    let luma =
+                        (0.299 * rgba[0] as f64 + 0.587 * rgba[1] as f64 + 0.114 * rgba[2] as f64)
+                            .round() as u8;
+                    rgba[..3].fill(luma);
+                }
+                out[0] = lut[rgba[0] as usize];
+                out[1] = lut[rgba[1] as usize];
+                out[2] = lut[rgba[2] as usize];
+                out[3] = rgba[3];
+            }
+        });
+
+    Ok(level.scale)
+}
+
+pub(super) fn validate_render_size(width: u32, height: u32) -> Result<(), String> {
+    if width == 0 || height == 0 || width as u64 * height as u64 > MAX_RENDER_PIXELS {
+        return Err(format!("无效的输出尺寸: {width}x{height}"));
+    }
+    Ok(())
+}
+
+/// 离屏渲染视口 拼接对应分辨率级别的 chunk 应用处理流程后返回一张图片
+/// 视口超出图片的部分为透明
+/// 数据格式与 chunk 相同：宽度(4字节) + 高度(4字节) + RGBA 数据
+/// # Arguments
+/// * `file_path` - 图片文件路径 需要已经预处理或生成了代理副本
+/// * `viewport` - 视口（原图像素坐标）
+/// * `width` / `height` - 输出图片尺寸
+/// * `pipeline` - 处理流程 为空时不做处理
+#[tauri::command]
+pub fn render_viewport(
+    file_path: String,
+    viewport: Viewport,
+    width: u32,
+    height: u32,
+    pipeline: Option<RenderPipeline>,
+) -> Result<Response, String> {
+    validate_viewport(&viewport)?;
+    validate_render_size(width, height)?;
+    let pipeline = pipeline.unwrap_or_default();
+    validate_pipeline(&pipeline)?;
+
+    let stopwatch = Stopwatch::start();
+    let mut output = vec![0u8; 8 + width as usize * height as usize * 4];
+    output[0..4].copy_from_slice(&width.to_be_bytes());
+    output[4..8].copy_from_slice(&height.to_be_bytes());
+    let scale = render_viewport_into(
+        &file_path,
+        &viewport,
+        (width, height),
+        &pipeline,
+        &mut output[8..],
+    )?;
+
+    println!(
+        "[RUST] 离屏渲染完成: {width}x{height} 使用 1/{scale} 级别 (耗时: {}ms)",
+        stopwatch.elapsed_ms()
+    );
+    Ok(Response::new(output))
+}
@@ -0,0 +1,260 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter};
+
+use super::catalog::open_catalog;
+use super::folder_index::{
+    collect_images, enqueue_folder_index, file_stamp, generate_thumbnail, normalize_folder,
+    store_folder_file, thumbnail_path,
+};
+use super::preprocess_queue::enqueue_preprocess;
+use super::types::{WatchedFolder, WatchedImageAdded};
+use super::utils::app_data_subdir;
+
+// 监视文件夹：扫描仪、显微镜等设备把图片写入固定的文件夹 出现新图片时自动读取尺寸、生成缩略图
+// 加入图片目录和文件夹索引 按配置加入预处理队列 并通知前端
+// 没有使用系统的文件通知 按间隔轮询 网络共享目录上也能工作
+// 配置保存在应用数据目录 启动时恢复
+
+// 出现新图片时发出的事件
+pub const WATCHED_IMAGE_ADDED_EVENT: &str = "watch_folder://image_added";
+
+// 监视文件夹配置 位于应用数据目录的 watch 子目录下
+const WATCHED_FOLDERS_FILE: &str = "watched_folders.json";
+
+// 默认轮询间隔和上下限
+const DEFAULT_POLL_INTERVAL_MS: u64 = 2000;
+const MIN_POLL_INTERVAL_MS: u64 = 500;
+const MAX_POLL_INTERVAL_MS: u64 = 60 * 60 * 1000;
+
+// 正在监视的文件夹 值为停止标志
+static WATCHERS: OnceLock<Mutex<HashMap<String, Arc<AtomicBool>>>> = OnceLock::new();
+
+fn watchers() -> &'static Mutex<HashMap<String, Arc<AtomicBool>>> {
+    WATCHERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn load_config(app: &AppHandle) -> Vec<WatchedFolder> {
+    app_data_subdir(app, "watch")
+        .ok()
+        .and_then(|dir| fs::read_to_string(dir.join(WATCHED_FOLDERS_FILE)).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_config(app: &AppHandle, folders: &[WatchedFolder]) -> Result<(), String> {
+    let dir = app_data_subdir(app, "watch")?;
+    let json =
+        serde_json::to_string(folders).map_err(|e| format!("序列化监视文件夹配置失败: {e}"))?;
+    fs::write(dir.join(WATCHED_FOLDERS_FILE), json)
+        .map_err(|e| format!("保存监视文件夹配置失败: {e}"))
+}
+
+// 启动监视线程 已经在监视时先停止旧的线程（配置可能变了）
+fn start_watcher(app: &AppHandle, config: WatchedFolder) -> Result<(), String> {
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let mut active = watchers()
+        .lock()
+        .map_err(|e| format!("监视文件夹状态加锁失败: {e}"))?;
+    if let Some(previous) = active.insert(config.folder.clone(), stop_flag.clone()) {
+        previous.store(true, Ordering::Relaxed);
+    }
+
+    let folder = config.folder.clone();
+    let thread_app = app.clone();
+    let spawn_result = thread::Builder::new()
+        .name("watch-folder".to_string())
+        .spawn(move || watch_loop(thread_app, config, stop_flag));
+    if let Err(e) = spawn_result {
+        active.remove(&folder);
+        return Err(format!("启动监视文件夹线程失败: {e}"));
+    }
+    Ok(())
+}
+
+/// 启动时调用 恢复保存的监视文件夹
+/// 已经不存在的文件夹保留在配置中 但不启动监视
+pub fn start_watch_folders(app: &AppHandle) {
+    for config in load_config(app) {
+        if !Path::new(&config.folder).is_dir() {
+            println!("[RUST] 监视文件夹不存在，跳过: {}", config.folder);
+            continue;
+        }
+        // 关闭期间放进来的图片由文件夹索引补上 不会自动预处理
+        if let Err(e) = enqueue_folder_index(app, config.folder.clone(), config.recursive) {
+            println!("[RUST] {e}");
+        }
+        let folder = config.folder.clone();
+        match start_watcher(app, config) {
+            Ok(_) => println!("[RUST] 已恢复监视文件夹: {folder}"),
+            Err(e) => println!("[RUST] {e}"),
+        }
+    }
+}
+
+/// 添加监视文件夹 已经在监视时更新配置
+/// 文件夹中已有的图片加入文件夹索引 之后新出现的图片逐个处理并发出 watch_folder://image_added 事件
+/// # Arguments
+/// * `path` - 文件夹路径
+/// * `recursive` - 是否包含子文件夹 默认为 false
+/// * `auto_preprocess` - 新图片是否自动加入预处理队列 默认为 false
+/// * `interval_ms` - 轮询间隔（毫秒） 默认为 2000
+/// # Returns
+/// * `Result<WatchedFolder, String>` - 保存后的配置
+#[tauri::command]
+pub fn add_watch_folder(
+    app: AppHandle,
+    path: String,
+    recursive: Option<bool>,
+    auto_preprocess: Option<bool>,
+    interval_ms: Option<u64>,
+) -> Result<WatchedFolder, String> {
+    let config = WatchedFolder {
+        folder: normalize_folder(&path)?,
+        recursive: recursive.unwrap_or(false),
+        auto_preprocess: auto_preprocess.unwrap_or(false),
+        interval_ms: interval_ms
+            .unwrap_or(DEFAULT_POLL_INTERVAL_MS)
+            .clamp(MIN_POLL_INTERVAL_MS, MAX_POLL_INTERVAL_MS),
+    };
+
+    let mut folders = load_config(&app);
+    folders.retain(|f| f.folder != config.folder);
+    folders.push(config.clone());
+    folders.sort_by(|a, b| a.folder.cmp(&b.folder));
+    save_config(&app, &folders)?;
+
+    enqueue_folder_index(&app, config.folder.clone(), config.recursive)?;
+    start_watcher(&app, config.clone())?;
+    println!(
+        "[RUST] 已添加监视文件夹: {} (间隔 {}ms 自动预处理 {})",
+        config.folder, config.interval_ms, config.auto_preprocess
+    );
+    Ok(config)
+}
+
+/// 移除监视文件夹 已经索引的图片保留在图库中
+/// # Returns
+/// * `Result<bool, String>` - false 表示该文件夹没有被监视
+#[tauri::command]
+pub fn remove_watch_folder(app: AppHandle, path: String) -> Result<bool, String> {
+    // 文件夹可能已经被删除 无法规范化时按原样匹配
+    let folder = normalize_folder(&path).unwrap_or(path);
+    let mut folders = load_config(&app);
+    let count = folders.len();
+    folders.retain(|f| f.folder != folder);
+    let removed = folders.len() != count;
+    if removed {
+        save_config(&app, &folders)?;
+    }
+
+    let flag = watchers()
+        .lock()
+        .map_err(|e| format!("监视文件夹状态加锁失败: {e}"))?
+        .remove(&folder);
+    if let Some(flag) = flag {
+        flag.store(true, Ordering::Relaxed);
+    }
+    if removed {
+        println!("[RUST] 已移除监视文件夹: {folder}");
+    }
+    Ok(removed)
+}
+
+/// 列出所有监视文件夹 按路径排序
+#[tauri::command]
+pub fn list_watch_folders(app: AppHandle) -> Result<Vec<WatchedFolder>, String> {
+    Ok(load_config(&app))
+}
+
+// 文件夹中的图片和各自的大小、修改时间
+fn scan_folder(config: &WatchedFolder) -> HashMap<PathBuf, (u64, u64)> {
+    let mut files = Vec::new();
+    collect_images(Path::new(&config.folder), config.recursive, &mut files);
+    files
+        .into_iter()
+        .filter_map(|path| file_stamp(&path).map(|stamp| (path, stamp)))
+        .collect()
+}
+
+fn watch_loop(app: AppHandle, config: WatchedFolder, stop_flag: Arc<AtomicBool>) {
+    let interval = Duration::from_millis(config.interval_ms);
+    // 启动时已有的图片由文件夹索引处理 这里只关心之后出现的
+    let mut known: HashSet<PathBuf> = scan_folder(&config).into_keys().collect();
+    // 新出现但可能还在写入的图片 值为上一次轮询时的状态
+    let mut appearing: HashMap<PathBuf, (u64, u64)> = HashMap::new();
+
+    while !stop_flag.load(Ordering::Relaxed) {
+        thread::sleep(interval);
+        if stop_flag.load(Ordering::Relaxed) {
+            break;
+        }
+
+        let current = scan_folder(&config);
+        for (path, stamp) in &current {
+            if known.contains(path) {
+                continue;
+            }
+            // 设备写入大文件需要时间 两次轮询之间大小和修改时间都没变再处理
+            if appearing.get(path) != Some(stamp) {
+                appearing.insert(path.clone(), *stamp);
+                continue;
+            }
+            appearing.remove(path);
+            known.insert(path.clone());
+            let file_path = path.to_string_lossy().to_string();
+            if let Err(e) = ingest_image(&app, &config, &file_path, *stamp) {
+                println!("[RUST] 处理监视文件夹中的新图片失败: {file_path} ({e})");
+            }
+        }
+        // 被删除或移走的文件 重新出现时按新图片处理
+        known.retain(|path| current.contains_key(path));
+        appearing.retain(|path, _| current.contains_key(path));
+    }
+
+    println!("[RUST] 监视文件夹线程退出: {}", config.folder);
+}
+
+fn ingest_image(
+    app: &AppHandle,
+    config: &WatchedFolder,
+    file_path: &str,
+    stamp: (u64, u64),
+) -> Result<(), String> {
+    let (width, height) =
+        image::image_dimensions(file_path).map_err(|e| format!("读取图片尺寸失败: {e}"))?;
+    let thumbnail_dir = app_data_subdir(app, "thumbnails")?;
+    let thumbnail = generate_thumbnail(
+        Path::new(file_path),
+        &thumbnail_path(&thumbnail_dir, file_path),
+    )?;
+    let connection = open_catalog(app)?;
+    store_folder_file(
+        &connection,
+        &config.folder,
+        file_path,
+        stamp,
+        thumbnail.as_deref(),
+    )?;
+
+    let preprocess_queued = config.auto_preprocess && enqueue_preprocess(app, file_path)?;
+    println!("[RUST] 监视文件夹出现新图片: {file_path} ({width}x{height})");
+    let payload = WatchedImageAdded {
+        folder: config.folder.clone(),
+        file_path: file_path.to_string(),
+        width,
+        height,
+        thumbnail_path: thumbnail,
+        preprocess_queued,
+    };
+    if let Err(e) = app.emit(WATCHED_IMAGE_ADDED_EVENT, payload) {
+        println!("[RUST] 发送新图片事件失败: {e}");
+    }
+    Ok(())
+}
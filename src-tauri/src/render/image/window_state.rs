@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use tauri::Window;
+
+use super::navigation_history::record_viewport;
+use super::types::{Viewport, WindowImageState};
+
+// 所有窗口的状态 以窗口 label 为键
+// 后端所有与"当前图片"相关的状态都放在这里 避免多个窗口互相覆盖
+static WINDOW_STATES: OnceLock<Mutex<HashMap<String, WindowImageState>>> = OnceLock::new();
+
+fn window_states() -> &'static Mutex<HashMap<String, WindowImageState>> {
+    WINDOW_STATES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 修改指定窗口的状态 窗口不存在时先创建默认状态
+pub fn update_window_state<F: FnOnce(&mut WindowImageState)>(label: &str, f: F) {
+    if let Ok(mut states) = window_states().lock() {
+        f(states.entry(label.to_string()).or_default());
+    }
+}
+
+/// 读取指定窗口的状态
+pub fn window_state(label: &str) -> WindowImageState {
+    window_states()
+        .lock()
+        .ok()
+        .and_then(|states| states.get(label).cloned())
+        .unwrap_or_default()
+}
+
+/// 所有窗口当前打开的图片
+pub fn open_images() -> Vec<String> {
+    window_states()
+        .lock()
+        .map(|states| {
+            states
+                .values()
+                .filter_map(|state| state.file_path.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 记录窗口当前打开的图片
+/// 切换到另一张图片时 旧图片的视口不再有意义 一并清空
+pub fn set_window_image(label: &str, file_path: &str) {
+    update_window_state(label, |state| {
+        if state.file_path.as_deref() != Some(file_path) {
+            state.viewport = None;
+        }
+        state.file_path = Some(file_path.to_string());
+    });
+}
+
+/// 窗口关闭时移除其状态
+pub fn remove_window_state(label: &str) {
+    if let Ok(mut states) = window_states().lock() {
+        if states.remove(label).is_some() {
+            println!("[RUST] 窗口 {label} 已关闭，清理其图片状态");
+        }
+    }
+}
+
+/// 获取调用窗口的图片状态
+#[tauri::command]
+pub fn get_window_state(window: Window) -> Result<WindowImageState, String> {
+    Ok(window_state(window.label()))
+}
+
+/// 上报调用窗口的视口 供后端预取等逻辑参考 同时记入图片的浏览轨迹
+#[tauri::command]
+pub fn set_window_viewport(window: Window, viewport: Viewport) -> Result<(), String> {
+    let mut file_path = None;
+    update_window_state(window.label(), |state| {
+        state.viewport = Some(viewport);
+        file_path = state.file_path.clone();
+    });
+    if let Some(file_path) = file_path {
+        record_viewport(&file_path, viewport);
+    }
+    Ok(())
+}
+
+/// 合并调用窗口的显示设置 只影响这个窗口
+/// 值为 null 的键会被删除
+#[tauri::command]
+pub fn set_window_settings(
+    window: Window,
+    settings: serde_json::Map<String, serde_json::Value>,
+) -> Result<WindowImageState, String> {
+    update_window_state(window.label(), |state| {
+        for (key, value) in settings {
+            if value.is_null() {
+                state.settings.remove(&key);
+            } else {
+                state.settings.insert(key, value);
+            }
+        }
+    });
+    Ok(window_state(window.label()))
+}
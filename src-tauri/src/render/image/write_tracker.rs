@@ -0,0 +1,78 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+
+use super::chunk_pages::forget_chunk_pages;
+use super::chunk_processing::chunk_memory_cache;
+use super::magnifier::forget_magnifier_cache;
+use super::preprocess_progress::report_chunk_done;
+
+// 预处理过程中的读取：记录正在预处理的图片和其中已经完整写入的 chunk
+// 预处理期间的 chunk 请求只返回已完成的 chunk 其余的返回 [CHUNK_NOT_READY] 前端稍后重试
+// chunk 文件先写临时文件再改名（见 process_single_chunk_parallel） 不会读到写了一半的文件
+// 缓存目录里残留的旧 chunk（例如上次用不同的 chunk 尺寸预处理）在写入完成前也不会被返回
+
+#[derive(Default)]
+struct CacheWrite {
+    // 同一张图片嵌套的写入数（例如重建时先清理缓存再预处理）
+    writers: usize,
+    written: HashSet<(u32, u32)>,
+}
+
+static CACHE_WRITES: OnceLock<Mutex<HashMap<String, CacheWrite>>> = OnceLock::new();
+
+fn cache_writes() -> &'static Mutex<HashMap<String, CacheWrite>> {
+    CACHE_WRITES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 图片正在写入缓存的标记 结束（包括出错）时自动移除
+pub struct CacheWriteGuard {
+    file_path: String,
+}
+
+impl Drop for CacheWriteGuard {
+    fn drop(&mut self) {
+        let mut writes = cache_writes().lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(write) = writes.get_mut(&self.file_path) {
+            write.writers -= 1;
+            if write.writers == 0 {
+                writes.remove(&self.file_path);
+            }
+        }
+    }
+}
+
+/// 开始写入图片的缓存 之后的 chunk 请求只返回通过 mark_chunk_written 标记过的 chunk
+/// 内存中缓存的这张图片的 chunk 和 chunk 列表同时移除
+/// # Arguments
+/// * `file_path` - 图片文件路径
+pub fn begin_cache_write(file_path: &str) -> CacheWriteGuard {
+    chunk_memory_cache().invalidate_image(file_path);
+    forget_chunk_pages(file_path);
+    forget_magnifier_cache(file_path);
+    let mut writes = cache_writes().lock().unwrap_or_else(|e| e.into_inner());
+    writes.entry(file_path.to_string()).or_default().writers += 1;
+    CacheWriteGuard {
+        file_path: file_path.to_string(),
+    }
+}
+
+/// 标记 chunk 已经完整写入磁盘 同时更新预处理进度
+pub fn mark_chunk_written(file_path: &str, chunk_x: u32, chunk_y: u32) {
+    {
+        let mut writes = cache_writes().lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(write) = writes.get_mut(file_path) {
+            write.written.insert((chunk_x, chunk_y));
+        }
+    }
+    report_chunk_done(file_path);
+}
+
+/// chunk 的写入状态
+/// # Returns
+/// * `Option<bool>` - 图片没有在写入缓存时为 None 否则为 chunk 是否已经写完
+pub fn chunk_write_state(file_path: &str, chunk_x: u32, chunk_y: u32) -> Option<bool> {
+    let writes = cache_writes().lock().unwrap_or_else(|e| e.into_inner());
+    writes
+        .get(file_path)
+        .map(|write| write.written.contains(&(chunk_x, chunk_y)))
+}
@@ -0,0 +1,17 @@
+use std::path::Path;
+
+/// 获取路径所在磁盘的可用空间（字节）
+/// 路径本身不存在时（例如缓存目录还没创建）向上查找最近的已存在目录
+pub fn available_space(path: &Path) -> Result<u64, String> {
+    let mut current = Some(path);
+    while let Some(dir) = current {
+        if dir.exists() {
+            return fs4::available_space(dir)
+                .map_err(|e| format!("获取磁盘可用空间失败: {e} (路径: {dir:?})"));
+        }
+        current = dir.parent();
+    }
+
+    // 相对路径一直向上都不存在时 以当前工作目录为准
+    fs4::available_space(".").map_err(|e| format!("获取磁盘可用空间失败: {e}"))
+}
@@ -0,0 +1,56 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+// 计时统计：按名称汇总各阶段的耗时（次数、总耗时、最大值、最近一次）
+// 由 TimingSpan 在结束时写入 只保存在内存中 进程重启后清零
+
+/// 一项计时统计
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TimingMetric {
+    pub name: String,  // 统计项名称
+    pub count: u64,    // 记录次数
+    pub total_ms: f64, // 总耗时
+    pub max_ms: f64,   // 最大耗时
+    pub last_ms: f64,  // 最近一次的耗时
+}
+
+static TIMING_METRICS: OnceLock<Mutex<BTreeMap<&'static str, TimingMetric>>> = OnceLock::new();
+
+fn timing_metrics() -> &'static Mutex<BTreeMap<&'static str, TimingMetric>> {
+    TIMING_METRICS.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+/// 记录一次耗时
+pub fn record_duration(name: &'static str, duration: Duration) {
+    let ms = duration.as_secs_f64() * 1000.0;
+    let mut metrics = timing_metrics().lock().unwrap_or_else(|e| e.into_inner());
+    let metric = metrics.entry(name).or_insert_with(|| TimingMetric {
+        name: name.to_string(),
+        ..Default::default()
+    });
+    metric.count += 1;
+    metric.total_ms += ms;
+    metric.max_ms = metric.max_ms.max(ms);
+    metric.last_ms = ms;
+}
+
+/// 所有计时统计 按名称排序
+pub fn timing_snapshot() -> Vec<TimingMetric> {
+    timing_metrics()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .values()
+        .cloned()
+        .collect()
+}
+
+/// 清空计时统计
+pub fn reset_timings() {
+    timing_metrics()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clear();
+}
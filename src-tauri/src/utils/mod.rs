@@ -1 +1,3 @@
+pub mod disk;
+pub mod metrics;
 pub mod time;
@@ -1,8 +1,70 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use super::metrics::record_duration;
+
+// 时间工具
+// get_time 是墙上时间 用于记录时间点（修改时间、日志时间戳）
+// 测量耗时使用单调时钟（Stopwatch / TimingSpan） 系统时间被同步或手动调整时耗时不会变成负数或跳变
+
+/// 当前的墙上时间（毫秒时间戳）
 pub fn get_time() -> u128 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_millis()
 }
+
+/// 单调时钟的秒表 可以在线程之间传递
+#[derive(Debug, Clone, Copy)]
+pub struct Stopwatch {
+    start: Instant,
+}
+
+impl Stopwatch {
+    /// 从现在开始计时
+    pub fn start() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+
+    /// 已经过去的时间
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// 已经过去的毫秒数
+    pub fn elapsed_ms(&self) -> u128 {
+        self.start.elapsed().as_millis()
+    }
+}
+
+/// 计时区间 结束（drop）时把耗时记录到计时统计中（见 metrics）
+/// 提前返回或出错时同样会记录
+pub struct TimingSpan {
+    name: &'static str,
+    stopwatch: Stopwatch,
+}
+
+impl TimingSpan {
+    /// 开始一个计时区间
+    /// # Arguments
+    /// * `name` - 统计项名称 例如 "preprocess.decode"
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            stopwatch: Stopwatch::start(),
+        }
+    }
+
+    /// 区间开始后已经过去的毫秒数
+    pub fn elapsed_ms(&self) -> u128 {
+        self.stopwatch.elapsed_ms()
+    }
+}
+
+impl Drop for TimingSpan {
+    fn drop(&mut self) {
+        record_duration(self.name, self.stopwatch.elapsed());
+    }
+}